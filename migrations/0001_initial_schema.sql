@@ -1,4 +1,6 @@
--- Create all database tables
+-- Migration 0001: initial schema
+-- Tables as of the first release. IF NOT EXISTS lets databases created before
+-- migrations were tracked adopt this migration without changes.
 
 SET FOREIGN_KEY_CHECKS=0;
 
@@ -0,0 +1,50 @@
+-- Migration 0002: swap expiry
+-- Adds per-currency default expiry, swap deadlines and the messages that are
+-- edited when a swap is resolved.
+-- Databases that loaded the old create_tables.sql may already have the columns,
+-- so each ALTER only runs when information_schema says it is missing.
+
+SET @ddl = IF(
+    (SELECT COUNT(*) FROM information_schema.COLUMNS
+     WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'currency' AND COLUMN_NAME = 'swap_expiry_minutes') = 0,
+    'ALTER TABLE currency ADD COLUMN swap_expiry_minutes INT NULL AFTER ticker',
+    'DO 0'
+);
+PREPARE stmt FROM @ddl;
+EXECUTE stmt;
+DEALLOCATE PREPARE stmt;
+
+SET @ddl = IF(
+    (SELECT COUNT(*) FROM information_schema.COLUMNS
+     WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'currency_swap' AND COLUMN_NAME = 'expires_at') = 0,
+    'ALTER TABLE currency_swap ADD COLUMN expires_at DATETIME NULL AFTER status',
+    'DO 0'
+);
+PREPARE stmt FROM @ddl;
+EXECUTE stmt;
+DEALLOCATE PREPARE stmt;
+
+SET @ddl = IF(
+    (SELECT COUNT(*) FROM information_schema.STATISTICS
+     WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'currency_swap' AND INDEX_NAME = 'idx_swap_status_expiry') = 0,
+    'ALTER TABLE currency_swap ADD INDEX idx_swap_status_expiry (status, expires_at)',
+    'DO 0'
+);
+PREPARE stmt FROM @ddl;
+EXECUTE stmt;
+DEALLOCATE PREPARE stmt;
+
+CREATE TABLE IF NOT EXISTS swap_message (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    swap_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    message_id BIGINT NOT NULL,
+    date_created DATETIME DEFAULT CURRENT_TIMESTAMP,
+    
+    INDEX idx_swap_message_swap (swap_id),
+    
+    CONSTRAINT fk_swap_message_swap
+        FOREIGN KEY (swap_id)
+        REFERENCES currency_swap(id)
+        ON DELETE CASCADE ON UPDATE CASCADE
+);
@@ -0,0 +1,34 @@
+-- Migration 0003: partial fills
+-- Open swaps can be filled in parts. `filled_amount` tracks how much of the
+-- maker's offer has been taken and `swap_fill` records each fill.
+
+ALTER TABLE currency_swap ADD COLUMN filled_amount DECIMAL(24,8) NOT NULL DEFAULT 0 AFTER taker_amount;
+
+CREATE TABLE IF NOT EXISTS swap_fill (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    swap_id BIGINT NOT NULL,
+    taker_id BIGINT NOT NULL,
+    maker_amount DECIMAL(24,8) NOT NULL,
+    taker_amount DECIMAL(24,8) NOT NULL,
+    date_created DATETIME DEFAULT CURRENT_TIMESTAMP,
+    
+    INDEX idx_swap_fill_swap (swap_id),
+    INDEX idx_swap_fill_date (date_created),
+    
+    CONSTRAINT fk_swap_fill_swap
+        FOREIGN KEY (swap_id)
+        REFERENCES currency_swap(id)
+        ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT fk_swap_fill_taker
+        FOREIGN KEY (taker_id)
+        REFERENCES account(id)
+        ON DELETE RESTRICT ON UPDATE CASCADE
+);
+
+-- Swaps accepted before partial fills were filled in one go
+UPDATE currency_swap SET filled_amount = maker_amount WHERE status IN ('accepted', 'completed');
+
+INSERT INTO swap_fill (swap_id, taker_id, maker_amount, taker_amount, date_created)
+SELECT id, taker_id, maker_amount, taker_amount, date_updated
+FROM currency_swap
+WHERE status IN ('accepted', 'completed') AND taker_id IS NOT NULL;
@@ -0,0 +1,58 @@
+-- Migration 0004: order book
+-- Limit orders per currency pair, stored against the pair's canonical
+-- (alphabetical by ticker) base and quote. Each order holds its unfilled
+-- balance in escrow: the quote currency for bids, the base currency for asks.
+
+CREATE TABLE IF NOT EXISTS limit_order (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    discord_id BIGINT NOT NULL,
+    account_id BIGINT NOT NULL,
+    base_currency_id BIGINT NOT NULL,
+    quote_currency_id BIGINT NOT NULL,
+    side ENUM('buy','sell') NOT NULL,
+    price DECIMAL(24,8) NOT NULL,
+    quantity DECIMAL(24,8) NOT NULL,
+    filled_quantity DECIMAL(24,8) NOT NULL DEFAULT 0,
+    escrow_remaining DECIMAL(24,8) NOT NULL,
+    status ENUM('open','filled','cancelled') NOT NULL DEFAULT 'open',
+    date_created DATETIME DEFAULT CURRENT_TIMESTAMP,
+    date_updated DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    
+    INDEX idx_limit_order_book (base_currency_id, quote_currency_id, side, status, price, id),
+    INDEX idx_limit_order_discord (discord_id, status),
+    
+    CONSTRAINT fk_limit_order_account
+        FOREIGN KEY (account_id)
+        REFERENCES account(id)
+        ON DELETE RESTRICT ON UPDATE CASCADE,
+    CONSTRAINT fk_limit_order_base_currency
+        FOREIGN KEY (base_currency_id)
+        REFERENCES currency(id)
+        ON DELETE RESTRICT ON UPDATE CASCADE,
+    CONSTRAINT fk_limit_order_quote_currency
+        FOREIGN KEY (quote_currency_id)
+        REFERENCES currency(id)
+        ON DELETE RESTRICT ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS order_fill (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    bid_order_id BIGINT NOT NULL,
+    ask_order_id BIGINT NOT NULL,
+    price DECIMAL(24,8) NOT NULL,
+    quantity DECIMAL(24,8) NOT NULL,
+    quote_amount DECIMAL(24,8) NOT NULL,
+    date_created DATETIME DEFAULT CURRENT_TIMESTAMP,
+    
+    INDEX idx_order_fill_bid (bid_order_id),
+    INDEX idx_order_fill_ask (ask_order_id),
+    
+    CONSTRAINT fk_order_fill_bid
+        FOREIGN KEY (bid_order_id)
+        REFERENCES limit_order(id)
+        ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT fk_order_fill_ask
+        FOREIGN KEY (ask_order_id)
+        REFERENCES limit_order(id)
+        ON DELETE CASCADE ON UPDATE CASCADE
+);
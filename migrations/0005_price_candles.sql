@@ -0,0 +1,31 @@
+-- Migration 0005: price candles
+-- OHLCV rollups per canonical pair at 1 minute, 1 hour and 1 day resolution.
+-- Every logged trade updates the three buckets it falls in, so price queries
+-- read a few rows here instead of scanning tradelog and the fill tables.
+-- Volume is in the base currency, quote_volume in the quote currency.
+-- Existing history is loaded with `--backfill-candles`.
+
+CREATE TABLE IF NOT EXISTS price_candle (
+    base_currency_id BIGINT NOT NULL,
+    quote_currency_id BIGINT NOT NULL,
+    resolution ENUM('1m','1h','1d') NOT NULL,
+    bucket_start DATETIME NOT NULL,
+    open DECIMAL(24,8) NOT NULL,
+    high DECIMAL(24,8) NOT NULL,
+    low DECIMAL(24,8) NOT NULL,
+    close DECIMAL(24,8) NOT NULL,
+    volume DECIMAL(32,8) NOT NULL DEFAULT 0,
+    quote_volume DECIMAL(32,8) NOT NULL DEFAULT 0,
+    trade_count INT NOT NULL DEFAULT 0,
+    
+    PRIMARY KEY (base_currency_id, quote_currency_id, resolution, bucket_start),
+    
+    CONSTRAINT fk_price_candle_base_currency
+        FOREIGN KEY (base_currency_id)
+        REFERENCES currency(id)
+        ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT fk_price_candle_quote_currency
+        FOREIGN KEY (quote_currency_id)
+        REFERENCES currency(id)
+        ON DELETE CASCADE ON UPDATE CASCADE
+);
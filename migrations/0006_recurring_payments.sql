@@ -0,0 +1,26 @@
+-- Migration 0006: recurring payments
+-- Standing orders that send a fixed amount from one user to another every day,
+-- week or month. Payer and recipient are Discord IDs; accounts are resolved when
+-- each payment is sent, the same as `$send`.
+
+CREATE TABLE IF NOT EXISTS recurring_payment (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    payer_id BIGINT NOT NULL,
+    recipient_id BIGINT NOT NULL,
+    currency_id BIGINT NOT NULL,
+    amount DECIMAL(24,8) NOT NULL,
+    period ENUM('daily','weekly','monthly') NOT NULL,
+    next_run_at DATETIME NOT NULL,
+    failure_count INT NOT NULL DEFAULT 0,
+    status ENUM('active','suspended','cancelled') NOT NULL DEFAULT 'active',
+    date_created DATETIME DEFAULT CURRENT_TIMESTAMP,
+    date_updated DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    
+    INDEX idx_recurring_due (status, next_run_at),
+    INDEX idx_recurring_payer (payer_id, status),
+    
+    CONSTRAINT fk_recurring_currency
+        FOREIGN KEY (currency_id)
+        REFERENCES currency(id)
+        ON DELETE RESTRICT ON UPDATE CASCADE
+);
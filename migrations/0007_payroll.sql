@@ -0,0 +1,27 @@
+-- Migration 0007: scheduled payroll
+-- Pays every member holding a Discord role a fixed amount of the guild's
+-- currency each day, week or month. Members are resolved from the role when
+-- each run starts; created_by is the admin who set it up and gets the report.
+
+CREATE TABLE IF NOT EXISTS payroll_schedule (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    role_id BIGINT NOT NULL,
+    currency_id BIGINT NOT NULL,
+    amount DECIMAL(24,8) NOT NULL,
+    source ENUM('treasury','mint') NOT NULL,
+    period ENUM('daily','weekly','monthly') NOT NULL,
+    next_run_at DATETIME NOT NULL,
+    created_by BIGINT NOT NULL,
+    status ENUM('active','cancelled') NOT NULL DEFAULT 'active',
+    date_created DATETIME DEFAULT CURRENT_TIMESTAMP,
+    date_updated DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    
+    INDEX idx_payroll_due (status, next_run_at),
+    INDEX idx_payroll_guild (guild_id, status),
+    
+    CONSTRAINT fk_payroll_currency
+        FOREIGN KEY (currency_id)
+        REFERENCES currency(id)
+        ON DELETE RESTRICT ON UPDATE CASCADE
+);
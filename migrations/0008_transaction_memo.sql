@@ -0,0 +1,13 @@
+-- Migration 0008: transaction memos
+-- An optional note the sender attaches to a transfer, shown in receipts and
+-- history and searched by `$transaction list <text>`.
+
+SET @ddl = IF(
+    (SELECT COUNT(*) FROM information_schema.COLUMNS
+     WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'transaction' AND COLUMN_NAME = 'memo') = 0,
+    'ALTER TABLE transaction ADD COLUMN memo VARCHAR(200) NULL AFTER amount',
+    'DO 0'
+);
+PREPARE stmt FROM @ddl;
+EXECUTE stmt;
+DEALLOCATE PREPARE stmt;
@@ -0,0 +1,27 @@
+-- Migration 0009: invoices
+-- A request from issuer_id for payer_id to pay an amount. Paying it is a normal
+-- transfer, whose transaction is linked here. An invoice is 'paying' only while
+-- that transfer runs, so it can't be paid twice.
+
+CREATE TABLE IF NOT EXISTS invoice (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    issuer_id BIGINT NOT NULL,
+    payer_id BIGINT NOT NULL,
+    currency_id BIGINT NOT NULL,
+    amount DECIMAL(24,8) NOT NULL,
+    memo VARCHAR(200) NULL,
+    status ENUM('open','paying','paid','declined','expired') NOT NULL DEFAULT 'open',
+    transaction_uuid CHAR(36) NULL,
+    expires_at DATETIME NOT NULL,
+    date_created DATETIME DEFAULT CURRENT_TIMESTAMP,
+    date_updated DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    
+    INDEX idx_invoice_payer (payer_id, status),
+    INDEX idx_invoice_issuer (issuer_id, status),
+    INDEX idx_invoice_expiry (status, expires_at),
+    
+    CONSTRAINT fk_invoice_currency
+        FOREIGN KEY (currency_id)
+        REFERENCES currency(id)
+        ON DELETE RESTRICT ON UPDATE CASCADE
+);
@@ -0,0 +1,27 @@
+-- Migration 0010: wire transfer journal
+-- Every wire is written here before either balance changes. The SMITE side is
+-- applied in the same transaction that moves the entry to 'smite_applied', so
+-- an entry left in 'initiated' or 'smite_applied' is a transfer that stopped
+-- half way and is picked up by the recovery worker. remote_before is the
+-- UnbelievaBoat bank balance read just before the remote side was changed.
+
+CREATE TABLE IF NOT EXISTS wire_transfer (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    discord_id BIGINT NOT NULL,
+    currency_id BIGINT NOT NULL,
+    direction ENUM('in','out') NOT NULL,
+    amount DECIMAL(24,8) NOT NULL,
+    state ENUM('initiated','smite_applied','remote_applied','compensated','failed') NOT NULL DEFAULT 'initiated',
+    remote_before BIGINT NULL,
+    last_error VARCHAR(255) NULL,
+    date_created DATETIME DEFAULT CURRENT_TIMESTAMP,
+    date_updated DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    
+    INDEX idx_wire_user (discord_id, id),
+    INDEX idx_wire_state (state, date_updated),
+    
+    CONSTRAINT fk_wire_currency
+        FOREIGN KEY (currency_id)
+        REFERENCES currency(id)
+        ON DELETE RESTRICT ON UPDATE CASCADE
+);
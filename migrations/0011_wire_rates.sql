@@ -0,0 +1,44 @@
+-- Migration 0011: wire rates and fees
+-- Each currency's admins can set how many SMITE units one UnbelievaBoat unit is
+-- worth, limits per transfer, and a bridge fee paid into the tax account.
+-- Currencies without a row wire 1:1 with no limits or fee. Journal entries now
+-- record the fee and the whole UnbelievaBoat units moved; entries from before
+-- were 1:1, so their UnbelievaBoat units are their amount.
+
+CREATE TABLE IF NOT EXISTS wire_config (
+    currency_id BIGINT PRIMARY KEY,
+    rate DECIMAL(24,8) NOT NULL DEFAULT 1,
+    min_amount DECIMAL(24,8) NULL,
+    max_amount DECIMAL(24,8) NULL,
+    fee_percent DECIMAL(24,8) NOT NULL DEFAULT 0,
+    fee_flat DECIMAL(24,8) NOT NULL DEFAULT 0,
+    rounding ENUM('down','up','nearest') NOT NULL DEFAULT 'down',
+    date_updated DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    
+    CONSTRAINT fk_wire_config_currency
+        FOREIGN KEY (currency_id)
+        REFERENCES currency(id)
+        ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+SET @ddl = IF(
+    (SELECT COUNT(*) FROM information_schema.COLUMNS
+     WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'wire_transfer' AND COLUMN_NAME = 'fee') = 0,
+    'ALTER TABLE wire_transfer ADD COLUMN fee DECIMAL(24,8) NOT NULL DEFAULT 0 AFTER amount',
+    'DO 0'
+);
+PREPARE stmt FROM @ddl;
+EXECUTE stmt;
+DEALLOCATE PREPARE stmt;
+
+SET @ddl = IF(
+    (SELECT COUNT(*) FROM information_schema.COLUMNS
+     WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'wire_transfer' AND COLUMN_NAME = 'remote_amount') = 0,
+    'ALTER TABLE wire_transfer ADD COLUMN remote_amount BIGINT NOT NULL DEFAULT 0 AFTER fee',
+    'DO 0'
+);
+PREPARE stmt FROM @ddl;
+EXECUTE stmt;
+DEALLOCATE PREPARE stmt;
+
+UPDATE wire_transfer SET remote_amount = FLOOR(amount) WHERE remote_amount = 0;
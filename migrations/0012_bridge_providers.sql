@@ -0,0 +1,23 @@
+-- Migration 0012: economy bridge providers
+-- Wires can go to economy bots other than UnbelievaBoat. Each currency picks a
+-- provider by api_type; the generic HTTP JSON provider keeps its endpoint and
+-- field names in `settings` as JSON. Currencies without a row use UnbelievaBoat.
+-- UnbelievaBoat is inserted here too so it keeps id 1 on a fresh database.
+
+INSERT IGNORE INTO api_type (id, name) VALUES (1, 'UnbelievaBoat'), (2, 'HTTP JSON');
+
+CREATE TABLE IF NOT EXISTS bridge_provider (
+    currency_id BIGINT PRIMARY KEY,
+    api_type_id TINYINT NOT NULL DEFAULT 1,
+    settings TEXT NULL,
+    date_updated DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    
+    CONSTRAINT fk_bridge_provider_currency
+        FOREIGN KEY (currency_id)
+        REFERENCES currency(id)
+        ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT fk_bridge_provider_type
+        FOREIGN KEY (api_type_id)
+        REFERENCES api_type(id)
+        ON DELETE RESTRICT ON UPDATE CASCADE
+);
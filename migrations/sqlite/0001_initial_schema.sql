@@ -0,0 +1,117 @@
+-- SQLite migration 0001: initial schema
+-- The MySQL schema as of migration 0005, minus the order book and price candles,
+-- which need the MySQL backend. Amounts are INTEGER counts of 10^-8 units and
+-- dates are UTC 'YYYY-MM-DD HH:MM:SS' text, so both compare and sort correctly.
+
+CREATE TABLE IF NOT EXISTS currency (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER UNIQUE NOT NULL,
+    name TEXT UNIQUE NOT NULL,
+    ticker TEXT UNIQUE NOT NULL,
+    swap_expiry_minutes INTEGER NULL,
+    date_created TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS account (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    discord_id INTEGER NOT NULL,
+    currency_id INTEGER NOT NULL REFERENCES currency(id) ON DELETE RESTRICT,
+    balance INTEGER NOT NULL DEFAULT 0,
+    date_created TEXT DEFAULT CURRENT_TIMESTAMP,
+    date_updated TEXT DEFAULT CURRENT_TIMESTAMP,
+
+    UNIQUE (discord_id, currency_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_account_currency ON account (currency_id);
+
+CREATE TABLE IF NOT EXISTS currency_swap (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    maker_id INTEGER NOT NULL REFERENCES account(id) ON DELETE RESTRICT,
+    taker_id INTEGER NULL REFERENCES account(id) ON DELETE RESTRICT,
+    maker_currency_id INTEGER NOT NULL REFERENCES currency(id) ON DELETE RESTRICT,
+    taker_currency_id INTEGER NOT NULL REFERENCES currency(id) ON DELETE RESTRICT,
+    maker_amount INTEGER NOT NULL,
+    taker_amount INTEGER NOT NULL,
+    filled_amount INTEGER NOT NULL DEFAULT 0,
+    status TEXT NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'accepted', 'completed', 'cancelled', 'expired')),
+    expires_at TEXT NULL,
+    date_created TEXT DEFAULT CURRENT_TIMESTAMP,
+    date_updated TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_swap_status_expiry ON currency_swap (status, expires_at);
+CREATE INDEX IF NOT EXISTS idx_swap_maker ON currency_swap (maker_id);
+CREATE INDEX IF NOT EXISTS idx_swap_taker ON currency_swap (taker_id);
+
+CREATE TABLE IF NOT EXISTS swap_message (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    swap_id INTEGER NOT NULL REFERENCES currency_swap(id) ON DELETE CASCADE,
+    channel_id INTEGER NOT NULL,
+    message_id INTEGER NOT NULL,
+    date_created TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_swap_message_swap ON swap_message (swap_id);
+
+CREATE TABLE IF NOT EXISTS swap_fill (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    swap_id INTEGER NOT NULL REFERENCES currency_swap(id) ON DELETE CASCADE,
+    taker_id INTEGER NOT NULL REFERENCES account(id) ON DELETE RESTRICT,
+    maker_amount INTEGER NOT NULL,
+    taker_amount INTEGER NOT NULL,
+    date_created TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_swap_fill_swap ON swap_fill (swap_id);
+
+-- TRANSACTION is a keyword in SQLite, so the table name is always quoted
+CREATE TABLE IF NOT EXISTS "transaction" (
+    uuid TEXT PRIMARY KEY,
+    sender_id INTEGER NOT NULL REFERENCES account(id) ON DELETE RESTRICT,
+    receiver_id INTEGER NOT NULL REFERENCES account(id) ON DELETE RESTRICT,
+    amount INTEGER NOT NULL,
+    date_created TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_transaction_sender ON "transaction" (sender_id);
+CREATE INDEX IF NOT EXISTS idx_transaction_receiver ON "transaction" (receiver_id);
+
+CREATE TABLE IF NOT EXISTS tradelog (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    base_currency_id INTEGER NOT NULL REFERENCES currency(id) ON DELETE RESTRICT,
+    quote_currency_id INTEGER NOT NULL REFERENCES currency(id) ON DELETE RESTRICT,
+    price INTEGER NOT NULL,
+    date_created TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_tradelog_pair_date ON tradelog (base_currency_id, quote_currency_id, date_created);
+
+CREATE TABLE IF NOT EXISTS tax_account (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    currency_id INTEGER UNIQUE NOT NULL REFERENCES currency(id) ON DELETE CASCADE,
+    balance INTEGER NOT NULL DEFAULT 0,
+    tax_percentage INTEGER NOT NULL DEFAULT 0,
+    date_created TEXT DEFAULT CURRENT_TIMESTAMP,
+    date_updated TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS api_type (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    date_created TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS api_token (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    currency_id INTEGER NOT NULL REFERENCES currency(id) ON DELETE CASCADE,
+    api_type_id INTEGER NOT NULL REFERENCES api_type(id) ON DELETE RESTRICT,
+    encrypted_token TEXT NOT NULL,
+    date_created TEXT DEFAULT CURRENT_TIMESTAMP,
+    date_updated TEXT DEFAULT CURRENT_TIMESTAMP,
+
+    UNIQUE (currency_id, api_type_id)
+);
+
+INSERT INTO api_type (id, name) VALUES (1, 'UnbelievaBoat');
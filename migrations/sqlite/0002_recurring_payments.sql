@@ -0,0 +1,19 @@
+-- SQLite migration 0002: recurring payments
+-- The same standing orders as MySQL migration 0006.
+
+CREATE TABLE IF NOT EXISTS recurring_payment (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    payer_id INTEGER NOT NULL,
+    recipient_id INTEGER NOT NULL,
+    currency_id INTEGER NOT NULL REFERENCES currency(id) ON DELETE RESTRICT,
+    amount INTEGER NOT NULL,
+    period TEXT NOT NULL CHECK (period IN ('daily', 'weekly', 'monthly')),
+    next_run_at TEXT NOT NULL,
+    failure_count INTEGER NOT NULL DEFAULT 0,
+    status TEXT NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'suspended', 'cancelled')),
+    date_created TEXT DEFAULT CURRENT_TIMESTAMP,
+    date_updated TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_recurring_due ON recurring_payment (status, next_run_at);
+CREATE INDEX IF NOT EXISTS idx_recurring_payer ON recurring_payment (payer_id, status);
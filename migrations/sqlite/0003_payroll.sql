@@ -0,0 +1,20 @@
+-- SQLite migration 0003: scheduled payroll
+-- The same schedules as MySQL migration 0007.
+
+CREATE TABLE IF NOT EXISTS payroll_schedule (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    role_id INTEGER NOT NULL,
+    currency_id INTEGER NOT NULL REFERENCES currency(id) ON DELETE RESTRICT,
+    amount INTEGER NOT NULL,
+    source TEXT NOT NULL CHECK (source IN ('treasury', 'mint')),
+    period TEXT NOT NULL CHECK (period IN ('daily', 'weekly', 'monthly')),
+    next_run_at TEXT NOT NULL,
+    created_by INTEGER NOT NULL,
+    status TEXT NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'cancelled')),
+    date_created TEXT DEFAULT CURRENT_TIMESTAMP,
+    date_updated TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_payroll_due ON payroll_schedule (status, next_run_at);
+CREATE INDEX IF NOT EXISTS idx_payroll_guild ON payroll_schedule (guild_id, status);
@@ -0,0 +1,4 @@
+-- SQLite migration 0004: transaction memos
+-- The same column as MySQL migration 0008.
+
+ALTER TABLE "transaction" ADD COLUMN memo TEXT;
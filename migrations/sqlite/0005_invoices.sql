@@ -0,0 +1,20 @@
+-- SQLite migration 0005: invoices
+-- The same invoices as MySQL migration 0009.
+
+CREATE TABLE IF NOT EXISTS invoice (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    issuer_id INTEGER NOT NULL,
+    payer_id INTEGER NOT NULL,
+    currency_id INTEGER NOT NULL REFERENCES currency(id) ON DELETE RESTRICT,
+    amount INTEGER NOT NULL,
+    memo TEXT,
+    status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'paying', 'paid', 'declined', 'expired')),
+    transaction_uuid TEXT,
+    expires_at TEXT NOT NULL,
+    date_created TEXT DEFAULT CURRENT_TIMESTAMP,
+    date_updated TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_invoice_payer ON invoice (payer_id, status);
+CREATE INDEX IF NOT EXISTS idx_invoice_issuer ON invoice (issuer_id, status);
+CREATE INDEX IF NOT EXISTS idx_invoice_expiry ON invoice (status, expires_at);
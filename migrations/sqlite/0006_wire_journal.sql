@@ -0,0 +1,19 @@
+-- SQLite migration 0006: wire transfer journal
+-- The same journal as MySQL migration 0010.
+
+CREATE TABLE IF NOT EXISTS wire_transfer (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    discord_id INTEGER NOT NULL,
+    currency_id INTEGER NOT NULL REFERENCES currency(id) ON DELETE RESTRICT,
+    direction TEXT NOT NULL CHECK (direction IN ('in', 'out')),
+    amount INTEGER NOT NULL,
+    state TEXT NOT NULL DEFAULT 'initiated'
+        CHECK (state IN ('initiated', 'smite_applied', 'remote_applied', 'compensated', 'failed')),
+    remote_before INTEGER,
+    last_error TEXT,
+    date_created TEXT DEFAULT CURRENT_TIMESTAMP,
+    date_updated TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_wire_user ON wire_transfer (discord_id, id);
+CREATE INDEX IF NOT EXISTS idx_wire_state ON wire_transfer (state, date_updated);
@@ -0,0 +1,18 @@
+-- SQLite migration 0007: wire rates and fees
+-- The same table and columns as MySQL migration 0011.
+
+CREATE TABLE IF NOT EXISTS wire_config (
+    currency_id INTEGER PRIMARY KEY REFERENCES currency(id) ON DELETE CASCADE,
+    rate INTEGER NOT NULL DEFAULT 100000000,
+    min_amount INTEGER,
+    max_amount INTEGER,
+    fee_percent INTEGER NOT NULL DEFAULT 0,
+    fee_flat INTEGER NOT NULL DEFAULT 0,
+    rounding TEXT NOT NULL DEFAULT 'down' CHECK (rounding IN ('down', 'up', 'nearest')),
+    date_updated TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+ALTER TABLE wire_transfer ADD COLUMN fee INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE wire_transfer ADD COLUMN remote_amount INTEGER NOT NULL DEFAULT 0;
+
+UPDATE wire_transfer SET remote_amount = amount / 100000000 WHERE remote_amount = 0;
@@ -0,0 +1,11 @@
+-- SQLite migration 0008: economy bridge providers
+-- The same table as MySQL migration 0012.
+
+INSERT OR IGNORE INTO api_type (id, name) VALUES (2, 'HTTP JSON');
+
+CREATE TABLE IF NOT EXISTS bridge_provider (
+    currency_id INTEGER PRIMARY KEY REFERENCES currency(id) ON DELETE CASCADE,
+    api_type_id INTEGER NOT NULL DEFAULT 1 REFERENCES api_type(id) ON DELETE RESTRICT,
+    settings TEXT,
+    date_updated TEXT DEFAULT CURRENT_TIMESTAMP
+);
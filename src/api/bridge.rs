@@ -0,0 +1,154 @@
+//! Economy bridges: the other side of a wire.
+//!
+//! `EconomyBridge` is what `wire_service` needs from another economy bot: read a
+//! user's bank balance and move it by a relative amount. Each provider maps its own
+//! failures onto `ApiError`, so a user the provider has never seen is always
+//! `NotFound` and throttling is always `RateLimited`. UnbelievaBoat is api_type 1;
+//! any other bot with a JSON HTTP API can be described with `HttpJsonSettings`
+//! (api_type 2). A currency without a `bridge_provider` row uses UnbelievaBoat.
+
+use serenity::async_trait;
+use super::http_json::{HttpJsonClient, HttpJsonSettings};
+use super::unbelievaboat::models::{ApiError, BalanceResponse};
+use super::unbelievaboat::UnbelievaboatClient;
+
+/// `api_type` ids
+pub const UNBELIEVABOAT_API_TYPE: i32 = 1;
+pub const HTTP_JSON_API_TYPE: i32 = 2;
+
+/// A user's balance on the other economy. Only `bank` is wired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BridgeBalance {
+    pub cash: i64,
+    pub bank: i64,
+}
+
+impl From<BalanceResponse> for BridgeBalance {
+    fn from(balance: BalanceResponse) -> Self {
+        BridgeBalance {
+            cash: balance.cash,
+            bank: balance.bank,
+        }
+    }
+}
+
+/// Another economy bot that wires can move balances on
+#[async_trait]
+pub trait EconomyBridge: Send + Sync {
+    /// Provider name shown to users
+    fn name(&self) -> &str;
+
+    /// Wait until another request fits in the provider's rate limit
+    async fn rate_limit(&self);
+
+    async fn get_balance(&self, guild_id: u64, user_id: u64) -> Result<BridgeBalance, ApiError>;
+
+    /// Move the user's bank by `delta` (negative to take) and return the new balance
+    async fn apply_delta(&self, guild_id: u64, user_id: u64, delta: i64) -> Result<BridgeBalance, ApiError>;
+}
+
+/// Which economy a currency wires to
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum BridgeProvider {
+    #[default]
+    UnbelievaBoat,
+    HttpJson(Box<HttpJsonSettings>),
+}
+
+impl BridgeProvider {
+    pub fn api_type_id(&self) -> i32 {
+        match self {
+            BridgeProvider::UnbelievaBoat => UNBELIEVABOAT_API_TYPE,
+            BridgeProvider::HttpJson(_) => HTTP_JSON_API_TYPE,
+        }
+    }
+
+    /// Provider name shown to users
+    pub fn name(&self) -> &str {
+        match self {
+            BridgeProvider::UnbelievaBoat => "UnbelievaBoat",
+            BridgeProvider::HttpJson(settings) => &settings.name,
+        }
+    }
+
+    /// From a `bridge_provider` row
+    pub fn from_row(api_type_id: i32, settings: Option<&str>) -> Result<Self, String> {
+        match api_type_id {
+            UNBELIEVABOAT_API_TYPE => Ok(BridgeProvider::UnbelievaBoat),
+            HTTP_JSON_API_TYPE => {
+                let settings = settings.ok_or("The HTTP JSON provider has no settings".to_string())?;
+                serde_json::from_str(settings)
+                    .map(|settings| BridgeProvider::HttpJson(Box::new(settings)))
+                    .map_err(|e| format!("Invalid HTTP JSON provider settings: {}", e))
+            }
+            _ => Err(format!("Unknown wire provider type {}", api_type_id)),
+        }
+    }
+
+    /// Settings as stored in `bridge_provider`
+    pub fn settings_json(&self) -> Option<String> {
+        match self {
+            BridgeProvider::UnbelievaBoat => None,
+            BridgeProvider::HttpJson(settings) => serde_json::to_string(settings).ok(),
+        }
+    }
+
+    /// `unbelievaboat`, or `http <base_url> [key=value ...]` (see `HttpJsonSettings::set`)
+    pub fn parse(args: &[&str]) -> Result<Self, String> {
+        match args.first().map(|kind| kind.to_lowercase()).as_deref() {
+            Some("unbelievaboat") | Some("ub") if args.len() == 1 => Ok(BridgeProvider::UnbelievaBoat),
+            Some("http") => {
+                let base_url = args.get(1).ok_or("❌ Usage: `http <base_url> [key=value ...]`".to_string())?;
+                let mut settings = HttpJsonSettings::new(base_url);
+                for option in &args[2..] {
+                    let (key, value) = option
+                        .split_once('=')
+                        .ok_or(format!("❌ Expected `key=value`, got '{}'", option))?;
+                    settings.set(key, value)?;
+                }
+                settings.validate()?;
+                Ok(BridgeProvider::HttpJson(Box::new(settings)))
+            }
+            _ => Err("❌ Provider must be `unbelievaboat` or `http <base_url> [key=value ...]`".to_string()),
+        }
+    }
+
+    /// A client for the provider, authenticated with the currency's API token
+    pub fn connect(&self, api_token: String) -> Box<dyn EconomyBridge> {
+        match self {
+            BridgeProvider::UnbelievaBoat => Box::new(UnbelievaboatClient::new(api_token)),
+            BridgeProvider::HttpJson(settings) => Box::new(HttpJsonClient::new(settings.as_ref().clone(), api_token)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_parses_and_round_trips() {
+        assert_eq!(BridgeProvider::parse(&["UnbelievaBoat"]), Ok(BridgeProvider::UnbelievaBoat));
+        assert_eq!(BridgeProvider::from_row(1, None), Ok(BridgeProvider::UnbelievaBoat));
+
+        let provider = BridgeProvider::parse(&[
+            "http", "https://economy.example/api/", "name=Coins", "bank=data.balance", "method=post", "rps=5",
+        ])
+        .unwrap();
+        let BridgeProvider::HttpJson(settings) = &provider else {
+            panic!("expected the HTTP JSON provider");
+        };
+        assert_eq!(settings.base_url, "https://economy.example/api");
+        assert_eq!((settings.bank_field.as_str(), settings.delta_method.as_str()), ("data.balance", "POST"));
+        assert_eq!((provider.name(), provider.api_type_id()), ("Coins", HTTP_JSON_API_TYPE));
+
+        let stored = provider.settings_json();
+        assert_eq!(BridgeProvider::from_row(HTTP_JSON_API_TYPE, stored.as_deref()), Ok(provider));
+
+        assert!(BridgeProvider::parse(&["http", "ftp://economy.example"]).is_err());
+        assert!(BridgeProvider::parse(&["http", "https://economy.example", "colour=red"]).is_err());
+        assert!(BridgeProvider::parse(&["paypal"]).is_err());
+        assert!(BridgeProvider::from_row(HTTP_JSON_API_TYPE, None).is_err());
+        assert!(BridgeProvider::from_row(9, None).is_err());
+    }
+}
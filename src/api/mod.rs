@@ -1,3 +1,5 @@
+pub mod bridge;
+pub mod http_json;
 pub mod unbelievaboat;
 
 pub use unbelievaboat::UnbelievaboatClient;
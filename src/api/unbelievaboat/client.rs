@@ -1,10 +1,12 @@
 use reqwest::Client as HttpClient;
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
 use super::models::{
-    BalanceResponse, BalanceUpdateRequest, BalanceModifyRequest, ApiError, RateLimitInfo,
+    BalanceResponse, BalanceModifyRequest, ApiError, RateLimitInfo,
     RateLimitResponse,
 };
+use serenity::async_trait;
 use tracing::warn;
+use crate::api::bridge::{BridgeBalance, EconomyBridge};
 
 /// Unbelievaboat API client for handling Discord economy interactions
 pub struct UnbelievaboatClient {
@@ -174,52 +176,6 @@ impl UnbelievaboatClient {
             .map_err(|e| ApiError::DeserializationError(format!("Failed to parse response: {}", e)))
     }
 
-    /// PUT /users/{user_id}/balance
-    /// 
-    /// Sets the balance (cash and/or bank) for a Discord user. This is a complete override,
-    /// not an increment/decrement operation.
-    /// 
-    /// # Arguments
-    /// * `guild_id` - The Discord guild ID
-    /// * `user_id` - The Discord user ID
-    /// * `cash` - Optional: Set cash balance to this value
-    /// * `bank` - Optional: Set bank balance to this value
-    /// 
-    /// # Returns
-    /// * `Ok(BalanceResponse)` - Updated balance information
-    /// * `Err(ApiError)` - Error with detailed error type and rate limit info
-    pub async fn set_user_balance(
-        &self,
-        guild_id: u64,
-        user_id: u64,
-        cash: Option<i64>,
-        bank: Option<i64>,
-    ) -> Result<BalanceResponse, ApiError> {
-        let url = format!("{}/guilds/{}/users/{}", self.base_url, guild_id, user_id);
-        let headers = self.create_headers()
-            .map_err(|e| ApiError::RequestError(e))?;
-
-        let body = BalanceUpdateRequest { cash, bank };
-
-        let response = self.http_client
-            .put(&url)
-            .headers(headers)
-            .json(&body)
-            .send()
-            .await
-            .map_err(|e| ApiError::RequestError(format!("Request failed: {}", e)))?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            return Err(Self::handle_error_response(status, response).await);
-        }
-
-        response
-            .json::<BalanceResponse>()
-            .await
-            .map_err(|e| ApiError::DeserializationError(format!("Failed to parse response: {}", e)))
-    }
-
     /// PATCH /users/{user_id}/balance
     /// 
     /// Modifies the balance (cash and/or bank) for a Discord user. This operation
@@ -267,4 +223,77 @@ impl UnbelievaboatClient {
     }
 }
 
+#[async_trait]
+impl EconomyBridge for UnbelievaboatClient {
+    fn name(&self) -> &str {
+        "UnbelievaBoat"
+    }
+
+    async fn rate_limit(&self) {
+        crate::utils::rate_limit_ub_api().await;
+    }
 
+    async fn get_balance(&self, guild_id: u64, user_id: u64) -> Result<BridgeBalance, ApiError> {
+        self.get_user_balance(guild_id, user_id).await.map(BridgeBalance::from)
+    }
+
+    async fn apply_delta(&self, guild_id: u64, user_id: u64, delta: i64) -> Result<BridgeBalance, ApiError> {
+        self.modify_user_balance(guild_id, user_id, None, Some(delta)).await.map(BridgeBalance::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::unbelievaboat::mock::MockUnbelievaboat;
+
+    #[tokio::test]
+    async fn test_balance_is_read_and_moved_by_deltas() {
+        let mock = MockUnbelievaboat::start().await;
+        mock.set_balance(1, 7, 50, 100);
+        let client = mock.client();
+
+        let balance = client.get_user_balance(1, 7).await.unwrap();
+        assert_eq!((balance.user_id.as_str(), balance.cash, balance.bank), ("7", 50, 100));
+
+        let balance = client.modify_user_balance(1, 7, None, Some(-30)).await.unwrap();
+        assert_eq!((balance.cash, balance.bank), (50, 70));
+        assert_eq!(mock.balance(1, 7), Some((50, 70)));
+
+        // A change creates a user the API hasn't seen yet
+        assert!(matches!(client.get_user_balance(1, 8).await, Err(ApiError::NotFound(_))));
+        client.modify_user_balance(1, 8, Some(5), Some(10)).await.unwrap();
+        assert_eq!(mock.balance(1, 8), Some((5, 10)));
+    }
+
+    #[tokio::test]
+    async fn test_error_statuses_are_mapped() {
+        let mock = MockUnbelievaboat::start().await;
+        mock.set_balance(1, 7, 0, 100);
+        mock.set_balance(2, 7, 0, 100);
+        mock.forbid_guild(2);
+
+        let wrong_token = mock.client_with_token("stolen");
+        assert!(matches!(wrong_token.get_user_balance(1, 7).await, Err(ApiError::Unauthorized(_))));
+
+        let client = mock.client();
+        assert!(matches!(client.get_user_balance(2, 7).await, Err(ApiError::Forbidden(_))));
+        assert!(matches!(client.modify_user_balance(2, 7, None, Some(1)).await, Err(ApiError::Forbidden(_))));
+
+        mock.rate_limit_next("PATCH", 1500);
+        assert!(matches!(
+            client.modify_user_balance(1, 7, None, Some(1)).await,
+            Err(ApiError::RateLimited { retry_after: 1500, is_global: false })
+        ));
+        // Without a readable body the client waits a second
+        mock.fail_next("GET", 429, "slow down");
+        assert!(matches!(client.get_user_balance(1, 7).await, Err(ApiError::RateLimited { retry_after: 1000, .. })));
+
+        mock.fail_next("GET", 503, "maintenance");
+        assert!(matches!(client.get_user_balance(1, 7).await, Err(ApiError::ServerError(503, _))));
+
+        // None of the refused changes went through
+        assert_eq!(mock.balance(1, 7), Some((0, 100)));
+        assert_eq!(mock.balance(2, 7), Some((0, 100)));
+    }
+}
@@ -0,0 +1,263 @@
+//! In-process fake of the UnbelievaBoat API for tests.
+//!
+//! Serves `GET` and `PATCH /guilds/{guild_id}/users/{user_id}` on a local port with
+//! the status codes the real API uses: 401 for a wrong token, 403 for a guild the
+//! application can't manage, 404 for a user it has never seen and 429 with a
+//! `retry_after` body. A `PATCH` creates the user like the real API does. Failures
+//! can be queued for the next request of a method to break a wire half way, either
+//! instead of serving it or after serving it, like a reply lost on the way back.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use super::UnbelievaboatClient;
+
+pub const MOCK_TOKEN: &str = "mock-token";
+
+struct QueuedFailure {
+    method: String,
+    status: u16,
+    body: String,
+    /// Serve the request before answering with the failure
+    applied: bool,
+}
+
+#[derive(Default)]
+struct MockState {
+    /// (guild_id, user_id) -> (cash, bank)
+    balances: HashMap<(u64, u64), (i64, i64)>,
+    forbidden_guilds: HashSet<u64>,
+    /// Queued failures, taken by the next matching request
+    failures: VecDeque<QueuedFailure>,
+    /// (method, path) of every request served
+    requests: Vec<(String, String)>,
+}
+
+pub struct MockUnbelievaboat {
+    base_url: String,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockUnbelievaboat {
+    /// Listen on a free local port until the test's runtime shuts down
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(MockState::default()));
+
+        let server_state = state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, server_state.clone()));
+            }
+        });
+
+        Self { base_url, state }
+    }
+
+    /// A client pointed at the mock with its token
+    pub fn client(&self) -> UnbelievaboatClient {
+        self.client_with_token(MOCK_TOKEN)
+    }
+
+    pub fn client_with_token(&self, token: &str) -> UnbelievaboatClient {
+        UnbelievaboatClient::with_base_url(token.to_string(), self.base_url.clone())
+    }
+
+    pub fn set_balance(&self, guild_id: u64, user_id: u64, cash: i64, bank: i64) {
+        self.state.lock().unwrap().balances.insert((guild_id, user_id), (cash, bank));
+    }
+
+    /// (cash, bank), or None if the user was never created
+    pub fn balance(&self, guild_id: u64, user_id: u64) -> Option<(i64, i64)> {
+        self.state.lock().unwrap().balances.get(&(guild_id, user_id)).copied()
+    }
+
+    /// Answer 403 for every request about this guild
+    pub fn forbid_guild(&self, guild_id: u64) {
+        self.state.lock().unwrap().forbidden_guilds.insert(guild_id);
+    }
+
+    /// Answer the next `method` request with `status` and `body` instead of serving it
+    pub fn fail_next(&self, method: &str, status: u16, body: &str) {
+        self.queue_failure(method, status, body, false);
+    }
+
+    /// Serve the next `method` request, then answer it with `status` and `body`
+    pub fn fail_next_after_applying(&self, method: &str, status: u16, body: &str) {
+        self.queue_failure(method, status, body, true);
+    }
+
+    fn queue_failure(&self, method: &str, status: u16, body: &str, applied: bool) {
+        self.state.lock().unwrap().failures.push_back(QueuedFailure {
+            method: method.to_string(),
+            status,
+            body: body.to_string(),
+            applied,
+        });
+    }
+
+    /// Answer the next `method` request with a 429 asking to wait `retry_after` ms
+    pub fn rate_limit_next(&self, method: &str, retry_after: i64) {
+        let body = serde_json::json!({
+            "message": "You are being rate limited.",
+            "retry_after": retry_after,
+            "global": false,
+        });
+        self.fail_next(method, 429, &body.to_string());
+    }
+
+    /// Number of requests served with this method
+    pub fn request_count(&self, method: &str) -> usize {
+        self.state.lock().unwrap().requests.iter().filter(|(m, _)| m == method).count()
+    }
+}
+
+/// Read one request, answer it and close the connection
+async fn serve(mut stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 4096];
+
+    // Headers first, then as much body as Content-Length says
+    let header_end = loop {
+        match stream.read(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => data.extend_from_slice(&buf[..n]),
+        }
+        if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+    let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+    let content_length = header(&head, "content-length").and_then(|v| v.parse::<usize>().ok()).unwrap_or(0);
+    while data.len() < header_end + content_length {
+        match stream.read(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => data.extend_from_slice(&buf[..n]),
+        }
+    }
+    let body = String::from_utf8_lossy(&data[header_end..header_end + content_length]).to_string();
+
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    let authorization = header(&head, "authorization");
+
+    let (status, response) = handle(&state, &method, &path, authorization.as_deref(), &body);
+    let reply = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        response.len(),
+        response
+    );
+    let _ = stream.write_all(reply.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+fn handle(
+    state: &Mutex<MockState>,
+    method: &str,
+    path: &str,
+    authorization: Option<&str>,
+    body: &str,
+) -> (u16, String) {
+    let mut state = state.lock().unwrap();
+    state.requests.push((method.to_string(), path.to_string()));
+
+    if let Some(idx) = state.failures.iter().position(|failure| failure.method == method) {
+        let failure = state.failures.remove(idx).unwrap();
+        if failure.applied {
+            serve_request(&mut state, method, path, authorization, body);
+        }
+        return (failure.status, failure.body);
+    }
+
+    serve_request(&mut state, method, path, authorization, body)
+}
+
+fn serve_request(
+    state: &mut MockState,
+    method: &str,
+    path: &str,
+    authorization: Option<&str>,
+    body: &str,
+) -> (u16, String) {
+    if authorization != Some(format!("Bearer {}", MOCK_TOKEN).as_str()) {
+        return (401, error_body("401: Unauthorized"));
+    }
+
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    let (guild_id, user_id) = match segments.as_slice() {
+        ["guilds", guild_id, "users", user_id] => match (guild_id.parse::<u64>(), user_id.parse::<u64>()) {
+            (Ok(guild_id), Ok(user_id)) => (guild_id, user_id),
+            _ => return (400, error_body("Invalid ID")),
+        },
+        _ => return (404, error_body("404: Not Found")),
+    };
+
+    if state.forbidden_guilds.contains(&guild_id) {
+        return (403, error_body("Missing Permissions"));
+    }
+
+    match method {
+        "GET" => match state.balances.get(&(guild_id, user_id)) {
+            Some(&(cash, bank)) => (200, balance_body(user_id, cash, bank)),
+            None => (404, error_body("Unknown User")),
+        },
+        "PATCH" => {
+            let request: serde_json::Value = match serde_json::from_str(body) {
+                Ok(request) => request,
+                Err(_) => return (400, error_body("Invalid JSON")),
+            };
+            let cash_delta = request.get("cash").and_then(|v| v.as_i64()).unwrap_or(0);
+            let bank_delta = request.get("bank").and_then(|v| v.as_i64()).unwrap_or(0);
+
+            let entry = state.balances.entry((guild_id, user_id)).or_insert((0, 0));
+            entry.0 += cash_delta;
+            entry.1 += bank_delta;
+            let (cash, bank) = *entry;
+            (200, balance_body(user_id, cash, bank))
+        }
+        _ => (405, error_body("405: Method Not Allowed")),
+    }
+}
+
+fn header(head: &str, name: &str) -> Option<String> {
+    head.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim().to_string())
+}
+
+fn balance_body(user_id: u64, cash: i64, bank: i64) -> String {
+    serde_json::json!({
+        "rank": "1",
+        "user_id": user_id.to_string(),
+        "cash": cash,
+        "bank": bank,
+        "total": cash + bank,
+    })
+    .to_string()
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "message": message }).to_string()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Error",
+    }
+}
@@ -1,5 +1,7 @@
 pub mod client;
 pub mod models;
+#[cfg(test)]
+pub mod mock;
 
 pub use client::UnbelievaboatClient;
 pub use models::{ApiError, BalanceResponse, RateLimitInfo};
@@ -15,13 +15,6 @@ pub struct BalanceResponse {
     pub bank: i64,
 }
 
-/// Request body for PUT user balance (set balance)
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct BalanceUpdateRequest {
-    pub cash: Option<i64>,
-    pub bank: Option<i64>,
-}
-
 /// Request body for PATCH user balance (modify balance)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BalanceModifyRequest {
@@ -79,6 +72,22 @@ pub enum ApiError {
     DeserializationError(String),
 }
 
+impl ApiError {
+    /// The request was turned away without being acted on (400, 401, 403, 404 or 429).
+    /// After anything else, like a timeout, a 5xx or an unreadable reply, a change
+    /// may have been applied.
+    pub fn is_refusal(&self) -> bool {
+        matches!(
+            self,
+            ApiError::BadRequest(_)
+                | ApiError::Unauthorized(_)
+                | ApiError::Forbidden(_)
+                | ApiError::NotFound(_)
+                | ApiError::RateLimited { .. }
+        )
+    }
+}
+
 impl std::fmt::Display for ApiError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -1,6 +1,7 @@
 use serenity::model::channel::Message;
 use serenity::prelude::Context;
 use crate::services::balance_service;
+use crate::utils::Invocation;
 
 pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(), String> {
     let currency_ticker = if args.is_empty() {
@@ -31,7 +32,7 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
         Some(args[0].to_uppercase())
     };
 
-    match balance_service::get_balance(ctx, msg, currency_ticker.as_deref()).await {
+    match balance_service::get_balance(ctx, &Invocation::from(msg), currency_ticker.as_deref()).await {
         Ok(result) => {
             let embed = balance_service::create_balance_embed(&result);
             msg.channel_id
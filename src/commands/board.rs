@@ -4,5 +4,10 @@ use serenity::prelude::Context;
 use crate::services::board_service;
 
 pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(), String> {
-    board_service::list_currencies(ctx, msg, args).await
+    let (sort_by, page_num) = board_service::parse_board_args(args);
+    let page = board_service::list_currencies(ctx, sort_by, page_num).await?;
+
+    page.send(ctx, msg.channel_id, msg.author.id)
+        .await
+        .map_err(|e| format!("Failed to send message: {}", e))
 }
@@ -0,0 +1,28 @@
+use serenity::model::channel::Message;
+use serenity::prelude::Context;
+use crate::services::order_service;
+
+pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(), String> {
+    if args.is_empty() {
+        let help_embed = serenity::builder::CreateEmbed::default()
+            .title("📖 Book Command")
+            .description("Show the open bids and asks for a currency pair")
+            .field("Usage", "`$book <BASE/QUOTE>`", false)
+            .field("Examples", "`$book ABC/XYZ`", false)
+            .color(0x0b5394);
+
+        msg.channel_id
+            .send_message(ctx, serenity::builder::CreateMessage::default().embed(help_embed))
+            .await
+            .map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    let embed = order_service::get_book(ctx, args[0]).await?;
+    msg.channel_id
+        .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
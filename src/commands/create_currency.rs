@@ -1,6 +1,7 @@
 use serenity::model::channel::Message;
 use serenity::prelude::Context;
 use crate::services::create_currency_service;
+use crate::utils::Invocation;
 
 pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(), String> {
     // Parse quoted currency name
@@ -63,7 +64,7 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
 
     let ticker = remaining_args[0];
 
-    match create_currency_service::execute_create_currency(ctx, msg, &name, ticker).await {
+    match create_currency_service::execute_create_currency(ctx, &Invocation::from(msg), &name, ticker).await {
         Ok(result) => {
             let embed = create_currency_service::create_currency_embed(&result);
             msg.channel_id
@@ -24,16 +24,36 @@ pub async fn execute(ctx: &Context, msg: &Message) -> Result<(), String> {
         )
         .field(
             "💸 Transactions",
-            "`$send @user <amount> <TICKER>` - Transfer funds\n`$transaction [list|UUID]` - View transaction history",
+            "`$send @user <amount> <TICKER> [\"memo\"]` - Transfer funds, with an optional memo\n`$transaction [list|UUID]` - View transaction history\n`$transaction list <text>` - Search your history by memo",
             false,
         ).field(
             "💸 Multiple Transactions",
-            "`$send (@user1 @user2 ... @userN) <amount> <TICKER>` - Transfer funds\n`$transaction [list|UUID]` - View transaction history",
+            "`$send @user1 @user2 ... @userN <amount> <TICKER>` - Send each user the same amount\n`$send @user1:<amount> @user2:<amount> ... <TICKER>` - Send each user their own amount\nAll or nothing, up to 25 recipients, tax on the total",
+            false,
+        )
+        .field(
+            "🔁 Recurring Payments",
+            "`$recurring create @user <amount> <TICKER> <daily|weekly|monthly>` - Pay someone on a schedule\n`$recurring list` - View your recurring payments\n`$recurring cancel <ID>` - Stop a recurring payment",
+            false,
+        )
+        .field(
+            "🧾 Invoices",
+            "`$invoice @user <amount> <TICKER> [\"memo\"]` - Ask someone to pay you\n`$invoice pay <ID>` / `$invoice decline <ID>` - Settle an invoice (or use its buttons)\n`$invoice list [open|paid|declined|expired]` - View your invoices",
+            false,
+        )
+        .field(
+            "💼 Payroll",
+            "`$payroll <@role> <amount> <TICKER> [treasury|mint] [daily|weekly|monthly]` - Pay every member of a role (Admin)\n`$payroll list` - View scheduled payrolls\n`$payroll cancel <ID>` - Stop a scheduled payroll (Admin)",
             false,
         )
         .field(
             "💱 Swaps & Trading",
-            "`$swap set <amount> <TICKER> [@user] [<amount> <TICKER>]` - Create swap offer\n`$swap list [status]` - View swaps (pending/accepted/all)\n`$swap accept <ID>` - Accept swap\n`$swap deny <ID>` - Reject swap",
+            "`$swap set <amount> <TICKER> [@user] [<amount> <TICKER>]` - Create swap offer\n`$swap list [status]` - View swaps (pending/accepted/all)\n`$swap accept <ID>` - Accept swap\n`$swap deny <ID>` - Reject swap\n`$route <amount> <FROM> <TO>` - Convert through a chain of open swaps",
+            false,
+        )
+        .field(
+            "📖 Order Book",
+            "`$order buy|sell <quantity> <BASE>/<QUOTE> <price>` - Place limit order\n`$order cancel <ID>` - Cancel order\n`$order list` - View your open orders\n`$book <BASE>/<QUOTE>` - View bids and asks",
             false,
         )
         .field(
@@ -43,12 +63,12 @@ pub async fn execute(ctx: &Context, msg: &Message) -> Result<(), String> {
         )
         .field(
             "📊 Prices & Charts",
-            "`$price <BASE>/<QUOTE> [timeframe]` - Get price\n`$price chart <BASE>/<QUOTE> [timeframe]` - Generate chart\n`$price list [filter]` - View price list",
+            "`$price <BASE>/<QUOTE> [timeframe]` - Get price\n`$price chart <BASE>/<QUOTE> [timeframe] [candles <interval>]` - Generate chart\n`$price list [filter]` - View price list",
             false,
         )
         .field(
-            "🌐 Bridge to UnbelievaBoat",
-            "`$wire in <amount> <TICKER>` - Transfer from UnbelievaBoat to SMITE (Works in DM or guilds)\n`$wire out <amount> <TICKER>` - Transfer from SMITE to UnbelievaBoat (Works in DM or guilds)\n`$wire set token <guild_id> <token>` - Configure API token (DM-only, Admin)\n\n⚠️ **SECURITY WARNING**: Always run `$wire set token` in **DMs** to avoid exposing your token in public chat!",
+            "🌐 Bridge to UnbelievaBoat & Other Economies",
+            "`$wire in <amount> <TICKER>` - Transfer from UnbelievaBoat to SMITE (Works in DM or guilds)\n`$wire out <amount> <TICKER>` - Transfer from SMITE to UnbelievaBoat (Works in DM or guilds)\nBoth show a quote with the rate and fee to confirm first\n`$wire history` - Your recent wire transfers\n`$wire reconcile [<ID> complete|refund]` - Settle interrupted wires (Admin)\n`$wire config [rate|min|max|fee|rounding <value>]` - Wire rate, limits and fee (Admin)\n`$wire provider [unbelievaboat|http <base_url> ...]` - Economy the currency wires to (Admin)\n`$wire set token <guild_id> <token>` - Configure API token (DM-only, Admin)\n\n⚠️ **SECURITY WARNING**: Always run `$wire set token` in **DMs** to avoid exposing your token in public chat!",
             false,
         )
         .field(
@@ -31,7 +31,7 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
 
     let ticker = args[0].to_uppercase();
 
-    match info_service::execute_info(ctx, msg, &ticker).await {
+    match info_service::execute_info(ctx, &ticker).await {
         Ok(result) => {
             let embed = info_service::create_info_embed(&result);
             msg.channel_id
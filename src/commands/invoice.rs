@@ -0,0 +1,119 @@
+use serenity::model::channel::Message;
+use serenity::prelude::Context;
+use crate::services::invoice_service;
+use crate::utils::Invocation;
+use crate::utils::Amount;
+
+pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(), String> {
+    if args.is_empty() {
+        let help_embed = serenity::builder::CreateEmbed::default()
+            .title("🧾 Invoice Command")
+            .description("Ask another user to pay you, with a memo saying what for")
+            .field("Usage",
+                "`$invoice <@user or id> <amount> <currency> [\"memo\"]`\n\
+                 `$invoice pay <id>`\n\
+                 `$invoice decline <id>`\n\
+                 `$invoice list [open|paid|declined|expired]`",
+                false)
+            .field("Examples",
+                "`$invoice @Alice 25 ABC \"web design\"` (ask Alice for 25 ABC)\n\
+                 `$invoice pay 7` (pay invoice 7 through a normal send)\n\
+                 `$invoice list open` (invoices still waiting to be paid)",
+                false)
+            .field("Notes",
+                "• Created in a guild; the payer gets a DM with Pay and Decline buttons\n\
+                 • Paying sends the amount like `$send`, tax included, and links the transaction\n\
+                 • Invoices expire after 7 days; both sides get a DM when one is settled",
+                false)
+            .color(0x4682b4);
+
+        msg.channel_id
+            .send_message(ctx, serenity::builder::CreateMessage::default().embed(help_embed))
+            .await
+            .map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    match args[0].to_lowercase().as_str() {
+        "list" => {
+            let status = match args.get(1) {
+                Some(status) => {
+                    let status = status.to_lowercase();
+                    if !invoice_service::LIST_STATUSES.contains(&status.as_str()) {
+                        return Err(format!(
+                            "❌ Unknown status '{}'. Use `open`, `paid`, `declined` or `expired`",
+                            status
+                        ));
+                    }
+                    Some(status)
+                }
+                None => None,
+            };
+
+            let page = invoice_service::list_invoices(ctx, &Invocation::from(msg), status.as_deref()).await?;
+            page.send(ctx, msg.channel_id, msg.author.id).await?;
+        }
+        action @ ("pay" | "decline") => {
+            let id = args.get(1)
+                .ok_or(format!("Please specify an invoice ID: `$invoice {} <id>`", action))?
+                .trim_start_matches('#')
+                .parse::<i64>()
+                .map_err(|_| "Invalid invoice ID".to_string())?;
+
+            let invocation = Invocation::from(msg);
+            let embed = if action == "pay" {
+                invoice_service::pay_invoice(ctx, &invocation, id).await?
+            } else {
+                invoice_service::decline_invoice(ctx, &invocation, id).await?
+            };
+            msg.channel_id
+                .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        _ => {
+            // Everything from the first quoted word on is the memo: "web design"
+            let (args, memo) = match args.iter().position(|arg| arg.starts_with(['"', '“'])) {
+                Some(start) => {
+                    let memo = args[start..].join(" ");
+                    (&args[..start], Some(memo.trim_matches(['"', '“', '”']).to_string()))
+                }
+                None => (args, None),
+            };
+
+            if args.len() != 3 {
+                return Err("Usage: `$invoice <@user> <amount> <currency> [\"memo\"]`".to_string());
+            }
+
+            let payer_id = parse_user_id(args[0])?;
+            let amount = args[1].parse::<Amount>()
+                .map_err(|e| format!("❌ Invalid amount: {}", e))?;
+
+            let result = invoice_service::create_invoice(
+                ctx, &Invocation::from(msg), payer_id, amount, args[2], memo.as_deref()
+            ).await?;
+
+            let message = serenity::builder::CreateMessage::default()
+                .embed(invoice_service::create_invoice_embed(&result))
+                .components(invoice_service::create_invoice_buttons(result.id));
+            msg.channel_id
+                .send_message(ctx, message)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_user_id(input: &str) -> Result<i64, String> {
+    // Remove mention formatting: <@123456789> -> 123456789
+    let cleaned = input
+        .trim_start_matches('<')
+        .trim_start_matches('@')
+        .trim_start_matches('!')
+        .trim_end_matches('>');
+
+    cleaned.parse::<i64>()
+        .map_err(|_| "Invalid user ID or mention".to_string())
+}
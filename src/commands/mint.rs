@@ -1,6 +1,8 @@
 use serenity::model::channel::Message;
 use serenity::prelude::Context;
 use crate::services::mint_service;
+use crate::utils::Invocation;
+use crate::utils::Amount;
 
 pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(), String> {
     if args.len() < 2 {
@@ -29,14 +31,14 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
     }
 
     // Parse amount
-    let amount: f64 = args[0]
+    let amount: Amount = args[0]
         .parse()
-        .map_err(|_| "Invalid amount".to_string())?;
+        .map_err(|e| format!("{}", e))?;
 
     let currency_ticker = args[1].to_uppercase();
     let user_id = msg.author.id.get() as i64;
 
-    match mint_service::execute_mint(ctx, msg, user_id, amount, &currency_ticker).await {
+    match mint_service::execute_mint(ctx, &Invocation::from(msg), user_id, amount, &currency_ticker).await {
         Ok(result) => {
             let embed = mint_service::create_mint_embed(&result);
             msg.channel_id
@@ -11,6 +11,12 @@ pub mod info;
 pub mod board;
 pub mod help;
 pub mod wire;
+pub mod order;
+pub mod book;
+pub mod route;
+pub mod recurring;
+pub mod payroll;
+pub mod invoice;
 
 
 use serenity::model::channel::Message;
@@ -102,45 +108,53 @@ pub async fn handle_message(ctx: &Context, msg: &Message) {
         "info" => info::execute(ctx, msg, args).await,
         "board" | "list" | "ls" => board::execute(ctx, msg, args).await,
         "wire" => wire::execute(ctx, msg, args).await,
+        "order" | "orders" => order::execute(ctx, msg, args).await,
+        "book" | "depth" => book::execute(ctx, msg, args).await,
+        "route" => route::execute(ctx, msg, args).await,
+        "recurring" => recurring::execute(ctx, msg, args).await,
+        "payroll" => payroll::execute(ctx, msg, args).await,
+        "invoice" | "invoices" => invoice::execute(ctx, msg, args).await,
         _ => return,
     };
 
     if let Err(e) = result {
-        let error_msg = e.to_string();
-        error!("Error executing command {}: {}", command_name, error_msg);
-        
-        // Extract clean error message from database errors
-        // Pattern: "error returned from database: 1644 (45000): Insufficient balance to accept swap"
-        let clean_error = if error_msg.contains("error returned from database:") {
-            // Find the last colon, everything after it is the actual error message
-            if let Some(last_colon) = error_msg.rfind(": ") {
-                error_msg[last_colon + 2..].trim().to_string()
-            } else {
-                error_msg.clone()
-            }
-        } else {
-            error_msg.clone()
-        };
-        
-        // Determine error type and create user-friendly message
-        let user_message = if error_msg.contains("429") || error_msg.contains("rate limit") {
-            "⚠️ **Rate Limited**: Discord is rate limiting us. Please try again in a moment.".to_string()
-        } else if error_msg.contains("HTTP request") {
-            "⚠️ **Network Error**: Having trouble connecting to Discord. Please try again.".to_string()
-        } else if clean_error.len() > 0 {
-            format!("❌ {}", clean_error)
-        } else {
-            "❌ An error occurred while executing the command.".to_string()
-        };
-
-        // Send error to user as Discord message embed
-        let embed = serenity::builder::CreateEmbed::default()
-            .title("Command Error")
-            .description(user_message)
-            .color(0xff0000);
+        error!("Error executing command {}: {}", command_name, e);
 
         let _ = msg.channel_id
-            .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
+            .send_message(ctx, serenity::builder::CreateMessage::default().embed(create_error_embed(&e)))
             .await;
     }
 }
+
+/// Turn a command error into the user-facing "Command Error" embed
+/// Shared by prefix and slash commands
+pub fn create_error_embed(error_msg: &str) -> serenity::builder::CreateEmbed {
+    // Extract clean error message from database errors
+    // Pattern: "error returned from database: 1644 (45000): Insufficient balance to accept swap"
+    let clean_error = if error_msg.contains("error returned from database:") {
+        // Find the last colon, everything after it is the actual error message
+        if let Some(last_colon) = error_msg.rfind(": ") {
+            error_msg[last_colon + 2..].trim().to_string()
+        } else {
+            error_msg.to_string()
+        }
+    } else {
+        error_msg.to_string()
+    };
+    
+    // Determine error type and create user-friendly message
+    let user_message = if error_msg.contains("429") || error_msg.contains("rate limit") {
+        "⚠️ **Rate Limited**: Discord is rate limiting us. Please try again in a moment.".to_string()
+    } else if error_msg.contains("HTTP request") {
+        "⚠️ **Network Error**: Having trouble connecting to Discord. Please try again.".to_string()
+    } else if !clean_error.is_empty() {
+        format!("❌ {}", clean_error)
+    } else {
+        "❌ An error occurred while executing the command.".to_string()
+    };
+
+    serenity::builder::CreateEmbed::default()
+        .title("Command Error")
+        .description(user_message)
+        .color(0xff0000)
+}
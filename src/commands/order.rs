@@ -0,0 +1,80 @@
+use serenity::model::channel::Message;
+use serenity::prelude::Context;
+use crate::db::orderbook::OrderSide;
+use crate::services::order_service;
+use crate::utils::Invocation;
+use crate::utils::Amount;
+
+pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(), String> {
+    if args.is_empty() {
+        let help_embed = serenity::builder::CreateEmbed::default()
+            .title("📖 Order Command")
+            .description("Place limit orders on a currency pair's order book")
+            .field("Usage",
+                "`$order buy <quantity> <BASE/QUOTE> <price>`\n\
+                 `$order sell <quantity> <BASE/QUOTE> <price>`\n\
+                 `$order cancel <order_id>`\n\
+                 `$order list`",
+                false)
+            .field("Examples",
+                "`$order buy 10 ABC/XYZ 1.5` (buy 10 ABC, paying at most 1.5 XYZ each)\n\
+                 `$order sell 4 ABC/XYZ 2` (sell 4 ABC for at least 2 XYZ each)\n\
+                 `$order cancel 42` (cancel order 42 and get its escrow back)",
+                false)
+            .field("Notes",
+                "• Guild only (no DMs)\n\
+                 • Price is in QUOTE per 1 BASE; each pair is quoted in alphabetical order\n\
+                 • The order's cost is held in escrow until it fills or is cancelled\n\
+                 • Orders match best price first, then oldest first, at the resting order's price\n\
+                 • Use `$book BASE/QUOTE` to see the book",
+                false)
+            .color(0x0b5394);
+
+        msg.channel_id
+            .send_message(ctx, serenity::builder::CreateMessage::default().embed(help_embed))
+            .await
+            .map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    match args[0].to_lowercase().as_str() {
+        "cancel" => {
+            let order_id = args.get(1)
+                .ok_or("Please specify an order ID: `$order cancel <id>`".to_string())?
+                .parse::<i64>()
+                .map_err(|_| "Invalid order ID".to_string())?;
+
+            let embed = order_service::cancel_order(ctx, &Invocation::from(msg), order_id).await?;
+            msg.channel_id
+                .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        "list" => {
+            let page = order_service::list_orders(ctx, &Invocation::from(msg)).await?;
+            page.send(ctx, msg.channel_id, msg.author.id).await?;
+        }
+        side => {
+            let side = OrderSide::parse(side)
+                .ok_or("Unknown subcommand. Use `buy`, `sell`, `cancel` or `list`".to_string())?;
+
+            if args.len() < 4 {
+                return Err(format!("Usage: `$order {} <quantity> <BASE/QUOTE> <price>`", side.as_str()));
+            }
+
+            let quantity = args[1].parse::<Amount>()
+                .map_err(|e| format!("❌ Invalid quantity: {}", e))?;
+            let price = args[3].parse::<Amount>()
+                .map_err(|e| format!("❌ Invalid price: {}", e))?;
+
+            let result = order_service::place_order(ctx, &Invocation::from(msg), side, quantity, args[2], price).await?;
+            let embed = order_service::create_order_embed(&result);
+            msg.channel_id
+                .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use serenity::model::channel::Message;
+use serenity::prelude::Context;
+use crate::db::payroll::PayrollSource;
+use crate::db::recurring::RecurringPeriod;
+use crate::services::payroll_service;
+use crate::utils::Invocation;
+use crate::utils::Amount;
+
+pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(), String> {
+    if args.is_empty() {
+        let help_embed = serenity::builder::CreateEmbed::default()
+            .title("💼 Payroll Command")
+            .description("Pay every member of a role a fixed amount of the guild's currency")
+            .field("Usage",
+                "`$payroll <@role> <amount> <currency> [treasury|mint] [daily|weekly|monthly]`\n\
+                 `$payroll list`\n\
+                 `$payroll cancel <id>`",
+                false)
+            .field("Examples",
+                "`$payroll @Citizen 100 ABC` (pay every Citizen 100 ABC from the treasury now)\n\
+                 `$payroll @Soldier 50 ABC mint weekly` (mint 50 ABC for every Soldier each week)\n\
+                 `$payroll cancel 3` (stop scheduled payroll 3)",
+                false)
+            .field("Notes",
+                "• Guild only (no DMs), Admin only\n\
+                 • Pays the guild's own currency, from the tax account (treasury) by default\n\
+                 • Every member gets their own transaction; the report lists any that failed\n\
+                 • Scheduled payrolls first run one period from now and DM you each report",
+                false)
+            .color(0x2e8b57);
+
+        msg.channel_id
+            .send_message(ctx, serenity::builder::CreateMessage::default().embed(help_embed))
+            .await
+            .map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    match args[0].to_lowercase().as_str() {
+        "list" => {
+            let page = payroll_service::list_payrolls(ctx, &Invocation::from(msg)).await?;
+            page.send(ctx, msg.channel_id, msg.author.id).await?;
+        }
+        "cancel" => {
+            let id = args.get(1)
+                .ok_or("Please specify a payroll ID: `$payroll cancel <id>`".to_string())?
+                .trim_start_matches('#')
+                .parse::<i64>()
+                .map_err(|_| "Invalid payroll ID".to_string())?;
+
+            let embed = payroll_service::cancel_payroll(ctx, &Invocation::from(msg), id).await?;
+            msg.channel_id
+                .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        _ => {
+            if args.len() < 3 {
+                return Err("Usage: `$payroll <@role> <amount> <currency> [treasury|mint] [daily|weekly|monthly]`".to_string());
+            }
+
+            let amount = args[1].parse::<Amount>()
+                .map_err(|e| format!("❌ Invalid amount: {}", e))?;
+
+            // Source and period may come in either order
+            let mut source = PayrollSource::Treasury;
+            let mut period = None;
+            for option in &args[3..] {
+                if let Some(parsed) = PayrollSource::parse(option) {
+                    source = parsed;
+                } else if let Some(parsed) = RecurringPeriod::parse(option) {
+                    period = Some(parsed);
+                } else {
+                    return Err(format!(
+                        "❌ Unknown option '{}'. Use `treasury` or `mint`, and `daily`, `weekly` or `monthly`",
+                        option
+                    ));
+                }
+            }
+
+            let invocation = Invocation::from(msg);
+            let embed = match period {
+                Some(period) => {
+                    let scheduled = payroll_service::schedule_payroll(
+                        ctx, &invocation, args[0], amount, args[2], source, period
+                    ).await?;
+                    payroll_service::create_payroll_schedule_embed(&scheduled)
+                }
+                None => {
+                    let report = payroll_service::run_payroll(ctx, &invocation, args[0], amount, args[2], source).await?;
+                    payroll_service::create_payroll_report_embed(&report)
+                }
+            };
+            msg.channel_id
+                .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(())
+}
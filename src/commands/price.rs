@@ -1,4 +1,3 @@
-use serenity::all::CreateEmbedFooter;
 use serenity::model::channel::Message;
 use serenity::prelude::Context;
 use crate::services::{price_service, chart_service};
@@ -11,11 +10,13 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
         let help_embed = serenity::builder::CreateEmbed::default()
             .title("💹 Price Command")
             .description("Display the VWAP and last traded price for a currency pair, or generate a price chart")
-            .field("Usage", "`$price <base>/<quote> [timeframe]`\n`$price chart <base>/<quote>`\n`$price list [filters] [page]`", false)
+            .field("Usage", "`$price <base>/<quote> [timeframe]`\n`$price chart <base>/<quote> [timeframe] [candles <interval>] [maN]`\n`$price list [filters] [page]`", false)
             .field("Examples",
                 "`$price ABC/XYZ` (default 24h VWAP)\n\
                  `$price BTC/USD 1h` (1 hour VWAP)\n\
                  `$price chart BTC/USD` (generate price chart)\n\
+                 `$price chart BTC/USD 7d candles 1h` (hourly candles with volume, MA 20 and VWAP)\n\
+                 `$price chart BTC/USD 1d candles 5m ma10 ma50` (custom moving averages)\n\
                  `$price list` (all prices)\n\
                  `$price list BTC/` (all BTC pairs)",
                 false)
@@ -58,7 +59,7 @@ async fn execute_price(ctx: &Context, msg: &Message, args: &[&str]) -> Result<()
     let pool = {
         let data = ctx.data.read().await;
         data.get::<crate::DatabasePool>()
-            .ok_or("Database not initialized".to_string())?
+            .ok_or(crate::db::MYSQL_ONLY.to_string())?
             .clone()
     };
 
@@ -68,33 +69,7 @@ async fn execute_price(ctx: &Context, msg: &Message, args: &[&str]) -> Result<()
     // Call service to get price data
     let price_data = price_service::get_price(&pool, &base_ticker, &quote_ticker, timeframe_arg).await?;
 
-    // Format footer text
-    let footer_text = format!("1 {} = {:.2} {} (Timeframe: {})", 
-        price_data.base_ticker,
-        price_data.last_price, 
-        price_data.quote_ticker,
-        price_data.timeframe
-    );
-
-    // Build embed
-    let mut embed = serenity::builder::CreateEmbed::default()
-        .title("💹 Trading Price")
-        .field("Pair", format!("{}/{}", price_data.base_ticker, price_data.quote_ticker), false)
-        .field("Timeframe", format!("**{}**", price_data.timeframe), false);
-
-    // Add VWAP field if available
-    let vwap_label = format!("VWAP ({})", price_data.timeframe);
-    if let Some(vwap) = price_data.vwap {
-        embed = embed.field(&vwap_label, format!("**{:.2} {}**", vwap, price_data.quote_ticker), false);
-    } else {
-        embed = embed.field(&vwap_label, format!("No trades in {}", price_data.timeframe), false);
-    }
-
-    // Add Last Price field
-    embed = embed
-        .field("Last Price", format!("**{:.2} {}**", price_data.last_price, price_data.quote_ticker), false)
-        .footer(CreateEmbedFooter::new(footer_text))
-        .color(0x00ff00);
+    let embed = price_service::create_price_embed(&price_data);
 
     msg.channel_id
         .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
@@ -109,14 +84,15 @@ async fn execute_chart(ctx: &Context, msg: &Message, args: &[&str]) -> Result<()
     tracing::info!("🎨 Chart command received from user {} with args: {:?}", msg.author.id, args);
     
     if args.is_empty() {
-        return Err("❌ Usage: `$price chart <base>/<quote> [timeframe]`".to_string());
+        return Err("❌ Usage: `$price chart <base>/<quote> [timeframe] [candles <interval>] [maN]`".to_string());
     }
 
     // Parse and validate pair
     let (base_ticker, quote_ticker) = price_service::parse_price_pair(args[0])?;
 
-    // Parse timeframe (default to "all")
-    let timeframe = args.get(1).copied().unwrap_or("all");
+    // Parse timeframe (default to "all") and candle options
+    let request = chart_service::parse_chart_args(&args[1..])?;
+    let timeframe = request.timeframe.as_str();
     
     tracing::info!("Parsed pair: {} / {} with timeframe: {}", base_ticker, quote_ticker, timeframe);
 
@@ -124,7 +100,7 @@ async fn execute_chart(ctx: &Context, msg: &Message, args: &[&str]) -> Result<()
     let pool = {
         let data = ctx.data.read().await;
         data.get::<crate::DatabasePool>()
-            .ok_or("Database not initialized".to_string())?
+            .ok_or(crate::db::MYSQL_ONLY.to_string())?
             .clone()
     };
     tracing::debug!("Got database pool");
@@ -186,7 +162,11 @@ async fn execute_chart(ctx: &Context, msg: &Message, args: &[&str]) -> Result<()
 
     // Generate the chart
     tracing::info!("Generating chart image for {}/{} with timeframe {}", base_ticker, quote_ticker, timeframe);
-    let chart_data = match chart_service::generate_chart_with_timeframe(&pool, &base_ticker, &quote_ticker, timeframe, 1024, 768).await {
+    let chart_result = match &request.candles {
+        Some(options) => chart_service::generate_candle_chart(&pool, &base_ticker, &quote_ticker, timeframe, options, 1024, 768).await,
+        None => chart_service::generate_chart_with_timeframe(&pool, &base_ticker, &quote_ticker, timeframe, 1024, 768).await,
+    };
+    let chart_data = match chart_result {
         Ok(data) => {
             if data.is_empty() {
                 return Err("❌ Chart generation failed: produced empty image data".to_string());
@@ -266,7 +246,7 @@ async fn execute_list(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
     let pool = {
         let data = ctx.data.read().await;
         data.get::<crate::DatabasePool>()
-            .ok_or("Database not initialized".to_string())?
+            .ok_or(crate::db::MYSQL_ONLY.to_string())?
             .clone()
     };
 
@@ -285,24 +265,11 @@ async fn execute_list(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
         return Err("❌ No price data found".to_string());
     }
 
-    // Format page and get description
+    // Build every page; the buttons flip between them
     let items_per_page = 10;
-    let (description, page_num, total_pages) = price_service::format_price_list_page(&prices, page_num, items_per_page)?;
+    let page = price_service::create_price_list_pages(&prices, page_num, items_per_page)?;
 
-    // Create embed
-    let embed = serenity::builder::CreateEmbed::default()
-        .title("💹 Price List")
-        .description(description)
-        .footer(CreateEmbedFooter::new(format!(
-            "Page {}/{}",
-            page_num, total_pages
-        )))
-        .color(0x00ff00);
-
-    msg.channel_id
-        .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
-        .await
-        .map_err(|e| e.to_string())?;
+    page.send(ctx, msg.channel_id, msg.author.id).await?;
 
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,92 @@
+use serenity::model::channel::Message;
+use serenity::prelude::Context;
+use crate::db::recurring::RecurringPeriod;
+use crate::services::recurring_service;
+use crate::utils::Invocation;
+use crate::utils::Amount;
+
+pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(), String> {
+    if args.is_empty() {
+        let help_embed = serenity::builder::CreateEmbed::default()
+            .title("🔁 Recurring Command")
+            .description("Send a fixed amount to someone every day, week or month")
+            .field("Usage",
+                "`$recurring create <@user or id> <amount> <currency> <daily|weekly|monthly>`\n\
+                 `$recurring list`\n\
+                 `$recurring cancel <id>`",
+                false)
+            .field("Examples",
+                "`$recurring create @Alice 50 ABC weekly` (pay Alice 50 ABC every week)\n\
+                 `$recurring cancel 7` (stop recurring payment 7)",
+                false)
+            .field("Notes",
+                "• Guild only (no DMs)\n\
+                 • The first payment is one period from now\n\
+                 • Each payment is a normal `$send`, so the currency's tax applies\n\
+                 • You get a DM when a payment fails, e.g. for lack of funds\n\
+                 • A payment that fails 3 times in a row is suspended",
+                false)
+            .color(0x5865f2);
+
+        msg.channel_id
+            .send_message(ctx, serenity::builder::CreateMessage::default().embed(help_embed))
+            .await
+            .map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    match args[0].to_lowercase().as_str() {
+        "create" | "new" => {
+            if args.len() < 5 {
+                return Err("Usage: `$recurring create <@user> <amount> <currency> <daily|weekly|monthly>`".to_string());
+            }
+
+            let recipient_id = parse_user_id(args[1])?;
+            let amount = args[2].parse::<Amount>()
+                .map_err(|e| format!("❌ Invalid amount: {}", e))?;
+            let period = RecurringPeriod::parse(args[4])
+                .ok_or("❌ Period must be `daily`, `weekly` or `monthly`".to_string())?;
+
+            let result = recurring_service::create_recurring(
+                ctx, &Invocation::from(msg), recipient_id, amount, args[3], period
+            ).await?;
+            let embed = recurring_service::create_recurring_embed(&result);
+            msg.channel_id
+                .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        "list" => {
+            let page = recurring_service::list_recurring(ctx, &Invocation::from(msg)).await?;
+            page.send(ctx, msg.channel_id, msg.author.id).await?;
+        }
+        "cancel" => {
+            let id = args.get(1)
+                .ok_or("Please specify a payment ID: `$recurring cancel <id>`".to_string())?
+                .trim_start_matches('#')
+                .parse::<i64>()
+                .map_err(|_| "Invalid payment ID".to_string())?;
+
+            let embed = recurring_service::cancel_recurring(ctx, &Invocation::from(msg), id).await?;
+            msg.channel_id
+                .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        _ => return Err("Unknown subcommand. Use `create`, `list` or `cancel`".to_string()),
+    }
+
+    Ok(())
+}
+
+fn parse_user_id(input: &str) -> Result<i64, String> {
+    // Remove mention formatting: <@123456789> -> 123456789
+    let cleaned = input
+        .trim_start_matches('<')
+        .trim_start_matches('@')
+        .trim_start_matches('!')
+        .trim_end_matches('>');
+
+    cleaned.parse::<i64>()
+        .map_err(|_| "Invalid user ID or mention".to_string())
+}
@@ -0,0 +1,73 @@
+use serenity::builder::{CreateInteractionResponse, EditMessage};
+use serenity::model::channel::Message;
+use serenity::prelude::Context;
+use crate::services::route_service;
+use crate::utils::Invocation;
+use crate::utils::Amount;
+
+pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(), String> {
+    if args.len() < 3 {
+        let help_embed = serenity::builder::CreateEmbed::default()
+            .title("🧭 Route Command")
+            .description("Convert one currency into another through a chain of open swaps")
+            .field("Usage", "`$route <amount> <FROM> <TO>`", false)
+            .field("Examples",
+                "`$route 100 ABC XYZ` (best way to turn 100 ABC into XYZ)",
+                false)
+            .field("Notes",
+                "• Shows a preview first; nothing is traded until you press Confirm\n\
+                 • Routes use up to 4 open swaps in a row, best rate first\n\
+                 • Either every leg fills or none of them do",
+                false)
+            .color(0xffa500);
+
+        msg.channel_id
+            .send_message(ctx, serenity::builder::CreateMessage::default().embed(help_embed))
+            .await
+            .map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    let amount = args[0].parse::<Amount>()
+        .map_err(|e| format!("❌ {}", e))?;
+    let from_ticker = args[1].to_uppercase();
+    let to_ticker = args[2].to_uppercase();
+
+    let plan = route_service::quote_route(ctx, &Invocation::from(msg), amount, &from_ticker, &to_ticker).await?;
+
+    let mut preview = msg.channel_id
+        .send_message(
+            ctx,
+            serenity::builder::CreateMessage::default()
+                .embed(route_service::create_route_preview_embed(&plan))
+                .components(route_service::create_route_buttons()),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let Some(interaction) = route_service::await_confirmation(ctx, &preview, msg.author.id).await else {
+        let embed = serenity::builder::CreateEmbed::default()
+            .title("🧭 Route Cancelled")
+            .description("Nothing was traded.")
+            .color(0x808080);
+        preview
+            .edit(ctx, EditMessage::new().embed(embed).components(Vec::new()))
+            .await
+            .map_err(|e| e.to_string())?;
+        return Ok(());
+    };
+
+    // Acknowledge right away; the route is shown by editing the preview
+    let _ = interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await;
+
+    let embed = match route_service::execute_route(ctx, &plan).await {
+        Ok(paid) => route_service::create_route_result_embed(&plan, paid),
+        Err(e) => crate::commands::create_error_embed(&e),
+    };
+    preview
+        .edit(ctx, EditMessage::new().embed(embed).components(Vec::new()))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
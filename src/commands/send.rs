@@ -1,6 +1,8 @@
 use serenity::model::channel::Message;
 use serenity::prelude::Context;
 use crate::services::send_service;
+use crate::utils::Invocation;
+use crate::utils::Amount;
 
 pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(), String> {
     if args.len() < 2 {
@@ -8,19 +10,22 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
             .title("💸 Send Command")
             .description("Transfer currency to one or more users")
             .field("Usage", 
-                "`$send <@user or id> <amount> <currency>`\n\
-                 `$send (@user1 @user2 ... @userN) <amount> <currency>`",
+                "`$send <@user or id> <amount> <currency> [\"memo\"]`\n\
+                 `$send @user1 @user2 ... @userN <amount> <currency> [\"memo\"]`\n\
+                 `$send @user1:<amount> @user2:<amount> ... <currency> [\"memo\"]`",
                 false)
             .field("Examples",
                 "`$send @Alice 100 BTC` (send to one user)\n\
-                 `$send (@Alice @Bob @Charlie) 50 USD` (send to multiple users)\n\
-                 `$send (@Alice 123456789 @Bob) 75 ETH` (mixed IDs and mentions)",
+                 `$send @Alice @Bob @Charlie 50 USD` (50 USD to each)\n\
+                 `$send @Alice:10 @Bob:25 ETH` (different amounts)\n\
+                 `$send @Bob 10 ABC \"rent for March\"` (with a memo)",
                 false)
             .field("Notes",
-                "• Works in guilds and DMs\n\
-                 • For multiple users, wrap them in parentheses: `(@user1 @user2 ...)`\n\
-                 • Each user receives the same amount\n\
-                 • Amount must be positive",
+                "• Guild only (no DMs)\n\
+                 • Every recipient is paid or none are\n\
+                 • Tax is charged on the total\n\
+                 • At most 25 recipients; amounts must be positive\n\
+                 • The memo is shown in `$transaction` and can be searched with `$transaction list <text>`",
                 false)
             .color(0x00ff00);
 
@@ -31,141 +36,52 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
         return Ok(());
     }
 
-    let mut recipients = Vec::new();
-    let mut amount_idx = 0;
-    
-    // Check if first argument starts with '(' for multiple recipients
-    if args[0].starts_with('(') {
-        // Multiple recipients mode: parse until we find ')'
-        let mut found_end = false;
-        for (idx, arg) in args.iter().enumerate() {
-            let clean_arg = if idx == 0 {
-                arg.trim_start_matches('(').trim()
-            } else {
-                arg.trim()
-            };
-            
-            let clean_arg = if clean_arg.ends_with(')') {
-                found_end = true;
-                clean_arg.trim_end_matches(')').trim()
-            } else {
-                clean_arg
-            };
-            
-            if !clean_arg.is_empty() {
-                match parse_user_id(clean_arg) {
-                    Ok(user_id) => recipients.push(user_id),
-                    Err(_) => return Err(format!("❌ Invalid user ID or mention: {}", clean_arg)),
-                }
-            }
-            
-            if found_end {
-                amount_idx = idx + 1;
-                break;
-            }
-        }
-        
-        if !found_end {
-            return Err("❌ Missing closing parenthesis for recipients list".to_string());
-        }
-    } else {
-        // Single recipient mode
-        match parse_user_id(args[0]) {
-            Ok(user_id) => recipients.push(user_id),
-            Err(e) => return Err(format!("❌ Invalid recipient: {}", e)),
+    // Everything from the first quoted word on is the memo: "rent for March"
+    let (args, memo) = match args.iter().position(|arg| arg.starts_with(['"', '“'])) {
+        Some(start) => {
+            let memo = args[start..].join(" ");
+            (&args[..start], Some(memo.trim_matches(['"', '“', '”']).to_string()))
         }
-        amount_idx = 1;
-    }
-    
-    // Validate we have at least one recipient
-    if recipients.is_empty() {
-        return Err("❌ Please specify at least one recipient".to_string());
-    }
-    
-    // Parse amount and currency
-    if amount_idx + 1 >= args.len() {
-        return Err("❌ Please specify amount and currency".to_string());
-    }
-    
-    let amount: f64 = args[amount_idx].parse()
-        .map_err(|_| "❌ Invalid amount".to_string())?;
-    
-    if amount <= 0.0 {
-        return Err("❌ Amount must be positive".to_string());
-    }
+        None => (args, None),
+    };
 
-    let currency_ticker = args[amount_idx + 1].to_uppercase();
+    let (currency_arg, rest) = args.split_last().ok_or("❌ Please specify amount and currency")?;
+    let currency_ticker = currency_arg.to_uppercase();
 
-    // Process each recipient and collect results
-    let mut successful_recipients = Vec::new();
-    let mut failed_recipients = Vec::new();
-    let mut total_sent = 0.0;
-    let mut total_tax = 0.0;
-    
-    for recipient_id in recipients {
-        match send_service::execute_send(ctx, msg, recipient_id, amount, &currency_ticker).await {
-            Ok((_receiver_id, _transaction_uuid, tax_amount)) => {
-                successful_recipients.push(recipient_id);
-                total_sent += amount;
-                total_tax += tax_amount;
-            }
-            Err(e) => {
-                failed_recipients.push((recipient_id, e));
-            }
-        }
-    }
-    
-    // Send success embed if any transfers succeeded
-    if !successful_recipients.is_empty() {
-        let result = send_service::SendResult {
-            sender_id: msg.author.id.get() as i64,
-            receiver_ids: successful_recipients.clone(),
-            amount: format!("{:.2}", amount),
-            currency_ticker: currency_ticker.clone(),
-            total_amount: format!("{:.2}", total_sent),
-            tax_amount: format!("{:.8}", total_tax),
-        };
-        
-        let embed = send_service::create_send_embed(&result);
-        msg.channel_id
-            .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
-            .await
-            .map_err(|e| e.to_string())?;
-    }
-    
-    // If any transfers failed, report them in an embed
-    if !failed_recipients.is_empty() {
-        let title = if successful_recipients.is_empty() {
-            "❌ All Transfers Failed"
-        } else {
-            "⚠️ Some Transfers Failed"
-        };
-
-        let description = if successful_recipients.is_empty() {
-            "All transfers could not be completed".to_string()
-        } else {
-            format!("Completed {} of {} transfers:", successful_recipients.len(), successful_recipients.len() + failed_recipients.len())
-        };
-
-        let mut embed = serenity::builder::CreateEmbed::default()
-            .title(title)
-            .description(description)
-            .color(0xff3333); // Red color for errors
+    // `@user:amount` pairs give each recipient their own amount
+    let payments = if rest.iter().any(|arg| arg.contains(':')) {
+        rest.iter()
+            .map(|arg| {
+                let (user, amount) = arg.rsplit_once(':')
+                    .ok_or_else(|| format!("❌ Missing amount for {}: use `@user:amount` for every recipient", arg))?;
+                let user_id = parse_user_id(user).map_err(|_| format!("❌ Invalid user ID or mention: {}", user))?;
+                let amount = amount.parse::<Amount>().map_err(|e| format!("❌ {}", e))?;
+                Ok((user_id, amount))
+            })
+            .collect::<Result<Vec<_>, String>>()?
+    } else {
+        let (amount_arg, users) = rest.split_last().ok_or("❌ Please specify amount and currency")?;
+        let amount: Amount = amount_arg.parse()
+            .map_err(|e| format!("❌ {}", e))?;
 
-        // Add error details for each failed recipient
-        for (recipient_id, error) in &failed_recipients {
-            embed = embed.field(
-                format!("<@{}>", recipient_id),
-                error,
-                false
-            );
-        }
+        // Recipients may also be wrapped in parentheses: (@user1 @user2)
+        users.iter()
+            .map(|arg| arg.trim_start_matches('(').trim_end_matches(')'))
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| {
+                parse_user_id(arg)
+                    .map(|user_id| (user_id, amount))
+                    .map_err(|_| format!("❌ Invalid user ID or mention: {}", arg))
+            })
+            .collect::<Result<Vec<_>, String>>()?
+    };
 
-        msg.channel_id
-            .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
-            .await
-            .map_err(|e| e.to_string())?;
-    }
+    let result = send_service::execute_send(ctx, &Invocation::from(msg), &payments, &currency_ticker, memo.as_deref()).await?;
+    let embed = send_service::create_send_embed(&result);
+    msg.channel_id
+        .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
+        .await
+        .map_err(|e| e.to_string())?;
 
     Ok(())
 }
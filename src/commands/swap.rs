@@ -1,7 +1,10 @@
 use serenity::model::channel::Message;
 use serenity::prelude::Context;
 use crate::services::swap_service;
+use crate::db::filter::{SwapSort, SwapStatus};
+use crate::utils::Invocation;
 use crate::utils::extract_clean_error;
+use crate::utils::Amount;
 
 pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(), String> {
     if args.is_empty() {
@@ -9,19 +12,23 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
             .title("🔄 Swap Command")
             .description("Trade currencies with other users")
             .field("Usage",
-                "`$swap <amount> <currency> [<@user or id> <amount> <currency>]`\n\
-                 `$swap accept [swap_id]`\n\
+                "`$swap <amount> <currency> [<@user or id> <amount> <currency>] [expiry]`\n\
+                 `$swap accept [swap_id] [amount]`\n\
                  `$swap deny [swap_id]`\n\
                  `$swap status <swap_id>`\n\
-                 `$swap list [filters] [pN]`",
+                 `$swap list [filters] [pN]`\n\
+                 `$swap expiry <currency> [duration|off]`",
                 false)
             .field("Examples",
                 "**Create targeted swap:**\n\
                  `$swap 100 BTC @Alice 50 USD`\n\n\
                  **Create open swap:**\n\
                  `$swap 100 EUR`\n\n\
+                 **Swap that expires after 24 hours:**\n\
+                 `$swap 100 BTC @Alice 50 USD 24h`\n\n\
                  **Accept/Deny:**\n\
                  `$swap accept 123` (accept swap ID 123)\n\
+                 `$swap accept 123 25` (fill 25 of open swap 123's offer)\n\
                  `$swap deny 123` (deny swap ID 123)\n\n\
                  **Check status:**\n\
                  `$swap status 123` (view swap info)\n\n\
@@ -33,16 +40,24 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
                 false)
             .field("List Filters",
                 "**Sort:** `oldest`, `latest` (default), `highmaker`, `lowmaker`, `hightaker`, `lowtaker`\n\
-                 **Status:** `pending` (default), `accepted`, `cancelled`, `all`\n\
+                 **Status:** `pending` (default), `accepted`, `cancelled`, `expired`, `all`\n\
                  **Currency:** `base:ABC` or `quote:XYZ`\n\
-                 **Pagination:** `pN` (page number, default p1)",
+                 **Pagination:** `pN` (starting page, default p1), then use the ◀ ▶ buttons",
                 false)
             .field("Notes",
                 "• Guild only (no DMs)\n\
                  • Specify amounts and currencies\n\
                  • Amounts must be positive\n\
                  • Your Discord ID is used as the maker\n\
-                 • Use `$swap status` to check swap details",
+                 • Use `$swap status` to check swap details\n\
+                 • Open swaps can be filled in parts at the maker's price; the rest stays open\n\
+                 • Expiry (`30m`, `24h`, `7d`, `2w`) refunds the maker if nobody accepts in time; \
+                   without one the currency's default is used",
+                false)
+            .field("Expiry",
+                "`$swap expiry ABC` - Show ABC's default swap expiry\n\
+                 `$swap expiry ABC 24h` - Set ABC's default (admin)\n\
+                 `$swap expiry ABC off` - Disable ABC's default (admin)",
                 false)
             .color(0xffa500);
 
@@ -62,7 +77,7 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
                 return Err("Please specify a swap ID: `$swap status <id>`".to_string());
             };
             
-            match swap_service::get_swap_status(ctx, msg, swap_id).await {
+            match swap_service::get_swap_status(ctx, swap_id).await {
                 Ok(embed) => {
                     msg.channel_id
                         .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
@@ -90,7 +105,15 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
                 None
             };
             
-            match swap_service::accept_swap(ctx, msg, swap_id).await {
+            // Optional amount of the maker's currency for a partial fill
+            let fill_amount = if args.len() > 2 {
+                Some(args[2].parse::<Amount>()
+                    .map_err(|e| format!("❌ {}", e))?)
+            } else {
+                None
+            };
+            
+            match swap_service::accept_swap(ctx, &Invocation::from(msg), swap_id, fill_amount).await {
                 Ok((result, _original_msg_id)) => {
                     let embed = swap_service::create_accept_deny_embed(&result);
                     msg.channel_id
@@ -119,7 +142,7 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
                 None
             };
             
-            match swap_service::deny_swap(ctx, msg, swap_id).await {
+            match swap_service::deny_swap(ctx, &Invocation::from(msg), swap_id).await {
                 Ok((result, _original_msg_id)) => {
                     let embed = swap_service::create_accept_deny_embed(&result);
                     msg.channel_id
@@ -140,6 +163,21 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
                 }
             }
         }
+        "expiry" => {
+            if args.len() < 2 {
+                return Err("Usage: `$swap expiry <currency> [duration|off]`".to_string());
+            }
+            
+            let response = swap_service::set_default_expiry(ctx, &Invocation::from(msg), &args[1].to_uppercase(), args.get(2).copied()).await?;
+            let embed = serenity::builder::CreateEmbed::default()
+                .title("⏱️ Swap Expiry")
+                .description(response)
+                .color(0x00ff00);
+            msg.channel_id
+                .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
+                .await
+                .map_err(|e| e.to_string())?;
+        }
         "list" => {
             // Parse arguments: $swap list [filters...] [pN]
             // Filters: oldest/latest, accepted/pending/cancelled, highmaker/lowmaker/hightaker/lowtaker, base:ABC, quote:XYZ
@@ -162,29 +200,17 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
                     base_currency = Some(&arg[5..]);
                 } else if arg.starts_with("quote:") {
                     quote_currency = Some(&arg[6..]);
-                } else if *arg == "oldest" || *arg == "latest" || *arg == "highmaker" || *arg == "lowmaker" || *arg == "hightaker" || *arg == "lowtaker" {
+                } else if SwapSort::parse(arg).is_some() {
                     sort_by = arg;
-                } else if *arg == "pending" || *arg == "accepted" || *arg == "cancelled" {
+                } else if SwapStatus::parse(arg).is_some_and(|s| s != SwapStatus::All) {
                     status = arg;  // Override default if explicitly specified
                 }
             }
             
-            // Get swap list from service
-            match swap_service::get_swaps_list(ctx, page, sort_by, status, base_currency, quote_currency).await {
-                Ok(result) => {
-                    let embed = if result.swaps.is_empty() {
-                        serenity::builder::CreateEmbed::default()
-                            .title("📊 Swap List")
-                            .description("No swaps found matching the criteria")
-                            .color(0xffa500)
-                    } else {
-                        swap_service::create_swap_list_embed(&result, sort_by, status, base_currency, quote_currency)
-                    };
-                    
-                    msg.channel_id
-                        .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
-                        .await
-                        .map_err(|e| e.to_string())?;
+            // Get swap list from service; further pages load as the user presses the buttons
+            match swap_service::list_swaps(ctx, page, sort_by, status, base_currency, quote_currency).await {
+                Ok(page) => {
+                    page.send(ctx, msg.channel_id, msg.author.id).await?;
                 }
                 Err(e) => {
                     let clean_error = extract_clean_error(&e);
@@ -221,19 +247,29 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
                 return Ok(());
             }
 
+            // A trailing duration like `24h` sets the swap's expiry
+            let (args, expiry_minutes) = match args.split_last() {
+                Some((last, rest)) if is_expiry_arg(last) => (rest, Some(swap_service::parse_expiry(last)?)),
+                _ => (args, None),
+            };
+            
+            if args.len() < 2 {
+                return Err("Usage: `$swap <amount> <currency> [<@user or id> <amount> <currency>] [expiry]`".to_string());
+            }
+
             // Determine format: amount-currency or currency-amount
             // Try to parse args[0] as number first
-            let (maker_amount, maker_ticker) = if let Ok(amount) = args[0].parse::<f64>() {
+            let (maker_amount, maker_ticker) = if let Ok(amount) = args[0].parse::<Amount>() {
                 // Format: amount currency ...
                 (amount, args[1].to_uppercase())
             } else {
                 // Format: currency amount ...
-                let amount = args[1].parse::<f64>()
+                let amount = args[1].parse::<Amount>()
                     .map_err(|_| "Invalid amount - expected number".to_string())?;
                 (amount, args[0].to_uppercase())
             };
 
-            if maker_amount <= 0.0 {
+            if !maker_amount.is_positive() {
                 msg.reply(ctx, "Amount must be positive").await
                     .map_err(|e| e.to_string())?;
                 return Ok(());
@@ -253,13 +289,13 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
                     let taker_id = parse_result.unwrap();
                     
                     // Determine format for taker: amount-currency or currency-amount
-                    let (taker_amount, taker_ticker) = if let Ok(amount) = args[3].parse::<f64>() {
+                    let (taker_amount, taker_ticker) = if let Ok(amount) = args[3].parse::<Amount>() {
                         // Format: @user amount currency
                         (amount, if args.len() > 4 { args[4].to_uppercase() } else { return Err("Taker currency required".to_string()); })
                     } else {
                         // Format: @user currency amount
                         let amount = if args.len() > 4 {
-                            args[4].parse::<f64>()
+                            args[4].parse::<Amount>()
                                 .map_err(|_| "Invalid taker amount".to_string())?
                         } else {
                             return Err("Taker amount required".to_string());
@@ -267,7 +303,7 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
                         (amount, args[3].to_uppercase())
                     };
 
-                    if taker_amount <= 0.0 {
+                    if !taker_amount.is_positive() {
                         msg.reply(ctx, "Amount must be positive").await
                             .map_err(|e| e.to_string())?;
                         return Ok(());
@@ -277,17 +313,17 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
                 } else {
                     // It's not a user ID, treat as OPEN swap with 2 currencies
                     // Format: $swap 10 ABC 10 XYZ OR $swap ABC 10 XYZ 10
-                    let (taker_amount, taker_ticker) = if let Ok(amount) = args[2].parse::<f64>() {
+                    let (taker_amount, taker_ticker) = if let Ok(amount) = args[2].parse::<Amount>() {
                         // Format: amount currency
                         (amount, args[3].to_uppercase())
                     } else {
                         // Format: currency amount
-                        let amount = args[3].parse::<f64>()
+                        let amount = args[3].parse::<Amount>()
                             .map_err(|_| "Invalid taker amount".to_string())?;
                         (amount, args[2].to_uppercase())
                     };
 
-                    if taker_amount <= 0.0 {
+                    if !taker_amount.is_positive() {
                         msg.reply(ctx, "Amount must be positive").await
                             .map_err(|e| e.to_string())?;
                         return Ok(());
@@ -302,20 +338,26 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
 
             match swap_service::execute_swap(
                 ctx,
-                msg,
+                &Invocation::from(msg),
                 maker_id,
                 maker_amount,
                 &maker_ticker,
                 taker_id,
                 taker_amount,
                 taker_ticker.as_deref(),
+                expiry_minutes,
             ).await {
                 Ok(result) => {
-                    let embed = swap_service::create_swap_embed(&result);
-                    msg.channel_id
-                        .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
+                    let message = serenity::builder::CreateMessage::default()
+                        .embed(swap_service::create_swap_embed(&result))
+                        .components(swap_service::create_swap_buttons(result.swap_id));
+                    let sent = msg.channel_id
+                        .send_message(ctx, message)
                         .await
                         .map_err(|e| e.to_string())?;
+                    
+                    // Store the announcement so it can be edited when the swap is resolved
+                    swap_service::store_swap_message(ctx, result.swap_id, sent.channel_id.get(), sent.id.get()).await;
                 }
                 Err(e) => {
                     msg.reply(ctx, format!("❌ Swap failed: {}", e)).await
@@ -328,6 +370,12 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
     Ok(())
 }
 
+/// True if the argument looks like a duration (`24h`, `7d`) rather than an amount or ticker
+fn is_expiry_arg(input: &str) -> bool {
+    input.starts_with(|c: char| c.is_ascii_digit())
+        && input.ends_with(|c: char| c.is_ascii_alphabetic())
+}
+
 fn parse_user_id(input: &str) -> Result<i64, String> {
     // Remove mention formatting: <@123456789> -> 123456789
     let cleaned = input
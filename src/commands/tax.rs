@@ -1,6 +1,7 @@
 use serenity::model::channel::Message;
 use serenity::prelude::Context;
 use crate::services::tax_service;
+use crate::storage::Storage;
 use tracing::debug;
 
 pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(), String> {
@@ -39,17 +40,12 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
 
     let subcommand = args[0].to_lowercase();
 
-    let pool = {
-        let data = ctx.data.read().await;
-        data.get::<crate::DatabasePool>()
-            .ok_or("Database not initialized".to_string())?
-            .clone()
-    };
+    let storage = crate::storage::from_ctx(ctx).await?;
 
     match subcommand.as_str() {
-        "set" => execute_set(ctx, msg, &pool, &args[1..]).await,
-        "collect" => execute_collect(ctx, msg, &pool, &args[1..]).await,
-        "info" => execute_info(ctx, msg, &pool, &args[1..]).await,
+        "set" => execute_set(ctx, msg, storage.as_ref(), &args[1..]).await,
+        "collect" => execute_collect(ctx, msg, storage.as_ref(), &args[1..]).await,
+        "info" => execute_info(ctx, msg, storage.as_ref(), &args[1..]).await,
         _ => Err(format!("❌ Unknown subcommand: '{}'. Use: set, collect, or info", subcommand)),
     }
 }
@@ -58,7 +54,7 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
 async fn execute_set(
     ctx: &Context,
     msg: &Message,
-    pool: &sqlx::mysql::MySqlPool,
+    storage: &dyn Storage,
     args: &[&str],
 ) -> Result<(), String> {
     if args.len() < 2 {
@@ -73,7 +69,7 @@ async fn execute_set(
         .map_err(|_| "❌ Percentage must be a valid integer (0-100)".to_string())?;
 
     // Get currency by ticker with guild_id
-    let currency = crate::db::currency::get_currency_by_ticker_with_guild(pool, &ticker)
+    let currency = storage.get_currency_by_ticker_with_guild(&ticker)
         .await
         .map_err(|e| format!("Database error: {}", e))?
         .ok_or(format!("❌ Currency '{}' not found", ticker))?;
@@ -83,7 +79,7 @@ async fn execute_set(
     debug!("Tax command for currency: {} (ID: {})", ticker, currency_id);
 
     // Set tax
-    let response = tax_service::set_tax(pool, currency_id, percentage, &ticker).await?;
+    let response = tax_service::set_tax(storage, currency_id, percentage, &ticker).await?;
 
     let embed = serenity::builder::CreateEmbed::default()
         .title("💰 Tax Set")
@@ -102,7 +98,7 @@ async fn execute_set(
 async fn execute_collect(
     ctx: &Context,
     msg: &Message,
-    pool: &sqlx::mysql::MySqlPool,
+    storage: &dyn Storage,
     args: &[&str],
 ) -> Result<(), String> {
     if args.is_empty() {
@@ -113,7 +109,7 @@ async fn execute_collect(
     let amount = args.get(1).copied();
 
     // Get currency by ticker with guild_id
-    let currency = crate::db::currency::get_currency_by_ticker_with_guild(pool, &ticker)
+    let currency = storage.get_currency_by_ticker_with_guild(&ticker)
         .await
         .map_err(|e| format!("Database error: {}", e))?
         .ok_or(format!("❌ Currency '{}' not found", ticker))?;
@@ -123,7 +119,7 @@ async fn execute_collect(
     let collector_id = msg.author.id.get() as i64;
 
     // Collect tax
-    let response = tax_service::collect_tax(pool, collector_id, currency_id, amount.map(|s| s.to_string())).await?;
+    let response = tax_service::collect_tax(storage, collector_id, currency_id, amount.map(|s| s.to_string())).await?;
 
     let embed = serenity::builder::CreateEmbed::default()
         .title("💰 Tax Collected")
@@ -142,7 +138,7 @@ async fn execute_collect(
 async fn execute_info(
     ctx: &Context,
     msg: &Message,
-    pool: &sqlx::mysql::MySqlPool,
+    storage: &dyn Storage,
     args: &[&str],
 ) -> Result<(), String> {
     if args.is_empty() {
@@ -152,7 +148,7 @@ async fn execute_info(
     let ticker = args[0].to_uppercase();
 
     // Get currency by ticker
-    let currency = crate::db::currency::get_currency_by_ticker(pool, &ticker)
+    let currency = storage.get_currency_by_ticker(&ticker)
         .await
         .map_err(|e| format!("Database error: {}", e))?
         .ok_or(format!("❌ Currency '{}' not found", ticker))?;
@@ -160,7 +156,7 @@ async fn execute_info(
     let currency_id = currency.0;
 
     // Get tax info
-    let response = tax_service::get_tax_info(pool, currency_id).await?;
+    let response = tax_service::get_tax_info(storage, currency_id).await?;
 
     let embed = serenity::builder::CreateEmbed::default()
         .title(&ticker)
@@ -1,6 +1,7 @@
 use serenity::model::channel::Message;
 use serenity::prelude::Context;
 use crate::services::transaction_service;
+use crate::utils::page::Page;
 
 pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(), String> {
     if args.is_empty() {
@@ -9,16 +10,19 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
             .description("View transaction details or history")
             .field("Usage",
                 "`$transaction <uuid>` (view specific transaction)\n\
-                 `$transaction list` (view all transactions)",
+                 `$transaction list [page]` (view all transactions)\n\
+                 `$transaction list [page] <text>` (only transactions whose memo contains the text)",
                 false)
             .field("Examples",
                 "`$transaction a1b2c3d4-e5f6-7890-abcd-ef1234567890`\n\
-                 `$transaction list`",
+                 `$transaction list`\n\
+                 `$transaction list rent`",
                 false)
             .field("Notes",
                 "• Works in guilds and DMs\n\
                  • Transaction UUID is shown in transfer receipts\n\
-                 • List shows your recent transactions",
+                 • List shows your recent transactions\n\
+                 • Memo search ignores case",
                 false)
             .color(0x00ff00);
 
@@ -29,77 +33,54 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
         return Ok(());
     }
 
-    // Get pool from context
-    let pool = {
-        let data = ctx.data.read().await;
-        data.get::<crate::DatabasePool>()
-            .ok_or("Database not initialized".to_string())?
-            .clone()
-    };
+    let storage = crate::storage::from_ctx(ctx).await?;
 
     let user_id = msg.author.id.get() as i64;
 
     match args[0].to_lowercase().as_str() {
         "list" => {
-            // Check if page number is specified (e.g., "p2" or "2")
+            // An optional page number ("p2" or "2"), then optional memo search text
+            let mut search_args = &args[1..];
             let mut page_num = 1;
-            if args.len() > 1 {
-                let page_arg = args[1].to_lowercase();
-                let page_str = if page_arg.starts_with('p') {
-                    &page_arg[1..]
-                } else {
-                    &page_arg
-                };
-                
-                page_num = page_str.parse::<usize>()
-                    .map_err(|_| "Invalid page number. Use: `$transaction list` or `$transaction list p2`".to_string())?;
+            if let Some(page_arg) = search_args.first() {
+                let page_arg = page_arg.to_lowercase();
+                let page_str = page_arg.strip_prefix('p').unwrap_or(&page_arg);
+                if let Ok(parsed) = page_str.parse::<usize>() {
+                    if parsed == 0 {
+                        return Err("Invalid page number. Use: `$transaction list` or `$transaction list p2`".to_string());
+                    }
+                    page_num = parsed;
+                    search_args = &search_args[1..];
+                }
             }
 
-            // Fetch the requested page
-            let (mut embeds, total_pages) = transaction_service::create_transaction_pages(&pool, user_id, page_num)
-                .await?;
-
-            if embeds.is_empty() {
-                let embed = serenity::builder::CreateEmbed::default()
-                    .title("📋 Transaction History")
-                    .description("No transactions found")
-                    .color(0xffa500);
+            let memo_search = search_args.join(" ");
+            let memo_search = memo_search.trim_matches(['"', '“', '”']).trim();
+            let memo_search = (!memo_search.is_empty()).then(|| memo_search.to_string());
 
-                msg.channel_id
-                    .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
-                    .await
-                    .map_err(|e| e.to_string())?;
-                return Ok(());
-            }
-
-            // Add page navigation info to footer if multiple pages exist
-            if total_pages > 1 {
-                embeds[0] = embeds[0].clone().footer(
-                    serenity::builder::CreateEmbedFooter::new(
-                        format!("Page {}/{}", page_num, total_pages)
-                    )
-                );
-            }
+            // Fetch the requested page; the rest load as the user presses the buttons
+            let history = transaction_service::TransactionHistory { storage, user_id, memo_search };
+            let page = Page::load(history, page_num).await?;
 
-            // Send the embed for the requested page
-            msg.channel_id
-                .send_message(ctx, serenity::builder::CreateMessage::default().embed(embeds[0].clone()))
-                .await
-                .map_err(|e| e.to_string())?;
+            page.send(ctx, msg.channel_id, msg.author.id).await?;
         }
         _ => {
             // Treat first arg as UUID
             let uuid = args[0];
 
-            let result = transaction_service::get_transaction_detail(&pool, uuid)
+            let result = transaction_service::get_transaction_detail(storage.as_ref(), uuid)
                 .await?;
 
-            let embed = serenity::builder::CreateEmbed::default()
+            let mut embed = serenity::builder::CreateEmbed::default()
                 .title("📜 Transaction Receipt")
                 .field("From", format!("<@{}>", result.sender_discord_id), true)
                 .field("To", format!("<@{}>", result.receiver_discord_id), true)
                 .field("Amount", format!("{:.2}", result.amount), true)
-                .field("Date", result.date, false)
+                .field("Date", result.date, false);
+            if let Some(memo) = result.memo {
+                embed = embed.field("Memo", memo, false);
+            }
+            let embed = embed
                 .footer(serenity::builder::CreateEmbedFooter::new(format!("ID: {}", uuid)))
                 .color(0x00ff00);
 
@@ -9,30 +9,47 @@
 
 use serenity::model::channel::Message;
 use serenity::prelude::Context;
-use crate::services::wire_service;
+use crate::api::bridge::BridgeProvider;
+use crate::services::wire_service::{self, WireDirection, WireResolution, WireSetting};
+use crate::utils::Invocation;
+use crate::utils::Amount;
 
 pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(), String> {
     if args.is_empty() || args[0] == "help" {
         let help_embed = serenity::builder::CreateEmbed::default()
             .title("💳 Wire Command")
-            .description("Bridge between SMITE economy and UnbelievaBoat balance")
+            .description("Bridge between SMITE and the currency's wire provider, UnbelievaBoat unless an admin chose another")
             .field("Usage",
-                "`$wire in <amount> <currency>` - Transfer from UnbelievaBoat to SMITE\n\
-                 `$wire out <amount> <currency>` - Transfer from SMITE to UnbelievaBoat\n\
+                "`$wire in <amount> <currency>` - Transfer from the wire provider to SMITE\n\
+                 `$wire out <amount> <currency>` - Transfer from SMITE to the wire provider\n\
+                 `$wire history` - Your recent wire transfers\n\
+                 `$wire reconcile` - Wires that stopped half way (Admin)\n\
+                 `$wire reconcile <id> complete|refund` - Settle one by hand (Admin)\n\
+                 `$wire config` - Show the rate, limits, fee and rounding (Admin)\n\
+                 `$wire config <setting> <value>` - Change one of them (Admin)\n\
+                 `$wire provider` - Show which economy the currency wires to (Admin)\n\
+                 `$wire provider unbelievaboat|http <base_url> [key=value ...]` - Change it (Admin)\n\
                  `$wire set token <guild_id> <token>` - Set API token (DM only)",
                 false)
             .field("Examples",
-                "`$wire in 100 ABC` - Remove 100 ABC from UnbelievaBoat, add to SMITE account\n\
-                 `$wire out 100 ABC` - Remove 100 ABC from SMITE account, add to UnbelievaBoat\n\
+                "`$wire in 100 ABC` - Remove 100 from the wire provider, add ABC to SMITE account\n\
+                 `$wire out 100 ABC` - Remove 100 ABC from SMITE account, add to the wire provider\n\
+                 `$wire config rate 2.5` - 1 unit on the wire provider is worth 2.5 ABC\n\
+                 `$wire config fee 1%` / `$wire config fee 5` / `$wire config min none` - Fee and limit settings\n\
+                 `$wire provider http https://economy.example/api name=Coins bank=data.bank method=POST` - Wire to another bot\n\
                  `$wire set token 905861000593539153 eyJhbGciOiJI...` - Store token securely in DM",
                 false)
             .field("Notes",
                 "• `wire in/out` works in DMs or guild channels\n\
                  • `wire set token` works **ONLY in DMs** (for security)\n\
-                 • Uses currency's configured UnbelievaBoat guild for transfers\n\
+                 • Uses the currency's own guild on the wire provider for transfers\n\
                  • Cannot go negative on either side\n\
+                 • `in` takes whole units of the wire provider, `out` takes SMITE; both show a quote to confirm within 2 minutes\n\
+                 • The bridge fee goes to the currency's tax account; `rounding` is down, up or nearest\n\
                  • Currency must exist in SMITE\n\
-                 • Each currency linked to one UnbelievaBoat guild",
+                 • Each currency is linked to one guild on its wire provider\n\
+                 • Interrupted wires are finished or reversed automatically; `complete` keeps the SMITE side, `refund` reverses it\n\
+                 • `http` keys: name, path, bank, cash, delta, method, header, prefix, rps; the token is per provider",
                 false)
             .color(0x00b0f4);
 
@@ -63,14 +80,14 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
         let token = args[3..].join(" ");
         
         if token.is_empty() {
-            return Err("❌ Token is missing. Provide the UnbelievaBoat API token.".to_string());
+            return Err("❌ Token is missing. Provide the provider's API token.".to_string());
         }
 
-        match wire_service::set_api_token(ctx, msg, Some(guild_id_arg), &token).await {
+        match wire_service::set_api_token(ctx, &Invocation::from(msg), Some(guild_id_arg), &token).await {
             Ok(_) => {
                 let embed = serenity::builder::CreateEmbed::default()
                     .title("✅ Token Set Successfully")
-                    .description(format!("UnbelievaBoat API token has been encrypted and stored for guild `{}`.\nPlease delete your message containing the token for security reasons.", guild_id_arg))
+                    .description(format!("Wire provider API token has been encrypted and stored for guild `{}`.\nPlease delete your message containing the token for security reasons.", guild_id_arg))
                     .color(0x00ff00);
 
                 msg.channel_id
@@ -90,84 +107,96 @@ pub async fn execute(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(),
         return Ok(());
     }
 
+    match args[0] {
+        "history" => {
+            let page = wire_service::wire_history(ctx, &Invocation::from(msg)).await?;
+            page.send(ctx, msg.channel_id, msg.author.id).await?;
+            return Ok(());
+        }
+        "reconcile" => {
+            let invocation = Invocation::from(msg);
+            if args.len() < 2 {
+                let page = wire_service::list_unsettled_wires(ctx, &invocation).await?;
+                page.send(ctx, msg.channel_id, msg.author.id).await?;
+                return Ok(());
+            }
+
+            let wire_id = args[1]
+                .trim_start_matches('#')
+                .parse::<i64>()
+                .map_err(|_| "❌ Invalid wire ID".to_string())?;
+            let resolution = args.get(2)
+                .and_then(|arg| WireResolution::parse(arg))
+                .ok_or("❌ Usage: `$wire reconcile <id> complete|refund`".to_string())?;
+
+            let embed = wire_service::resolve_wire(ctx, &invocation, wire_id, resolution).await?;
+            msg.channel_id
+                .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
+                .await
+                .map_err(|e| e.to_string())?;
+            return Ok(());
+        }
+        "config" => {
+            let invocation = Invocation::from(msg);
+            let embed = match (args.get(1), args.get(2)) {
+                (None, _) => wire_service::show_wire_config(ctx, &invocation).await?,
+                (Some(name), Some(value)) => {
+                    let setting = WireSetting::parse(name, value)?;
+                    wire_service::update_wire_config(ctx, &invocation, &setting).await?
+                }
+                (Some(_), None) => {
+                    return Err("❌ Usage: `$wire config rate|min|max|fee|rounding <value>`".to_string());
+                }
+            };
+            msg.channel_id
+                .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
+                .await
+                .map_err(|e| e.to_string())?;
+            return Ok(());
+        }
+        "provider" => {
+            let invocation = Invocation::from(msg);
+            let embed = if args.len() < 2 {
+                wire_service::show_bridge_provider(ctx, &invocation).await?
+            } else {
+                let provider = BridgeProvider::parse(&args[1..])?;
+                wire_service::set_bridge_provider(ctx, &invocation, &provider).await?
+            };
+            msg.channel_id
+                .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
+                .await
+                .map_err(|e| e.to_string())?;
+            return Ok(());
+        }
+        _ => {}
+    }
+
     // Handle wire in/out operations
     if args.len() < 3 {
         return Err("❌ Usage: `$wire in/out <amount> <currency>`".to_string());
     }
 
-    let direction = args[0];
+    let direction = WireDirection::parse(args[0])
+        .ok_or("❌ Direction must be `in` or `out`.".to_string())?;
     let amount_str = args[1];
     let currency_ticker = args[2].to_uppercase();
 
-    let amount: f64 = amount_str
+    let amount: Amount = amount_str
         .parse()
         .map_err(|_| "❌ Invalid amount. Please provide a valid number.".to_string())?;
 
-    if amount <= 0.0 {
-        return Err("❌ Amount must be greater than 0.".to_string());
-    }
-
-    match direction {
-        "in" => {
-            match wire_service::wire_in(ctx, msg, amount, &currency_ticker).await {
-                Ok(result) => {
-                    let embed = serenity::builder::CreateEmbed::default()
-                        .title("✅ Wire In Successful")
-                        .description(format!(
-                            "Transferred {} {} from UnbelievaBoat to SMITE",
-                            amount, currency_ticker
-                        ))
-                        .field("UnbelievaBoat Balance", format!("{} bank remaining", result.ub_balance), false)
-                        .field("SMITE Balance", format!("{} {}", result.smite_balance, currency_ticker), false)
-                        .color(0x00ff00);
-
-                    msg.channel_id
-                        .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
-                        .await
-                        .map_err(|e| e.to_string())?;
-                }
-                Err(e) => {
-                    let error_embed = e.to_embed();
-
-                    msg.channel_id
-                        .send_message(ctx, serenity::builder::CreateMessage::default().embed(error_embed))
-                        .await
-                        .map_err(|e| e.to_string())?;
-                }
-            }
-        }
-        "out" => {
-            match wire_service::wire_out(ctx, msg, amount, &currency_ticker).await {
-                Ok(result) => {
-                    let embed = serenity::builder::CreateEmbed::default()
-                        .title("✅ Wire Out Successful")
-                        .description(format!(
-                            "Transferred {} {} from SMITE to UnbelievaBoat",
-                            amount, currency_ticker
-                        ))
-                        .field("SMITE Balance", format!("{} {} remaining", result.smite_balance, currency_ticker), false)
-                        .field("UnbelievaBoat Balance", format!("{} bank", result.ub_balance), false)
-                        .color(0x00ff00);
-
-                    msg.channel_id
-                        .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
-                        .await
-                        .map_err(|e| e.to_string())?;
-                }
-                Err(e) => {
-                    let error_embed = e.to_embed();
-
-                    msg.channel_id
-                        .send_message(ctx, serenity::builder::CreateMessage::default().embed(error_embed))
-                        .await
-                        .map_err(|e| e.to_string())?;
-                }
-            }
-        }
-        _ => {
-            return Err("❌ Direction must be `in` or `out`.".to_string());
-        }
-    }
+    // Nothing moves yet: the user confirms the quote with its buttons
+    let message = match wire_service::quote_wire(ctx, direction, amount, &currency_ticker).await {
+        Ok((quote, ticker, provider)) => serenity::builder::CreateMessage::default()
+            .embed(wire_service::create_quote_embed(&quote, &ticker, &provider))
+            .components(wire_service::create_quote_buttons(msg.author.id.get(), &quote, &ticker)),
+        Err(e) => serenity::builder::CreateMessage::default().embed(e.to_embed()),
+    };
+
+    msg.channel_id
+        .send_message(ctx, message)
+        .await
+        .map_err(|e| e.to_string())?;
 
     Ok(())
 }
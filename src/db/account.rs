@@ -1,5 +1,6 @@
 use sqlx::mysql::MySqlPool;
 use sqlx::Row;
+use crate::utils::Amount;
 
 /// Create a new account for a user
 pub async fn create_account(
@@ -21,14 +22,14 @@ pub async fn get_account_balance(
     pool: &MySqlPool,
     discord_id: i64,
     currency_id: i64,
-) -> Result<Option<f64>, sqlx::Error> {
-    let row = sqlx::query("SELECT CAST(balance AS DOUBLE) as balance FROM account WHERE discord_id = ? AND currency_id = ?")
+) -> Result<Option<Amount>, sqlx::Error> {
+    let row = sqlx::query("SELECT balance FROM account WHERE discord_id = ? AND currency_id = ?")
         .bind(discord_id)
         .bind(currency_id)
         .fetch_optional(pool)
         .await?;
 
-    Ok(row.map(|r| r.get::<f64, _>("balance")))
+    Ok(row.map(|r| r.get::<Amount, _>("balance")))
 }
 
 /// Get account ID by Discord user ID and currency ID
@@ -50,7 +51,7 @@ pub async fn get_account_id(
 pub async fn update_balance(
     pool: &MySqlPool,
     account_id: i64,
-    amount: f64,
+    amount: Amount,
 ) -> Result<(), sqlx::Error> {
     sqlx::query("UPDATE account SET balance = balance + ? WHERE id = ?")
         .bind(amount)
@@ -65,8 +66,8 @@ pub async fn update_balance(
 pub async fn get_account(
     pool: &MySqlPool,
     account_id: i64,
-) -> Result<Option<(i64, i64, i64, f64)>, sqlx::Error> {
-    let row = sqlx::query("SELECT id, discord_id, currency_id, CAST(balance AS DOUBLE) as balance FROM account WHERE id = ?")
+) -> Result<Option<(i64, i64, i64, Amount)>, sqlx::Error> {
+    let row = sqlx::query("SELECT id, discord_id, currency_id, balance FROM account WHERE id = ?")
         .bind(account_id)
         .fetch_optional(pool)
         .await?;
@@ -75,7 +76,7 @@ pub async fn get_account(
         r.get::<i64, _>("id"),
         r.get::<i64, _>("discord_id"),
         r.get::<i64, _>("currency_id"),
-        r.get::<f64, _>("balance"),
+        r.get::<Amount, _>("balance"),
     )))
 }
 
@@ -83,7 +84,7 @@ pub async fn get_account(
 pub async fn set_balance(
     pool: &MySqlPool,
     account_id: i64,
-    balance: f64,
+    balance: Amount,
 ) -> Result<(), sqlx::Error> {
     sqlx::query("UPDATE account SET balance = ? WHERE id = ?")
         .bind(balance)
@@ -107,37 +108,15 @@ pub async fn get_discord_id_by_account_id(
     Ok(row.map(|r| r.get::<i64, _>("discord_id")))
 }
 
-/// Add balance to an account by discord_id and currency_id
-pub async fn add_balance(
-    pool: &MySqlPool,
-    discord_id: i64,
-    currency_id: i64,
-    amount: f64,
-) -> Result<(), sqlx::Error> {
-    // First, try to get or create the account
-    if let None = get_account_balance(pool, discord_id, currency_id).await? {
-        create_account(pool, discord_id, currency_id).await?;
-    }
-
-    sqlx::query("UPDATE account SET balance = balance + ? WHERE discord_id = ? AND currency_id = ?")
-        .bind(amount)
-        .bind(discord_id)
-        .bind(currency_id)
-        .execute(pool)
-        .await?;
-
-    Ok(())
-}
-
 /// Get total balance across all accounts for a currency
 pub async fn get_total_balance(
     pool: &MySqlPool,
     currency_id: i64,
-) -> Result<Option<f64>, sqlx::Error> {
-    let row = sqlx::query("SELECT CAST(SUM(CAST(balance AS DOUBLE)) AS DOUBLE) as total FROM account WHERE currency_id = ?")
+) -> Result<Option<Amount>, sqlx::Error> {
+    let row = sqlx::query("SELECT SUM(balance) as total FROM account WHERE currency_id = ?")
         .bind(currency_id)
         .fetch_optional(pool)
         .await?;
 
-    Ok(row.and_then(|r| r.get::<Option<f64>, _>("total")))
+    Ok(row.and_then(|r| r.get::<Option<Amount>, _>("total")))
 }
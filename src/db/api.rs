@@ -17,6 +17,41 @@ pub async fn get_api_token(
     Ok(row.map(|r| r.get::<String, _>("encrypted_token")))
 }
 
+/// The currency's wire provider as (api_type_id, settings JSON)
+pub async fn get_bridge_provider(
+    pool: &MySqlPool,
+    currency_id: i64,
+) -> Result<Option<(i32, Option<String>)>, sqlx::Error> {
+    let row: Option<(i64, Option<String>)> = sqlx::query_as(
+        "SELECT CAST(api_type_id AS SIGNED), settings FROM bridge_provider WHERE currency_id = ?"
+    )
+    .bind(currency_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|(api_type_id, settings)| (api_type_id as i32, settings)))
+}
+
+/// Choose the currency's wire provider
+pub async fn set_bridge_provider(
+    pool: &MySqlPool,
+    currency_id: i64,
+    api_type_id: i32,
+    settings: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO bridge_provider (currency_id, api_type_id, settings) VALUES (?, ?, ?)
+         ON DUPLICATE KEY UPDATE api_type_id = VALUES(api_type_id), settings = VALUES(settings)"
+    )
+    .bind(currency_id)
+    .bind(api_type_id as i8)
+    .bind(settings)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
 /// Store encrypted API token for a currency
 /// api_type_id: 1 = UnbelievaBoat
 pub async fn store_api_token(
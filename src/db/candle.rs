@@ -0,0 +1,449 @@
+//! OHLCV rollups of the tradelog.
+//!
+//! Each trade updates its 1 minute, 1 hour and 1 day candle in one upsert, so
+//! price lookups and charts read a handful of pre-aggregated rows. Candles are
+//! keyed by the pair's canonical order (see `tradelog::normalize_pair`), with
+//! volume in the base currency and quote volume in the quote currency.
+//!
+//! Buckets are aligned on the server's local DATETIME values, the same clock
+//! `tradelog.date_created` uses, so a backfill rebuilds exactly the candles the
+//! live upserts would have produced.
+
+use std::collections::{BTreeMap, HashMap};
+use chrono::{NaiveDateTime, Timelike};
+use sqlx::mysql::{MySql, MySqlPool};
+use sqlx::QueryBuilder;
+use crate::utils::Amount;
+
+/// Rows per INSERT when writing a backfill
+const BACKFILL_BATCH_SIZE: usize = 500;
+
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Resolution {
+    Minute,
+    Hour,
+    Day,
+}
+
+impl Resolution {
+    pub const ALL: [Resolution; 3] = [Resolution::Minute, Resolution::Hour, Resolution::Day];
+
+    /// Value stored in `price_candle.resolution`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Resolution::Minute => "1m",
+            Resolution::Hour => "1h",
+            Resolution::Day => "1d",
+        }
+    }
+
+    pub fn seconds(&self) -> i64 {
+        match self {
+            Resolution::Minute => 60,
+            Resolution::Hour => 3_600,
+            Resolution::Day => 86_400,
+        }
+    }
+
+    /// MySQL DATE_FORMAT pattern that truncates a DATETIME to its bucket
+    fn bucket_format(&self) -> &'static str {
+        match self {
+            Resolution::Minute => "%Y-%m-%d %H:%i:00",
+            Resolution::Hour => "%Y-%m-%d %H:00:00",
+            Resolution::Day => "%Y-%m-%d 00:00:00",
+        }
+    }
+
+    /// Start of the bucket `at` falls in
+    pub fn bucket_start(&self, at: NaiveDateTime) -> NaiveDateTime {
+        let minute = at.with_second(0).and_then(|t| t.with_nanosecond(0)).unwrap_or(at);
+        match self {
+            Resolution::Minute => minute,
+            Resolution::Hour => minute.with_minute(0).unwrap_or(minute),
+            Resolution::Day => at.date().and_hms_opt(0, 0, 0).unwrap_or(minute),
+        }
+    }
+
+    /// Coarsest resolution that fits evenly into `interval_seconds`,
+    /// so its candles can be merged into candles of that interval
+    pub fn for_interval(interval_seconds: i64) -> Resolution {
+        Self::ALL
+            .into_iter()
+            .rev()
+            .find(|resolution| interval_seconds % resolution.seconds() == 0)
+            .unwrap_or(Resolution::Minute)
+    }
+
+    /// Resolution that keeps a window of `window_seconds` (None for all history)
+    /// to a chartable number of candles
+    pub fn for_window(window_seconds: Option<i64>) -> Resolution {
+        match window_seconds {
+            Some(seconds) if seconds <= 2 * 86_400 => Resolution::Minute,
+            Some(seconds) if seconds <= 60 * 86_400 => Resolution::Hour,
+            Some(_) => Resolution::Day,
+            None => Resolution::Hour,
+        }
+    }
+}
+
+/// One stored candle
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandleRow {
+    pub base_currency_id: i64,
+    pub quote_currency_id: i64,
+    pub bucket_start: NaiveDateTime,
+    pub open: Amount,
+    pub high: Amount,
+    pub low: Amount,
+    pub close: Amount,
+    pub volume: Amount,
+    pub quote_volume: Amount,
+    pub trade_count: i64,
+}
+
+/// Fold one trade into its minute, hour and day candles.
+/// `volume` is the base currency traded and `quote_volume` the quote currency paid for it.
+/// Takes any executor so it can join the caller's transaction.
+pub async fn record_trade<'e, E>(
+    executor: E,
+    base_currency_id: i64,
+    quote_currency_id: i64,
+    price: Amount,
+    volume: Amount,
+    quote_volume: Amount,
+) -> Result<(), sqlx::Error>
+where
+    E: sqlx::Executor<'e, Database = MySql>,
+{
+    let values = Resolution::ALL
+        .iter()
+        .map(|resolution| format!(
+            "(?, ?, '{}', DATE_FORMAT(NOW(), '{}'), ?, ?, ?, ?, ?, ?, 1)",
+            resolution.as_str(),
+            resolution.bucket_format()
+        ))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    // The open is only set when the bucket is created; later trades move the rest
+    let sql = format!(
+        "INSERT INTO price_candle
+            (base_currency_id, quote_currency_id, resolution, bucket_start, open, high, low, close, volume, quote_volume, trade_count)
+         VALUES {}
+         ON DUPLICATE KEY UPDATE
+            high = GREATEST(high, VALUES(high)),
+            low = LEAST(low, VALUES(low)),
+            close = VALUES(close),
+            volume = volume + VALUES(volume),
+            quote_volume = quote_volume + VALUES(quote_volume),
+            trade_count = trade_count + 1",
+        values
+    );
+
+    let mut query = sqlx::query(&sql);
+    for _ in Resolution::ALL {
+        query = query
+            .bind(base_currency_id)
+            .bind(quote_currency_id)
+            .bind(price)
+            .bind(price)
+            .bind(price)
+            .bind(price)
+            .bind(volume)
+            .bind(quote_volume);
+    }
+    query.execute(executor).await?;
+
+    Ok(())
+}
+
+/// Candles for a canonical pair, oldest first.
+/// With `since_minutes`, only buckets starting in that many minutes before now.
+pub async fn get_candles(
+    pool: &MySqlPool,
+    base_currency_id: i64,
+    quote_currency_id: i64,
+    resolution: Resolution,
+    since_minutes: Option<i64>,
+) -> Result<Vec<CandleRow>, sqlx::Error> {
+    let window = if since_minutes.is_some() {
+        "AND bucket_start >= DATE_SUB(NOW(), INTERVAL ? MINUTE)"
+    } else {
+        ""
+    };
+    let sql = format!(
+        "SELECT DATE_FORMAT(bucket_start, '%Y-%m-%d %H:%i:%s'), open, high, low, close, volume, quote_volume, trade_count
+         FROM price_candle
+         WHERE base_currency_id = ? AND quote_currency_id = ? AND resolution = ? {}
+         ORDER BY bucket_start ASC",
+        window
+    );
+
+    let mut query = sqlx::query_as::<_, (String, Amount, Amount, Amount, Amount, Amount, Amount, i64)>(&sql)
+        .bind(base_currency_id)
+        .bind(quote_currency_id)
+        .bind(resolution.as_str());
+    if let Some(minutes) = since_minutes {
+        query = query.bind(minutes);
+    }
+
+    let rows = query.fetch_all(pool).await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(bucket, open, high, low, close, volume, quote_volume, trade_count)| {
+            Some(CandleRow {
+                base_currency_id,
+                quote_currency_id,
+                bucket_start: NaiveDateTime::parse_from_str(&bucket, DATE_FORMAT).ok()?,
+                open,
+                high,
+                low,
+                close,
+                volume,
+                quote_volume,
+                trade_count,
+            })
+        })
+        .collect())
+}
+
+/// True when trades were logged but no candles exist yet, i.e. the backfill hasn't been run
+pub async fn needs_backfill(pool: &MySqlPool) -> Result<bool, sqlx::Error> {
+    let (has_trades, has_candles): (i64, i64) = sqlx::query_as(
+        "SELECT EXISTS(SELECT 1 FROM tradelog), EXISTS(SELECT 1 FROM price_candle)"
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(has_trades != 0 && has_candles == 0)
+}
+
+/// Rebuild every candle from the tradelog and the fill history.
+/// Prices come from `tradelog`; volumes from `swap_fill` and `order_fill`.
+/// Runs in one transaction, and should run while the bot is stopped so no
+/// trade lands between the read and the rewrite. Returns the number of candles written.
+pub async fn backfill(pool: &MySqlPool) -> Result<usize, sqlx::Error> {
+    let tickers: HashMap<i64, String> = sqlx::query_as::<_, (i64, String)>("SELECT id, ticker FROM currency")
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+
+    let trades: Vec<(i64, i64, Amount, NaiveDateTime)> = sqlx::query_as::<_, (i64, i64, Amount, String)>(
+        "SELECT base_currency_id, quote_currency_id, price, DATE_FORMAT(date_created, '%Y-%m-%d %H:%i:%s')
+         FROM tradelog
+         ORDER BY date_created ASC, id ASC"
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .filter_map(|(base, quote, price, date)| {
+        Some((base, quote, price, NaiveDateTime::parse_from_str(&date, DATE_FORMAT).ok()?))
+    })
+    .collect();
+
+    // Swap fills are stored maker/taker, so flip them into canonical order like normalize_pair does
+    let swap_fills = sqlx::query_as::<_, (i64, i64, Amount, Amount, String)>(
+        "SELECT cs.maker_currency_id, cs.taker_currency_id, sf.maker_amount, sf.taker_amount,
+                DATE_FORMAT(sf.date_created, '%Y-%m-%d %H:%i:%s')
+         FROM swap_fill sf
+         JOIN currency_swap cs ON sf.swap_id = cs.id"
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|(maker, taker, maker_amount, taker_amount, date)| {
+        let maker_ticker = tickers.get(&maker).map(String::as_str).unwrap_or_default();
+        let taker_ticker = tickers.get(&taker).map(String::as_str).unwrap_or_default();
+        if maker_ticker <= taker_ticker {
+            (maker, taker, maker_amount, taker_amount, date)
+        } else {
+            (taker, maker, taker_amount, maker_amount, date)
+        }
+    });
+
+    // Order book fills are already in canonical order
+    let order_fills = sqlx::query_as::<_, (i64, i64, Amount, Amount, String)>(
+        "SELECT lo.base_currency_id, lo.quote_currency_id, f.quantity, f.quote_amount,
+                DATE_FORMAT(f.date_created, '%Y-%m-%d %H:%i:%s')
+         FROM order_fill f
+         JOIN limit_order lo ON f.bid_order_id = lo.id"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let volumes: Vec<(i64, i64, Amount, Amount, NaiveDateTime)> = swap_fills
+        .chain(order_fills)
+        .filter_map(|(base, quote, volume, quote_volume, date)| {
+            Some((base, quote, volume, quote_volume, NaiveDateTime::parse_from_str(&date, DATE_FORMAT).ok()?))
+        })
+        .collect();
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM price_candle").execute(&mut *tx).await?;
+
+    let mut written = 0;
+    for resolution in Resolution::ALL {
+        let candles = aggregate_candles(&trades, &volumes, resolution);
+        for batch in candles.chunks(BACKFILL_BATCH_SIZE) {
+            let mut builder: QueryBuilder<MySql> = QueryBuilder::new(
+                "INSERT INTO price_candle
+                    (base_currency_id, quote_currency_id, resolution, bucket_start, open, high, low, close, volume, quote_volume, trade_count) "
+            );
+            builder.push_values(batch, |mut row, candle| {
+                row.push_bind(candle.base_currency_id)
+                    .push_bind(candle.quote_currency_id)
+                    .push_bind(resolution.as_str())
+                    .push_bind(candle.bucket_start.format(DATE_FORMAT).to_string())
+                    .push_bind(candle.open)
+                    .push_bind(candle.high)
+                    .push_bind(candle.low)
+                    .push_bind(candle.close)
+                    .push_bind(candle.volume)
+                    .push_bind(candle.quote_volume)
+                    .push_bind(candle.trade_count);
+            });
+            builder.build().execute(&mut *tx).await?;
+        }
+        written += candles.len();
+    }
+
+    tx.commit().await?;
+    Ok(written)
+}
+
+/// Roll trades up into candles of one resolution.
+/// `trades` are (base_id, quote_id, price, time) in time order; `volumes` are
+/// (base_id, quote_id, base amount, quote amount, time) and are added to the
+/// candle of the same bucket. Volume in a bucket without a logged price is dropped.
+pub fn aggregate_candles(
+    trades: &[(i64, i64, Amount, NaiveDateTime)],
+    volumes: &[(i64, i64, Amount, Amount, NaiveDateTime)],
+    resolution: Resolution,
+) -> Vec<CandleRow> {
+    let mut candles: BTreeMap<(i64, i64, NaiveDateTime), CandleRow> = BTreeMap::new();
+
+    for &(base, quote, price, at) in trades {
+        let bucket_start = resolution.bucket_start(at);
+        candles
+            .entry((base, quote, bucket_start))
+            .and_modify(|candle| {
+                candle.high = candle.high.max(price);
+                candle.low = candle.low.min(price);
+                candle.close = price;
+                candle.trade_count += 1;
+            })
+            .or_insert(CandleRow {
+                base_currency_id: base,
+                quote_currency_id: quote,
+                bucket_start,
+                open: price,
+                high: price,
+                low: price,
+                close: price,
+                volume: Amount::ZERO,
+                quote_volume: Amount::ZERO,
+                trade_count: 1,
+            });
+    }
+
+    for &(base, quote, volume, quote_volume, at) in volumes {
+        if let Some(candle) = candles.get_mut(&(base, quote, resolution.bucket_start(at))) {
+            candle.volume = candle.volume + volume;
+            candle.quote_volume = candle.quote_volume + quote_volume;
+        }
+    }
+
+    candles.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::amount as amt;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, DATE_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn test_bucket_start() {
+        let time = at("2024-03-05 14:37:52");
+        assert_eq!(Resolution::Minute.bucket_start(time), at("2024-03-05 14:37:00"));
+        assert_eq!(Resolution::Hour.bucket_start(time), at("2024-03-05 14:00:00"));
+        assert_eq!(Resolution::Day.bucket_start(time), at("2024-03-05 00:00:00"));
+    }
+
+    #[test]
+    fn test_resolution_choice() {
+        assert_eq!(Resolution::for_interval(5 * 60), Resolution::Minute);
+        assert_eq!(Resolution::for_interval(4 * 3_600), Resolution::Hour);
+        assert_eq!(Resolution::for_interval(86_400), Resolution::Day);
+        assert_eq!(Resolution::for_interval(90), Resolution::Minute);
+        assert_eq!(Resolution::for_window(Some(86_400)), Resolution::Minute);
+        assert_eq!(Resolution::for_window(Some(30 * 86_400)), Resolution::Hour);
+        assert_eq!(Resolution::for_window(Some(365 * 86_400)), Resolution::Day);
+    }
+
+    #[test]
+    fn test_aggregate_candles() {
+        let trades = vec![
+            (1, 2, amt("1"), at("2024-03-05 14:00:10")),
+            (1, 2, amt("3"), at("2024-03-05 14:20:00")),
+            (1, 2, amt("0.5"), at("2024-03-05 14:40:00")),
+            (1, 2, amt("2"), at("2024-03-05 14:59:59")),
+            (1, 2, amt("4"), at("2024-03-05 16:05:00")),
+            // Another pair in the same hour gets its own candle
+            (1, 3, amt("7"), at("2024-03-05 14:30:00")),
+        ];
+        let volumes = vec![
+            (1, 2, amt("10"), amt("20"), at("2024-03-05 14:20:00")),
+            (1, 2, amt("5"), amt("2.5"), at("2024-03-05 14:40:00")),
+            // No price in this bucket
+            (1, 2, amt("99"), amt("99"), at("2024-03-05 15:00:00")),
+        ];
+
+        let hourly = aggregate_candles(&trades, &volumes, Resolution::Hour);
+        assert_eq!(hourly.len(), 3);
+
+        let first = &hourly[0];
+        assert_eq!((first.base_currency_id, first.quote_currency_id), (1, 2));
+        assert_eq!(first.bucket_start, at("2024-03-05 14:00:00"));
+        assert_eq!(
+            (first.open, first.high, first.low, first.close),
+            (amt("1"), amt("3"), amt("0.5"), amt("2"))
+        );
+        assert_eq!((first.volume, first.quote_volume, first.trade_count), (amt("15"), amt("22.5"), 4));
+
+        assert_eq!(hourly[1].bucket_start, at("2024-03-05 16:00:00"));
+        assert_eq!(hourly[1].volume, Amount::ZERO);
+        assert_eq!(hourly[2].quote_currency_id, 3);
+
+        let daily = aggregate_candles(&trades, &volumes, Resolution::Day);
+        assert_eq!(daily.len(), 2);
+        assert_eq!((daily[0].close, daily[0].trade_count), (amt("4"), 5));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local MySQL database in TEST_DATABASE_URL"]
+    async fn test_record_trade_rolls_up() {
+        let pool = MySqlPool::connect(&std::env::var("TEST_DATABASE_URL").unwrap()).await.unwrap();
+        let (base, quote): (i64, i64) = sqlx::query_as("SELECT MIN(id), MAX(id) FROM currency")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        record_trade(&pool, base, quote, amt("2"), amt("10"), amt("20")).await.unwrap();
+        record_trade(&pool, base, quote, amt("1"), amt("4"), amt("4")).await.unwrap();
+
+        for resolution in Resolution::ALL {
+            let candles = get_candles(&pool, base, quote, resolution, None).await.unwrap();
+            let latest = candles.last().unwrap();
+            assert_eq!(latest.close, amt("1"));
+            assert!(latest.high >= amt("2") && latest.low <= amt("1"));
+        }
+    }
+}
@@ -71,6 +71,36 @@ pub async fn get_currency_date(
     Ok(row.map(|r| r.get::<String, _>("date_str")))
 }
 
+/// Get the default swap expiry (in minutes) for a currency, None if swaps never expire
+pub async fn get_swap_expiry_minutes(
+    pool: &MySqlPool,
+    currency_id: i64,
+) -> Result<Option<i64>, sqlx::Error> {
+    let minutes: Option<Option<i32>> = sqlx::query_scalar(
+        "SELECT swap_expiry_minutes FROM currency WHERE id = ?"
+    )
+    .bind(currency_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(minutes.flatten().map(i64::from))
+}
+
+/// Set the default swap expiry (in minutes) for a currency, None to disable
+pub async fn set_swap_expiry_minutes(
+    pool: &MySqlPool,
+    currency_id: i64,
+    minutes: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE currency SET swap_expiry_minutes = ? WHERE id = ?")
+        .bind(minutes)
+        .bind(currency_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
 /// Get all currencies for a guild with optional sorting
 /// sort_by: "oldest" (default) or "recent"
 pub async fn get_currencies_by_guild_sorted(
@@ -124,3 +154,25 @@ pub async fn get_currencies_paginated(
 
     Ok((currencies, count_row))
 }
+
+/// Currencies whose ticker starts with `prefix`, for slash command autocomplete
+/// Returns (name, ticker) ordered by ticker
+pub async fn search_tickers(
+    pool: &MySqlPool,
+    prefix: &str,
+    limit: i64,
+) -> Result<Vec<(String, String)>, sqlx::Error> {
+    // Escape LIKE wildcards so the input is matched literally
+    let escaped = prefix
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+
+    sqlx::query_as::<_, (String, String)>(
+        "SELECT name, ticker FROM currency WHERE ticker LIKE CONCAT(?, '%') ORDER BY ticker LIMIT ?"
+    )
+    .bind(escaped)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
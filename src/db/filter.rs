@@ -0,0 +1,297 @@
+//! Typed filters for the list queries.
+//!
+//! User input only ever reaches SQL as a bound parameter. The few pieces that
+//! have to be part of the statement text, like sort columns and interval units,
+//! come from the fixed strings of an enum, never from the caller.
+
+use sqlx::mysql::MySql;
+use sqlx::{Database, Encode, QueryBuilder, Type};
+
+/// Sort order of `$swap list`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SwapSort {
+    Oldest,
+    #[default]
+    Latest,
+    HighMaker,
+    LowMaker,
+    HighTaker,
+    LowTaker,
+}
+
+impl SwapSort {
+    pub const ALL: [SwapSort; 6] = [
+        SwapSort::Oldest,
+        SwapSort::Latest,
+        SwapSort::HighMaker,
+        SwapSort::LowMaker,
+        SwapSort::HighTaker,
+        SwapSort::LowTaker,
+    ];
+
+    pub fn parse(sort: &str) -> Option<SwapSort> {
+        Self::ALL.into_iter().find(|s| s.as_str() == sort.to_lowercase())
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SwapSort::Oldest => "oldest",
+            SwapSort::Latest => "latest",
+            SwapSort::HighMaker => "highmaker",
+            SwapSort::LowMaker => "lowmaker",
+            SwapSort::HighTaker => "hightaker",
+            SwapSort::LowTaker => "lowtaker",
+        }
+    }
+
+    /// ORDER BY clause; the swap ID breaks ties so pages don't overlap
+    fn order_by(&self) -> &'static str {
+        match self {
+            SwapSort::Oldest => " ORDER BY cs.date_created ASC, cs.id ASC",
+            SwapSort::Latest => " ORDER BY cs.date_created DESC, cs.id DESC",
+            SwapSort::HighMaker => " ORDER BY cs.maker_amount DESC, cs.id DESC",
+            SwapSort::LowMaker => " ORDER BY cs.maker_amount ASC, cs.id DESC",
+            SwapSort::HighTaker => " ORDER BY cs.taker_amount DESC, cs.id DESC",
+            SwapSort::LowTaker => " ORDER BY cs.taker_amount ASC, cs.id DESC",
+        }
+    }
+}
+
+/// Which swaps `$swap list` shows by status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SwapStatus {
+    #[default]
+    All,
+    Pending,
+    Accepted,
+    Cancelled,
+    Expired,
+}
+
+impl SwapStatus {
+    pub const ALL: [SwapStatus; 5] = [
+        SwapStatus::All,
+        SwapStatus::Pending,
+        SwapStatus::Accepted,
+        SwapStatus::Cancelled,
+        SwapStatus::Expired,
+    ];
+
+    pub fn parse(status: &str) -> Option<SwapStatus> {
+        Self::ALL.into_iter().find(|s| s.as_str() == status.to_lowercase())
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SwapStatus::All => "all",
+            SwapStatus::Pending => "pending",
+            SwapStatus::Accepted => "accepted",
+            SwapStatus::Cancelled => "cancelled",
+            SwapStatus::Expired => "expired",
+        }
+    }
+}
+
+/// Filters for the swap list. Tickers match the maker and taker currency, ignoring case.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SwapFilter {
+    pub status: SwapStatus,
+    pub maker_ticker: Option<String>,
+    pub taker_ticker: Option<String>,
+}
+
+impl SwapFilter {
+    /// Tables and joins the swap list selects from, aliased as the filter expects
+    const FROM: &'static str = " FROM currency_swap cs
+         JOIN account a_maker ON cs.maker_id = a_maker.id
+         LEFT JOIN account a_taker ON cs.taker_id = a_taker.id
+         JOIN currency c_maker ON cs.maker_currency_id = c_maker.id
+         JOIN currency c_taker ON cs.taker_currency_id = c_taker.id";
+
+    /// `SELECT <columns> FROM ... WHERE <filter>`, for either SQL backend
+    pub fn select<DB>(&self, columns: &str) -> QueryBuilder<'static, DB>
+    where
+        DB: Database,
+        String: Encode<'static, DB> + Type<DB>,
+    {
+        let mut builder = QueryBuilder::new("SELECT ");
+        builder.push(columns).push(Self::FROM).push(" WHERE 1=1");
+
+        if self.status != SwapStatus::All {
+            builder.push(" AND cs.status = ").push_bind(self.status.as_str().to_string());
+        }
+        if let Some(ticker) = &self.maker_ticker {
+            builder.push(" AND UPPER(c_maker.ticker) = ").push_bind(ticker.to_uppercase());
+        }
+        if let Some(ticker) = &self.taker_ticker {
+            builder.push(" AND UPPER(c_taker.ticker) = ").push_bind(ticker.to_uppercase());
+        }
+
+        builder
+    }
+
+    /// One sorted page of the filtered swaps
+    pub fn select_page<DB>(&self, columns: &str, sort: SwapSort, limit: i64, offset: i64) -> QueryBuilder<'static, DB>
+    where
+        DB: Database,
+        String: Encode<'static, DB> + Type<DB>,
+        i64: Encode<'static, DB> + Type<DB>,
+    {
+        let mut builder = self.select(columns);
+        builder.push(sort.order_by());
+        builder.push(" LIMIT ").push_bind(limit);
+        builder.push(" OFFSET ").push_bind(offset);
+        builder
+    }
+}
+
+/// Filters for the latest price list
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PriceFilter {
+    pub base_ticker: Option<String>,
+    pub quote_ticker: Option<String>,
+}
+
+impl PriceFilter {
+    /// Append the filter's conditions to a query over `c1` (base) and `c2` (quote) currencies
+    pub fn push_conditions(&self, builder: &mut QueryBuilder<'static, MySql>) {
+        if let Some(ticker) = &self.base_ticker {
+            builder.push(" AND c1.ticker = ").push_bind(ticker.to_uppercase());
+        }
+        if let Some(ticker) = &self.quote_ticker {
+            builder.push(" AND c2.ticker = ").push_bind(ticker.to_uppercase());
+        }
+    }
+}
+
+/// Unit of a MySQL `INTERVAL`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntervalUnit {
+    Minute,
+    Hour,
+    Day,
+    Month,
+    Year,
+}
+
+impl IntervalUnit {
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            IntervalUnit::Minute => "MINUTE",
+            IntervalUnit::Hour => "HOUR",
+            IntervalUnit::Day => "DAY",
+            IntervalUnit::Month => "MONTH",
+            IntervalUnit::Year => "YEAR",
+        }
+    }
+}
+
+/// A look-back window such as "7 DAY", ending now
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub amount: i64,
+    pub unit: IntervalUnit,
+}
+
+impl TimeWindow {
+    pub fn minutes(amount: i64) -> TimeWindow {
+        TimeWindow { amount, unit: IntervalUnit::Minute }
+    }
+
+    /// Append `AND <column> >= DATE_SUB(NOW(), INTERVAL ? <unit>)`, binding the amount
+    pub fn push_since(&self, builder: &mut QueryBuilder<'static, MySql>, column: &'static str) {
+        builder
+            .push(" AND ")
+            .push(column)
+            .push(" >= DATE_SUB(NOW(), INTERVAL ")
+            .push_bind(self.amount)
+            .push(" ")
+            .push(self.unit.keyword())
+            .push(")");
+    }
+}
+
+impl std::fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.amount, self.unit.keyword())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::mysql::MySqlPool;
+    use crate::db;
+
+    const PAYLOADS: [&str; 4] = [
+        "' OR '1'='1",
+        "ABC') OR 1=1 -- ",
+        "x'; DROP TABLE currency; --",
+        "\\' UNION SELECT ticker FROM currency #",
+    ];
+
+    #[test]
+    fn test_parse_sort_and_status() {
+        for sort in SwapSort::ALL {
+            assert_eq!(SwapSort::parse(sort.as_str()), Some(sort));
+        }
+        assert_eq!(SwapSort::parse("HighMaker"), Some(SwapSort::HighMaker));
+        assert_eq!(SwapSort::parse("cs.id; DROP TABLE currency"), None);
+
+        for status in SwapStatus::ALL {
+            assert_eq!(SwapStatus::parse(status.as_str()), Some(status));
+        }
+        assert_eq!(SwapStatus::parse("pending' OR '1'='1"), None);
+    }
+
+    #[test]
+    fn test_input_is_never_in_sql() {
+        for payload in PAYLOADS {
+            let filter = SwapFilter {
+                status: SwapStatus::Pending,
+                maker_ticker: Some(payload.to_string()),
+                taker_ticker: Some(payload.to_string()),
+            };
+            let sql = filter.select_page::<MySql>("cs.id", SwapSort::HighTaker, 5, 10).into_sql();
+            assert!(!sql.contains(&payload.to_uppercase()), "{}", sql);
+            assert!(!sql.contains("pending"));
+            assert!(sql.ends_with("ORDER BY cs.taker_amount DESC, cs.id DESC LIMIT ? OFFSET ?"));
+            assert_eq!(sql.matches('?').count(), 5);
+
+            let mut builder = QueryBuilder::new("SELECT 1 FROM currency c1, currency c2 WHERE 1=1");
+            PriceFilter { base_ticker: Some(payload.to_string()), quote_ticker: None }.push_conditions(&mut builder);
+            let sql = builder.into_sql();
+            assert!(sql.ends_with("AND c1.ticker = ?"));
+        }
+
+        let mut builder = QueryBuilder::new("SELECT 1 FROM tradelog WHERE 1=1");
+        TimeWindow { amount: 7, unit: IntervalUnit::Day }.push_since(&mut builder, "date_created");
+        assert!(builder.into_sql().ends_with("AND date_created >= DATE_SUB(NOW(), INTERVAL ? DAY)"));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local MySQL database in TEST_DATABASE_URL"]
+    async fn test_injection_payloads_match_nothing() {
+        let pool = MySqlPool::connect(&std::env::var("TEST_DATABASE_URL").unwrap()).await.unwrap();
+        let currencies: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM currency").fetch_one(&pool).await.unwrap();
+
+        for payload in PAYLOADS {
+            let filter = SwapFilter {
+                status: SwapStatus::All,
+                maker_ticker: Some(payload.to_string()),
+                taker_ticker: None,
+            };
+            let (swaps, total) = db::swap::get_swaps_paginated(&pool, 1, 5, SwapSort::Latest, &filter).await.unwrap();
+            assert!(swaps.is_empty());
+            assert_eq!(total, 0);
+
+            let filter = PriceFilter { base_ticker: None, quote_ticker: Some(payload.to_string()) };
+            let prices = db::tradelog::get_latest_prices_with_filter(&pool, &filter).await.unwrap();
+            assert!(prices.is_empty());
+        }
+
+        // Nothing was dropped or changed along the way
+        let after: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM currency").fetch_one(&pool).await.unwrap();
+        assert_eq!(after, currencies);
+    }
+}
@@ -0,0 +1,130 @@
+//! Invoices: one user asking another to pay them.
+//!
+//! An invoice moves from 'open' to 'paid', 'declined' or 'expired'. Paying it
+//! first claims it ('paying') so the transfer runs once, then links the
+//! transaction it made. A claim that never finished is settled by the expiry
+//! worker, which finds the payment by its memo or reopens the invoice.
+
+use sqlx::mysql::MySqlPool;
+use crate::storage::InvoiceRow;
+use crate::utils::Amount;
+
+const SELECT_INVOICE: &str = "SELECT CAST(i.id AS SIGNED), i.issuer_id, i.payer_id, CAST(i.currency_id AS SIGNED), c.ticker,
+        i.amount, i.memo, CAST(i.status AS CHAR), i.transaction_uuid, CAST(UNIX_TIMESTAMP(i.expires_at) AS SIGNED)
+     FROM invoice i
+     JOIN currency c ON i.currency_id = c.id";
+
+pub async fn create_invoice(
+    pool: &MySqlPool,
+    issuer_id: i64,
+    payer_id: i64,
+    currency_id: i64,
+    amount: Amount,
+    memo: Option<&str>,
+    expires_at: i64,
+) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO invoice (issuer_id, payer_id, currency_id, amount, memo, expires_at)
+         VALUES (?, ?, ?, ?, ?, FROM_UNIXTIME(?))"
+    )
+    .bind(issuer_id)
+    .bind(payer_id)
+    .bind(currency_id)
+    .bind(amount)
+    .bind(memo)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+
+    Ok(result.last_insert_id() as i64)
+}
+
+pub async fn get_invoice(pool: &MySqlPool, id: i64) -> Result<Option<InvoiceRow>, sqlx::Error> {
+    sqlx::query_as(&format!("{} WHERE i.id = ?", SELECT_INVOICE))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Invoices the user issued or has to pay, newest first, optionally with one status
+pub async fn get_invoices_by_user(
+    pool: &MySqlPool,
+    discord_id: i64,
+    status: Option<&str>,
+    limit: i64,
+) -> Result<Vec<InvoiceRow>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "{} WHERE (i.issuer_id = ? OR i.payer_id = ?) AND (? IS NULL OR i.status = ?)
+         ORDER BY i.id DESC LIMIT ?",
+        SELECT_INVOICE
+    ))
+    .bind(discord_id)
+    .bind(discord_id)
+    .bind(status)
+    .bind(status)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Open invoices past their expiry, oldest first
+pub async fn get_overdue_invoices(pool: &MySqlPool, limit: i64) -> Result<Vec<InvoiceRow>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "{} WHERE i.status = 'open' AND i.expires_at <= NOW() ORDER BY i.expires_at, i.id LIMIT ?",
+        SELECT_INVOICE
+    ))
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Invoices still 'paying' that haven't changed for `idle_seconds`, oldest first
+pub async fn get_stalled_invoices(pool: &MySqlPool, idle_seconds: i64, limit: i64) -> Result<Vec<InvoiceRow>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "{} WHERE i.status = 'paying' AND i.date_updated <= NOW() - INTERVAL ? SECOND ORDER BY i.id LIMIT ?",
+        SELECT_INVOICE
+    ))
+    .bind(idle_seconds)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Move an open invoice that hasn't expired to 'paying'.
+/// Returns false if it was paid, declined or expired in the meantime.
+pub async fn claim_invoice(pool: &MySqlPool, id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE invoice SET status = 'paying' WHERE id = ? AND status = 'open' AND expires_at > NOW()"
+    )
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Move an invoice from one status to another.
+/// Returns false if it wasn't in `from`.
+pub async fn set_invoice_status(pool: &MySqlPool, id: i64, from: &str, to: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE invoice SET status = ? WHERE id = ? AND status = ?")
+        .bind(to)
+        .bind(id)
+        .bind(from)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Mark a claimed invoice paid by the transaction `transaction_uuid`
+pub async fn complete_invoice(pool: &MySqlPool, id: i64, transaction_uuid: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE invoice SET status = 'paid', transaction_uuid = ? WHERE id = ? AND status = 'paying'"
+    )
+    .bind(transaction_uuid)
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
@@ -0,0 +1,645 @@
+//! Ledger operations that move money between accounts.
+//!
+//! Each operation runs in a single database transaction and locks the rows it
+//! touches with `SELECT ... FOR UPDATE`, so the balance check, debit, credit,
+//! tax and transaction log either all happen or none do.
+
+use std::collections::HashMap;
+use std::future::Future;
+
+use sqlx::mysql::{MySql, MySqlPool};
+use sqlx::Transaction;
+use crate::db::payroll::PayrollSource;
+use crate::utils::Amount;
+use crate::utils::errors::LedgerError;
+
+/// Times an operation is retried when MySQL picks it as a deadlock victim
+const MAX_DEADLOCK_RETRIES: u32 = 3;
+
+/// Outcome of a committed transfer
+pub struct TransferReceipt {
+    pub transaction_uuid: String,
+    pub tax_amount: Amount,
+}
+
+/// Outcome of a committed transfer to several receivers
+pub struct BatchTransferReceipt {
+    /// One per receiver, in the order they were given
+    pub transaction_uuids: Vec<String>,
+    pub tax_amount: Amount,
+}
+
+/// Move `amount` from sender to receiver, charging the currency's tax to the sender.
+/// The receiver account is created if it doesn't exist yet and must not be the
+/// sender's. The memo, if any, is stored on the transaction record.
+pub async fn transfer(
+    pool: &MySqlPool,
+    sender_discord_id: i64,
+    receiver_discord_id: i64,
+    currency_id: i64,
+    amount: Amount,
+    memo: Option<&str>,
+) -> Result<TransferReceipt, LedgerError> {
+    with_deadlock_retry(|| transfer_once(pool, sender_discord_id, receiver_discord_id, currency_id, amount, memo)).await
+}
+
+/// Pay several receivers in one transaction: all of them are paid or none are.
+/// Tax is charged to the sender on the total, and each receiver gets a transaction
+/// record of their own, all with the same memo. Receivers must be distinct and not
+/// the sender.
+pub async fn transfer_many(
+    pool: &MySqlPool,
+    sender_discord_id: i64,
+    currency_id: i64,
+    payments: &[(i64, Amount)],
+    memo: Option<&str>,
+) -> Result<BatchTransferReceipt, LedgerError> {
+    with_deadlock_retry(|| transfer_many_once(pool, sender_discord_id, currency_id, payments, memo)).await
+}
+
+/// Add `amount` (negative to burn) to a user's balance.
+/// Returns (previous balance, new balance).
+pub async fn mint(
+    pool: &MySqlPool,
+    discord_id: i64,
+    currency_id: i64,
+    amount: Amount,
+) -> Result<(Amount, Amount), LedgerError> {
+    with_deadlock_retry(|| mint_once(pool, discord_id, currency_id, amount)).await
+}
+
+/// Move `amount` (or everything when None) from the currency's tax account to a user.
+/// Returns the amount collected.
+pub async fn collect_tax(
+    pool: &MySqlPool,
+    discord_id: i64,
+    currency_id: i64,
+    amount: Option<Amount>,
+) -> Result<Amount, LedgerError> {
+    with_deadlock_retry(|| collect_tax_once(pool, discord_id, currency_id, amount)).await
+}
+
+/// Pay `amount` to a user from the currency's tax account or by minting it.
+/// The transaction is logged from the issuer's account, whose balance is not touched.
+/// Returns the transaction UUID.
+pub async fn payout(
+    pool: &MySqlPool,
+    issuer_discord_id: i64,
+    recipient_discord_id: i64,
+    currency_id: i64,
+    amount: Amount,
+    source: PayrollSource,
+) -> Result<String, LedgerError> {
+    with_deadlock_retry(|| payout_once(pool, issuer_discord_id, recipient_discord_id, currency_id, amount, source)).await
+}
+
+async fn transfer_once(
+    pool: &MySqlPool,
+    sender_discord_id: i64,
+    receiver_discord_id: i64,
+    currency_id: i64,
+    amount: Amount,
+    memo: Option<&str>,
+) -> Result<TransferReceipt, LedgerError> {
+    // One row read twice would have the credit overwrite the debit
+    if sender_discord_id == receiver_discord_id {
+        return Err(LedgerError::DuplicateAccount);
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // Lock both accounts in a fixed order so opposite transfers can't deadlock
+    let (sender, receiver) = if sender_discord_id < receiver_discord_id {
+        let sender = lock_account(&mut tx, sender_discord_id, currency_id, false).await?;
+        let receiver = lock_account(&mut tx, receiver_discord_id, currency_id, true).await?;
+        (sender, receiver)
+    } else {
+        let receiver = lock_account(&mut tx, receiver_discord_id, currency_id, true).await?;
+        let sender = lock_account(&mut tx, sender_discord_id, currency_id, false).await?;
+        (sender, receiver)
+    };
+    let (sender_account_id, sender_balance) = sender.ok_or(LedgerError::AccountNotFound)?;
+    let (receiver_account_id, receiver_balance) = receiver.ok_or(LedgerError::AccountNotFound)?;
+
+    let tax_percentage: i32 = sqlx::query_scalar(
+        "SELECT tax_percentage FROM tax_account WHERE currency_id = ?"
+    )
+    .bind(currency_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .unwrap_or(0);
+
+    let tax_amount = if tax_percentage > 0 {
+        amount.percent(tax_percentage)
+    } else {
+        Amount::ZERO
+    };
+    let total_deduction = amount + tax_amount;
+
+    // Returning early drops `tx`, which rolls it back
+    if sender_balance < total_deduction {
+        return Err(LedgerError::InsufficientBalance {
+            required: total_deduction,
+            available: sender_balance,
+        });
+    }
+
+    let receiver_new_balance = receiver_balance + amount;
+    if !receiver_new_balance.is_within_limit() {
+        return Err(LedgerError::BalanceLimit {
+            current: receiver_balance,
+            new_balance: receiver_new_balance,
+        });
+    }
+
+    set_balance(&mut tx, sender_account_id, sender_balance - total_deduction).await?;
+    set_balance(&mut tx, receiver_account_id, receiver_new_balance).await?;
+
+    if tax_amount.is_positive() {
+        sqlx::query("UPDATE tax_account SET balance = balance + ? WHERE currency_id = ?")
+            .bind(tax_amount)
+            .bind(currency_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let transaction_uuid = uuid::Uuid::new_v4().to_string();
+    sqlx::query("INSERT INTO transaction (uuid, sender_id, receiver_id, amount, memo) VALUES (?, ?, ?, ?, ?)")
+        .bind(&transaction_uuid)
+        .bind(sender_account_id)
+        .bind(receiver_account_id)
+        .bind(amount)
+        .bind(memo)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(TransferReceipt {
+        transaction_uuid,
+        tax_amount,
+    })
+}
+
+async fn transfer_many_once(
+    pool: &MySqlPool,
+    sender_discord_id: i64,
+    currency_id: i64,
+    payments: &[(i64, Amount)],
+    memo: Option<&str>,
+) -> Result<BatchTransferReceipt, LedgerError> {
+    let mut discord_ids: Vec<i64> = payments.iter().map(|(id, _)| *id).collect();
+    discord_ids.push(sender_discord_id);
+    discord_ids.sort_unstable();
+    if discord_ids.windows(2).any(|pair| pair[0] == pair[1]) {
+        return Err(LedgerError::DuplicateAccount);
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // Lock every account in Discord ID order so overlapping sends can't deadlock
+    let mut accounts = HashMap::new();
+    for discord_id in discord_ids {
+        let create = discord_id != sender_discord_id;
+        if let Some(account) = lock_account(&mut tx, discord_id, currency_id, create).await? {
+            accounts.insert(discord_id, account);
+        }
+    }
+    let (sender_account_id, sender_balance) = *accounts.get(&sender_discord_id).ok_or(LedgerError::AccountNotFound)?;
+
+    let tax_percentage: i32 = sqlx::query_scalar(
+        "SELECT tax_percentage FROM tax_account WHERE currency_id = ?"
+    )
+    .bind(currency_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .unwrap_or(0);
+
+    let total: Amount = payments.iter().map(|(_, amount)| *amount).sum();
+    let tax_amount = if tax_percentage > 0 {
+        total.percent(tax_percentage)
+    } else {
+        Amount::ZERO
+    };
+    let total_deduction = total + tax_amount;
+
+    // Returning early drops `tx`, which rolls it back
+    if sender_balance < total_deduction {
+        return Err(LedgerError::InsufficientBalance {
+            required: total_deduction,
+            available: sender_balance,
+        });
+    }
+
+    set_balance(&mut tx, sender_account_id, sender_balance - total_deduction).await?;
+
+    let mut transaction_uuids = Vec::with_capacity(payments.len());
+    for (receiver_discord_id, amount) in payments {
+        let (receiver_account_id, receiver_balance) =
+            *accounts.get(receiver_discord_id).ok_or(LedgerError::AccountNotFound)?;
+
+        let receiver_new_balance = receiver_balance + *amount;
+        if !receiver_new_balance.is_within_limit() {
+            return Err(LedgerError::BalanceLimit {
+                current: receiver_balance,
+                new_balance: receiver_new_balance,
+            });
+        }
+        set_balance(&mut tx, receiver_account_id, receiver_new_balance).await?;
+
+        let transaction_uuid = uuid::Uuid::new_v4().to_string();
+        sqlx::query("INSERT INTO transaction (uuid, sender_id, receiver_id, amount, memo) VALUES (?, ?, ?, ?, ?)")
+            .bind(&transaction_uuid)
+            .bind(sender_account_id)
+            .bind(receiver_account_id)
+            .bind(*amount)
+            .bind(memo)
+            .execute(&mut *tx)
+            .await?;
+        transaction_uuids.push(transaction_uuid);
+    }
+
+    if tax_amount.is_positive() {
+        sqlx::query("UPDATE tax_account SET balance = balance + ? WHERE currency_id = ?")
+            .bind(tax_amount)
+            .bind(currency_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(BatchTransferReceipt {
+        transaction_uuids,
+        tax_amount,
+    })
+}
+
+async fn mint_once(
+    pool: &MySqlPool,
+    discord_id: i64,
+    currency_id: i64,
+    amount: Amount,
+) -> Result<(Amount, Amount), LedgerError> {
+    let mut tx = pool.begin().await?;
+
+    let (account_id, current_balance) = lock_account(&mut tx, discord_id, currency_id, true)
+        .await?
+        .ok_or(LedgerError::AccountNotFound)?;
+
+    let new_balance = current_balance + amount;
+    if new_balance.is_negative() {
+        return Err(LedgerError::NegativeBalance { current: current_balance, new_balance });
+    }
+    if !new_balance.is_within_limit() {
+        return Err(LedgerError::BalanceLimit { current: current_balance, new_balance });
+    }
+
+    set_balance(&mut tx, account_id, new_balance).await?;
+    tx.commit().await?;
+
+    Ok((current_balance, new_balance))
+}
+
+async fn collect_tax_once(
+    pool: &MySqlPool,
+    discord_id: i64,
+    currency_id: i64,
+    amount: Option<Amount>,
+) -> Result<Amount, LedgerError> {
+    let mut tx = pool.begin().await?;
+
+    // Accounts before the tax row, the same order transfers use
+    let (account_id, account_balance) = lock_account(&mut tx, discord_id, currency_id, true)
+        .await?
+        .ok_or(LedgerError::AccountNotFound)?;
+
+    let tax_balance: Amount = sqlx::query_scalar(
+        "SELECT balance FROM tax_account WHERE currency_id = ? FOR UPDATE"
+    )
+    .bind(currency_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(LedgerError::TaxAccountNotFound)?;
+
+    let collect_amount = amount.unwrap_or(tax_balance);
+    if collect_amount > tax_balance {
+        return Err(LedgerError::InsufficientBalance {
+            required: collect_amount,
+            available: tax_balance,
+        });
+    }
+
+    let new_balance = account_balance + collect_amount;
+    if !new_balance.is_within_limit() {
+        return Err(LedgerError::BalanceLimit { current: account_balance, new_balance });
+    }
+
+    sqlx::query("UPDATE tax_account SET balance = ? WHERE currency_id = ?")
+        .bind(tax_balance - collect_amount)
+        .bind(currency_id)
+        .execute(&mut *tx)
+        .await?;
+    set_balance(&mut tx, account_id, new_balance).await?;
+
+    tx.commit().await?;
+
+    Ok(collect_amount)
+}
+
+async fn payout_once(
+    pool: &MySqlPool,
+    issuer_discord_id: i64,
+    recipient_discord_id: i64,
+    currency_id: i64,
+    amount: Amount,
+    source: PayrollSource,
+) -> Result<String, LedgerError> {
+    let mut tx = pool.begin().await?;
+
+    // Accounts in a fixed order, then the tax row, the same order transfers use
+    let (issuer, recipient) = if issuer_discord_id <= recipient_discord_id {
+        let issuer = lock_account(&mut tx, issuer_discord_id, currency_id, true).await?;
+        let recipient = lock_account(&mut tx, recipient_discord_id, currency_id, true).await?;
+        (issuer, recipient)
+    } else {
+        let recipient = lock_account(&mut tx, recipient_discord_id, currency_id, true).await?;
+        let issuer = lock_account(&mut tx, issuer_discord_id, currency_id, true).await?;
+        (issuer, recipient)
+    };
+    let (issuer_account_id, _) = issuer.ok_or(LedgerError::AccountNotFound)?;
+    let (recipient_account_id, recipient_balance) = recipient.ok_or(LedgerError::AccountNotFound)?;
+
+    let new_balance = recipient_balance + amount;
+    if !new_balance.is_within_limit() {
+        return Err(LedgerError::BalanceLimit { current: recipient_balance, new_balance });
+    }
+
+    if source == PayrollSource::Treasury {
+        let tax_balance: Amount = sqlx::query_scalar(
+            "SELECT balance FROM tax_account WHERE currency_id = ? FOR UPDATE"
+        )
+        .bind(currency_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(LedgerError::TaxAccountNotFound)?;
+
+        if tax_balance < amount {
+            return Err(LedgerError::InsufficientBalance {
+                required: amount,
+                available: tax_balance,
+            });
+        }
+
+        sqlx::query("UPDATE tax_account SET balance = ? WHERE currency_id = ?")
+            .bind(tax_balance - amount)
+            .bind(currency_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    set_balance(&mut tx, recipient_account_id, new_balance).await?;
+
+    let transaction_uuid = uuid::Uuid::new_v4().to_string();
+    sqlx::query("INSERT INTO transaction (uuid, sender_id, receiver_id, amount) VALUES (?, ?, ?, ?)")
+        .bind(&transaction_uuid)
+        .bind(issuer_account_id)
+        .bind(recipient_account_id)
+        .bind(amount)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(transaction_uuid)
+}
+
+/// Lock a user's account row for the rest of the transaction, returning (id, balance).
+/// With `create` set, a zero-balance account is inserted if missing.
+pub(super) async fn lock_account(
+    tx: &mut Transaction<'_, MySql>,
+    discord_id: i64,
+    currency_id: i64,
+    create: bool,
+) -> Result<Option<(i64, Amount)>, sqlx::Error> {
+    let select = "SELECT id, balance FROM account WHERE discord_id = ? AND currency_id = ? FOR UPDATE";
+
+    let account = sqlx::query_as::<_, (i64, Amount)>(select)
+        .bind(discord_id)
+        .bind(currency_id)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+    if account.is_some() || !create {
+        return Ok(account);
+    }
+
+    sqlx::query(
+        "INSERT INTO account (discord_id, currency_id, balance) VALUES (?, ?, 0)
+         ON DUPLICATE KEY UPDATE id = id"
+    )
+    .bind(discord_id)
+    .bind(currency_id)
+    .execute(&mut **tx)
+    .await?;
+
+    sqlx::query_as::<_, (i64, Amount)>(select)
+        .bind(discord_id)
+        .bind(currency_id)
+        .fetch_optional(&mut **tx)
+        .await
+}
+
+pub(super) async fn set_balance(
+    tx: &mut Transaction<'_, MySql>,
+    account_id: i64,
+    balance: Amount,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE account SET balance = ? WHERE id = ?")
+        .bind(balance)
+        .bind(account_id)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(())
+}
+
+/// Re-run an operation that MySQL rolled back to break a deadlock
+pub(super) async fn with_deadlock_retry<T, F, Fut>(mut operation: F) -> Result<T, LedgerError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, LedgerError>>,
+{
+    let mut attempt = 0;
+    loop {
+        match operation().await {
+            Err(LedgerError::Database(e)) if is_deadlock(&e) && attempt < MAX_DEADLOCK_RETRIES => {
+                attempt += 1;
+                tracing::warn!("Ledger deadlock, retrying (attempt {})", attempt);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// SQLSTATE 40001 is what MySQL reports for ER_LOCK_DEADLOCK
+fn is_deadlock(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Database(db_error) => db_error.code().as_deref() == Some("40001"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::mysql::MySqlPoolOptions;
+
+    const USERS: i64 = 6;
+    const SENDS: usize = 300;
+
+    async fn connect_test_db() -> MySqlPool {
+        let url = std::env::var("TEST_DATABASE_URL")
+            .expect("TEST_DATABASE_URL must point at a scratch MySQL database");
+        let pool = MySqlPoolOptions::new()
+            .max_connections(32)
+            .connect(&url)
+            .await
+            .expect("Failed to connect to test database");
+        super::super::migrate::run(&mut pool.acquire().await.unwrap()).await.unwrap();
+        pool
+    }
+
+    /// Create a throwaway currency with a tax account and `USERS` funded holders
+    async fn setup_currency(pool: &MySqlPool, tax_percentage: i32) -> (i64, Vec<i64>) {
+        let tag = uuid::Uuid::new_v4().simple().to_string();
+        let guild_id = rand::random::<u32>() as i64 + 1_000_000_000_000;
+        let currency_id = crate::db::currency::create_currency(
+            pool,
+            guild_id,
+            &format!("Ledger Test {}", &tag[..12]),
+            &format!("T{}", &tag[..8].to_uppercase()),
+        )
+        .await
+        .unwrap();
+        crate::db::tax::create_tax_account(pool, currency_id, tax_percentage).await.unwrap();
+
+        let users: Vec<i64> = (1..=USERS).map(|i| guild_id * 100 + i).collect();
+        for user in &users {
+            mint(pool, *user, currency_id, "1000".parse().unwrap()).await.unwrap();
+        }
+        (currency_id, users)
+    }
+
+    async fn total_supply(pool: &MySqlPool, currency_id: i64) -> Amount {
+        let accounts = crate::db::account::get_total_balance(pool, currency_id)
+            .await
+            .unwrap()
+            .unwrap_or(Amount::ZERO);
+        let tax = crate::db::tax::get_total_tax_balance(pool, currency_id)
+            .await
+            .unwrap()
+            .unwrap_or(Amount::ZERO);
+        accounts + tax
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    #[ignore = "requires a local MySQL database in TEST_DATABASE_URL"]
+    async fn test_parallel_sends_conserve_supply() {
+        let pool = connect_test_db().await;
+        let (currency_id, users) = setup_currency(&pool, 3).await;
+        let initial_supply = total_supply(&pool, currency_id).await;
+
+        let mut handles = Vec::new();
+        for i in 0..SENDS {
+            let pool = pool.clone();
+            let sender = users[i % users.len()];
+            let receiver = users[(i * 7 + 1) % users.len()];
+            let amount: Amount = format!("{}.37", i % 90 + 1).parse().unwrap();
+            handles.push(tokio::spawn(async move {
+                match transfer(&pool, sender, receiver, currency_id, amount, None).await {
+                    Ok(_) | Err(LedgerError::InsufficientBalance { .. }) => Ok(()),
+                    Err(e) => Err(e.to_string()),
+                }
+            }));
+        }
+        for handle in handles {
+            handle.await.unwrap().unwrap();
+        }
+
+        assert_eq!(total_supply(&pool, currency_id).await, initial_supply);
+
+        let negative: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM account WHERE currency_id = ? AND balance < 0"
+        )
+        .bind(currency_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(negative, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    #[ignore = "requires a local MySQL database in TEST_DATABASE_URL"]
+    async fn test_parallel_overdraft_is_rejected() {
+        let pool = connect_test_db().await;
+        let (currency_id, users) = setup_currency(&pool, 0).await;
+
+        // Ten concurrent sends of 400 from a 1000 balance: at most two may succeed
+        let mut handles = Vec::new();
+        for _ in 0..10 {
+            let pool = pool.clone();
+            let (sender, receiver) = (users[0], users[1]);
+            handles.push(tokio::spawn(async move {
+                transfer(&pool, sender, receiver, currency_id, "400".parse().unwrap(), None).await.is_ok()
+            }));
+        }
+        let mut succeeded = 0;
+        for handle in handles {
+            if handle.await.unwrap() {
+                succeeded += 1;
+            }
+        }
+        assert_eq!(succeeded, 2);
+
+        let balance = crate::db::account::get_account_balance(&pool, users[0], currency_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(balance, "200".parse().unwrap());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local MySQL database in TEST_DATABASE_URL"]
+    async fn test_mint_and_collect_tax() {
+        let pool = connect_test_db().await;
+        let (currency_id, users) = setup_currency(&pool, 10).await;
+
+        transfer(&pool, users[0], users[1], currency_id, "100".parse().unwrap(), None).await.unwrap();
+        let to_self = transfer(&pool, users[0], users[0], currency_id, "100".parse().unwrap(), None).await;
+        assert!(matches!(to_self, Err(LedgerError::DuplicateAccount)));
+        let collected = collect_tax(&pool, users[2], currency_id, None).await.unwrap();
+        assert_eq!(collected, "10".parse().unwrap());
+
+        let burned = mint(&pool, users[3], currency_id, "-1000.01".parse().unwrap()).await;
+        assert!(matches!(burned, Err(LedgerError::NegativeBalance { .. })));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local MySQL database in TEST_DATABASE_URL"]
+    async fn test_payout_from_treasury() {
+        let pool = connect_test_db().await;
+        let (currency_id, users) = setup_currency(&pool, 10).await;
+
+        transfer(&pool, users[0], users[1], currency_id, "100".parse().unwrap(), None).await.unwrap();
+        payout(&pool, users[2], users[3], currency_id, "6".parse().unwrap(), PayrollSource::Treasury).await.unwrap();
+        let short = payout(&pool, users[2], users[3], currency_id, "6".parse().unwrap(), PayrollSource::Treasury).await;
+        assert!(matches!(short, Err(LedgerError::InsufficientBalance { .. })));
+
+        let balance = crate::db::account::get_account_balance(&pool, users[3], currency_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(balance, "1006".parse().unwrap());
+    }
+}
@@ -0,0 +1,462 @@
+//! Versioned schema migrations.
+//!
+//! Numbered migrations in `migrations/` run once each, in order, and are recorded in
+//! the `schema_migration` table with a SHA-256 checksum. Editing a migration that has
+//! already been applied is an error - add a new numbered migration instead.
+//!
+//! `migrations/procedures.sql` is repeatable: every procedure in it is dropped and
+//! recreated, so it is re-run after the numbered migrations whenever its checksum changes.
+//!
+//! Files may use the mysql client's `DELIMITER` directive, so stored procedure bodies
+//! containing `;` can be written the same way they would be loaded by hand.
+
+use sha2::{Digest, Sha256};
+use sqlx::mysql::MySqlConnection;
+use sqlx::Row;
+use tracing::info;
+use crate::utils::errors::MigrationError;
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+/// Numbered migrations, oldest first. Append only.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "0001_initial_schema",
+        sql: include_str!("../../migrations/0001_initial_schema.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "0002_swap_expiry",
+        sql: include_str!("../../migrations/0002_swap_expiry.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "0003_swap_partial_fills",
+        sql: include_str!("../../migrations/0003_swap_partial_fills.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "0004_order_book",
+        sql: include_str!("../../migrations/0004_order_book.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "0005_price_candles",
+        sql: include_str!("../../migrations/0005_price_candles.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "0006_recurring_payments",
+        sql: include_str!("../../migrations/0006_recurring_payments.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "0007_payroll",
+        sql: include_str!("../../migrations/0007_payroll.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "0008_transaction_memo",
+        sql: include_str!("../../migrations/0008_transaction_memo.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "0009_invoices",
+        sql: include_str!("../../migrations/0009_invoices.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "0010_wire_journal",
+        sql: include_str!("../../migrations/0010_wire_journal.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "0011_wire_rates",
+        sql: include_str!("../../migrations/0011_wire_rates.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "0012_bridge_providers",
+        sql: include_str!("../../migrations/0012_bridge_providers.sql"),
+    },
+];
+
+const PROCEDURES_NAME: &str = "procedures";
+const PROCEDURES_SQL: &str = include_str!("../../migrations/procedures.sql");
+
+/// Held while migrating so two bot instances starting together don't race
+const LOCK_NAME: &str = "smite_schema_migration";
+const LOCK_TIMEOUT_SECONDS: i64 = 60;
+
+const CREATE_TRACKING_TABLE: &str = "CREATE TABLE IF NOT EXISTS schema_migration (
+    name VARCHAR(128) PRIMARY KEY,
+    version BIGINT NULL UNIQUE,
+    checksum CHAR(64) NOT NULL,
+    applied_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+)";
+
+/// Where a migration stands relative to the database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationState {
+    /// Applied at the given time
+    Applied(String),
+    Pending,
+    /// Applied, but the file has changed since (an error for numbered migrations)
+    Modified(String),
+    /// Recorded in the database but not known to this build
+    Unknown,
+}
+
+impl std::fmt::Display for MigrationState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationState::Applied(at) => write!(f, "applied {}", at),
+            MigrationState::Pending => write!(f, "pending"),
+            MigrationState::Modified(at) => write!(f, "modified since applied {}", at),
+            MigrationState::Unknown => write!(f, "unknown to this build"),
+        }
+    }
+}
+
+/// One row recorded in `schema_migration`
+pub(super) struct AppliedMigration {
+    pub name: String,
+    pub version: Option<i64>,
+    pub checksum: String,
+    pub applied_at: String,
+}
+
+/// Apply every pending migration, then the stored procedures if they changed.
+/// Stops at the first failing statement.
+pub async fn run(conn: &mut MySqlConnection) -> Result<(), MigrationError> {
+    let locked: Option<i64> = sqlx::query_scalar("SELECT GET_LOCK(?, ?)")
+        .bind(LOCK_NAME)
+        .bind(LOCK_TIMEOUT_SECONDS)
+        .fetch_one(&mut *conn)
+        .await?;
+    if locked != Some(1) {
+        return Err(MigrationError::Locked);
+    }
+
+    let result = apply_pending(conn).await;
+
+    sqlx::query("SELECT RELEASE_LOCK(?)")
+        .bind(LOCK_NAME)
+        .execute(&mut *conn)
+        .await?;
+
+    result
+}
+
+async fn apply_pending(conn: &mut MySqlConnection) -> Result<(), MigrationError> {
+    sqlx::raw_sql(CREATE_TRACKING_TABLE).execute(&mut *conn).await?;
+    let applied = fetch_applied(conn).await?;
+
+    // Refuse to touch a database whose history doesn't match this build
+    for row in &applied {
+        let Some(version) = row.version else { continue };
+        match MIGRATIONS.iter().find(|m| m.version == version) {
+            Some(migration) if checksum(migration.sql) != row.checksum => {
+                return Err(MigrationError::ChecksumMismatch(row.name.clone()));
+            }
+            Some(_) => {}
+            None => return Err(MigrationError::UnknownVersion(row.name.clone())),
+        }
+    }
+
+    for migration in MIGRATIONS {
+        if applied.iter().any(|row| row.version == Some(migration.version)) {
+            continue;
+        }
+
+        info!("Applying migration {}...", migration.name);
+        execute_script(conn, migration.name, migration.sql).await?;
+
+        sqlx::query("INSERT INTO schema_migration (name, version, checksum) VALUES (?, ?, ?)")
+            .bind(migration.name)
+            .bind(migration.version)
+            .bind(checksum(migration.sql))
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    let procedures_checksum = checksum(PROCEDURES_SQL);
+    let procedures_current = applied
+        .iter()
+        .any(|row| row.name == PROCEDURES_NAME && row.checksum == procedures_checksum);
+
+    if !procedures_current {
+        info!("Loading stored procedures...");
+        execute_script(conn, PROCEDURES_NAME, PROCEDURES_SQL).await?;
+
+        sqlx::query(
+            "INSERT INTO schema_migration (name, version, checksum) VALUES (?, NULL, ?) \
+             ON DUPLICATE KEY UPDATE checksum = VALUES(checksum), applied_at = CURRENT_TIMESTAMP"
+        )
+        .bind(PROCEDURES_NAME)
+        .bind(&procedures_checksum)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// State of every known migration plus anything recorded that this build doesn't know.
+/// Read-only: nothing is created or applied.
+pub async fn status(conn: &mut MySqlConnection) -> Result<Vec<(String, MigrationState)>, MigrationError> {
+    let tracked: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM information_schema.TABLES \
+         WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'schema_migration'"
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let applied = if tracked > 0 { fetch_applied(conn).await? } else { Vec::new() };
+
+    let state_of = |name: &str, sql: &str| match applied.iter().find(|row| row.name == name) {
+        Some(row) if row.checksum == checksum(sql) => MigrationState::Applied(row.applied_at.clone()),
+        Some(row) => MigrationState::Modified(row.applied_at.clone()),
+        None => MigrationState::Pending,
+    };
+
+    let mut states: Vec<(String, MigrationState)> = MIGRATIONS
+        .iter()
+        .map(|m| (m.name.to_string(), state_of(m.name, m.sql)))
+        .collect();
+    states.push((PROCEDURES_NAME.to_string(), state_of(PROCEDURES_NAME, PROCEDURES_SQL)));
+
+    for row in &applied {
+        if !states.iter().any(|(name, _)| *name == row.name) {
+            states.push((row.name.clone(), MigrationState::Unknown));
+        }
+    }
+
+    Ok(states)
+}
+
+async fn fetch_applied(conn: &mut MySqlConnection) -> Result<Vec<AppliedMigration>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT name, version, checksum, DATE_FORMAT(applied_at, '%Y-%m-%d %H:%i:%s') \
+         FROM schema_migration ORDER BY version IS NULL, version"
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| AppliedMigration {
+            name: row.get(0),
+            version: row.get(1),
+            checksum: row.get(2),
+            applied_at: row.get(3),
+        })
+        .collect())
+}
+
+async fn execute_script(conn: &mut MySqlConnection, name: &str, sql: &str) -> Result<(), MigrationError> {
+    for (index, statement) in split_statements(sql).iter().enumerate() {
+        sqlx::raw_sql(statement)
+            .execute(&mut *conn)
+            .await
+            .map_err(|source| MigrationError::Statement {
+                migration: name.to_string(),
+                statement: index + 1,
+                source,
+            })?;
+    }
+    Ok(())
+}
+
+/// Hex SHA-256 of a migration file. Line endings are normalised so a checkout
+/// with CRLF endings doesn't look modified.
+pub fn checksum(sql: &str) -> String {
+    hex::encode(Sha256::digest(sql.replace("\r\n", "\n").as_bytes()))
+}
+
+/// Split a script into statements the way the mysql client does: on the current
+/// delimiter (`;` until a `DELIMITER` line changes it), ignoring delimiters inside
+/// quotes and comments. Comments are dropped from the output.
+pub fn split_statements(sql: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut delimiter = ";".to_string();
+    let mut current = String::new();
+    let mut rest = sql;
+
+    while let Some(c) = rest.chars().next() {
+        // DELIMITER is a client directive, only recognised at the start of a statement
+        if current.trim().is_empty() && starts_with_ignore_case(rest, "DELIMITER") {
+            let line_end = rest.find('\n').unwrap_or(rest.len());
+            let new_delimiter = rest["DELIMITER".len()..line_end].trim();
+            if !new_delimiter.is_empty() && rest["DELIMITER".len()..].starts_with(char::is_whitespace) {
+                delimiter = new_delimiter.to_string();
+                current.clear();
+                rest = &rest[line_end..];
+                continue;
+            }
+        }
+
+        if rest.starts_with(delimiter.as_str()) {
+            push_statement(&mut statements, &current);
+            current.clear();
+            rest = &rest[delimiter.len()..];
+            continue;
+        }
+
+        match c {
+            '\'' | '"' | '`' => {
+                let end = quoted_len(rest, c);
+                current.push_str(&rest[..end]);
+                rest = &rest[end..];
+            }
+            '-' if rest.starts_with("--") && rest[2..].chars().next().is_none_or(char::is_whitespace) => {
+                // Keep the newline so the statement text keeps its line structure
+                rest = &rest[rest.find('\n').unwrap_or(rest.len())..];
+            }
+            '/' if rest.starts_with("/*") => {
+                rest = rest[2..].find("*/").map_or("", |end| &rest[end + 4..]);
+            }
+            _ => {
+                current.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    push_statement(&mut statements, &current);
+    statements
+}
+
+fn push_statement(statements: &mut Vec<String>, statement: &str) {
+    let statement = statement.trim();
+    if !statement.is_empty() {
+        statements.push(statement.to_string());
+    }
+}
+
+fn starts_with_ignore_case(text: &str, prefix: &str) -> bool {
+    text.get(..prefix.len()).is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+}
+
+/// Byte length of the quoted string at the start of `text`, including both quotes.
+/// Handles backslash escapes and doubled quotes; runs to the end if unterminated.
+fn quoted_len(text: &str, quote: char) -> usize {
+    let mut chars = text.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' && quote != '`' {
+            chars.next();
+        } else if c == quote {
+            // A doubled quote is an escaped quote, not the end
+            if text[i + 1..].starts_with(quote) {
+                chars.next();
+            } else {
+                return i + 1;
+            }
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_on_semicolons() {
+        let sql = "-- comment; not a statement\nCREATE TABLE a (id INT);\n\n/* block; */ INSERT INTO a VALUES (1);\nSELECT 1";
+        assert_eq!(
+            split_statements(sql),
+            vec!["CREATE TABLE a (id INT)", "INSERT INTO a VALUES (1)", "SELECT 1"]
+        );
+    }
+
+    #[test]
+    fn test_split_ignores_delimiters_in_quotes() {
+        let sql = "INSERT INTO a VALUES ('x;y', \"it\\\"s;\", 'don''t;');SELECT `odd;name` FROM a;";
+        assert_eq!(
+            split_statements(sql),
+            vec![
+                "INSERT INTO a VALUES ('x;y', \"it\\\"s;\", 'don''t;')",
+                "SELECT `odd;name` FROM a",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_with_delimiter_directive() {
+        let sql = "DELIMITER //\n\nDROP PROCEDURE IF EXISTS p //\n\nCREATE PROCEDURE p()\nBEGIN\n    -- inner comment\n    SELECT 1;\n    SELECT 2;\nEND //\n\ndelimiter ;\n\nSELECT 3;";
+        let statements = split_statements(sql);
+        assert_eq!(statements.len(), 3);
+        assert_eq!(statements[0], "DROP PROCEDURE IF EXISTS p");
+        assert!(statements[1].starts_with("CREATE PROCEDURE p()"));
+        assert!(statements[1].contains("SELECT 1;\n    SELECT 2;"));
+        assert!(statements[1].ends_with("END"));
+        assert!(!statements[1].contains("inner comment"));
+        assert_eq!(statements[2], "SELECT 3");
+    }
+
+    #[test]
+    fn test_migration_files_split() {
+        for migration in MIGRATIONS {
+            let statements = split_statements(migration.sql);
+            assert!(!statements.is_empty(), "{} has no statements", migration.name);
+            assert!(statements.iter().all(|s| !s.to_uppercase().starts_with("DELIMITER")));
+        }
+
+        // Retired procedures are only dropped; every other one is dropped before it is created
+        let procedures = split_statements(PROCEDURES_SQL);
+        let name_after = |statement: &str, prefix: &str| {
+            statement[prefix.len()..].trim_start().split(|c: char| c == '(' || c.is_whitespace()).next().map(str::to_string)
+        };
+        let mut dropped = Vec::new();
+        let mut creates = 0;
+        for statement in &procedures {
+            if statement.starts_with("DROP PROCEDURE IF EXISTS") {
+                dropped.extend(name_after(statement, "DROP PROCEDURE IF EXISTS"));
+            } else if statement.starts_with("CREATE PROCEDURE") {
+                let name = name_after(statement, "CREATE PROCEDURE").unwrap();
+                assert!(dropped.contains(&name), "{} must be dropped before it is created", name);
+                creates += 1;
+            }
+        }
+        assert!(creates > 0);
+        assert!(procedures.iter().all(|s| s.starts_with("CREATE PROCEDURE") || s.starts_with("DROP PROCEDURE")));
+    }
+
+    #[test]
+    fn test_migration_versions_are_sequential() {
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, index as i64 + 1);
+            assert!(migration.name.starts_with(&format!("{:04}_", migration.version)));
+        }
+    }
+
+    #[test]
+    fn test_checksum_ignores_line_endings() {
+        assert_eq!(checksum("SELECT 1;\nSELECT 2;"), checksum("SELECT 1;\r\nSELECT 2;"));
+        assert_ne!(checksum("SELECT 1;"), checksum("SELECT 2;"));
+        assert_eq!(checksum("").len(), 64);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local MySQL database in TEST_DATABASE_URL"]
+    async fn test_run_is_idempotent() {
+        use sqlx::Connection;
+
+        let url = std::env::var("TEST_DATABASE_URL")
+            .expect("TEST_DATABASE_URL must point at a scratch MySQL database");
+        let mut conn = MySqlConnection::connect(&url).await.unwrap();
+
+        run(&mut conn).await.unwrap();
+        run(&mut conn).await.unwrap();
+
+        let states = status(&mut conn).await.unwrap();
+        assert_eq!(states.len(), MIGRATIONS.len() + 1);
+        assert!(states.iter().all(|(_, state)| matches!(state, MigrationState::Applied(_))));
+    }
+}
@@ -1,5 +1,9 @@
-use sqlx::mysql::MySqlPool;
-use tracing::{info, warn, debug};
+use sqlx::mysql::{MySqlConnection, MySqlPool};
+use sqlx::sqlite::SqlitePool;
+use sqlx::Connection;
+use tracing::{info, warn};
+use crate::utils::errors::MigrationError;
+use migrate::MigrationState;
 
 pub mod currency;
 pub mod account;
@@ -8,59 +12,109 @@ pub mod transaction;
 pub mod tradelog;
 pub mod tax;
 pub mod api;
+pub mod ledger;
+pub mod migrate;
+pub mod orderbook;
+pub mod candle;
+pub mod filter;
+pub mod recurring;
+pub mod payroll;
+pub mod invoice;
+pub mod wire;
+pub mod sqlite;
 
-/// Initialize the MySQL connection pool and create tables
-pub async fn init_db() -> Result<MySqlPool, sqlx::Error> {
-    let database_url = std::env::var("DATABASE_URL")
-        .expect("DATABASE_URL not set in .env file");
+/// Shown by commands that read MySQL-only tables when the bot runs on SQLite
+pub const MYSQL_ONLY: &str = "This command is not supported on SQLite. It needs the MySQL backend.";
 
-    let pool = MySqlPool::connect(&database_url).await?;
+/// The database `DATABASE_URL` points at
+pub enum Backend {
+    MySql(MySqlPool),
+    Sqlite(SqlitePool),
+}
+
+/// Apply pending migrations and connect to the database in `DATABASE_URL`,
+/// which is SQLite for a `sqlite:` URL and MySQL otherwise.
+/// Any migration failure is returned so the bot never runs on a half-built schema.
+pub async fn init_db() -> Result<Backend, MigrationError> {
+    let database_url = database_url();
+
+    if sqlite::is_sqlite_url(&database_url) {
+        return Ok(Backend::Sqlite(sqlite::open(&database_url).await?));
+    }
+
+    run_migrations(&database_url).await?;
 
-    // Create all tables
-    create_tables(&pool).await?;
+    let pool = MySqlPool::connect(&database_url).await?;
+    
+    match candle::needs_backfill(&pool).await {
+        Ok(true) => warn!("Price candles are empty but trades exist; run with --backfill-candles to load price history"),
+        Ok(false) => {}
+        Err(e) => warn!("Failed to check price candles: {}", e),
+    }
     
     // Initialize API types
     if let Err(e) = initialize_api_types(&pool).await {
         warn!("Failed to initialize API types: {}", e);
     }
 
-    Ok(pool)
+    Ok(Backend::MySql(pool))
 }
 
-/// Read and execute SQL file for creating tables
-async fn execute_sql_file(pool: &MySqlPool, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let sql_content = std::fs::read_to_string(file_path)
-        .map_err(|e| format!("Failed to read {}: {}", file_path, e))?;
-
-    // For create_tables.sql, split by semicolon
-    for statement in sql_content.split(';') {
-        let trimmed = statement.trim();
-        if !trimmed.is_empty() && !trimmed.starts_with("--") {
-            match sqlx::raw_sql(trimmed).execute(pool).await {
-                Ok(_) => {},
-                Err(e) => {
-                    // Log but don't fail - tables might already exist
-                    debug!("Database schema note: {}", e);
-                }
-            }
-        }
-    }
+fn database_url() -> String {
+    std::env::var("DATABASE_URL")
+        .expect("DATABASE_URL not set in .env file")
+}
 
+/// Migrations run on their own connection so session settings a migration
+/// changes (e.g. FOREIGN_KEY_CHECKS) never end up in the pool
+async fn run_migrations(database_url: &str) -> Result<(), MigrationError> {
+    info!("Checking database migrations...");
+    let mut conn = MySqlConnection::connect(database_url).await?;
+    migrate::run(&mut conn).await?;
+    conn.close().await?;
+    info!("Database schema is up to date");
     Ok(())
 }
 
-/// Create all database tables
-async fn create_tables(pool: &MySqlPool) -> Result<(), sqlx::Error> {
-    info!("Initializing database schema...");
-    
-    // Create tables from SQL file
-    if let Err(e) = execute_sql_file(pool, "migrations/create_tables.sql").await {
-        warn!("Failed to create tables: {}", e);
-    } else {
-        info!("Tables initialized successfully");
+/// Apply pending migrations without starting the bot
+pub async fn migrate_only() -> Result<(), MigrationError> {
+    let database_url = database_url();
+    if sqlite::is_sqlite_url(&database_url) {
+        sqlite::open(&database_url).await?.close().await;
+        return Ok(());
+    }
+    run_migrations(&database_url).await
+}
+
+/// Apply pending migrations, then rebuild the price candles from the trade history.
+/// Returns the number of candles written.
+pub async fn backfill_candles() -> Result<usize, MigrationError> {
+    let database_url = database_url();
+    if sqlite::is_sqlite_url(&database_url) {
+        return Err(MigrationError::MySqlOnly("Price candles"));
     }
+    run_migrations(&database_url).await?;
 
-    Ok(())
+    let pool = MySqlPool::connect(&database_url).await?;
+    let written = candle::backfill(&pool).await?;
+    pool.close().await;
+    Ok(written)
+}
+
+/// State of every migration, without applying anything
+pub async fn migration_status() -> Result<Vec<(String, MigrationState)>, MigrationError> {
+    let database_url = database_url();
+    if sqlite::is_sqlite_url(&database_url) {
+        let pool = sqlite::connect(&database_url).await?;
+        let states = sqlite::status(&pool).await?;
+        pool.close().await;
+        return Ok(states);
+    }
+
+    let mut conn = MySqlConnection::connect(&database_url).await?;
+    let states = migrate::status(&mut conn).await?;
+    conn.close().await?;
+    Ok(states)
 }
 
 /// Initialize default API types (UnbelievaBoat, etc.)
@@ -0,0 +1,593 @@
+//! Central limit order book.
+//!
+//! Orders are kept per canonical pair (see `tradelog::normalize_pair`) with prices
+//! in quote currency per unit of base. Placing an order moves its full cost into
+//! escrow, then matches it against the opposite side with price-time priority:
+//! best price first, oldest order first at the same price, always trading at the
+//! resting order's price. Whatever doesn't fill rests on the book until it is
+//! filled or cancelled.
+//!
+//! Matching runs in one database transaction with the touched orders and accounts
+//! locked, like the other ledger operations.
+
+use sqlx::mysql::{MySql, MySqlPool};
+use sqlx::Transaction;
+use crate::utils::Amount;
+use crate::utils::errors::LedgerError;
+use super::candle;
+use super::ledger::{lock_account, set_balance, with_deadlock_retry};
+
+/// Most fills a single order takes in one go; anything left rests on the book
+const MAX_FILLS_PER_ORDER: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderSide {
+    /// Buy the base currency, paying the quote currency
+    Buy,
+    /// Sell the base currency for the quote currency
+    Sell,
+}
+
+impl OrderSide {
+    pub fn parse(side: &str) -> Option<OrderSide> {
+        match side.to_lowercase().as_str() {
+            "buy" | "bid" => Some(OrderSide::Buy),
+            "sell" | "ask" => Some(OrderSide::Sell),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderSide::Buy => "buy",
+            OrderSide::Sell => "sell",
+        }
+    }
+
+    pub fn opposite(&self) -> OrderSide {
+        match self {
+            OrderSide::Buy => OrderSide::Sell,
+            OrderSide::Sell => OrderSide::Buy,
+        }
+    }
+}
+
+/// Outcome of placing an order
+pub struct OrderReceipt {
+    pub order_id: i64,
+    /// Base currency traded by the fills made while placing the order
+    pub filled_quantity: Amount,
+    /// Quote currency paid (buy) or received (sell) by those fills
+    pub quote_amount: Amount,
+    pub fills: usize,
+    /// "open" if part of the order is resting on the book, otherwise "filled"
+    pub status: String,
+}
+
+/// An open order being matched
+struct BookOrder {
+    id: i64,
+    discord_id: i64,
+    /// Account holding the order's escrow
+    account_id: i64,
+    price: Amount,
+    remaining: Amount,
+    escrow_remaining: Amount,
+}
+
+/// Quantity and quote amount for a fill between two orders at `price`.
+/// The quote amount is rounded down so a bid's fills never cost more than its escrow,
+/// which was rounded up.
+pub fn fill_terms(incoming_remaining: Amount, resting_remaining: Amount, price: Amount) -> Option<(Amount, Amount)> {
+    let quantity = incoming_remaining.min(resting_remaining);
+    quantity.checked_mul_floor(price).map(|quote_amount| (quantity, quote_amount))
+}
+
+/// Place a limit order and match it against the book.
+pub async fn place_order(
+    pool: &MySqlPool,
+    discord_id: i64,
+    base_currency_id: i64,
+    quote_currency_id: i64,
+    side: OrderSide,
+    price: Amount,
+    quantity: Amount,
+) -> Result<OrderReceipt, LedgerError> {
+    with_deadlock_retry(|| {
+        place_order_once(pool, discord_id, base_currency_id, quote_currency_id, side, price, quantity)
+    })
+    .await
+}
+
+/// Cancel an open order and return its remaining escrow to the owner.
+/// Returns the amount refunded.
+pub async fn cancel_order(
+    pool: &MySqlPool,
+    discord_id: i64,
+    order_id: i64,
+) -> Result<Amount, LedgerError> {
+    with_deadlock_retry(|| cancel_order_once(pool, discord_id, order_id)).await
+}
+
+async fn place_order_once(
+    pool: &MySqlPool,
+    discord_id: i64,
+    base_currency_id: i64,
+    quote_currency_id: i64,
+    side: OrderSide,
+    price: Amount,
+    quantity: Amount,
+) -> Result<OrderReceipt, LedgerError> {
+    // Bids escrow their cost rounded up, asks escrow the base they sell
+    let (escrow, escrow_currency_id) = match side {
+        OrderSide::Buy => (
+            quantity.checked_mul_ceil(price).ok_or(LedgerError::AmountTooLarge)?,
+            quote_currency_id,
+        ),
+        OrderSide::Sell => (quantity, base_currency_id),
+    };
+    if !escrow.is_within_limit() {
+        return Err(LedgerError::AmountTooLarge);
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let (account_id, balance) = lock_account(&mut tx, discord_id, escrow_currency_id, false)
+        .await?
+        .ok_or(LedgerError::AccountNotFound)?;
+
+    // Returning early drops `tx`, which rolls it back
+    if balance < escrow {
+        return Err(LedgerError::InsufficientBalance {
+            required: escrow,
+            available: balance,
+        });
+    }
+    set_balance(&mut tx, account_id, balance - escrow).await?;
+
+    let result = sqlx::query(
+        "INSERT INTO limit_order (discord_id, account_id, base_currency_id, quote_currency_id, side, price, quantity, escrow_remaining)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(discord_id)
+    .bind(account_id)
+    .bind(base_currency_id)
+    .bind(quote_currency_id)
+    .bind(side.as_str())
+    .bind(price)
+    .bind(quantity)
+    .bind(escrow)
+    .execute(&mut *tx)
+    .await?;
+
+    let mut incoming = BookOrder {
+        id: result.last_insert_id() as i64,
+        discord_id,
+        account_id,
+        price,
+        remaining: quantity,
+        escrow_remaining: escrow,
+    };
+
+    let mut filled_quantity = Amount::ZERO;
+    let mut quote_total = Amount::ZERO;
+    let mut fills = 0;
+
+    while incoming.remaining.is_positive() && fills < MAX_FILLS_PER_ORDER {
+        let Some(mut resting) = next_match(&mut tx, base_currency_id, quote_currency_id, side, price, discord_id).await? else {
+            break;
+        };
+
+        // Trades happen at the resting order's price
+        let trade_price = resting.price;
+        let (fill_quantity, quote_amount) = fill_terms(incoming.remaining, resting.remaining, trade_price)
+            .ok_or(LedgerError::AmountTooLarge)?;
+
+        let (bid, ask) = match side {
+            OrderSide::Buy => (&mut incoming, &mut resting),
+            OrderSide::Sell => (&mut resting, &mut incoming),
+        };
+        settle_fill(&mut tx, base_currency_id, quote_currency_id, bid, ask, trade_price, fill_quantity, quote_amount).await?;
+
+        save_order(&mut tx, &resting).await?;
+
+        filled_quantity = filled_quantity + fill_quantity;
+        quote_total = quote_total + quote_amount;
+        fills += 1;
+    }
+
+    save_order(&mut tx, &incoming).await?;
+    tx.commit().await?;
+
+    Ok(OrderReceipt {
+        order_id: incoming.id,
+        filled_quantity,
+        quote_amount: quote_total,
+        fills,
+        status: if incoming.remaining.is_positive() { "open" } else { "filled" }.to_string(),
+    })
+}
+
+/// Best resting order on the other side that crosses `limit_price`, locked for update.
+/// The caller's own orders are skipped so nobody trades with themselves.
+async fn next_match(
+    tx: &mut Transaction<'_, MySql>,
+    base_currency_id: i64,
+    quote_currency_id: i64,
+    side: OrderSide,
+    limit_price: Amount,
+    discord_id: i64,
+) -> Result<Option<BookOrder>, sqlx::Error> {
+    let (price_condition, order_by) = match side {
+        OrderSide::Buy => ("price <= ?", "price ASC, id ASC"),
+        OrderSide::Sell => ("price >= ?", "price DESC, id ASC"),
+    };
+
+    let sql = format!(
+        "SELECT id, discord_id, account_id, price, quantity - filled_quantity, escrow_remaining
+         FROM limit_order
+         WHERE base_currency_id = ? AND quote_currency_id = ? AND side = ? AND status = 'open'
+           AND discord_id != ? AND {}
+         ORDER BY {}
+         LIMIT 1
+         FOR UPDATE",
+        price_condition, order_by
+    );
+
+    let row = sqlx::query_as::<_, (i64, i64, i64, Amount, Amount, Amount)>(&sql)
+        .bind(base_currency_id)
+        .bind(quote_currency_id)
+        .bind(side.opposite().as_str())
+        .bind(discord_id)
+        .bind(limit_price)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+    Ok(row.map(|(id, discord_id, account_id, price, remaining, escrow_remaining)| BookOrder {
+        id,
+        discord_id,
+        account_id,
+        price,
+        remaining,
+        escrow_remaining,
+    }))
+}
+
+/// Pay out one fill from both orders' escrow and record it
+#[allow(clippy::too_many_arguments)]
+async fn settle_fill(
+    tx: &mut Transaction<'_, MySql>,
+    base_currency_id: i64,
+    quote_currency_id: i64,
+    bid: &mut BookOrder,
+    ask: &mut BookOrder,
+    price: Amount,
+    quantity: Amount,
+    quote_amount: Amount,
+) -> Result<(), LedgerError> {
+    bid.remaining = bid.remaining - quantity;
+    bid.escrow_remaining = bid.escrow_remaining - quote_amount;
+    ask.remaining = ask.remaining - quantity;
+    ask.escrow_remaining = ask.escrow_remaining - quantity;
+
+    // The bidder receives base, the asker receives quote
+    let bid_base_account = credit(tx, bid.discord_id, base_currency_id, quantity).await?;
+    let ask_quote_account = credit(tx, ask.discord_id, quote_currency_id, quote_amount).await?;
+
+    sqlx::query("INSERT INTO transaction (uuid, sender_id, receiver_id, amount) VALUES (?, ?, ?, ?), (?, ?, ?, ?)")
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(bid.account_id)
+        .bind(ask_quote_account)
+        .bind(quote_amount)
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(ask.account_id)
+        .bind(bid_base_account)
+        .bind(quantity)
+        .execute(&mut **tx)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO order_fill (bid_order_id, ask_order_id, price, quantity, quote_amount) VALUES (?, ?, ?, ?, ?)"
+    )
+    .bind(bid.id)
+    .bind(ask.id)
+    .bind(price)
+    .bind(quantity)
+    .bind(quote_amount)
+    .execute(&mut **tx)
+    .await?;
+
+    // Same tables `$price` reads, so book trades show up in prices and charts
+    sqlx::query("INSERT INTO tradelog (base_currency_id, quote_currency_id, price) VALUES (?, ?, ?)")
+        .bind(base_currency_id)
+        .bind(quote_currency_id)
+        .bind(price)
+        .execute(&mut **tx)
+        .await?;
+    candle::record_trade(&mut **tx, base_currency_id, quote_currency_id, price, quantity, quote_amount).await?;
+
+    Ok(())
+}
+
+/// Add `amount` to a user's account, creating it if needed. Returns the account ID.
+async fn credit(
+    tx: &mut Transaction<'_, MySql>,
+    discord_id: i64,
+    currency_id: i64,
+    amount: Amount,
+) -> Result<i64, LedgerError> {
+    let (account_id, balance) = lock_account(tx, discord_id, currency_id, true)
+        .await?
+        .ok_or(LedgerError::AccountNotFound)?;
+
+    let new_balance = balance + amount;
+    if !new_balance.is_within_limit() {
+        return Err(LedgerError::BalanceLimit { current: balance, new_balance });
+    }
+
+    set_balance(tx, account_id, new_balance).await?;
+    Ok(account_id)
+}
+
+/// Write an order's progress back. A fully filled order is closed and any escrow
+/// left over from price improvement or rounding goes back to its owner.
+async fn save_order(tx: &mut Transaction<'_, MySql>, order: &BookOrder) -> Result<(), sqlx::Error> {
+    let filled = !order.remaining.is_positive();
+
+    if filled && order.escrow_remaining.is_positive() {
+        sqlx::query("UPDATE account SET balance = balance + ? WHERE id = ?")
+            .bind(order.escrow_remaining)
+            .bind(order.account_id)
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    sqlx::query(
+        "UPDATE limit_order SET filled_quantity = quantity - ?, escrow_remaining = ?, status = ? WHERE id = ?"
+    )
+    .bind(order.remaining)
+    .bind(if filled { Amount::ZERO } else { order.escrow_remaining })
+    .bind(if filled { "filled" } else { "open" })
+    .bind(order.id)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+async fn cancel_order_once(
+    pool: &MySqlPool,
+    discord_id: i64,
+    order_id: i64,
+) -> Result<Amount, LedgerError> {
+    let mut tx = pool.begin().await?;
+
+    let (owner_id, account_id, status, escrow_remaining) = sqlx::query_as::<_, (i64, i64, String, Amount)>(
+        "SELECT discord_id, account_id, status, escrow_remaining FROM limit_order WHERE id = ? FOR UPDATE"
+    )
+    .bind(order_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(LedgerError::OrderNotFound)?;
+
+    if owner_id != discord_id {
+        return Err(LedgerError::NotOrderOwner);
+    }
+    if status != "open" {
+        return Err(LedgerError::OrderNotOpen);
+    }
+
+    sqlx::query("UPDATE account SET balance = balance + ? WHERE id = ?")
+        .bind(escrow_remaining)
+        .bind(account_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("UPDATE limit_order SET status = 'cancelled', escrow_remaining = 0 WHERE id = ?")
+        .bind(order_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(escrow_remaining)
+}
+
+/// Get an order by ID
+/// Returns: (discord_id, base_ticker, quote_ticker, side, price, quantity, filled_quantity, status)
+pub async fn get_order(
+    pool: &MySqlPool,
+    order_id: i64,
+) -> Result<Option<(i64, String, String, String, Amount, Amount, Amount, String)>, sqlx::Error> {
+    sqlx::query_as::<_, (i64, String, String, String, Amount, Amount, Amount, String)>(
+        "SELECT o.discord_id, cb.ticker, cq.ticker, CAST(o.side AS CHAR), o.price, o.quantity, o.filled_quantity, CAST(o.status AS CHAR)
+         FROM limit_order o
+         JOIN currency cb ON o.base_currency_id = cb.id
+         JOIN currency cq ON o.quote_currency_id = cq.id
+         WHERE o.id = ?"
+    )
+    .bind(order_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Get a user's open orders, newest first
+/// Returns: Vec<(order_id, base_ticker, quote_ticker, side, price, quantity, filled_quantity)>
+pub async fn get_open_orders_by_user(
+    pool: &MySqlPool,
+    discord_id: i64,
+) -> Result<Vec<(i64, String, String, String, Amount, Amount, Amount)>, sqlx::Error> {
+    sqlx::query_as::<_, (i64, String, String, String, Amount, Amount, Amount)>(
+        "SELECT o.id, cb.ticker, cq.ticker, CAST(o.side AS CHAR), o.price, o.quantity, o.filled_quantity
+         FROM limit_order o
+         JOIN currency cb ON o.base_currency_id = cb.id
+         JOIN currency cq ON o.quote_currency_id = cq.id
+         WHERE o.discord_id = ? AND o.status = 'open'
+         ORDER BY o.id DESC"
+    )
+    .bind(discord_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Aggregated open quantity per price level for one side of a pair, best price first
+/// Returns: Vec<(price, quantity, order_count)>
+pub async fn get_book_depth(
+    pool: &MySqlPool,
+    base_currency_id: i64,
+    quote_currency_id: i64,
+    side: OrderSide,
+    levels: i64,
+) -> Result<Vec<(Amount, Amount, i64)>, sqlx::Error> {
+    let order_by = match side {
+        OrderSide::Buy => "price DESC",
+        OrderSide::Sell => "price ASC",
+    };
+
+    let sql = format!(
+        "SELECT price, SUM(quantity - filled_quantity), COUNT(*)
+         FROM limit_order
+         WHERE base_currency_id = ? AND quote_currency_id = ? AND side = ? AND status = 'open'
+         GROUP BY price
+         ORDER BY {}
+         LIMIT ?",
+        order_by
+    );
+
+    sqlx::query_as::<_, (Amount, Amount, i64)>(&sql)
+        .bind(base_currency_id)
+        .bind(quote_currency_id)
+        .bind(side.as_str())
+        .bind(levels)
+        .fetch_all(pool)
+        .await
+}
+
+/// Get total amount of a currency held in escrow by open orders
+pub async fn get_total_order_escrow(
+    pool: &MySqlPool,
+    currency_id: i64,
+) -> Result<Option<Amount>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT SUM(escrow_remaining) FROM limit_order
+         WHERE status = 'open'
+           AND ((side = 'buy' AND quote_currency_id = ?) OR (side = 'sell' AND base_currency_id = ?))"
+    )
+    .bind(currency_id)
+    .bind(currency_id)
+    .fetch_one(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::amount as amt;
+    use sqlx::mysql::MySqlPoolOptions;
+
+    #[test]
+    fn test_fill_terms() {
+        // Fills take the smaller remaining quantity
+        assert_eq!(fill_terms(amt("10"), amt("4"), amt("1.5")), Some((amt("4"), amt("6"))));
+        assert_eq!(fill_terms(amt("2"), amt("4"), amt("1.5")), Some((amt("2"), amt("3"))));
+
+        // Quote rounds down, escrow rounds up, so a bid always covers its fills
+        let (_, quote) = fill_terms(amt("1"), amt("1"), amt("0.33333333")).unwrap();
+        let escrow = amt("3").checked_mul_ceil(amt("0.33333333")).unwrap();
+        assert!(quote + quote + quote <= escrow);
+    }
+
+    #[test]
+    fn test_parse_side() {
+        assert_eq!(OrderSide::parse("BUY"), Some(OrderSide::Buy));
+        assert_eq!(OrderSide::parse("ask"), Some(OrderSide::Sell));
+        assert_eq!(OrderSide::parse("hold"), None);
+        assert_eq!(OrderSide::Buy.opposite(), OrderSide::Sell);
+    }
+
+    async fn connect_test_db() -> MySqlPool {
+        let url = std::env::var("TEST_DATABASE_URL")
+            .expect("TEST_DATABASE_URL must point at a scratch MySQL database");
+        let pool = MySqlPoolOptions::new()
+            .max_connections(8)
+            .connect(&url)
+            .await
+            .expect("Failed to connect to test database");
+        super::super::migrate::run(&mut pool.acquire().await.unwrap()).await.unwrap();
+        pool
+    }
+
+    /// Two throwaway currencies with funded users; returns (base_id, quote_id, users)
+    async fn setup_pair(pool: &MySqlPool) -> (i64, i64, Vec<i64>) {
+        let tag = uuid::Uuid::new_v4().simple().to_string().to_uppercase();
+        let guild_id = rand::random::<u32>() as i64 + 1_000_000_000_000;
+        let base = crate::db::currency::create_currency(pool, guild_id, &format!("Base {}", &tag[..12]), &format!("A{}", &tag[..8]))
+            .await
+            .unwrap();
+        let quote = crate::db::currency::create_currency(pool, guild_id + 1, &format!("Quote {}", &tag[..12]), &format!("B{}", &tag[..8]))
+            .await
+            .unwrap();
+
+        let users: Vec<i64> = (1..=3).map(|i| guild_id * 100 + i).collect();
+        for user in &users {
+            crate::db::ledger::mint(pool, *user, base, amt("1000")).await.unwrap();
+            crate::db::ledger::mint(pool, *user, quote, amt("1000")).await.unwrap();
+        }
+        (base, quote, users)
+    }
+
+    async fn balance(pool: &MySqlPool, discord_id: i64, currency_id: i64) -> Amount {
+        crate::db::account::get_account_balance(pool, discord_id, currency_id)
+            .await
+            .unwrap()
+            .unwrap_or(Amount::ZERO)
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local MySQL database in TEST_DATABASE_URL"]
+    async fn test_price_time_priority() {
+        let pool = connect_test_db().await;
+        let (base, quote, users) = setup_pair(&pool).await;
+        let (alice, bob, carol) = (users[0], users[1], users[2]);
+
+        // Two asks at the same price: Alice's is older so it fills first
+        let first = place_order(&pool, alice, base, quote, OrderSide::Sell, amt("2"), amt("5")).await.unwrap();
+        let second = place_order(&pool, bob, base, quote, OrderSide::Sell, amt("2"), amt("5")).await.unwrap();
+        // A worse ask that should not be touched
+        place_order(&pool, bob, base, quote, OrderSide::Sell, amt("3"), amt("5")).await.unwrap();
+
+        // Carol bids above the asks and trades at the resting price of 2
+        let receipt = place_order(&pool, carol, base, quote, OrderSide::Buy, amt("2.5"), amt("7")).await.unwrap();
+        assert_eq!(receipt.filled_quantity, amt("7"));
+        assert_eq!(receipt.quote_amount, amt("14"));
+        assert_eq!(receipt.fills, 2);
+        assert_eq!(receipt.status, "filled");
+
+        assert_eq!(get_order(&pool, first.order_id).await.unwrap().unwrap().7, "filled");
+        let second = get_order(&pool, second.order_id).await.unwrap().unwrap();
+        assert_eq!((second.6, second.7.as_str()), (amt("2"), "open"));
+
+        // Carol paid 14 for 7, and the unused escrow from bidding 2.5 came back
+        assert_eq!(balance(&pool, carol, quote).await, amt("986"));
+        assert_eq!(balance(&pool, carol, base).await, amt("1007"));
+        assert_eq!(balance(&pool, alice, quote).await, amt("1010"));
+        assert_eq!(balance(&pool, alice, base).await, amt("995"));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local MySQL database in TEST_DATABASE_URL"]
+    async fn test_cancel_refunds_escrow() {
+        let pool = connect_test_db().await;
+        let (base, quote, users) = setup_pair(&pool).await;
+
+        let order = place_order(&pool, users[0], base, quote, OrderSide::Buy, amt("0.5"), amt("10")).await.unwrap();
+        assert_eq!(order.status, "open");
+        assert_eq!(balance(&pool, users[0], quote).await, amt("995"));
+        assert_eq!(get_total_order_escrow(&pool, quote).await.unwrap(), Some(amt("5")));
+
+        assert!(matches!(cancel_order(&pool, users[1], order.order_id).await, Err(LedgerError::NotOrderOwner)));
+        assert_eq!(cancel_order(&pool, users[0], order.order_id).await.unwrap(), amt("5"));
+        assert_eq!(balance(&pool, users[0], quote).await, amt("1000"));
+        assert!(matches!(cancel_order(&pool, users[0], order.order_id).await, Err(LedgerError::OrderNotOpen)));
+    }
+}
@@ -0,0 +1,145 @@
+//! Payroll schedules: pay every member of a Discord role on a schedule.
+//!
+//! Only the schedule is stored. Who gets paid is read from the role when each
+//! run starts, and every member is paid with `ledger::payout`.
+
+use sqlx::mysql::MySqlPool;
+use crate::db::recurring::RecurringPeriod;
+use crate::storage::PayrollRow;
+use crate::utils::Amount;
+
+/// Where payroll money comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayrollSource {
+    /// The currency's tax account
+    Treasury,
+    /// Newly minted
+    Mint,
+}
+
+impl PayrollSource {
+    pub fn parse(source: &str) -> Option<PayrollSource> {
+        match source.to_lowercase().as_str() {
+            "treasury" | "tax" => Some(PayrollSource::Treasury),
+            "mint" => Some(PayrollSource::Mint),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayrollSource::Treasury => "treasury",
+            PayrollSource::Mint => "mint",
+        }
+    }
+}
+
+const SELECT_PAYROLL: &str = "SELECT CAST(p.id AS SIGNED), p.guild_id, p.role_id, CAST(p.currency_id AS SIGNED), c.ticker,
+        p.amount, CAST(p.source AS CHAR), CAST(p.period AS CHAR), CAST(UNIX_TIMESTAMP(p.next_run_at) AS SIGNED),
+        p.created_by, CAST(p.status AS CHAR)
+     FROM payroll_schedule p
+     JOIN currency c ON p.currency_id = c.id";
+
+#[allow(clippy::too_many_arguments)]
+pub async fn create_payroll(
+    pool: &MySqlPool,
+    guild_id: i64,
+    role_id: i64,
+    currency_id: i64,
+    amount: Amount,
+    source: PayrollSource,
+    period: RecurringPeriod,
+    next_run_at: i64,
+    created_by: i64,
+) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO payroll_schedule (guild_id, role_id, currency_id, amount, source, period, next_run_at, created_by)
+         VALUES (?, ?, ?, ?, ?, ?, FROM_UNIXTIME(?), ?)"
+    )
+    .bind(guild_id)
+    .bind(role_id)
+    .bind(currency_id)
+    .bind(amount)
+    .bind(source.as_str())
+    .bind(period.as_str())
+    .bind(next_run_at)
+    .bind(created_by)
+    .execute(pool)
+    .await?;
+
+    Ok(result.last_insert_id() as i64)
+}
+
+pub async fn get_payroll(pool: &MySqlPool, id: i64) -> Result<Option<PayrollRow>, sqlx::Error> {
+    sqlx::query_as(&format!("{} WHERE p.id = ?", SELECT_PAYROLL))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// The guild's active schedules, oldest first
+pub async fn get_payrolls_by_guild(pool: &MySqlPool, guild_id: i64) -> Result<Vec<PayrollRow>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "{} WHERE p.guild_id = ? AND p.status = 'active' ORDER BY p.id",
+        SELECT_PAYROLL
+    ))
+    .bind(guild_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Active schedules whose next run has come, most overdue first
+pub async fn get_due_payrolls(pool: &MySqlPool, limit: i64) -> Result<Vec<PayrollRow>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "{} WHERE p.status = 'active' AND p.next_run_at <= NOW() ORDER BY p.next_run_at, p.id LIMIT ?",
+        SELECT_PAYROLL
+    ))
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Move an active schedule's next run from `due_at` to `next_run_at`.
+/// Returns false if it was rescheduled or cancelled in the meantime.
+pub async fn reschedule_payroll(
+    pool: &MySqlPool,
+    id: i64,
+    due_at: i64,
+    next_run_at: i64,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE payroll_schedule SET next_run_at = FROM_UNIXTIME(?)
+         WHERE id = ? AND status = 'active' AND next_run_at = FROM_UNIXTIME(?)"
+    )
+    .bind(next_run_at)
+    .bind(id)
+    .bind(due_at)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Set a schedule's status ("active" or "cancelled")
+pub async fn set_payroll_status(pool: &MySqlPool, id: i64, status: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE payroll_schedule SET status = ? WHERE id = ?")
+        .bind(status)
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_source() {
+        assert_eq!(PayrollSource::parse("Treasury"), Some(PayrollSource::Treasury));
+        assert_eq!(PayrollSource::parse("tax"), Some(PayrollSource::Treasury));
+        assert_eq!(PayrollSource::parse("MINT"), Some(PayrollSource::Mint));
+        assert_eq!(PayrollSource::parse("wallet"), None);
+    }
+}
@@ -0,0 +1,187 @@
+//! Recurring payments: standing orders that `$send` a fixed amount on a schedule.
+//!
+//! Times cross this module as unix timestamps, so the schedule arithmetic lives in
+//! `RecurringPeriod` and is the same for every backend.
+
+use chrono::{DateTime, Days, Months};
+use sqlx::mysql::MySqlPool;
+use crate::storage::RecurringRow;
+use crate::utils::Amount;
+
+/// How often a recurring payment is sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecurringPeriod {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl RecurringPeriod {
+    pub const ALL: [RecurringPeriod; 3] = [RecurringPeriod::Daily, RecurringPeriod::Weekly, RecurringPeriod::Monthly];
+
+    pub fn parse(period: &str) -> Option<RecurringPeriod> {
+        Self::ALL.into_iter().find(|p| p.as_str() == period.to_lowercase())
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecurringPeriod::Daily => "daily",
+            RecurringPeriod::Weekly => "weekly",
+            RecurringPeriod::Monthly => "monthly",
+        }
+    }
+
+    /// The run one period after `timestamp`. A monthly run lands on the same day
+    /// of the next month, or on its last day when that month is shorter.
+    pub fn next_run(&self, timestamp: i64) -> i64 {
+        let Some(time) = DateTime::from_timestamp(timestamp, 0) else {
+            return timestamp;
+        };
+        let next = match self {
+            RecurringPeriod::Daily => time.checked_add_days(Days::new(1)),
+            RecurringPeriod::Weekly => time.checked_add_days(Days::new(7)),
+            RecurringPeriod::Monthly => time.checked_add_months(Months::new(1)),
+        };
+        next.map_or(timestamp, |next| next.timestamp())
+    }
+}
+
+const SELECT_RECURRING: &str = "SELECT CAST(rp.id AS SIGNED), rp.payer_id, rp.recipient_id, CAST(rp.currency_id AS SIGNED),
+        c.ticker, rp.amount, CAST(rp.period AS CHAR), CAST(UNIX_TIMESTAMP(rp.next_run_at) AS SIGNED),
+        rp.failure_count, CAST(rp.status AS CHAR)
+     FROM recurring_payment rp
+     JOIN currency c ON rp.currency_id = c.id";
+
+pub async fn create_recurring_payment(
+    pool: &MySqlPool,
+    payer_id: i64,
+    recipient_id: i64,
+    currency_id: i64,
+    amount: Amount,
+    period: RecurringPeriod,
+    next_run_at: i64,
+) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO recurring_payment (payer_id, recipient_id, currency_id, amount, period, next_run_at)
+         VALUES (?, ?, ?, ?, ?, FROM_UNIXTIME(?))"
+    )
+    .bind(payer_id)
+    .bind(recipient_id)
+    .bind(currency_id)
+    .bind(amount)
+    .bind(period.as_str())
+    .bind(next_run_at)
+    .execute(pool)
+    .await?;
+
+    Ok(result.last_insert_id() as i64)
+}
+
+pub async fn get_recurring_payment(pool: &MySqlPool, id: i64) -> Result<Option<RecurringRow>, sqlx::Error> {
+    sqlx::query_as(&format!("{} WHERE rp.id = ?", SELECT_RECURRING))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// The payer's active and suspended payments, oldest first
+pub async fn get_recurring_payments_by_payer(pool: &MySqlPool, payer_id: i64) -> Result<Vec<RecurringRow>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "{} WHERE rp.payer_id = ? AND rp.status <> 'cancelled' ORDER BY rp.id",
+        SELECT_RECURRING
+    ))
+    .bind(payer_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Active payments whose next run has come, most overdue first
+pub async fn get_due_recurring_payments(pool: &MySqlPool, limit: i64) -> Result<Vec<RecurringRow>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "{} WHERE rp.status = 'active' AND rp.next_run_at <= NOW() ORDER BY rp.next_run_at, rp.id LIMIT ?",
+        SELECT_RECURRING
+    ))
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Move an active payment's next run from `due_at` to `next_run_at`.
+/// Returns false if the payment was rescheduled, suspended or cancelled in the meantime.
+pub async fn reschedule_recurring_payment(
+    pool: &MySqlPool,
+    id: i64,
+    due_at: i64,
+    next_run_at: i64,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE recurring_payment SET next_run_at = FROM_UNIXTIME(?)
+         WHERE id = ? AND status = 'active' AND next_run_at = FROM_UNIXTIME(?)"
+    )
+    .bind(next_run_at)
+    .bind(id)
+    .bind(due_at)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Count a failed run, returning the failures in a row so far
+pub async fn record_recurring_failure(pool: &MySqlPool, id: i64) -> Result<i32, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("UPDATE recurring_payment SET failure_count = failure_count + 1 WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+    let failures = sqlx::query_scalar("SELECT failure_count FROM recurring_payment WHERE id = ?")
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(failures)
+}
+
+pub async fn reset_recurring_failures(pool: &MySqlPool, id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE recurring_payment SET failure_count = 0 WHERE id = ? AND failure_count <> 0")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Set a payment's status ("active", "suspended" or "cancelled")
+pub async fn set_recurring_status(pool: &MySqlPool, id: i64, status: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE recurring_payment SET status = ? WHERE id = ?")
+        .bind(status)
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_run() {
+        let at = |s: &str| chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap().and_utc().timestamp();
+
+        assert_eq!(RecurringPeriod::Daily.next_run(at("2024-02-28 09:00")), at("2024-02-29 09:00"));
+        assert_eq!(RecurringPeriod::Weekly.next_run(at("2024-12-30 18:30")), at("2025-01-06 18:30"));
+        assert_eq!(RecurringPeriod::Monthly.next_run(at("2024-01-31 12:00")), at("2024-02-29 12:00"));
+        assert_eq!(RecurringPeriod::Monthly.next_run(at("2024-03-15 12:00")), at("2024-04-15 12:00"));
+    }
+
+    #[test]
+    fn test_parse_period() {
+        assert_eq!(RecurringPeriod::parse("Weekly"), Some(RecurringPeriod::Weekly));
+        assert_eq!(RecurringPeriod::parse("hourly"), None);
+    }
+}
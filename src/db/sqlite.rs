@@ -0,0 +1,185 @@
+//! SQLite schema for self-hosted single-guild deployments.
+//!
+//! SQLite has its own numbered migrations in `migrations/sqlite/`, recorded in a
+//! `schema_migration` table with the same checksum rules as MySQL. There are no
+//! stored procedures: swaps are settled by `storage::swaps` on both engines.
+//! Each migration runs in one write transaction, so a failed one leaves nothing behind.
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use tracing::info;
+use crate::utils::errors::MigrationError;
+use super::migrate::{checksum, split_statements, AppliedMigration, Migration, MigrationState};
+
+/// Numbered SQLite migrations, oldest first. Append only.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "0001_initial_schema",
+        sql: include_str!("../../migrations/sqlite/0001_initial_schema.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "0002_recurring_payments",
+        sql: include_str!("../../migrations/sqlite/0002_recurring_payments.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "0003_payroll",
+        sql: include_str!("../../migrations/sqlite/0003_payroll.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "0004_transaction_memo",
+        sql: include_str!("../../migrations/sqlite/0004_transaction_memo.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "0005_invoices",
+        sql: include_str!("../../migrations/sqlite/0005_invoices.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "0006_wire_journal",
+        sql: include_str!("../../migrations/sqlite/0006_wire_journal.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "0007_wire_rates",
+        sql: include_str!("../../migrations/sqlite/0007_wire_rates.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "0008_bridge_providers",
+        sql: include_str!("../../migrations/sqlite/0008_bridge_providers.sql"),
+    },
+];
+
+/// How long a write waits for another connection's write to finish
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const CREATE_TRACKING_TABLE: &str = "CREATE TABLE IF NOT EXISTS schema_migration (
+    name TEXT PRIMARY KEY,
+    version INTEGER NULL UNIQUE,
+    checksum TEXT NOT NULL,
+    applied_at TEXT DEFAULT CURRENT_TIMESTAMP
+)";
+
+/// True if `DATABASE_URL` points at SQLite rather than MySQL
+pub fn is_sqlite_url(database_url: &str) -> bool {
+    database_url.starts_with("sqlite:")
+}
+
+/// Connect to the database file, creating it if missing
+pub async fn connect(database_url: &str) -> Result<SqlitePool, sqlx::Error> {
+    let options = SqliteConnectOptions::from_str(database_url)?
+        .create_if_missing(true)
+        .foreign_keys(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        .busy_timeout(BUSY_TIMEOUT);
+
+    SqlitePoolOptions::new().connect_with(options).await
+}
+
+/// Apply every pending migration. Stops at the first failing statement.
+pub async fn run(pool: &SqlitePool) -> Result<(), MigrationError> {
+    sqlx::raw_sql(CREATE_TRACKING_TABLE).execute(pool).await?;
+    let applied = fetch_applied(pool).await?;
+
+    // Refuse to touch a database whose history doesn't match this build
+    for row in &applied {
+        let Some(version) = row.version else { continue };
+        match MIGRATIONS.iter().find(|m| m.version == version) {
+            Some(migration) if checksum(migration.sql) != row.checksum => {
+                return Err(MigrationError::ChecksumMismatch(row.name.clone()));
+            }
+            Some(_) => {}
+            None => return Err(MigrationError::UnknownVersion(row.name.clone())),
+        }
+    }
+
+    for migration in MIGRATIONS {
+        if applied.iter().any(|row| row.version == Some(migration.version)) {
+            continue;
+        }
+
+        info!("Applying SQLite migration {}...", migration.name);
+        let mut tx = pool.begin_with("BEGIN IMMEDIATE").await?;
+
+        for (index, statement) in split_statements(migration.sql).iter().enumerate() {
+            sqlx::raw_sql(statement)
+                .execute(&mut *tx)
+                .await
+                .map_err(|source| MigrationError::Statement {
+                    migration: migration.name.to_string(),
+                    statement: index + 1,
+                    source,
+                })?;
+        }
+
+        sqlx::query("INSERT INTO schema_migration (name, version, checksum) VALUES (?, ?, ?)")
+            .bind(migration.name)
+            .bind(migration.version)
+            .bind(checksum(migration.sql))
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+    }
+
+    Ok(())
+}
+
+/// Connect and apply pending migrations
+pub async fn open(database_url: &str) -> Result<SqlitePool, MigrationError> {
+    info!("Checking SQLite migrations...");
+    let pool = connect(database_url).await?;
+    run(&pool).await?;
+    info!("Database schema is up to date");
+    Ok(pool)
+}
+
+/// State of every known migration plus anything recorded that this build doesn't know
+pub async fn status(pool: &SqlitePool) -> Result<Vec<(String, MigrationState)>, MigrationError> {
+    let tracked: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'schema_migration'"
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let applied = if tracked > 0 { fetch_applied(pool).await? } else { Vec::new() };
+
+    let state_of = |name: &str, sql: &str| match applied.iter().find(|row| row.name == name) {
+        Some(row) if row.checksum == checksum(sql) => MigrationState::Applied(row.applied_at.clone()),
+        Some(row) => MigrationState::Modified(row.applied_at.clone()),
+        None => MigrationState::Pending,
+    };
+
+    let mut states: Vec<(String, MigrationState)> = MIGRATIONS
+        .iter()
+        .map(|m| (m.name.to_string(), state_of(m.name, m.sql)))
+        .collect();
+
+    for row in &applied {
+        if !states.iter().any(|(name, _)| *name == row.name) {
+            states.push((row.name.clone(), MigrationState::Unknown));
+        }
+    }
+
+    Ok(states)
+}
+
+async fn fetch_applied(pool: &SqlitePool) -> Result<Vec<AppliedMigration>, sqlx::Error> {
+    let rows: Vec<(String, Option<i64>, String, String)> = sqlx::query_as(
+        "SELECT name, version, checksum, applied_at FROM schema_migration ORDER BY version"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(name, version, checksum, applied_at)| AppliedMigration { name, version, checksum, applied_at })
+        .collect())
+}
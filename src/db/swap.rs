@@ -1,15 +1,19 @@
 use sqlx::mysql::MySqlPool;
 use sqlx::Row;
+use crate::storage::swaps;
+use crate::utils::Amount;
+use crate::utils::errors::StorageError;
+use super::filter::{SwapFilter, SwapSort};
 
 /// Get a swap by ID (direct query)
 /// Returns: (id, maker_id, taker_id, maker_currency_id, taker_currency_id, maker_amount, taker_amount, status)
 pub async fn get_swap_by_id(
     pool: &MySqlPool,
     swap_id: i64,
-) -> Result<Option<(i64, i64, Option<i64>, i64, i64, f64, f64, String)>, sqlx::Error> {
-    sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, f64, f64, String)>(
+) -> Result<Option<(i64, i64, Option<i64>, i64, i64, Amount, Amount, String)>, sqlx::Error> {
+    sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, Amount, Amount, String)>(
         "SELECT CAST(id AS SIGNED), CAST(maker_id AS SIGNED), CAST(taker_id AS SIGNED), CAST(maker_currency_id AS SIGNED), 
-                CAST(taker_currency_id AS SIGNED), CAST(maker_amount AS DOUBLE), CAST(taker_amount AS DOUBLE), status 
+                CAST(taker_currency_id AS SIGNED), maker_amount, taker_amount, status 
          FROM currency_swap WHERE id = ?"
     )
     .bind(swap_id)
@@ -21,10 +25,10 @@ pub async fn get_swap_by_id(
 pub async fn get_pending_swaps_for_maker(
     pool: &MySqlPool,
     maker_account_id: i64,
-) -> Result<Vec<(i64, i64, Option<i64>, i64, i64, f64, f64, String)>, sqlx::Error> {
-    sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, f64, f64, String)>(
+) -> Result<Vec<(i64, i64, Option<i64>, i64, i64, Amount, Amount, String)>, sqlx::Error> {
+    sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, Amount, Amount, String)>(
         "SELECT CAST(id AS SIGNED), CAST(maker_id AS SIGNED), CAST(taker_id AS SIGNED), CAST(maker_currency_id AS SIGNED), 
-                CAST(taker_currency_id AS SIGNED), CAST(maker_amount AS DOUBLE), CAST(taker_amount AS DOUBLE), status 
+                CAST(taker_currency_id AS SIGNED), maker_amount, taker_amount, status 
          FROM currency_swap WHERE maker_id = ? AND status = 'pending'"
     )
     .bind(maker_account_id)
@@ -36,10 +40,10 @@ pub async fn get_pending_swaps_for_maker(
 pub async fn get_pending_swaps_for_taker(
     pool: &MySqlPool,
     taker_account_id: i64,
-) -> Result<Vec<(i64, i64, Option<i64>, i64, i64, f64, f64, String)>, sqlx::Error> {
-    sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, f64, f64, String)>(
+) -> Result<Vec<(i64, i64, Option<i64>, i64, i64, Amount, Amount, String)>, sqlx::Error> {
+    sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, Amount, Amount, String)>(
         "SELECT CAST(id AS SIGNED), CAST(maker_id AS SIGNED), CAST(taker_id AS SIGNED), CAST(maker_currency_id AS SIGNED), 
-                CAST(taker_currency_id AS SIGNED), CAST(maker_amount AS DOUBLE), CAST(taker_amount AS DOUBLE), status 
+                CAST(taker_currency_id AS SIGNED), maker_amount, taker_amount, status 
          FROM currency_swap WHERE taker_id = ? AND status = 'pending'"
     )
     .bind(taker_account_id)
@@ -47,98 +51,56 @@ pub async fn get_pending_swaps_for_taker(
     .await
 }
 
-/// Get all open swaps (swaps where taker_id is NULL) - direct query
+/// Get all open swaps (swaps where taker_id is NULL) that can still be filled - direct query
+/// Returns: Vec<(id, maker_id, maker_discord_id, maker_currency_id, taker_currency_id, maker_amount, taker_amount, filled_amount)>
 pub async fn get_open_swaps(
     pool: &MySqlPool,
-) -> Result<Vec<(i64, i64, Option<i64>, i64, i64, f64, f64, String)>, sqlx::Error> {
-    sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, f64, f64, String)>(
-        "SELECT CAST(id AS SIGNED), CAST(maker_id AS SIGNED), CAST(taker_id AS SIGNED), CAST(maker_currency_id AS SIGNED), 
-                CAST(taker_currency_id AS SIGNED), CAST(maker_amount AS DOUBLE), CAST(taker_amount AS DOUBLE), status 
-         FROM currency_swap WHERE taker_id IS NULL AND status = 'pending'"
+) -> Result<Vec<(i64, i64, i64, i64, i64, Amount, Amount, Amount)>, sqlx::Error> {
+    sqlx::query_as::<_, (i64, i64, i64, i64, i64, Amount, Amount, Amount)>(
+        "SELECT CAST(cs.id AS SIGNED), CAST(cs.maker_id AS SIGNED), a.discord_id, CAST(cs.maker_currency_id AS SIGNED), 
+                CAST(cs.taker_currency_id AS SIGNED), cs.maker_amount, cs.taker_amount, cs.filled_amount 
+         FROM currency_swap cs
+         JOIN account a ON cs.maker_id = a.id
+         WHERE cs.taker_id IS NULL AND cs.status = 'pending'
+           AND (cs.expires_at IS NULL OR cs.expires_at > NOW())"
     )
     .fetch_all(pool)
     .await
 }
 
-/// Create a new currency swap (targeted swap)
-pub async fn create_swap(
+/// Fill several swaps as one user in a single transaction, in order
+/// Each fill is (swap_id, amount of the maker's currency). If any fill fails, none of them happen.
+/// Returns: Vec<(maker amount filled, taker amount paid, fully filled)>
+pub async fn fill_swaps(
     pool: &MySqlPool,
-    maker_id: i64,
-    maker_currency_id: i64,
-    taker_currency_id: i64,
-    maker_amount: f64,
-    taker_amount: f64,
     taker_id: i64,
-) -> Result<i64, sqlx::Error> {
-    // Acquire a single connection to maintain session variables
-    let mut conn = pool.acquire().await?;
-
-    sqlx::query(
-        "CALL sp_create_swap(?, ?, ?, ?, ?, ?)"
-    )
-    .bind(maker_id)
-    .bind(maker_currency_id)
-    .bind(taker_currency_id)
-    .bind(maker_amount)
-    .bind(taker_amount)
-    .bind(taker_id)
-    .execute(&mut *conn)
-    .await?;
+    fills: &[(i64, Amount)],
+) -> Result<Vec<(Amount, Amount, bool)>, StorageError> {
+    let mut tx = pool.begin().await?;
+    let mut results = Vec::with_capacity(fills.len());
 
-    let swap_id: i64 = sqlx::query_scalar("SELECT CAST(@swap_id AS SIGNED)")
-        .fetch_one(&mut *conn)
-        .await?;
-
-    Ok(swap_id)
-}
-
-/// Create an open currency swap (any user can accept)
-pub async fn create_swap_open(
-    pool: &MySqlPool,
-    maker_id: i64,
-    maker_currency_id: i64,
-    taker_currency_id: i64,
-    maker_amount: f64,
-    taker_amount: f64,
-) -> Result<i64, sqlx::Error> {
-    // Acquire a single connection to maintain session variables
-    let mut conn = pool.acquire().await?;
-
-    sqlx::query(
-        "CALL sp_create_swap_open(?, ?, ?, ?, ?)"
-    )
-    .bind(maker_id)
-    .bind(maker_currency_id)
-    .bind(taker_currency_id)
-    .bind(maker_amount)
-    .bind(taker_amount)
-    .execute(&mut *conn)
-    .await?;
-
-    let swap_id: i64 = sqlx::query_scalar("SELECT CAST(@swap_id AS SIGNED)")
-        .fetch_one(&mut *conn)
-        .await?;
+    for (swap_id, fill_amount) in fills {
+        // Returning early drops `tx`, which rolls back every fill
+        results.push(swaps::fill(&mut tx, *swap_id, taker_id, Some(*fill_amount)).await?);
+    }
 
-    Ok(swap_id)
+    tx.commit().await?;
+    Ok(results)
 }
 
-/// Accept a swap as the taker
-pub async fn accept_swap(
+/// How much of a swap's maker amount has been filled, and in how many fills
+/// Returns: (filled_amount, fill_count)
+pub async fn get_swap_fill_progress(
     pool: &MySqlPool,
     swap_id: i64,
-    taker_id: i64,
-    uuid1: &str,
-    uuid2: &str,
-) -> Result<(), sqlx::Error> {
-    sqlx::query("CALL sp_accept_swap(?, ?, ?, ?)")
-        .bind(swap_id)
-        .bind(taker_id)
-        .bind(uuid1)
-        .bind(uuid2)
-        .execute(pool)
-        .await?;
-
-    Ok(())
+) -> Result<Option<(Amount, i64)>, sqlx::Error> {
+    sqlx::query_as::<_, (Amount, i64)>(
+        "SELECT cs.filled_amount, (SELECT COUNT(*) FROM swap_fill sf WHERE sf.swap_id = cs.id)
+         FROM currency_swap cs WHERE cs.id = ?"
+    )
+    .bind(swap_id)
+    .fetch_optional(pool)
+    .await
 }
 
 /// Complete a swap
@@ -151,22 +113,51 @@ pub async fn complete_swap(pool: &MySqlPool, swap_id: i64) -> Result<(), sqlx::E
     Ok(())
 }
 
-/// Cancel a swap
-pub async fn cancel_swap(pool: &MySqlPool, swap_id: i64) -> Result<(), sqlx::Error> {
-    sqlx::query("CALL sp_cancel_swap(?)")
-        .bind(swap_id)
-        .execute(pool)
-        .await?;
+/// Get pending swaps whose expiry has passed, oldest first
+/// `maker_amount` is the unfilled part that will be refunded
+/// Returns: Vec<(swap_id, maker_discord_id, taker_discord_id, maker_ticker, taker_ticker, maker_amount, taker_amount)>
+pub async fn get_overdue_swaps(
+    pool: &MySqlPool,
+    limit: i64,
+) -> Result<Vec<(i64, i64, Option<i64>, String, String, Amount, Amount)>, sqlx::Error> {
+    sqlx::query_as::<_, (i64, i64, Option<i64>, String, String, Amount, Amount)>(
+        "SELECT CAST(cs.id AS SIGNED), CAST(a_maker.discord_id AS SIGNED), CAST(a_taker.discord_id AS SIGNED),
+                c_maker.ticker, c_taker.ticker, cs.maker_amount - cs.filled_amount, cs.taker_amount
+         FROM currency_swap cs
+         JOIN account a_maker ON cs.maker_id = a_maker.id
+         LEFT JOIN account a_taker ON cs.taker_id = a_taker.id
+         JOIN currency c_maker ON cs.maker_currency_id = c_maker.id
+         JOIN currency c_taker ON cs.taker_currency_id = c_taker.id
+         WHERE cs.status = 'pending' AND cs.expires_at IS NOT NULL AND cs.expires_at <= NOW()
+         ORDER BY cs.expires_at ASC
+         LIMIT ?"
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
 
-    Ok(())
+/// Get the expiry of a swap as a unix timestamp (None if it never expires)
+pub async fn get_swap_expiry(
+    pool: &MySqlPool,
+    swap_id: i64,
+) -> Result<Option<i64>, sqlx::Error> {
+    let expires_at: Option<Option<i64>> = sqlx::query_scalar(
+        "SELECT CAST(UNIX_TIMESTAMP(expires_at) AS SIGNED) FROM currency_swap WHERE id = ?"
+    )
+    .bind(swap_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(expires_at.flatten())
 }
 
 /// Get a swap by ID
 pub async fn get_swap(
     pool: &MySqlPool,
     swap_id: i64,
-) -> Result<Option<(i64, i64, Option<i64>, i64, i64, f64, f64, String)>, sqlx::Error> {
-    sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, f64, f64, String)>(
+) -> Result<Option<(i64, i64, Option<i64>, i64, i64, Amount, Amount, String)>, sqlx::Error> {
+    sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, Amount, Amount, String)>(
         "CALL sp_get_swap(?)"
     )
     .bind(swap_id)
@@ -178,8 +169,8 @@ pub async fn get_swap(
 pub async fn get_pending_swaps_by_maker(
     pool: &MySqlPool,
     maker_id: i64,
-) -> Result<Vec<(i64, i64, Option<i64>, i64, i64, f64, f64, String)>, sqlx::Error> {
-    sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, f64, f64, String)>(
+) -> Result<Vec<(i64, i64, Option<i64>, i64, i64, Amount, Amount, String)>, sqlx::Error> {
+    sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, Amount, Amount, String)>(
         "CALL sp_get_pending_swaps_by_maker(?)"
     )
     .bind(maker_id)
@@ -191,8 +182,8 @@ pub async fn get_pending_swaps_by_maker(
 pub async fn get_swaps_by_maker(
     pool: &MySqlPool,
     maker_id: i64,
-) -> Result<Vec<(i64, i64, Option<i64>, i64, i64, f64, f64, String)>, sqlx::Error> {
-    sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, f64, f64, String)>(
+) -> Result<Vec<(i64, i64, Option<i64>, i64, i64, Amount, Amount, String)>, sqlx::Error> {
+    sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, Amount, Amount, String)>(
         "CALL sp_get_swaps_by_maker(?)"
     )
     .bind(maker_id)
@@ -204,8 +195,8 @@ pub async fn get_swaps_by_maker(
 pub async fn get_swaps_by_taker(
     pool: &MySqlPool,
     taker_id: i64,
-) -> Result<Vec<(i64, i64, Option<i64>, i64, i64, f64, f64, String)>, sqlx::Error> {
-    sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, f64, f64, String)>(
+) -> Result<Vec<(i64, i64, Option<i64>, i64, i64, Amount, Amount, String)>, sqlx::Error> {
+    sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, Amount, Amount, String)>(
         "CALL sp_get_swaps_by_taker(?)"
     )
     .bind(taker_id)
@@ -216,8 +207,8 @@ pub async fn get_swaps_by_taker(
 /// Get all pending swaps (admin view)
 pub async fn get_all_pending_swaps(
     pool: &MySqlPool,
-) -> Result<Vec<(i64, i64, Option<i64>, i64, i64, f64, f64, String)>, sqlx::Error> {
-    sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, f64, f64, String)>(
+) -> Result<Vec<(i64, i64, Option<i64>, i64, i64, Amount, Amount, String)>, sqlx::Error> {
+    sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, Amount, Amount, String)>(
         "CALL sp_get_all_pending_swaps()"
     )
     .fetch_all(pool)
@@ -227,8 +218,8 @@ pub async fn get_all_pending_swaps(
 /// Get all open swaps (swaps without a taker)
 pub async fn get_all_open_swaps(
     pool: &MySqlPool,
-) -> Result<Vec<(i64, i64, Option<i64>, i64, i64, f64, f64, String)>, sqlx::Error> {
-    sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, f64, f64, String)>(
+) -> Result<Vec<(i64, i64, Option<i64>, i64, i64, Amount, Amount, String)>, sqlx::Error> {
+    sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, Amount, Amount, String)>(
         "CALL sp_get_all_open_swaps()"
     )
     .fetch_all(pool)
@@ -254,124 +245,75 @@ pub async fn store_swap_message(
     Ok(())
 }
 
-/// Get swap message ID by swap ID
-pub async fn get_swap_message(
+/// Get all stored messages for a swap
+/// Returns: Vec<(channel_id, message_id)>
+pub async fn get_swap_messages(
     pool: &MySqlPool,
     swap_id: i64,
-) -> Result<Option<(i64, i64, i64)>, sqlx::Error> {
-    sqlx::query_as::<_, (i64, i64, i64)>(
-        "SELECT swap_id, channel_id, message_id FROM swap_message WHERE swap_id = ?"
+) -> Result<Vec<(i64, i64)>, sqlx::Error> {
+    sqlx::query_as::<_, (i64, i64)>(
+        "SELECT channel_id, message_id FROM swap_message WHERE swap_id = ? ORDER BY id"
     )
     .bind(swap_id)
-    .fetch_optional(pool)
+    .fetch_all(pool)
     .await
 }
 
 /// Get paginated swaps with optional filters
-/// Returns: Vec<(swap_id, maker_id, taker_id, maker_currency_id, taker_currency_id, maker_amount, taker_amount, status, maker_ticker, taker_ticker)>
-/// Supports filters:
-/// - oldest/latest: sort order (default: latest)
-/// - pending/accepted/cancelled: status filter (default: pending)
-/// - highmaker/lowmaker/hightaker/lowtaker: sort by amount
-/// - base:ABC/quote:XYZ: filter by currency ticker
+/// Returns: Vec<(swap_id, maker_id, taker_id, maker_currency_id, taker_currency_id, maker_amount, taker_amount, status, maker_ticker, taker_ticker, filled_amount)>
+/// Status and ticker filters come from `SwapFilter` and the order from `SwapSort`; every value is bound
 pub async fn get_swaps_paginated(
     pool: &MySqlPool,
     page: usize,
     page_size: usize,
-    sort_by: &str,           // "oldest", "latest", "highmaker", "lowmaker", "hightaker", "lowtaker"
-    status_filter: &str,     // "pending", "accepted", "cancelled", or "all"
-    base_currency: Option<&str>,  // filter by base currency ticker (maker currency)
-    quote_currency: Option<&str>, // filter by quote currency ticker (taker currency)
-) -> Result<(Vec<(i64, i64, Option<i64>, i64, i64, f64, f64, String, String, String)>, i64), sqlx::Error> {
-    let offset = (page - 1) * page_size;
+    sort: SwapSort,
+    filter: &SwapFilter,
+) -> Result<(Vec<(i64, i64, Option<i64>, i64, i64, Amount, Amount, String, String, String, Amount)>, i64), sqlx::Error> {
+    let offset = (page.max(1) - 1) * page_size;
+    
+    // Get total count
+    let total_count: (i64,) = filter
+        .select("COUNT(*) as count")
+        .build_query_as()
+        .fetch_one(pool)
+        .await?;
     
-    // Build the query
-    let mut query_str = String::from(
-        "SELECT 
-            CAST(cs.id AS SIGNED),
+    let mut query = filter.select_page(
+        "CAST(cs.id AS SIGNED),
             CAST(a_maker.discord_id AS SIGNED),
             CAST(a_taker.discord_id AS SIGNED),
             CAST(cs.maker_currency_id AS SIGNED),
             CAST(cs.taker_currency_id AS SIGNED),
-            CAST(cs.maker_amount AS DOUBLE),
-            CAST(cs.taker_amount AS DOUBLE),
+            cs.maker_amount,
+            cs.taker_amount,
             cs.status,
             c_maker.ticker,
-            c_taker.ticker
-         FROM currency_swap cs
-         JOIN account a_maker ON cs.maker_id = a_maker.id
-         LEFT JOIN account a_taker ON cs.taker_id = a_taker.id
-         JOIN currency c_maker ON cs.maker_currency_id = c_maker.id
-         JOIN currency c_taker ON cs.taker_currency_id = c_taker.id
-         WHERE 1=1"
-    );
-    
-    // Add status filter
-    if status_filter != "all" {
-        query_str.push_str(&format!(" AND cs.status = '{}'", status_filter));
-    }
-    
-    // Add base currency filter (maker_currency)
-    if let Some(base_ticker) = base_currency {
-        query_str.push_str(&format!(" AND UPPER(c_maker.ticker) = UPPER('{}')", base_ticker));
-    }
-    
-    // Add quote currency filter (taker_currency)
-    if let Some(quote_ticker) = quote_currency {
-        query_str.push_str(&format!(" AND UPPER(c_taker.ticker) = UPPER('{}')", quote_ticker));
-    }
-    
-    // Add ORDER BY clause
-    match sort_by {
-        "oldest" => query_str.push_str(" ORDER BY cs.date_created ASC"),
-        "latest" => query_str.push_str(" ORDER BY cs.date_created DESC"),
-        "highmaker" => query_str.push_str(" ORDER BY cs.maker_amount DESC"),
-        "lowmaker" => query_str.push_str(" ORDER BY cs.maker_amount ASC"),
-        "hightaker" => query_str.push_str(" ORDER BY cs.taker_amount DESC"),
-        "lowtaker" => query_str.push_str(" ORDER BY cs.taker_amount ASC"),
-        _ => query_str.push_str(" ORDER BY cs.date_created DESC"),
-    }
-    
-    // Get total count
-    let count_query = format!(
-        "SELECT COUNT(*) as count FROM currency_swap cs
-         JOIN account a_maker ON cs.maker_id = a_maker.id
-         LEFT JOIN account a_taker ON cs.taker_id = a_taker.id
-         JOIN currency c_maker ON cs.maker_currency_id = c_maker.id
-         JOIN currency c_taker ON cs.taker_currency_id = c_taker.id
-         WHERE 1=1{}{}{}",
-        if status_filter != "all" { format!(" AND cs.status = '{}'", status_filter) } else { String::new() },
-        if let Some(base_ticker) = base_currency { format!(" AND UPPER(c_maker.ticker) = UPPER('{}')", base_ticker) } else { String::new() },
-        if let Some(quote_ticker) = quote_currency { format!(" AND UPPER(c_taker.ticker) = UPPER('{}')", quote_ticker) } else { String::new() }
+            c_taker.ticker,
+            cs.filled_amount",
+        sort,
+        page_size as i64,
+        offset as i64,
     );
     
-    let total_count: (i64,) = sqlx::query_as(&count_query)
-        .fetch_one(pool)
-        .await?;
-    
-    // Add LIMIT and OFFSET
-    query_str.push_str(&format!(" LIMIT {} OFFSET {}", page_size, offset));
-    
     // Execute query
-    let swaps = sqlx::query_as::<_, (i64, i64, Option<i64>, i64, i64, f64, f64, String, String, String)>(
-        &query_str
-    )
-    .fetch_all(pool)
-    .await?;
+    let swaps = query
+        .build_query_as::<(i64, i64, Option<i64>, i64, i64, Amount, Amount, String, String, String, Amount)>()
+        .fetch_all(pool)
+        .await?;
     
     Ok((swaps, total_count.0))
 }
 
-/// Get total maker amount in pending/open swaps for a currency
+/// Get total maker amount still held in escrow by pending swaps for a currency
 pub async fn get_total_swap_maker_amount(
     pool: &MySqlPool,
     currency_id: i64,
-) -> Result<Option<f64>, sqlx::Error> {
-    let row = sqlx::query("SELECT CAST(SUM(CAST(maker_amount AS DOUBLE)) AS DOUBLE) as total FROM currency_swap WHERE maker_currency_id = ? AND status = 'pending'")
+) -> Result<Option<Amount>, sqlx::Error> {
+    let row = sqlx::query("SELECT SUM(maker_amount - filled_amount) as total FROM currency_swap WHERE maker_currency_id = ? AND status = 'pending'")
         .bind(currency_id)
         .fetch_optional(pool)
         .await?;
 
-    Ok(row.and_then(|r| r.get::<Option<f64>, _>("total")))
+    Ok(row.and_then(|r| r.get::<Option<Amount>, _>("total")))
 }
 
@@ -1,5 +1,8 @@
 use sqlx::mysql::MySqlPool;
-use sqlx::Row;
+use sqlx::{QueryBuilder, Row};
+use crate::utils::Amount;
+use super::candle::{self, Resolution};
+use super::filter::{IntervalUnit, PriceFilter, TimeWindow};
 
 /// Normalize currency pair to canonical order (alphabetically by ticker)
 /// Returns (base_currency_id, quote_currency_id, is_reversed)
@@ -27,21 +30,29 @@ pub async fn normalize_pair(
     }
 }
 
-/// Add a price log entry for a currency pair
+/// Add a price log entry for a currency pair and fold the trade into its candles
 /// base_currency_id and quote_currency_id should be in canonical order (alphabetically sorted by ticker)
+/// `volume` is the base currency traded and `quote_volume` the quote currency paid for it
 pub async fn add_price_log(
     pool: &MySqlPool,
     base_currency_id: i64,
     quote_currency_id: i64,
-    price: f64,
+    price: Amount,
+    volume: Amount,
+    quote_volume: Amount,
 ) -> Result<i64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
     let result = sqlx::query("INSERT INTO tradelog (base_currency_id, quote_currency_id, price) VALUES (?, ?, ?)")
         .bind(base_currency_id)
         .bind(quote_currency_id)
         .bind(price)
-        .execute(pool)
+        .execute(&mut *tx)
         .await?;
 
+    candle::record_trade(&mut *tx, base_currency_id, quote_currency_id, price, volume, quote_volume).await?;
+
+    tx.commit().await?;
     Ok(result.last_insert_id() as i64)
 }
 
@@ -52,9 +63,10 @@ pub async fn get_latest_price_for_pair(
     base_currency_id: i64,
     quote_currency_id: i64,
 ) -> Result<Option<(f64, bool)>, sqlx::Error> {
+    // The close of the newest minute candle is the last traded price
     // First, get the price as a string to handle DECIMAL properly
     let row: Option<(String,)> = sqlx::query_as(
-        "SELECT CAST(price AS CHAR) as price_str FROM tradelog WHERE base_currency_id = ? AND quote_currency_id = ? ORDER BY date_created DESC LIMIT 1"
+        "SELECT CAST(close AS CHAR) as price_str FROM price_candle WHERE base_currency_id = ? AND quote_currency_id = ? AND resolution = '1m' ORDER BY bucket_start DESC LIMIT 1"
     )
     .bind(base_currency_id)
     .bind(quote_currency_id)
@@ -107,33 +119,6 @@ pub async fn get_price_logs_for_pair(
     .await
 }
 
-/// Get price logs for a currency pair with timestamps (for charting)
-/// Returns: (id, price, date_created as string)
-pub async fn get_price_logs_with_timestamps(
-    pool: &MySqlPool,
-    base_currency_id: i64,
-    quote_currency_id: i64,
-) -> Result<Vec<(i64, f64, String)>, sqlx::Error> {
-    let rows = sqlx::query(
-        "SELECT id, CAST(price AS CHAR) as price_str, DATE_FORMAT(date_created, '%Y-%m-%d %H:%i:%s') as date_str FROM tradelog WHERE base_currency_id = ? AND quote_currency_id = ? ORDER BY date_created ASC"
-    )
-    .bind(base_currency_id)
-    .bind(quote_currency_id)
-    .fetch_all(pool)
-    .await?;
-
-    Ok(rows
-        .into_iter()
-        .filter_map(|row| {
-            let id: i64 = row.get(0);
-            let price_str: String = row.get(1);
-            let date_str: String = row.get(2);
-            let price = price_str.parse::<f64>().ok()?;
-            Some((id, price, date_str))
-        })
-        .collect())
-}
-
 /// Get price logs for a currency pair within a date range
 pub async fn get_price_logs_in_range(
     pool: &MySqlPool,
@@ -154,89 +139,77 @@ pub async fn get_price_logs_in_range(
 }
 
 /// Calculate VWAP (Volume Weighted Average Price) for a currency pair
-/// Queries accepted swaps from currency_swap table within the specified timeframe
-/// Timeframe examples: "1 MINUTE", "1 HOUR", "1 DAY", "7 DAY", "30 DAY", "1 YEAR"
-/// Returns the VWAP as f64, or None if no accepted swaps exist in the timeframe
+/// Sums the candle volumes of every trade (swap fills and order book fills) within `window`
+/// The window is rounded to whole candles, minutes for windows up to hours, hours for days, days beyond that
+/// Returns the VWAP as f64, or None if no trades exist in the window
 pub async fn calculate_vwap(
     pool: &MySqlPool,
     base_currency_id: i64,
     quote_currency_id: i64,
-    timeframe: &str,
+    window: TimeWindow,
 ) -> Result<Option<f64>, sqlx::Error> {
-    // Query accepted swaps for this currency pair
-    // Price = taker_amount / maker_amount (quote / base)
-    // Volume = maker_amount (base currency volume)
-    // VWAP = Σ((taker_amount / maker_amount) × maker_amount) / Σ(maker_amount)
-    //      = Σ(taker_amount) / Σ(maker_amount)
-    let sql = format!(
+    let resolution = match window.unit {
+        IntervalUnit::Minute | IntervalUnit::Hour => Resolution::Minute,
+        IntervalUnit::Day => Resolution::Hour,
+        IntervalUnit::Month | IntervalUnit::Year => Resolution::Day,
+    };
+
+    // VWAP = Σ(price × base volume) / Σ(base volume) = Σ(quote volume) / Σ(base volume)
+    let mut query = QueryBuilder::new(
         "SELECT 
-            COALESCE(CAST(SUM(CAST(cs.taker_amount AS DECIMAL(20,8))) AS CHAR), '0') as total_taker,
-            COALESCE(CAST(SUM(CAST(cs.maker_amount AS DECIMAL(20,8))) AS CHAR), '0') as total_maker
-         FROM currency_swap cs
-         WHERE cs.maker_currency_id = ? 
-           AND cs.taker_currency_id = ?
-           AND cs.status = 'accepted'
-           AND cs.date_created >= DATE_SUB(NOW(), INTERVAL {})",
-        timeframe
+            COALESCE(SUM(quote_volume), 0) as total_quote,
+            COALESCE(SUM(volume), 0) as total_base
+         FROM price_candle
+         WHERE base_currency_id = "
     );
+    query.push_bind(base_currency_id);
+    query.push(" AND quote_currency_id = ").push_bind(quote_currency_id);
+    query.push(" AND resolution = ").push_bind(resolution.as_str());
+    window.push_since(&mut query, "bucket_start");
     
-    let result: Option<(String, String)> = sqlx::query_as(&sql)
-        .bind(base_currency_id)
-        .bind(quote_currency_id)
+    let result: Option<(Amount, Amount)> = query
+        .build_query_as()
         .fetch_optional(pool)
         .await?;
 
-    match result {
-        Some((total_taker_str, total_maker_str)) => {
-            let total_taker: f64 = total_taker_str.parse().unwrap_or(0.0);
-            let total_maker: f64 = total_maker_str.parse().unwrap_or(0.0);
-
-            if total_maker > 0.0 {
-                Ok(Some(total_taker / total_maker))
-            } else {
-                Ok(None)
-            }
-        }
-        None => Ok(None),
-    }
+    // Divide the exact totals so the VWAP doesn't accumulate float error
+    Ok(result
+        .and_then(|(total_quote, total_base)| total_quote.checked_div(total_base))
+        .map(|vwap| vwap.to_f64()))
 }
 
 /// Get all latest prices, optionally filtered by base or quote ticker
 /// Returns: Vec<(base_ticker, quote_ticker, price)>
-/// If filter_base is Some("ABC"), returns all pairs like ABC/XYZ
-/// If filter_quote is Some("XYZ"), returns all pairs like ABC/XYZ
-/// If both are None, returns all pairs sorted by most recent
+/// A base filter like ABC returns all pairs like ABC/XYZ, a quote filter like XYZ all pairs like ABC/XYZ
+/// Without filters, returns all pairs sorted by most recent
 pub async fn get_latest_prices_with_filter(
     pool: &MySqlPool,
-    filter_base: Option<&str>,
-    filter_quote: Option<&str>,
+    filter: &PriceFilter,
 ) -> Result<Vec<(String, String, f64)>, sqlx::Error> {
-    let mut query = String::from(
+    // Each pair's newest minute candle holds its last price
+    let mut query = QueryBuilder::new(
         "SELECT 
             c1.ticker as base_ticker,
             c2.ticker as quote_ticker,
-            (SELECT CAST(price AS CHAR) FROM tradelog 
-             WHERE base_currency_id = tl.base_currency_id 
-             AND quote_currency_id = tl.quote_currency_id 
-             ORDER BY date_created DESC LIMIT 1) as latest_price,
-            MAX(tl.date_created) as max_date
-         FROM tradelog tl
-         JOIN currency c1 ON tl.base_currency_id = c1.id
-         JOIN currency c2 ON tl.quote_currency_id = c2.id
-         WHERE 1=1"
+            CAST(pc.close AS CHAR) as latest_price,
+            pc.bucket_start as max_date
+         FROM price_candle pc
+         JOIN (SELECT base_currency_id, quote_currency_id, MAX(bucket_start) as latest
+               FROM price_candle
+               WHERE resolution = '1m'
+               GROUP BY base_currency_id, quote_currency_id) newest
+           ON pc.base_currency_id = newest.base_currency_id
+          AND pc.quote_currency_id = newest.quote_currency_id
+          AND pc.bucket_start = newest.latest
+         JOIN currency c1 ON pc.base_currency_id = c1.id
+         JOIN currency c2 ON pc.quote_currency_id = c2.id
+         WHERE pc.resolution = '1m'"
     );
 
-    if let Some(base) = filter_base {
-        query.push_str(&format!(" AND c1.ticker = UPPER('{}')", base.to_uppercase()));
-    }
-
-    if let Some(quote) = filter_quote {
-        query.push_str(&format!(" AND c2.ticker = UPPER('{}')", quote.to_uppercase()));
-    }
-
-    query.push_str(" GROUP BY tl.base_currency_id, tl.quote_currency_id ORDER BY max_date DESC");
+    filter.push_conditions(&mut query);
+    query.push(" ORDER BY max_date DESC");
 
-    let rows = sqlx::query(&query).fetch_all(pool).await?;
+    let rows = query.build().fetch_all(pool).await?;
 
     Ok(rows
         .into_iter()
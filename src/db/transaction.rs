@@ -1,33 +1,26 @@
 use sqlx::mysql::MySqlPool;
+use crate::utils::Amount;
 
-/// Create a new transaction record
-pub async fn create_transaction(
-    pool: &MySqlPool,
-    uuid: &str,
-    sender_id: i64,
-    receiver_id: i64,
-    amount: f64,
-) -> Result<(), sqlx::Error> {
-    sqlx::query(
-        "INSERT INTO transaction (uuid, sender_id, receiver_id, amount) VALUES (?, ?, ?, ?)"
-    )
-    .bind(uuid)
-    .bind(sender_id)
-    .bind(receiver_id)
-    .bind(amount)
-    .execute(pool)
-    .await?;
+/// Longest memo a transaction can carry, in characters
+pub const MAX_MEMO_LENGTH: usize = 200;
 
-    Ok(())
+/// LIKE pattern matching memos that contain `search`, with wildcards escaped so
+/// the text is matched literally
+pub fn memo_pattern(search: &str) -> String {
+    let escaped = search
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
 }
 
-/// Get transaction by UUID - returns (sender_id, receiver_id, date_created, amount, uuid)
+/// Get transaction by UUID - returns (sender_id, receiver_id, date_created, amount, uuid, memo)
 pub async fn get_transaction_by_uuid(
     pool: &MySqlPool,
     uuid: &str,
-) -> Result<Option<(i64, i64, String, f64, String)>, sqlx::Error> {
-    sqlx::query_as::<_, (i64, i64, String, f64, String)>(
-        "SELECT sender_id, receiver_id, DATE_FORMAT(date_created, '%Y-%m-%d %H:%i:%s'), CAST(amount AS DOUBLE), uuid FROM transaction WHERE uuid = ?"
+) -> Result<Option<(i64, i64, String, Amount, String, Option<String>)>, sqlx::Error> {
+    sqlx::query_as::<_, (i64, i64, String, Amount, String, Option<String>)>(
+        "SELECT sender_id, receiver_id, DATE_FORMAT(date_created, '%Y-%m-%d %H:%i:%s'), amount, uuid, memo FROM transaction WHERE uuid = ?"
     )
     .bind(uuid)
     .fetch_optional(pool)
@@ -38,8 +31,8 @@ pub async fn get_transaction_by_uuid(
 pub async fn get_transaction(
     pool: &MySqlPool,
     uuid: &str,
-) -> Result<Option<(String, i64, i64, f64)>, sqlx::Error> {
-    sqlx::query_as::<_, (String, i64, i64, f64)>(
+) -> Result<Option<(String, i64, i64, Amount)>, sqlx::Error> {
+    sqlx::query_as::<_, (String, i64, i64, Amount)>(
         "SELECT uuid, sender_id, receiver_id, amount FROM transaction WHERE uuid = ?"
     )
     .bind(uuid)
@@ -51,8 +44,8 @@ pub async fn get_transaction(
 pub async fn get_transactions_by_sender(
     pool: &MySqlPool,
     sender_id: i64,
-) -> Result<Vec<(String, i64, i64, f64)>, sqlx::Error> {
-    sqlx::query_as::<_, (String, i64, i64, f64)>(
+) -> Result<Vec<(String, i64, i64, Amount)>, sqlx::Error> {
+    sqlx::query_as::<_, (String, i64, i64, Amount)>(
         "SELECT uuid, sender_id, receiver_id, amount FROM transaction WHERE sender_id = ?"
     )
     .bind(sender_id)
@@ -64,8 +57,8 @@ pub async fn get_transactions_by_sender(
 pub async fn get_transactions_by_receiver(
     pool: &MySqlPool,
     receiver_id: i64,
-) -> Result<Vec<(String, i64, i64, f64)>, sqlx::Error> {
-    sqlx::query_as::<_, (String, i64, i64, f64)>(
+) -> Result<Vec<(String, i64, i64, Amount)>, sqlx::Error> {
+    sqlx::query_as::<_, (String, i64, i64, Amount)>(
         "SELECT uuid, sender_id, receiver_id, amount FROM transaction WHERE receiver_id = ?"
     )
     .bind(receiver_id)
@@ -79,7 +72,7 @@ pub async fn get_user_transactions(
     pool: &MySqlPool,
     account_id: i64,
     limit: u32,
-) -> Result<Vec<(i64, i64, f64, String, String, String)>, sqlx::Error> {
+) -> Result<Vec<(i64, i64, Amount, String, String, String)>, sqlx::Error> {
     // First need to get all account IDs for this Discord ID (one per currency)
     let discord_id = account_id;
     let account_query = sqlx::query_as::<_, (i64,)>(
@@ -98,7 +91,7 @@ pub async fn get_user_transactions(
     
     // Build a query that checks if sender_id or receiver_id match any of the user's account IDs
     let mut query_str = String::from(
-        "SELECT t.sender_id, t.receiver_id, CAST(t.amount AS DOUBLE), DATE_FORMAT(t.date_created, '%Y-%m-%d %H:%i:%s'), t.uuid, \
+        "SELECT t.sender_id, t.receiver_id, t.amount, DATE_FORMAT(t.date_created, '%Y-%m-%d %H:%i:%s'), t.uuid, \
          COALESCE((SELECT c.ticker FROM currency c JOIN account a ON a.currency_id = c.id WHERE a.id = t.sender_id LIMIT 1), '') AS ticker \
          FROM transaction t \
          WHERE "
@@ -118,7 +111,7 @@ pub async fn get_user_transactions(
     query_str.push_str(&or_conditions.join(""));
     query_str.push_str(" ORDER BY t.date_created DESC LIMIT ?");
     
-    let mut query = sqlx::query_as::<_, (i64, i64, f64, String, String, String)>(&query_str);
+    let mut query = sqlx::query_as::<_, (i64, i64, Amount, String, String, String)>(&query_str);
     
     // Bind all account IDs (each appears twice: once for sender check, once for receiver check)
     for &acct_id in &account_ids {
@@ -129,15 +122,42 @@ pub async fn get_user_transactions(
     query.fetch_all(pool).await
 }
 
+/// UUID of the newest transaction from one user to another in a currency whose memo
+/// is exactly `memo`
+pub async fn find_transaction_by_memo(
+    pool: &MySqlPool,
+    sender_discord_id: i64,
+    receiver_discord_id: i64,
+    currency_id: i64,
+    memo: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT t.uuid FROM transaction t
+         JOIN account s ON t.sender_id = s.id
+         JOIN account r ON t.receiver_id = r.id
+         WHERE s.discord_id = ? AND r.discord_id = ? AND s.currency_id = ? AND r.currency_id = ? AND t.memo = ?
+         ORDER BY t.date_created DESC LIMIT 1"
+    )
+    .bind(sender_discord_id)
+    .bind(receiver_discord_id)
+    .bind(currency_id)
+    .bind(currency_id)
+    .bind(memo)
+    .fetch_optional(pool)
+    .await
+}
+
 /// Get paginated transactions for a user (as sender or receiver) across all their accounts
-/// Returns: Vec<(sender_id, receiver_id, amount, date_created, uuid, currency_ticker)>
-/// Supports: page number, page size, automatic OFFSET calculation
+/// Returns: Vec<(sender_id, receiver_id, amount, date_created, uuid, currency_ticker, memo)>
+/// Supports: page number, page size, automatic OFFSET calculation, and only
+/// keeping transactions whose memo contains `memo_search`
 pub async fn get_user_transactions_paginated(
     pool: &MySqlPool,
     account_id: i64,
     page: usize,
     page_size: usize,
-) -> Result<(Vec<(i64, i64, f64, String, String, String)>, i64), sqlx::Error> {
+    memo_search: Option<&str>,
+) -> Result<(Vec<(i64, i64, Amount, String, String, String, Option<String>)>, i64), sqlx::Error> {
     // First get all account IDs for this Discord ID (one per currency)
     let discord_id = account_id;
     let account_query = sqlx::query_as::<_, (i64,)>(
@@ -155,7 +175,7 @@ pub async fn get_user_transactions_paginated(
     
     // First, get total count of transactions
     let mut count_query_str = String::from(
-        "SELECT COUNT(*) as count FROM transaction t WHERE "
+        "SELECT COUNT(*) as count FROM transaction t WHERE ("
     );
     
     let or_conditions_count: Vec<String> = (0..account_ids.len())
@@ -169,11 +189,18 @@ pub async fn get_user_transactions_paginated(
         .collect();
     
     count_query_str.push_str(&or_conditions_count.join(""));
+    count_query_str.push(')');
+    if memo_search.is_some() {
+        count_query_str.push_str(" AND t.memo LIKE ?");
+    }
     
     let mut count_query = sqlx::query_as::<_, (i64,)>(&count_query_str);
     for &acct_id in &account_ids {
         count_query = count_query.bind(acct_id).bind(acct_id);
     }
+    if let Some(search) = memo_search {
+        count_query = count_query.bind(memo_pattern(search));
+    }
     
     let (total_count,) = count_query.fetch_one(pool).await?;
 
@@ -182,10 +209,11 @@ pub async fn get_user_transactions_paginated(
     
     // Build the paginated query
     let mut query_str = String::from(
-        "SELECT t.sender_id, t.receiver_id, CAST(t.amount AS DOUBLE), DATE_FORMAT(t.date_created, '%Y-%m-%d %H:%i:%s'), t.uuid, \
-         COALESCE((SELECT c.ticker FROM currency c JOIN account a ON a.currency_id = c.id WHERE a.id = t.sender_id LIMIT 1), '') AS ticker \
+        "SELECT t.sender_id, t.receiver_id, t.amount, DATE_FORMAT(t.date_created, '%Y-%m-%d %H:%i:%s'), t.uuid, \
+         COALESCE((SELECT c.ticker FROM currency c JOIN account a ON a.currency_id = c.id WHERE a.id = t.sender_id LIMIT 1), '') AS ticker, \
+         t.memo \
          FROM transaction t \
-         WHERE "
+         WHERE ("
     );
     
     // Add conditions for all account IDs
@@ -200,14 +228,21 @@ pub async fn get_user_transactions_paginated(
         .collect();
     
     query_str.push_str(&or_conditions.join(""));
+    query_str.push(')');
+    if memo_search.is_some() {
+        query_str.push_str(" AND t.memo LIKE ?");
+    }
     query_str.push_str(" ORDER BY t.date_created DESC LIMIT ? OFFSET ?");
     
-    let mut query = sqlx::query_as::<_, (i64, i64, f64, String, String, String)>(&query_str);
+    let mut query = sqlx::query_as::<_, (i64, i64, Amount, String, String, String, Option<String>)>(&query_str);
     
     // Bind all account IDs (each appears twice: once for sender check, once for receiver check)
     for &acct_id in &account_ids {
         query = query.bind(acct_id).bind(acct_id);
     }
+    if let Some(search) = memo_search {
+        query = query.bind(memo_pattern(search));
+    }
     query = query.bind(page_size as i64).bind(offset as i64);
     
     let transactions = query.fetch_all(pool).await?;
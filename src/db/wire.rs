@@ -0,0 +1,278 @@
+//! Wire transfer journal and per-currency wire settings.
+//!
+//! A wire moves from 'initiated' to 'smite_applied' when the SMITE balance
+//! changes, then to 'remote_applied' once UnbelievaBoat has been updated, or to
+//! 'compensated' if the SMITE change was reversed. 'failed' means the SMITE
+//! balance was never touched. Both balance steps change the state in the same
+//! transaction as the balance, so the journal always says which side was applied.
+//! `amount` is what the user's balance moves by and `fee` what the tax account
+//! gets; `remote_amount` is the whole UnbelievaBoat units moved.
+
+use sqlx::mysql::{MySql, MySqlPool};
+use sqlx::Transaction;
+use crate::storage::{NewWire, WireRow};
+use crate::utils::{Amount, Rounding};
+use crate::utils::errors::LedgerError;
+use super::ledger::{lock_account, set_balance, with_deadlock_retry};
+
+/// How a currency's wires convert and what they cost
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WireConfig {
+    /// SMITE units one UnbelievaBoat unit is worth
+    pub rate: Amount,
+    /// Limits on the SMITE amount of one transfer
+    pub min_amount: Option<Amount>,
+    pub max_amount: Option<Amount>,
+    /// Bridge fee in SMITE units, paid into the tax account
+    pub fee_percent: Amount,
+    pub fee_flat: Amount,
+    /// How SMITE amounts become whole UnbelievaBoat units
+    pub rounding: Rounding,
+}
+
+impl Default for WireConfig {
+    /// 1:1 with no limits or fee, as wires worked before they were configurable
+    fn default() -> Self {
+        WireConfig {
+            rate: Amount::from_whole(1),
+            min_amount: None,
+            max_amount: None,
+            fee_percent: Amount::ZERO,
+            fee_flat: Amount::ZERO,
+            rounding: Rounding::Down,
+        }
+    }
+}
+
+/// (rate, min_amount, max_amount, fee_percent, fee_flat, rounding) as stored
+pub type WireConfigRow = (Amount, Option<Amount>, Option<Amount>, Amount, Amount, String);
+
+impl From<WireConfigRow> for WireConfig {
+    fn from((rate, min_amount, max_amount, fee_percent, fee_flat, rounding): WireConfigRow) -> Self {
+        WireConfig {
+            rate,
+            min_amount,
+            max_amount,
+            fee_percent,
+            fee_flat,
+            rounding: Rounding::parse(&rounding).unwrap_or_default(),
+        }
+    }
+}
+
+const SELECT_WIRE: &str = "SELECT CAST(w.id AS SIGNED), w.discord_id, CAST(w.currency_id AS SIGNED), c.ticker,
+        CAST(w.direction AS CHAR), w.amount, w.fee, w.remote_amount, CAST(w.state AS CHAR), w.remote_before, w.last_error,
+        CAST(UNIX_TIMESTAMP(w.date_created) AS SIGNED), CAST(UNIX_TIMESTAMP(w.date_updated) AS SIGNED)
+     FROM wire_transfer w
+     JOIN currency c ON w.currency_id = c.id";
+
+pub async fn create_wire(pool: &MySqlPool, wire: NewWire) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO wire_transfer (discord_id, currency_id, direction, amount, fee, remote_amount)
+         VALUES (?, ?, ?, ?, ?, ?)"
+    )
+    .bind(wire.discord_id)
+    .bind(wire.currency_id)
+    .bind(wire.direction)
+    .bind(wire.amount)
+    .bind(wire.fee)
+    .bind(wire.remote_amount)
+    .execute(pool)
+    .await?;
+
+    Ok(result.last_insert_id() as i64)
+}
+
+pub async fn get_wire(pool: &MySqlPool, id: i64) -> Result<Option<WireRow>, sqlx::Error> {
+    sqlx::query_as(&format!("{} WHERE w.id = ?", SELECT_WIRE))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// The user's wires, newest first
+pub async fn get_wires_by_user(pool: &MySqlPool, discord_id: i64, limit: i64) -> Result<Vec<WireRow>, sqlx::Error> {
+    sqlx::query_as(&format!("{} WHERE w.discord_id = ? ORDER BY w.id DESC LIMIT ?", SELECT_WIRE))
+        .bind(discord_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+}
+
+/// Wires still 'initiated' or 'smite_applied' that haven't changed for `idle_seconds`,
+/// oldest first, optionally for one currency
+pub async fn get_unsettled_wires(
+    pool: &MySqlPool,
+    currency_id: Option<i64>,
+    idle_seconds: i64,
+    limit: i64,
+) -> Result<Vec<WireRow>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "{} WHERE w.state IN ('initiated', 'smite_applied') AND w.date_updated <= NOW() - INTERVAL ? SECOND
+           AND (? IS NULL OR w.currency_id = ?)
+         ORDER BY w.id LIMIT ?",
+        SELECT_WIRE
+    ))
+    .bind(idle_seconds)
+    .bind(currency_id)
+    .bind(currency_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Apply the SMITE side of an initiated wire: credit the user for 'in', debit them
+/// for 'out', and pay the fee into the tax account. Returns the new balance.
+pub async fn apply_wire(pool: &MySqlPool, id: i64) -> Result<Amount, LedgerError> {
+    with_deadlock_retry(|| move_balance(pool, id, "initiated", "smite_applied", false)).await
+}
+
+/// Reverse the SMITE side of a wire whose remote side didn't happen.
+/// Returns the new balance.
+pub async fn compensate_wire(pool: &MySqlPool, id: i64) -> Result<Amount, LedgerError> {
+    with_deadlock_retry(|| move_balance(pool, id, "smite_applied", "compensated", true)).await
+}
+
+/// Note the UnbelievaBoat bank balance seen just before the remote side is changed.
+/// Returns false if the wire isn't 'smite_applied'.
+pub async fn record_wire_remote_balance(pool: &MySqlPool, id: i64, balance: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE wire_transfer SET remote_before = ? WHERE id = ? AND state = 'smite_applied'")
+        .bind(balance)
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Move a wire from one state to another, recording `error` if given.
+/// Returns false if it wasn't in `from`.
+pub async fn set_wire_state(
+    pool: &MySqlPool,
+    id: i64,
+    from: &str,
+    to: &str,
+    error: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE wire_transfer SET state = ?, last_error = COALESCE(?, last_error), date_updated = CURRENT_TIMESTAMP
+         WHERE id = ? AND state = ?"
+    )
+    .bind(to)
+    .bind(error.map(truncate_error))
+    .bind(id)
+    .bind(from)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Change the user's balance by the wire's amount and the tax account by its fee,
+/// and move it from `from` to `to`. Credits the user for 'in', debits them for 'out';
+/// `reverse` swaps the two and takes the fee back.
+async fn move_balance(pool: &MySqlPool, id: i64, from: &'static str, to: &str, reverse: bool) -> Result<Amount, LedgerError> {
+    let mut tx = pool.begin().await?;
+
+    let (discord_id, currency_id, direction, amount, fee, state): (i64, i64, String, Amount, Amount, String) = sqlx::query_as(
+        "SELECT discord_id, CAST(currency_id AS SIGNED), CAST(direction AS CHAR), amount, fee, CAST(state AS CHAR)
+         FROM wire_transfer WHERE id = ? FOR UPDATE"
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(LedgerError::WireState(from))?;
+
+    if state != from {
+        return Err(LedgerError::WireState(from));
+    }
+
+    let credit = (direction == "in") != reverse;
+    let (account_id, current_balance) = lock_account(&mut tx, discord_id, currency_id, credit)
+        .await?
+        .ok_or(LedgerError::InsufficientBalance { required: amount, available: Amount::ZERO })?;
+
+    let new_balance = if credit { current_balance + amount } else { current_balance - amount };
+    if new_balance.is_negative() {
+        return Err(LedgerError::InsufficientBalance { required: amount, available: current_balance });
+    }
+    if !new_balance.is_within_limit() {
+        return Err(LedgerError::BalanceLimit { current: current_balance, new_balance });
+    }
+
+    set_balance(&mut tx, account_id, new_balance).await?;
+    if fee.is_positive() {
+        move_fee(&mut tx, currency_id, if reverse { -fee } else { fee }).await?;
+    }
+    sqlx::query("UPDATE wire_transfer SET state = ? WHERE id = ?")
+        .bind(to)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(new_balance)
+}
+
+/// Add `fee` (negative to take it back) to the currency's tax account
+async fn move_fee(tx: &mut Transaction<'_, MySql>, currency_id: i64, fee: Amount) -> Result<(), LedgerError> {
+    let tax_balance: Amount = sqlx::query_scalar("SELECT balance FROM tax_account WHERE currency_id = ? FOR UPDATE")
+        .bind(currency_id)
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or(LedgerError::TaxAccountNotFound)?;
+
+    let new_balance = tax_balance + fee;
+    if new_balance.is_negative() {
+        return Err(LedgerError::InsufficientBalance { required: -fee, available: tax_balance });
+    }
+
+    sqlx::query("UPDATE tax_account SET balance = ? WHERE currency_id = ?")
+        .bind(new_balance)
+        .bind(currency_id)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(())
+}
+
+/// The currency's wire settings, None if it has never been configured
+pub async fn get_wire_config(pool: &MySqlPool, currency_id: i64) -> Result<Option<WireConfig>, sqlx::Error> {
+    let row: Option<WireConfigRow> = sqlx::query_as(
+        "SELECT rate, min_amount, max_amount, fee_percent, fee_flat, CAST(rounding AS CHAR)
+         FROM wire_config WHERE currency_id = ?"
+    )
+    .bind(currency_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(WireConfig::from))
+}
+
+pub async fn set_wire_config(pool: &MySqlPool, currency_id: i64, config: &WireConfig) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO wire_config (currency_id, rate, min_amount, max_amount, fee_percent, fee_flat, rounding)
+         VALUES (?, ?, ?, ?, ?, ?, ?)
+         ON DUPLICATE KEY UPDATE rate = VALUES(rate), min_amount = VALUES(min_amount), max_amount = VALUES(max_amount),
+             fee_percent = VALUES(fee_percent), fee_flat = VALUES(fee_flat), rounding = VALUES(rounding)"
+    )
+    .bind(currency_id)
+    .bind(config.rate)
+    .bind(config.min_amount)
+    .bind(config.max_amount)
+    .bind(config.fee_percent)
+    .bind(config.fee_flat)
+    .bind(config.rounding.as_str())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Errors are stored in a VARCHAR(255)
+pub fn truncate_error(error: &str) -> &str {
+    match error.char_indices().nth(255) {
+        Some((end, _)) => &error[..end],
+        None => error,
+    }
+}
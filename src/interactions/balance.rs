@@ -0,0 +1,21 @@
+use serenity::builder::CreateCommand;
+use serenity::model::application::CommandInteraction;
+use serenity::prelude::Context;
+use crate::interactions::options::Options;
+use crate::services::balance_service;
+use crate::utils::Invocation;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("balance")
+        .description("Check your account balance")
+        .add_option(super::currency_option("currency", "Currency ticker (defaults to this server's currency)", false))
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> Result<(), String> {
+    let options = Options::of(command);
+    let ticker = options.ticker("currency");
+
+    let result = balance_service::get_balance(ctx, &Invocation::from(command), ticker.as_deref()).await?;
+    super::respond(ctx, command, balance_service::create_balance_embed(&result)).await?;
+    Ok(())
+}
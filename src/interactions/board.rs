@@ -0,0 +1,28 @@
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::model::application::{CommandInteraction, CommandOptionType};
+use serenity::prelude::Context;
+use crate::interactions::options::Options;
+use crate::services::board_service;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("board")
+        .description("List all currencies")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "sort", "Sort order")
+                .add_string_choice("Oldest", "oldest")
+                .add_string_choice("Recent", "recent"),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::Integer, "page", "Page number")
+                .min_int_value(1),
+        )
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> Result<(), String> {
+    let options = Options::of(command);
+    let sort_by = options.string("sort").unwrap_or("oldest");
+    let page_num = options.integer("page").unwrap_or(1) as usize;
+
+    let page = board_service::list_currencies(ctx, sort_by, page_num).await?;
+    super::respond_with_page(ctx, command, page).await
+}
@@ -0,0 +1,20 @@
+use serenity::builder::CreateCommand;
+use serenity::model::application::CommandInteraction;
+use serenity::prelude::Context;
+use crate::interactions::options::Options;
+use crate::services::info_service;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("info")
+        .description("Get detailed information about a currency")
+        .add_option(super::currency_option("currency", "Currency ticker", true))
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> Result<(), String> {
+    let options = Options::of(command);
+    let ticker = options.require_ticker("currency")?;
+
+    let info = info_service::execute_info(ctx, &ticker).await?;
+    super::respond(ctx, command, info_service::create_info_embed(&info)).await?;
+    Ok(())
+}
@@ -0,0 +1,64 @@
+//! Pay and Decline buttons on invoices. Invoices are created with `$invoice`;
+//! there is no slash command for them yet.
+
+use serenity::builder::{
+    CreateInteractionResponse, CreateInteractionResponseFollowup, CreateInteractionResponseMessage,
+    EditInteractionResponse,
+};
+use serenity::model::application::ComponentInteraction;
+use serenity::prelude::Context;
+use tracing::{debug, error};
+use crate::services::invoice_service::{self, InvoiceAction};
+use crate::utils::Invocation;
+
+/// Pay or Decline pressed on an invoice. On success the invoice message shows the
+/// outcome and loses its buttons; failures get a reply only the presser sees.
+pub async fn handle_button(ctx: &Context, component: &ComponentInteraction, action: InvoiceAction, id: i64) {
+    if let Err(remaining_ms) = crate::utils::check_global_rate_limit().await {
+        reply_ephemeral(ctx, component, &format!("⚠️ Server is handling too many requests. Please wait {}ms and try again.", remaining_ms)).await;
+        return;
+    }
+
+    // Acknowledge the press without changing the message yet
+    if let Err(e) = component.defer(&ctx.http).await {
+        debug!("Failed to acknowledge invoice button: {}", e);
+        return;
+    }
+
+    let invocation = Invocation::from(component);
+    let result = match action {
+        InvoiceAction::Pay => invoice_service::pay_invoice(ctx, &invocation, id).await,
+        InvoiceAction::Decline => invoice_service::decline_invoice(ctx, &invocation, id).await,
+    };
+
+    match result {
+        Ok(embed) => {
+            let edit = EditInteractionResponse::new().embed(embed).components(Vec::new());
+            if let Err(e) = component.edit_response(&ctx.http, edit).await {
+                debug!("Failed to update invoice message: {}", e);
+            }
+        }
+        Err(e) => {
+            error!("Invoice button for {} failed: {}", id, e);
+            let followup = CreateInteractionResponseFollowup::new()
+                .embed(crate::commands::create_error_embed(&e))
+                .ephemeral(true);
+            if let Err(e) = component.create_followup(&ctx.http, followup).await {
+                debug!("Failed to send invoice button error: {}", e);
+            }
+        }
+    }
+}
+
+/// Answer a button press that hasn't been acknowledged with a message only the user can see
+async fn reply_ephemeral(ctx: &Context, component: &ComponentInteraction, content: &str) {
+    let message = CreateInteractionResponseMessage::new()
+        .content(content)
+        .ephemeral(true);
+    if let Err(e) = component
+        .create_response(&ctx.http, CreateInteractionResponse::Message(message))
+        .await
+    {
+        debug!("Failed to respond to invoice button: {}", e);
+    }
+}
@@ -0,0 +1,24 @@
+use serenity::builder::CreateCommand;
+use serenity::model::application::CommandInteraction;
+use serenity::prelude::Context;
+use crate::interactions::options::Options;
+use crate::services::mint_service;
+use crate::utils::Invocation;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("mint")
+        .description("Mint currency to your account (Admin/Minter only)")
+        .add_option(super::amount_option("amount", "Amount to mint", true))
+        .add_option(super::currency_option("currency", "Currency ticker", true))
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> Result<(), String> {
+    let options = Options::of(command);
+    let amount = options.require_amount("amount")?;
+    let currency_ticker = options.require_ticker("currency")?;
+
+    let invocation = Invocation::from(command);
+    let result = mint_service::execute_mint(ctx, &invocation, invocation.user_id_i64(), amount, &currency_ticker).await?;
+    super::respond(ctx, command, mint_service::create_mint_embed(&result)).await?;
+    Ok(())
+}
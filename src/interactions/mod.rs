@@ -0,0 +1,231 @@
+//! Slash command front-end, running alongside the `$` prefix commands in `commands`.
+//!
+//! Each submodule registers one application command and maps its options onto the
+//! same `services::*` functions the prefix commands use, passing an `Invocation`
+//! built from the interaction.
+
+pub mod options;
+pub mod ping;
+pub mod balance;
+pub mod send;
+pub mod swap;
+pub mod price;
+pub mod info;
+pub mod board;
+pub mod tax;
+pub mod mint;
+pub mod wire;
+pub mod invoice;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use serenity::builder::{
+    CreateAutocompleteResponse, CreateCommandOption, CreateEmbed, CreateInteractionResponse,
+    CreateInteractionResponseMessage, EditInteractionResponse,
+};
+use serenity::model::application::{Command, CommandInteraction, CommandOptionType, ComponentInteraction, Interaction};
+use serenity::model::channel::Message;
+use serenity::prelude::Context;
+use tracing::{debug, error, info, warn};
+use crate::utils::page::Page;
+
+/// Discord shows at most 25 autocomplete choices
+const MAX_AUTOCOMPLETE_CHOICES: i64 = 25;
+
+static COMMANDS_REGISTERED: AtomicBool = AtomicBool::new(false);
+
+/// Register the global slash commands. Only the first call does any work, so this is
+/// safe to call from every shard's ready event.
+pub async fn register_commands(ctx: &Context) {
+    if COMMANDS_REGISTERED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let commands = vec![
+        ping::register(),
+        balance::register(),
+        send::register(),
+        swap::register(),
+        price::register(),
+        info::register(),
+        board::register(),
+        tax::register(),
+        mint::register(),
+        wire::register(),
+    ];
+
+    match Command::set_global_commands(&ctx.http, commands).await {
+        Ok(registered) => info!("Registered {} slash commands", registered.len()),
+        Err(e) => {
+            error!("Failed to register slash commands: {}", e);
+            // Allow the next ready event to try again
+            COMMANDS_REGISTERED.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+pub async fn handle_interaction(ctx: &Context, interaction: Interaction) {
+    match interaction {
+        Interaction::Command(command) => handle_command(ctx, &command).await,
+        Interaction::Autocomplete(command) => handle_autocomplete(ctx, &command).await,
+        Interaction::Component(component) => handle_component(ctx, &component).await,
+        _ => {}
+    }
+}
+
+async fn handle_command(ctx: &Context, command: &CommandInteraction) {
+    if command.user.bot {
+        return;
+    }
+
+    let start_time = Instant::now();
+    let command_name = command.data.name.as_str();
+
+    // Same limits as prefix commands
+    if let Err(remaining_ms) = crate::utils::check_global_rate_limit().await {
+        let embed = CreateEmbed::default()
+            .title("Global Rate Limit")
+            .description(format!("⚠️ Server is handling too many requests. Please wait {}ms and try again.", remaining_ms))
+            .color(0xff9900);
+        respond_ephemeral(ctx, command, embed).await;
+        return;
+    }
+
+    if let Err((remaining, _)) = crate::utils::check_cooldown(command.user.id, &format!("/{}", command_name)).await {
+        // Every interaction needs a response, so always tell the user
+        let embed = CreateEmbed::default()
+            .title("Command Cooldown")
+            .description(format!("⏳ Please wait {} seconds before using this command again.", remaining))
+            .color(0xffa500);
+        respond_ephemeral(ctx, command, embed).await;
+        return;
+    }
+
+    // Defer so slow database or API calls don't hit the 3 second response deadline.
+    // Wire responses are ephemeral since they can carry API tokens.
+    let deferred = if command_name == "wire" {
+        command.defer_ephemeral(&ctx.http).await
+    } else {
+        command.defer(&ctx.http).await
+    };
+    if let Err(e) = deferred {
+        warn!("Failed to defer /{}: {}", command_name, e);
+        return;
+    }
+
+    let result = match command_name {
+        "ping" => ping::run(ctx, command, start_time).await,
+        "balance" => balance::run(ctx, command).await,
+        "send" => send::run(ctx, command).await,
+        "swap" => swap::run(ctx, command).await,
+        "price" => price::run(ctx, command).await,
+        "info" => info::run(ctx, command).await,
+        "board" => board::run(ctx, command).await,
+        "tax" => tax::run(ctx, command).await,
+        "mint" => mint::run(ctx, command).await,
+        "wire" => wire::run(ctx, command).await,
+        _ => Err(format!("Unknown command: /{}", command_name)),
+    };
+
+    if let Err(e) = result {
+        error!("Error executing slash command {}: {}", command_name, e);
+
+        let _ = command
+            .edit_response(&ctx.http, EditInteractionResponse::new().embed(crate::commands::create_error_embed(&e)))
+            .await;
+    }
+}
+
+/// Buttons that outlive a single command run. Page buttons are handled by the
+/// collector spawned with the page, so anything unrecognised is left alone.
+async fn handle_component(ctx: &Context, component: &ComponentInteraction) {
+    if component.user.bot {
+        return;
+    }
+
+    if let Some((action, swap_id)) = crate::services::swap_service::parse_swap_button(&component.data.custom_id) {
+        swap::handle_button(ctx, component, action, swap_id).await;
+    } else if let Some((action, id)) = crate::services::invoice_service::parse_invoice_button(&component.data.custom_id) {
+        invoice::handle_button(ctx, component, action, id).await;
+    } else if let Some(button) = crate::services::wire_service::parse_wire_button(&component.data.custom_id) {
+        wire::handle_button(ctx, component, button).await;
+    }
+}
+
+/// Suggest currency tickers for whichever option is being typed
+async fn handle_autocomplete(ctx: &Context, command: &CommandInteraction) {
+    let Some(focused) = command.data.autocomplete() else {
+        return;
+    };
+
+    let storage = match crate::storage::from_ctx(ctx).await {
+        Ok(storage) => storage,
+        Err(_) => return,
+    };
+
+    let prefix = focused.value.trim().to_uppercase();
+    let currencies = match storage.search_tickers(&prefix, MAX_AUTOCOMPLETE_CHOICES).await {
+        Ok(currencies) => currencies,
+        Err(e) => {
+            warn!("Ticker autocomplete failed: {}", e);
+            Vec::new()
+        }
+    };
+
+    let mut response = CreateAutocompleteResponse::new();
+    for (name, ticker) in currencies {
+        // Choice names are limited to 100 characters
+        let label: String = format!("{} ({})", ticker, name).chars().take(100).collect();
+        response = response.add_string_choice(label, ticker);
+    }
+
+    if let Err(e) = command
+        .create_response(&ctx.http, CreateInteractionResponse::Autocomplete(response))
+        .await
+    {
+        debug!("Failed to send autocomplete choices: {}", e);
+    }
+}
+
+/// A string option that autocompletes currency tickers
+pub fn currency_option(name: &str, description: &str, required: bool) -> CreateCommandOption {
+    CreateCommandOption::new(CommandOptionType::String, name, description)
+        .required(required)
+        .set_autocomplete(true)
+}
+
+/// A string option for an exact decimal amount
+pub fn amount_option(name: &str, description: &str, required: bool) -> CreateCommandOption {
+    CreateCommandOption::new(CommandOptionType::String, name, description).required(required)
+}
+
+/// Replace the deferred response with an embed
+pub async fn respond(ctx: &Context, command: &CommandInteraction, embed: CreateEmbed) -> Result<Message, String> {
+    command
+        .edit_response(&ctx.http, EditInteractionResponse::new().embed(embed))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Replace the deferred response with a paginated embed and let the user page through it
+pub async fn respond_with_page(ctx: &Context, command: &CommandInteraction, mut page: Page) -> Result<(), String> {
+    let embed = page.current_embed().await?;
+    let message = command
+        .edit_response(&ctx.http, EditInteractionResponse::new().embed(embed).components(page.components()))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    page.spawn_navigation(ctx, message, command.user.id);
+    Ok(())
+}
+
+/// Answer an interaction that hasn't been deferred with a message only the user can see
+async fn respond_ephemeral(ctx: &Context, command: &CommandInteraction, embed: CreateEmbed) {
+    let message = CreateInteractionResponseMessage::new().embed(embed).ephemeral(true);
+    if let Err(e) = command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(message))
+        .await
+    {
+        debug!("Failed to respond to /{}: {}", command.data.name, e);
+    }
+}
@@ -0,0 +1,74 @@
+//! Typed access to slash command options
+
+use serenity::model::application::{CommandInteraction, ResolvedOption, ResolvedValue};
+use serenity::model::id::UserId;
+use crate::utils::Amount;
+
+pub struct Options<'a>(Vec<ResolvedOption<'a>>);
+
+impl<'a> Options<'a> {
+    pub fn of(command: &'a CommandInteraction) -> Self {
+        Options(command.data.options())
+    }
+
+    /// The invoked subcommand's name and its options
+    pub fn subcommand(self) -> Option<(&'a str, Options<'a>)> {
+        self.0.into_iter().find_map(|option| match option.value {
+            ResolvedValue::SubCommand(options) => Some((option.name, Options(options))),
+            _ => None,
+        })
+    }
+
+    fn value(&self, name: &str) -> Option<&ResolvedValue<'a>> {
+        self.0.iter().find(|option| option.name == name).map(|option| &option.value)
+    }
+
+    pub fn string(&self, name: &str) -> Option<&'a str> {
+        match self.value(name) {
+            Some(ResolvedValue::String(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn integer(&self, name: &str) -> Option<i64> {
+        match self.value(name) {
+            Some(ResolvedValue::Integer(n)) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn user(&self, name: &str) -> Option<UserId> {
+        match self.value(name) {
+            Some(ResolvedValue::User(user, _)) => Some(user.id),
+            _ => None,
+        }
+    }
+
+    pub fn require_string(&self, name: &str) -> Result<&'a str, String> {
+        self.string(name).ok_or_else(|| format!("Missing option `{}`", name))
+    }
+
+    pub fn require_integer(&self, name: &str) -> Result<i64, String> {
+        self.integer(name).ok_or_else(|| format!("Missing option `{}`", name))
+    }
+
+    /// Currency ticker, uppercased like the prefix commands do
+    pub fn ticker(&self, name: &str) -> Option<String> {
+        self.string(name).map(|s| s.trim().to_uppercase())
+    }
+
+    pub fn require_ticker(&self, name: &str) -> Result<String, String> {
+        self.ticker(name).ok_or_else(|| format!("Missing option `{}`", name))
+    }
+
+    /// Amounts are string options so they keep all 8 decimal places
+    pub fn amount(&self, name: &str) -> Result<Option<Amount>, String> {
+        self.string(name)
+            .map(|s| s.parse::<Amount>().map_err(|e| format!("❌ {}", e)))
+            .transpose()
+    }
+
+    pub fn require_amount(&self, name: &str) -> Result<Amount, String> {
+        self.amount(name)?.ok_or_else(|| format!("Missing option `{}`", name))
+    }
+}
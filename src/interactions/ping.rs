@@ -0,0 +1,16 @@
+use std::time::Instant;
+use serenity::builder::CreateCommand;
+use serenity::model::application::CommandInteraction;
+use serenity::prelude::Context;
+use crate::services::ping_service;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("ping").description("Check the bot's latency and uptime")
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction, start_time: Instant) -> Result<(), String> {
+    // Measured after the deferred response, like the prefix command's placeholder message
+    let metrics = ping_service::get_ping_metrics(ctx, start_time).await?;
+    super::respond(ctx, command, ping_service::create_ping_embed(&metrics)).await?;
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use serenity::builder::{CreateAttachment, CreateCommand, CreateCommandOption, EditInteractionResponse};
+use serenity::model::application::{CommandInteraction, CommandOptionType};
+use serenity::prelude::Context;
+use crate::interactions::options::Options;
+use crate::services::{chart_service, price_service};
+
+const ITEMS_PER_PAGE: usize = 10;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("price")
+        .description("Currency pair prices and charts")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "pair", "Show the VWAP and last traded price for a pair")
+                .add_sub_option(super::currency_option("base", "Base currency", true))
+                .add_sub_option(super::currency_option("quote", "Quote currency", true))
+                .add_sub_option(CreateCommandOption::new(CommandOptionType::String, "timeframe", "VWAP timeframe, e.g. 1h, 24h, 7d (default 24h)")),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "chart", "Generate a price chart for a pair")
+                .add_sub_option(super::currency_option("base", "Base currency", true))
+                .add_sub_option(super::currency_option("quote", "Quote currency", true))
+                .add_sub_option(CreateCommandOption::new(CommandOptionType::String, "timeframe", "Chart timeframe, e.g. 1d, 7d, 1M (default all)"))
+                .add_sub_option(CreateCommandOption::new(CommandOptionType::String, "candles", "Draw candles of this interval, e.g. 5m, 1h, 1d"))
+                .add_sub_option(CreateCommandOption::new(CommandOptionType::String, "moving_averages", "Moving average periods for candles, e.g. ma20 ma50")),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "list", "List the latest prices")
+                .add_sub_option(super::currency_option("base", "Only pairs with this base currency", false))
+                .add_sub_option(super::currency_option("quote", "Only pairs with this quote currency", false))
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Integer, "page", "Page number")
+                        .min_int_value(1),
+                ),
+        )
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> Result<(), String> {
+    let (subcommand, options) = Options::of(command)
+        .subcommand()
+        .ok_or("Missing subcommand".to_string())?;
+
+    let pool = {
+        let data = ctx.data.read().await;
+        data.get::<crate::DatabasePool>()
+            .ok_or(crate::db::MYSQL_ONLY.to_string())?
+            .clone()
+    };
+
+    match subcommand {
+        "pair" => {
+            let base_ticker = options.require_ticker("base")?;
+            let quote_ticker = options.require_ticker("quote")?;
+            let timeframe = options.string("timeframe").unwrap_or("24h");
+
+            let price_data = price_service::get_price(&pool, &base_ticker, &quote_ticker, timeframe).await?;
+            super::respond(ctx, command, price_service::create_price_embed(&price_data)).await?;
+        }
+        "chart" => {
+            let base_ticker = options.require_ticker("base")?;
+            let quote_ticker = options.require_ticker("quote")?;
+            let timeframe = options.string("timeframe").unwrap_or("all");
+
+            // Same argument syntax as `$price chart`
+            let mut args = vec![timeframe];
+            if let Some(interval) = options.string("candles") {
+                args.extend(["candles", interval]);
+            }
+            args.extend(options.string("moving_averages").unwrap_or("").split_whitespace());
+            let request = chart_service::parse_chart_args(&args)?;
+
+            let chart_data = match &request.candles {
+                Some(candles) => chart_service::generate_candle_chart(&pool, &base_ticker, &quote_ticker, timeframe, candles, 1024, 768).await?,
+                None => chart_service::generate_chart_with_timeframe(&pool, &base_ticker, &quote_ticker, timeframe, 1024, 768).await?,
+            };
+            let filename = format!("chart_{}_{}_{}.png", base_ticker, quote_ticker, timeframe);
+
+            command
+                .edit_response(
+                    &ctx.http,
+                    EditInteractionResponse::new().new_attachment(CreateAttachment::bytes(chart_data, filename)),
+                )
+                .await
+                .map_err(|e| format!("Failed to send chart: {}", e))?;
+        }
+        "list" => {
+            let filter_base = options.ticker("base");
+            let filter_quote = options.ticker("quote");
+            let page_num = options.integer("page").unwrap_or(1) as usize;
+
+            let prices = price_service::get_price_list(&pool, filter_base.as_deref(), filter_quote.as_deref()).await?;
+            if prices.is_empty() {
+                return Err("❌ No price data found".to_string());
+            }
+
+            let page = price_service::create_price_list_pages(&prices, page_num, ITEMS_PER_PAGE)?;
+            super::respond_with_page(ctx, command, page).await?;
+        }
+        _ => return Err(format!("❌ Unknown subcommand: '{}'", subcommand)),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::model::application::{CommandInteraction, CommandOptionType};
+use serenity::prelude::Context;
+use crate::interactions::options::Options;
+use crate::services::send_service;
+use crate::utils::Invocation;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("send")
+        .description("Transfer currency to another user")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::User, "user", "Recipient")
+                .required(true),
+        )
+        .add_option(super::amount_option("amount", "Amount to send", true))
+        .add_option(super::currency_option("currency", "Currency ticker", true))
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "memo", "Note shown on the transaction")
+                .max_length(crate::db::transaction::MAX_MEMO_LENGTH as u16),
+        )
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> Result<(), String> {
+    let options = Options::of(command);
+    let receiver_id = options.user("user").ok_or("Missing option `user`".to_string())?.get() as i64;
+    let amount = options.require_amount("amount")?;
+    let currency_ticker = options.require_ticker("currency")?;
+    let memo = options.string("memo");
+
+    if !amount.is_positive() {
+        return Err("❌ Amount must be positive".to_string());
+    }
+
+    let invocation = Invocation::from(command);
+    let result = send_service::execute_send(ctx, &invocation, &[(receiver_id, amount)], &currency_ticker, memo).await?;
+
+    super::respond(ctx, command, send_service::create_send_embed(&result)).await?;
+    Ok(())
+}
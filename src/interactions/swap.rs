@@ -0,0 +1,223 @@
+use serenity::builder::{
+    CreateCommand, CreateCommandOption, CreateEmbed, CreateInteractionResponse,
+    CreateInteractionResponseFollowup, CreateInteractionResponseMessage, EditInteractionResponse,
+};
+use serenity::model::application::{CommandInteraction, CommandOptionType, ComponentInteraction};
+use serenity::prelude::Context;
+use tracing::{debug, error};
+use crate::interactions::options::Options;
+use crate::services::swap_service::{self, SwapAction};
+use crate::utils::Invocation;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("swap")
+        .description("Trade currencies with other users")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "create", "Create a swap offer")
+                .add_sub_option(super::amount_option("amount", "Amount you offer", true))
+                .add_sub_option(super::currency_option("currency", "Currency you offer", true))
+                .add_sub_option(super::amount_option("want_amount", "Amount you want in return", false))
+                .add_sub_option(super::currency_option("want_currency", "Currency you want in return", false))
+                .add_sub_option(CreateCommandOption::new(CommandOptionType::User, "user", "Only this user can accept (leave empty for an open swap)"))
+                .add_sub_option(CreateCommandOption::new(CommandOptionType::String, "expires", "Expire after e.g. 30m, 24h, 7d, 2w")),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "accept", "Accept a swap")
+                .add_sub_option(swap_id_option())
+                .add_sub_option(super::amount_option("amount", "Fill only this much of an open swap's offer", false)),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "deny", "Deny or cancel a swap")
+                .add_sub_option(swap_id_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "status", "View a swap's details")
+                .add_sub_option(swap_id_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "list", "List swaps")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "status", "Swap status (default: all)")
+                        .add_string_choice("All", "all")
+                        .add_string_choice("Pending", "pending")
+                        .add_string_choice("Accepted", "accepted")
+                        .add_string_choice("Cancelled", "cancelled")
+                        .add_string_choice("Expired", "expired"),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "sort", "Sort order (default: latest)")
+                        .add_string_choice("Latest", "latest")
+                        .add_string_choice("Oldest", "oldest")
+                        .add_string_choice("Highest maker amount", "highmaker")
+                        .add_string_choice("Lowest maker amount", "lowmaker")
+                        .add_string_choice("Highest taker amount", "hightaker")
+                        .add_string_choice("Lowest taker amount", "lowtaker"),
+                )
+                .add_sub_option(super::currency_option("base", "Only swaps offering this currency", false))
+                .add_sub_option(super::currency_option("quote", "Only swaps asking for this currency", false))
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Integer, "page", "Page number")
+                        .min_int_value(1),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "expiry", "Show or set a currency's default swap expiry")
+                .add_sub_option(super::currency_option("currency", "Currency ticker", true))
+                .add_sub_option(CreateCommandOption::new(CommandOptionType::String, "duration", "New default, e.g. 24h, or off (admin)")),
+        )
+}
+
+fn swap_id_option() -> CreateCommandOption {
+    CreateCommandOption::new(CommandOptionType::Integer, "id", "Swap ID")
+        .required(true)
+        .min_int_value(1)
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> Result<(), String> {
+    let (subcommand, options) = Options::of(command)
+        .subcommand()
+        .ok_or("Missing subcommand".to_string())?;
+    let invocation = Invocation::from(command);
+
+    match subcommand {
+        "create" => create(ctx, command, &invocation, &options).await,
+        "accept" => {
+            let swap_id = options.require_integer("id")?;
+            let fill_amount = options.amount("amount")?;
+            let (result, _) = swap_service::accept_swap(ctx, &invocation, Some(swap_id), fill_amount).await?;
+            super::respond(ctx, command, swap_service::create_accept_deny_embed(&result)).await?;
+            Ok(())
+        }
+        "deny" => {
+            let swap_id = options.require_integer("id")?;
+            let (result, _) = swap_service::deny_swap(ctx, &invocation, Some(swap_id)).await?;
+            super::respond(ctx, command, swap_service::create_accept_deny_embed(&result)).await?;
+            Ok(())
+        }
+        "status" => {
+            let swap_id = options.require_integer("id")?;
+            let embed = swap_service::get_swap_status(ctx, swap_id).await?;
+            super::respond(ctx, command, embed).await?;
+            Ok(())
+        }
+        "list" => {
+            let status = options.string("status").unwrap_or("all");
+            let sort_by = options.string("sort").unwrap_or("latest");
+            let base_currency = options.ticker("base");
+            let quote_currency = options.ticker("quote");
+            let page = options.integer("page").unwrap_or(1) as usize;
+
+            let page = swap_service::list_swaps(ctx, page, sort_by, status, base_currency.as_deref(), quote_currency.as_deref()).await?;
+            super::respond_with_page(ctx, command, page).await
+        }
+        "expiry" => {
+            let ticker = options.require_ticker("currency")?;
+            let response = swap_service::set_default_expiry(ctx, &invocation, &ticker, options.string("duration")).await?;
+            let embed = CreateEmbed::default()
+                .title("⏱️ Swap Expiry")
+                .description(response)
+                .color(0x00ff00);
+            super::respond(ctx, command, embed).await?;
+            Ok(())
+        }
+        _ => Err(format!("❌ Unknown subcommand: '{}'", subcommand)),
+    }
+}
+
+async fn create(
+    ctx: &Context,
+    command: &CommandInteraction,
+    invocation: &Invocation,
+    options: &Options<'_>,
+) -> Result<(), String> {
+    let maker_amount = options.require_amount("amount")?;
+    let maker_ticker = options.require_ticker("currency")?;
+    let taker_amount = options.amount("want_amount")?;
+    let taker_ticker = options.ticker("want_currency");
+    let taker_id = options.user("user").map(|id| id.get() as i64);
+
+    if !maker_amount.is_positive() || taker_amount.is_some_and(|amount| !amount.is_positive()) {
+        return Err("Amount must be positive".to_string());
+    }
+
+    if taker_amount.is_some() != taker_ticker.is_some() {
+        return Err("Provide both `want_amount` and `want_currency`, or neither".to_string());
+    }
+
+    if taker_id.is_some() && taker_amount.is_none() {
+        return Err("A swap with a specific user needs `want_amount` and `want_currency`".to_string());
+    }
+
+    let expiry_minutes = options
+        .string("expires")
+        .map(swap_service::parse_expiry)
+        .transpose()?;
+
+    let result = swap_service::execute_swap(
+        ctx,
+        invocation,
+        invocation.user_id_i64(),
+        maker_amount,
+        &maker_ticker,
+        taker_id,
+        taker_amount,
+        taker_ticker.as_deref(),
+        expiry_minutes,
+    ).await?;
+
+    let response = EditInteractionResponse::new()
+        .embed(swap_service::create_swap_embed(&result))
+        .components(swap_service::create_swap_buttons(result.swap_id));
+    let sent = command
+        .edit_response(&ctx.http, response)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Store the announcement so it can be edited when the swap is resolved
+    swap_service::store_swap_message(ctx, result.swap_id, sent.channel_id.get(), sent.id.get()).await;
+    Ok(())
+}
+
+/// Accept or Deny pressed on a swap announcement or DM. The service edits every stored
+/// message for the swap, so only failures need a reply.
+pub async fn handle_button(ctx: &Context, component: &ComponentInteraction, action: SwapAction, swap_id: i64) {
+    if let Err(remaining_ms) = crate::utils::check_global_rate_limit().await {
+        reply_ephemeral(ctx, component, &format!("⚠️ Server is handling too many requests. Please wait {}ms and try again.", remaining_ms)).await;
+        return;
+    }
+
+    // Acknowledge the press without changing the message yet
+    if let Err(e) = component.defer(&ctx.http).await {
+        debug!("Failed to acknowledge swap button: {}", e);
+        return;
+    }
+
+    let invocation = Invocation::from(component);
+    let result = match action {
+        SwapAction::Accept => swap_service::accept_swap(ctx, &invocation, Some(swap_id), None).await,
+        SwapAction::Deny => swap_service::deny_swap(ctx, &invocation, Some(swap_id)).await,
+    };
+
+    if let Err(e) = result {
+        error!("Swap button for {} failed: {}", swap_id, e);
+        let followup = CreateInteractionResponseFollowup::new()
+            .embed(crate::commands::create_error_embed(&e))
+            .ephemeral(true);
+        if let Err(e) = component.create_followup(&ctx.http, followup).await {
+            debug!("Failed to send swap button error: {}", e);
+        }
+    }
+}
+
+/// Answer a button press that hasn't been acknowledged with a message only the user can see
+async fn reply_ephemeral(ctx: &Context, component: &ComponentInteraction, content: &str) {
+    let message = CreateInteractionResponseMessage::new()
+        .content(content)
+        .ephemeral(true);
+    if let Err(e) = component
+        .create_response(&ctx.http, CreateInteractionResponse::Message(message))
+        .await
+    {
+        debug!("Failed to respond to swap button: {}", e);
+    }
+}
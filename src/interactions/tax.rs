@@ -0,0 +1,84 @@
+use serenity::builder::{CreateCommand, CreateCommandOption, CreateEmbed};
+use serenity::model::application::{CommandInteraction, CommandOptionType};
+use serenity::prelude::Context;
+use crate::interactions::options::Options;
+use crate::services::tax_service;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("tax")
+        .description("Manage currency taxes (Admin/Tax Collector only)")
+        .dm_permission(false)
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "set", "Set the tax percentage for a currency")
+                .add_sub_option(super::currency_option("currency", "Currency ticker", true))
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::Integer, "percentage", "Tax percentage (0-100)")
+                        .required(true)
+                        .min_int_value(0)
+                        .max_int_value(100),
+                ),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "collect", "Collect taxes into your account")
+                .add_sub_option(super::currency_option("currency", "Currency ticker", true))
+                .add_sub_option(super::amount_option("amount", "Amount to collect (default: all)", false)),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "info", "View tax info for a currency")
+                .add_sub_option(super::currency_option("currency", "Currency ticker", true)),
+        )
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> Result<(), String> {
+    // Check permissions - user must be in a guild and have admin or tax collector role
+    let guild_id = command
+        .guild_id
+        .ok_or("This command can only be used in a guild".to_string())?;
+
+    crate::utils::check_user_roles(ctx, guild_id, command.user.id, &["admin", "tax collector"])
+        .await?;
+
+    let (subcommand, options) = Options::of(command)
+        .subcommand()
+        .ok_or("Missing subcommand".to_string())?;
+
+    let ticker = options.require_ticker("currency")?;
+
+    let storage = crate::storage::from_ctx(ctx).await?;
+
+    let currency_id = storage.get_currency_by_ticker(&ticker)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or(format!("❌ Currency '{}' not found", ticker))?
+        .0;
+
+    let embed = match subcommand {
+        "set" => {
+            let percentage = options.require_integer("percentage")? as i32;
+            let response = tax_service::set_tax(storage.as_ref(), currency_id, percentage, &ticker).await?;
+            CreateEmbed::default()
+                .title("💰 Tax Set")
+                .description(response)
+                .color(0x00ff00)
+        }
+        "collect" => {
+            let amount = options.string("amount").map(|s| s.to_string());
+            let response = tax_service::collect_tax(storage.as_ref(), command.user.id.get() as i64, currency_id, amount).await?;
+            CreateEmbed::default()
+                .title("💰 Tax Collected")
+                .description(response)
+                .color(0x00ff00)
+        }
+        "info" => {
+            let response = tax_service::get_tax_info(storage.as_ref(), currency_id).await?;
+            CreateEmbed::default()
+                .title(&ticker)
+                .description(response)
+                .color(0x00b0f4)
+        }
+        _ => return Err(format!("❌ Unknown subcommand: '{}'", subcommand)),
+    };
+
+    super::respond(ctx, command, embed).await?;
+    Ok(())
+}
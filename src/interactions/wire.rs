@@ -0,0 +1,183 @@
+//! Wire slash command - responses are ephemeral (see `interactions::handle_command`)
+//! so API tokens and balances stay private. Also handles the Confirm and Cancel
+//! buttons on wire quotes, from `/wire` and `$wire` alike.
+
+use std::collections::HashMap;
+use lazy_static::lazy_static;
+use serenity::builder::{
+    CreateCommand, CreateCommandOption, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+    EditInteractionResponse,
+};
+use serenity::model::application::{CommandInteraction, CommandOptionType, ComponentInteraction};
+use serenity::model::id::MessageId;
+use serenity::prelude::Context;
+use tokio::sync::Mutex;
+use tracing::debug;
+use crate::interactions::options::Options;
+use crate::services::wire_service::{self, WireButton, WireDirection, QUOTE_VALID_SECONDS};
+use crate::utils::Invocation;
+
+lazy_static! {
+    // Quotes already confirmed, so a double press can't run a wire twice.
+    // Key: quote message, Value: when it was confirmed
+    static ref CONFIRMED_QUOTES: Mutex<HashMap<MessageId, i64>> = Mutex::new(HashMap::new());
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("wire")
+        .description("Bridge between SMITE and the currency's wire provider (UnbelievaBoat by default)")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "in", "Transfer from the wire provider to SMITE")
+                .add_sub_option(super::amount_option("amount", "Whole amount on the wire provider to transfer", true))
+                .add_sub_option(super::currency_option("currency", "Currency ticker", true)),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "out", "Transfer from SMITE to the wire provider")
+                .add_sub_option(super::amount_option("amount", "SMITE amount to transfer", true))
+                .add_sub_option(super::currency_option("currency", "Currency ticker", true)),
+        )
+        .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "history", "Your recent wire transfers"))
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "token", "Set the wire provider's API token for a guild (admin)")
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "guild_id", "Guild ID the currency belongs to")
+                        .required(true),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::String, "token", "API token for the currency's wire provider")
+                        .required(true),
+                ),
+        )
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> Result<(), String> {
+    let (subcommand, options) = Options::of(command)
+        .subcommand()
+        .ok_or("Missing subcommand".to_string())?;
+    let invocation = Invocation::from(command);
+
+    if subcommand == "token" {
+        // The option values never appear in the channel, so unlike `$wire set token`
+        // this is allowed outside of DMs
+        let guild_id = options
+            .require_string("guild_id")?
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| "❌ Invalid guild ID. Please provide a valid numeric guild ID.".to_string())?;
+        let token = options.require_string("token")?.trim();
+
+        let embed = match wire_service::set_api_token(ctx, &invocation, Some(guild_id), token).await {
+            Ok(_) => CreateEmbed::default()
+                .title("✅ Token Set Successfully")
+                .description(format!("The wire provider's API token has been encrypted and stored for guild `{}`.", guild_id))
+                .color(0x00ff00),
+            Err(e) => e.to_embed(),
+        };
+        super::respond(ctx, command, embed).await?;
+        return Ok(());
+    }
+
+    if subcommand == "history" {
+        let page = wire_service::wire_history(ctx, &invocation).await?;
+        super::respond_with_page(ctx, command, page).await?;
+        return Ok(());
+    }
+
+    let direction = WireDirection::parse(subcommand)
+        .ok_or(format!("❌ Unknown subcommand: '{}'", subcommand))?;
+
+    let amount = options.require_amount("amount")?;
+    let currency_ticker = options.require_ticker("currency")?;
+
+    // Nothing moves yet: the user confirms the quote with its buttons
+    let edit = match wire_service::quote_wire(ctx, direction, amount, &currency_ticker).await {
+        Ok((quote, ticker, provider)) => EditInteractionResponse::new()
+            .embed(wire_service::create_quote_embed(&quote, &ticker, &provider))
+            .components(wire_service::create_quote_buttons(invocation.user_id.get(), &quote, &ticker)),
+        Err(e) => EditInteractionResponse::new().embed(e.to_embed()),
+    };
+    command.edit_response(&ctx.http, edit).await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Confirm or Cancel pressed on a wire quote. Only the quoted user can press them,
+/// and only while the quote is valid; either way the quote then loses its buttons.
+pub async fn handle_button(ctx: &Context, component: &ComponentInteraction, button: WireButton) {
+    let user_id = match &button {
+        WireButton::Confirm { user_id, .. } | WireButton::Cancel { user_id } => *user_id,
+    };
+    if component.user.id.get() != user_id {
+        reply_ephemeral(ctx, component, &format!("❌ Only <@{}> can answer this quote", user_id)).await;
+        return;
+    }
+
+    if let Err(remaining_ms) = crate::utils::check_global_rate_limit().await {
+        reply_ephemeral(ctx, component, &format!("⚠️ Server is handling too many requests. Please wait {}ms and try again.", remaining_ms)).await;
+        return;
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let embed = match button {
+        WireButton::Cancel { .. } => CreateEmbed::default()
+            .title("❌ Wire Cancelled")
+            .description("Nothing was transferred")
+            .color(0x808080),
+        _ if component.message.timestamp.unix_timestamp() + QUOTE_VALID_SECONDS < now => CreateEmbed::default()
+            .title("⌛ Quote Expired")
+            .description("Nothing was transferred. Run the command again for a new quote.")
+            .color(0x808080),
+        WireButton::Confirm { direction, smite_amount, remote_amount, ticker, .. } => {
+            if !claim_quote(component.message.id, now).await {
+                reply_ephemeral(ctx, component, "⏳ This wire is already being sent").await;
+                return;
+            }
+
+            // Acknowledge the press without changing the message yet; the API calls can be slow
+            if let Err(e) = component.defer(&ctx.http).await {
+                debug!("Failed to acknowledge wire button: {}", e);
+                return;
+            }
+
+            let invocation = Invocation::from(component);
+            let result = wire_service::confirm_wire(ctx, &invocation, direction, smite_amount, remote_amount, &ticker).await;
+            let embed = match result {
+                Ok(result) => wire_service::create_wire_embed(&ticker, &result),
+                Err(e) => e.to_embed(),
+            };
+            let edit = EditInteractionResponse::new().embed(embed).components(Vec::new());
+            if let Err(e) = component.edit_response(&ctx.http, edit).await {
+                debug!("Failed to update wire quote: {}", e);
+            }
+            return;
+        }
+    };
+
+    let update = CreateInteractionResponseMessage::new().embed(embed).components(Vec::new());
+    if let Err(e) = component
+        .create_response(&ctx.http, CreateInteractionResponse::UpdateMessage(update))
+        .await
+    {
+        debug!("Failed to update wire quote: {}", e);
+    }
+}
+
+/// Mark a quote confirmed, returning false if it already was.
+/// Entries are kept only as long as a quote could still be confirmed.
+async fn claim_quote(message_id: MessageId, now: i64) -> bool {
+    let mut confirmed = CONFIRMED_QUOTES.lock().await;
+    confirmed.retain(|_, confirmed_at| *confirmed_at + QUOTE_VALID_SECONDS >= now);
+    confirmed.insert(message_id, now).is_none()
+}
+
+/// Answer a button press that hasn't been acknowledged with a message only the user can see
+async fn reply_ephemeral(ctx: &Context, component: &ComponentInteraction, content: &str) {
+    let message = CreateInteractionResponseMessage::new()
+        .content(content)
+        .ephemeral(true);
+    if let Err(e) = component
+        .create_response(&ctx.http, CreateInteractionResponse::Message(message))
+        .await
+    {
+        debug!("Failed to respond to wire button: {}", e);
+    }
+}
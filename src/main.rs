@@ -1,7 +1,9 @@
 use serenity::async_trait;
+use serenity::model::application::Interaction;
 use serenity::model::channel::Message;
 use serenity::model::gateway::Ready;
 use serenity::prelude::*;
+use std::sync::Arc;
 use std::time::Instant;
 use sqlx::mysql::MySqlPool;
 use tracing::{info, warn, error, debug};
@@ -9,7 +11,9 @@ use tracing_subscriber::EnvFilter;
 
 mod db;
 mod commands;
+mod interactions;
 mod services;
+mod storage;
 mod utils;
 mod blacklist;
 mod api;
@@ -28,6 +32,12 @@ impl TypeMapKey for DatabasePool {
     type Value = MySqlPool;
 }
 
+struct DataStore;
+
+impl TypeMapKey for DataStore {
+    type Value = Arc<dyn storage::Storage>;
+}
+
 struct CommandPrefix;
 
 impl TypeMapKey for CommandPrefix {
@@ -40,9 +50,24 @@ impl EventHandler for Handler {
         commands::handle_message(&ctx, &msg).await;
     }
 
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        interactions::handle_interaction(&ctx, interaction).await;
+    }
+
     async fn ready(&self, ctx: Context, ready: Ready) {
         info!("{} is connected!", ready.user.name);
         
+        // Background workers, each started once across shards:
+        // refund overdue swaps and expire overdue invoices
+        services::swap_expiry_service::start_worker(ctx.clone());
+        // send due recurring payments and scheduled payrolls
+        services::recurring_scheduler_service::start_worker(ctx.clone());
+        // settle wires left unfinished by a crash or an unconfirmed transfer
+        services::wire_recovery_service::start_worker(ctx.clone());
+        
+        // Slash commands run alongside the prefix commands (registered once across shards)
+        interactions::register_commands(&ctx).await;
+        
         // Check for rate limits now that bot is connected
         debug!("Checking Discord rate limit status...");
         match ctx.http.get_current_user().await {
@@ -74,6 +99,40 @@ async fn main() {
         .with_thread_ids(true)
         .init();
     
+    // Maintenance modes: run against the database and exit without starting the bot
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--status") {
+        match db::migration_status().await {
+            Ok(states) => {
+                for (name, state) in states {
+                    println!("{:<32} {}", name, state);
+                }
+            }
+            Err(e) => {
+                error!("Failed to read migration status: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if args.iter().any(|arg| arg == "--backfill-candles") {
+        match db::backfill_candles().await {
+            Ok(written) => info!("Rebuilt {} price candles from the trade history", written),
+            Err(e) => {
+                error!("Candle backfill failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if args.iter().any(|arg| arg == "--migrate-only") {
+        if let Err(e) = db::migrate_only().await {
+            error!("Migration failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    
     info!("🤖 Starting SMITE bot...");
     info!("   ______   __       __  ______  ________  ________ ");
     info!("  /      \\ |  \\     /  \\|      \\|        \\|        \\");
@@ -94,14 +153,14 @@ async fn main() {
     
     // Initialize database
     info!("Initializing database...");
-    let pool = match db::init_db().await {
-        Ok(p) => {
+    let backend = match db::init_db().await {
+        Ok(backend) => {
             info!("Database initialized successfully");
-            p
+            backend
         }
         Err(e) => {
             error!("Failed to initialize database: {}", e);
-            return;
+            std::process::exit(1);
         }
     };
     
@@ -120,11 +179,22 @@ async fn main() {
         .await
         .expect("Failed to create client");
 
-    // Store the start time, database pool, and prefix in client data
+    // Store the start time, database pool, storage and prefix in client data
     {
         let mut data = client.data.write().await;
         data.insert::<BotData>(Instant::now());
-        data.insert::<DatabasePool>(pool);
+        match backend {
+            db::Backend::MySql(pool) => {
+                data.insert::<DataStore>(Arc::new(storage::MySqlStorage::new(pool.clone())));
+                data.insert::<DatabasePool>(pool);
+            }
+            // The order book, prices and routes read MySQL tables directly, so their
+            // commands answer with `db::MYSQL_ONLY`
+            db::Backend::Sqlite(pool) => {
+                warn!("Running on SQLite: the order book, prices and routes are not supported");
+                data.insert::<DataStore>(Arc::new(storage::SqliteStorage::new(pool)));
+            }
+        }
         data.insert::<CommandPrefix>(prefix);
     }
 
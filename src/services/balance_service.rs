@@ -1,6 +1,6 @@
-use serenity::model::channel::Message;
+use crate::utils::Invocation;
 use serenity::prelude::Context;
-use crate::db;
+use crate::utils::Amount;
 
 pub struct BalanceResult {
     pub user_id: i64,
@@ -10,35 +10,29 @@ pub struct BalanceResult {
 
 pub async fn get_balance(
     ctx: &Context,
-    msg: &Message,
+    invocation: &Invocation,
     currency_ticker: Option<&str>,
 ) -> Result<BalanceResult, String> {
-    let user_id = msg.author.id.get() as i64;
+    let user_id = invocation.user_id_i64();
     
-    // Get pool from context
-    let pool = {
-        let data = ctx.data.read().await;
-        data.get::<crate::DatabasePool>()
-            .ok_or("Database not initialized".to_string())?
-            .clone()
-    };
+    let storage = crate::storage::from_ctx(ctx).await?;
     
     let (currency_id, ticker) = if let Some(ticker) = currency_ticker {
         // Look up currency by ticker (searches across all guilds)
-        let currency_data = db::currency::get_currency_by_ticker(&pool, ticker)
+        let currency_data = storage.get_currency_by_ticker(ticker)
             .await
             .map_err(|e| format!("Database error: {}", e))?
             .ok_or(format!("Currency {} not found", ticker))?;
         (currency_data.0, currency_data.2)
     } else {
         // No ticker specified - requires guild context to get default currency
-        let guild_id = msg
+        let guild_id = invocation
             .guild_id
             .ok_or("❌ Please specify a currency ticker (e.g., `$bal USD`). Default balance is only available in guilds.".to_string())?
             .get();
         
         // Get guild's default currency
-        let currency_data = db::currency::get_currency_by_guild(&pool, guild_id as i64)
+        let currency_data = storage.get_currency_by_guild(guild_id as i64)
             .await
             .map_err(|e| format!("Database error: {}", e))?
             .ok_or("Guild has no currency set up".to_string())?;
@@ -46,10 +40,10 @@ pub async fn get_balance(
     };
     
     // Get balance (treat missing account as 0 balance)
-    let balance = db::account::get_account_balance(&pool, user_id, currency_id)
+    let balance = storage.get_account_balance(user_id, currency_id)
         .await
         .map_err(|e| format!("Database error: {}", e))?
-        .unwrap_or(0.0);  // Return 0 if user has no account for this currency
+        .unwrap_or(Amount::ZERO);  // Return 0 if user has no account for this currency
     
     Ok(BalanceResult {
         user_id,
@@ -1,19 +1,14 @@
-use serenity::model::channel::Message;
+use serenity::async_trait;
 use serenity::prelude::Context;
 use serenity::builder::CreateEmbed;
+use std::sync::Arc;
+use crate::storage::Storage;
+use crate::utils::page::{Page, PageSource};
 
 const ITEMS_PER_PAGE: usize = 10;
 
-pub async fn list_currencies(ctx: &Context, msg: &Message, args: &[&str]) -> Result<(), String> {
-    // Extract pool from context
-    let pool = {
-        let data = ctx.data.read().await;
-        data.get::<crate::DatabasePool>()
-            .cloned()
-            .ok_or("Database pool not found")?
-    };
-
-    // Parse sort order and page number
+/// Parse board arguments into a sort order and page number
+pub fn parse_board_args(args: &[&str]) -> (&'static str, usize) {
     let mut sort_by = "oldest"; // default
     let mut page_num = 1;
 
@@ -29,36 +24,47 @@ pub async fn list_currencies(ctx: &Context, msg: &Message, args: &[&str]) -> Res
         }
     }
 
-    // Fetch paginated currencies from database (all currencies)
-    let (currencies, total_count) = crate::db::currency::get_currencies_paginated(&pool, sort_by, page_num, ITEMS_PER_PAGE)
-        .await
-        .map_err(|e| format!("Database error: {}", e))?;
+    (sort_by, page_num)
+}
 
-    if currencies.is_empty() && page_num == 1 {
-        return Err("❌ No currencies found. Create one with `$create_currency`".to_string());
-    }
+/// Currency board pages, fetched as the user navigates
+pub struct CurrencyBoard {
+    storage: Arc<dyn Storage>,
+    sort_by: String,
+}
 
-    // Calculate total pages
-    let total_pages = (total_count as usize + ITEMS_PER_PAGE - 1) / ITEMS_PER_PAGE;
+#[async_trait]
+impl PageSource for CurrencyBoard {
+    async fn fetch(&self, page_num: usize) -> Result<(CreateEmbed, usize), String> {
+        // Fetch paginated currencies from database (all currencies)
+        let (currencies, total_count) = self.storage.get_currencies_paginated(&self.sort_by, page_num, ITEMS_PER_PAGE)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
 
-    // Validate page number
-    if page_num < 1 || page_num > total_pages.max(1) {
-        return Err(format!(
-            "❌ Invalid page number. This command has {} page(s)",
-            total_pages
-        ));
-    }
+        if currencies.is_empty() && page_num == 1 {
+            return Err("❌ No currencies found. Create one with `$create_currency`".to_string());
+        }
+
+        // Calculate total pages
+        let total_pages = (total_count as usize).div_ceil(ITEMS_PER_PAGE);
+
+        // Validate page number
+        if page_num < 1 || page_num > total_pages.max(1) {
+            return Err(format!(
+                "❌ Invalid page number. This command has {} page(s)",
+                total_pages
+            ));
+        }
 
-    // Create embed for this page
-    let embed = create_currency_page(&currencies, page_num, total_pages, sort_by);
+        Ok((create_currency_page(&currencies, page_num, total_pages, &self.sort_by), total_pages))
+    }
+}
 
-    // Send the message
-    msg.channel_id
-        .send_message(ctx, serenity::builder::CreateMessage::default().embed(embed))
-        .await
-        .map_err(|e| format!("Failed to send message: {}", e))?;
+/// Open the currency board at `page_num`
+pub async fn list_currencies(ctx: &Context, sort_by: &str, page_num: usize) -> Result<Page, String> {
+    let storage = crate::storage::from_ctx(ctx).await?;
 
-    Ok(())
+    Page::load(CurrencyBoard { storage, sort_by: sort_by.to_string() }, page_num).await
 }
 
 fn create_currency_page(currencies: &[(i64, String, String)], page_num: usize, total_pages: usize, sort_by: &str) -> CreateEmbed {
@@ -1,7 +1,9 @@
 use sqlx::mysql::MySqlPool;
 use plotters::prelude::*;
-use chrono::{DateTime, Utc, NaiveDateTime, Duration};
+use chrono::{DateTime, Utc, Duration};
 use crate::db;
+use crate::db::candle::{CandleRow, Resolution};
+use crate::db::filter::TimeWindow;
 
 /// Chart data point with timestamp and price
 #[derive(Debug, Clone)]
@@ -29,12 +31,15 @@ pub fn parse_chart_timeframe(timeframe: &str) -> Result<Option<Duration>, String
     }
 }
 
-/// Get all price logs for a currency pair sorted by date
-pub async fn get_price_history(
+/// Stored candles for a pair in the requested direction, oldest first.
+/// With `since_minutes`, only candles from that many minutes before now.
+async fn load_candles(
     pool: &MySqlPool,
     base_ticker: &str,
     quote_ticker: &str,
-) -> Result<Vec<PricePoint>, String> {
+    resolution: Resolution,
+    since_minutes: Option<i64>,
+) -> Result<Vec<Candle>, String> {
     // Get currency IDs by tickers
     let base_currency = db::currency::get_currency_by_ticker(pool, base_ticker)
         .await
@@ -46,48 +51,74 @@ pub async fn get_price_history(
         .map_err(|e| format!("Database error: {}", e))?
         .ok_or(format!("❌ Currency '{}' not found", quote_ticker))?;
 
-    let base_currency_id = base_currency.0;
-    let quote_currency_id = quote_currency.0;
-
     // Get the canonical order
     let (canonical_base_id, canonical_quote_id, is_reversed) = 
-        db::tradelog::normalize_pair(pool, base_currency_id, quote_currency_id)
+        db::tradelog::normalize_pair(pool, base_currency.0, quote_currency.0)
             .await
             .map_err(|e| format!("Database error: {}", e))?;
 
-    // Fetch price logs with timestamps for this pair
-    let logs = db::tradelog::get_price_logs_with_timestamps(pool, canonical_base_id, canonical_quote_id)
+    let rows = db::candle::get_candles(pool, canonical_base_id, canonical_quote_id, resolution, since_minutes)
         .await
         .map_err(|e| format!("Database error: {}", e))?;
 
-    if logs.is_empty() {
-        return Err("❌ No trading history found for this pair.".to_string());
+    Ok(rows.iter().filter_map(|row| candle_from_row(row, is_reversed)).collect())
+}
+
+/// Convert a stored candle to chart values, inverting prices for a reversed pair.
+/// Inverting swaps the high and low, and volume becomes the other currency's.
+pub fn candle_from_row(row: &CandleRow, is_reversed: bool) -> Option<Candle> {
+    let start = DateTime::<Utc>::from_naive_utc_and_offset(row.bucket_start, Utc);
+    let (open, high, low, close) = (row.open.to_f64(), row.high.to_f64(), row.low.to_f64(), row.close.to_f64());
+
+    if !is_reversed {
+        return Some(Candle { start, open, high, low, close, volume: row.volume.to_f64() });
+    }
+    if low <= 0.0 {
+        return None;
     }
+    Some(Candle {
+        start,
+        open: 1.0 / open,
+        high: 1.0 / low,
+        low: 1.0 / high,
+        close: 1.0 / close,
+        volume: row.quote_volume.to_f64(),
+    })
+}
 
-    // Convert logs to price points
-    let mut points: Vec<PricePoint> = Vec::new();
-    
-    for (_id, price, date_str) in logs {
-        // Calculate the displayed price (invert if reversed)
-        let displayed_price = if is_reversed {
-            1.0 / price
-        } else {
-            price
-        };
+/// Closing prices over a window (None for all history), from candles sized to the window.
+/// Sparse history falls back to minute candles so a new pair still has a line to draw.
+async fn load_price_points(
+    pool: &MySqlPool,
+    base_ticker: &str,
+    quote_ticker: &str,
+    window: Option<Duration>,
+) -> Result<Vec<PricePoint>, String> {
+    let since_minutes = window.map(|duration| duration.num_minutes());
+    let resolution = Resolution::for_window(window.map(|duration| duration.num_seconds()));
 
-        // Parse timestamp from database string (format: "YYYY-MM-DD HH:MM:SS")
-        let naive_dt = NaiveDateTime::parse_from_str(&date_str, "%Y-%m-%d %H:%M:%S")
-            .unwrap_or_else(|_| Utc::now().naive_utc());
-        let timestamp = DateTime::<Utc>::from_naive_utc_and_offset(naive_dt, Utc);
-        
-        points.push(PricePoint {
-            timestamp,
-            price: displayed_price,
-        });
+    let mut candles = load_candles(pool, base_ticker, quote_ticker, resolution, since_minutes).await?;
+    if candles.len() < 2 && resolution != Resolution::Minute {
+        candles = load_candles(pool, base_ticker, quote_ticker, Resolution::Minute, since_minutes).await?;
     }
 
-    // Sort by timestamp (should already be sorted from DB, but ensure it)
-    points.sort_by_key(|p| p.timestamp);
+    Ok(candles
+        .into_iter()
+        .map(|candle| PricePoint { timestamp: candle.start, price: candle.close })
+        .collect())
+}
+
+/// Get the price history of a currency pair sorted by date
+pub async fn get_price_history(
+    pool: &MySqlPool,
+    base_ticker: &str,
+    quote_ticker: &str,
+) -> Result<Vec<PricePoint>, String> {
+    let points = load_price_points(pool, base_ticker, quote_ticker, None).await?;
+
+    if points.is_empty() {
+        return Err("❌ No trading history found for this pair.".to_string());
+    }
 
     Ok(points)
 }
@@ -99,18 +130,14 @@ pub async fn get_price_history_with_timeframe(
     quote_ticker: &str,
     timeframe: &str,
 ) -> Result<Vec<PricePoint>, String> {
-    let mut points = get_price_history(pool, base_ticker, quote_ticker).await?;
-    
-    // Parse timeframe and filter if needed
-    if let Some(duration) = parse_chart_timeframe(timeframe)? {
-        let now = Utc::now();
-        let cutoff_time = now - duration;
-        
-        points.retain(|p| p.timestamp >= cutoff_time);
-        
-        if points.is_empty() {
-            return Err(format!("❌ No price data found in the last {}", timeframe));
-        }
+    let window = parse_chart_timeframe(timeframe)?;
+    let points = load_price_points(pool, base_ticker, quote_ticker, window).await?;
+
+    if points.is_empty() {
+        return Err(match window {
+            Some(_) => format!("❌ No price data found in the last {}", timeframe),
+            None => "❌ No trading history found for this pair.".to_string(),
+        });
     }
     
     Ok(points)
@@ -345,3 +372,420 @@ pub fn parse_timeframe_to_minutes(timeframe: &str) -> Result<i64, String> {
     
     Ok(minutes)
 }
+
+/// Most candles drawn on one chart
+const MAX_CANDLES: i64 = 500;
+
+/// Moving average period used when a candle chart doesn't ask for any
+const DEFAULT_MOVING_AVERAGE: usize = 20;
+
+/// Line colours for moving average overlays, in the order they were asked for
+const MOVING_AVERAGE_COLORS: [RGBColor; 3] = [BLUE, MAGENTA, CYAN];
+
+const VWAP_COLOR: RGBColor = RGBColor(255, 140, 0);
+
+/// One OHLC bucket of trades
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candle {
+    pub start: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    /// Base currency traded in this bucket, as displayed
+    pub volume: f64,
+}
+
+/// How to draw a candlestick chart
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandleOptions {
+    pub interval: Duration,
+    pub interval_label: String,
+    /// Moving average periods, in candles
+    pub moving_averages: Vec<usize>,
+}
+
+/// Parsed `$price chart` arguments after the pair
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartRequest {
+    pub timeframe: String,
+    /// None draws the plain line chart
+    pub candles: Option<CandleOptions>,
+}
+
+/// Parse a candle interval such as 5m, 1h or 1d
+pub fn parse_candle_interval(interval: &str) -> Result<Duration, String> {
+    let interval = interval.to_lowercase();
+    let split_idx = interval.chars().take_while(|c| c.is_ascii_digit()).count();
+
+    let amount: i64 = interval[..split_idx]
+        .parse()
+        .map_err(|_| format!("❌ Invalid candle interval '{}'. Examples: 5m, 15m, 1h, 4h, 1d", interval))?;
+    if amount <= 0 {
+        return Err("❌ Candle interval must be positive".to_string());
+    }
+
+    match &interval[split_idx..] {
+        "m" => Ok(Duration::minutes(amount)),
+        "h" => Ok(Duration::hours(amount)),
+        "d" => Ok(Duration::days(amount)),
+        unit => Err(format!("❌ Unknown candle interval unit '{}'. Use: m, h, d", unit)),
+    }
+}
+
+/// Parse `[timeframe] [candles [interval] [maN ...]]`
+/// e.g. `7d candles 1h ma20 ma50`
+pub fn parse_chart_args(args: &[&str]) -> Result<ChartRequest, String> {
+    let mut timeframe = "all".to_string();
+    let mut candles: Option<CandleOptions> = None;
+    let mut moving_averages = Vec::new();
+
+    let mut iter = args.iter().peekable();
+    while let Some(arg) = iter.next() {
+        let lower = arg.to_lowercase();
+
+        if lower == "candles" || lower == "candle" {
+            // The interval is optional, so only take the next argument if it is one
+            let (interval, interval_label) = match iter.peek().map(|next| (next, parse_candle_interval(next))) {
+                Some((next, Ok(interval))) => {
+                    let label = next.to_lowercase();
+                    iter.next();
+                    (interval, label)
+                }
+                _ => (Duration::hours(1), "1h".to_string()),
+            };
+            candles = Some(CandleOptions { interval, interval_label, moving_averages: Vec::new() });
+        } else if let Some(period) = lower.strip_prefix("ma") {
+            let period: usize = period
+                .parse()
+                .map_err(|_| format!("❌ Invalid moving average '{}'. Example: ma20", arg))?;
+            if !(2..=200).contains(&period) {
+                return Err("❌ Moving average period must be between 2 and 200".to_string());
+            }
+            moving_averages.push(period);
+        } else {
+            parse_chart_timeframe(arg)?;
+            timeframe = lower;
+        }
+    }
+
+    if !moving_averages.is_empty() && candles.is_none() {
+        return Err("❌ Moving averages need a candle chart, e.g. `candles 1h ma20`".to_string());
+    }
+    if moving_averages.len() > MOVING_AVERAGE_COLORS.len() {
+        return Err(format!("❌ At most {} moving averages can be drawn", MOVING_AVERAGE_COLORS.len()));
+    }
+
+    if let Some(options) = candles.as_mut() {
+        options.moving_averages = if moving_averages.is_empty() { vec![DEFAULT_MOVING_AVERAGE] } else { moving_averages };
+    }
+
+    Ok(ChartRequest { timeframe, candles })
+}
+
+/// Merge candles into candles of `interval`, e.g. hourly candles into 4h ones.
+/// `candles` must be in time order; buckets without trades are left out.
+pub fn merge_candles(candles: &[Candle], interval: Duration) -> Vec<Candle> {
+    let seconds = interval.num_seconds().max(1);
+
+    let mut merged: Vec<Candle> = Vec::new();
+    for candle in candles {
+        let key = candle.start.timestamp().div_euclid(seconds) * seconds;
+        match merged.last_mut() {
+            Some(last) if last.start.timestamp() == key => {
+                last.high = last.high.max(candle.high);
+                last.low = last.low.min(candle.low);
+                last.close = candle.close;
+                last.volume += candle.volume;
+            }
+            _ => merged.push(Candle {
+                start: DateTime::<Utc>::from_timestamp(key, 0).unwrap_or(candle.start),
+                ..candle.clone()
+            }),
+        }
+    }
+
+    merged
+}
+
+/// Simple moving average of candle closes, starting at the first full window
+pub fn moving_average(candles: &[Candle], period: usize) -> Vec<PricePoint> {
+    if period == 0 || candles.len() < period {
+        return Vec::new();
+    }
+
+    candles
+        .windows(period)
+        .map(|window| {
+            let sum: f64 = window.iter().map(|c| c.close).sum();
+            PricePoint {
+                timestamp: window[period - 1].start,
+                price: sum / period as f64,
+            }
+        })
+        .collect()
+}
+
+/// Generate a candlestick chart with volume bars, moving averages and the VWAP as PNG bytes
+pub async fn generate_candle_chart(
+    pool: &MySqlPool,
+    base_ticker: &str,
+    quote_ticker: &str,
+    timeframe: &str,
+    options: &CandleOptions,
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>, String> {
+    let window = parse_chart_timeframe(timeframe)?;
+    let resolution = Resolution::for_interval(options.interval.num_seconds());
+    let stored = load_candles(pool, base_ticker, quote_ticker, resolution, window.map(|duration| duration.num_minutes())).await?;
+
+    let first_timestamp = match stored.first() {
+        Some(candle) => candle.start,
+        None if window.is_some() => return Err(format!("❌ No price data found in the last {}", timeframe)),
+        None => return Err("❌ No trading history found for this pair.".to_string()),
+    };
+
+    let span = Utc::now() - first_timestamp;
+    if span.num_seconds() / options.interval.num_seconds().max(1) > MAX_CANDLES {
+        return Err(format!(
+            "❌ Too many candles: {} of {} candles is more than {}. Use a shorter timeframe or a longer interval.",
+            timeframe, options.interval_label, MAX_CANDLES
+        ));
+    }
+
+    let candles = merge_candles(&stored, options.interval);
+
+    let base_id = db::currency::get_currency_by_ticker(pool, base_ticker)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or(format!("❌ Currency '{}' not found", base_ticker))?
+        .0;
+    let quote_id = db::currency::get_currency_by_ticker(pool, quote_ticker)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or(format!("❌ Currency '{}' not found", quote_ticker))?
+        .0;
+
+    // VWAP over the same window as the chart, in the displayed direction
+    let (canonical_base_id, canonical_quote_id, is_reversed) = db::tradelog::normalize_pair(pool, base_id, quote_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    let window_minutes = span.num_minutes() + 1;
+    let vwap = db::tradelog::calculate_vwap(pool, canonical_base_id, canonical_quote_id, TimeWindow::minutes(window_minutes))
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .filter(|vwap| *vwap > 0.0)
+        .map(|vwap| if is_reversed { 1.0 / vwap } else { vwap });
+
+    let averages: Vec<(usize, Vec<PricePoint>)> = options
+        .moving_averages
+        .iter()
+        .map(|period| (*period, moving_average(&candles, *period)))
+        .collect();
+
+    // Price range covers the candles and every overlay
+    let mut min_price = candles.iter().map(|c| c.low).fold(f64::INFINITY, f64::min);
+    let mut max_price = candles.iter().map(|c| c.high).fold(f64::NEG_INFINITY, f64::max);
+    for value in averages.iter().flat_map(|(_, line)| line.iter().map(|p| p.price)).chain(vwap) {
+        min_price = min_price.min(value);
+        max_price = max_price.max(value);
+    }
+    let padding = (max_price - min_price).max(1e-8) * 0.1;
+    let y_min = (min_price - padding).max(0.0);
+    let y_max = max_price + padding;
+
+    let max_volume = candles.iter().map(|c| c.volume).fold(0.0, f64::max).max(1e-8) * 1.1;
+
+    let x_min = candles[0].start;
+    let x_max = candles[candles.len() - 1].start + options.interval;
+    let slots = ((x_max - x_min).num_seconds() / options.interval.num_seconds().max(1)).max(1);
+    let candle_width = ((width.saturating_sub(100)) as f64 / slots as f64 * 0.7).clamp(1.0, 20.0) as u32;
+
+    let temp_file = format!("/tmp/smite_chart_{}.png", chrono::Utc::now().timestamp_millis());
+
+    {
+        let backend = BitMapBackend::new(&temp_file, (width, height));
+        let root = backend.into_drawing_area();
+        root.fill(&WHITE)
+            .map_err(|e| format!("Failed to fill canvas: {}", e))?;
+
+        let (upper, lower) = root.split_vertically(height * 3 / 4);
+
+        let mut price_chart = ChartBuilder::on(&upper)
+            .caption(
+                format!("{}/{} {} Candles ({})", base_ticker, quote_ticker, options.interval_label, timeframe),
+                ("sans-serif", 40.0).into_font(),
+            )
+            .margin(15)
+            .x_label_area_size(30)
+            .y_label_area_size(60)
+            .build_cartesian_2d(x_min..x_max, y_min..y_max)
+            .map_err(|e| format!("Failed to build chart: {}", e))?;
+
+        price_chart
+            .configure_mesh()
+            .y_desc(format!("{} ({} per 1 {})", quote_ticker, quote_ticker, base_ticker))
+            .draw()
+            .map_err(|e| format!("Failed to draw mesh: {}", e))?;
+
+        let half_interval = options.interval / 2;
+        price_chart
+            .draw_series(candles.iter().map(|c| {
+                CandleStick::new(c.start + half_interval, c.open, c.high, c.low, c.close, GREEN.filled(), RED.filled(), candle_width)
+            }))
+            .map_err(|e| format!("Failed to draw candles: {}", e))?;
+
+        for ((period, line), color) in averages.iter().zip(MOVING_AVERAGE_COLORS.iter()) {
+            if line.is_empty() {
+                continue;
+            }
+            price_chart
+                .draw_series(LineSeries::new(line.iter().map(|p| (p.timestamp + half_interval, p.price)), color))
+                .map_err(|e| format!("Failed to draw moving average: {}", e))?
+                .label(format!("MA {}", period))
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        }
+
+        if let Some(vwap) = vwap {
+            price_chart
+                .draw_series(LineSeries::new(vec![(x_min, vwap), (x_max, vwap)], &VWAP_COLOR))
+                .map_err(|e| format!("Failed to draw VWAP: {}", e))?
+                .label(format!("VWAP {:.4}", vwap))
+                .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], VWAP_COLOR));
+        }
+
+        price_chart
+            .configure_series_labels()
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()
+            .map_err(|e| format!("Failed to draw legend: {}", e))?;
+
+        let mut volume_chart = ChartBuilder::on(&lower)
+            .margin(15)
+            .x_label_area_size(40)
+            .y_label_area_size(60)
+            .build_cartesian_2d(x_min..x_max, 0.0..max_volume)
+            .map_err(|e| format!("Failed to build volume chart: {}", e))?;
+
+        volume_chart
+            .configure_mesh()
+            .y_desc(format!("Volume ({})", base_ticker))
+            .x_desc("Time")
+            .y_labels(4)
+            .draw()
+            .map_err(|e| format!("Failed to draw volume mesh: {}", e))?;
+
+        let bar_margin = options.interval / 7;
+        volume_chart
+            .draw_series(candles.iter().filter(|c| c.volume > 0.0).map(|c| {
+                let color = if c.close >= c.open { GREEN.mix(0.6) } else { RED.mix(0.6) };
+                Rectangle::new(
+                    [(c.start + bar_margin, 0.0), (c.start + options.interval - bar_margin, c.volume)],
+                    color.filled(),
+                )
+            }))
+            .map_err(|e| format!("Failed to draw volume: {}", e))?;
+
+        root.present()
+            .map_err(|e| format!("Failed to render chart: {}", e))?;
+    }
+
+    use std::fs;
+    let image_data = fs::read(&temp_file)
+        .map_err(|e| format!("Failed to read chart file: {}", e))?;
+    let _ = fs::remove_file(&temp_file);
+
+    Ok(image_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(seconds: i64, open: f64, high: f64, low: f64, close: f64, volume: f64) -> Candle {
+        Candle {
+            start: DateTime::<Utc>::from_timestamp(seconds, 0).unwrap(),
+            open,
+            high,
+            low,
+            close,
+            volume,
+        }
+    }
+
+    #[test]
+    fn test_merge_candles() {
+        let hourly = vec![
+            candle(0, 1.0, 3.0, 1.0, 2.0, 10.0),
+            candle(3_600, 2.0, 2.5, 0.5, 1.5, 5.0),
+            // Skips an empty 4h bucket
+            candle(8 * 3_600 + 3_600, 4.0, 4.0, 4.0, 4.0, 0.0),
+        ];
+
+        let merged = merge_candles(&hourly, Duration::hours(4));
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0], candle(0, 1.0, 3.0, 0.5, 1.5, 15.0));
+        assert_eq!(merged[1].start.timestamp(), 8 * 3_600);
+        assert_eq!(merged[1].close, 4.0);
+    }
+
+    #[test]
+    fn test_candle_from_row() {
+        let row = CandleRow {
+            base_currency_id: 1,
+            quote_currency_id: 2,
+            bucket_start: chrono::NaiveDateTime::parse_from_str("2024-03-05 14:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            open: "2".parse().unwrap(),
+            high: "4".parse().unwrap(),
+            low: "0.5".parse().unwrap(),
+            close: "1".parse().unwrap(),
+            volume: "10".parse().unwrap(),
+            quote_volume: "15".parse().unwrap(),
+            trade_count: 3,
+        };
+
+        let direct = candle_from_row(&row, false).unwrap();
+        assert_eq!((direct.open, direct.high, direct.low, direct.close, direct.volume), (2.0, 4.0, 0.5, 1.0, 10.0));
+
+        // The reversed pair's high comes from the stored low
+        let reversed = candle_from_row(&row, true).unwrap();
+        assert_eq!(
+            (reversed.open, reversed.high, reversed.low, reversed.close, reversed.volume),
+            (0.5, 2.0, 0.25, 1.0, 15.0)
+        );
+    }
+
+    #[test]
+    fn test_moving_average() {
+        let candles: Vec<Candle> = (0..4)
+            .map(|i| {
+                let price = (i + 1) as f64;
+                candle(i * 60, price, price, price, price, 0.0)
+            })
+            .collect();
+
+        let average: Vec<f64> = moving_average(&candles, 2).into_iter().map(|p| p.price).collect();
+        assert_eq!(average, vec![1.5, 2.5, 3.5]);
+        assert!(moving_average(&candles, 5).is_empty());
+    }
+
+    #[test]
+    fn test_parse_chart_args() {
+        let request = parse_chart_args(&["7d", "candles", "1h"]).unwrap();
+        assert_eq!(request.timeframe, "7d");
+        let options = request.candles.unwrap();
+        assert_eq!(options.interval, Duration::hours(1));
+        assert_eq!(options.moving_averages, vec![DEFAULT_MOVING_AVERAGE]);
+
+        let request = parse_chart_args(&["candles", "ma10", "ma50"]).unwrap();
+        assert_eq!(request.timeframe, "all");
+        assert_eq!(request.candles.unwrap().moving_averages, vec![10, 50]);
+
+        assert_eq!(parse_chart_args(&["1d"]).unwrap().candles, None);
+        assert!(parse_chart_args(&["ma20"]).is_err());
+        assert!(parse_chart_args(&["candles", "5x"]).is_err());
+        assert!(parse_candle_interval("0m").is_err());
+    }
+}
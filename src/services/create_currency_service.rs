@@ -1,6 +1,5 @@
-use serenity::model::channel::Message;
+use crate::utils::Invocation;
 use serenity::prelude::Context;
-use crate::db;
 use crate::blacklist;
 
 pub struct CreateCurrencyResult {
@@ -10,17 +9,17 @@ pub struct CreateCurrencyResult {
 
 pub async fn execute_create_currency(
     ctx: &Context,
-    msg: &Message,
+    invocation: &Invocation,
     name: &str,
     ticker: &str,
 ) -> Result<CreateCurrencyResult, String> {
     // Get guild ID (required)
-    let guild_id = msg
+    let guild_id = invocation
         .guild_id
         .ok_or("This command can only be used in a guild".to_string())?;
 
     // Check permission - user must be admin
-    crate::utils::check_user_roles(ctx, guild_id, msg.author.id, &["admin"])
+    crate::utils::check_user_roles(ctx, guild_id, invocation.user_id, &["admin"])
         .await?;
 
     let guild_id = guild_id.get() as i64;
@@ -53,16 +52,10 @@ pub async fn execute_create_currency(
         ));
     }
 
-    // Get pool from context
-    let pool = {
-        let data = ctx.data.read().await;
-        data.get::<crate::DatabasePool>()
-            .ok_or("Database not initialized".to_string())?
-            .clone()
-    };
+    let storage = crate::storage::from_ctx(ctx).await?;
 
     // Check if currency already exists in this guild
-    match db::currency::get_currency_by_guild(&pool, guild_id as i64).await {
+    match storage.get_currency_by_guild(guild_id).await {
         Ok(Some(_)) => {
             return Err(
                 "This guild already has a currency. Only one currency per guild is allowed."
@@ -76,7 +69,7 @@ pub async fn execute_create_currency(
     }
 
     // Create the currency
-    let currency_id = db::currency::create_currency(&pool, guild_id as i64, name, &ticker_upper)
+    storage.create_currency(guild_id, name, &ticker_upper)
         .await
         .map_err(|e| format!("Failed to create currency: {}", e))?;
 
@@ -1,32 +1,30 @@
-use serenity::model::channel::Message;
 use serenity::prelude::Context;
-use crate::db;
+use crate::storage::Storage;
+use crate::utils::Amount;
 
 pub struct CurrencyInfo {
     pub name: String,
     pub ticker: String,
-    pub total_in_circulation: f64,
-    pub account_balance_total: f64,
-    pub tax_balance_total: f64,
-    pub swap_maker_total: f64,
+    pub total_in_circulation: Amount,
+    pub account_balance_total: Amount,
+    pub tax_balance_total: Amount,
+    pub swap_maker_total: Amount,
+    pub order_escrow_total: Amount,
     pub date_created: String,
 }
 
 pub async fn execute_info(
     ctx: &Context,
-    msg: &Message,
     ticker: &str,
 ) -> Result<CurrencyInfo, String> {
-    // Get pool from context
-    let pool = {
-        let data = ctx.data.read().await;
-        data.get::<crate::DatabasePool>()
-            .ok_or("Database not initialized".to_string())?
-            .clone()
-    };
+    let storage = crate::storage::from_ctx(ctx).await?;
+    get_currency_info(storage.as_ref(), ticker).await
+}
 
+/// Circulation of the currency with this ticker, broken down by where it is held
+pub async fn get_currency_info(storage: &dyn Storage, ticker: &str) -> Result<CurrencyInfo, String> {
     // Get currency by ticker
-    let currency = db::currency::get_currency_by_ticker(&pool, ticker)
+    let currency = storage.get_currency_by_ticker(ticker)
         .await
         .map_err(|e| format!("Database error: {}", e))?
         .ok_or(format!("❌ Currency '{}' not found", ticker))?;
@@ -36,28 +34,34 @@ pub async fn execute_info(
     let currency_ticker = currency.2;
 
     // Get total balance across all accounts
-    let account_balance_total = db::account::get_total_balance(&pool, currency_id)
+    let account_balance_total = storage.get_total_balance(currency_id)
         .await
         .map_err(|e| format!("Database error: {}", e))?
-        .unwrap_or(0.0);
+        .unwrap_or(Amount::ZERO);
 
     // Get total tax balance
-    let tax_balance_total = db::tax::get_total_tax_balance(&pool, currency_id)
+    let tax_balance_total = storage.get_total_tax_balance(currency_id)
         .await
         .map_err(|e| format!("Database error: {}", e))?
-        .unwrap_or(0.0);
+        .unwrap_or(Amount::ZERO);
 
     // Get total maker amounts in pending/open swaps
-    let swap_maker_total = db::swap::get_total_swap_maker_amount(&pool, currency_id)
+    let swap_maker_total = storage.get_total_swap_maker_amount(currency_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .unwrap_or(Amount::ZERO);
+
+    // Get total held by open limit orders
+    let order_escrow_total = storage.get_total_order_escrow(currency_id)
         .await
         .map_err(|e| format!("Database error: {}", e))?
-        .unwrap_or(0.0);
+        .unwrap_or(Amount::ZERO);
 
     // Calculate total in circulation
-    let total_in_circulation = account_balance_total + tax_balance_total + swap_maker_total;
+    let total_in_circulation = account_balance_total + tax_balance_total + swap_maker_total + order_escrow_total;
 
     // Get creation date
-    let date_created = db::currency::get_currency_date(&pool, currency_id)
+    let date_created = storage.get_currency_date(currency_id)
         .await
         .map_err(|e| format!("Database error: {}", e))?
         .unwrap_or_else(|| "Unknown".to_string());
@@ -69,6 +73,7 @@ pub async fn execute_info(
         account_balance_total,
         tax_balance_total,
         swap_maker_total,
+        order_escrow_total,
         date_created,
     })
 }
@@ -79,10 +84,11 @@ pub fn create_info_embed(info: &CurrencyInfo) -> serenity::builder::CreateEmbed
         .field("Total in Circulation", format!("{:.2} {}", info.total_in_circulation, info.ticker), false)
         .field("Circulation Breakdown", 
             format!(
-                "🏦 **User Accounts:** {:.2} {}\n💰 **Tax Reserves:** {:.2} {}\n💱 **Pending Swaps:** {:.2} {}",
+                "🏦 **User Accounts:** {:.2} {}\n💰 **Tax Reserves:** {:.2} {}\n💱 **Pending Swaps:** {:.2} {}\n📖 **Open Orders:** {:.2} {}",
                 info.account_balance_total, info.ticker,
                 info.tax_balance_total, info.ticker,
-                info.swap_maker_total, info.ticker
+                info.swap_maker_total, info.ticker,
+                info.order_escrow_total, info.ticker
             ),
             false)
         .field("Created", &info.date_created, false)
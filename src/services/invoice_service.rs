@@ -0,0 +1,593 @@
+//! Invoice Service - one user asking another to pay them
+//!
+//! `$invoice` stores the request and DMs the payer Pay/Decline buttons. Paying
+//! claims the invoice, then sends the money with `send_service::send` like a
+//! `$send`, tax included, and links the transaction to the invoice. Invoices
+//! nobody acted on expire after `INVOICE_EXPIRY_DAYS`; the swap expiry worker
+//! closes them with `expire_overdue_invoices`, which first settles payments that
+//! were claimed but never finished.
+
+use serenity::builder::{
+    CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter, CreateMessage,
+};
+use serenity::model::prelude::{ButtonStyle, UserId};
+use serenity::prelude::Context;
+use tracing::{debug, warn};
+use crate::db::transaction::MAX_MEMO_LENGTH;
+use crate::services::send_service;
+use crate::storage::{InvoiceRow, NewInvoice, Storage};
+use crate::utils::page::Page;
+use crate::utils::{Amount, Invocation};
+
+/// Days an invoice can be paid before it expires
+pub const INVOICE_EXPIRY_DAYS: i64 = 7;
+
+/// Open invoices one user may have issued at once
+const MAX_OPEN_INVOICES_PER_ISSUER: usize = 25;
+
+/// Invoices per page in `$invoice list`, and the most that are listed
+const INVOICES_PER_PAGE: usize = 10;
+const MAX_LISTED_INVOICES: i64 = 100;
+
+/// Maximum invoices expired per run, so one run can't hold the pool for long
+const EXPIRY_BATCH_SIZE: i64 = 50;
+
+/// How long an invoice can sit in "paying" before it counts as stalled rather than in flight
+const PAYMENT_STALLED_AFTER_SECONDS: i64 = 300;
+
+/// Statuses `$invoice list` can filter by
+pub const LIST_STATUSES: [&str; 4] = ["open", "paid", "declined", "expired"];
+
+/// Button custom ID prefixes; the invoice ID follows the colon
+const PAY_BUTTON_PREFIX: &str = "invoice_pay:";
+const DECLINE_BUTTON_PREFIX: &str = "invoice_decline:";
+
+/// What an invoice button does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvoiceAction {
+    Pay,
+    Decline,
+}
+
+pub struct InvoiceResult {
+    pub id: i64,
+    pub issuer_id: i64,
+    pub payer_id: i64,
+    pub amount: Amount,
+    pub ticker: String,
+    pub memo: Option<String>,
+    /// Unix timestamp after which it can no longer be paid
+    pub expires_at: i64,
+}
+
+/// A paid invoice, as it was before it was paid
+pub struct InvoicePayment {
+    pub invoice: InvoiceRow,
+    pub transaction_uuid: String,
+    pub tax_amount: Amount,
+}
+
+/// Ask `payer_id` to pay the invoking user, and DM them the invoice
+pub async fn create_invoice(
+    ctx: &Context,
+    invocation: &Invocation,
+    payer_id: i64,
+    amount: Amount,
+    currency_ticker: &str,
+    memo: Option<&str>,
+) -> Result<InvoiceResult, String> {
+    // Paying goes through `$send`, which is guild only
+    invocation
+        .guild_id
+        .ok_or("This command can only be used in a guild".to_string())?;
+
+    let storage = crate::storage::from_ctx(ctx).await?;
+    let result = issue(storage.as_ref(), invocation.user_id_i64(), payer_id, amount, currency_ticker, memo).await?;
+
+    let dm = CreateMessage::default()
+        .embed(create_invoice_embed(&result))
+        .components(create_invoice_buttons(result.id));
+    if let Err(e) = UserId::new(payer_id as u64).dm(ctx, dm).await {
+        debug!("Could not DM user {} about invoice {}: {}", payer_id, result.id, e);
+    }
+
+    Ok(result)
+}
+
+/// Store an open invoice that expires `INVOICE_EXPIRY_DAYS` from now
+pub async fn issue(
+    storage: &dyn Storage,
+    issuer_id: i64,
+    payer_id: i64,
+    amount: Amount,
+    currency_ticker: &str,
+    memo: Option<&str>,
+) -> Result<InvoiceResult, String> {
+    if issuer_id == payer_id {
+        return Err("❌ Cannot invoice yourself".to_string());
+    }
+    if !amount.is_positive() {
+        return Err("❌ Amount must be positive".to_string());
+    }
+    let memo = send_service::clean_memo(memo)?;
+
+    let (currency_id, _, ticker) = storage.get_currency_by_ticker(currency_ticker)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("❌ Currency '{}' not found", currency_ticker))?;
+
+    let open = storage.get_invoices_by_user(issuer_id, Some("open"), MAX_LISTED_INVOICES)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    if open.iter().filter(|invoice| invoice.1 == issuer_id).count() >= MAX_OPEN_INVOICES_PER_ISSUER {
+        return Err(format!(
+            "❌ You already have {} open invoices. Wait for some to be paid or to expire.",
+            MAX_OPEN_INVOICES_PER_ISSUER
+        ));
+    }
+
+    let expires_at = chrono::Utc::now().timestamp() + INVOICE_EXPIRY_DAYS * 24 * 3600;
+    let id = storage.create_invoice(NewInvoice {
+        issuer_id,
+        payer_id,
+        currency_id,
+        amount,
+        memo: memo.clone(),
+        expires_at,
+    })
+    .await
+    .map_err(|e| format!("Failed to create invoice: {}", e))?;
+
+    Ok(InvoiceResult {
+        id,
+        issuer_id,
+        payer_id,
+        amount,
+        ticker,
+        memo,
+        expires_at,
+    })
+}
+
+/// Pay an invoice addressed to the invoking user, and DM both sides
+pub async fn pay_invoice(ctx: &Context, invocation: &Invocation, id: i64) -> Result<CreateEmbed, String> {
+    let storage = crate::storage::from_ctx(ctx).await?;
+    let payment = pay(storage.as_ref(), invocation.user_id_i64(), id).await?;
+
+    let embed = create_payment_embed(&payment);
+    notify(ctx, payment.invoice.1, embed.clone()).await;
+    notify(ctx, payment.invoice.2, embed.clone()).await;
+    Ok(embed)
+}
+
+/// Pay an invoice if `payer_id` is its payer. The invoice is claimed first, so it
+/// is paid at most once, and reopened if the transfer fails. A claim left behind
+/// by a crash or a failed link is settled by `settle_stalled_payments`.
+pub async fn pay(storage: &dyn Storage, payer_id: i64, id: i64) -> Result<InvoicePayment, String> {
+    let invoice = find_open(storage, payer_id, id, "pay").await?;
+    let (_, issuer_id, _, _, ref ticker, amount, ref memo, _, _, _) = invoice;
+
+    if !storage.claim_invoice(id).await.map_err(|e| format!("Database error: {}", e))? {
+        return Err(format!("❌ Invoice `#{}` can no longer be paid", id));
+    }
+
+    let transaction_memo = payment_memo(id, memo.as_deref());
+    let (_, transaction_uuid, tax_amount) =
+        match send_service::send(storage, payer_id, issuer_id, amount, ticker, Some(&transaction_memo)).await {
+            Ok(sent) => sent,
+            Err(error) => {
+                if let Err(e) = storage.set_invoice_status(id, "paying", "open").await {
+                    warn!("Could not reopen invoice {} after a failed payment: {}", id, e);
+                }
+                return Err(error);
+            }
+        };
+
+    match storage.complete_invoice(id, &transaction_uuid).await {
+        Ok(true) => {}
+        Ok(false) => warn!("Invoice {} was paid by {} but was no longer being paid", id, transaction_uuid),
+        Err(e) => warn!("Could not mark invoice {} paid by {}: {}", id, transaction_uuid, e),
+    }
+
+    Ok(InvoicePayment {
+        invoice,
+        transaction_uuid,
+        tax_amount,
+    })
+}
+
+/// Decline an invoice addressed to the invoking user, and DM both sides
+pub async fn decline_invoice(ctx: &Context, invocation: &Invocation, id: i64) -> Result<CreateEmbed, String> {
+    let storage = crate::storage::from_ctx(ctx).await?;
+    let invoice = decline(storage.as_ref(), invocation.user_id_i64(), id).await?;
+
+    let embed = create_status_embed("❌ Invoice Declined", &invoice, 0xff3333);
+    notify(ctx, invoice.1, embed.clone()).await;
+    notify(ctx, invoice.2, embed.clone()).await;
+    Ok(embed)
+}
+
+/// Decline an invoice if `payer_id` is its payer, returning it as it was
+pub async fn decline(storage: &dyn Storage, payer_id: i64, id: i64) -> Result<InvoiceRow, String> {
+    let invoice = find_open(storage, payer_id, id, "decline").await?;
+
+    if !storage.set_invoice_status(id, "open", "declined").await.map_err(|e| format!("Database error: {}", e))? {
+        return Err(format!("❌ Invoice `#{}` can no longer be declined", id));
+    }
+
+    Ok(invoice)
+}
+
+/// An invoice `payer_id` can still act on
+async fn find_open(storage: &dyn Storage, payer_id: i64, id: i64, action: &str) -> Result<InvoiceRow, String> {
+    let invoice = storage.get_invoice(id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or(format!("❌ Invoice `#{}` not found", id))?;
+
+    if invoice.2 != payer_id {
+        return Err(format!("❌ Only <@{}> can {} invoice `#{}`", invoice.2, action, id));
+    }
+    if invoice.7 != "open" {
+        return Err(format!("❌ Invoice `#{}` is already {}", id, status_label(&invoice.7)));
+    }
+    if invoice.9 <= chrono::Utc::now().timestamp() {
+        return Err(format!("❌ Invoice `#{}` has expired", id));
+    }
+
+    Ok(invoice)
+}
+
+/// Memo of the transaction that pays an invoice
+fn payment_memo(id: i64, memo: Option<&str>) -> String {
+    let memo = match memo {
+        Some(memo) => format!("Invoice #{}: {}", id, memo),
+        None => format!("Invoice #{}", id),
+    };
+    memo.chars().take(MAX_MEMO_LENGTH).collect()
+}
+
+/// Settle stalled payments, then expire every overdue invoice (up to one batch)
+/// and DM both sides. Returns the number of invoices expired
+pub async fn expire_overdue_invoices(ctx: &Context) -> Result<usize, String> {
+    let storage = crate::storage::from_ctx(ctx).await?;
+
+    for invoice in settle_stalled_payments(storage.as_ref()).await? {
+        let embed = create_status_embed("✅ Invoice Paid", &invoice, 0x00ff00);
+        notify(ctx, invoice.1, embed.clone()).await;
+        notify(ctx, invoice.2, embed).await;
+    }
+
+    let expired = expire_overdue(storage.as_ref()).await?;
+
+    for invoice in &expired {
+        let embed = create_status_embed("⌛ Invoice Expired", invoice, 0x808080);
+        notify(ctx, invoice.1, embed.clone()).await;
+        notify(ctx, invoice.2, embed).await;
+    }
+
+    Ok(expired.len())
+}
+
+/// Settle invoices left in "paying" (up to one batch). One whose payment went
+/// through is marked paid by that transaction, found by its memo; any other is
+/// reopened, so it can be paid again or expire. Returns the ones marked paid.
+pub async fn settle_stalled_payments(storage: &dyn Storage) -> Result<Vec<InvoiceRow>, String> {
+    let stalled = storage.get_stalled_invoices(PAYMENT_STALLED_AFTER_SECONDS, EXPIRY_BATCH_SIZE)
+        .await
+        .map_err(|e| format!("Failed to fetch stalled invoices: {}", e))?;
+
+    let mut paid = Vec::new();
+    for invoice in stalled {
+        let (id, issuer_id, payer_id, currency_id, _, _, ref memo, _, _, _) = invoice;
+        let transaction_memo = payment_memo(id, memo.as_deref());
+        let payment = match storage.find_transaction_by_memo(payer_id, issuer_id, currency_id, &transaction_memo).await {
+            Ok(payment) => payment,
+            Err(e) => {
+                warn!("Could not look up the payment of invoice {}: {}", id, e);
+                continue;
+            }
+        };
+
+        let settled = match &payment {
+            Some(transaction_uuid) => storage.complete_invoice(id, transaction_uuid).await,
+            None => storage.set_invoice_status(id, "paying", "open").await,
+        };
+        match settled {
+            Ok(true) if payment.is_some() => paid.push(invoice),
+            Ok(true) => debug!("Reopened invoice {} after an unfinished payment", id),
+            Ok(false) => {}
+            Err(e) => warn!("Could not settle the payment of invoice {}: {}", id, e),
+        }
+    }
+
+    Ok(paid)
+}
+
+/// Expire every overdue invoice (up to one batch), returning the ones expired
+pub async fn expire_overdue(storage: &dyn Storage) -> Result<Vec<InvoiceRow>, String> {
+    let overdue = storage.get_overdue_invoices(EXPIRY_BATCH_SIZE)
+        .await
+        .map_err(|e| format!("Failed to fetch overdue invoices: {}", e))?;
+
+    let mut expired = Vec::with_capacity(overdue.len());
+    for invoice in overdue {
+        // Left alone if it was paid or declined since the query above
+        match storage.set_invoice_status(invoice.0, "open", "expired").await {
+            Ok(true) => expired.push(invoice),
+            Ok(false) => {}
+            Err(e) => warn!("Could not expire invoice {}: {}", invoice.0, e),
+        }
+    }
+
+    Ok(expired)
+}
+
+/// Page through the invoices the invoking user issued or has to pay
+pub async fn list_invoices(ctx: &Context, invocation: &Invocation, status: Option<&str>) -> Result<Page, String> {
+    let storage = crate::storage::from_ctx(ctx).await?;
+    let user_id = invocation.user_id_i64();
+    let invoices = storage.get_invoices_by_user(user_id, status, MAX_LISTED_INVOICES)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let title = match status {
+        Some(status) => format!("🧾 Your {} Invoices", capitalize(status)),
+        None => "🧾 Your Invoices".to_string(),
+    };
+
+    if invoices.is_empty() {
+        return Ok(Page::new(vec![CreateEmbed::default()
+            .title(title)
+            .description("No invoices found")
+            .color(0xf1c40f)]));
+    }
+
+    let total_pages = invoices.len().div_ceil(INVOICES_PER_PAGE);
+    let embeds = invoices
+        .chunks(INVOICES_PER_PAGE)
+        .enumerate()
+        .map(|(idx, chunk)| {
+            let mut description = String::new();
+            for (id, issuer_id, payer_id, _, ticker, amount, memo, status, _, expires_at) in chunk {
+                let direction = if *issuer_id == user_id {
+                    format!("to <@{}>", payer_id)
+                } else {
+                    format!("from <@{}>", issuer_id)
+                };
+                description.push_str(&format!("**#{}** `{} {}` {}, {}", id, amount, ticker, direction, status_label(status)));
+                if status == "open" {
+                    description.push_str(&format!(" (expires <t:{}:R>)", expires_at));
+                }
+                description.push('\n');
+                if let Some(memo) = memo {
+                    description.push_str(&format!("└─ 📝 {}\n", memo));
+                }
+            }
+            CreateEmbed::default()
+                .title(title.clone())
+                .description(description)
+                .footer(CreateEmbedFooter::new(format!("Page {}/{}", idx + 1, total_pages)))
+                .color(0xf1c40f)
+        })
+        .collect();
+
+    Ok(Page::new(embeds))
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn status_label(status: &str) -> &'static str {
+    match status {
+        "open" => "🟡 open",
+        "paying" => "⏳ being paid",
+        "paid" => "✅ paid",
+        "declined" => "❌ declined",
+        "expired" => "⌛ expired",
+        _ => "unknown",
+    }
+}
+
+/// DM a user, ignoring users who don't accept DMs
+async fn notify(ctx: &Context, user_id: i64, embed: CreateEmbed) {
+    if let Err(e) = UserId::new(user_id as u64)
+        .dm(ctx, CreateMessage::default().embed(embed))
+        .await
+    {
+        debug!("Could not DM user {} about an invoice: {}", user_id, e);
+    }
+}
+
+/// Pay/Decline buttons for an open invoice
+pub fn create_invoice_buttons(id: i64) -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{}{}", PAY_BUTTON_PREFIX, id))
+            .label("Pay")
+            .emoji('💸')
+            .style(ButtonStyle::Success),
+        CreateButton::new(format!("{}{}", DECLINE_BUTTON_PREFIX, id))
+            .label("Decline")
+            .emoji('❌')
+            .style(ButtonStyle::Danger),
+    ])]
+}
+
+/// Parse a button custom ID created by `create_invoice_buttons`
+pub fn parse_invoice_button(custom_id: &str) -> Option<(InvoiceAction, i64)> {
+    let (action, id) = if let Some(id) = custom_id.strip_prefix(PAY_BUTTON_PREFIX) {
+        (InvoiceAction::Pay, id)
+    } else if let Some(id) = custom_id.strip_prefix(DECLINE_BUTTON_PREFIX) {
+        (InvoiceAction::Decline, id)
+    } else {
+        return None;
+    };
+
+    id.parse().ok().map(|id| (action, id))
+}
+
+pub fn create_invoice_embed(result: &InvoiceResult) -> CreateEmbed {
+    let mut embed = CreateEmbed::default()
+        .title("🧾 Invoice")
+        .field("ID", format!("`{}`", result.id), true)
+        .field("From", format!("<@{}>", result.issuer_id), true)
+        .field("To", format!("<@{}>", result.payer_id), true)
+        .field("Amount", format!("`{} {}`", result.amount, result.ticker), true)
+        .field("Expires", format!("<t:{}:R>", result.expires_at), true)
+        .color(0xf1c40f);
+
+    if let Some(memo) = &result.memo {
+        embed = embed.field("Memo", memo, false);
+    }
+
+    embed.footer(CreateEmbedFooter::new(format!(
+        "Pay with $invoice pay {0} or decline with $invoice decline {0}. Tax is charged to the payer.",
+        result.id
+    )))
+}
+
+fn create_payment_embed(payment: &InvoicePayment) -> CreateEmbed {
+    let mut embed = create_status_embed("✅ Invoice Paid", &payment.invoice, 0x00ff00);
+    if payment.tax_amount.is_positive() {
+        embed = embed.field("Tax", format!("`{} {}`", payment.tax_amount, payment.invoice.4), true);
+    }
+    embed.footer(CreateEmbedFooter::new(format!("Transaction {}", payment.transaction_uuid)))
+}
+
+/// An invoice after it was paid, declined or expired
+fn create_status_embed(title: &str, invoice: &InvoiceRow, color: u32) -> CreateEmbed {
+    let (id, issuer_id, payer_id, _, ticker, amount, memo, _, _, _) = invoice;
+    let mut embed = CreateEmbed::default()
+        .title(title)
+        .field("ID", format!("`{}`", id), true)
+        .field("From", format!("<@{}>", issuer_id), true)
+        .field("To", format!("<@{}>", payer_id), true)
+        .field("Amount", format!("`{} {}`", amount, ticker), true)
+        .color(color);
+
+    if let Some(memo) = memo {
+        embed = embed.field("Memo", memo, false);
+    }
+    embed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{amount, AccountStore, InvoiceStore, MemoryStorage, TransactionStore};
+
+    const ALICE: i64 = 1;
+    const BOB: i64 = 2;
+    const CAROL: i64 = 3;
+
+    #[tokio::test]
+    async fn test_invoice_is_paid_once_through_send() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 10, &[(ALICE, "100")]);
+
+        let invoice = issue(&storage, BOB, ALICE, amount("25"), "abc", Some("web design")).await.unwrap();
+        assert_eq!(invoice.ticker, "ABC");
+
+        assert_eq!(
+            pay(&storage, CAROL, invoice.id).await.err().unwrap(),
+            format!("❌ Only <@{}> can pay invoice `#{}`", ALICE, invoice.id)
+        );
+
+        let payment = pay(&storage, ALICE, invoice.id).await.unwrap();
+        assert_eq!(payment.tax_amount, amount("2.5"));
+        assert_eq!(storage.get_account_balance(ALICE, currency_id).await.unwrap(), Some(amount("72.5")));
+        assert_eq!(storage.get_account_balance(BOB, currency_id).await.unwrap(), Some(amount("25")));
+
+        let row = storage.get_invoice(invoice.id).await.unwrap().unwrap();
+        assert_eq!(row.7, "paid");
+        assert_eq!(row.8.as_deref(), Some(payment.transaction_uuid.as_str()));
+        let (.., memo) = storage.get_transaction_by_uuid(&payment.transaction_uuid).await.unwrap().unwrap();
+        assert_eq!(memo.as_deref(), Some(format!("Invoice #{}: web design", invoice.id).as_str()));
+
+        assert!(pay(&storage, ALICE, invoice.id).await.is_err());
+        assert!(decline(&storage, ALICE, invoice.id).await.is_err());
+        assert_eq!(storage.get_account_balance(BOB, currency_id).await.unwrap(), Some(amount("25")));
+    }
+
+    #[tokio::test]
+    async fn test_failed_payment_reopens_the_invoice() {
+        let storage = MemoryStorage::new();
+        storage.seed_currency("ABC", 0, &[(ALICE, "10")]);
+
+        let invoice = issue(&storage, BOB, ALICE, amount("25"), "ABC", None).await.unwrap();
+        let err = pay(&storage, ALICE, invoice.id).await.err().unwrap();
+        assert!(err.starts_with("❌ Insufficient balance"), "{}", err);
+        assert_eq!(storage.get_invoice(invoice.id).await.unwrap().unwrap().7, "open");
+
+        decline(&storage, ALICE, invoice.id).await.unwrap();
+        assert_eq!(storage.get_invoices_by_user(BOB, Some("declined"), 10).await.unwrap().len(), 1);
+        assert!(storage.get_invoices_by_user(ALICE, Some("open"), 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_overdue_invoices_expire() {
+        let storage = MemoryStorage::new();
+        storage.seed_currency("ABC", 0, &[(ALICE, "100")]);
+
+        let invoice = issue(&storage, BOB, ALICE, amount("5"), "ABC", None).await.unwrap();
+        assert_eq!(
+            issue(&storage, BOB, BOB, amount("5"), "ABC", None).await.err().unwrap(),
+            "❌ Cannot invoice yourself"
+        );
+        assert!(expire_overdue(&storage).await.unwrap().is_empty());
+
+        storage.advance_minutes(INVOICE_EXPIRY_DAYS * 24 * 60 + 1);
+        // Not yet swept, but it can't be claimed any more
+        assert!(!storage.claim_invoice(invoice.id).await.unwrap());
+
+        let expired = expire_overdue(&storage).await.unwrap();
+        assert_eq!(expired.iter().map(|i| i.0).collect::<Vec<_>>(), vec![invoice.id]);
+        assert_eq!(storage.get_invoice(invoice.id).await.unwrap().unwrap().7, "expired");
+        assert!(expire_overdue(&storage).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stalled_payments_are_settled() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 0, &[(ALICE, "100")]);
+
+        // Paid, but the invoice was never linked to the transaction
+        let paid = issue(&storage, BOB, ALICE, amount("10"), "ABC", Some("rent")).await.unwrap();
+        assert!(storage.claim_invoice(paid.id).await.unwrap());
+        let (_, transaction_uuid, _) = send_service::send(&storage, ALICE, BOB, amount("10"), "ABC", Some(&payment_memo(paid.id, Some("rent"))))
+            .await
+            .unwrap();
+        // Claimed, and the process stopped before anything was sent
+        let unpaid = issue(&storage, BOB, ALICE, amount("20"), "ABC", None).await.unwrap();
+        assert!(storage.claim_invoice(unpaid.id).await.unwrap());
+
+        // A payment still in flight is left alone
+        assert!(settle_stalled_payments(&storage).await.unwrap().is_empty());
+        assert_eq!(storage.get_invoice(unpaid.id).await.unwrap().unwrap().7, "paying");
+
+        storage.advance_minutes(PAYMENT_STALLED_AFTER_SECONDS / 60 + 1);
+        let settled = settle_stalled_payments(&storage).await.unwrap();
+        assert_eq!(settled.iter().map(|i| i.0).collect::<Vec<_>>(), vec![paid.id]);
+
+        let row = storage.get_invoice(paid.id).await.unwrap().unwrap();
+        assert_eq!((row.7.as_str(), row.8.as_deref()), ("paid", Some(transaction_uuid.as_str())));
+        assert_eq!(storage.get_invoice(unpaid.id).await.unwrap().unwrap().7, "open");
+
+        // The reopened invoice can be paid, once
+        pay(&storage, ALICE, unpaid.id).await.unwrap();
+        assert_eq!(storage.get_account_balance(ALICE, currency_id).await.unwrap(), Some(amount("70")));
+        assert!(settle_stalled_payments(&storage).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_invoice_button() {
+        assert_eq!(parse_invoice_button("invoice_pay:12"), Some((InvoiceAction::Pay, 12)));
+        assert_eq!(parse_invoice_button("invoice_decline:3"), Some((InvoiceAction::Decline, 3)));
+        assert_eq!(parse_invoice_button("swap_accept:3"), None);
+    }
+}
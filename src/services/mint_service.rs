@@ -1,52 +1,45 @@
-use serenity::model::channel::Message;
+use crate::utils::Invocation;
 use serenity::prelude::Context;
-use crate::db;
-
-// Maximum value for DECIMAL(24,8): 999,999,999,999,999.99999999
-const MAX_BALANCE: f64 = 999_999_999_999_999.99999999;
+use crate::storage::Storage;
+use crate::utils::Amount;
+use crate::utils::errors::LedgerError;
 
 pub struct MintResult {
     pub user_id: i64,
-    pub amount: f64,
-    pub new_balance: f64,
+    pub amount: Amount,
+    pub new_balance: Amount,
     pub currency_ticker: String,
 }
 
 pub async fn execute_mint(
     ctx: &Context,
-    msg: &Message,
+    invocation: &Invocation,
     user_id: i64,
-    amount: f64,
+    amount: Amount,
     currency_ticker: &str,
 ) -> Result<MintResult, String> {
     // Get guild ID (required)
-    let guild_id = msg
+    let guild_id = invocation
         .guild_id
         .ok_or("This command can only be used in a guild".to_string())?;
 
     // Check permission - user must be admin or minter
-    crate::utils::check_user_roles(ctx, guild_id, msg.author.id, &["admin", "minter"])
+    crate::utils::check_user_roles(ctx, guild_id, invocation.user_id, &["admin", "minter"])
         .await?;
 
     let guild_id_i64 = guild_id.get() as i64;
 
-    // Get pool from context
-    let pool = {
-        let data = ctx.data.read().await;
-        data.get::<crate::DatabasePool>()
-            .ok_or("Database not initialized".to_string())?
-            .clone()
-    };
+    let storage = crate::storage::from_ctx(ctx).await?;
 
     // Look up currency by ticker
-    let currency_id = db::currency::get_currency_by_ticker(&pool, currency_ticker)
+    let currency_id = storage.get_currency_by_ticker(currency_ticker)
         .await
         .map_err(|e| format!("Database error: {}", e))?
         .map(|(id, _, _)| id)
         .ok_or_else(|| format!("Currency '{}' not found", currency_ticker))?;
     
     // SECURITY: Verify the currency and check permissions
-    let currency_details = db::currency::get_currency_by_id(&pool, currency_id)
+    let currency_details = storage.get_currency_by_id(currency_id)
         .await
         .map_err(|e| format!("Database error: {}", e))?
         .ok_or("Currency not found".to_string())?;
@@ -58,62 +51,47 @@ pub async fn execute_mint(
         // This is a cross-guild mint attempt - check permission in target guild
         let target_guild_id = serenity::model::prelude::GuildId::new(currency_guild_id as u64);
         
-        crate::utils::check_user_roles(ctx, target_guild_id, msg.author.id, &["admin", "minter"])
+        crate::utils::check_user_roles(ctx, target_guild_id, invocation.user_id, &["admin", "minter"])
             .await?;
     }
 
-    // Get or create account
-    let account_id = match db::account::get_account_id(&pool, user_id, currency_id).await {
-        Ok(Some(id)) => id,
-        Ok(None) => {
-            // Account doesn't exist, create it
-            db::account::create_account(&pool, user_id, currency_id)
-                .await
-                .map_err(|e| format!("Failed to create account: {}", e))?
-        }
-        Err(e) => return Err(format!("Database error: {}", e)),
-    };
-
-    // Get current balance
-    let current_balance = db::account::get_account_balance(&pool, user_id, currency_id)
-        .await
-        .map_err(|e| format!("Database error: {}", e))?
-        .unwrap_or(0.0);
-
-    // Calculate new balance
-    let new_balance = current_balance + amount;
-
-    // Prevent negative balance
-    if new_balance < 0.0 {
-        return Err(format!(
-            "❌ Operation blocked: Cannot reduce balance below 0.\n\
-             Current balance: {:.8} {}\n\
-             Requested change: {:.8} {}\n\
-             New balance would be: {:.8} {}",
-            current_balance, currency_ticker,
-            amount, currency_ticker,
-            new_balance, currency_ticker
-        ));
-    }
-
-    // Check for overflow
-    if new_balance > MAX_BALANCE {
-        return Err(format!(
-            "❌ Operation blocked: Balance would exceed maximum limit.\n\
-             Current balance: {:.8} {}\n\
-             Requested mint: {:.8} {}\n\
-             New balance would be: {:.8} {}\n\
-             Maximum allowed: {:.8} {}",
-            current_balance, currency_ticker,
-            amount, currency_ticker,
-            new_balance, currency_ticker,
-            MAX_BALANCE, currency_ticker
-        ));
-    }
+    apply_mint(storage.as_ref(), user_id, currency_id, amount, currency_ticker).await
+}
 
-    // Update balance
-    db::account::update_balance(&pool, account_id, amount).await
-        .map_err(|e| format!("Failed to update balance: {}", e))?;
+/// Add `amount` (negative to burn) to a user's balance once permissions have been checked
+pub async fn apply_mint(
+    storage: &dyn Storage,
+    user_id: i64,
+    currency_id: i64,
+    amount: Amount,
+    currency_ticker: &str,
+) -> Result<MintResult, String> {
+    // Balance check and update happen in one locked transaction
+    let (_, new_balance) = storage.mint(user_id, currency_id, amount)
+        .await
+        .map_err(|e| match e {
+            LedgerError::NegativeBalance { current, new_balance } => format!(
+                "❌ Operation blocked: Cannot reduce balance below 0.\n\
+                 Current balance: {:.8} {}\n\
+                 Requested change: {:.8} {}\n\
+                 New balance would be: {:.8} {}",
+                current, currency_ticker,
+                amount, currency_ticker,
+                new_balance, currency_ticker
+            ),
+            LedgerError::BalanceLimit { current, new_balance } => format!(
+                "❌ Operation blocked: Balance would exceed maximum limit.\n\
+                 Current balance: {:.8} {}\n\
+                 Requested mint: {:.8} {}\n\
+                 New balance would be: {:.8} {}\n\
+                 Maximum allowed: {:.8} {}",
+                current, currency_ticker,
+                amount, currency_ticker,
+                new_balance, currency_ticker,
+                Amount::MAX, currency_ticker
+            ),
+            other => format!("Failed to update balance: {}", other),
+        })?;
 
     Ok(MintResult {
         user_id,
@@ -139,3 +117,31 @@ pub fn create_mint_embed(result: &MintResult) -> serenity::builder::CreateEmbed
         )
         .color(0x9900ff)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{amount, AccountStore, MemoryStorage};
+
+    const ALICE: i64 = 1;
+
+    #[tokio::test]
+    async fn test_apply_mint_and_burn() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 0, &[]);
+
+        // Minting creates the account
+        let result = apply_mint(&storage, ALICE, currency_id, amount("25"), "ABC").await.unwrap();
+        assert_eq!(result.new_balance, amount("25"));
+
+        let err = apply_mint(&storage, ALICE, currency_id, amount("-30"), "ABC").await.err().unwrap();
+        assert!(err.contains("Cannot reduce balance below 0"), "{}", err);
+
+        let err = apply_mint(&storage, ALICE, currency_id, Amount::MAX, "ABC").await.err().unwrap();
+        assert!(err.contains("Balance would exceed maximum limit"), "{}", err);
+
+        let result = apply_mint(&storage, ALICE, currency_id, amount("-25"), "ABC").await.unwrap();
+        assert_eq!(result.new_balance, Amount::ZERO);
+        assert_eq!(storage.get_account_balance(ALICE, currency_id).await.unwrap(), Some(Amount::ZERO));
+    }
+}
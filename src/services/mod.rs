@@ -2,6 +2,9 @@ pub mod ping_service;
 pub mod send_service;
 pub mod balance_service;
 pub mod swap_service;
+pub mod swap_expiry_service;
+pub mod order_service;
+pub mod route_service;
 pub mod mint_service;
 pub mod create_currency_service;
 pub mod transaction_service;
@@ -11,3 +14,8 @@ pub mod tax_service;
 pub mod info_service;
 pub mod board_service;
 pub mod wire_service;
+pub mod recurring_service;
+pub mod recurring_scheduler_service;
+pub mod payroll_service;
+pub mod invoice_service;
+pub mod wire_recovery_service;
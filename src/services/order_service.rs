@@ -0,0 +1,330 @@
+use serenity::builder::{CreateEmbed, CreateEmbedFooter};
+use serenity::prelude::Context;
+use crate::db;
+use crate::db::orderbook::OrderSide;
+use crate::services::price_service;
+use crate::utils::page::Page;
+use crate::utils::{Amount, Invocation};
+use crate::utils::errors::LedgerError;
+
+/// Price levels shown per side in `$book`
+const BOOK_DEPTH_LEVELS: i64 = 10;
+
+/// Orders per page in `$order list`
+const ORDERS_PER_PAGE: usize = 10;
+
+pub struct OrderResult {
+    pub order_id: i64,
+    pub user_id: i64,
+    pub side: OrderSide,
+    pub base_ticker: String,
+    pub quote_ticker: String,
+    pub price: Amount,
+    pub quantity: Amount,
+    pub filled_quantity: Amount,
+    pub quote_amount: Amount,
+    pub fills: usize,
+    pub status: String,
+}
+
+/// Resolve a `BASE/QUOTE` pair to (base_id, quote_id, base_ticker, quote_ticker, is_reversed),
+/// where the IDs are in canonical order and `is_reversed` says the user wrote it the other way round
+async fn resolve_pair(
+    pool: &sqlx::MySqlPool,
+    pair: &str,
+) -> Result<(i64, i64, String, String, bool), String> {
+    let (base_ticker, quote_ticker) = price_service::parse_price_pair(pair)
+        .map_err(|_| "❌ Invalid pair format. Use: `BASE/QUOTE`".to_string())?;
+
+    if base_ticker == quote_ticker {
+        return Err("❌ Base and quote currencies must be different".to_string());
+    }
+
+    let (base_id, _, _) = db::currency::get_currency_by_ticker(pool, &base_ticker)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or(format!("❌ Currency '{}' not found", base_ticker))?;
+
+    let (quote_id, _, _) = db::currency::get_currency_by_ticker(pool, &quote_ticker)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or(format!("❌ Currency '{}' not found", quote_ticker))?;
+
+    let (canonical_base, canonical_quote, is_reversed) = db::tradelog::normalize_pair(pool, base_id, quote_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    if is_reversed {
+        Ok((canonical_base, canonical_quote, quote_ticker, base_ticker, true))
+    } else {
+        Ok((canonical_base, canonical_quote, base_ticker, quote_ticker, false))
+    }
+}
+
+/// Place a limit order and match it against the book
+pub async fn place_order(
+    ctx: &Context,
+    invocation: &Invocation,
+    side: OrderSide,
+    quantity: Amount,
+    pair: &str,
+    price: Amount,
+) -> Result<OrderResult, String> {
+    invocation
+        .guild_id
+        .ok_or("This command can only be used in a guild".to_string())?;
+
+    if !quantity.is_positive() || !price.is_positive() {
+        return Err("❌ Quantity and price must be positive".to_string());
+    }
+
+    let pool = {
+        let data = ctx.data.read().await;
+        data.get::<crate::DatabasePool>()
+            .ok_or(crate::db::MYSQL_ONLY.to_string())?
+            .clone()
+    };
+
+    let (base_id, quote_id, base_ticker, quote_ticker, is_reversed) = resolve_pair(&pool, pair).await?;
+
+    // Each pair has one book, priced in its canonical quote currency
+    if is_reversed {
+        return Err(format!(
+            "❌ The {}/{} book is quoted as `{}/{}`. Place the order on that pair, e.g. `$order {} <qty> {}/{} <price>`",
+            quote_ticker, base_ticker,
+            base_ticker, quote_ticker,
+            side.opposite().as_str(), base_ticker, quote_ticker
+        ));
+    }
+
+    let user_id = invocation.user_id_i64();
+    let receipt = db::orderbook::place_order(&pool, user_id, base_id, quote_id, side, price, quantity)
+        .await
+        .map_err(|e| match e {
+            LedgerError::AccountNotFound => format!(
+                "❌ You don't have a {} account",
+                if side == OrderSide::Buy { &quote_ticker } else { &base_ticker }
+            ),
+            LedgerError::InsufficientBalance { required, available } => format!(
+                "❌ Insufficient balance\n\nOrder requires: {} {}\nAvailable: {} {}",
+                required, if side == OrderSide::Buy { &quote_ticker } else { &base_ticker },
+                available, if side == OrderSide::Buy { &quote_ticker } else { &base_ticker }
+            ),
+            LedgerError::BalanceLimit { .. } => {
+                "❌ Order blocked: a fill would push a balance over the maximum limit".to_string()
+            }
+            LedgerError::AmountTooLarge => "❌ Order value is too large".to_string(),
+            other => format!("Order failed: {}", other),
+        })?;
+
+    Ok(OrderResult {
+        order_id: receipt.order_id,
+        user_id,
+        side,
+        base_ticker,
+        quote_ticker,
+        price,
+        quantity,
+        filled_quantity: receipt.filled_quantity,
+        quote_amount: receipt.quote_amount,
+        fills: receipt.fills,
+        status: receipt.status,
+    })
+}
+
+/// Cancel one of the caller's open orders and refund its escrow
+pub async fn cancel_order(
+    ctx: &Context,
+    invocation: &Invocation,
+    order_id: i64,
+) -> Result<CreateEmbed, String> {
+    let pool = {
+        let data = ctx.data.read().await;
+        data.get::<crate::DatabasePool>()
+            .ok_or(crate::db::MYSQL_ONLY.to_string())?
+            .clone()
+    };
+
+    let (_, base_ticker, quote_ticker, side, _, _, _, _) = db::orderbook::get_order(&pool, order_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or(format!("❌ Order {} not found", order_id))?;
+
+    let refunded = db::orderbook::cancel_order(&pool, invocation.user_id_i64(), order_id)
+        .await
+        .map_err(|e| match e {
+            LedgerError::OrderNotFound => format!("❌ Order {} not found", order_id),
+            LedgerError::NotOrderOwner => "❌ You can only cancel your own orders".to_string(),
+            LedgerError::OrderNotOpen => format!("❌ Order {} is already filled or cancelled", order_id),
+            other => format!("Cancel failed: {}", other),
+        })?;
+
+    let refund_ticker = if side == "buy" { quote_ticker } else { base_ticker };
+
+    Ok(CreateEmbed::default()
+        .title("🗑️ Order Cancelled")
+        .field("Order ID", format!("`{}`", order_id), true)
+        .field("Refunded", format!("`{} {}`", refunded, refund_ticker), true)
+        .color(0x808080))
+}
+
+/// Page through the caller's open orders
+pub async fn list_orders(
+    ctx: &Context,
+    invocation: &Invocation,
+) -> Result<Page, String> {
+    let pool = {
+        let data = ctx.data.read().await;
+        data.get::<crate::DatabasePool>()
+            .ok_or(crate::db::MYSQL_ONLY.to_string())?
+            .clone()
+    };
+
+    let orders = db::orderbook::get_open_orders_by_user(&pool, invocation.user_id_i64())
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    if orders.is_empty() {
+        return Ok(Page::new(vec![CreateEmbed::default()
+            .title("📖 Your Open Orders")
+            .description("You have no open orders")
+            .color(0x0b5394)]));
+    }
+
+    let total_pages = orders.len().div_ceil(ORDERS_PER_PAGE);
+    let embeds = orders
+        .chunks(ORDERS_PER_PAGE)
+        .enumerate()
+        .map(|(idx, chunk)| {
+            let mut description = String::new();
+            for (id, base, quote, side, price, quantity, filled) in chunk {
+                description.push_str(&format!(
+                    "**#{}** {} `{} {}` @ `{} {}` ({} filled)\n",
+                    id, side.to_uppercase(), quantity, base, price, quote, filled
+                ));
+            }
+            CreateEmbed::default()
+                .title("📖 Your Open Orders")
+                .description(description)
+                .footer(CreateEmbedFooter::new(format!("Page {}/{}", idx + 1, total_pages)))
+                .color(0x0b5394)
+        })
+        .collect();
+
+    Ok(Page::new(embeds))
+}
+
+/// Depth view of a pair's book: best bids and asks, spread and last trade
+pub async fn get_book(ctx: &Context, pair: &str) -> Result<CreateEmbed, String> {
+    let pool = {
+        let data = ctx.data.read().await;
+        data.get::<crate::DatabasePool>()
+            .ok_or(crate::db::MYSQL_ONLY.to_string())?
+            .clone()
+    };
+
+    // A reversed pair just shows the canonical book
+    let (base_id, quote_id, base_ticker, quote_ticker, _) = resolve_pair(&pool, pair).await?;
+
+    let bids = db::orderbook::get_book_depth(&pool, base_id, quote_id, OrderSide::Buy, BOOK_DEPTH_LEVELS)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    let asks = db::orderbook::get_book_depth(&pool, base_id, quote_id, OrderSide::Sell, BOOK_DEPTH_LEVELS)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let last_price = db::tradelog::get_latest_price_for_pair(&pool, base_id, quote_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .map(|(price, _)| price);
+
+    let mut embed = CreateEmbed::default()
+        .title(format!("📖 Order Book: {}/{}", base_ticker, quote_ticker))
+        .description(format!("Prices in {} per {}", quote_ticker, base_ticker))
+        .field("🟥 Asks", format_depth(&asks, &base_ticker), true)
+        .field("🟩 Bids", format_depth(&bids, &base_ticker), true);
+
+    let spread = match (bids.first(), asks.first()) {
+        (Some((best_bid, _, _)), Some((best_ask, _, _))) => format!("`{}`", *best_ask - *best_bid),
+        _ => "—".to_string(),
+    };
+    embed = embed.field("Spread", spread, false);
+
+    if let Some(price) = last_price {
+        embed = embed.field("Last Price", format!("`{:.8} {}`", price, quote_ticker), false);
+    }
+
+    Ok(embed.color(0x0b5394))
+}
+
+/// One line per price level, e.g. "`1.5` × `20 ABC` (3)"
+pub fn format_depth(levels: &[(Amount, Amount, i64)], base_ticker: &str) -> String {
+    if levels.is_empty() {
+        return "No orders".to_string();
+    }
+
+    levels
+        .iter()
+        .map(|(price, quantity, count)| format!("`{}` × `{} {}` ({})", price, quantity, base_ticker, count))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn create_order_embed(result: &OrderResult) -> CreateEmbed {
+    let (title, color) = match (result.status.as_str(), result.fills) {
+        ("filled", _) => ("✅ Order Filled", 0x00ff00),
+        (_, 0) => ("📖 Order Placed", 0xffa500),
+        _ => ("🧩 Order Partially Filled", 0x00bfff),
+    };
+
+    let mut embed = CreateEmbed::default()
+        .title(title)
+        .field("Order ID", format!("`{}`", result.order_id), true)
+        .field("Trader", format!("<@{}>", result.user_id), true)
+        .field("Side", result.side.as_str().to_uppercase(), true)
+        .field("Quantity", format!("`{} {}`", result.quantity, result.base_ticker), true)
+        .field("Limit Price", format!("`{} {}`", result.price, result.quote_ticker), true);
+
+    if result.fills > 0 {
+        let verb = if result.side == OrderSide::Buy { "paid" } else { "received" };
+        embed = embed.field(
+            "Matched",
+            format!(
+                "`{} {}` for `{} {}` {} in {} fill(s)",
+                result.filled_quantity, result.base_ticker,
+                result.quote_amount, result.quote_ticker,
+                verb, result.fills
+            ),
+            false,
+        );
+    }
+
+    if result.status == "open" {
+        embed = embed.field(
+            "Resting",
+            format!("`{} {}` on the book", result.quantity - result.filled_quantity, result.base_ticker),
+            false,
+        );
+    }
+
+    embed.color(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_depth() {
+        assert_eq!(format_depth(&[], "ABC"), "No orders");
+
+        let levels = vec![
+            ("1.5".parse().unwrap(), "20".parse().unwrap(), 3),
+            ("1.25".parse().unwrap(), "4.5".parse().unwrap(), 1),
+        ];
+        assert_eq!(
+            format_depth(&levels, "ABC"),
+            "`1.5` × `20 ABC` (3)\n`1.25` × `4.5 ABC` (1)"
+        );
+    }
+}
@@ -0,0 +1,514 @@
+//! Payroll Service - pay every member of a Discord role a fixed amount
+//!
+//! Admins pay a role in their guild's currency, from the currency's tax account
+//! (the treasury) or by minting it. Each member is paid in a transaction of their
+//! own, so one failure doesn't stop the rest, and the admin gets a report of who
+//! was paid and who wasn't. Scheduled payrolls are run by the recurring payment
+//! worker through `run_due_payrolls`.
+
+use serenity::builder::{CreateEmbed, CreateEmbedFooter, CreateMessage};
+use serenity::model::prelude::{GuildId, RoleId, UserId};
+use serenity::prelude::Context;
+use tracing::{debug, info, warn};
+use crate::db::payroll::PayrollSource;
+use crate::db::recurring::RecurringPeriod;
+use crate::storage::{NewPayroll, PayrollRow, Storage};
+use crate::utils::errors::LedgerError;
+use crate::utils::page::Page;
+use crate::utils::{Amount, Invocation};
+
+/// Most members one payroll may pay
+const MAX_PAYROLL_RECIPIENTS: usize = 1000;
+
+/// Most scheduled payrolls the worker runs per check
+const PAYROLL_BATCH_SIZE: i64 = 10;
+
+/// Failures listed in a report before the rest are only counted
+const FAILURES_SHOWN: usize = 10;
+
+/// Schedules per page in `$payroll list`
+const PAYROLLS_PER_PAGE: usize = 10;
+
+pub struct PayrollReport {
+    pub role_id: i64,
+    pub amount: Amount,
+    pub ticker: String,
+    pub source: PayrollSource,
+    /// (member, transaction uuid)
+    pub paid: Vec<(i64, String)>,
+    /// (member, error)
+    pub failed: Vec<(i64, String)>,
+}
+
+impl PayrollReport {
+    pub fn total_paid(&self) -> Amount {
+        self.paid.iter().map(|_| self.amount).sum()
+    }
+}
+
+pub struct ScheduledPayroll {
+    pub id: i64,
+    pub role_id: i64,
+    pub amount: Amount,
+    pub ticker: String,
+    pub source: PayrollSource,
+    pub period: RecurringPeriod,
+    /// Unix timestamp of the first run
+    pub next_run_at: i64,
+}
+
+/// The guild and currency a payroll command acts on, once the caller is known to be an admin
+struct PayrollTarget {
+    guild_id: GuildId,
+    currency_id: i64,
+    ticker: String,
+}
+
+async fn authorize(
+    ctx: &Context,
+    invocation: &Invocation,
+    storage: &dyn Storage,
+    currency_ticker: &str,
+) -> Result<PayrollTarget, String> {
+    let guild_id = invocation
+        .guild_id
+        .ok_or("This command can only be used in a guild".to_string())?;
+
+    crate::utils::check_user_roles(ctx, guild_id, invocation.user_id, &["admin"]).await?;
+
+    let (currency_id, currency_guild_id, _, ticker) = storage.get_currency_by_ticker_with_guild(currency_ticker)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("❌ Currency '{}' not found", currency_ticker))?;
+
+    if currency_guild_id != guild_id.get() as i64 {
+        return Err("❌ Payroll can only pay this guild's own currency".to_string());
+    }
+
+    Ok(PayrollTarget { guild_id, currency_id, ticker })
+}
+
+/// Pay every member of a role now
+pub async fn run_payroll(
+    ctx: &Context,
+    invocation: &Invocation,
+    role: &str,
+    amount: Amount,
+    currency_ticker: &str,
+    source: PayrollSource,
+) -> Result<PayrollReport, String> {
+    let storage = crate::storage::from_ctx(ctx).await?;
+    let target = authorize(ctx, invocation, storage.as_ref(), currency_ticker).await?;
+
+    let (role_id, _) = crate::utils::resolve_role(ctx, target.guild_id, role).await?;
+    let members = crate::utils::get_role_members(ctx, target.guild_id, role_id).await?;
+    let recipients: Vec<i64> = members.iter().map(|id| id.get() as i64).collect();
+
+    info!(
+        "User {} is paying {} {} to {} members of role {}",
+        invocation.user_id, amount, target.ticker, recipients.len(), role_id
+    );
+
+    pay_role(
+        storage.as_ref(),
+        invocation.user_id_i64(),
+        role_id.get() as i64,
+        &recipients,
+        target.currency_id,
+        &target.ticker,
+        amount,
+        source,
+    )
+    .await
+}
+
+/// Schedule a payroll whose first run is one period from now
+pub async fn schedule_payroll(
+    ctx: &Context,
+    invocation: &Invocation,
+    role: &str,
+    amount: Amount,
+    currency_ticker: &str,
+    source: PayrollSource,
+    period: RecurringPeriod,
+) -> Result<ScheduledPayroll, String> {
+    if !amount.is_positive() {
+        return Err("❌ Amount must be positive".to_string());
+    }
+
+    let storage = crate::storage::from_ctx(ctx).await?;
+    let target = authorize(ctx, invocation, storage.as_ref(), currency_ticker).await?;
+    let (role_id, _) = crate::utils::resolve_role(ctx, target.guild_id, role).await?;
+
+    let next_run_at = period.next_run(chrono::Utc::now().timestamp());
+    let id = storage.create_payroll(NewPayroll {
+        guild_id: target.guild_id.get() as i64,
+        role_id: role_id.get() as i64,
+        currency_id: target.currency_id,
+        amount,
+        source,
+        period,
+        next_run_at,
+        created_by: invocation.user_id_i64(),
+    })
+    .await
+    .map_err(|e| format!("Failed to schedule payroll: {}", e))?;
+
+    Ok(ScheduledPayroll {
+        id,
+        role_id: role_id.get() as i64,
+        amount,
+        ticker: target.ticker,
+        source,
+        period,
+        next_run_at,
+    })
+}
+
+/// Pay `amount` to each recipient, one transaction each, logged from the issuer
+#[allow(clippy::too_many_arguments)]
+pub async fn pay_role(
+    storage: &dyn Storage,
+    issuer_id: i64,
+    role_id: i64,
+    recipients: &[i64],
+    currency_id: i64,
+    ticker: &str,
+    amount: Amount,
+    source: PayrollSource,
+) -> Result<PayrollReport, String> {
+    if !amount.is_positive() {
+        return Err("❌ Amount must be positive".to_string());
+    }
+    if recipients.is_empty() {
+        return Err(format!("❌ Nobody holds <@&{}>", role_id));
+    }
+    if recipients.len() > MAX_PAYROLL_RECIPIENTS {
+        return Err(format!(
+            "❌ <@&{}> has {} members; payroll can pay at most {}",
+            role_id, recipients.len(), MAX_PAYROLL_RECIPIENTS
+        ));
+    }
+
+    let mut report = PayrollReport {
+        role_id,
+        amount,
+        ticker: ticker.to_string(),
+        source,
+        paid: Vec::new(),
+        failed: Vec::new(),
+    };
+
+    for &recipient_id in recipients {
+        match storage.payout(issuer_id, recipient_id, currency_id, amount, source).await {
+            Ok(uuid) => report.paid.push((recipient_id, uuid)),
+            Err(e) => report.failed.push((recipient_id, payout_error(e, ticker))),
+        }
+    }
+
+    Ok(report)
+}
+
+fn payout_error(error: LedgerError, ticker: &str) -> String {
+    match error {
+        LedgerError::InsufficientBalance { available, .. } => {
+            format!("Treasury only has {:.2} {}", available, ticker)
+        }
+        LedgerError::TaxAccountNotFound => "No treasury (tax account) for this currency".to_string(),
+        LedgerError::BalanceLimit { .. } => "Balance would exceed the maximum limit".to_string(),
+        other => format!("Payment failed: {}", other),
+    }
+}
+
+/// Cancel one of the guild's payroll schedules
+pub async fn cancel_payroll(ctx: &Context, invocation: &Invocation, id: i64) -> Result<CreateEmbed, String> {
+    let guild_id = invocation
+        .guild_id
+        .ok_or("This command can only be used in a guild".to_string())?;
+
+    crate::utils::check_user_roles(ctx, guild_id, invocation.user_id, &["admin"]).await?;
+
+    let storage = crate::storage::from_ctx(ctx).await?;
+    let (_, _, role_id, _, ticker, amount, _, period, _, _, _) =
+        cancel(storage.as_ref(), guild_id.get() as i64, id).await?;
+
+    Ok(CreateEmbed::default()
+        .title("🗑️ Payroll Cancelled")
+        .description(format!(
+            "Payroll `#{}` of **{} {}** {} to <@&{}> will not run again.",
+            id, amount, ticker, period, role_id
+        ))
+        .color(0x808080))
+}
+
+/// Cancel a schedule if it belongs to `guild_id`, returning it as it was
+pub async fn cancel(storage: &dyn Storage, guild_id: i64, id: i64) -> Result<PayrollRow, String> {
+    let payroll = storage.get_payroll(id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .filter(|payroll| payroll.1 == guild_id && payroll.10 == "active")
+        .ok_or(format!("❌ Payroll `#{}` not found", id))?;
+
+    storage.set_payroll_status(id, "cancelled")
+        .await
+        .map_err(|e| format!("Failed to cancel payroll: {}", e))?;
+
+    Ok(payroll)
+}
+
+/// Page through the guild's payroll schedules
+pub async fn list_payrolls(ctx: &Context, invocation: &Invocation) -> Result<Page, String> {
+    let guild_id = invocation
+        .guild_id
+        .ok_or("This command can only be used in a guild".to_string())?;
+
+    let storage = crate::storage::from_ctx(ctx).await?;
+    let payrolls = storage.get_payrolls_by_guild(guild_id.get() as i64)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    if payrolls.is_empty() {
+        return Ok(Page::new(vec![CreateEmbed::default()
+            .title("💼 Scheduled Payrolls")
+            .description("This guild has no scheduled payrolls")
+            .color(0x2e8b57)]));
+    }
+
+    let total_pages = payrolls.len().div_ceil(PAYROLLS_PER_PAGE);
+    let embeds = payrolls
+        .chunks(PAYROLLS_PER_PAGE)
+        .enumerate()
+        .map(|(idx, chunk)| {
+            let mut description = String::new();
+            for (id, _, role_id, _, ticker, amount, source, period, next_run_at, _, _) in chunk {
+                description.push_str(&format!(
+                    "**#{}** `{} {}` {} to <@&{}> from {}, next <t:{}:R>\n",
+                    id, amount, ticker, period, role_id, source, next_run_at
+                ));
+            }
+            CreateEmbed::default()
+                .title("💼 Scheduled Payrolls")
+                .description(description)
+                .footer(CreateEmbedFooter::new(format!("Page {}/{}", idx + 1, total_pages)))
+                .color(0x2e8b57)
+        })
+        .collect();
+
+    Ok(Page::new(embeds))
+}
+
+/// Claim every due schedule (up to one batch) by moving it to its next run.
+/// Returns the claimed schedules with their next run.
+pub async fn claim_due_payrolls(storage: &dyn Storage) -> Result<Vec<(PayrollRow, i64)>, String> {
+    let due = storage.get_due_payrolls(PAYROLL_BATCH_SIZE)
+        .await
+        .map_err(|e| format!("Failed to fetch due payrolls: {}", e))?;
+
+    let mut claimed = Vec::with_capacity(due.len());
+    for payroll in due {
+        let (id, due_at) = (payroll.0, payroll.8);
+        let Some(period) = RecurringPeriod::parse(&payroll.7) else {
+            warn!("Payroll {} has an unknown period '{}'", id, payroll.7);
+            continue;
+        };
+
+        let next_run_at = period.next_run(due_at);
+        match storage.reschedule_payroll(id, due_at, next_run_at).await {
+            Ok(true) => claimed.push((payroll, next_run_at)),
+            Ok(false) => {}
+            Err(e) => warn!("Could not reschedule payroll {}: {}", id, e),
+        }
+    }
+
+    Ok(claimed)
+}
+
+/// Run every due payroll schedule and DM each report to the admin who set it up
+pub async fn run_due_payrolls(ctx: &Context, storage: &dyn Storage) -> Result<usize, String> {
+    let claimed = claim_due_payrolls(storage).await?;
+
+    for (payroll, next_run_at) in &claimed {
+        let (id, guild_id, role_id, currency_id, ref ticker, amount, ref source, _, _, created_by, _) = *payroll;
+        let guild_id = GuildId::new(guild_id as u64);
+        let source = PayrollSource::parse(source).unwrap_or(PayrollSource::Treasury);
+
+        let members = match crate::utils::get_role_members(ctx, guild_id, RoleId::new(role_id as u64)).await {
+            Ok(members) => members,
+            Err(e) => {
+                warn!("Payroll {} could not list the members of role {}: {}", id, role_id, e);
+                notify(ctx, created_by, id, CreateEmbed::default()
+                    .title("⚠️ Scheduled Payroll Failed")
+                    .description(format!(
+                        "Payroll `#{}` could not list the members of <@&{}>:\n{}\n\nThe next attempt is <t:{}:R>.",
+                        id, role_id, e, next_run_at
+                    ))
+                    .color(0xffaa00)).await;
+                continue;
+            }
+        };
+        let recipients: Vec<i64> = members.iter().map(|id| id.get() as i64).collect();
+
+        let embed = match pay_role(storage, created_by, role_id, &recipients, currency_id, ticker, amount, source).await {
+            Ok(report) => {
+                info!("Payroll {} paid {} and failed {} members", id, report.paid.len(), report.failed.len());
+                create_payroll_report_embed(&report)
+                    .title(format!("💼 Scheduled Payroll #{}", id))
+                    .footer(CreateEmbedFooter::new(format!("Next run: {}", format_timestamp(*next_run_at))))
+            }
+            Err(e) => CreateEmbed::default()
+                .title(format!("⚠️ Scheduled Payroll #{} Failed", id))
+                .description(e)
+                .color(0xffaa00),
+        };
+        notify(ctx, created_by, id, embed).await;
+    }
+
+    Ok(claimed.len())
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|time| time.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_default()
+}
+
+async fn notify(ctx: &Context, user_id: i64, payroll_id: i64, embed: CreateEmbed) {
+    if let Err(e) = UserId::new(user_id as u64)
+        .dm(ctx, CreateMessage::default().embed(embed))
+        .await
+    {
+        debug!("Could not DM user {} about payroll {}: {}", user_id, payroll_id, e);
+    }
+}
+
+pub fn create_payroll_report_embed(report: &PayrollReport) -> CreateEmbed {
+    let source = match report.source {
+        PayrollSource::Treasury => "the treasury",
+        PayrollSource::Mint => "newly minted currency",
+    };
+
+    let mut embed = CreateEmbed::default()
+        .title("💼 Payroll Report")
+        .description(format!(
+            "Paid <@&{}> `{} {}` each from {}",
+            report.role_id, report.amount, report.ticker, source
+        ))
+        .field("Paid", format!("{} member(s)", report.paid.len()), true)
+        .field("Failed", format!("{} member(s)", report.failed.len()), true)
+        .field("Total Paid", format!("`{} {}`", report.total_paid(), report.ticker), true)
+        .color(if report.failed.is_empty() { 0x2e8b57 } else { 0xffaa00 });
+
+    if !report.failed.is_empty() {
+        let mut failures: Vec<String> = report.failed
+            .iter()
+            .take(FAILURES_SHOWN)
+            .map(|(user_id, error)| format!("<@{}>: {}", user_id, error))
+            .collect();
+        if report.failed.len() > FAILURES_SHOWN {
+            failures.push(format!("...and {} more", report.failed.len() - FAILURES_SHOWN));
+        }
+        embed = embed.field("Failures", failures.join("\n"), false);
+    }
+
+    embed
+}
+
+pub fn create_payroll_schedule_embed(payroll: &ScheduledPayroll) -> CreateEmbed {
+    CreateEmbed::default()
+        .title("💼 Payroll Scheduled")
+        .field("ID", format!("`{}`", payroll.id), true)
+        .field("Role", format!("<@&{}>", payroll.role_id), true)
+        .field("Amount", format!("`{} {}` {}", payroll.amount, payroll.ticker, payroll.period.as_str()), true)
+        .field("Source", payroll.source.as_str(), true)
+        .field("First Run", format!("<t:{}:F>", payroll.next_run_at), true)
+        .footer(CreateEmbedFooter::new(format!(
+            "Cancel with $payroll cancel {}. Each run's report is sent to you by DM.",
+            payroll.id
+        )))
+        .color(0x2e8b57)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{amount, AccountStore, MemoryStorage, PayrollStore, TaxStore, TransactionStore};
+
+    const ADMIN: i64 = 1;
+    const ALICE: i64 = 2;
+    const BOB: i64 = 3;
+    const CAROL: i64 = 4;
+    const ROLE: i64 = 500;
+
+    #[tokio::test]
+    async fn test_pay_role_from_treasury() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 10, &[(ADMIN, "100")]);
+        // 9 tax from the transfer plus 16 leaves 25 in the treasury, enough for two members
+        storage.transfer(ADMIN, CAROL, currency_id, amount("90"), None).await.unwrap();
+        storage.add_tax(currency_id, amount("16")).await.unwrap();
+
+        let report = pay_role(
+            &storage, ADMIN, ROLE, &[ALICE, BOB, CAROL], currency_id, "ABC", amount("10"), PayrollSource::Treasury
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(report.paid.iter().map(|(id, _)| *id).collect::<Vec<_>>(), [ALICE, BOB]);
+        assert_eq!(report.failed, [(CAROL, "Treasury only has 5.00 ABC".to_string())]);
+        assert_eq!(report.total_paid(), amount("20"));
+        assert_eq!(storage.get_tax_account(currency_id).await.unwrap().unwrap().2, amount("5"));
+        assert_eq!(storage.get_account_balance(ALICE, currency_id).await.unwrap(), Some(amount("10")));
+
+        // One transaction per member, logged from the admin
+        let (transactions, total) = storage.get_user_transactions_paginated(ALICE, 1, 10, None).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(transactions[0].4, report.paid[0].1);
+        assert_eq!(storage.get_user_transactions_paginated(ADMIN, 1, 10, None).await.unwrap().1, 3);
+    }
+
+    #[tokio::test]
+    async fn test_pay_role_by_minting() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 0, &[]);
+
+        let report = pay_role(&storage, ADMIN, ROLE, &[ALICE, BOB], currency_id, "ABC", amount("7.5"), PayrollSource::Mint)
+            .await
+            .unwrap();
+        assert!(report.failed.is_empty());
+        assert_eq!(storage.total_supply(currency_id), amount("15"));
+        assert_eq!(storage.get_account_balance(ADMIN, currency_id).await.unwrap(), Some(Amount::ZERO));
+
+        assert!(pay_role(&storage, ADMIN, ROLE, &[], currency_id, "ABC", amount("1"), PayrollSource::Mint).await.is_err());
+        assert!(pay_role(&storage, ADMIN, ROLE, &[ALICE], currency_id, "ABC", amount("0"), PayrollSource::Mint).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_due_payrolls_are_claimed_once() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 0, &[]);
+        let next_run_at = RecurringPeriod::Daily.next_run(chrono::Utc::now().timestamp());
+        let id = storage.create_payroll(NewPayroll {
+            guild_id: 1001,
+            role_id: ROLE,
+            currency_id,
+            amount: amount("5"),
+            source: PayrollSource::Mint,
+            period: RecurringPeriod::Daily,
+            next_run_at,
+            created_by: ADMIN,
+        })
+        .await
+        .unwrap();
+
+        assert!(claim_due_payrolls(&storage).await.unwrap().is_empty());
+        storage.advance_minutes(24 * 60);
+        let claimed = claim_due_payrolls(&storage).await.unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].1, RecurringPeriod::Daily.next_run(next_run_at));
+        assert!(claim_due_payrolls(&storage).await.unwrap().is_empty());
+
+        assert!(cancel(&storage, 9999, id).await.is_err());
+        cancel(&storage, 1001, id).await.unwrap();
+        assert!(storage.get_payrolls_by_guild(1001).await.unwrap().is_empty());
+    }
+}
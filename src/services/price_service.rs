@@ -1,5 +1,8 @@
 use sqlx::mysql::MySqlPool;
+use serenity::builder::{CreateEmbed, CreateEmbedFooter};
+use crate::utils::page::Page;
 use crate::db;
+use crate::db::filter::{IntervalUnit, PriceFilter, TimeWindow};
 
 /// Result struct for price query
 #[derive(Debug)]
@@ -12,9 +15,9 @@ pub struct PriceResult {
     pub is_reversed: bool,
 }
 
-/// Convert user-friendly timeframe string to MySQL INTERVAL format
-/// Examples: "1m" -> "1 MINUTE", "1h" -> "1 HOUR", "1d" -> "1 DAY", etc.
-pub fn parse_timeframe(timeframe: &str) -> Result<String, String> {
+/// Convert user-friendly timeframe string to a look-back window
+/// Examples: "1m" -> 1 MINUTE, "1h" -> 1 HOUR, "1d" -> 1 DAY, etc.
+pub fn parse_timeframe(timeframe: &str) -> Result<TimeWindow, String> {
     let timeframe = timeframe.to_lowercase();
     
     // Find where the letters start
@@ -24,19 +27,21 @@ pub fn parse_timeframe(timeframe: &str) -> Result<String, String> {
         return Err("❌ Invalid timeframe format. Examples: 1m, 5m, 1h, 4h, 1d, 7d, 1mnt, 1y".to_string());
     }
     
-    let amount = &timeframe[..split_idx];
+    let amount: i64 = timeframe[..split_idx]
+        .parse()
+        .map_err(|_| "❌ Invalid timeframe number".to_string())?;
     let unit = &timeframe[split_idx..];
     
     let interval_unit = match unit {
-        "m" => "MINUTE",
-        "h" => "HOUR",
-        "d" => "DAY",
-        "mnt" => "MONTH",
-        "y" => "YEAR",
+        "m" => IntervalUnit::Minute,
+        "h" => IntervalUnit::Hour,
+        "d" => IntervalUnit::Day,
+        "mnt" => IntervalUnit::Month,
+        "y" => IntervalUnit::Year,
         _ => return Err(format!("❌ Unknown timeframe unit: '{}'. Use: m, h, d, mnt, y", unit)),
     };
     
-    Ok(format!("{} {}", amount, interval_unit))
+    Ok(TimeWindow { amount, unit: interval_unit })
 }
 
 /// Get price and VWAP for a currency pair
@@ -76,7 +81,7 @@ pub async fn get_price(
             .map_err(|e| format!("Database error: {}", e))?;
 
     // Parse timeframe argument (default to 24h if not provided)
-    let mysql_timeframe = parse_timeframe(timeframe_arg)?;
+    let window = parse_timeframe(timeframe_arg)?;
 
     // Get the latest price
     let price_result = db::tradelog::get_latest_price_for_pair(pool, canonical_base_id, canonical_quote_id)
@@ -94,7 +99,7 @@ pub async fn get_price(
     };
 
     // Calculate VWAP with the specified timeframe
-    let vwap_result = db::tradelog::calculate_vwap(pool, canonical_base_id, canonical_quote_id, &mysql_timeframe)
+    let vwap_result = db::tradelog::calculate_vwap(pool, canonical_base_id, canonical_quote_id, window)
         .await
         .map_err(|e| format!("Database error: {}", e))?;
 
@@ -122,7 +127,11 @@ pub async fn get_price_list(
     filter_base: Option<&str>,
     filter_quote: Option<&str>,
 ) -> Result<Vec<(String, String, f64)>, String> {
-    db::tradelog::get_latest_prices_with_filter(pool, filter_base, filter_quote)
+    let filter = PriceFilter {
+        base_ticker: filter_base.map(str::to_string),
+        quote_ticker: filter_quote.map(str::to_string),
+    };
+    db::tradelog::get_latest_prices_with_filter(pool, &filter)
         .await
         .map_err(|e| format!("Database error: {}", e))
 }
@@ -204,3 +213,68 @@ pub fn format_price_list_page(
     Ok((description, page_num, total_pages))
 }
 
+
+/// Build the embed shown for a single pair's price
+pub fn create_price_embed(price_data: &PriceResult) -> CreateEmbed {
+    // Format footer text
+    let footer_text = format!("1 {} = {:.2} {} (Timeframe: {})", 
+        price_data.base_ticker,
+        price_data.last_price, 
+        price_data.quote_ticker,
+        price_data.timeframe
+    );
+
+    // Build embed
+    let mut embed = CreateEmbed::default()
+        .title("💹 Trading Price")
+        .field("Pair", format!("{}/{}", price_data.base_ticker, price_data.quote_ticker), false)
+        .field("Timeframe", format!("**{}**", price_data.timeframe), false);
+
+    // Add VWAP field if available
+    let vwap_label = format!("VWAP ({})", price_data.timeframe);
+    if let Some(vwap) = price_data.vwap {
+        embed = embed.field(&vwap_label, format!("**{:.2} {}**", vwap, price_data.quote_ticker), false);
+    } else {
+        embed = embed.field(&vwap_label, format!("No trades in {}", price_data.timeframe), false);
+    }
+
+    // Add Last Price field
+    embed
+        .field("Last Price", format!("**{:.2} {}**", price_data.last_price, price_data.quote_ticker), false)
+        .footer(CreateEmbedFooter::new(footer_text))
+        .color(0x00ff00)
+}
+
+/// Page through the price list starting at the 1-based `page_num`
+/// Prices are already in memory, so every page is built up front
+pub fn create_price_list_pages(
+    prices: &[(String, String, f64)],
+    page_num: usize,
+    items_per_page: usize,
+) -> Result<Page, String> {
+    // Validates page_num against the total
+    format_price_list_page(prices, page_num, items_per_page)?;
+
+    let total_pages = prices.len().div_ceil(items_per_page);
+    let mut embeds = Vec::with_capacity(total_pages);
+    for page in 1..=total_pages {
+        let (description, page, total_pages) = format_price_list_page(prices, page, items_per_page)?;
+        embeds.push(create_price_list_embed(description, page, total_pages));
+    }
+
+    let mut page = Page::new(embeds);
+    page.current_page = page_num - 1;
+    Ok(page)
+}
+
+/// Build the embed for one page of the price list
+pub fn create_price_list_embed(description: String, page_num: usize, total_pages: usize) -> CreateEmbed {
+    CreateEmbed::default()
+        .title("💹 Price List")
+        .description(description)
+        .footer(CreateEmbedFooter::new(format!(
+            "Page {}/{}",
+            page_num, total_pages
+        )))
+        .color(0x00ff00)
+}
@@ -0,0 +1,252 @@
+//! Recurring Scheduler Service - Background worker that sends due recurring payments
+//!
+//! Each due payment is moved to its next run before it is sent, so a payment is
+//! never sent twice for the same run, and goes through `send_service::send` like
+//! a `$send`, tax included. A failed run is skipped and the payer gets a DM;
+//! after `MAX_RECURRING_FAILURES` failures in a row the payment is suspended.
+//! Runs missed while the bot was offline are sent one per check until the
+//! payment is back on schedule. Scheduled payrolls are run on the same tick by
+//! `payroll_service::run_due_payrolls`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use serenity::builder::{CreateEmbed, CreateMessage};
+use serenity::model::prelude::UserId;
+use serenity::prelude::Context;
+use tracing::{debug, error, info, warn};
+use crate::db::recurring::RecurringPeriod;
+use crate::services::send_service;
+use crate::storage::{RecurringRow, Storage};
+use crate::utils::Amount;
+
+/// How often the worker looks for due payments
+const RECURRING_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Maximum payments sent per run, so one run can't hold the pool for long
+const RECURRING_BATCH_SIZE: i64 = 50;
+
+/// Failed runs in a row before a payment is suspended
+pub const MAX_RECURRING_FAILURES: i32 = 3;
+
+static WORKER_STARTED: AtomicBool = AtomicBool::new(false);
+
+pub enum RunOutcome {
+    Paid { tax_amount: Amount },
+    Failed { error: String, failures: i32, suspended: bool },
+}
+
+/// One recurring payment the worker tried to send
+pub struct PaymentRun {
+    pub payment: RecurringRow,
+    /// Unix timestamp of the payment's next run
+    pub next_run_at: i64,
+    pub outcome: RunOutcome,
+}
+
+/// Start the recurring payment worker. Only the first call spawns it, so this is
+/// safe to call from every shard's ready event.
+pub fn start_worker(ctx: Context) {
+    if WORKER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    tokio::spawn(async move {
+        info!("Recurring payment worker started");
+        let mut interval = tokio::time::interval(RECURRING_CHECK_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let storage = match crate::storage::from_ctx(&ctx).await {
+                Ok(storage) => storage,
+                Err(e) => {
+                    error!("Recurring payment run failed: {}", e);
+                    continue;
+                }
+            };
+
+            match run_due_payments(storage.as_ref()).await {
+                Ok(runs) if runs.is_empty() => debug!("No recurring payments due"),
+                Ok(runs) => {
+                    info!("Ran {} recurring payment(s)", runs.len());
+                    for run in &runs {
+                        match &run.outcome {
+                            RunOutcome::Paid { tax_amount } => {
+                                debug!("Sent recurring payment {} ({} tax)", run.payment.0, tax_amount)
+                            }
+                            RunOutcome::Failed { .. } => notify_failure(&ctx, run).await,
+                        }
+                    }
+                }
+                Err(e) => error!("Recurring payment run failed: {}", e),
+            }
+
+            match crate::services::payroll_service::run_due_payrolls(&ctx, storage.as_ref()).await {
+                Ok(0) => debug!("No payrolls due"),
+                Ok(count) => info!("Ran {} scheduled payroll(s)", count),
+                Err(e) => error!("Payroll run failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Send every due payment (up to one batch)
+pub async fn run_due_payments(storage: &dyn Storage) -> Result<Vec<PaymentRun>, String> {
+    let due = storage.get_due_recurring_payments(RECURRING_BATCH_SIZE)
+        .await
+        .map_err(|e| format!("Failed to fetch due recurring payments: {}", e))?;
+
+    let mut runs = Vec::with_capacity(due.len());
+
+    for payment in due {
+        let (id, payer_id, recipient_id, _, ref ticker, amount, ref period, due_at, failure_count, _) = payment;
+
+        let Some(period) = RecurringPeriod::parse(period) else {
+            warn!("Recurring payment {} has an unknown period '{}'", id, period);
+            continue;
+        };
+
+        // Claim this run first; another run that got here first leaves it false
+        let next_run_at = period.next_run(due_at);
+        match storage.reschedule_recurring_payment(id, due_at, next_run_at).await {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) => {
+                warn!("Could not reschedule recurring payment {}: {}", id, e);
+                continue;
+            }
+        }
+
+        let memo = format!("Recurring payment #{}", id);
+        let outcome = match send_service::send(storage, payer_id, recipient_id, amount, ticker, Some(&memo)).await {
+            Ok((_, _, tax_amount)) => {
+                if failure_count > 0 {
+                    if let Err(e) = storage.reset_recurring_failures(id).await {
+                        warn!("Could not reset failures of recurring payment {}: {}", id, e);
+                    }
+                }
+                RunOutcome::Paid { tax_amount }
+            }
+            Err(error) => record_failure(storage, id, error).await,
+        };
+
+        runs.push(PaymentRun { payment, next_run_at, outcome });
+    }
+
+    Ok(runs)
+}
+
+/// Count a failed run and suspend the payment once it has failed too often in a row
+async fn record_failure(storage: &dyn Storage, id: i64, error: String) -> RunOutcome {
+    let failures = match storage.record_recurring_failure(id).await {
+        Ok(failures) => failures,
+        Err(e) => {
+            warn!("Could not record failure of recurring payment {}: {}", id, e);
+            return RunOutcome::Failed { error, failures: 0, suspended: false };
+        }
+    };
+
+    let mut suspended = false;
+    if failures >= MAX_RECURRING_FAILURES {
+        match storage.set_recurring_status(id, "suspended").await {
+            Ok(()) => suspended = true,
+            Err(e) => warn!("Could not suspend recurring payment {}: {}", id, e),
+        }
+    }
+
+    RunOutcome::Failed { error, failures, suspended }
+}
+
+/// DM the payer about a failed run
+async fn notify_failure(ctx: &Context, run: &PaymentRun) {
+    let RunOutcome::Failed { error, failures, suspended } = &run.outcome else {
+        return;
+    };
+    let (id, payer_id, recipient_id, _, ticker, amount, period, _, _, _) = &run.payment;
+
+    let embed = if *suspended {
+        CreateEmbed::default()
+            .title("⛔ Recurring Payment Suspended")
+            .description(format!(
+                "Your {} payment `#{}` of **{} {}** to <@{}> failed {} times in a row and has been suspended.\n\n\
+                 Last error:\n{}\n\n\
+                 Cancel it with `$recurring cancel {}` and set it up again once the problem is fixed.",
+                period, id, amount, ticker, recipient_id, failures, error, id
+            ))
+            .color(0xff3333)
+    } else {
+        CreateEmbed::default()
+            .title("⚠️ Recurring Payment Failed")
+            .description(format!(
+                "Your {} payment `#{}` of **{} {}** to <@{}> could not be sent:\n{}\n\n\
+                 The next attempt is <t:{}:R>. After {} failures in a row the payment is suspended.",
+                period, id, amount, ticker, recipient_id, error, run.next_run_at, MAX_RECURRING_FAILURES
+            ))
+            .color(0xffaa00)
+    };
+
+    if let Err(e) = UserId::new(*payer_id as u64)
+        .dm(ctx, CreateMessage::default().embed(embed))
+        .await
+    {
+        debug!("Could not DM user {} about recurring payment {}: {}", payer_id, id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::recurring_service;
+    use crate::storage::{amount, AccountStore, MemoryStorage, RecurringStore};
+
+    const ALICE: i64 = 1;
+    const BOB: i64 = 2;
+    const WEEK_MINUTES: i64 = 7 * 24 * 60;
+
+    #[tokio::test]
+    async fn test_due_payments_are_sent_once_with_tax() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 10, &[(ALICE, "100")]);
+        let payment = recurring_service::schedule(&storage, ALICE, BOB, amount("20"), "ABC", RecurringPeriod::Weekly)
+            .await
+            .unwrap();
+
+        assert!(run_due_payments(&storage).await.unwrap().is_empty());
+
+        storage.advance_minutes(WEEK_MINUTES);
+        let runs = run_due_payments(&storage).await.unwrap();
+        assert_eq!(runs.len(), 1);
+        assert!(matches!(runs[0].outcome, RunOutcome::Paid { tax_amount } if tax_amount == amount("2")));
+        assert_eq!(runs[0].next_run_at, RecurringPeriod::Weekly.next_run(payment.next_run_at));
+
+        // Already rescheduled, so the same run isn't sent again
+        assert!(run_due_payments(&storage).await.unwrap().is_empty());
+        assert_eq!(storage.get_account_balance(ALICE, currency_id).await.unwrap(), Some(amount("78")));
+        assert_eq!(storage.get_account_balance(BOB, currency_id).await.unwrap(), Some(amount("20")));
+    }
+
+    #[tokio::test]
+    async fn test_repeated_failures_suspend_the_payment() {
+        let storage = MemoryStorage::new();
+        storage.seed_currency("ABC", 0, &[(ALICE, "5")]);
+        let payment = recurring_service::schedule(&storage, ALICE, BOB, amount("20"), "ABC", RecurringPeriod::Daily)
+            .await
+            .unwrap();
+
+        for attempt in 1..=MAX_RECURRING_FAILURES {
+            storage.advance_minutes(24 * 60);
+            let runs = run_due_payments(&storage).await.unwrap();
+            let RunOutcome::Failed { error, failures, suspended } = &runs[0].outcome else {
+                panic!("payment should have failed");
+            };
+            assert!(error.starts_with("❌ Insufficient balance"), "{}", error);
+            assert_eq!(*failures, attempt);
+            assert_eq!(*suspended, attempt == MAX_RECURRING_FAILURES);
+        }
+
+        storage.advance_minutes(24 * 60);
+        assert!(run_due_payments(&storage).await.unwrap().is_empty());
+        let row = storage.get_recurring_payment(payment.id).await.unwrap().unwrap();
+        assert_eq!(row.9, "suspended");
+    }
+}
@@ -0,0 +1,252 @@
+//! Recurring Service - standing orders that send a fixed amount on a schedule
+//!
+//! `$recurring create` stores the order; the scheduler in
+//! `recurring_scheduler_service` sends each payment when it falls due.
+
+use serenity::builder::{CreateEmbed, CreateEmbedFooter};
+use serenity::prelude::Context;
+use crate::db::recurring::RecurringPeriod;
+use crate::storage::{NewRecurringPayment, RecurringRow, Storage};
+use crate::utils::page::Page;
+use crate::utils::{Amount, Invocation};
+
+/// Active and suspended payments one user may have at once
+const MAX_RECURRING_PER_PAYER: usize = 25;
+
+/// Payments per page in `$recurring list`
+const RECURRING_PER_PAGE: usize = 10;
+
+pub struct RecurringResult {
+    pub id: i64,
+    pub payer_id: i64,
+    pub recipient_id: i64,
+    pub amount: Amount,
+    pub ticker: String,
+    pub period: RecurringPeriod,
+    /// Unix timestamp of the first payment
+    pub next_run_at: i64,
+    pub tax_percentage: i32,
+}
+
+/// Set up a recurring payment from the invoking user
+pub async fn create_recurring(
+    ctx: &Context,
+    invocation: &Invocation,
+    recipient_id: i64,
+    amount: Amount,
+    currency_ticker: &str,
+    period: RecurringPeriod,
+) -> Result<RecurringResult, String> {
+    // Payments go through `$send`, which is guild only
+    invocation
+        .guild_id
+        .ok_or("This command can only be used in a guild".to_string())?;
+
+    let storage = crate::storage::from_ctx(ctx).await?;
+    schedule(storage.as_ref(), invocation.user_id_i64(), recipient_id, amount, currency_ticker, period).await
+}
+
+/// Store a recurring payment whose first run is one period from now
+pub async fn schedule(
+    storage: &dyn Storage,
+    payer_id: i64,
+    recipient_id: i64,
+    amount: Amount,
+    currency_ticker: &str,
+    period: RecurringPeriod,
+) -> Result<RecurringResult, String> {
+    if payer_id == recipient_id {
+        return Err("❌ Cannot set up a recurring payment to yourself".to_string());
+    }
+    if !amount.is_positive() {
+        return Err("❌ Amount must be positive".to_string());
+    }
+
+    let (currency_id, _, ticker) = storage.get_currency_by_ticker(currency_ticker)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("❌ Currency '{}' not found", currency_ticker))?;
+
+    let existing = storage.get_recurring_payments_by_payer(payer_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    if existing.len() >= MAX_RECURRING_PER_PAYER {
+        return Err(format!(
+            "❌ You already have {} recurring payments. Cancel one before adding another.",
+            MAX_RECURRING_PER_PAYER
+        ));
+    }
+
+    let tax_percentage = storage.get_tax_percentage(currency_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .unwrap_or(0);
+
+    let next_run_at = period.next_run(chrono::Utc::now().timestamp());
+    let id = storage.create_recurring_payment(NewRecurringPayment {
+        payer_id,
+        recipient_id,
+        currency_id,
+        amount,
+        period,
+        next_run_at,
+    })
+    .await
+    .map_err(|e| format!("Failed to create recurring payment: {}", e))?;
+
+    Ok(RecurringResult {
+        id,
+        payer_id,
+        recipient_id,
+        amount,
+        ticker,
+        period,
+        next_run_at,
+        tax_percentage,
+    })
+}
+
+/// Cancel one of the invoking user's recurring payments
+pub async fn cancel_recurring(ctx: &Context, invocation: &Invocation, id: i64) -> Result<CreateEmbed, String> {
+    let storage = crate::storage::from_ctx(ctx).await?;
+    let (_, _, recipient_id, _, ticker, amount, period, _, _, _) =
+        cancel(storage.as_ref(), invocation.user_id_i64(), id).await?;
+
+    Ok(CreateEmbed::default()
+        .title("🗑️ Recurring Payment Cancelled")
+        .description(format!(
+            "Recurring payment `#{}` of **{} {}** {} to <@{}> will not be sent again.",
+            id, amount, ticker, period, recipient_id
+        ))
+        .color(0x808080))
+}
+
+/// Cancel a payment if `payer_id` is its payer, returning it as it was
+pub async fn cancel(storage: &dyn Storage, payer_id: i64, id: i64) -> Result<RecurringRow, String> {
+    let payment = storage.get_recurring_payment(id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .filter(|payment| payment.9 != "cancelled")
+        .ok_or(format!("❌ Recurring payment `#{}` not found", id))?;
+
+    if payment.1 != payer_id {
+        return Err("❌ Only the payer can cancel a recurring payment".to_string());
+    }
+
+    storage.set_recurring_status(id, "cancelled")
+        .await
+        .map_err(|e| format!("Failed to cancel recurring payment: {}", e))?;
+
+    Ok(payment)
+}
+
+/// Page through the invoking user's recurring payments
+pub async fn list_recurring(ctx: &Context, invocation: &Invocation) -> Result<Page, String> {
+    let storage = crate::storage::from_ctx(ctx).await?;
+    let payments = storage.get_recurring_payments_by_payer(invocation.user_id_i64())
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    if payments.is_empty() {
+        return Ok(Page::new(vec![CreateEmbed::default()
+            .title("🔁 Your Recurring Payments")
+            .description("You have no recurring payments")
+            .color(0x5865f2)]));
+    }
+
+    let total_pages = payments.len().div_ceil(RECURRING_PER_PAGE);
+    let embeds = payments
+        .chunks(RECURRING_PER_PAGE)
+        .enumerate()
+        .map(|(idx, chunk)| {
+            let mut description = String::new();
+            for (id, _, recipient_id, _, ticker, amount, period, next_run_at, failures, status) in chunk {
+                let schedule = if status == "suspended" {
+                    "⛔ suspended".to_string()
+                } else {
+                    format!("next <t:{}:R>", next_run_at)
+                };
+                description.push_str(&format!(
+                    "**#{}** `{} {}` {} to <@{}>, {}",
+                    id, amount, ticker, period, recipient_id, schedule
+                ));
+                if *failures > 0 && status == "active" {
+                    description.push_str(&format!(" ({} failed)", failures));
+                }
+                description.push('\n');
+            }
+            CreateEmbed::default()
+                .title("🔁 Your Recurring Payments")
+                .description(description)
+                .footer(CreateEmbedFooter::new(format!("Page {}/{}", idx + 1, total_pages)))
+                .color(0x5865f2)
+        })
+        .collect();
+
+    Ok(Page::new(embeds))
+}
+
+pub fn create_recurring_embed(result: &RecurringResult) -> CreateEmbed {
+    let mut embed = CreateEmbed::default()
+        .title("🔁 Recurring Payment Created")
+        .field("ID", format!("`{}`", result.id), true)
+        .field("From", format!("<@{}>", result.payer_id), true)
+        .field("To", format!("<@{}>", result.recipient_id), true)
+        .field("Amount", format!("`{} {}` {}", result.amount, result.ticker, result.period.as_str()), true)
+        .field("First Payment", format!("<t:{}:F>", result.next_run_at), true)
+        .color(0x5865f2);
+
+    if result.tax_percentage > 0 {
+        embed = embed.field(
+            "Tax",
+            format!(
+                "Each payment also charges you {}% tax (`{} {}` at the current rate)",
+                result.tax_percentage,
+                result.amount.percent(result.tax_percentage),
+                result.ticker
+            ),
+            false,
+        );
+    }
+
+    embed.footer(CreateEmbedFooter::new(format!(
+        "Cancel with $recurring cancel {}. You get a DM if a payment fails.",
+        result.id
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{amount, MemoryStorage, RecurringStore};
+
+    const ALICE: i64 = 1;
+    const BOB: i64 = 2;
+
+    #[tokio::test]
+    async fn test_schedule_and_cancel() {
+        let storage = MemoryStorage::new();
+        storage.seed_currency("ABC", 10, &[(ALICE, "100")]);
+
+        let result = schedule(&storage, ALICE, BOB, amount("50"), "abc", RecurringPeriod::Weekly).await.unwrap();
+        assert_eq!((result.ticker.as_str(), result.tax_percentage), ("ABC", 10));
+        assert!(result.next_run_at > chrono::Utc::now().timestamp() + 6 * 24 * 3600);
+
+        assert_eq!(
+            schedule(&storage, ALICE, ALICE, amount("1"), "ABC", RecurringPeriod::Daily).await.err().unwrap(),
+            "❌ Cannot set up a recurring payment to yourself"
+        );
+        assert_eq!(
+            schedule(&storage, ALICE, BOB, amount("1"), "XYZ", RecurringPeriod::Daily).await.err().unwrap(),
+            "❌ Currency 'XYZ' not found"
+        );
+
+        assert_eq!(
+            cancel(&storage, BOB, result.id).await.err().unwrap(),
+            "❌ Only the payer can cancel a recurring payment"
+        );
+        cancel(&storage, ALICE, result.id).await.unwrap();
+        assert!(cancel(&storage, ALICE, result.id).await.is_err());
+        assert!(storage.get_recurring_payments_by_payer(ALICE).await.unwrap().is_empty());
+    }
+}
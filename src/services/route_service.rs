@@ -0,0 +1,491 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use serenity::builder::{
+    CreateActionRow, CreateButton, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+};
+use serenity::model::prelude::{ButtonStyle, ComponentInteraction, Message, UserId};
+use serenity::prelude::Context;
+use crate::db;
+use crate::db::filter::PriceFilter;
+use crate::services::swap_service::{self, AcceptDenyResult};
+use crate::utils::{Amount, Invocation};
+
+/// Most swaps chained in one route
+const MAX_HOPS: usize = 4;
+
+/// How long the preview's buttons wait for the user to decide
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+
+const CONFIRM_BUTTON_ID: &str = "route_confirm";
+const CANCEL_BUTTON_ID: &str = "route_cancel";
+
+/// An open swap that can be filled as part of a route
+#[derive(Debug, Clone)]
+pub struct OpenSwap {
+    pub id: i64,
+    pub maker_discord_id: i64,
+    pub maker_currency_id: i64,
+    pub taker_currency_id: i64,
+    pub maker_amount: Amount,
+    pub taker_amount: Amount,
+    pub filled_amount: Amount,
+}
+
+/// Part of a hop: how much of one swap's maker currency is taken and what it costs
+#[derive(Debug, Clone)]
+pub struct RouteFill {
+    pub swap: OpenSwap,
+    /// Maker's currency received
+    pub receive: Amount,
+    /// Taker's currency paid (the swap's last fill may end up paying slightly less)
+    pub pay: Amount,
+}
+
+/// One currency conversion, filled from one or more swaps on the same pair
+#[derive(Debug, Clone)]
+pub struct RouteHop {
+    pub from_currency_id: i64,
+    pub to_currency_id: i64,
+    pub amount_in: Amount,
+    pub amount_out: Amount,
+    pub fills: Vec<RouteFill>,
+}
+
+/// A previewed route, executed exactly as quoted once the user confirms
+pub struct RoutePlan {
+    pub user_id: i64,
+    pub amount_in: Amount,
+    pub amount_out: Amount,
+    pub hops: Vec<RouteHop>,
+    pub tickers: HashMap<i64, String>,
+    /// What `amount_in` would be worth along the same path at the latest traded prices
+    pub market_estimate: Option<f64>,
+}
+
+impl RoutePlan {
+    fn ticker(&self, currency_id: i64) -> &str {
+        self.tickers.get(&currency_id).map(|s| s.as_str()).unwrap_or("???")
+    }
+}
+
+/// Convert `amount_in` through a pair's swaps, best rate first (oldest first at the same rate).
+/// Returns None if nothing can be filled.
+pub fn fill_hop(swaps: &[OpenSwap], amount_in: Amount) -> Option<RouteHop> {
+    let first = swaps.first()?;
+    let mut remaining_in = amount_in;
+    let mut fills = Vec::new();
+
+    for swap in swaps {
+        if !remaining_in.is_positive() {
+            break;
+        }
+
+        let left = swap.maker_amount - swap.filled_amount;
+        // Rounding the receive amount down means the payment, rounded up, never exceeds what we have
+        let Some(receive) = remaining_in.checked_mul_ratio_floor(swap.maker_amount, swap.taker_amount) else {
+            continue;
+        };
+        let receive = receive.min(left);
+        if !receive.is_positive() {
+            continue;
+        }
+        let Some(pay) = receive.checked_mul_ratio_ceil(swap.taker_amount, swap.maker_amount) else {
+            continue;
+        };
+
+        remaining_in = remaining_in - pay;
+        fills.push(RouteFill { swap: swap.clone(), receive, pay });
+    }
+
+    if fills.is_empty() {
+        return None;
+    }
+
+    Some(RouteHop {
+        from_currency_id: first.taker_currency_id,
+        to_currency_id: first.maker_currency_id,
+        amount_in: fills.iter().fold(Amount::ZERO, |sum, fill| sum + fill.pay),
+        amount_out: fills.iter().fold(Amount::ZERO, |sum, fill| sum + fill.receive),
+        fills,
+    })
+}
+
+/// Find the chain of swaps that turns `amount` of `from` into the most `to`, using at most
+/// `max_hops` conversions and never passing through the same currency twice.
+/// Ties go to the route with fewer hops.
+pub fn find_best_route(swaps: &[OpenSwap], from: i64, to: i64, amount: Amount, max_hops: usize) -> Option<Vec<RouteHop>> {
+    // Edges go from the currency a taker pays to the currency they receive
+    let mut edges: BTreeMap<i64, BTreeMap<i64, Vec<OpenSwap>>> = BTreeMap::new();
+    for swap in swaps {
+        edges
+            .entry(swap.taker_currency_id)
+            .or_default()
+            .entry(swap.maker_currency_id)
+            .or_default()
+            .push(swap.clone());
+    }
+    for pairs in edges.values_mut() {
+        for pair_swaps in pairs.values_mut() {
+            // Display-grade f64 is fine for ordering; the fills themselves use exact amounts
+            pair_swaps.sort_by(|a, b| {
+                let rate_a = a.maker_amount.to_f64() / a.taker_amount.to_f64();
+                let rate_b = b.maker_amount.to_f64() / b.taker_amount.to_f64();
+                rate_b.partial_cmp(&rate_a).unwrap_or(std::cmp::Ordering::Equal).then(a.id.cmp(&b.id))
+            });
+        }
+    }
+
+    let mut best: HashMap<i64, (Amount, Vec<RouteHop>)> = HashMap::new();
+    let mut frontier = vec![(from, amount, Vec::<RouteHop>::new())];
+
+    for _ in 0..max_hops {
+        let mut next = Vec::new();
+
+        for (currency, held, path) in &frontier {
+            let Some(pairs) = edges.get(currency) else {
+                continue;
+            };
+
+            for (target, pair_swaps) in pairs {
+                if *target == from || path.iter().any(|hop| hop.to_currency_id == *target) {
+                    continue;
+                }
+                let Some(hop) = fill_hop(pair_swaps, *held) else {
+                    continue;
+                };
+                if best.get(target).is_some_and(|(best_out, _)| hop.amount_out <= *best_out) {
+                    continue;
+                }
+
+                let out = hop.amount_out;
+                let mut new_path = path.clone();
+                new_path.push(hop);
+                best.insert(*target, (out, new_path.clone()));
+                if *target != to {
+                    next.push((*target, out, new_path));
+                }
+            }
+        }
+
+        frontier = next;
+    }
+
+    best.remove(&to).map(|(_, path)| path)
+}
+
+/// Rate from one ticker to another at the latest traded price, if the pair has traded
+fn market_rate(prices: &[(String, String, f64)], from: &str, to: &str) -> Option<f64> {
+    prices.iter().find_map(|(base, quote, price)| {
+        if base == from && quote == to {
+            Some(*price)
+        } else if base == to && quote == from && *price > 0.0 {
+            Some(1.0 / *price)
+        } else {
+            None
+        }
+    })
+}
+
+/// Find the best route and price it, without touching any balances
+pub async fn quote_route(
+    ctx: &Context,
+    invocation: &Invocation,
+    amount: Amount,
+    from_ticker: &str,
+    to_ticker: &str,
+) -> Result<RoutePlan, String> {
+    if !amount.is_positive() {
+        return Err("❌ Amount must be positive".to_string());
+    }
+    if from_ticker == to_ticker {
+        return Err("❌ Currencies must be different".to_string());
+    }
+
+    let pool = {
+        let data = ctx.data.read().await;
+        data.get::<crate::DatabasePool>()
+            .ok_or(crate::db::MYSQL_ONLY.to_string())?
+            .clone()
+    };
+
+    let (from_id, _, _) = db::currency::get_currency_by_ticker(&pool, from_ticker)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or(format!("❌ Currency '{}' not found", from_ticker))?;
+    let (to_id, _, _) = db::currency::get_currency_by_ticker(&pool, to_ticker)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or(format!("❌ Currency '{}' not found", to_ticker))?;
+
+    let user_id = invocation.user_id_i64();
+
+    // Your own open swaps can't be accepted by you, so they aren't part of your graph
+    let swaps: Vec<OpenSwap> = db::swap::get_open_swaps(&pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .into_iter()
+        .filter(|(_, _, maker_discord_id, ..)| *maker_discord_id != user_id)
+        .map(|(id, _, maker_discord_id, maker_currency_id, taker_currency_id, maker_amount, taker_amount, filled_amount)| OpenSwap {
+            id,
+            maker_discord_id,
+            maker_currency_id,
+            taker_currency_id,
+            maker_amount,
+            taker_amount,
+            filled_amount,
+        })
+        .collect();
+
+    let hops = find_best_route(&swaps, from_id, to_id, amount, MAX_HOPS)
+        .ok_or(format!("❌ No route from {} to {} through open swaps", from_ticker, to_ticker))?;
+
+    let mut tickers = HashMap::new();
+    tickers.insert(from_id, from_ticker.to_string());
+    tickers.insert(to_id, to_ticker.to_string());
+    for hop in &hops {
+        if let Entry::Vacant(entry) = tickers.entry(hop.to_currency_id) {
+            let ticker = db::currency::get_currency_by_id(&pool, hop.to_currency_id)
+                .await
+                .map_err(|e| format!("Database error: {}", e))?
+                .map(|c| c.3)
+                .unwrap_or_else(|| "???".to_string());
+            entry.insert(ticker);
+        }
+    }
+
+    // Price the same path at the latest traded prices so the user can compare
+    let prices = db::tradelog::get_latest_prices_with_filter(&pool, &PriceFilter::default())
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    let market_estimate = hops.iter().try_fold(amount.to_f64(), |value, hop| {
+        market_rate(&prices, &tickers[&hop.from_currency_id], &tickers[&hop.to_currency_id]).map(|rate| value * rate)
+    });
+
+    Ok(RoutePlan {
+        user_id,
+        amount_in: hops[0].amount_in,
+        amount_out: hops[hops.len() - 1].amount_out,
+        hops,
+        tickers,
+        market_estimate,
+    })
+}
+
+/// Fill every swap in the plan in one transaction; if any leg fails, nothing changes.
+/// Returns the amount actually paid, which can be slightly less than quoted.
+pub async fn execute_route(ctx: &Context, plan: &RoutePlan) -> Result<Amount, String> {
+    let pool = {
+        let data = ctx.data.read().await;
+        data.get::<crate::DatabasePool>()
+            .ok_or(crate::db::MYSQL_ONLY.to_string())?
+            .clone()
+    };
+
+    let fills: Vec<&RouteFill> = plan.hops.iter().flat_map(|hop| hop.fills.iter()).collect();
+    let requests: Vec<(i64, Amount)> = fills.iter().map(|fill| (fill.swap.id, fill.receive)).collect();
+
+    let results = db::swap::fill_swaps(&pool, plan.user_id, &requests)
+        .await
+        .map_err(|e| format!("Route failed, nothing was traded: {}", e))?;
+
+    let storage = crate::storage::from_ctx(ctx).await?;
+
+    for (fill, (maker_filled, taker_paid, fully_filled)) in fills.iter().zip(&results) {
+        let swap = &fill.swap;
+        let maker_ticker = plan.ticker(swap.maker_currency_id);
+        let taker_ticker = plan.ticker(swap.taker_currency_id);
+
+        swap_service::log_swap_price(
+            storage.as_ref(),
+            (swap.maker_currency_id, maker_ticker, swap.maker_amount, *maker_filled),
+            (swap.taker_currency_id, taker_ticker, swap.taker_amount, *taker_paid),
+        ).await;
+
+        if *fully_filled {
+            let result = AcceptDenyResult {
+                swap_id: swap.id,
+                maker_id: swap.maker_discord_id,
+                taker_id: plan.user_id,
+                maker_offer: format!("{:.2} {}", swap.maker_amount, maker_ticker),
+                taker_offer: format!("{:.2} {}", swap.taker_amount, taker_ticker),
+                status: "accepted".to_string(),
+                remaining: None,
+            };
+            swap_service::update_swap_messages(ctx, &result).await;
+        }
+    }
+
+    let first_hop_fills = plan.hops[0].fills.len();
+    Ok(results[..first_hop_fills].iter().fold(Amount::ZERO, |sum, (_, paid, _)| sum + *paid))
+}
+
+/// "ABC → DEF → XYZ"
+fn format_path(plan: &RoutePlan) -> String {
+    let mut path = plan.ticker(plan.hops[0].from_currency_id).to_string();
+    for hop in &plan.hops {
+        path.push_str(" → ");
+        path.push_str(plan.ticker(hop.to_currency_id));
+    }
+    path
+}
+
+pub fn create_route_preview_embed(plan: &RoutePlan) -> CreateEmbed {
+    let from = plan.ticker(plan.hops[0].from_currency_id);
+    let to = plan.ticker(plan.hops[plan.hops.len() - 1].to_currency_id);
+
+    let mut legs = String::new();
+    for (idx, hop) in plan.hops.iter().enumerate() {
+        let swap_ids: Vec<String> = hop.fills.iter().map(|fill| format!("#{}", fill.swap.id)).collect();
+        legs.push_str(&format!(
+            "**{}.** `{} {}` → `{} {}` via swap {}\n",
+            idx + 1,
+            hop.amount_in, plan.ticker(hop.from_currency_id),
+            hop.amount_out, plan.ticker(hop.to_currency_id),
+            swap_ids.join(", ")
+        ));
+    }
+
+    let mut embed = CreateEmbed::default()
+        .title("🧭 Route Preview")
+        .description(format!("**{}**", format_path(plan)))
+        .field("You Pay", format!("`{} {}`", plan.amount_in, from), true)
+        .field("You Receive", format!("`{} {}`", plan.amount_out, to), true)
+        .field("Legs", legs, false);
+
+    if let Some(estimate) = plan.market_estimate {
+        let difference = if estimate > 0.0 { (plan.amount_out.to_f64() / estimate - 1.0) * 100.0 } else { 0.0 };
+        embed = embed.field(
+            "Market Estimate",
+            format!("≈ `{:.8} {}` at the latest prices ({:+.2}%)", estimate, to, difference),
+            false,
+        );
+    }
+
+    embed
+        .field("Confirm", format!("Confirm within {} seconds. If any leg fails, nothing is traded.", CONFIRM_TIMEOUT.as_secs()), false)
+        .color(0xffa500)
+}
+
+pub fn create_route_result_embed(plan: &RoutePlan, paid: Amount) -> CreateEmbed {
+    let from = plan.ticker(plan.hops[0].from_currency_id);
+    let to = plan.ticker(plan.hops[plan.hops.len() - 1].to_currency_id);
+    let fills: usize = plan.hops.iter().map(|hop| hop.fills.len()).sum();
+
+    CreateEmbed::default()
+        .title("✅ Route Executed")
+        .description(format!("**{}**", format_path(plan)))
+        .field("Paid", format!("`{} {}`", paid, from), true)
+        .field("Received", format!("`{} {}`", plan.amount_out, to), true)
+        .field("Swaps Filled", fills.to_string(), true)
+        .color(0x00ff00)
+}
+
+pub fn create_route_buttons() -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(CONFIRM_BUTTON_ID).label("Confirm").style(ButtonStyle::Success),
+        CreateButton::new(CANCEL_BUTTON_ID).label("Cancel").style(ButtonStyle::Danger),
+    ])]
+}
+
+/// Wait for `author_id` to press Confirm on a route preview.
+/// Returns the confirming interaction, or None if they cancelled or the buttons timed out.
+pub async fn await_confirmation(ctx: &Context, message: &Message, author_id: UserId) -> Option<ComponentInteraction> {
+    while let Some(interaction) = message
+        .await_component_interaction(&ctx.shard)
+        .timeout(CONFIRM_TIMEOUT)
+        .await
+    {
+        if interaction.user.id != author_id {
+            let reply = CreateInteractionResponseMessage::new()
+                .content("Only the person who asked for this route can confirm it.")
+                .ephemeral(true);
+            let _ = interaction
+                .create_response(ctx, CreateInteractionResponse::Message(reply))
+                .await;
+            continue;
+        }
+
+        return match interaction.data.custom_id.as_str() {
+            CONFIRM_BUTTON_ID => Some(interaction),
+            _ => {
+                let _ = interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await;
+                None
+            }
+        };
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::amount as amt;
+
+    /// Maker offers `maker` of `to` for `taker` of `from`
+    fn swap(id: i64, from: i64, to: i64, taker: &str, maker: &str) -> OpenSwap {
+        OpenSwap {
+            id,
+            maker_discord_id: 1000 + id,
+            maker_currency_id: to,
+            taker_currency_id: from,
+            maker_amount: amt(maker),
+            taker_amount: amt(taker),
+            filled_amount: Amount::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_fill_hop_best_rate_first() {
+        let swaps = vec![
+            swap(1, 1, 2, "10", "20"),
+            swap(2, 1, 2, "10", "10"),
+        ];
+        let hop = fill_hop(&swaps, amt("15")).unwrap();
+        assert_eq!(hop.fills.len(), 2);
+        assert_eq!((hop.fills[0].pay, hop.fills[0].receive), (amt("10"), amt("20")));
+        assert_eq!((hop.fills[1].pay, hop.fills[1].receive), (amt("5"), amt("5")));
+        assert_eq!((hop.amount_in, hop.amount_out), (amt("15"), amt("25")));
+    }
+
+    #[test]
+    fn test_fill_hop_never_overpays() {
+        let swaps = vec![swap(1, 1, 2, "3", "1")];
+        let hop = fill_hop(&swaps, amt("1")).unwrap();
+        assert_eq!(hop.amount_out, amt("0.33333333"));
+        assert!(hop.amount_in <= amt("1"));
+    }
+
+    #[test]
+    fn test_finds_multi_hop_route() {
+        // 1 → 3 directly at 1:1, or 1 → 2 → 3 at 2:1 then 1:2
+        let swaps = vec![
+            swap(1, 1, 3, "100", "100"),
+            swap(2, 1, 2, "100", "200"),
+            swap(3, 2, 3, "100", "200"),
+        ];
+        let route = find_best_route(&swaps, 1, 3, amt("50"), MAX_HOPS).unwrap();
+        assert_eq!(route.len(), 2);
+        assert_eq!(route[1].amount_out, amt("200"));
+
+        // With one hop allowed only the direct swap is used
+        let route = find_best_route(&swaps, 1, 3, amt("50"), 1).unwrap();
+        assert_eq!(route.len(), 1);
+        assert_eq!(route[0].amount_out, amt("50"));
+    }
+
+    #[test]
+    fn test_no_route() {
+        let swaps = vec![swap(1, 2, 3, "1", "1")];
+        assert!(find_best_route(&swaps, 1, 3, amt("1"), MAX_HOPS).is_none());
+    }
+
+    #[test]
+    fn test_market_rate() {
+        let prices = vec![("ABC".to_string(), "XYZ".to_string(), 2.0)];
+        assert_eq!(market_rate(&prices, "ABC", "XYZ"), Some(2.0));
+        assert_eq!(market_rate(&prices, "XYZ", "ABC"), Some(0.5));
+        assert_eq!(market_rate(&prices, "ABC", "DEF"), None);
+    }
+}
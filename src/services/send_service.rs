@@ -1,180 +1,314 @@
-use serenity::model::channel::Message;
+use crate::utils::Invocation;
 use serenity::prelude::Context;
-use crate::db;
+use crate::db::transaction::MAX_MEMO_LENGTH;
+use crate::storage::Storage;
+use crate::utils::Amount;
+use crate::utils::errors::LedgerError;
 
-// Maximum value for DECIMAL(24,8): 999,999,999,999,999.99999999
-const MAX_BALANCE: f64 = 999_999_999_999_999.99999999;
+/// Most recipients one `$send` may pay
+pub const MAX_SEND_RECIPIENTS: usize = 25;
 
 pub struct SendResult {
     pub sender_id: i64,
     pub receiver_ids: Vec<i64>,
-    pub amount: String,
+    /// What each receiver got, in the same order as `receiver_ids`
+    pub amounts: Vec<Amount>,
+    /// One transaction per receiver, in the same order as `receiver_ids`
+    pub transaction_uuids: Vec<String>,
     pub currency_ticker: String,
-    pub total_amount: String,
-    pub tax_amount: String,
+    pub total_amount: Amount,
+    pub tax_amount: Amount,
+    pub memo: Option<String>,
 }
 
 pub async fn execute_send(
     ctx: &Context,
-    msg: &Message,
-    receiver_id: i64,
-    amount: f64,
+    invocation: &Invocation,
+    payments: &[(i64, Amount)],
     currency_ticker: &str,
-) -> Result<(i64, String, f64), String> {
+    memo: Option<&str>,
+) -> Result<SendResult, String> {
     // Guild is required for sending
-    let _guild_id = msg
+    let _guild_id = invocation
         .guild_id
         .ok_or("This command can only be used in a guild".to_string())?;
 
-    let sender_id = msg.author.id.get() as i64;
+    let storage = crate::storage::from_ctx(ctx).await?;
+    send_many(storage.as_ref(), invocation.user_id_i64(), payments, currency_ticker, memo).await
+}
+
+/// Trim a memo, treating a blank one as none, and check its length
+pub fn clean_memo(memo: Option<&str>) -> Result<Option<String>, String> {
+    let Some(memo) = memo.map(str::trim).filter(|memo| !memo.is_empty()) else {
+        return Ok(None);
+    };
+    if memo.chars().count() > MAX_MEMO_LENGTH {
+        return Err(format!("❌ Memo is too long (at most {} characters)", MAX_MEMO_LENGTH));
+    }
+    Ok(Some(memo.to_string()))
+}
+
+/// Pay several users at once: every receiver is paid or none are.
+/// Tax is charged to the sender on the total, and every transaction gets the memo.
+pub async fn send_many(
+    storage: &dyn Storage,
+    sender_id: i64,
+    payments: &[(i64, Amount)],
+    currency_ticker: &str,
+    memo: Option<&str>,
+) -> Result<SendResult, String> {
+    let memo = clean_memo(memo)?;
+    if payments.is_empty() {
+        return Err("❌ Please specify at least one recipient".to_string());
+    }
+    if payments.len() > MAX_SEND_RECIPIENTS {
+        return Err(format!("❌ You can send to at most {} users at once", MAX_SEND_RECIPIENTS));
+    }
+    for (index, (receiver_id, amount)) in payments.iter().enumerate() {
+        if *receiver_id == sender_id {
+            return Err("Cannot transfer to yourself".to_string());
+        }
+        if !amount.is_positive() {
+            return Err("❌ Amount must be positive".to_string());
+        }
+        if payments[..index].iter().any(|(id, _)| id == receiver_id) {
+            return Err(format!("❌ <@{}> is listed more than once", receiver_id));
+        }
+    }
+
+    let (currency_id, _currency_name, ticker) = storage.get_currency_by_ticker(currency_ticker)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Currency '{}' not found", currency_ticker))?;
+
+    let total_amount: Amount = payments.iter().map(|(_, amount)| *amount).sum();
+
+    // Debits, credits, tax and one transaction log per receiver happen in one locked transaction
+    let receipt = storage.transfer_many(sender_id, currency_id, payments, memo.as_deref())
+        .await
+        .map_err(|e| match e {
+            LedgerError::AccountNotFound => "Sender has no account".to_string(),
+            LedgerError::InsufficientBalance { required, available } => format!(
+                "❌ Insufficient balance\n\nAmount: {:.2} {}\nTax: {:.2} {}\nTotal: {:.2} {}\nAvailable: {:.2} {}",
+                total_amount, ticker,
+                required - total_amount, ticker,
+                required, ticker,
+                available, ticker
+            ),
+            LedgerError::BalanceLimit { current, new_balance } => format!(
+                "❌ Transfer blocked: Receiver balance would exceed maximum limit.\n\
+                 Receiver current balance: {:.8} {}\n\
+                 New balance would be: {:.8} {}\n\
+                 Maximum allowed: {:.8} {}",
+                current, ticker,
+                new_balance, ticker,
+                Amount::MAX, ticker
+            ),
+            other => format!("Transfer failed: {}", other),
+        })?;
+
+    Ok(SendResult {
+        sender_id,
+        receiver_ids: payments.iter().map(|(id, _)| *id).collect(),
+        amounts: payments.iter().map(|(_, amount)| *amount).collect(),
+        transaction_uuids: receipt.transaction_uuids,
+        currency_ticker: ticker,
+        total_amount,
+        tax_amount: receipt.tax_amount,
+        memo,
+    })
+}
+
+/// Send `amount` of a currency from one user to another, with an optional memo
+/// Returns (receiver_id, transaction uuid, tax charged to the sender)
+pub async fn send(
+    storage: &dyn Storage,
+    sender_id: i64,
+    receiver_id: i64,
+    amount: Amount,
+    currency_ticker: &str,
+    memo: Option<&str>,
+) -> Result<(i64, String, Amount), String> {
+    let memo = clean_memo(memo)?;
 
     // Prevent self transfer
     if sender_id == receiver_id {
         return Err("Cannot transfer to yourself".to_string());
     }
     
-    // Get pool from context
-    let pool = {
-        let data = ctx.data.read().await;
-        data.get::<crate::DatabasePool>()
-            .ok_or("Database not initialized".to_string())?
-            .clone()
-    };
-    
     // Get currency by ticker
-    let (currency_id, _currency_name, _) = db::currency::get_currency_by_ticker(&pool, currency_ticker)
+    let (currency_id, _currency_name, _) = storage.get_currency_by_ticker(currency_ticker)
         .await
         .map_err(|e| format!("Database error: {}", e))?
         .ok_or_else(|| format!("Currency '{}' not found", currency_ticker))?;
     
-    // Get sender and receiver account IDs
-    let sender_account_id = db::account::get_account_id(&pool, sender_id, currency_id)
-        .await
-        .map_err(|e| format!("Database error: {}", e))?
-        .ok_or("Sender has no account".to_string())?;
-    
-    // Get or create receiver account
-    let receiver_account_id = match db::account::get_account_id(&pool, receiver_id, currency_id)
-        .await
-        .map_err(|e| format!("Database error: {}", e))?
-    {
-        Some(account_id) => account_id,
-        None => {
-            // Create account for receiver
-            db::account::create_account(&pool, receiver_id, currency_id)
-                .await
-                .map_err(|e| format!("Failed to create receiver account: {}", e))?
-        }
-    };
-    
-    // Verify sender has sufficient balance
-    let sender_balance = db::account::get_account_balance(&pool, sender_id, currency_id)
+    // Debit, credit, tax and transaction log happen in one locked transaction
+    let receipt = storage.transfer(sender_id, receiver_id, currency_id, amount, memo.as_deref())
         .await
-        .map_err(|e| format!("Database error: {}", e))?
-        .ok_or("Sender has no account".to_string())?;
-    
-    // Calculate tax
-    let tax_percentage = db::tax::get_tax_percentage(&pool, currency_id)
-        .await
-        .map_err(|e| format!("Database error: {}", e))?
-        .unwrap_or(0);
-    
-    let tax_amount = if tax_percentage > 0 {
-        (amount * tax_percentage as f64) / 100.0
-    } else {
-        0.0
-    };
-    
-    let total_deduction = amount + tax_amount;
-    
-    if sender_balance < total_deduction {
-        return Err(format!(
-            "❌ Insufficient balance\n\nAmount: {:.2} {}\nTax: {:.2} {}\nTotal: {:.2} {}\nAvailable: {:.2} {}",
-            amount, currency_ticker,
-            tax_amount, currency_ticker,
-            total_deduction, currency_ticker,
-            sender_balance, currency_ticker
-        ));
-    }
+        .map_err(|e| match e {
+            LedgerError::AccountNotFound => "Sender has no account".to_string(),
+            LedgerError::InsufficientBalance { required, available } => format!(
+                "❌ Insufficient balance\n\nAmount: {:.2} {}\nTax: {:.2} {}\nTotal: {:.2} {}\nAvailable: {:.2} {}",
+                amount, currency_ticker,
+                required - amount, currency_ticker,
+                required, currency_ticker,
+                available, currency_ticker
+            ),
+            LedgerError::BalanceLimit { current, new_balance } => format!(
+                "❌ Transfer blocked: Receiver balance would exceed maximum limit.\n\
+                 Receiver current balance: {:.8} {}\n\
+                 Transfer amount: {:.8} {}\n\
+                 New balance would be: {:.8} {}\n\
+                 Maximum allowed: {:.8} {}",
+                current, currency_ticker,
+                amount, currency_ticker,
+                new_balance, currency_ticker,
+                Amount::MAX, currency_ticker
+            ),
+            other => format!("Transfer failed: {}", other),
+        })?;
     
-    // Check receiver balance won't exceed maximum
-    let receiver_balance = db::account::get_account_balance(&pool, receiver_id, currency_id)
-        .await
-        .map_err(|e| format!("Database error: {}", e))?
-        .unwrap_or(0.0);
-    
-    let receiver_new_balance = receiver_balance + amount;
-    if receiver_new_balance > MAX_BALANCE {
-        return Err(format!(
-            "❌ Transfer blocked: Receiver balance would exceed maximum limit.\n\
-             Receiver current balance: {:.8} {}\n\
-             Transfer amount: {:.8} {}\n\
-             New balance would be: {:.8} {}\n\
-             Maximum allowed: {:.8} {}",
-            receiver_balance, currency_ticker,
-            amount, currency_ticker,
-            receiver_new_balance, currency_ticker,
-            MAX_BALANCE, currency_ticker
-        ));
-    }
-    
-    // Execute transfer - deduct both amount and tax from sender
-    db::account::update_balance(&pool, sender_account_id, -total_deduction).await
-        .map_err(|e| format!("Failed to update sender balance: {}", e))?;
-    
-    // Send only the amount (without tax) to receiver
-    db::account::update_balance(&pool, receiver_account_id, amount).await
-        .map_err(|e| format!("Failed to update receiver balance: {}", e))?;
-    
-    // Add tax to tax account if tax was deducted
-    if tax_amount > 0.0 {
-        db::tax::add_tax(&pool, currency_id, tax_amount)
-            .await
-            .map_err(|e| format!("Failed to record tax: {}", e))?;
-    }
-    
-    // Log transaction
-    let transaction_uuid = uuid::Uuid::new_v4().to_string();
-    let _transaction = db::transaction::create_transaction(
-        &pool,
-        &transaction_uuid,
-        sender_account_id,
-        receiver_account_id,
-        amount,
-    ).await
-    .map_err(|e| format!("Failed to log transaction: {}", e))?;
-    
-    Ok((receiver_id, transaction_uuid, tax_amount))
+    Ok((receiver_id, receipt.transaction_uuid, receipt.tax_amount))
 }
 
 pub fn create_send_embed(result: &SendResult) -> serenity::builder::CreateEmbed {
+    let equal_amounts = result.amounts.windows(2).all(|pair| pair[0] == pair[1]);
+
     let mut recipients_str = String::new();
-    for receiver_id in &result.receiver_ids {
-        recipients_str.push_str(&format!("<@{}>\n", receiver_id));
+    for (receiver_id, amount) in result.receiver_ids.iter().zip(&result.amounts) {
+        if equal_amounts {
+            recipients_str.push_str(&format!("<@{}>\n", receiver_id));
+        } else {
+            recipients_str.push_str(&format!("<@{}>: {} {}\n", receiver_id, amount, result.currency_ticker));
+        }
     }
-    
+
     let mut embed = serenity::builder::CreateEmbed::default()
         .title("💸 Transfer Successful")
         .field("From", format!("<@{}>", result.sender_id), false)
         .field("To", recipients_str, false)
         .color(0x00ff00);
-    
-    // Parse amounts to display breakdown
-    if let (Ok(amount), Ok(tax)) = (result.amount.parse::<f64>(), result.tax_amount.parse::<f64>()) {
-        let total_charged = amount + tax;
-        
-        if tax > 0.0 {
-            let breakdown = format!(
-                "**Amount Sent**: {} {}\n**Tax Deducted**: {} {}\n**Total Charged**: {:.2} {}",
-                result.amount, result.currency_ticker,
-                result.tax_amount, result.currency_ticker,
-                total_charged, result.currency_ticker
-            );
-            embed = embed.field("Transfer Breakdown", breakdown, false);
-        } else {
-            embed = embed.field("Amount", format!("{} {}", result.amount, result.currency_ticker), false);
-        }
+
+    if equal_amounts && result.amounts.len() > 1 {
+        embed = embed.field("Each", format!("{} {}", result.amounts[0], result.currency_ticker), false);
+    }
+
+    if result.tax_amount.is_positive() {
+        let breakdown = format!(
+            "**Amount Sent**: {} {}\n**Tax Deducted**: {} {}\n**Total Charged**: {:.2} {}",
+            result.total_amount, result.currency_ticker,
+            result.tax_amount, result.currency_ticker,
+            result.total_amount + result.tax_amount, result.currency_ticker
+        );
+        embed = embed.field("Transfer Breakdown", breakdown, false);
+    } else {
+        embed = embed.field("Amount", format!("{} {}", result.total_amount, result.currency_ticker), false);
+    }
+
+    if let Some(memo) = &result.memo {
+        embed = embed.field("Memo", memo, false);
+    }
+
+    match result.transaction_uuids.as_slice() {
+        [uuid] => embed.footer(serenity::builder::CreateEmbedFooter::new(format!("Transaction {}", uuid))),
+        uuids => embed.footer(serenity::builder::CreateEmbedFooter::new(format!("{} transactions", uuids.len()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{amount, AccountStore, MemoryStorage, TransactionStore};
+
+    const ALICE: i64 = 1;
+    const BOB: i64 = 2;
+    const CAROL: i64 = 3;
+
+    #[tokio::test]
+    async fn test_send_charges_tax_to_sender() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 10, &[(ALICE, "100")]);
+
+        let (receiver, uuid, tax) = send(&storage, ALICE, BOB, amount("50"), "abc", None).await.unwrap();
+        assert_eq!((receiver, tax), (BOB, amount("5")));
+        assert_eq!(storage.get_account_balance(ALICE, currency_id).await.unwrap(), Some(amount("45")));
+        assert_eq!(storage.get_account_balance(BOB, currency_id).await.unwrap(), Some(amount("50")));
+        assert_eq!(storage.total_supply(currency_id), amount("100"));
+
+        let (_, _, _, logged, _, _) = storage.get_transaction_by_uuid(&uuid).await.unwrap().unwrap();
+        assert_eq!(logged, amount("50"));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_sends_change_nothing() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 10, &[(ALICE, "100")]);
+
+        // 95 plus 9.5 tax is more than Alice has
+        let err = send(&storage, ALICE, BOB, amount("95"), "ABC", None).await.unwrap_err();
+        assert!(err.starts_with("❌ Insufficient balance"), "{}", err);
+        assert_eq!(send(&storage, ALICE, ALICE, amount("1"), "ABC", None).await.unwrap_err(), "Cannot transfer to yourself");
+        assert_eq!(send(&storage, BOB, ALICE, amount("1"), "ABC", None).await.unwrap_err(), "Sender has no account");
+        assert_eq!(send(&storage, ALICE, BOB, amount("1"), "XYZ", None).await.unwrap_err(), "Currency 'XYZ' not found");
+        // The ledger refuses a self-send on its own too
+        let to_self = storage.transfer(ALICE, ALICE, currency_id, amount("1"), None).await;
+        assert!(matches!(to_self, Err(LedgerError::DuplicateAccount)));
+
+        assert_eq!(storage.get_account_balance(ALICE, currency_id).await.unwrap(), Some(amount("100")));
+        assert_eq!(storage.get_account_balance(BOB, currency_id).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_send_many_is_all_or_nothing() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 10, &[(ALICE, "100")]);
+
+        // 35 plus 3.5 tax on the total
+        let result = send_many(&storage, ALICE, &[(BOB, amount("10")), (CAROL, amount("25"))], "abc", None).await.unwrap();
+        assert_eq!((result.total_amount, result.tax_amount), (amount("35"), amount("3.5")));
+        assert_eq!(result.currency_ticker, "ABC");
+        assert_eq!(result.transaction_uuids.len(), 2);
+        assert_eq!(storage.get_account_balance(ALICE, currency_id).await.unwrap(), Some(amount("61.5")));
+        assert_eq!(storage.get_user_transactions_paginated(ALICE, 1, 10, None).await.unwrap().1, 2);
+
+        // 60 each plus tax is more than Alice has, so neither is paid
+        let err = send_many(&storage, ALICE, &[(BOB, amount("30")), (CAROL, amount("30"))], "ABC", None).await.err().unwrap();
+        assert!(err.starts_with("❌ Insufficient balance"), "{}", err);
+        assert_eq!(storage.get_account_balance(BOB, currency_id).await.unwrap(), Some(amount("10")));
+        assert_eq!(storage.get_account_balance(CAROL, currency_id).await.unwrap(), Some(amount("25")));
+
+        assert_eq!(
+            send_many(&storage, ALICE, &[(BOB, amount("1")), (BOB, amount("1"))], "ABC", None).await.err().unwrap(),
+            "❌ <@2> is listed more than once"
+        );
+        assert_eq!(
+            send_many(&storage, ALICE, &[(BOB, amount("1")), (ALICE, amount("1"))], "ABC", None).await.err().unwrap(),
+            "Cannot transfer to yourself"
+        );
+        let too_many: Vec<(i64, Amount)> = (10..36).map(|id| (id, amount("1"))).collect();
+        assert!(send_many(&storage, ALICE, &too_many, "ABC", None).await.is_err());
+        assert_eq!(storage.total_supply(currency_id), amount("100"));
+    }
+
+    #[tokio::test]
+    async fn test_memo_is_stored_and_searchable() {
+        let storage = MemoryStorage::new();
+        storage.seed_currency("ABC", 0, &[(ALICE, "100")]);
+
+        let (_, uuid, _) = send(&storage, ALICE, BOB, amount("10"), "ABC", Some("  rent for March ")).await.unwrap();
+        let (.., memo) = storage.get_transaction_by_uuid(&uuid).await.unwrap().unwrap();
+        assert_eq!(memo.as_deref(), Some("rent for March"));
+
+        let result = send_many(&storage, ALICE, &[(BOB, amount("1")), (CAROL, amount("1"))], "ABC", Some("Lunch")).await.unwrap();
+        assert_eq!(result.memo.as_deref(), Some("Lunch"));
+        send(&storage, ALICE, BOB, amount("1"), "ABC", Some("   ")).await.unwrap();
+
+        let (rows, total) = storage.get_user_transactions_paginated(BOB, 1, 10, Some("RENT")).await.unwrap();
+        assert_eq!((total, rows[0].4.as_str()), (1, uuid.as_str()));
+        assert_eq!(storage.get_user_transactions_paginated(ALICE, 1, 10, Some("lunch")).await.unwrap().1, 2);
+        assert_eq!(storage.get_user_transactions_paginated(ALICE, 1, 10, None).await.unwrap().1, 4);
+
+        let long_memo = "x".repeat(MAX_MEMO_LENGTH + 1);
+        assert!(send(&storage, ALICE, BOB, amount("1"), "ABC", Some(&long_memo)).await.is_err());
     }
-    
-    embed
 }
@@ -0,0 +1,117 @@
+//! Swap Expiry Service - Background worker that expires overdue swaps
+//!
+//! Pending swaps hold the maker's funds in escrow. Once a swap's `expires_at` has
+//! passed, the worker closes it with `expire_swap` (the same refund as a cancel),
+//! edits the stored swap messages and DMs both parties. Overdue invoices are
+//! expired on the same tick by `invoice_service::expire_overdue_invoices`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use serenity::builder::{CreateEmbed, CreateMessage};
+use serenity::model::prelude::UserId;
+use serenity::prelude::Context;
+use tracing::{debug, error, info, warn};
+use crate::services::swap_service::{self, AcceptDenyResult};
+
+/// How often the worker looks for overdue swaps
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Maximum swaps expired per run, so one run can't hold the pool for long
+const EXPIRY_BATCH_SIZE: i64 = 50;
+
+static WORKER_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Start the expiry worker. Only the first call spawns it, so this is safe to
+/// call from every shard's ready event.
+pub fn start_worker(ctx: Context) {
+    if WORKER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    tokio::spawn(async move {
+        info!("Swap expiry worker started");
+        let mut interval = tokio::time::interval(EXPIRY_CHECK_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            match expire_overdue_swaps(&ctx).await {
+                Ok(0) => debug!("No overdue swaps"),
+                Ok(count) => info!("Expired {} overdue swap(s)", count),
+                Err(e) => error!("Swap expiry run failed: {}", e),
+            }
+
+            match crate::services::invoice_service::expire_overdue_invoices(&ctx).await {
+                Ok(0) => debug!("No overdue invoices"),
+                Ok(count) => info!("Expired {} overdue invoice(s)", count),
+                Err(e) => error!("Invoice expiry run failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Expire every overdue swap (up to one batch) and notify the parties
+/// Returns the number of swaps expired
+pub async fn expire_overdue_swaps(ctx: &Context) -> Result<usize, String> {
+    let storage = crate::storage::from_ctx(ctx).await?;
+
+    let overdue = storage.get_overdue_swaps(EXPIRY_BATCH_SIZE)
+        .await
+        .map_err(|e| format!("Failed to fetch overdue swaps: {}", e))?;
+
+    let mut expired = 0;
+
+    for (swap_id, maker_id, taker_id, maker_ticker, taker_ticker, maker_amount, taker_amount) in overdue {
+        // The backend re-checks the status under a row lock, so a swap accepted
+        // since the query above is left alone
+        if let Err(e) = storage.expire_swap(swap_id).await {
+            warn!("Could not expire swap {}: {}", swap_id, e);
+            continue;
+        }
+        expired += 1;
+
+        let result = AcceptDenyResult {
+            swap_id,
+            maker_id,
+            taker_id: taker_id.unwrap_or(0),
+            maker_offer: format!("{:.2} {}", maker_amount, maker_ticker),
+            taker_offer: format!("{:.2} {}", taker_amount, taker_ticker),
+            status: "expired".to_string(),
+            remaining: None,
+        };
+        // Edit the channel announcement and the taker's DM
+        swap_service::update_swap_messages(ctx, &result).await;
+
+        let maker_notice = CreateEmbed::default()
+            .title("⏱️ Swap Expired")
+            .description(format!(
+                "Your swap `{}` expired before it was accepted.\n**{}** has been refunded to your account.",
+                swap_id, result.maker_offer
+            ))
+            .color(0x808080);
+        notify(ctx, maker_id, maker_notice).await;
+
+        if let Some(taker_id) = taker_id {
+            let taker_notice = CreateEmbed::default()
+                .title("⏱️ Swap Expired")
+                .description(format!(
+                    "The swap `{}` from <@{}> ({} for {}) has expired and can no longer be accepted.",
+                    swap_id, maker_id, result.maker_offer, result.taker_offer
+                ))
+                .color(0x808080);
+            notify(ctx, taker_id, taker_notice).await;
+        }
+    }
+
+    Ok(expired)
+}
+
+/// DM a user, ignoring users who don't accept DMs
+async fn notify(ctx: &Context, user_id: i64, embed: CreateEmbed) {
+    if let Err(e) = UserId::new(user_id as u64)
+        .dm(ctx, CreateMessage::default().embed(embed))
+        .await
+    {
+        debug!("Could not DM user {} about expired swap: {}", user_id, e);
+    }
+}
@@ -1,8 +1,16 @@
-use serenity::model::channel::Message;
+use std::sync::Arc;
+use crate::utils::Invocation;
+use serenity::async_trait;
 use serenity::prelude::Context;
-use serenity::model::prelude::UserId;
-use crate::db;
-use uuid::Uuid;
+use serenity::builder::{CreateActionRow, CreateButton, EditMessage};
+use serenity::model::prelude::{ButtonStyle, ChannelId, MessageId, UserId};
+use crate::db::filter::{SwapFilter, SwapSort, SwapStatus};
+use crate::utils::Amount;
+use crate::utils::page::{Page, PageSource};
+use crate::storage::{NewSwap, Storage};
+
+/// Longest expiry a swap can be given (30 days)
+const MAX_SWAP_EXPIRY_MINUTES: i64 = 30 * 1440;
 
 pub struct SwapResult {
     pub swap_id: i64,
@@ -12,7 +20,8 @@ pub struct SwapResult {
     pub maker_currency: String,
     pub taker_amount: String,
     pub taker_currency: String,
-    pub status: String
+    pub status: String,
+    pub expires_at: Option<i64>,
 }
 
 pub struct AcceptDenyResult {
@@ -22,59 +31,125 @@ pub struct AcceptDenyResult {
     pub maker_offer: String,
     pub taker_offer: String,
     pub status: String,
+    /// What is left of the maker's offer after a partial fill
+    pub remaining: Option<String>,
 }
 
 pub async fn execute_swap(
     ctx: &Context,
-    msg: &Message,
+    invocation: &Invocation,
     maker_id: i64,
-    maker_amount: f64,
+    maker_amount: Amount,
     maker_ticker: &str,
     taker_id: Option<i64>,
-    taker_amount: Option<f64>,
+    taker_amount: Option<Amount>,
     taker_ticker: Option<&str>,
+    expiry_minutes: Option<i64>,
 ) -> Result<SwapResult, String> {
-    // Get guild_id if available (works for both guild and DM)
-    let guild_id = msg.guild_id.map(|id| id.get() as i64).unwrap_or(0);
-
-    // Get pool from context
-    let pool = {
-        let data = ctx.data.read().await;
-        data.get::<crate::DatabasePool>()
-            .ok_or("Database not initialized".to_string())?
-            .clone()
-    };
+    let storage = crate::storage::from_ctx(ctx).await?;
+    
+    let taker_ticker = taker_ticker.ok_or("Taker currency required".to_string())?;
+    let taker_amount = taker_amount.ok_or("Taker amount required".to_string())?;
+    
+    let result = create_swap(
+        storage.as_ref(),
+        maker_id,
+        (maker_amount, maker_ticker),
+        (taker_amount, taker_ticker),
+        taker_id,
+        expiry_minutes,
+    ).await?;
+    
+    // Send DM to taker if in mutual guild
+    if let Some(taker_id_val) = taker_id {
+        let taker_user_id = UserId::new(taker_id_val as u64);
+        if let Ok(_) = taker_user_id.to_user(ctx).await {
+            let msg_guild_id = invocation.guild_id;
+            if let Some(guild_id_obj) = msg_guild_id {
+                if let Ok(_) = guild_id_obj.member(ctx, taker_user_id).await {
+                    let mut embed = serenity::builder::CreateEmbed::default()
+                        .title("🔄 Swap Request")
+                        .description(format!("<@{}> has initiated a swap with you", maker_id))
+                        .field("Swap ID", format!("`{}`", result.swap_id), false)
+                        .field("Maker Offers", format!("`{} {}`", result.maker_amount, result.maker_currency), true)
+                        .field("Maker Wants", format!("`{} {}`", result.taker_amount, result.taker_currency), true)
+                        .field("Status", "⏳ **Awaiting Acceptance**", false);
+                    
+                    if let Some(ts) = result.expires_at {
+                        embed = embed.field("Expires", format!("<t:{}:R>", ts), false);
+                    }
+                    
+                    let embed = embed
+                        .footer(serenity::builder::CreateEmbedFooter::new("ℹ️ Balances have been deducted. They will be credited when you accept."))
+                        .color(0xffa500);
+                    
+                    let dm_message = serenity::builder::CreateMessage::default()
+                        .embed(embed)
+                        .components(create_swap_buttons(result.swap_id));
+                    
+                    // Store the DM so it can be edited when the swap is resolved
+                    if let Ok(dm) = taker_user_id.dm(ctx, dm_message).await {
+                        let _ = storage.store_swap_message(result.swap_id, dm.channel_id.get() as i64, dm.id.get() as i64).await;
+                    }
+                }
+            }
+        }
+    }
+    
+    Ok(result)
+}
+
+/// Escrow the maker's offer and open a swap for `want` in return, targeted at
+/// `taker_id` or open to anyone. Offer and want are (amount, ticker).
+pub async fn create_swap(
+    storage: &dyn Storage,
+    maker_id: i64,
+    offer: (Amount, &str),
+    want: (Amount, &str),
+    taker_id: Option<i64>,
+    expiry_minutes: Option<i64>,
+) -> Result<SwapResult, String> {
+    let (maker_amount, maker_ticker) = offer;
+    let (taker_amount, taker_ticker) = want;
     
     // Get maker's currency by ticker
-    let maker_currency = db::currency::get_currency_by_ticker(&pool, maker_ticker)
+    let maker_currency = storage.get_currency_by_ticker(maker_ticker)
         .await
         .map_err(|e| format!("Database error: {}", e))?
         .ok_or(format!("Currency {} not found", maker_ticker))?;
     let maker_currency_id = maker_currency.0;
     let maker_currency_name = maker_currency.2;
     
+    // Fall back to the maker currency's default expiry when none was given
+    let expiry_minutes = match expiry_minutes {
+        Some(minutes) => Some(minutes),
+        None => storage.get_swap_expiry_minutes(maker_currency_id)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?,
+    };
+    
     // Get maker's account ID (must exist)
-    let maker_account_id = db::account::get_account_id(&pool, maker_id, maker_currency_id)
+    let maker_account_id = storage.get_account_id(maker_id, maker_currency_id)
         .await
         .map_err(|e| format!("Database error: {}", e))?
         .ok_or("Maker has no account for this currency".to_string())?;
     
     // Verify maker has sufficient balance
-    let maker_balance = db::account::get_account_balance(&pool, maker_id, maker_currency_id)
+    let maker_balance = storage.get_account_balance(maker_id, maker_currency_id)
         .await
         .map_err(|e| format!("Database error: {}", e))?
         .ok_or("Maker has no account".to_string())?;
     
     // Calculate tax on maker's amount
-    let maker_tax_percentage = db::tax::get_tax_percentage(&pool, maker_currency_id)
+    let maker_tax_percentage = storage.get_tax_percentage(maker_currency_id)
         .await
         .map_err(|e| format!("Database error: {}", e))?
         .unwrap_or(0);
     
     let maker_tax_amount = if maker_tax_percentage > 0 {
-        (maker_amount * maker_tax_percentage as f64) / 100.0
+        maker_amount.percent(maker_tax_percentage)
     } else {
-        0.0
+        Amount::ZERO
     };
     
     let maker_total_deduction = maker_amount + maker_tax_amount;
@@ -86,388 +161,421 @@ pub async fn execute_swap(
         ));
     }
     
-    // If taker is specified, this is a targeted swap
-    if let (Some(taker_id_val), Some(taker_amount_val), Some(taker_ticker_val)) = (taker_id, taker_amount, taker_ticker) {
-        // Get taker's currency by ticker
-        let taker_currency = db::currency::get_currency_by_ticker(&pool, taker_ticker_val)
-            .await
-            .map_err(|e| format!("Database error: {}", e))?
-            .ok_or(format!("Currency {} not found", taker_ticker_val))?;
-        let taker_currency_id = taker_currency.0;
-        let taker_currency_name = taker_currency.2;
-        
-        // Get or create taker account for their currency
-        let taker_account_id = db::account::get_account_id(&pool, taker_id_val, taker_currency_id).await
-            .map_err(|e| format!("Database error: {}", e))?;
-        
-        let taker_account_id_final = if let Some(id) = taker_account_id {
-            id
-        } else {
-            db::account::create_account(&pool, taker_id_val, taker_currency_id)
+    // Get taker's currency by ticker
+    let taker_currency = storage.get_currency_by_ticker(taker_ticker)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or(format!("Currency {} not found", taker_ticker))?;
+    let taker_currency_id = taker_currency.0;
+    let taker_currency_name = taker_currency.2;
+    
+    // A targeted swap needs the taker to hold enough of their currency already
+    let taker_account_id = match taker_id {
+        Some(taker_id_val) => {
+            // Get or create taker account for their currency
+            let taker_account_id = match storage.get_account_id(taker_id_val, taker_currency_id)
                 .await
-                .map_err(|e| format!("Failed to create taker account: {}", e))?
-        };
-        
-        // Verify taker has sufficient balance in their currency
-        let taker_balance = db::account::get_account_balance(&pool, taker_id_val, taker_currency_id)
-            .await
-            .map_err(|e| format!("Database error: {}", e))?
-            .ok_or("Taker has no account".to_string())?;
-        
-        if taker_balance < taker_amount_val {
-            return Err(format!("Taker has insufficient {} balance", taker_ticker_val));
-        }
-        
-        // Create the targeted swap (deduction and swap creation handled atomically by procedure)
-        let swap_id = db::swap::create_swap(
-            &pool,
-            maker_account_id,
-            maker_currency_id,
-            taker_currency_id,
-            maker_amount,
-            taker_amount_val,
-            taker_account_id_final,
-        ).await
-        .map_err(|e| format!("Failed to create swap: {}", e))?;
-        
-        // Deduct tax from maker if applicable
-        if maker_tax_amount > 0.0 {
-            db::account::update_balance(&pool, maker_account_id, -maker_tax_amount).await
-                .map_err(|e| format!("Failed to deduct maker tax: {}", e))?;
+                .map_err(|e| format!("Database error: {}", e))?
+            {
+                Some(id) => id,
+                None => storage.create_account(taker_id_val, taker_currency_id)
+                    .await
+                    .map_err(|e| format!("Failed to create taker account: {}", e))?,
+            };
             
-            db::tax::add_tax(&pool, maker_currency_id, maker_tax_amount)
+            // Verify taker has sufficient balance in their currency
+            let taker_balance = storage.get_account_balance(taker_id_val, taker_currency_id)
                 .await
-                .map_err(|e| format!("Failed to record tax: {}", e))?;
-        }
-        
-        // Send DM to taker if in mutual guild
-        let taker_user_id = UserId::new(taker_id_val as u64);
-        if let Ok(_) = taker_user_id.to_user(ctx).await {
-            let msg_guild_id = msg.guild_id;
-            if let Some(guild_id_obj) = msg_guild_id {
-                if let Ok(_) = guild_id_obj.member(ctx, taker_user_id).await {
-                    let embed = serenity::builder::CreateEmbed::default()
-                        .title("🔄 Swap Request")
-                        .description(format!("<@{}> has initiated a swap with you", maker_id))
-                        .field("Swap ID", format!("`{}`", swap_id), false)
-                        .field("Maker Offers", format!("`{:.2} {}`", maker_amount, maker_currency_name), true)
-                        .field("Maker Wants", format!("`{:.2} {}`", taker_amount_val, taker_currency_name), true)
-                        .field("Status", "⏳ **Awaiting Acceptance**", false)
-                        .field("To Accept", format!("`$swap accept {}`", swap_id), true)
-                        .field("To Deny", format!("`$swap deny {}`", swap_id), true)
-                        .footer(serenity::builder::CreateEmbedFooter::new("ℹ️ Balances have been deducted. They will be credited when you accept."))
-                        .color(0xffa500);
-                    
-                    let _ = taker_user_id.dm(ctx, serenity::builder::CreateMessage::default().embed(embed)).await;
-                }
+                .map_err(|e| format!("Database error: {}", e))?
+                .ok_or("Taker has no account".to_string())?;
+            
+            if taker_balance < taker_amount {
+                return Err(format!("Taker has insufficient {} balance", taker_ticker));
             }
+            Some(taker_account_id)
         }
+        None => None,
+    };
+    
+    // Deduction and swap creation happen atomically in the backend
+    let swap_id = storage.create_swap(NewSwap {
+        maker_account_id,
+        maker_currency_id,
+        taker_currency_id,
+        maker_amount,
+        taker_amount,
+        taker_account_id,
+        expiry_minutes,
+    }).await
+    .map_err(|e| format!("Failed to create swap: {}", e))?;
+    
+    let expires_at = storage.get_swap_expiry(swap_id).await.unwrap_or(None);
+    
+    // Deduct tax from maker if applicable
+    if maker_tax_amount.is_positive() {
+        storage.update_balance(maker_account_id, -maker_tax_amount).await
+            .map_err(|e| format!("Failed to deduct maker tax: {}", e))?;
         
-        // Store the message ID for later editing
-        let _ = db::swap::store_swap_message(&pool, swap_id, msg.channel_id.get() as i64, msg.id.get() as i64).await;
-        
-        Ok(SwapResult {
-            swap_id,
-            maker_id,
-            taker_id: Some(taker_id_val),
-            maker_amount: format!("{:.2}", maker_amount),
-            maker_currency: maker_currency_name,
-            taker_amount: format!("{:.2}", taker_amount_val),
-            taker_currency: taker_currency_name,
-            status: "pending".to_string()
-        })
+        storage.add_tax(maker_currency_id, maker_tax_amount)
+            .await
+            .map_err(|e| format!("Failed to record tax: {}", e))?;
+    }
+    
+    Ok(SwapResult {
+        swap_id,
+        maker_id,
+        taker_id,
+        maker_amount: format!("{:.2}", maker_amount),
+        maker_currency: maker_currency_name,
+        taker_amount: format!("{:.2}", taker_amount),
+        taker_currency: taker_currency_name,
+        status: "pending".to_string(),
+        expires_at,
+    })
+}
+
+/// Button custom ID prefixes; the swap ID follows the colon
+const ACCEPT_BUTTON_PREFIX: &str = "swap_accept:";
+const DENY_BUTTON_PREFIX: &str = "swap_deny:";
+
+/// What a swap button does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapAction {
+    Accept,
+    Deny,
+}
+
+/// Accept/Deny buttons for a pending swap
+pub fn create_swap_buttons(swap_id: i64) -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{}{}", ACCEPT_BUTTON_PREFIX, swap_id))
+            .label("Accept")
+            .emoji('✅')
+            .style(ButtonStyle::Success),
+        CreateButton::new(format!("{}{}", DENY_BUTTON_PREFIX, swap_id))
+            .label("Deny")
+            .emoji('❌')
+            .style(ButtonStyle::Danger),
+    ])]
+}
+
+/// Parse a button custom ID created by `create_swap_buttons`
+pub fn parse_swap_button(custom_id: &str) -> Option<(SwapAction, i64)> {
+    let (action, swap_id) = if let Some(id) = custom_id.strip_prefix(ACCEPT_BUTTON_PREFIX) {
+        (SwapAction::Accept, id)
+    } else if let Some(id) = custom_id.strip_prefix(DENY_BUTTON_PREFIX) {
+        (SwapAction::Deny, id)
     } else {
-        // Open swap - taker_id is None, but we have taker_amount and taker_ticker
-        // Get taker's currency by ticker
-        let taker_ticker_str = taker_ticker.ok_or("Taker currency required for open swap".to_string())?;
-        let taker_amount_val = taker_amount.ok_or("Taker amount required for open swap".to_string())?;
-        
-        let taker_currency = db::currency::get_currency_by_ticker(&pool, taker_ticker_str)
+        return None;
+    };
+    
+    swap_id.parse().ok().map(|id| (action, id))
+}
+
+/// Edit every stored message for a resolved swap to show its final status
+/// and remove the Accept/Deny buttons
+pub async fn update_swap_messages(ctx: &Context, result: &AcceptDenyResult) {
+    let storage = match crate::storage::from_ctx(ctx).await {
+        Ok(storage) => storage,
+        Err(_) => return,
+    };
+    
+    let messages = match storage.get_swap_messages(result.swap_id).await {
+        Ok(messages) => messages,
+        Err(e) => {
+            tracing::warn!("Could not load messages for swap {}: {}", result.swap_id, e);
+            return;
+        }
+    };
+    
+    let edit = EditMessage::new()
+        .embed(create_accept_deny_embed(result))
+        .components(Vec::new());
+    
+    for (channel_id, message_id) in messages {
+        if let Err(e) = ChannelId::new(channel_id as u64)
+            .edit_message(ctx, MessageId::new(message_id as u64), edit.clone())
             .await
-            .map_err(|e| format!("Database error: {}", e))?
-            .ok_or(format!("Currency {} not found", taker_ticker_str))?;
-        let taker_currency_id = taker_currency.0;
-        let taker_currency_name = taker_currency.2;
-        
-        // Create the open swap with both currencies and amounts
-        let swap_id = db::swap::create_swap_open(
-            &pool,
-            maker_account_id,
-            maker_currency_id,
-            taker_currency_id,
-            maker_amount,
-            taker_amount_val,
-        ).await
-        .map_err(|e| format!("Failed to create open swap: {}", e))?;
-        
-        // Deduct tax from maker if applicable
-        if maker_tax_amount > 0.0 {
-            db::account::update_balance(&pool, maker_account_id, -maker_tax_amount).await
-                .map_err(|e| format!("Failed to deduct maker tax: {}", e))?;
-            
-            db::tax::add_tax(&pool, maker_currency_id, maker_tax_amount)
-                .await
-                .map_err(|e| format!("Failed to record tax: {}", e))?;
+        {
+            tracing::debug!("Could not edit message {} for swap {}: {}", message_id, result.swap_id, e);
         }
-        
-        Ok(SwapResult {
-            swap_id,
-            maker_id,
-            taker_id: None,
-            maker_amount: format!("{:.2}", maker_amount),
-            maker_currency: maker_currency_name,
-            taker_amount: format!("{:.2}", taker_amount_val),
-            taker_currency: taker_currency_name,
-            status: "pending".to_string(),
-        })
     }
 }
 
+/// Remember a message showing a swap so it can be edited when the swap is resolved
+pub async fn store_swap_message(ctx: &Context, swap_id: i64, channel_id: u64, message_id: u64) {
+    let storage = match crate::storage::from_ctx(ctx).await {
+        Ok(storage) => storage,
+        Err(_) => return,
+    };
+    
+    if let Err(e) = storage.store_swap_message(swap_id, channel_id as i64, message_id as i64).await {
+        tracing::warn!("Failed to store message for swap {}: {}", swap_id, e);
+    }
+}
+
+/// Accept a swap, or fill `fill_amount` of the maker's currency from an open swap
 pub async fn accept_swap(
     ctx: &Context,
-    msg: &Message,
+    invocation: &Invocation,
     swap_id: Option<i64>,
+    fill_amount: Option<Amount>,
 ) -> Result<(AcceptDenyResult, Option<u64>), String> {
-    let user_id = msg.author.id.get() as i64;
-    
-    // Get pool from context
-    let pool = {
-        let data = ctx.data.read().await;
-        data.get::<crate::DatabasePool>()
-            .ok_or("Database not initialized".to_string())?
-            .clone()
-    };
+    // Accepting all pending swaps at once is not supported
+    let id = swap_id.ok_or("Please specify a swap ID with `$swap accept <id>`".to_string())?;
     
-    if let Some(id) = swap_id {
-        // Accept a specific swap by ID
-        // Get swap details: (id, maker_id, taker_id, maker_currency_id, taker_currency_id, maker_amount, taker_amount, status)
-        let swap_details = db::swap::get_swap_by_id(&pool, id).await
-            .map_err(|e| format!("Failed to fetch swap: {}", e))?
-            .ok_or("Swap not found".to_string())?;
-        
-        let status = swap_details.7.as_str();
-        if status != "pending" {
-            if status == "accepted" {
-                return Err("❌ This swap has already been accepted!".to_string());
-            } else if status == "cancelled" {
-                return Err("❌ This swap has been cancelled!".to_string());
-            } else if status == "expired" {
-                return Err("❌ This swap has expired!".to_string());
-            }
-            return Err(format!("❌ Swap status is '{}', cannot accept.", status));
+    let storage = crate::storage::from_ctx(ctx).await?;
+    let result = fill_swap(storage.as_ref(), invocation.user_id_i64(), id, fill_amount).await?;
+    
+    // A partial fill leaves the swap pending, so its messages and buttons are left as they are
+    if result.remaining.is_none() {
+        update_swap_messages(ctx, &result).await;
+    }
+    
+    Ok((result, invocation.message_id.map(|id| id.get())))
+}
+
+/// Fill a swap as `user_id`: the whole swap, or `fill_amount` of the maker's currency from an open swap
+pub async fn fill_swap(
+    storage: &dyn Storage,
+    user_id: i64,
+    id: i64,
+    fill_amount: Option<Amount>,
+) -> Result<AcceptDenyResult, String> {
+    // Get swap details: (id, maker_id, taker_id, maker_currency_id, taker_currency_id, maker_amount, taker_amount, status)
+    let swap_details = storage.get_swap_by_id(id).await
+        .map_err(|e| format!("Failed to fetch swap: {}", e))?
+        .ok_or("Swap not found".to_string())?;
+    
+    let status = swap_details.7.as_str();
+    if status != "pending" {
+        if status == "accepted" {
+            return Err("❌ This swap has already been accepted!".to_string());
+        } else if status == "cancelled" {
+            return Err("❌ This swap has been cancelled!".to_string());
+        } else if status == "expired" {
+            return Err("❌ This swap has expired!".to_string());
         }
-        
-        let _swap_id = swap_details.0;
-        let maker_account_id = swap_details.1;
-        let taker_id_existing = swap_details.2;
-        let maker_currency_id = swap_details.3;
-        let taker_currency_id = swap_details.4;
-        let maker_amount = swap_details.5;
-        let taker_amount = swap_details.6;
-        
-        // Get the actual Discord user IDs from account IDs
-        let maker_discord_id = db::account::get_discord_id_by_account_id(&pool, maker_account_id)
+        return Err(format!("❌ Swap status is '{}', cannot accept.", status));
+    }
+    
+    let maker_account_id = swap_details.1;
+    let taker_id_existing = swap_details.2;
+    let maker_currency_id = swap_details.3;
+    let taker_currency_id = swap_details.4;
+    let maker_amount = swap_details.5;
+    let taker_amount = swap_details.6;
+    
+    // Get the actual Discord user IDs from account IDs
+    let maker_discord_id = storage.get_discord_id_by_account_id(maker_account_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or("Maker account not found".to_string())?;
+    
+    let taker_discord_id = if let Some(taker_account_id) = taker_id_existing {
+        storage.get_discord_id_by_account_id(taker_account_id)
             .await
             .map_err(|e| format!("Database error: {}", e))?
-            .ok_or("Maker account not found".to_string())?;
-        
-        let taker_discord_id = if let Some(taker_account_id) = taker_id_existing {
-            db::account::get_discord_id_by_account_id(&pool, taker_account_id)
-                .await
-                .map_err(|e| format!("Database error: {}", e))?
-                .ok_or("Taker account not found".to_string())?
-        } else {
-            0 // Open swap has no taker yet
-        };
-        
-        // SECURITY: Verify user is authorized to accept this swap
+            .ok_or("Taker account not found".to_string())?
+    } else {
+        0 // Open swap has no taker yet
+    };
+    
+    // SECURITY: Verify user is authorized to accept this swap
+    if taker_discord_id != 0 {
+        // Targeted swap: Only the taker can accept
+        if user_id != taker_discord_id {
+            return Err("❌ You are not authorized to accept this swap. Only the designated taker can accept targeted swaps.".to_string());
+        }
+    } else {
+        // Open swap: The maker CANNOT accept their own swap
+        if user_id == maker_discord_id {
+            return Err("❌ You cannot accept your own open swap. Another user must accept it.".to_string());
+        }
+    }
+    
+    if let Some(fill) = fill_amount {
+        if !fill.is_positive() {
+            return Err("❌ Fill amount must be greater than 0".to_string());
+        }
         if taker_discord_id != 0 {
-            // Targeted swap: Only the taker can accept
-            if user_id != taker_discord_id {
-                return Err("❌ You are not authorized to accept this swap. Only the designated taker can accept targeted swaps.".to_string());
-            }
-        } else {
-            // Open swap: The maker CANNOT accept their own swap
-            if user_id == maker_discord_id {
-                return Err("❌ You cannot accept your own open swap. Another user must accept it.".to_string());
-            }
+            return Err("❌ Only open swaps can be partially filled. Use `$swap accept <id>` to accept the whole swap.".to_string());
         }
-        
-        // Generate unique UUIDs for the two transactions
-        let uuid1 = Uuid::new_v4().to_string();
-        let uuid2 = Uuid::new_v4().to_string();
-        
-        // Call procedure to accept swap atomically (handles all balance deductions, credits, and transactions)
-        db::swap::accept_swap(&pool, id, user_id, &uuid1, &uuid2)
-            .await
-            .map_err(|e| e.to_string())?;
-        
-        // Get currency tickers
-        let maker_currency_ticker = db::currency::get_currency_by_id(&pool, maker_currency_id)
-            .await
-            .unwrap_or(None)
-            .map(|c| c.3)
-            .unwrap_or_else(|| "???".to_string());
-        let taker_currency_ticker = db::currency::get_currency_by_id(&pool, taker_currency_id)
-            .await
-            .unwrap_or(None)
-            .map(|c| c.3)
-            .unwrap_or_else(|| "???".to_string());
-        
-        // Determine canonical order (alphabetically by ticker)
-        let (base_currency_id, quote_currency_id, base_amount, quote_amount) = 
-            if maker_currency_ticker <= taker_currency_ticker {
-                (maker_currency_id, taker_currency_id, maker_amount, taker_amount)
-            } else {
-                (taker_currency_id, maker_currency_id, taker_amount, maker_amount)
-            };
-        
-        // Calculate price (quote_amount / base_amount)
-        let price = if base_amount != 0.0 {
-            quote_amount / base_amount
-        } else {
-            0.0
-        };
-        
-        // Log the trading price to tradelog
-        let _ = db::tradelog::add_price_log(&pool, base_currency_id, quote_currency_id, price)
+    }
+    
+    // Balance deductions, credits and transactions all happen atomically in the backend
+    let (maker_filled, taker_paid, fully_filled) = storage.accept_swap(id, user_id, fill_amount)
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    // Get currency tickers
+    let maker_currency_ticker = storage.get_currency_by_id(maker_currency_id)
+        .await
+        .unwrap_or(None)
+        .map(|c| c.3)
+        .unwrap_or_else(|| "???".to_string());
+    let taker_currency_ticker = storage.get_currency_by_id(taker_currency_id)
+        .await
+        .unwrap_or(None)
+        .map(|c| c.3)
+        .unwrap_or_else(|| "???".to_string());
+    
+    // Every fill trades at the maker's price
+    log_swap_price(
+        storage,
+        (maker_currency_id, &maker_currency_ticker, maker_amount, maker_filled),
+        (taker_currency_id, &taker_currency_ticker, taker_amount, taker_paid),
+    ).await;
+    
+    if !fully_filled {
+        let filled = storage.get_swap_fill_progress(id)
             .await
-            .map_err(|e| format!("Failed to log price: {}", e));
+            .map_err(|e| format!("Database error: {}", e))?
+            .map(|(filled, _)| filled)
+            .unwrap_or(maker_filled);
         
-        Ok((AcceptDenyResult {
+        return Ok(AcceptDenyResult {
             swap_id: id,
             maker_id: maker_discord_id,
             taker_id: user_id,
-            maker_offer: format!("{:.2} {}", maker_amount, maker_currency_ticker),
-            taker_offer: format!("{:.2} {}", taker_amount, taker_currency_ticker),
-            status: "accepted".to_string(),
-        }, Some(msg.id.get())))
+            maker_offer: format!("{:.2} {}", maker_filled, maker_currency_ticker),
+            taker_offer: format!("{:.2} {}", taker_paid, taker_currency_ticker),
+            status: "partially filled".to_string(),
+            remaining: Some(format!("{} {}", maker_amount - filled, maker_currency_ticker)),
+        });
+    }
+    
+    Ok(AcceptDenyResult {
+        swap_id: id,
+        maker_id: maker_discord_id,
+        taker_id: user_id,
+        maker_offer: format!("{:.2} {}", maker_amount, maker_currency_ticker),
+        taker_offer: format!("{:.2} {}", taker_amount, taker_currency_ticker),
+        status: "accepted".to_string(),
+        remaining: None,
+    })
+}
 
-    } else {
-        // Accept all pending swaps - not typically used, but keep for compatibility
-        Err("Please specify a swap ID with `$swap accept <id>`".to_string())
+/// Log a swap fill's price to the tradelog and candles in the pair's canonical order (alphabetically by ticker)
+/// Each side is (currency_id, ticker, swap amount, amount traded in this fill)
+pub async fn log_swap_price(storage: &dyn Storage, maker: (i64, &str, Amount, Amount), taker: (i64, &str, Amount, Amount)) {
+    let (base, quote) = if maker.1 <= taker.1 { (maker, taker) } else { (taker, maker) };
+    
+    // Calculate price (quote_amount / base_amount)
+    let price = quote.2.checked_div(base.2).unwrap_or(Amount::ZERO);
+    
+    if let Err(e) = storage.add_price_log(base.0, quote.0, price, base.3, quote.3).await {
+        tracing::warn!("Failed to log price: {}", e);
     }
 }
 
 pub async fn deny_swap(
     ctx: &Context,
-    msg: &Message,
+    invocation: &Invocation,
     swap_id: Option<i64>,
 ) -> Result<(AcceptDenyResult, Option<u64>), String> {
-    let user_id = msg.author.id.get() as i64;
-    
-    // Get pool from context
-    let pool = {
-        let data = ctx.data.read().await;
-        data.get::<crate::DatabasePool>()
-            .ok_or("Database not initialized".to_string())?
-            .clone()
-    };
+    // Denying all pending swaps at once is not supported
+    let id = swap_id.ok_or("Please specify a swap ID with `$swap deny <id>`".to_string())?;
     
-    if let Some(id) = swap_id {
-        // Deny specific swap
-        let swap_details = db::swap::get_swap_by_id(&pool, id).await
-            .map_err(|e| format!("Failed to fetch swap: {}", e))?
-            .ok_or("Swap not found".to_string())?;
-        
-        let status = swap_details.7.as_str();
-        if status != "pending" {
-            if status == "accepted" {
-                return Err("❌ This swap has already been accepted!".to_string());
-            } else if status == "cancelled" {
-                return Err("❌ This swap has already been cancelled!".to_string());
-            } else if status == "expired" {
-                return Err("❌ This swap has expired!".to_string());
-            }
-            return Err(format!("❌ Swap status is '{}', cannot deny.", status));
+    let storage = crate::storage::from_ctx(ctx).await?;
+    let result = cancel_swap(storage.as_ref(), invocation.user_id_i64(), id).await?;
+    update_swap_messages(ctx, &result).await;
+    
+    Ok((result, invocation.message_id.map(|id| id.get())))
+}
+
+/// Cancel a pending swap as its maker or taker, refunding the maker's escrow
+pub async fn cancel_swap(
+    storage: &dyn Storage,
+    user_id: i64,
+    id: i64,
+) -> Result<AcceptDenyResult, String> {
+    let swap_details = storage.get_swap_by_id(id).await
+        .map_err(|e| format!("Failed to fetch swap: {}", e))?
+        .ok_or("Swap not found".to_string())?;
+    
+    let status = swap_details.7.as_str();
+    if status != "pending" {
+        if status == "accepted" {
+            return Err("❌ This swap has already been accepted!".to_string());
+        } else if status == "cancelled" {
+            return Err("❌ This swap has already been cancelled!".to_string());
+        } else if status == "expired" {
+            return Err("❌ This swap has expired!".to_string());
         }
-        
-        let maker_account_id = swap_details.1;
-        let taker_id_existing = swap_details.2;
-        
-        // Get the actual Discord user IDs from account IDs
-        let maker_discord_id = db::account::get_discord_id_by_account_id(&pool, maker_account_id)
+        return Err(format!("❌ Swap status is '{}', cannot deny.", status));
+    }
+    
+    let maker_account_id = swap_details.1;
+    let taker_id_existing = swap_details.2;
+    
+    // Get the actual Discord user IDs from account IDs
+    let maker_discord_id = storage.get_discord_id_by_account_id(maker_account_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or("Maker account not found".to_string())?;
+    
+    let taker_discord_id = if let Some(taker_account_id) = taker_id_existing {
+        storage.get_discord_id_by_account_id(taker_account_id)
             .await
             .map_err(|e| format!("Database error: {}", e))?
-            .ok_or("Maker account not found".to_string())?;
-        
-        let taker_discord_id = if let Some(taker_account_id) = taker_id_existing {
-            db::account::get_discord_id_by_account_id(&pool, taker_account_id)
-                .await
-                .map_err(|e| format!("Database error: {}", e))?
-                .ok_or("Taker account not found".to_string())?
-        } else {
-            0
-        };
-        
-        // SECURITY: Only the maker or the taker can deny a swap
-        let is_authorized = (user_id == maker_discord_id) || (taker_discord_id != 0 && user_id == taker_discord_id);
-        if !is_authorized {
-            let error_msg = if taker_discord_id == 0 {
-                "❌ You are not authorized to deny this swap. Only the maker can deny an open swap.".to_string()
-            } else {
-                "❌ You are not authorized to deny this swap. Only the maker or taker can deny a targeted swap.".to_string()
-            };
-            return Err(error_msg);
-        }
-        // Call procedure to cancel/deny swap atomically (handles refunds)
-        db::swap::cancel_swap(&pool, id)
-            .await
-            .map_err(|e| format!("Failed to deny swap: {}", e))?;
-        
-        // Extract amounts from swap details for the response
-        let maker_amount = swap_details.5;
-        let taker_amount = swap_details.6;
-        
-        // Get currency names
-        let maker_currency_id = swap_details.3;
-        let taker_currency_id = swap_details.4;
-        let maker_currency_ticker = db::currency::get_currency_by_id(&pool, maker_currency_id)
-            .await
-            .unwrap_or(None)
-            .map(|c| c.3)
-            .unwrap_or_else(|| "???".to_string());
-        let taker_currency_ticker = db::currency::get_currency_by_id(&pool, taker_currency_id)
-            .await
-            .unwrap_or(None)
-            .map(|c| c.3)
-            .unwrap_or_else(|| "???".to_string());
-        
-        let taker_discord_id_final = if let Some(_) = taker_id_existing {
-            taker_discord_id
+            .ok_or("Taker account not found".to_string())?
+    } else {
+        0 // Open swap, no specific taker
+    };
+    
+    // SECURITY: Only the maker or the taker can deny a swap
+    let is_authorized = (user_id == maker_discord_id) || (taker_discord_id != 0 && user_id == taker_discord_id);
+    if !is_authorized {
+        let error_msg = if taker_discord_id == 0 {
+            "❌ You are not authorized to deny this swap. Only the maker can deny an open swap.".to_string()
         } else {
-            0 // Open swap, no specific taker
+            "❌ You are not authorized to deny this swap. Only the maker or taker can deny a targeted swap.".to_string()
         };
-        
-        Ok((AcceptDenyResult {
-            swap_id: id,
-            maker_id: maker_discord_id,
-            taker_id: taker_discord_id_final,
-            maker_offer: format!("{:.2} {}", maker_amount, maker_currency_ticker),
-            taker_offer: format!("{:.2} {}", taker_amount, taker_currency_ticker),
-            status: "cancelled".to_string(),
-        }, Some(msg.id.get())))
-    } else {
-        // Deny all pending swaps - not typically used
-        Err("Please specify a swap ID with `$swap deny <id>`".to_string())
+        return Err(error_msg);
     }
+    // Cancel atomically in the backend (handles refunds)
+    storage.cancel_swap(id)
+        .await
+        .map_err(|e| format!("Failed to deny swap: {}", e))?;
+    
+    // Extract amounts from swap details for the response
+    let maker_amount = swap_details.5;
+    let taker_amount = swap_details.6;
+    
+    // Get currency names
+    let maker_currency_id = swap_details.3;
+    let taker_currency_id = swap_details.4;
+    let maker_currency_ticker = storage.get_currency_by_id(maker_currency_id)
+        .await
+        .unwrap_or(None)
+        .map(|c| c.3)
+        .unwrap_or_else(|| "???".to_string());
+    let taker_currency_ticker = storage.get_currency_by_id(taker_currency_id)
+        .await
+        .unwrap_or(None)
+        .map(|c| c.3)
+        .unwrap_or_else(|| "???".to_string());
+    
+    Ok(AcceptDenyResult {
+        swap_id: id,
+        maker_id: maker_discord_id,
+        taker_id: taker_discord_id,
+        maker_offer: format!("{:.2} {}", maker_amount, maker_currency_ticker),
+        taker_offer: format!("{:.2} {}", taker_amount, taker_currency_ticker),
+        status: "cancelled".to_string(),
+        remaining: None,
+    })
 }
 
 pub async fn get_swap_status(
     ctx: &Context,
-    _msg: &Message,
     swap_id: i64,
 ) -> Result<serenity::builder::CreateEmbed, String> {
-    // Get pool from context
-    let pool = {
-        let data = ctx.data.read().await;
-        data.get::<crate::DatabasePool>()
-            .ok_or("Database not initialized".to_string())?
-            .clone()
-    };
+    let storage = crate::storage::from_ctx(ctx).await?;
     
     // Fetch swap details
-    let swap_details = db::swap::get_swap_by_id(&pool, swap_id).await
+    let swap_details = storage.get_swap_by_id(swap_id).await
         .map_err(|e| format!("Database error: {}", e))?
         .ok_or("Swap not found".to_string())?;
     
@@ -480,13 +588,13 @@ pub async fn get_swap_status(
     let status = swap_details.7.as_str();
     
     // Get Discord IDs from account IDs
-    let maker_discord_id = db::account::get_discord_id_by_account_id(&pool, maker_account_id)
+    let maker_discord_id = storage.get_discord_id_by_account_id(maker_account_id)
         .await
         .map_err(|e| format!("Database error: {}", e))?
         .ok_or("Maker account not found".to_string())?;
     
     let taker_discord_id = if let Some(taker_acc_id) = taker_account_id {
-        db::account::get_discord_id_by_account_id(&pool, taker_acc_id)
+        storage.get_discord_id_by_account_id(taker_acc_id)
             .await
             .map_err(|e| format!("Database error: {}", e))?
             .ok_or("Taker account not found".to_string())?
@@ -495,13 +603,13 @@ pub async fn get_swap_status(
     };
     
     // Get currency tickers
-    let maker_ticker = db::currency::get_currency_by_id(&pool, maker_currency_id)
+    let maker_ticker = storage.get_currency_by_id(maker_currency_id)
         .await
         .unwrap_or(None)
         .map(|c| c.3)
         .unwrap_or_else(|| "???".to_string());
     
-    let taker_ticker = db::currency::get_currency_by_id(&pool, taker_currency_id)
+    let taker_ticker = storage.get_currency_by_id(taker_currency_id)
         .await
         .unwrap_or(None)
         .map(|c| c.3)
@@ -541,10 +649,34 @@ pub async fn get_swap_status(
             .field("Taker Wants", format!("`{:.2} {}`", taker_amount, taker_ticker), true);
     }
     
+    // Open swaps can be filled in parts; show how far along they are
+    if let Some((filled, fills)) = storage.get_swap_fill_progress(swap_id).await.unwrap_or(None) {
+        if taker_account_id.is_none() || fills > 1 {
+            embed = embed.field(
+                "Filled",
+                format!("{} in {} fill(s)", format_fill_progress(filled, maker_amount, &maker_ticker), fills),
+                false,
+            );
+        }
+    }
+    
+    if status == "pending" {
+        if let Some(ts) = storage.get_swap_expiry(swap_id).await.unwrap_or(None) {
+            embed = embed.field("Expires", format!("<t:{}:R>", ts), false);
+        }
+    }
+    
     embed = embed.color(color);
     Ok(embed)
 }
 
+/// e.g. "25/100 ABC filled (25.0%)"
+pub fn format_fill_progress(filled: Amount, total: Amount, ticker: &str) -> String {
+    // Display only, so f64 is fine for the percentage
+    let percent = if total.is_positive() { filled.to_f64() / total.to_f64() * 100.0 } else { 0.0 };
+    format!("`{}/{} {}` filled ({:.1}%)", filled, total, ticker, percent)
+}
+
 pub fn create_swap_embed(result: &SwapResult) -> serenity::builder::CreateEmbed {
     let mut embed = serenity::builder::CreateEmbed::default()
         .title("🔄 Swap Created")
@@ -563,42 +695,136 @@ pub fn create_swap_embed(result: &SwapResult) -> serenity::builder::CreateEmbed
             .field("Status", format!("**{}**", result.status), false);
     }
     
+    if let Some(ts) = result.expires_at {
+        embed = embed.field("Expires", format!("<t:{}:R>", ts), false);
+    }
+    
     embed.color(0xffa500)
 }
 
 pub fn create_accept_deny_embed(result: &AcceptDenyResult) -> serenity::builder::CreateEmbed {
-    let title = if result.status == "accepted" {
-        "✅ Swap Accepted"
-    } else {
-        "❌ Swap Denied"
+    let (title, color) = match result.status.as_str() {
+        "accepted" => ("✅ Swap Accepted", 0x00ff00),  // Green
+        "partially filled" => ("🧩 Swap Partially Filled", 0x00bfff),  // Blue
+        "expired" => ("⏱️ Swap Expired", 0x808080),    // Gray
+        _ => ("❌ Swap Denied", 0xff0000),             // Red
     };
     
-    let color = if result.status == "accepted" {
-        0x00ff00  // Green
+    let taker = if result.taker_id != 0 {
+        format!("<@{}>", result.taker_id)
     } else {
-        0xff0000  // Red
+        "**Open Swap**".to_string()
     };
     
-    serenity::builder::CreateEmbed::default()
+    let mut embed = serenity::builder::CreateEmbed::default()
         .title(title)
         .field("Swap ID", format!("`{}`", result.swap_id), true)
         .field("Status", format!("**{}**", result.status), true)
         .field("Maker", format!("<@{}>", result.maker_id), true)
         .field("Maker Offers", result.maker_offer.clone(), true)
-        .field("Taker", format!("<@{}>", result.taker_id), true)
-        .field("Taker Wants", result.taker_offer.clone(), true)
-        .color(color)
+        .field("Taker", taker, true)
+        .field("Taker Wants", result.taker_offer.clone(), true);
+    
+    if let Some(remaining) = &result.remaining {
+        embed = embed.field("Remaining", format!("`{}` still open", remaining), false);
+    }
+    
+    embed.color(color)
 }
 
+/// Parse a swap expiry such as `30m`, `24h`, `7d` or `2w` into minutes
+pub fn parse_expiry(input: &str) -> Result<i64, String> {
+    let input = input.to_lowercase();
+    let split_idx = input.chars().take_while(|c| c.is_ascii_digit()).count();
+    
+    let amount: i64 = input[..split_idx]
+        .parse()
+        .map_err(|_| format!("❌ Invalid expiry '{}'. Examples: 30m, 24h, 7d, 2w", input))?;
+    
+    let minutes = match &input[split_idx..] {
+        "m" => amount,
+        "h" => amount.saturating_mul(60),
+        "d" => amount.saturating_mul(1440),
+        "w" => amount.saturating_mul(10080),
+        unit => return Err(format!("❌ Unknown expiry unit '{}'. Use: m, h, d, w", unit)),
+    };
+    
+    if minutes <= 0 || minutes > MAX_SWAP_EXPIRY_MINUTES {
+        return Err("❌ Expiry must be between 1 minute and 30 days".to_string());
+    }
+    
+    Ok(minutes)
+}
+
+/// Format minutes back into the shortest unit `parse_expiry` accepts
+pub fn format_expiry(minutes: i64) -> String {
+    if minutes % 10080 == 0 {
+        format!("{}w", minutes / 10080)
+    } else if minutes % 1440 == 0 {
+        format!("{}d", minutes / 1440)
+    } else if minutes % 60 == 0 {
+        format!("{}h", minutes / 60)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+/// Show or set the default expiry for swaps offering a currency
+/// `expiry` of None shows the current setting; "off" disables expiry (admin only to change)
+pub async fn set_default_expiry(
+    ctx: &Context,
+    invocation: &Invocation,
+    ticker: &str,
+    expiry: Option<&str>,
+) -> Result<String, String> {
+    let storage = crate::storage::from_ctx(ctx).await?;
+    
+    let (currency_id, currency_guild_id, _, currency_ticker) = storage.get_currency_by_ticker_with_guild(ticker)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or(format!("Currency {} not found", ticker))?;
+    
+    let Some(expiry) = expiry else {
+        let current = storage.get_swap_expiry_minutes(currency_id)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        return Ok(match current {
+            Some(minutes) => format!("⏱️ {} swaps expire after **{}** by default", currency_ticker, format_expiry(minutes)),
+            None => format!("⏱️ {} swaps do not expire by default", currency_ticker),
+        });
+    };
+    
+    // Only admins of the currency's guild can change its default
+    let target_guild_id = serenity::model::prelude::GuildId::new(currency_guild_id as u64);
+    crate::utils::check_user_roles(ctx, target_guild_id, invocation.user_id, &["admin"]).await?;
+    
+    let minutes = match expiry.to_lowercase().as_str() {
+        "off" | "none" | "never" => None,
+        _ => Some(parse_expiry(expiry)?),
+    };
+    
+    storage.set_swap_expiry_minutes(currency_id, minutes)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    
+    Ok(match minutes {
+        Some(minutes) => format!("✅ {} swaps now expire after **{}** by default", currency_ticker, format_expiry(minutes)),
+        None => format!("✅ {} swaps no longer expire by default", currency_ticker),
+    })
+}
+
+/// (id, maker_id, taker_id, maker_ticker, taker_ticker, maker_amount, taker_amount, status, filled_amount)
+pub type SwapListEntry = (i64, i64, Option<i64>, String, String, Amount, Amount, String, Amount);
+
 pub struct SwapListResult {
-    pub swaps: Vec<(i64, i64, Option<i64>, String, String, f64, f64, String)>,  // (id, maker_id, taker_id, maker_ticker, taker_ticker, maker_amount, taker_amount, status)
+    pub swaps: Vec<SwapListEntry>,
     pub current_page: usize,
     pub total_pages: usize,
     pub total_swaps: i64,
 }
 
 pub async fn get_swaps_list(
-    ctx: &Context,
+    storage: &dyn Storage,
     page: usize,
     sort_by: &str,
     status: &str,
@@ -608,46 +834,26 @@ pub async fn get_swaps_list(
     // Validate page number
     let page = if page < 1 { 1 } else { page };
     
-    // Get pool from context
-    let pool = {
-        let data = ctx.data.read().await;
-        data.get::<crate::DatabasePool>()
-            .ok_or("Database not initialized".to_string())?
-            .clone()
-    };
-    
     let page_size = 5;  // 5 swaps per page
     
-    // Validate sort_by
-    let sort_by_validated = match sort_by {
-        "oldest" | "latest" | "highmaker" | "lowmaker" | "hightaker" | "lowtaker" => sort_by,
-        _ => "latest",
-    };
-    
-    // Validate status
-    let status_validated = match status {
-        "pending" | "accepted" | "cancelled" | "all" => status,
-        _ => "pending",
+    // Unknown sort keys and statuses fall back to the defaults
+    let sort = SwapSort::parse(sort_by).unwrap_or_default();
+    let filter = SwapFilter {
+        status: SwapStatus::parse(status).unwrap_or(SwapStatus::Pending),
+        maker_ticker: base_currency.map(str::to_string),
+        taker_ticker: quote_currency.map(str::to_string),
     };
     
     // Call database function
-    let (raw_swaps, total_count) = db::swap::get_swaps_paginated(
-        &pool,
-        page,
-        page_size,
-        sort_by_validated,
-        status_validated,
-        base_currency,
-        quote_currency,
-    )
+    let (raw_swaps, total_count) = storage.get_swaps_paginated(page, page_size, sort, &filter)
     .await
     .map_err(|e| format!("Database error: {}", e))?;
     
     // Transform raw swaps to simplified format
     let swaps = raw_swaps
         .into_iter()
-        .map(|(id, maker_id, taker_id, _maker_currency_id, _taker_currency_id, maker_amount, taker_amount, status, maker_ticker, taker_ticker)| {
-            (id, maker_id, taker_id, maker_ticker, taker_ticker, maker_amount, taker_amount, status)
+        .map(|(id, maker_id, taker_id, _maker_currency_id, _taker_currency_id, maker_amount, taker_amount, status, maker_ticker, taker_ticker, filled_amount)| {
+            (id, maker_id, taker_id, maker_ticker, taker_ticker, maker_amount, taker_amount, status, filled_amount)
         })
         .collect();
     
@@ -665,6 +871,59 @@ pub async fn get_swaps_list(
     })
 }
 
+/// Swap list pages, fetched as the user navigates
+pub struct SwapListPages {
+    storage: Arc<dyn Storage>,
+    sort_by: String,
+    status: String,
+    base_currency: Option<String>,
+    quote_currency: Option<String>,
+}
+
+#[async_trait]
+impl PageSource for SwapListPages {
+    async fn fetch(&self, page: usize) -> Result<(serenity::builder::CreateEmbed, usize), String> {
+        let result = get_swaps_list(
+            self.storage.as_ref(),
+            page,
+            &self.sort_by,
+            &self.status,
+            self.base_currency.as_deref(),
+            self.quote_currency.as_deref(),
+        ).await?;
+        
+        let embed = create_swap_list_embed(
+            &result,
+            &self.sort_by,
+            &self.status,
+            self.base_currency.as_deref(),
+            self.quote_currency.as_deref(),
+        );
+        Ok((embed, result.total_pages))
+    }
+}
+
+/// Open the swap list at `page` with the given filters
+pub async fn list_swaps(
+    ctx: &Context,
+    page: usize,
+    sort_by: &str,
+    status: &str,
+    base_currency: Option<&str>,
+    quote_currency: Option<&str>,
+) -> Result<Page, String> {
+    let storage = crate::storage::from_ctx(ctx).await?;
+    
+    let source = SwapListPages {
+        storage,
+        sort_by: sort_by.to_string(),
+        status: status.to_string(),
+        base_currency: base_currency.map(|s| s.to_string()),
+        quote_currency: quote_currency.map(|s| s.to_string()),
+    };
+    Page::load(source, page).await
+}
+
 pub fn create_swap_list_embed(
     result: &SwapListResult,
     sort_by: &str,
@@ -682,16 +941,23 @@ pub fn create_swap_list_embed(
     
     // Build table data
     let mut table_data = Vec::new();
-    for (id, maker_id, taker_id, maker_ticker, taker_ticker, maker_amount, taker_amount, swap_status) in &result.swaps {
+    for (id, maker_id, taker_id, maker_ticker, taker_ticker, maker_amount, taker_amount, swap_status, filled_amount) in &result.swaps {
         let taker_str = if let Some(tid) = taker_id {
             format!("<@{}>", tid)
         } else {
             "Open".to_string()
         };
         
+        // Only pending swaps can be part-way filled
+        let fill_str = if swap_status == "pending" && filled_amount.is_positive() {
+            format!(" ({})", format_fill_progress(*filled_amount, *maker_amount, maker_ticker))
+        } else {
+            String::new()
+        };
+        
         let swap_line = format!(
-            "\n**ID:** `{}`\n**Maker:** <@{}> | **Taker:** {}\n**Offer:** `{:.2} {}` → `{:.2} {}`\n**Status:** {}\n",
-            id, maker_id, taker_str, maker_amount, maker_ticker, taker_amount, taker_ticker, swap_status
+            "\n**ID:** `{}`\n**Maker:** <@{}> | **Taker:** {}\n**Offer:** `{:.2} {}` → `{:.2} {}`\n**Status:** {}{}\n",
+            id, maker_id, taker_str, maker_amount, maker_ticker, taker_amount, taker_ticker, swap_status, fill_str
         );
         table_data.push(swap_line);
     }
@@ -728,20 +994,149 @@ pub fn create_swap_list_embed(
     
     embed = embed.field("Filters", filter_desc, false);
     
-    // Navigation instructions
-    if result.total_pages > 1 {
-        let mut nav_help = String::new();
-        if result.current_page > 1 {
-            nav_help.push_str(&format!("`$swap list ... p{}` to go to previous page\n", result.current_page - 1));
-        }
-        if result.current_page < result.total_pages {
-            nav_help.push_str(&format!("`$swap list ... p{}` to go to next page", result.current_page + 1));
-        }
-        if !nav_help.is_empty() {
-            embed = embed.field("Navigation", nav_help, false);
-        }
-    }
-    
     embed
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{amount, AccountStore, MemoryStorage, SwapStore};
+
+    const ALICE: i64 = 1;
+    const BOB: i64 = 2;
+    const CAROL: i64 = 3;
+
+    #[test]
+    fn test_parse_swap_button() {
+        assert_eq!(parse_swap_button("swap_accept:42"), Some((SwapAction::Accept, 42)));
+        assert_eq!(parse_swap_button("swap_deny:7"), Some((SwapAction::Deny, 7)));
+        assert_eq!(parse_swap_button("swap_accept:abc"), None);
+        assert_eq!(parse_swap_button("page_next"), None);
+    }
+
+    #[test]
+    fn test_format_fill_progress() {
+        let amount = |s: &str| s.parse::<Amount>().unwrap();
+        assert_eq!(
+            format_fill_progress(amount("25"), amount("100"), "ABC"),
+            "`25/100 ABC` filled (25.0%)"
+        );
+        assert_eq!(
+            format_fill_progress(amount("0.5"), amount("3"), "XYZ"),
+            "`0.5/3 XYZ` filled (16.7%)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_targeted_swap_escrows_and_settles() {
+        let storage = MemoryStorage::new();
+        let abc = storage.seed_currency("ABC", 10, &[(ALICE, "100")]);
+        let xyz = storage.seed_currency("XYZ", 0, &[(BOB, "50")]);
+
+        let err = create_swap(&storage, ALICE, (amount("40"), "ABC"), (amount("60"), "XYZ"), Some(BOB), None).await.err().unwrap();
+        assert_eq!(err, "Taker has insufficient XYZ balance");
+
+        // The maker's amount and its tax are escrowed up front
+        let swap = create_swap(&storage, ALICE, (amount("40"), "ABC"), (amount("20"), "XYZ"), Some(BOB), None).await.unwrap();
+        assert_eq!(storage.get_account_balance(ALICE, abc).await.unwrap(), Some(amount("56")));
+        assert_eq!(storage.total_supply(abc), amount("100"));
+
+        assert!(fill_swap(&storage, CAROL, swap.swap_id, None).await.err().unwrap().contains("not authorized"));
+        assert!(fill_swap(&storage, BOB, swap.swap_id, Some(amount("10"))).await.err().unwrap().contains("Only open swaps"));
+
+        let result = fill_swap(&storage, BOB, swap.swap_id, None).await.unwrap();
+        assert_eq!((result.status.as_str(), result.remaining), ("accepted", None));
+        assert_eq!(storage.get_account_balance(BOB, abc).await.unwrap(), Some(amount("40")));
+        assert_eq!(storage.get_account_balance(BOB, xyz).await.unwrap(), Some(amount("30")));
+        assert_eq!(storage.get_account_balance(ALICE, xyz).await.unwrap(), Some(amount("20")));
+        assert_eq!(storage.trades(), vec![(abc, xyz, amount("0.5"), amount("40"), amount("20"))]);
+
+        let err = fill_swap(&storage, BOB, swap.swap_id, None).await.err().unwrap();
+        assert_eq!(err, "❌ This swap has already been accepted!");
+    }
+
+    #[tokio::test]
+    async fn test_partial_fills_round_up_and_last_fill_pays_the_rest() {
+        let storage = MemoryStorage::new();
+        let abc = storage.seed_currency("ABC", 0, &[(ALICE, "3")]);
+        let xyz = storage.seed_currency("XYZ", 0, &[(BOB, "1")]);
+
+        let swap = create_swap(&storage, ALICE, (amount("3"), "ABC"), (amount("1"), "XYZ"), None, None).await.unwrap();
+        assert!(fill_swap(&storage, ALICE, swap.swap_id, None).await.err().unwrap().contains("your own open swap"));
+
+        let first = fill_swap(&storage, BOB, swap.swap_id, Some(amount("1"))).await.unwrap();
+        assert_eq!(first.status, "partially filled");
+        assert_eq!(first.remaining.as_deref(), Some("2 ABC"));
+        fill_swap(&storage, BOB, swap.swap_id, Some(amount("1"))).await.unwrap();
+
+        let err = fill_swap(&storage, BOB, swap.swap_id, Some(amount("2"))).await.err().unwrap();
+        assert_eq!(err, "Fill amount exceeds the remaining amount of the swap");
+
+        fill_swap(&storage, BOB, swap.swap_id, None).await.unwrap();
+        let paid: Vec<Amount> = storage.trades().into_iter().map(|trade| trade.4).collect();
+        assert_eq!(paid, vec![amount("0.33333334"), amount("0.33333334"), amount("0.33333332")]);
+        assert_eq!(storage.get_account_balance(BOB, xyz).await.unwrap(), Some(Amount::ZERO));
+        assert_eq!(storage.get_account_balance(BOB, abc).await.unwrap(), Some(amount("3")));
+        assert_eq!(storage.get_swap_fill_progress(swap.swap_id).await.unwrap(), Some((amount("3"), 3)));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_refunds_unfilled_part() {
+        let storage = MemoryStorage::new();
+        let abc = storage.seed_currency("ABC", 0, &[(ALICE, "100")]);
+        storage.seed_currency("XYZ", 0, &[(BOB, "100")]);
+
+        let swap = create_swap(&storage, ALICE, (amount("10"), "ABC"), (amount("10"), "XYZ"), None, None).await.unwrap();
+        fill_swap(&storage, BOB, swap.swap_id, Some(amount("4"))).await.unwrap();
+
+        assert!(cancel_swap(&storage, BOB, swap.swap_id).await.err().unwrap().contains("Only the maker"));
+        let result = cancel_swap(&storage, ALICE, swap.swap_id).await.unwrap();
+        assert_eq!(result.status, "cancelled");
+        assert_eq!(storage.get_account_balance(ALICE, abc).await.unwrap(), Some(amount("96")));
+        assert_eq!(storage.total_supply(abc), amount("100"));
+
+        let err = fill_swap(&storage, BOB, swap.swap_id, None).await.err().unwrap();
+        assert_eq!(err, "❌ This swap has been cancelled!");
+    }
+
+    #[tokio::test]
+    async fn test_expired_swap_is_refunded() {
+        let storage = MemoryStorage::new();
+        let abc = storage.seed_currency("ABC", 0, &[(ALICE, "100")]);
+        storage.seed_currency("XYZ", 0, &[(BOB, "100")]);
+
+        let swap = create_swap(&storage, ALICE, (amount("10"), "ABC"), (amount("5"), "XYZ"), None, Some(60)).await.unwrap();
+        assert!(storage.get_overdue_swaps(10).await.unwrap().is_empty());
+
+        storage.advance_minutes(61);
+        let err = fill_swap(&storage, BOB, swap.swap_id, None).await.err().unwrap();
+        assert_eq!(err, "Swap has expired");
+
+        let overdue = storage.get_overdue_swaps(10).await.unwrap();
+        assert_eq!(overdue.len(), 1);
+        storage.expire_swap(overdue[0].0).await.unwrap();
+        assert_eq!(storage.get_account_balance(ALICE, abc).await.unwrap(), Some(amount("100")));
+        assert!(storage.get_overdue_swaps(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_swaps_list_filters_and_sorts() {
+        let storage = MemoryStorage::new();
+        storage.seed_currency("ABC", 0, &[(ALICE, "100")]);
+        storage.seed_currency("XYZ", 0, &[(ALICE, "100"), (BOB, "100")]);
+
+        create_swap(&storage, ALICE, (amount("10"), "ABC"), (amount("1"), "XYZ"), None, None).await.unwrap();
+        create_swap(&storage, ALICE, (amount("30"), "ABC"), (amount("1"), "XYZ"), None, None).await.unwrap();
+        let cancelled = create_swap(&storage, ALICE, (amount("20"), "XYZ"), (amount("1"), "ABC"), None, None).await.unwrap();
+        cancel_swap(&storage, ALICE, cancelled.swap_id).await.unwrap();
+
+        let list = get_swaps_list(&storage, 1, "highmaker", "pending", Some("abc"), None).await.unwrap();
+        let maker_amounts: Vec<Amount> = list.swaps.iter().map(|swap| swap.5).collect();
+        assert_eq!(maker_amounts, vec![amount("30"), amount("10")]);
+        assert_eq!((list.total_swaps, list.total_pages), (2, 1));
+
+        let list = get_swaps_list(&storage, 1, "latest", "cancelled", None, Some("ABC")).await.unwrap();
+        assert_eq!(list.swaps.iter().map(|swap| swap.0).collect::<Vec<_>>(), vec![cancelled.swap_id]);
+    }
+}
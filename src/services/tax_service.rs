@@ -1,11 +1,12 @@
-use sqlx::mysql::MySqlPool;
 use serenity::model::channel::Message;
 use serenity::prelude::Context;
-use crate::db;
+use crate::storage::Storage;
+use crate::utils::Amount;
+use crate::utils::errors::LedgerError;
 
 /// Set tax percentage for a currency
 pub async fn set_tax(
-    pool: &MySqlPool,
+    storage: &dyn Storage,
     currency_id: i64,
     tax_percentage: i32,
     ticker: &str,
@@ -16,10 +17,10 @@ pub async fn set_tax(
     }
 
     // Check if tax account exists
-    match db::tax::get_tax_account(pool, currency_id).await {
+    match storage.get_tax_account(currency_id).await {
         Ok(Some(_)) => {
             // Update existing tax account
-            db::tax::set_tax_percentage(pool, currency_id, tax_percentage)
+            storage.set_tax_percentage(currency_id, tax_percentage)
                 .await
                 .map_err(|e| format!("Database error: {}", e))?;
             
@@ -27,7 +28,7 @@ pub async fn set_tax(
         },
         Ok(None) => {
             // Create new tax account
-            db::tax::create_tax_account(pool, currency_id, tax_percentage)
+            storage.create_tax_account(currency_id, tax_percentage)
                 .await
                 .map_err(|e| format!("Database error: {}", e))?;
             
@@ -41,20 +42,20 @@ pub async fn set_tax(
 
 /// Collect tax from a currency's tax account
 pub async fn collect_tax(
-    pool: &MySqlPool,
+    storage: &dyn Storage,
     user_id: i64,
     currency_id: i64,
     amount: Option<String>,
 ) -> Result<String, String> {
     // Get tax account
-    let tax_account = db::tax::get_tax_account(pool, currency_id)
+    let tax_account = storage.get_tax_account(currency_id)
         .await
         .map_err(|e| format!("Database error: {}", e))?
         .ok_or("❌ No tax account found for this currency")?;
 
     let current_balance = tax_account.2;
 
-    if current_balance <= 0.0 {
+    if !current_balance.is_positive() {
         return Err("❌ No taxes to collect".to_string());
     }
 
@@ -63,14 +64,14 @@ pub async fn collect_tax(
         if amt_str.to_lowercase() == "all" {
             current_balance
         } else {
-            amt_str.parse::<f64>()
-                .map_err(|_| "❌ Invalid amount".to_string())?
+            amt_str.parse::<Amount>()
+                .map_err(|e| format!("❌ {}", e))?
         }
     } else {
         current_balance
     };
 
-    if collect_amount <= 0.0 {
+    if !collect_amount.is_positive() {
         return Err("❌ Collection amount must be positive".to_string());
     }
 
@@ -81,15 +82,17 @@ pub async fn collect_tax(
         ))?;
     }
 
-    // Collect tax
-    let collected = db::tax::collect_tax(pool, currency_id, collect_amount)
+    // Move the tax into the collector's account in one locked transaction
+    let collected = storage.collect_tax(user_id, currency_id, Some(collect_amount))
         .await
-        .map_err(|e| format!("Database error: {}", e))?;
-
-    // Add collected amount to user's account for this currency
-    db::account::add_balance(pool, user_id, currency_id, collected)
-        .await
-        .map_err(|e| format!("Failed to add tax to account: {}", e))?;
+        .map_err(|e| match e {
+            LedgerError::InsufficientBalance { available, .. } => format!(
+                "❌ Insufficient tax balance. Available: {:.2}",
+                available
+            ),
+            LedgerError::TaxAccountNotFound => "❌ No tax account found for this currency".to_string(),
+            other => format!("Failed to collect tax: {}", other),
+        })?;
 
     Ok(format!(
         "✅ Collected {:.2} tax and added to your account",
@@ -99,10 +102,10 @@ pub async fn collect_tax(
 
 /// Get tax information for a currency
 pub async fn get_tax_info(
-    pool: &MySqlPool,
+    storage: &dyn Storage,
     currency_id: i64,
 ) -> Result<String, String> {
-    match db::tax::get_tax_account(pool, currency_id).await {
+    match storage.get_tax_account(currency_id).await {
         Ok(Some(tax_account)) => {
             let balance = tax_account.2;
             let percentage = tax_account.3;
@@ -122,3 +125,44 @@ pub async fn get_tax_info(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{amount, AccountStore, CurrencyStore, MemoryStorage};
+
+    const ALICE: i64 = 1;
+    const BOB: i64 = 2;
+    const COLLECTOR: i64 = 3;
+
+    #[tokio::test]
+    async fn test_set_tax() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.create_currency(1, "Test Coin", "ABC").await.unwrap();
+
+        assert!(get_tax_info(&storage, currency_id).await.is_err());
+        assert_eq!(
+            set_tax(&storage, currency_id, 5, "ABC").await.unwrap(),
+            "✅ Tax account created with 5% tax for ABC"
+        );
+        assert_eq!(set_tax(&storage, currency_id, 10, "ABC").await.unwrap(), "✅ Tax set to 10% for ABC");
+        assert!(set_tax(&storage, currency_id, 101, "ABC").await.is_err());
+        assert!(get_tax_info(&storage, currency_id).await.unwrap().contains("Percentage: **10%**"));
+    }
+
+    #[tokio::test]
+    async fn test_collect_tax() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 10, &[(ALICE, "100")]);
+
+        assert_eq!(collect_tax(&storage, COLLECTOR, currency_id, None).await.unwrap_err(), "❌ No taxes to collect");
+
+        storage.transfer(ALICE, BOB, currency_id, amount("50"), None).await.unwrap();
+        assert!(collect_tax(&storage, COLLECTOR, currency_id, Some("6".to_string())).await.is_err());
+        collect_tax(&storage, COLLECTOR, currency_id, Some("2".to_string())).await.unwrap();
+        collect_tax(&storage, COLLECTOR, currency_id, Some("all".to_string())).await.unwrap();
+
+        assert_eq!(storage.get_account_balance(COLLECTOR, currency_id).await.unwrap(), Some(amount("5")));
+        assert_eq!(storage.total_supply(currency_id), amount("100"));
+    }
+}
@@ -1,6 +1,11 @@
+use std::sync::Arc;
 use sqlx::mysql::MySqlPool;
+use serenity::async_trait;
 use serenity::builder::CreateEmbed;
+use crate::utils::page::PageSource;
 use crate::db;
+use crate::storage::Storage;
+use crate::utils::Amount;
 
 pub struct TransactionListResult {
     pub formatted_message: String,
@@ -10,15 +15,16 @@ pub struct TransactionListResult {
 pub struct TransactionDetailResult {
     pub sender_discord_id: i64,
     pub receiver_discord_id: i64,
-    pub amount: f64,
+    pub amount: Amount,
     pub date: String,
+    pub memo: Option<String>,
 }
 
 /// Get all transactions for pagination (no limit)
 pub async fn get_transaction_list_for_pagination(
     pool: &MySqlPool,
     user_id: i64,
-) -> Result<Vec<(i64, i64, f64, String, String, String)>, String> {
+) -> Result<Vec<(i64, i64, Amount, String, String, String)>, String> {
     // Get all transactions for the user (as sender or receiver)
     db::transaction::get_user_transactions(pool, user_id, 1000)
         .await
@@ -26,15 +32,17 @@ pub async fn get_transaction_list_for_pagination(
 }
 
 /// Create paginated embeds for transactions (10 per page) - with OFFSET/LIMIT
+/// With `memo_search`, only transactions whose memo contains that text are listed
 pub async fn create_transaction_pages(
-    pool: &MySqlPool,
+    storage: &dyn Storage,
     user_id: i64,
     page: usize,
+    memo_search: Option<&str>,
 ) -> Result<(Vec<CreateEmbed>, usize), String> {
     const TRANSACTIONS_PER_PAGE: usize = 10;
     
     // Fetch paginated transactions from database
-    let (transactions, total_count) = db::transaction::get_user_transactions_paginated(pool, user_id, page, TRANSACTIONS_PER_PAGE)
+    let (transactions, total_count) = storage.get_user_transactions_paginated(user_id, page, TRANSACTIONS_PER_PAGE, memo_search)
         .await
         .map_err(|e| format!("Failed to fetch transactions: {}", e))?;
 
@@ -43,9 +51,13 @@ pub async fn create_transaction_pages(
     let mut pages = Vec::new();
 
     if transactions.is_empty() && page == 1 {
+        let description = match memo_search {
+            Some(search) => format!("No transactions with a memo matching \"{}\"", search),
+            None => "No transactions found".to_string(),
+        };
         let embed = CreateEmbed::default()
             .title("📋 Transaction History")
-            .description("No transactions found")
+            .description(description)
             .color(0xffa500);
         pages.push(embed);
         return Ok((pages, total_pages));
@@ -55,15 +67,18 @@ pub async fn create_transaction_pages(
         return Err(format!("❌ Invalid page number. This command has {} page(s)", total_pages));
     }
 
-    let mut description = String::new();
+    let mut description = match memo_search {
+        Some(search) => format!("Memos matching \"{}\"\n\n", search),
+        None => String::new(),
+    };
 
     for tx in &transactions {
-        // tx is (sender_id, receiver_id, amount, date, uuid, currency_ticker)
-        let sender_discord_id = db::account::get_discord_id_by_account_id(pool, tx.0)
+        // tx is (sender_id, receiver_id, amount, date, uuid, currency_ticker, memo)
+        let sender_discord_id = storage.get_discord_id_by_account_id(tx.0)
             .await
             .unwrap_or(None)
             .unwrap_or(tx.0);
-        let receiver_discord_id = db::account::get_discord_id_by_account_id(pool, tx.1)
+        let receiver_discord_id = storage.get_discord_id_by_account_id(tx.1)
             .await
             .unwrap_or(None)
             .unwrap_or(tx.1);
@@ -72,7 +87,11 @@ pub async fn create_transaction_pages(
             "<@{}> → <@{}> | `{:.2} {}`\n",
             sender_discord_id, receiver_discord_id, tx.2, tx.5
         ));
-        description.push_str(&format!("└─ `{}`\n\n", tx.4));
+        description.push_str(&format!("└─ `{}`\n", tx.4));
+        if let Some(memo) = &tx.6 {
+            description.push_str(&format!("└─ 📝 {}\n", memo));
+        }
+        description.push('\n');
     }
 
     let embed = CreateEmbed::default()
@@ -88,6 +107,23 @@ pub async fn create_transaction_pages(
     Ok((pages, total_pages))
 }
 
+/// A user's transaction history, fetched a page at a time as they navigate
+pub struct TransactionHistory {
+    pub storage: Arc<dyn Storage>,
+    pub user_id: i64,
+    /// Only list transactions whose memo contains this text
+    pub memo_search: Option<String>,
+}
+
+#[async_trait]
+impl PageSource for TransactionHistory {
+    async fn fetch(&self, page: usize) -> Result<(CreateEmbed, usize), String> {
+        let (mut embeds, total_pages) = create_transaction_pages(self.storage.as_ref(), self.user_id, page, self.memo_search.as_deref()).await?;
+        let embed = embeds.pop().ok_or("No transactions found".to_string())?;
+        Ok((embed, total_pages))
+    }
+}
+
 /// Get formatted transaction list (top 10 most recent)
 pub async fn get_transaction_list(
     pool: &MySqlPool,
@@ -134,22 +170,22 @@ pub async fn get_transaction_list(
 
 /// Get formatted transaction details by UUID
 pub async fn get_transaction_detail(
-    pool: &MySqlPool,
+    storage: &dyn Storage,
     uuid: &str,
 ) -> Result<TransactionDetailResult, String> {
     // Fetch specific transaction
-    let transaction = db::transaction::get_transaction_by_uuid(pool, uuid)
+    let transaction = storage.get_transaction_by_uuid(uuid)
         .await
         .map_err(|e| format!("Failed to fetch transaction: {}", e))?
         .ok_or("❌ Transaction not found".to_string())?;
 
     // Get sender and receiver Discord IDs
-    let sender_discord_id = db::account::get_discord_id_by_account_id(pool, transaction.0)
+    let sender_discord_id = storage.get_discord_id_by_account_id(transaction.0)
         .await
         .map_err(|e| format!("Database error: {}", e))?
         .ok_or("Sender not found".to_string())?;
 
-    let receiver_discord_id = db::account::get_discord_id_by_account_id(pool, transaction.1)
+    let receiver_discord_id = storage.get_discord_id_by_account_id(transaction.1)
         .await
         .map_err(|e| format!("Database error: {}", e))?
         .ok_or("Receiver not found".to_string())?;
@@ -159,5 +195,6 @@ pub async fn get_transaction_detail(
         receiver_discord_id,
         amount: transaction.3,
         date: transaction.2,
+        memo: transaction.5,
     })
 }
@@ -0,0 +1,46 @@
+//! Wire Recovery Service - Background worker that settles stuck wire transfers
+//!
+//! A wire the bot stopped half way through (an UnbelievaBoat failure whose
+//! compensation also failed, or a restart mid-transfer) stays 'initiated' or
+//! 'smite_applied' in the journal. The worker runs once on startup and then
+//! periodically, finishing or reversing each with `wire_service::recover_unsettled_wires`.
+//! Wires it can't decide on are left for `$wire reconcile`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use serenity::prelude::Context;
+use tracing::{debug, error, info, warn};
+use crate::services::wire_service;
+
+/// How often the worker looks for stuck wires
+const RECOVERY_INTERVAL: Duration = Duration::from_secs(300);
+
+static WORKER_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Start the recovery worker. Only the first call spawns it, so this is safe to
+/// call from every shard's ready event.
+pub fn start_worker(ctx: Context) {
+    if WORKER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    tokio::spawn(async move {
+        info!("Wire recovery worker started");
+        // The first tick fires immediately, so anything left by the last run is settled on startup
+        let mut interval = tokio::time::interval(RECOVERY_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            match wire_service::recover_unsettled_wires(&ctx).await {
+                Ok((0, 0)) => debug!("No stuck wires"),
+                Ok((resolved, 0)) => info!("Recovered {} stuck wire(s)", resolved),
+                Ok((resolved, needs_review)) => warn!(
+                    "Recovered {} stuck wire(s); {} need `$wire reconcile`",
+                    resolved, needs_review
+                ),
+                Err(e) => error!("Wire recovery run failed: {}", e),
+            }
+        }
+    });
+}
@@ -1,39 +1,202 @@
-//! Wire Service - Core bridge logic for SMITE ↔ UnbelievaBoat transfers
+//! Wire Service - Core bridge logic for transfers between SMITE and another economy bot
+//!
+//! UnbelievaBoat is the default provider; a currency can instead wire to any
+//! economy bot with a JSON HTTP API (see `api::bridge`). Everything here runs
+//! against `EconomyBridge`, so the journal and recovery work the same for both.
 //!
 //! DISCLAIMER: This implementation does NOT violate UnbelievaBoat's Terms of Service.
 //! This module uses the official UnbelievaBoat API with explicit authentication tokens.
 //! Wire transfers are NOT automation - they result from intentional, manual user commands.
-//! Every transfer requires explicit user invocation; there are no background processes
-//! or scheduled tasks performing automated transactions. API calls are direct responses
-//! to user-initiated commands, making this a legitimate integration, not a violation.
+//! Every transfer requires explicit user invocation; the only background task is the
+//! recovery worker, which finishes or reverses transfers users already started and
+//! never starts one. API calls are direct responses to user-initiated commands,
+//! making this a legitimate integration, not a violation.
+//!
+//! Every transfer is journalled in `wire_transfer` before either side changes. The
+//! SMITE balance moves in the same transaction as the journal state, and the
+//! provider's bank balance is recorded just before it is changed. Both sides
+//! only ever move by the transfer amount, and undoing one is the opposite move,
+//! so activity on either side during a transfer is never overwritten. A change the
+//! provider refuses is reversed on SMITE straight away. One it doesn't confirm, like
+//! a timeout or a 5xx, may still have happened, so that wire is left for
+//! `recover_unsettled_wires` to finish or reverse from the recorded bank balance,
+//! as is any wire cut short by a restart. An admin can also settle it with
+//! `$wire reconcile`.
+//!
+//! Each currency's `WireConfig` sets the conversion rate, per-transfer limits,
+//! a bridge fee paid into the tax account and how SMITE amounts round to whole
+//! units on the provider. `quote` works a transfer out from it, and the user sees
+//! that quote and confirms it before anything moves.
 
-use serenity::model::channel::Message;
+use std::sync::Arc;
+use crate::utils::Invocation;
+use serenity::builder::{CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter};
+use serenity::model::prelude::ButtonStyle;
 use serenity::prelude::Context;
-use crate::db;
-use crate::api::unbelievaboat::UnbelievaboatClient;
+use crate::api::bridge::{BridgeProvider, EconomyBridge};
+use crate::api::unbelievaboat::models::ApiError;
+use crate::db::wire::WireConfig;
+use crate::storage::{NewWire, Storage, WireRow};
+use crate::utils::page::Page;
 use crate::utils::{encrypt_token, decrypt_token};
-use crate::utils::errors::WireError;
+use crate::utils::{Amount, Rounding};
+use crate::utils::errors::{LedgerError, WireError};
 use tracing;
 
+/// Journal states, see `db::wire`
+const STATE_INITIATED: &str = "initiated";
+const STATE_SMITE_APPLIED: &str = "smite_applied";
+const STATE_REMOTE_APPLIED: &str = "remote_applied";
+const STATE_COMPENSATED: &str = "compensated";
+const STATE_FAILED: &str = "failed";
+
+/// How long a wire must sit unchanged before it counts as stuck rather than in flight
+pub const WIRE_STUCK_AFTER_SECONDS: i64 = 300;
+
+/// Maximum stuck wires settled per recovery run
+const RECOVERY_BATCH_SIZE: i64 = 50;
+
+/// Wires per page in `$wire history` and `$wire reconcile`, and the most that are listed
+const WIRES_PER_PAGE: usize = 10;
+const MAX_LISTED_WIRES: i64 = 50;
+
+/// How long a quote can be confirmed for
+pub const QUOTE_VALID_SECONDS: i64 = 120;
+
+/// How often a rate-limited request is sent again, and the longest `retry_after`
+/// (ms) waited out; a longer one fails the request straight away
+const RATE_LIMIT_RETRIES: usize = 2;
+const MAX_RETRY_AFTER_MS: i64 = 5_000;
+
+/// Button custom ID prefixes. Confirm is followed by
+/// `{user_id}:{in|out}:{smite_amount}:{remote_amount}:{TICKER}`, Cancel by the user ID.
+const CONFIRM_BUTTON_PREFIX: &str = "wire_confirm:";
+const CANCEL_BUTTON_PREFIX: &str = "wire_cancel:";
+
 /// Direction of wire transfer
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WireDirection {
-    /// Transfer from UnbelievaBoat to SMITE (add to SMITE)
+    /// Transfer from the provider to SMITE (add to SMITE)
     In,
-    /// Transfer from SMITE to UnbelievaBoat (subtract from SMITE)
+    /// Transfer from SMITE to the provider (subtract from SMITE)
     Out,
 }
 
+impl WireDirection {
+    /// As stored in the journal
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WireDirection::In => "in",
+            WireDirection::Out => "out",
+        }
+    }
+
+    pub fn parse(direction: &str) -> Option<Self> {
+        match direction {
+            "in" => Some(WireDirection::In),
+            "out" => Some(WireDirection::Out),
+            _ => None,
+        }
+    }
+}
+
+/// What a wire moves on each side, worked out from the currency's `WireConfig`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WireQuote {
+    pub direction: WireDirection,
+    /// What the SMITE balance moves by: the amount credited for "in", debited for "out"
+    pub smite_amount: Amount,
+    /// Bridge fee paid into the tax account, taken from what the user sends
+    pub fee: Amount,
+    /// Whole units moved on the provider
+    pub remote_amount: i64,
+    /// SMITE units per provider unit
+    pub rate: Amount,
+}
+
 pub struct WireResult {
-    pub smite_balance: f64,
-    pub ub_balance: i64,
+    pub wire_id: i64,
+    pub quote: WireQuote,
+    /// Name of the economy on the other side
+    pub provider: String,
+    pub smite_balance: Amount,
+    pub remote_balance: i64,
+}
+
+/// A pressed quote button
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WireButton {
+    /// Run the quoted wire. Only the amounts are carried, so the quote is worked out
+    /// again and must still match.
+    Confirm { user_id: u64, direction: WireDirection, smite_amount: Amount, remote_amount: i64, ticker: String },
+    Cancel { user_id: u64 },
+}
+
+/// Work out a wire. For "in" `amount` is the whole provider units sent; for
+/// "out" it is the SMITE amount sent. The limits apply to the SMITE value before the
+/// fee, and the fee is rounded down.
+pub fn quote(config: &WireConfig, direction: WireDirection, amount: Amount) -> Result<WireQuote, WireError> {
+    if !amount.is_positive() {
+        return Err(WireError::InvalidAmount("Amount must be greater than 0.".to_string()));
+    }
+
+    let gross = match direction {
+        WireDirection::In => {
+            // What leaves the provider, so it has to be whole
+            let units = amount
+                .whole_part()
+                .filter(|_| amount.is_whole())
+                .ok_or(WireError::InvalidAmount(format!("The other economy only takes whole amounts, got {}", amount)))?;
+            Amount::from_whole(units)
+                .checked_mul_floor(config.rate)
+                .filter(Amount::is_within_limit)
+                .ok_or(WireError::InvalidAmount(format!("{} is too large to wire", amount)))?
+        }
+        WireDirection::Out => amount,
+    };
+
+    if let Some(min) = config.min_amount.filter(|min| gross < *min) {
+        return Err(WireError::InvalidAmount(format!("Wires must be worth at least {}, this one is worth {}", min, gross)));
+    }
+    if let Some(max) = config.max_amount.filter(|max| gross > *max) {
+        return Err(WireError::InvalidAmount(format!("Wires can be worth at most {}, this one is worth {}", max, gross)));
+    }
+
+    let fee = gross
+        .checked_mul_ratio_floor(config.fee_percent, Amount::from_whole(100))
+        .map(|percent_fee| percent_fee + config.fee_flat)
+        .filter(|fee| *fee < gross)
+        .ok_or(WireError::InvalidAmount(format!("The bridge fee would take all of {}", gross)))?;
+    let net = gross - fee;
+
+    let (smite_amount, remote_amount) = match direction {
+        WireDirection::In => (net, amount.whole_part().unwrap_or_default()),
+        WireDirection::Out => {
+            let units = net.checked_div_whole(config.rate, config.rounding).unwrap_or_default();
+            if units < 1 {
+                return Err(WireError::InvalidAmount(format!(
+                    "{} after the fee is less than one unit on the other economy ({} each)",
+                    net, config.rate
+                )));
+            }
+            (amount, units)
+        }
+    };
+
+    Ok(WireQuote {
+        direction,
+        smite_amount,
+        fee,
+        remote_amount,
+        rate: config.rate,
+    })
 }
 
-/// Set UnbelievaBoat API token for a currency (admin only, DM-only for security)
+/// Set the API token for a currency's wire provider (admin only, DM-only for security)
 /// User must have admin permissions in the target guild
 pub async fn set_api_token(
     ctx: &Context,
-    msg: &Message,
+    invocation: &Invocation,
     guild_id_arg: Option<u64>,
     token: &str,
 ) -> Result<(), WireError> {
@@ -45,26 +208,20 @@ pub async fn set_api_token(
 
     // Verify user has admin permissions in the target guild
     let target_guild_id = serenity::model::prelude::GuildId::new(guild_id as u64);
-    crate::utils::check_user_roles(ctx, target_guild_id, msg.author.id, &["admin"])
+    crate::utils::check_user_roles(ctx, target_guild_id, invocation.user_id, &["admin"])
         .await
-        .map_err(|e| WireError::InvalidConfig(e))?;
-
-    // Get pool from context
-    let pool = {
-        let data = ctx.data.read().await;
-        data.get::<crate::DatabasePool>()
-            .ok_or(WireError::Database("Database not initialized".to_string()))?
-            .clone()
-    };
+        .map_err(WireError::InvalidConfig)?;
+
+    let storage = crate::storage::from_ctx(ctx).await.map_err(WireError::Database)?;
 
     // Get the guild's currency
-    let currency_data = db::currency::get_currency_by_guild(&pool, guild_id)
+    let currency_data = storage.get_currency_by_guild(guild_id)
         .await
         .map_err(|e| WireError::Database(format!("Database error: {}", e)))?
         .ok_or(WireError::InvalidConfig("No currency found for this guild".to_string()))?;
 
     let currency_id = currency_data.0;
-
+    let provider = bridge_provider(storage.as_ref(), currency_id).await?;
 
     // Get encryption key from environment
     let encryption_key = std::env::var("TOKEN_ENCRYPTION_KEY")
@@ -73,314 +230,1257 @@ pub async fn set_api_token(
     // Encrypt the token (CryptoError is automatically converted via #[from])
     let encrypted_token = encrypt_token(token, &encryption_key)?;
 
-    // Store encrypted token in database
-    db::api::store_api_token(&pool, currency_id, 1, &encrypted_token)
+    // Store encrypted token in database, for the provider the currency uses now
+    storage.store_api_token(currency_id, provider.api_type_id(), &encrypted_token)
         .await
         .map_err(|e| WireError::Database(format!("Failed to store token: {}", e)))?;
 
     Ok(())
 }
 
-/// Core wire transfer function for both directions
-/// ATOMIC: All DB operations wrapped in a transaction; compensating transaction on API failure
-async fn execute_wire_transfer(
+/// Quote a wire for the user to confirm: (quote, ticker, provider name)
+pub async fn quote_wire(
     ctx: &Context,
-    msg: &Message,
     direction: WireDirection,
-    amount: f64,
+    amount: Amount,
+    currency_ticker: &str,
+) -> Result<(WireQuote, String, String), WireError> {
+    let storage = crate::storage::from_ctx(ctx).await.map_err(WireError::Database)?;
+    let (currency_id, _, _, ticker) = find_currency(storage.as_ref(), currency_ticker).await?;
+    let config = wire_config(storage.as_ref(), currency_id).await?;
+    let provider = bridge_provider(storage.as_ref(), currency_id).await?;
+
+    Ok((quote(&config, direction, amount)?, ticker, provider.name().to_string()))
+}
+
+/// Run a wire the invoking user confirmed. It is quoted again with the current
+/// settings, and refused if what either side moves has changed.
+pub async fn confirm_wire(
+    ctx: &Context,
+    invocation: &Invocation,
+    direction: WireDirection,
+    smite_amount: Amount,
+    remote_amount: i64,
     currency_ticker: &str,
 ) -> Result<WireResult, WireError> {
-    let user_id = msg.author.id.get() as i64;
-
-    // Get pool from context
-    let pool = {
-        let data = ctx.data.read().await;
-        data.get::<crate::DatabasePool>()
-            .ok_or(WireError::Database("Database not initialized".to_string()))?
-            .clone()
+    let storage = crate::storage::from_ctx(ctx).await.map_err(WireError::Database)?;
+    let (currency_id, currency_guild_id, _, _) = find_currency(storage.as_ref(), currency_ticker).await?;
+    let config = wire_config(storage.as_ref(), currency_id).await?;
+
+    let amount = match direction {
+        WireDirection::In => Amount::from_whole(remote_amount),
+        WireDirection::Out => smite_amount,
     };
+    let quote = quote(&config, direction, amount)?;
+    if quote.smite_amount != smite_amount || quote.remote_amount != remote_amount {
+        return Err(WireError::InvalidConfig(
+            "The wire settings changed since this quote. Run the command again for a new one.".to_string()
+        ));
+    }
+
+    let bridge = connect_bridge(storage.as_ref(), currency_id).await?;
+
+    // Use currency's guild_id for the provider's API
+    // This ensures we're always talking to the correct guild
+    transfer(
+        storage.as_ref(),
+        bridge.as_ref(),
+        currency_guild_id as u64,
+        invocation.user_id.get(),
+        currency_id,
+        &quote,
+    )
+    .await
+}
 
-    // Verify currency exists in SMITE
-    let (currency_id, currency_guild_id, _, _) = db::currency::get_currency_by_ticker_with_guild(&pool, currency_ticker)
+/// Verify currency exists in SMITE: (id, guild_id, name, ticker)
+async fn find_currency(storage: &dyn Storage, currency_ticker: &str) -> Result<(i64, i64, String, String), WireError> {
+    storage.get_currency_by_ticker_with_guild(currency_ticker)
         .await
         .map_err(|e| WireError::Database(format!("Database error: {}", e)))?
-        .ok_or(WireError::InvalidConfig(format!("Currency {} not found in SMITE", currency_ticker)))?;
+        .ok_or(WireError::InvalidConfig(format!("Currency {} not found in SMITE", currency_ticker)))
+}
 
-    // Get UnbelievaBoat API token from database
-    let encrypted_token = db::api::get_api_token(&pool, currency_id, 1)
+/// The currency's wire settings, or 1:1 with no fee if they were never set
+async fn wire_config(storage: &dyn Storage, currency_id: i64) -> Result<WireConfig, WireError> {
+    Ok(storage.get_wire_config(currency_id)
         .await
         .map_err(|e| WireError::Database(format!("Database error: {}", e)))?
-        .ok_or(WireError::InvalidConfig("UnbelievaBoat API token not configured for this currency".to_string()))?;
-
-    // Decrypt the token (CryptoError is automatically converted via #[from])
-    let encryption_key = std::env::var("TOKEN_ENCRYPTION_KEY")
-        .map_err(|_| WireError::InvalidConfig("TOKEN_ENCRYPTION_KEY not set in environment".to_string()))?;
-    let ub_token = decrypt_token(&encrypted_token, &encryption_key)?;
-
-    // Initialize UnbelievaBoat client
-    let ub_client = UnbelievaboatClient::new(ub_token);
+        .unwrap_or_default())
+}
 
-    // Use currency's guild_id for UnbelievaBoat API
-    // This ensures we're always talking to the correct UnbelievaBoat guild
-    let guild_id = currency_guild_id as u64;
+/// Run a wire for a user against a guild on the bridged economy, journalling each step
+pub async fn transfer(
+    storage: &dyn Storage,
+    bridge: &dyn EconomyBridge,
+    guild_id: u64,
+    user_id: u64,
+    currency_id: i64,
+    quote: &WireQuote,
+) -> Result<WireResult, WireError> {
+    let direction = quote.direction;
+    let remote_amount = quote.remote_amount;
 
-    // DIRECTION-SPECIFIC LOGIC: Check source balance and prepare for transfer
+    // DIRECTION-SPECIFIC LOGIC: Check source balance before anything is journalled
     match direction {
         WireDirection::In => {
-            // wire_in: Check UnbelievaBoat balance (source of funds)
-            crate::utils::rate_limit_ub_api().await;
-
-            let ub_bank_amount = match ub_client
-                .get_user_balance(guild_id, msg.author.id.get())
+            // wire_in: Check the provider's bank (source of funds)
+            let remote_bank_amount = retry_rate_limited(bridge, || remote_bank(bridge, guild_id, user_id))
                 .await
-            {
-                Ok(ub_balance) => ub_balance.bank,
-                Err(crate::api::unbelievaboat::models::ApiError::NotFound(_)) => 0,
-                Err(e) => return Err(WireError::Api(format!("Failed to fetch UnbelievaBoat balance: {}", e))),
-            };
+                .map_err(|e| WireError::Api(format!("Failed to fetch {} balance: {}", bridge.name(), e)))?;
 
-            if ub_bank_amount < amount as i64 {
+            if remote_bank_amount < remote_amount {
                 return Err(WireError::InsufficientBalance(format!(
-                    "Insufficient UnbelievaBoat balance. You have {} but need {}",
-                    ub_bank_amount, amount as i64
+                    "Insufficient {} balance. You have {} but need {}",
+                    bridge.name(), remote_bank_amount, remote_amount
                 )));
             }
         }
         WireDirection::Out => {
             // wire_out: Check SMITE balance (source of funds)
-            let mut tx = pool.begin().await
-                .map_err(|e| WireError::Transaction(format!("Failed to start transaction: {}", e)))?;
-
-            let current_smite_balance: f64 = match sqlx::query_scalar(
-                "SELECT CAST(balance AS DOUBLE) as balance FROM account WHERE discord_id = ? AND currency_id = ?"
-            )
-            .bind(user_id)
-            .bind(currency_id)
-            .fetch_optional(&mut *tx)
-            .await
-            .map_err(|e| WireError::Database(format!("Database error: {}", e)))?
-            {
-                Some(balance) => balance,
-                None => 0.0,
-            };
+            let current_smite_balance = storage.get_account_balance(user_id as i64, currency_id)
+                .await
+                .map_err(|e| WireError::Database(format!("Database error: {}", e)))?
+                .unwrap_or(Amount::ZERO);
 
-            if current_smite_balance < amount {
+            if current_smite_balance < quote.smite_amount {
                 return Err(WireError::InsufficientBalance(format!(
                     "Insufficient SMITE balance. You have {} but need {}",
-                    current_smite_balance, amount
+                    current_smite_balance, quote.smite_amount
                 )));
             }
+        }
+    }
 
-            // Rollback the balance check transaction
-            tx.rollback().await
-                .map_err(|e| WireError::Transaction(format!("Failed to rollback: {}", e)))?;
+    let wire_id = storage.create_wire(NewWire {
+        discord_id: user_id as i64,
+        currency_id,
+        direction: direction.as_str().to_string(),
+        amount: quote.smite_amount,
+        fee: quote.fee,
+        remote_amount,
+    })
+    .await
+        .map_err(|e| WireError::Database(format!("Failed to journal wire transfer: {}", e)))?;
+
+    // Apply the SMITE side; the balance is checked again under the row lock
+    let new_smite_balance = match storage.apply_wire(wire_id).await {
+        Ok(balance) => balance,
+        Err(e) => {
+            let reason = e.to_string();
+            mark_wire(storage, wire_id, STATE_INITIATED, STATE_FAILED, Some(&reason)).await;
+            return Err(match e {
+                LedgerError::InsufficientBalance { required, available } => WireError::InsufficientBalance(format!(
+                    "Insufficient SMITE balance. You have {} but need {}",
+                    available, required
+                )),
+                e => WireError::Transaction(format!("Failed to update balance: {}", e)),
+            });
         }
+    };
+
+    // NOW make external API calls (the SMITE side is committed)
+    // Nothing has changed remotely yet, so any failure here can be undone
+    let remote_bank_before = match retry_rate_limited(bridge, || remote_bank(bridge, guild_id, user_id)).await {
+        Ok(bank) => bank,
+        Err(api_error) => return compensate(storage, wire_id, bridge.name(), api_error).await,
+    };
+
+    if let Err(e) = storage.record_wire_remote_balance(wire_id, remote_bank_before).await {
+        // Without the snapshot recovery can't tell whether the provider was changed,
+        // so undo the SMITE side before touching it
+        return compensate(storage, wire_id, bridge.name(), format!("Failed to journal {} balance: {}", bridge.name(), e)).await;
     }
 
-    // START ATOMIC TRANSACTION: All DB operations in one transaction
-    let mut tx = pool.begin().await
-        .map_err(|e| WireError::Transaction(format!("Failed to start transaction: {}", e)))?;
+    // DIRECTION-SPECIFIC: the provider's bank moves the opposite way to SMITE.
+    // A relative change keeps any activity there since the read above.
+    let remote_delta = match direction {
+        WireDirection::In => -remote_amount,
+        WireDirection::Out => remote_amount,
+    };
 
-    // Get or create account (or fetch existing for wire_out)
-    let account_id = match sqlx::query_scalar::<_, i64>(
-        "SELECT id FROM account WHERE discord_id = ? AND currency_id = ?"
-    )
-    .bind(user_id)
-    .bind(currency_id)
-    .fetch_optional(&mut *tx)
-    .await
-    .map_err(|e| WireError::Database(format!("Database error: {}", e)))?
+    let new_remote_bank = match retry_rate_limited(bridge, || bridge.apply_delta(guild_id, user_id, remote_delta)).await {
+        Ok(balance) => balance.bank,
+        Err(api_error) if api_error.is_refusal() => return compensate(storage, wire_id, bridge.name(), api_error).await,
+        // The change may have gone through. Reversing SMITE now could pay the user
+        // twice, so recovery decides later from the bank recorded above.
+        Err(api_error) => {
+            let reason = format!("{} didn't confirm the transfer: {}", bridge.name(), api_error);
+            tracing::warn!("wire {}: {}", wire_id, reason);
+            mark_wire(storage, wire_id, STATE_SMITE_APPLIED, STATE_SMITE_APPLIED, Some(&reason)).await;
+            return Err(WireError::Unconfirmed(format!("Wire #{}: {}", wire_id, reason)));
+        }
+    };
+
+    if new_remote_bank != remote_bank_before + remote_delta {
+        tracing::warn!(
+            "wire {}: {} bank is {} after the transfer, expected {}; it changed in between",
+            wire_id, bridge.name(), new_remote_bank, remote_bank_before + remote_delta
+        );
+    }
+
+    if new_remote_bank < 0 {
+        // The bank was spent between the balance check and the change: undo both sides
+        bridge.rate_limit().await;
+        if let Err(api_error) = bridge.apply_delta(guild_id, user_id, -remote_delta).await {
+            tracing::error!("Failed to reverse {} change for wire {}: {}", bridge.name(), wire_id, api_error);
+            let reason = format!("{} bank went to {} and could not be restored: {}", bridge.name(), new_remote_bank, api_error);
+            mark_wire(storage, wire_id, STATE_SMITE_APPLIED, STATE_SMITE_APPLIED, Some(&reason)).await;
+            return Err(WireError::CompensationFailed(format!("Wire #{}: {}", wire_id, reason)));
+        }
+        let reason = format!(
+            "Insufficient {} balance. Your bank changed during the transfer and would be {}",
+            bridge.name(), new_remote_bank
+        );
+        return match compensate(storage, wire_id, bridge.name(), &reason).await {
+            Err(WireError::Api(_)) => Err(WireError::InsufficientBalance(reason)),
+            result => result,
+        };
+    }
+
+    mark_wire(storage, wire_id, STATE_SMITE_APPLIED, STATE_REMOTE_APPLIED, None).await;
+    tracing::info!(
+        "wire_{} SUCCESS: wire {} moved {} SMITE and {} {} (fee {})",
+        direction.as_str(), wire_id, quote.smite_amount, remote_amount, bridge.name(), quote.fee
+    );
+    Ok(WireResult {
+        wire_id,
+        quote: quote.clone(),
+        provider: bridge.name().to_string(),
+        smite_balance: new_smite_balance,
+        remote_balance: new_remote_bank,
+    })
+}
+
+/// The quote a user confirms before their wire runs
+pub fn create_quote_embed(quote: &WireQuote, currency_ticker: &str, provider: &str) -> CreateEmbed {
+    let expires_at = chrono::Utc::now().timestamp() + QUOTE_VALID_SECONDS;
+    let (title, send, receive) = match quote.direction {
+        WireDirection::In => (
+            "💱 Wire In Quote",
+            format!("{} {}", quote.remote_amount, provider),
+            format!("{} {}", quote.smite_amount, currency_ticker),
+        ),
+        WireDirection::Out => (
+            "💱 Wire Out Quote",
+            format!("{} {}", quote.smite_amount, currency_ticker),
+            format!("{} {}", quote.remote_amount, provider),
+        ),
+    };
+
+    CreateEmbed::default()
+        .title(title)
+        .field("You Send", send, true)
+        .field("You Receive", receive, true)
+        .field("Bridge Fee", format!("{} {}", quote.fee, currency_ticker), true)
+        .field("Rate", format!("1 {} = {} {}", provider, quote.rate, currency_ticker), true)
+        .field("Expires", format!("<t:{}:R>", expires_at), true)
+        .footer(CreateEmbedFooter::new("Nothing moves until you confirm"))
+        .color(0x00b0f4)
+}
+
+/// Confirm/Cancel buttons for a quote shown to `user_id`
+pub fn create_quote_buttons(user_id: u64, quote: &WireQuote, currency_ticker: &str) -> Vec<CreateActionRow> {
+    let confirm_id = format!(
+        "{}{}:{}:{}:{}:{}",
+        CONFIRM_BUTTON_PREFIX, user_id, quote.direction.as_str(), quote.smite_amount, quote.remote_amount, currency_ticker
+    );
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(confirm_id)
+            .label("Confirm")
+            .emoji('✅')
+            .style(ButtonStyle::Success),
+        CreateButton::new(format!("{}{}", CANCEL_BUTTON_PREFIX, user_id))
+            .label("Cancel")
+            .emoji('❌')
+            .style(ButtonStyle::Danger),
+    ])]
+}
+
+/// Parse a button custom ID created by `create_quote_buttons`
+pub fn parse_wire_button(custom_id: &str) -> Option<WireButton> {
+    if let Some(user_id) = custom_id.strip_prefix(CANCEL_BUTTON_PREFIX) {
+        return user_id.parse().ok().map(|user_id| WireButton::Cancel { user_id });
+    }
+
+    let mut parts = custom_id.strip_prefix(CONFIRM_BUTTON_PREFIX)?.splitn(5, ':');
+    Some(WireButton::Confirm {
+        user_id: parts.next()?.parse().ok()?,
+        direction: WireDirection::parse(parts.next()?)?,
+        smite_amount: parts.next()?.parse().ok()?,
+        remote_amount: parts.next()?.parse().ok()?,
+        ticker: parts.next()?.to_string(),
+    })
+}
+
+/// Build the success embed for a completed wire transfer
+pub fn create_wire_embed(currency_ticker: &str, result: &WireResult) -> CreateEmbed {
+    let quote = &result.quote;
+    let embed = match quote.direction {
+        WireDirection::In => CreateEmbed::default()
+            .title("✅ Wire In Successful")
+            .description(format!(
+                "Transferred {} from {} to SMITE as {} {}",
+                quote.remote_amount, result.provider, quote.smite_amount, currency_ticker
+            ))
+            .field(format!("{} Balance", result.provider), format!("{} bank remaining", result.remote_balance), false)
+            .field("SMITE Balance", format!("{} {}", result.smite_balance, currency_ticker), false),
+        WireDirection::Out => CreateEmbed::default()
+            .title("✅ Wire Out Successful")
+            .description(format!(
+                "Transferred {} {} from SMITE to {} as {}",
+                quote.smite_amount, currency_ticker, result.provider, quote.remote_amount
+            ))
+            .field("SMITE Balance", format!("{} {} remaining", result.smite_balance, currency_ticker), false)
+            .field(format!("{} Balance", result.provider), format!("{} bank", result.remote_balance), false),
+    };
+
+    let embed = if quote.fee.is_positive() {
+        embed.field("Bridge Fee", format!("{} {}", quote.fee, currency_ticker), false)
+    } else {
+        embed
+    };
+    embed
+        .footer(CreateEmbedFooter::new(format!("Wire #{}", result.wire_id)))
+        .color(0x00ff00)
+}
+
+/// Send a request within the provider's rate limit, and again after waiting out a
+/// 429 up to `RATE_LIMIT_RETRIES` times. A rate-limited request wasn't acted on, so
+/// sending it again can't apply a change twice.
+async fn retry_rate_limited<T, F, Fut>(bridge: &dyn EconomyBridge, mut request: F) -> Result<T, ApiError>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, ApiError>>,
+{
+    let mut retries = 0;
+    loop {
+        bridge.rate_limit().await;
+        match request().await {
+            Err(ApiError::RateLimited { retry_after, .. })
+                if retries < RATE_LIMIT_RETRIES && retry_after <= MAX_RETRY_AFTER_MS =>
+            {
+                retries += 1;
+                tracing::warn!("{} rate limited the request, retrying in {} ms", bridge.name(), retry_after);
+                tokio::time::sleep(std::time::Duration::from_millis(retry_after.max(0) as u64)).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// The user's bank on the bridged economy. A user it has never seen has nothing
+/// yet; the first change creates them.
+async fn remote_bank(bridge: &dyn EconomyBridge, guild_id: u64, user_id: u64) -> Result<i64, ApiError> {
+    match bridge.get_balance(guild_id, user_id).await {
+        Ok(balance) => Ok(balance.bank),
+        Err(ApiError::NotFound(_)) => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
+/// Reverse the SMITE side of a wire after the other side refused the change.
+/// If that fails too the wire stays 'smite_applied' for the recovery worker.
+async fn compensate(
+    storage: &dyn Storage,
+    wire_id: i64,
+    provider: &str,
+    api_error: impl std::fmt::Display,
+) -> Result<WireResult, WireError> {
+    let reason = api_error.to_string();
+    tracing::error!("API ERROR: {}, attempting compensation (wire_id: {})", reason, wire_id);
+    mark_wire(storage, wire_id, STATE_SMITE_APPLIED, STATE_SMITE_APPLIED, Some(&reason)).await;
+
+    match storage.compensate_wire(wire_id).await {
+        Ok(restored_balance) => {
+            tracing::info!("Compensation committed successfully (wire_id: {}, balance: {})", wire_id, restored_balance);
+            Err(WireError::Api(format!(
+                "{} API failed. Your balance has been restored. Error: {}",
+                provider, reason
+            )))
+        }
+        Err(e) => {
+            tracing::error!("Failed to compensate wire {}: {}", wire_id, e);
+            mark_wire(storage, wire_id, STATE_SMITE_APPLIED, STATE_SMITE_APPLIED, Some(&format!("Compensation failed: {}", e))).await;
+            Err(WireError::CompensationFailed(format!(
+                "Wire #{}: {} (API error: {})",
+                wire_id, e, reason
+            )))
+        }
+    }
+}
+
+/// Move a wire between journal states, logging rather than failing if the journal
+/// can't be written; the state it was left in is picked up by recovery
+async fn mark_wire(storage: &dyn Storage, wire_id: i64, from: &str, to: &str, error: Option<&str>) {
+    match storage.set_wire_state(wire_id, from, to, error).await {
+        Ok(true) => {}
+        Ok(false) => tracing::warn!("Wire {} was not '{}' when moving it to '{}'", wire_id, from, to),
+        Err(e) => tracing::error!("Failed to move wire {} to '{}': {}", wire_id, to, e),
+    }
+}
+
+/// The currency's wire provider, UnbelievaBoat unless another was chosen
+async fn bridge_provider(storage: &dyn Storage, currency_id: i64) -> Result<BridgeProvider, WireError> {
+    match storage.get_bridge_provider(currency_id)
+        .await
+        .map_err(|e| WireError::Database(format!("Database error: {}", e)))?
     {
-        Some(id) => id,
-        None => {
-            match direction {
-                WireDirection::In => {
-                    // wire_in: Create account if it doesn't exist
-                    let result = sqlx::query(
-                        "INSERT INTO account (discord_id, currency_id, balance) VALUES (?, ?, 0.0)"
-                    )
-                    .bind(user_id)
-                    .bind(currency_id)
-                    .execute(&mut *tx)
-                    .await
-                    .map_err(|e| WireError::Database(format!("Failed to create account: {}", e)))?;
-                    
-                    result.last_insert_id() as i64
-                }
-                WireDirection::Out => {
-                    // wire_out: Account must exist
-                    return Err(WireError::InsufficientBalance(format!(
-                        "Insufficient SMITE balance. You have 0 but need {}",
-                        amount
-                    )));
-                }
+        Some((api_type_id, settings)) => BridgeProvider::from_row(api_type_id, settings.as_deref())
+            .map_err(WireError::InvalidConfig),
+        None => Ok(BridgeProvider::default()),
+    }
+}
+
+/// A client for the currency's wire provider with its decrypted API token
+async fn connect_bridge(storage: &dyn Storage, currency_id: i64) -> Result<Box<dyn EconomyBridge>, WireError> {
+    let provider = bridge_provider(storage, currency_id).await?;
+
+    // Get the provider's API token from database
+    let encrypted_token = storage.get_api_token(currency_id, provider.api_type_id())
+        .await
+        .map_err(|e| WireError::Database(format!("Database error: {}", e)))?
+        .ok_or(WireError::InvalidConfig(format!("{} API token not configured for this currency", provider.name())))?;
+
+    // Decrypt the token (CryptoError is automatically converted via #[from])
+    let encryption_key = std::env::var("TOKEN_ENCRYPTION_KEY")
+        .map_err(|_| WireError::InvalidConfig("TOKEN_ENCRYPTION_KEY not set in environment".to_string()))?;
+    let api_token = decrypt_token(&encrypted_token, &encryption_key)?;
+
+    Ok(provider.connect(api_token))
+}
+
+/// How a stuck wire was resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WireRecovery {
+    /// Stopped before the SMITE balance changed
+    Failed,
+    /// The provider had been updated, so the wire is complete
+    Completed,
+    /// The provider hadn't been updated, so the SMITE side was reversed
+    Compensated,
+    /// The provider's balance doesn't tell either way; an admin has to decide
+    NeedsReview(String),
+}
+
+/// Finish or reverse every wire that has sat unsettled for `WIRE_STUCK_AFTER_SECONDS`
+/// Returns (wires resolved, wires left for an admin)
+pub async fn recover_unsettled_wires(ctx: &Context) -> Result<(usize, usize), String> {
+    let storage = crate::storage::from_ctx(ctx).await?;
+    let wires = storage.get_unsettled_wires(None, WIRE_STUCK_AFTER_SECONDS, RECOVERY_BATCH_SIZE)
+        .await
+        .map_err(|e| format!("Failed to fetch unsettled wires: {}", e))?;
+
+    let mut resolved = 0;
+    let mut needs_review = 0;
+    for wire in &wires {
+        let (id, _, currency_id, ..) = wire;
+        let outcome = match storage.get_currency_by_id(*currency_id).await {
+            Ok(Some((_, guild_id, _, _))) => match connect_bridge(storage.as_ref(), *currency_id).await {
+                Ok(bridge) => recover_wire(storage.as_ref(), bridge.as_ref(), guild_id as u64, wire).await,
+                Err(e) => Err(e),
+            },
+            Ok(None) => Err(WireError::InvalidConfig(format!("Currency {} not found", currency_id))),
+            Err(e) => Err(WireError::Database(e.to_string())),
+        };
+
+        match outcome {
+            Ok(WireRecovery::NeedsReview(reason)) => {
+                tracing::warn!("Wire {} needs an admin: {}", id, reason);
+                needs_review += 1;
             }
+            Ok(outcome) => {
+                tracing::info!("Recovered wire {}: {:?}", id, outcome);
+                resolved += 1;
+            }
+            Err(e) => tracing::error!("Failed to recover wire {}: {}", id, e),
         }
-    };
+    }
 
-    // Get current SMITE balance (within transaction)
-    let current_smite_balance: f64 = sqlx::query_scalar(
-        "SELECT CAST(balance AS DOUBLE) as balance FROM account WHERE id = ?"
-    )
-    .bind(account_id)
-    .fetch_one(&mut *tx)
-    .await
-    .map_err(|e| WireError::Database(format!("Failed to fetch balance: {}", e)))?;
+    Ok((resolved, needs_review))
+}
+
+/// Settle one stuck wire by comparing the bridged bank balance with the
+/// one recorded before the remote change
+pub async fn recover_wire(
+    storage: &dyn Storage,
+    bridge: &dyn EconomyBridge,
+    guild_id: u64,
+    wire: &WireRow,
+) -> Result<WireRecovery, WireError> {
+    let (id, discord_id, _, _, direction, _, _, remote_amount, state, remote_before, ..) = wire;
+
+    if state == STATE_INITIATED {
+        // Nothing was applied on either side
+        mark_wire(storage, *id, STATE_INITIATED, STATE_FAILED, Some("Interrupted before the SMITE balance changed")).await;
+        return Ok(WireRecovery::Failed);
+    }
 
-    // DIRECTION-SPECIFIC: Update SMITE balance
-    let new_smite_balance = match direction {
-        WireDirection::In => current_smite_balance + amount,
-        WireDirection::Out => current_smite_balance - amount,
+    let Some(before) = remote_before else {
+        // The bank balance is recorded before the provider is touched, so it never was
+        return reverse_wire(storage, *id).await;
     };
 
-    sqlx::query(
-        "UPDATE account SET balance = ? WHERE id = ?"
-    )
-    .bind(new_smite_balance)
-    .bind(account_id)
-    .execute(&mut *tx)
-    .await
-    .map_err(|e| WireError::Database(format!("Failed to update balance: {}", e)))?;
+    bridge.rate_limit().await;
+    let bank = remote_bank(bridge, guild_id, *discord_id as u64)
+        .await
+        .map_err(|e| WireError::Api(format!("Failed to fetch {} balance: {}", bridge.name(), e)))?;
 
-    // COMMIT TRANSACTION before external API call
-    tx.commit().await
-        .map_err(|e| WireError::Transaction(format!("Failed to commit transaction: {}", e)))?;
+    let expected = if direction == "in" { before - remote_amount } else { before + remote_amount };
 
-    // NOW make external API calls (outside transaction)
-    crate::utils::rate_limit_ub_api().await;
+    if bank == *before {
+        reverse_wire(storage, *id).await
+    } else if bank == expected {
+        mark_wire(storage, *id, STATE_SMITE_APPLIED, STATE_REMOTE_APPLIED, None).await;
+        Ok(WireRecovery::Completed)
+    } else {
+        let reason = format!(
+            "{} bank is {}, expected {} (applied) or {} (not applied)",
+            bridge.name(), bank, expected, before
+        );
+        mark_wire(storage, *id, STATE_SMITE_APPLIED, STATE_SMITE_APPLIED, Some(&reason)).await;
+        Ok(WireRecovery::NeedsReview(reason))
+    }
+}
 
-    // DIRECTION-SPECIFIC: API calls and calculations
-    match direction {
-        WireDirection::In => {
-            // wire_in: Subtract from UnbelievaBoat bank
-            let ub_bank_amount = ub_client
-                .get_user_balance(guild_id, msg.author.id.get())
-                .await
-                .map_err(|e| WireError::Api(format!("Failed to fetch UnbelievaBoat balance: {}", e)))?
-                .bank;
+async fn reverse_wire(storage: &dyn Storage, wire_id: i64) -> Result<WireRecovery, WireError> {
+    match storage.compensate_wire(wire_id).await {
+        Ok(_) => Ok(WireRecovery::Compensated),
+        Err(e) => {
+            let reason = format!("Compensation failed: {}", e);
+            mark_wire(storage, wire_id, STATE_SMITE_APPLIED, STATE_SMITE_APPLIED, Some(&reason)).await;
+            Err(WireError::CompensationFailed(reason))
+        }
+    }
+}
+
+/// What an admin decided about a stuck wire
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireResolution {
+    /// The provider was updated; keep the SMITE side
+    Complete,
+    /// The provider wasn't updated; reverse the SMITE side
+    Refund,
+}
 
-            let new_ub_bank = ub_bank_amount - amount as i64;
+impl WireResolution {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.to_lowercase().as_str() {
+            "complete" => Some(WireResolution::Complete),
+            "refund" => Some(WireResolution::Refund),
+            _ => None,
+        }
+    }
+}
 
-            crate::utils::rate_limit_ub_api().await;
+/// Page through the invoking user's wires
+pub async fn wire_history(ctx: &Context, invocation: &Invocation) -> Result<Page, String> {
+    let storage = crate::storage::from_ctx(ctx).await?;
+    let wires = storage.get_wires_by_user(invocation.user_id_i64(), MAX_LISTED_WIRES)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
 
-            match ub_client
-                .set_user_balance(guild_id, msg.author.id.get(), None, Some(new_ub_bank))
+    Ok(wire_pages("💳 Your Wire Transfers", &wires, false))
+}
+
+/// List the guild currency's stuck wires (admin only)
+pub async fn list_unsettled_wires(ctx: &Context, invocation: &Invocation) -> Result<Page, String> {
+    let (storage, currency_id, ticker) = admin_currency(ctx, invocation).await?;
+    let wires = storage.get_unsettled_wires(Some(currency_id), WIRE_STUCK_AFTER_SECONDS, MAX_LISTED_WIRES)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(wire_pages(&format!("🧮 Unsettled {} Wires", ticker), &wires, true))
+}
+
+/// Settle a stuck wire of the guild currency by hand (admin only)
+pub async fn resolve_wire(
+    ctx: &Context,
+    invocation: &Invocation,
+    wire_id: i64,
+    resolution: WireResolution,
+) -> Result<CreateEmbed, String> {
+    let (storage, currency_id, _) = admin_currency(ctx, invocation).await?;
+    let (id, discord_id, _, ticker, direction, amount, _, _, state, _, _, _, date_updated) = storage.get_wire(wire_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .filter(|wire| wire.2 == currency_id)
+        .ok_or(format!("❌ Wire `#{}` not found for this guild's currency", wire_id))?;
+
+    if state != STATE_INITIATED && state != STATE_SMITE_APPLIED {
+        return Err(format!("❌ Wire `#{}` is already settled ({})", id, state_label(&state)));
+    }
+    if date_updated > chrono::Utc::now().timestamp() - WIRE_STUCK_AFTER_SECONDS {
+        return Err(format!("❌ Wire `#{}` may still be in progress. Try again in a few minutes.", id));
+    }
+
+    // Recovery or another admin may settle the wire first. Each step only applies
+    // from the state read above, so whoever is second is told it's already settled.
+    let already_settled = || format!("❌ Wire `#{}` was settled while you were resolving it. Check `$wire history`.", id);
+    let note = format!("Resolved by <@{}>", invocation.user_id);
+    let outcome = match (resolution, state.as_str()) {
+        (_, STATE_INITIATED) => {
+            let moved = storage.set_wire_state(id, STATE_INITIATED, STATE_FAILED, Some(&note))
                 .await
-            {
-                Ok(_) => {
-                    tracing::info!("wire_in SUCCESS: transferred {} {}", amount, currency_ticker);
-                    Ok(WireResult {
-                        smite_balance: new_smite_balance,
-                        ub_balance: new_ub_bank,
-                    })
-                }
-                Err(api_error) => {
-                    compensate_smite_balance(&pool, account_id, current_smite_balance, api_error).await
-                }
+                .map_err(|e| format!("Database error: {}", e))?;
+            if !moved {
+                return Err(already_settled());
             }
+            "marked failed; no balance had changed"
         }
-        WireDirection::Out => {
-            // wire_out: Add to UnbelievaBoat bank
-            let ub_balance = match ub_client
-                .get_user_balance(guild_id, msg.author.id.get())
+        (WireResolution::Complete, _) => {
+            let moved = storage.set_wire_state(id, STATE_SMITE_APPLIED, STATE_REMOTE_APPLIED, Some(&note))
                 .await
-            {
-                Ok(balance) => balance,
-                Err(api_error) => {
-                    return compensate_smite_balance(&pool, account_id, current_smite_balance, api_error).await;
-                }
-            };
+                .map_err(|e| format!("Database error: {}", e))?;
+            if !moved {
+                return Err(already_settled());
+            }
+            "marked complete"
+        }
+        (WireResolution::Refund, _) => {
+            // Checks the wire is still 'smite_applied' under the row lock it reverses it in
+            match storage.compensate_wire(id).await {
+                Ok(_) => {}
+                Err(LedgerError::WireState(_)) => return Err(already_settled()),
+                Err(e) => return Err(format!("❌ Could not reverse wire `#{}`: {}", id, e)),
+            }
+            storage.set_wire_state(id, STATE_COMPENSATED, STATE_COMPENSATED, Some(&note))
+                .await
+                .map_err(|e| format!("Database error: {}", e))?;
+            "reversed on SMITE"
+        }
+    };
 
-            let ub_bank_amount = ub_balance.bank;
-            let new_ub_bank = ub_bank_amount + amount as i64;
+    Ok(CreateEmbed::default()
+        .title("✅ Wire Resolved")
+        .description(format!(
+            "Wire `#{}` ({} {} {} for <@{}>) was {}",
+            id, direction_label(&direction), amount, ticker, discord_id, outcome
+        ))
+        .color(0x00ff00))
+}
 
-            crate::utils::rate_limit_ub_api().await;
+/// A change to a currency's wire settings, as given to `$wire config`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WireSetting {
+    Rate(Amount),
+    Min(Option<Amount>),
+    Max(Option<Amount>),
+    FeePercent(Amount),
+    FeeFlat(Amount),
+    NoFee,
+    Rounding(Rounding),
+}
 
-            match ub_client
-                .set_user_balance(guild_id, msg.author.id.get(), None, Some(new_ub_bank))
-                .await
-            {
-                Ok(_) => {
-                    tracing::info!("wire_out SUCCESS: transferred {} {}", amount, currency_ticker);
-                    Ok(WireResult {
-                        smite_balance: new_smite_balance,
-                        ub_balance: new_ub_bank,
-                    })
-                }
-                Err(api_error) => {
-                    compensate_smite_balance(&pool, account_id, current_smite_balance, api_error).await
-                }
+impl WireSetting {
+    /// `rate <x>`, `min|max <x|none>`, `fee <x%|x|none>` or `rounding <down|up|nearest>`
+    pub fn parse(name: &str, value: &str) -> Result<Self, String> {
+        let value = value.trim().to_lowercase();
+        let amount = |input: &str| input.parse::<Amount>().map_err(|e| format!("❌ Invalid amount '{}': {}", input, e));
+        let limit = |input: &str| if input == "none" { Ok(None) } else { amount(input).map(Some) };
+
+        match name.to_lowercase().as_str() {
+            "rate" => amount(&value).map(WireSetting::Rate),
+            "min" => limit(&value).map(WireSetting::Min),
+            "max" => limit(&value).map(WireSetting::Max),
+            "fee" if value == "none" => Ok(WireSetting::NoFee),
+            "fee" => match value.strip_suffix('%') {
+                Some(percent) => amount(percent).map(WireSetting::FeePercent),
+                None => amount(&value).map(WireSetting::FeeFlat),
+            },
+            "rounding" => Rounding::parse(&value)
+                .map(WireSetting::Rounding)
+                .ok_or("❌ Rounding must be `down`, `up` or `nearest`".to_string()),
+            _ => Err(format!("❌ Unknown setting '{}'. Use `rate`, `min`, `max`, `fee` or `rounding`", name)),
+        }
+    }
+
+    /// Apply the change, refusing settings no wire could use
+    pub fn apply(&self, config: &mut WireConfig) -> Result<(), String> {
+        let mut updated = config.clone();
+        match self {
+            WireSetting::Rate(rate) => updated.rate = *rate,
+            WireSetting::Min(min) => updated.min_amount = *min,
+            WireSetting::Max(max) => updated.max_amount = *max,
+            WireSetting::FeePercent(percent) => updated.fee_percent = *percent,
+            WireSetting::FeeFlat(flat) => updated.fee_flat = *flat,
+            WireSetting::NoFee => {
+                updated.fee_percent = Amount::ZERO;
+                updated.fee_flat = Amount::ZERO;
+            }
+            WireSetting::Rounding(rounding) => updated.rounding = *rounding,
+        }
+
+        if !updated.rate.is_positive() || !updated.rate.is_within_limit() {
+            return Err("❌ The rate must be greater than 0".to_string());
+        }
+        let limits = [updated.min_amount, updated.max_amount];
+        if limits.iter().flatten().any(|limit| !limit.is_positive() || !limit.is_within_limit()) {
+            return Err("❌ Limits must be greater than 0".to_string());
+        }
+        if let (Some(min), Some(max)) = (updated.min_amount, updated.max_amount) {
+            if min > max {
+                return Err(format!("❌ The minimum ({}) can't be above the maximum ({})", min, max));
             }
         }
+        if updated.fee_percent.is_negative() || updated.fee_percent >= Amount::from_whole(100) {
+            return Err("❌ The fee percentage must be at least 0 and below 100".to_string());
+        }
+        if updated.fee_flat.is_negative() || !updated.fee_flat.is_within_limit() {
+            return Err("❌ The flat fee can't be negative".to_string());
+        }
+
+        *config = updated;
+        Ok(())
     }
 }
 
-/// Transfer from UnbelievaBoat to SMITE
-/// Subtracts from UnbelievaBoat bank, adds to SMITE account
-pub async fn wire_in(
+/// Show the guild currency's wire settings (admin only)
+pub async fn show_wire_config(ctx: &Context, invocation: &Invocation) -> Result<CreateEmbed, String> {
+    let (storage, currency_id, ticker) = admin_currency(ctx, invocation).await?;
+    let config = storage.get_wire_config(currency_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .unwrap_or_default();
+    let provider = bridge_provider(storage.as_ref(), currency_id).await.map_err(|e| e.to_string())?;
+
+    Ok(create_config_embed(&format!("⚙️ {} Wire Settings", ticker), &config, &ticker, provider.name()))
+}
+
+/// Change one of the guild currency's wire settings (admin only)
+pub async fn update_wire_config(
     ctx: &Context,
-    msg: &Message,
-    amount: f64,
-    currency_ticker: &str,
-) -> Result<WireResult, WireError> {
-    execute_wire_transfer(ctx, msg, WireDirection::In, amount, currency_ticker).await
+    invocation: &Invocation,
+    setting: &WireSetting,
+) -> Result<CreateEmbed, String> {
+    let (storage, currency_id, ticker) = admin_currency(ctx, invocation).await?;
+    let mut config = storage.get_wire_config(currency_id)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .unwrap_or_default();
+    setting.apply(&mut config)?;
+
+    storage.set_wire_config(currency_id, &config)
+        .await
+        .map_err(|e| format!("Failed to save wire settings: {}", e))?;
+    let provider = bridge_provider(storage.as_ref(), currency_id).await.map_err(|e| e.to_string())?;
+
+    Ok(create_config_embed(&format!("✅ {} Wire Settings Updated", ticker), &config, &ticker, provider.name()))
+}
+
+/// Show which economy the guild currency wires to (admin only)
+pub async fn show_bridge_provider(ctx: &Context, invocation: &Invocation) -> Result<CreateEmbed, String> {
+    let (storage, currency_id, ticker) = admin_currency(ctx, invocation).await?;
+    let provider = bridge_provider(storage.as_ref(), currency_id).await.map_err(|e| e.to_string())?;
+
+    Ok(create_provider_embed(&format!("🌐 {} Wire Provider", ticker), &provider))
 }
 
-/// Transfer from SMITE to UnbelievaBoat
-/// Subtracts from SMITE account, adds to UnbelievaBoat bank
-pub async fn wire_out(
+/// Switch the economy the guild currency wires to (admin only)
+pub async fn set_bridge_provider(
     ctx: &Context,
-    msg: &Message,
-    amount: f64,
-    currency_ticker: &str,
-) -> Result<WireResult, WireError> {
-    execute_wire_transfer(ctx, msg, WireDirection::Out, amount, currency_ticker).await
+    invocation: &Invocation,
+    provider: &BridgeProvider,
+) -> Result<CreateEmbed, String> {
+    let (storage, currency_id, ticker) = admin_currency(ctx, invocation).await?;
+    let settings = provider.settings_json();
+
+    storage.set_bridge_provider(currency_id, provider.api_type_id(), settings.as_deref())
+        .await
+        .map_err(|e| format!("Failed to save wire provider: {}", e))?;
+
+    let has_token = storage.get_api_token(currency_id, provider.api_type_id())
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .is_some();
+    let mut embed = create_provider_embed(&format!("✅ {} Wire Provider Updated", ticker), provider);
+    if !has_token {
+        embed = embed.footer(CreateEmbedFooter::new(format!(
+            "Set this provider's API token with `$wire set token` in a DM before wiring to {}",
+            provider.name()
+        )));
+    }
+    Ok(embed)
 }
 
-/// Helper function to compensate SMITE balance on API failure
-/// Used by both wire_in and wire_out to restore original balance when UnbelievaBoat API fails
-async fn compensate_smite_balance(
-    pool: &sqlx::MySqlPool,
-    account_id: i64,
-    original_balance: f64,
-    api_error: crate::api::unbelievaboat::models::ApiError,
-) -> Result<WireResult, WireError> {
-    tracing::error!("API ERROR: {}, attempting compensation (account_id: {}, restore_balance: {})", api_error, account_id, original_balance);
-    
-    let mut compensating_tx = pool.begin().await
-        .map_err(|e| {
-            tracing::error!("Failed to start compensating transaction: {}", e);
-            WireError::CompensationFailed(format!("Failed to start compensating transaction: {}", e))
-        })?;
-    
-    // Restore original balance
-    let rows_affected = sqlx::query(
-        "UPDATE account SET balance = ? WHERE id = ?"
-    )
-    .bind(original_balance)
-    .bind(account_id)
-    .execute(&mut *compensating_tx)
-    .await
-    .map_err(|e| {
-        tracing::error!("Failed to execute compensation UPDATE: {}", e);
-        WireError::CompensationFailed(format!("Failed to compensate balance: {}", e))
-    })?
-    .rows_affected();
-
-    if rows_affected == 0 {
-        tracing::warn!("Compensation UPDATE found 0 rows (account_id: {})", account_id);
-    }
-    
-    compensating_tx.commit().await
-        .map_err(|e| {
-            tracing::error!("Failed to commit compensating transaction: {}", e);
-            WireError::CompensationFailed(format!("Failed to commit compensation: {}", e))
-        })?;
-
-    tracing::info!("Compensating transaction committed successfully (account_id: {}, restored_balance: {})", account_id, original_balance);
-    
-    Err(WireError::Api(format!(
-        "UnbelievaBoat API failed. Your balance has been restored. Error: {}",
-        api_error
-    )))
-}
\ No newline at end of file
+fn create_provider_embed(title: &str, provider: &BridgeProvider) -> CreateEmbed {
+    let embed = CreateEmbed::default()
+        .title(title)
+        .field("Provider", provider.name(), true)
+        .color(0x00b0f4);
+
+    match provider {
+        BridgeProvider::UnbelievaBoat => embed.field("API", "UnbelievaBoat", true),
+        BridgeProvider::HttpJson(settings) => embed
+            .field("API", "HTTP JSON", true)
+            .field("Balance", format!("GET {}{}", settings.base_url, settings.path), false)
+            .field("Transfer", format!("{} {}{}", settings.delta_method, settings.base_url, settings.path), false)
+            .field(
+                "Fields",
+                format!(
+                    "bank `{}`, cash `{}`, delta `{}`",
+                    settings.bank_field,
+                    settings.cash_field.as_deref().unwrap_or("none"),
+                    settings.delta_field
+                ),
+                false,
+            )
+            .field("Rate Limit", format!("{} requests/second", settings.requests_per_second), true),
+    }
+}
+
+fn create_config_embed(title: &str, config: &WireConfig, ticker: &str, provider: &str) -> CreateEmbed {
+    let limit = |limit: Option<Amount>| limit.map_or("none".to_string(), |limit| format!("{} {}", limit, ticker));
+    CreateEmbed::default()
+        .title(title)
+        .field("Rate", format!("1 {} = {} {}", provider, config.rate, ticker), false)
+        .field("Minimum", limit(config.min_amount), true)
+        .field("Maximum", limit(config.max_amount), true)
+        .field("Fee", format!("{}% + {} {}", config.fee_percent, config.fee_flat, ticker), true)
+        .field("Rounding", config.rounding.as_str(), true)
+        .footer(CreateEmbedFooter::new("Limits and the fee are in SMITE, before the fee is taken"))
+        .color(0x00b0f4)
+}
+
+/// The storage and guild currency (id, ticker) for an admin managing its wires
+async fn admin_currency(
+    ctx: &Context,
+    invocation: &Invocation,
+) -> Result<(Arc<dyn Storage>, i64, String), String> {
+    let guild_id = invocation.guild_id
+        .ok_or("This command can only be used in a guild".to_string())?;
+    crate::utils::check_user_roles(ctx, guild_id, invocation.user_id, &["admin"]).await?;
+
+    let storage = crate::storage::from_ctx(ctx).await?;
+    let (currency_id, _, ticker) = storage.get_currency_by_guild(guild_id.get() as i64)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or("❌ This guild has no currency".to_string())?;
+
+    Ok((storage, currency_id, ticker))
+}
+
+fn wire_pages(title: &str, wires: &[WireRow], show_user: bool) -> Page {
+    if wires.is_empty() {
+        return Page::new(vec![CreateEmbed::default()
+            .title(title)
+            .description("No wire transfers found")
+            .color(0x00b0f4)]);
+    }
+
+    let total_pages = wires.len().div_ceil(WIRES_PER_PAGE);
+    let embeds = wires
+        .chunks(WIRES_PER_PAGE)
+        .enumerate()
+        .map(|(idx, chunk)| {
+            let mut description = String::new();
+            for (id, discord_id, _, ticker, direction, amount, _, _, state, _, last_error, date_created, _) in chunk {
+                description.push_str(&format!(
+                    "**#{}** {} `{} {}`, {} <t:{}:R>",
+                    id, direction_label(direction), amount, ticker, state_label(state), date_created
+                ));
+                if show_user {
+                    description.push_str(&format!(" by <@{}>", discord_id));
+                }
+                description.push('\n');
+                if let Some(error) = last_error.as_ref().filter(|_| state != STATE_REMOTE_APPLIED) {
+                    description.push_str(&format!("└─ {}\n", error));
+                }
+            }
+            CreateEmbed::default()
+                .title(title)
+                .description(description)
+                .footer(CreateEmbedFooter::new(format!("Page {}/{}", idx + 1, total_pages)))
+                .color(0x00b0f4)
+        })
+        .collect();
+
+    Page::new(embeds)
+}
+
+fn direction_label(direction: &str) -> &'static str {
+    match direction {
+        "in" => "⬇️ In",
+        "out" => "⬆️ Out",
+        _ => "unknown",
+    }
+}
+
+fn state_label(state: &str) -> &'static str {
+    match state {
+        STATE_INITIATED => "⏳ started",
+        STATE_SMITE_APPLIED => "⏳ in progress",
+        STATE_REMOTE_APPLIED => "✅ completed",
+        STATE_COMPENSATED => "↩️ reversed",
+        STATE_FAILED => "❌ failed",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::unbelievaboat::mock::MockUnbelievaboat;
+    use crate::api::unbelievaboat::UnbelievaboatClient;
+    use crate::storage::{amount, AccountStore, ApiTokenStore, MemoryStorage, WireStore};
+
+    const ALICE: i64 = 1;
+
+    /// A client for paths that must not reach UnbelievaBoat
+    fn unreachable_client() -> UnbelievaboatClient {
+        UnbelievaboatClient::with_base_url("token".to_string(), "http://127.0.0.1:9".to_string())
+    }
+
+    fn out_wire(currency_id: i64, amount: Amount) -> NewWire {
+        NewWire {
+            discord_id: ALICE,
+            currency_id,
+            direction: "out".to_string(),
+            amount,
+            fee: Amount::ZERO,
+            remote_amount: amount.whole_part().unwrap(),
+        }
+    }
+
+    fn config(rate: &str, fee_percent: &str, fee_flat: &str, rounding: Rounding) -> WireConfig {
+        WireConfig {
+            rate: amount(rate),
+            fee_percent: amount(fee_percent),
+            fee_flat: amount(fee_flat),
+            rounding,
+            ..WireConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_quote_converts_and_charges_the_fee() {
+        // 10 UnbelievaBoat at 2.5 is 25, less 2% + 0.5
+        let quote_in = quote(&config("2.5", "2", "0.5", Rounding::Down), WireDirection::In, amount("10")).unwrap();
+        assert_eq!((quote_in.smite_amount, quote_in.fee, quote_in.remote_amount), (amount("24"), amount("1"), 10));
+
+        // 26 SMITE less 1 fee is 25, or 10 UnbelievaBoat; 27 is 10.4 of them
+        let out = config("2.5", "0", "1", Rounding::Down);
+        let quote_out = quote(&out, WireDirection::Out, amount("26")).unwrap();
+        assert_eq!((quote_out.smite_amount, quote_out.fee, quote_out.remote_amount), (amount("26"), amount("1"), 10));
+        assert_eq!(quote(&out, WireDirection::Out, amount("27")).unwrap().remote_amount, 10);
+        let up = config("2.5", "0", "1", Rounding::Up);
+        assert_eq!(quote(&up, WireDirection::Out, amount("27")).unwrap().remote_amount, 11);
+
+        // The default is the old 1:1 with no fee
+        let plain = quote(&WireConfig::default(), WireDirection::Out, amount("7")).unwrap();
+        assert_eq!((plain.smite_amount, plain.fee, plain.remote_amount), (amount("7"), Amount::ZERO, 7));
+    }
+
+    #[test]
+    fn test_quote_refuses_amounts_outside_the_settings() {
+        let invalid = |config: &WireConfig, direction, value: &str| {
+            matches!(quote(config, direction, amount(value)), Err(WireError::InvalidAmount(_)))
+        };
+        let limited = WireConfig {
+            min_amount: Some(amount("10")),
+            max_amount: Some(amount("100")),
+            ..config("2", "0", "0", Rounding::Down)
+        };
+
+        assert!(invalid(&limited, WireDirection::In, "1.5"));
+        assert!(invalid(&limited, WireDirection::In, "4"));
+        assert!(!invalid(&limited, WireDirection::In, "5"));
+        assert!(invalid(&limited, WireDirection::Out, "100.5"));
+        assert!(invalid(&limited, WireDirection::Out, "0"));
+        // Less than one UnbelievaBoat unit after the fee
+        assert!(invalid(&config("2", "0", "1", Rounding::Down), WireDirection::Out, "2.5"));
+        // A fee that takes everything
+        assert!(invalid(&config("1", "0", "5", Rounding::Down), WireDirection::In, "5"));
+    }
+
+    #[test]
+    fn test_wire_settings_are_validated() {
+        let mut config = WireConfig::default();
+        WireSetting::parse("fee", "1.5%").unwrap().apply(&mut config).unwrap();
+        WireSetting::parse("fee", "2").unwrap().apply(&mut config).unwrap();
+        WireSetting::parse("max", "50").unwrap().apply(&mut config).unwrap();
+        WireSetting::parse("rounding", "nearest").unwrap().apply(&mut config).unwrap();
+        assert_eq!((config.fee_percent, config.fee_flat), (amount("1.5"), amount("2")));
+        assert_eq!((config.max_amount, config.rounding), (Some(amount("50")), Rounding::Nearest));
+
+        let before = config.clone();
+        for (name, value) in [("rate", "0"), ("min", "60"), ("fee", "100%"), ("fee", "-1"), ("max", "-5")] {
+            assert!(WireSetting::parse(name, value).unwrap().apply(&mut config).is_err(), "{} {}", name, value);
+        }
+        assert_eq!(config, before);
+
+        WireSetting::parse("fee", "none").unwrap().apply(&mut config).unwrap();
+        WireSetting::parse("max", "none").unwrap().apply(&mut config).unwrap();
+        assert_eq!(config, WireConfig { rounding: Rounding::Nearest, ..WireConfig::default() });
+        assert!(WireSetting::parse("rounding", "sideways").is_err());
+        assert!(WireSetting::parse("speed", "1").is_err());
+    }
+
+    #[test]
+    fn test_parse_wire_button() {
+        let quote = quote(&config("0.5", "0", "0", Rounding::Down), WireDirection::Out, amount("3.25")).unwrap();
+        let buttons = create_quote_buttons(42, &quote, "ABC");
+        assert_eq!(buttons.len(), 1);
+
+        assert_eq!(
+            parse_wire_button("wire_confirm:42:out:3.25:6:ABC"),
+            Some(WireButton::Confirm {
+                user_id: 42,
+                direction: WireDirection::Out,
+                smite_amount: amount("3.25"),
+                remote_amount: 6,
+                ticker: "ABC".to_string(),
+            })
+        );
+        assert_eq!(parse_wire_button("wire_cancel:42"), Some(WireButton::Cancel { user_id: 42 }));
+        assert_eq!(parse_wire_button("wire_confirm:42:sideways:1:1:ABC"), None);
+        assert_eq!(parse_wire_button("invoice_pay:3"), None);
+    }
+
+    #[tokio::test]
+    async fn test_stuck_wires_are_settled_without_calling_the_api() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 0, &[(ALICE, "100")]);
+
+        // Cut off before the SMITE side
+        let initiated = storage.create_wire(out_wire(currency_id, amount("30"))).await.unwrap();
+        // Cut off before UnbelievaBoat was read
+        let applied = storage.create_wire(out_wire(currency_id, amount("30"))).await.unwrap();
+        storage.apply_wire(applied).await.unwrap();
+        assert_eq!(storage.get_account_balance(ALICE, currency_id).await.unwrap(), Some(amount("70")));
+
+        assert!(storage.get_unsettled_wires(None, WIRE_STUCK_AFTER_SECONDS, 10).await.unwrap().is_empty());
+        storage.advance_minutes(10);
+        let stuck = storage.get_unsettled_wires(None, WIRE_STUCK_AFTER_SECONDS, 10).await.unwrap();
+        assert_eq!(stuck.len(), 2);
+
+        let client = unreachable_client();
+        assert_eq!(recover_wire(&storage, &client, 1, &stuck[0]).await.unwrap(), WireRecovery::Failed);
+        assert_eq!(recover_wire(&storage, &client, 1, &stuck[1]).await.unwrap(), WireRecovery::Compensated);
+
+        assert_eq!(storage.get_account_balance(ALICE, currency_id).await.unwrap(), Some(amount("100")));
+        assert_eq!(storage.get_wire(initiated).await.unwrap().unwrap().8, STATE_FAILED);
+        assert_eq!(storage.get_wire(applied).await.unwrap().unwrap().8, STATE_COMPENSATED);
+        assert!(storage.get_unsettled_wires(None, 0, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_wire_out_is_refused_before_journalling_without_funds() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 0, &[(ALICE, "10")]);
+
+        let quote = quote(&WireConfig::default(), WireDirection::Out, amount("11")).unwrap();
+        let result = transfer(&storage, &unreachable_client(), 1, ALICE as u64, currency_id, &quote).await;
+        assert!(matches!(result, Err(WireError::InsufficientBalance(_))));
+        assert!(storage.get_wires_by_user(ALICE, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_bridge_provider_defaults_to_unbelievaboat() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 0, &[]);
+        assert_eq!(bridge_provider(&storage, currency_id).await.unwrap(), BridgeProvider::UnbelievaBoat);
+
+        let provider = BridgeProvider::parse(&["http", "https://economy.example", "name=Coins"]).unwrap();
+        storage.set_bridge_provider(currency_id, provider.api_type_id(), provider.settings_json().as_deref())
+            .await
+            .unwrap();
+        assert_eq!(bridge_provider(&storage, currency_id).await.unwrap(), provider);
+
+        // Tokens are kept per provider, so switching needs a new one
+        storage.store_api_token(currency_id, crate::api::bridge::UNBELIEVABOAT_API_TYPE, "sealed").await.unwrap();
+        assert!(matches!(connect_bridge(&storage, currency_id).await, Err(WireError::InvalidConfig(_))));
+    }
+
+    const GUILD: u64 = 1;
+
+    async fn wire(
+        storage: &MemoryStorage,
+        mock: &MockUnbelievaboat,
+        currency_id: i64,
+        direction: WireDirection,
+        amount: Amount,
+    ) -> Result<WireResult, WireError> {
+        let quote = quote(&WireConfig::default(), direction, amount).unwrap();
+        transfer(storage, &mock.client(), GUILD, ALICE as u64, currency_id, &quote).await
+    }
+
+    #[tokio::test]
+    async fn test_wire_in_and_out_against_the_api() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 0, &[(ALICE, "100")]);
+        let mock = MockUnbelievaboat::start().await;
+        mock.set_balance(GUILD, ALICE as u64, 7, 50);
+
+        let result = wire(&storage, &mock, currency_id, WireDirection::In, amount("20")).await.unwrap();
+        assert_eq!((result.smite_balance, result.remote_balance), (amount("120"), 30));
+        assert_eq!(mock.balance(GUILD, ALICE as u64), Some((7, 30)));
+
+        let result = wire(&storage, &mock, currency_id, WireDirection::Out, amount("50")).await.unwrap();
+        assert_eq!((result.smite_balance, result.remote_balance), (amount("70"), 80));
+        assert_eq!(mock.balance(GUILD, ALICE as u64), Some((7, 80)));
+        assert_eq!(result.provider, "UnbelievaBoat");
+
+        let wires = storage.get_wires_by_user(ALICE, 10).await.unwrap();
+        assert_eq!(wires.len(), 2);
+        assert!(wires.iter().all(|wire| wire.8 == STATE_REMOTE_APPLIED));
+        // The bank before each change is kept for recovery
+        assert_eq!(wires.iter().map(|wire| wire.9).collect::<Vec<_>>(), vec![Some(30), Some(50)]);
+    }
+
+    #[tokio::test]
+    async fn test_wire_out_creates_a_user_the_api_has_not_seen() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 0, &[(ALICE, "100")]);
+        let mock = MockUnbelievaboat::start().await;
+
+        let result = wire(&storage, &mock, currency_id, WireDirection::Out, amount("10")).await.unwrap();
+        assert_eq!((result.smite_balance, result.remote_balance), (amount("90"), 10));
+        assert_eq!(mock.balance(GUILD, ALICE as u64), Some((0, 10)));
+    }
+
+    #[tokio::test]
+    async fn test_wire_in_is_refused_without_api_funds() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 0, &[(ALICE, "100")]);
+        let mock = MockUnbelievaboat::start().await;
+
+        // A user the API doesn't know has nothing to wire in
+        let result = wire(&storage, &mock, currency_id, WireDirection::In, amount("1")).await;
+        assert!(matches!(result, Err(WireError::InsufficientBalance(_))));
+
+        // Cash doesn't count, only the bank is wired
+        mock.set_balance(GUILD, ALICE as u64, 500, 5);
+        let result = wire(&storage, &mock, currency_id, WireDirection::In, amount("10")).await;
+        assert!(matches!(result, Err(WireError::InsufficientBalance(_))));
+
+        assert_eq!(mock.request_count("PATCH"), 0);
+        assert!(storage.get_wires_by_user(ALICE, 10).await.unwrap().is_empty());
+        assert_eq!(storage.get_account_balance(ALICE, currency_id).await.unwrap(), Some(amount("100")));
+        assert_eq!(mock.balance(GUILD, ALICE as u64), Some((500, 5)));
+    }
+
+    #[tokio::test]
+    async fn test_api_failure_mid_wire_restores_smite() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 0, &[(ALICE, "100")]);
+        let mock = MockUnbelievaboat::start().await;
+        mock.set_balance(GUILD, ALICE as u64, 0, 50);
+
+        // The SMITE side is applied, then the change is refused
+        mock.fail_next("PATCH", 400, r#"{"message":"Invalid balance"}"#);
+        let result = wire(&storage, &mock, currency_id, WireDirection::Out, amount("40")).await;
+        assert!(matches!(&result, Err(WireError::Api(msg)) if msg.contains("restored")));
+
+        mock.forbid_guild(GUILD);
+        let result = wire(&storage, &mock, currency_id, WireDirection::In, amount("10")).await;
+        assert!(matches!(result, Err(WireError::Api(_))));
+
+        assert_eq!(storage.get_account_balance(ALICE, currency_id).await.unwrap(), Some(amount("100")));
+        assert_eq!(mock.balance(GUILD, ALICE as u64), Some((0, 50)));
+        let wires = storage.get_wires_by_user(ALICE, 10).await.unwrap();
+        assert_eq!(wires.len(), 1);
+        assert_eq!(wires[0].8, STATE_COMPENSATED);
+        assert!(wires[0].10.as_deref().is_some_and(|error| error.contains("Invalid balance")));
+    }
+
+    #[tokio::test]
+    async fn test_unconfirmed_change_is_left_for_recovery() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 0, &[(ALICE, "100")]);
+        let mock = MockUnbelievaboat::start().await;
+        mock.set_balance(GUILD, ALICE as u64, 0, 50);
+        let client = mock.client();
+
+        // The change goes through but the reply is a 503: SMITE must not be reversed
+        mock.fail_next_after_applying("PATCH", 503, "Service Unavailable");
+        let result = wire(&storage, &mock, currency_id, WireDirection::Out, amount("40")).await;
+        assert!(matches!(&result, Err(WireError::Unconfirmed(msg)) if msg.contains("503")));
+        assert_eq!(storage.get_account_balance(ALICE, currency_id).await.unwrap(), Some(amount("60")));
+        assert_eq!(mock.balance(GUILD, ALICE as u64), Some((0, 90)));
+
+        // A 503 for a change that didn't happen is left the same way
+        mock.fail_next("PATCH", 503, "Service Unavailable");
+        let result = wire(&storage, &mock, currency_id, WireDirection::Out, amount("20")).await;
+        assert!(matches!(result, Err(WireError::Unconfirmed(_))));
+        assert_eq!(storage.get_account_balance(ALICE, currency_id).await.unwrap(), Some(amount("40")));
+
+        storage.advance_minutes(10);
+        let stuck = storage.get_unsettled_wires(None, WIRE_STUCK_AFTER_SECONDS, 10).await.unwrap();
+        assert_eq!(stuck.len(), 2);
+        assert_eq!(recover_wire(&storage, &client, GUILD, &stuck[0]).await.unwrap(), WireRecovery::Completed);
+        assert_eq!(recover_wire(&storage, &client, GUILD, &stuck[1]).await.unwrap(), WireRecovery::Compensated);
+
+        // Paid once on each side: 40 out of SMITE and 40 into the bank
+        assert_eq!(storage.get_account_balance(ALICE, currency_id).await.unwrap(), Some(amount("60")));
+        assert_eq!(mock.balance(GUILD, ALICE as u64), Some((0, 90)));
+        assert_eq!(mock.request_count("PATCH"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_requests_are_retried_after_the_wait() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 0, &[(ALICE, "100")]);
+        let mock = MockUnbelievaboat::start().await;
+        mock.set_balance(GUILD, ALICE as u64, 0, 50);
+
+        // A 429 on the balance check and on the change, then 200s
+        mock.rate_limit_next("GET", 20);
+        mock.rate_limit_next("PATCH", 30);
+        let result = wire(&storage, &mock, currency_id, WireDirection::In, amount("10")).await.unwrap();
+        assert_eq!((result.smite_balance, result.remote_balance), (amount("110"), 40));
+        assert_eq!(mock.balance(GUILD, ALICE as u64), Some((0, 40)));
+        assert_eq!(mock.request_count("PATCH"), 2);
+
+        // Still throttled after every retry: the change never happened, so it's reversed
+        for _ in 0..=RATE_LIMIT_RETRIES {
+            mock.rate_limit_next("PATCH", 10);
+        }
+        let result = wire(&storage, &mock, currency_id, WireDirection::Out, amount("30")).await;
+        assert!(matches!(&result, Err(WireError::Api(msg)) if msg.contains("Retry after 10 ms")));
+        assert_eq!(mock.request_count("PATCH"), 2 + RATE_LIMIT_RETRIES + 1);
+
+        // A wait longer than the cap isn't sat through
+        mock.rate_limit_next("PATCH", MAX_RETRY_AFTER_MS + 1);
+        let result = wire(&storage, &mock, currency_id, WireDirection::Out, amount("30")).await;
+        assert!(matches!(result, Err(WireError::Api(_))));
+
+        assert_eq!(storage.get_account_balance(ALICE, currency_id).await.unwrap(), Some(amount("110")));
+        assert_eq!(mock.balance(GUILD, ALICE as u64), Some((0, 40)));
+        let states: Vec<_> = storage.get_wires_by_user(ALICE, 10).await.unwrap().into_iter().map(|wire| wire.8).collect();
+        assert_eq!(states, [STATE_COMPENSATED, STATE_COMPENSATED, STATE_REMOTE_APPLIED]);
+    }
+
+    #[tokio::test]
+    async fn test_recovery_reads_the_api_to_settle_stuck_wires() {
+        let storage = MemoryStorage::new();
+        let currency_id = storage.seed_currency("ABC", 0, &[(ALICE, "100")]);
+        let mock = MockUnbelievaboat::start().await;
+        mock.set_balance(GUILD, ALICE as u64, 0, 50);
+        let client = mock.client();
+
+        // Cut off after UnbelievaBoat was changed
+        let changed = storage.create_wire(out_wire(currency_id, amount("30"))).await.unwrap();
+        storage.apply_wire(changed).await.unwrap();
+        storage.record_wire_remote_balance(changed, 50).await.unwrap();
+        client.modify_user_balance(GUILD, ALICE as u64, None, Some(30)).await.unwrap();
+        storage.advance_minutes(10);
+
+        let stuck = storage.get_unsettled_wires(None, WIRE_STUCK_AFTER_SECONDS, 10).await.unwrap();
+        assert_eq!(recover_wire(&storage, &client, GUILD, &stuck[0]).await.unwrap(), WireRecovery::Completed);
+
+        // Cut off just before it
+        let unchanged = storage.create_wire(out_wire(currency_id, amount("30"))).await.unwrap();
+        storage.apply_wire(unchanged).await.unwrap();
+        storage.record_wire_remote_balance(unchanged, 80).await.unwrap();
+        storage.advance_minutes(10);
+
+        let stuck = storage.get_unsettled_wires(None, WIRE_STUCK_AFTER_SECONDS, 10).await.unwrap();
+        assert_eq!(recover_wire(&storage, &client, GUILD, &stuck[0]).await.unwrap(), WireRecovery::Compensated);
+
+        assert_eq!(storage.get_account_balance(ALICE, currency_id).await.unwrap(), Some(amount("70")));
+        assert_eq!(mock.balance(GUILD, ALICE as u64), Some((0, 80)));
+        assert_eq!(storage.get_wire(changed).await.unwrap().unwrap().8, STATE_REMOTE_APPLIED);
+        assert_eq!(storage.get_wire(unchanged).await.unwrap().unwrap().8, STATE_COMPENSATED);
+    }
+}
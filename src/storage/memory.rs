@@ -0,0 +1,1492 @@
+//! In-memory backend for tests.
+//!
+//! Every operation takes the one lock for its whole run, which gives it the
+//! all-or-nothing behaviour of the MySQL transactions. Checks happen before any
+//! row is touched, so a rejected operation leaves the state as it was. The swap
+//! operations follow the settlement in `storage::swaps` and reject with the same
+//! messages.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use serenity::async_trait;
+use crate::db::filter::{SwapFilter, SwapSort, SwapStatus};
+use crate::db::ledger::{BatchTransferReceipt, TransferReceipt};
+use crate::db::payroll::PayrollSource;
+use crate::db::wire::WireConfig;
+use crate::utils::Amount;
+use crate::utils::errors::{LedgerError, StorageError};
+use super::swaps;
+use super::{
+    AccountStore, ApiTokenStore, CurrencyStore, InvoiceRow, InvoiceStore, NewInvoice, NewPayroll, NewRecurringPayment,
+    NewSwap, NewWire, OverdueSwapRow, PayrollRow, PayrollStore, RecurringRow, RecurringStore, SwapListRow, SwapRow, SwapStore,
+    TaxStore, TradeLogStore, TransactionRow, TransactionStore, WireRow, WireStore,
+};
+
+struct Currency {
+    id: i64,
+    guild_id: i64,
+    name: String,
+    ticker: String,
+    swap_expiry_minutes: Option<i64>,
+    /// Unix timestamp
+    date_created: i64,
+}
+
+struct Account {
+    id: i64,
+    discord_id: i64,
+    currency_id: i64,
+    balance: Amount,
+}
+
+struct Swap {
+    id: i64,
+    maker_id: i64,
+    taker_id: Option<i64>,
+    maker_currency_id: i64,
+    taker_currency_id: i64,
+    maker_amount: Amount,
+    taker_amount: Amount,
+    filled_amount: Amount,
+    status: String,
+    expires_at: Option<i64>,
+}
+
+struct SwapFill {
+    swap_id: i64,
+    taker_amount: Amount,
+}
+
+struct Transaction {
+    uuid: String,
+    sender_id: i64,
+    receiver_id: i64,
+    amount: Amount,
+    date_created: String,
+    memo: Option<String>,
+}
+
+struct TaxAccount {
+    id: i64,
+    currency_id: i64,
+    balance: Amount,
+    tax_percentage: i32,
+}
+
+struct RecurringPayment {
+    id: i64,
+    payer_id: i64,
+    recipient_id: i64,
+    currency_id: i64,
+    amount: Amount,
+    period: &'static str,
+    next_run_at: i64,
+    failure_count: i32,
+    status: String,
+}
+
+struct Payroll {
+    id: i64,
+    guild_id: i64,
+    role_id: i64,
+    currency_id: i64,
+    amount: Amount,
+    source: &'static str,
+    period: &'static str,
+    next_run_at: i64,
+    created_by: i64,
+    status: String,
+}
+
+struct Invoice {
+    id: i64,
+    issuer_id: i64,
+    payer_id: i64,
+    currency_id: i64,
+    amount: Amount,
+    memo: Option<String>,
+    status: String,
+    transaction_uuid: Option<String>,
+    expires_at: i64,
+    date_updated: i64,
+}
+
+struct Wire {
+    id: i64,
+    discord_id: i64,
+    currency_id: i64,
+    direction: String,
+    amount: Amount,
+    fee: Amount,
+    remote_amount: i64,
+    state: String,
+    remote_before: Option<i64>,
+    last_error: Option<String>,
+    date_created: i64,
+    date_updated: i64,
+}
+
+/// (base_currency_id, quote_currency_id, price, volume, quote_volume)
+pub type Trade = (i64, i64, Amount, Amount, Amount);
+
+#[derive(Default)]
+struct State {
+    currencies: Vec<Currency>,
+    accounts: Vec<Account>,
+    swaps: Vec<Swap>,
+    swap_fills: Vec<SwapFill>,
+    swap_messages: Vec<(i64, i64, i64)>,
+    transactions: Vec<Transaction>,
+    trades: Vec<Trade>,
+    tax_accounts: Vec<TaxAccount>,
+    recurring_payments: Vec<RecurringPayment>,
+    payrolls: Vec<Payroll>,
+    invoices: Vec<Invoice>,
+    wires: Vec<Wire>,
+    wire_configs: HashMap<i64, WireConfig>,
+    api_tokens: HashMap<(i64, i32), String>,
+    bridge_providers: HashMap<i64, (i32, Option<String>)>,
+    /// Seconds added to the wall clock, so tests can move past swap expiries
+    clock_offset: i64,
+}
+
+impl State {
+    fn now(&self) -> i64 {
+        chrono::Utc::now().timestamp() + self.clock_offset
+    }
+
+    fn currency(&self, currency_id: i64) -> Option<&Currency> {
+        self.currencies.iter().find(|c| c.id == currency_id)
+    }
+
+    fn currency_by_ticker(&self, ticker: &str) -> Option<&Currency> {
+        self.currencies.iter().find(|c| c.ticker.eq_ignore_ascii_case(ticker))
+    }
+
+    fn ticker(&self, currency_id: i64) -> String {
+        self.currency(currency_id).map(|c| c.ticker.clone()).unwrap_or_default()
+    }
+
+    fn account(&self, account_id: i64) -> Option<&Account> {
+        self.accounts.iter().find(|a| a.id == account_id)
+    }
+
+    fn account_mut(&mut self, account_id: i64) -> Option<&mut Account> {
+        self.accounts.iter_mut().find(|a| a.id == account_id)
+    }
+
+    fn find_account(&self, discord_id: i64, currency_id: i64) -> Option<&Account> {
+        self.accounts.iter().find(|a| a.discord_id == discord_id && a.currency_id == currency_id)
+    }
+
+    fn discord_id(&self, account_id: i64) -> Option<i64> {
+        self.account(account_id).map(|a| a.discord_id)
+    }
+
+    /// Account ID for a user and currency, inserting a zero-balance account if missing
+    fn get_or_create_account(&mut self, discord_id: i64, currency_id: i64) -> i64 {
+        if let Some(account) = self.find_account(discord_id, currency_id) {
+            return account.id;
+        }
+        let id = self.accounts.len() as i64 + 1;
+        self.accounts.push(Account { id, discord_id, currency_id, balance: Amount::ZERO });
+        id
+    }
+
+    fn credit(&mut self, account_id: i64, amount: Amount) {
+        if let Some(account) = self.account_mut(account_id) {
+            account.balance = account.balance + amount;
+        }
+    }
+
+    fn tax_account_mut(&mut self, currency_id: i64) -> Option<&mut TaxAccount> {
+        self.tax_accounts.iter_mut().find(|t| t.currency_id == currency_id)
+    }
+
+    fn log_transaction(&mut self, sender_id: i64, receiver_id: i64, amount: Amount, memo: Option<&str>) -> String {
+        let uuid = uuid::Uuid::new_v4().to_string();
+        let date_created = chrono::DateTime::from_timestamp(self.now(), 0)
+            .unwrap_or_default()
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        self.transactions.push(Transaction {
+            uuid: uuid.clone(),
+            sender_id,
+            receiver_id,
+            amount,
+            date_created,
+            memo: memo.map(str::to_string),
+        });
+        uuid
+    }
+
+    fn recurring_row(&self, payment: &RecurringPayment) -> RecurringRow {
+        (
+            payment.id,
+            payment.payer_id,
+            payment.recipient_id,
+            payment.currency_id,
+            self.ticker(payment.currency_id),
+            payment.amount,
+            payment.period.to_string(),
+            payment.next_run_at,
+            payment.failure_count,
+            payment.status.clone(),
+        )
+    }
+
+    fn recurring_payment_mut(&mut self, id: i64) -> Option<&mut RecurringPayment> {
+        self.recurring_payments.iter_mut().find(|p| p.id == id)
+    }
+
+    fn payroll_row(&self, payroll: &Payroll) -> PayrollRow {
+        (
+            payroll.id,
+            payroll.guild_id,
+            payroll.role_id,
+            payroll.currency_id,
+            self.ticker(payroll.currency_id),
+            payroll.amount,
+            payroll.source.to_string(),
+            payroll.period.to_string(),
+            payroll.next_run_at,
+            payroll.created_by,
+            payroll.status.clone(),
+        )
+    }
+
+    fn payroll_mut(&mut self, id: i64) -> Option<&mut Payroll> {
+        self.payrolls.iter_mut().find(|p| p.id == id)
+    }
+
+    fn invoice_row(&self, invoice: &Invoice) -> InvoiceRow {
+        (
+            invoice.id,
+            invoice.issuer_id,
+            invoice.payer_id,
+            invoice.currency_id,
+            self.ticker(invoice.currency_id),
+            invoice.amount,
+            invoice.memo.clone(),
+            invoice.status.clone(),
+            invoice.transaction_uuid.clone(),
+            invoice.expires_at,
+        )
+    }
+
+    fn invoice_mut(&mut self, id: i64) -> Option<&mut Invoice> {
+        self.invoices.iter_mut().find(|i| i.id == id)
+    }
+
+    fn wire_row(&self, wire: &Wire) -> WireRow {
+        (
+            wire.id,
+            wire.discord_id,
+            wire.currency_id,
+            self.ticker(wire.currency_id),
+            wire.direction.clone(),
+            wire.amount,
+            wire.fee,
+            wire.remote_amount,
+            wire.state.clone(),
+            wire.remote_before,
+            wire.last_error.clone(),
+            wire.date_created,
+            wire.date_updated,
+        )
+    }
+
+    fn wire_mut(&mut self, id: i64) -> Option<&mut Wire> {
+        self.wires.iter_mut().find(|w| w.id == id)
+    }
+
+    /// Change the user's balance by the wire's amount and the tax account by its fee,
+    /// and move it from `from` to `to`. Credits the user for "in", debits them for "out";
+    /// `reverse` swaps the two and takes the fee back.
+    fn move_wire_balance(&mut self, id: i64, from: &'static str, to: &str, reverse: bool) -> Result<Amount, LedgerError> {
+        let (discord_id, currency_id, credit, amount, fee) = match self.wires.iter().find(|w| w.id == id) {
+            Some(wire) if wire.state == from => {
+                (wire.discord_id, wire.currency_id, (wire.direction == "in") != reverse, wire.amount, wire.fee)
+            }
+            _ => return Err(LedgerError::WireState(from)),
+        };
+
+        let current_balance = match self.find_account(discord_id, currency_id) {
+            Some(account) => account.balance,
+            None if credit => Amount::ZERO,
+            None => return Err(LedgerError::InsufficientBalance { required: amount, available: Amount::ZERO }),
+        };
+        let new_balance = if credit { current_balance + amount } else { current_balance - amount };
+        if new_balance.is_negative() {
+            return Err(LedgerError::InsufficientBalance { required: amount, available: current_balance });
+        }
+        if !new_balance.is_within_limit() {
+            return Err(LedgerError::BalanceLimit { current: current_balance, new_balance });
+        }
+        if fee.is_positive() {
+            let tax_account = self.tax_account_mut(currency_id).ok_or(LedgerError::TaxAccountNotFound)?;
+            let new_tax_balance = if reverse { tax_account.balance - fee } else { tax_account.balance + fee };
+            if new_tax_balance.is_negative() {
+                return Err(LedgerError::InsufficientBalance { required: fee, available: tax_account.balance });
+            }
+            tax_account.balance = new_tax_balance;
+        }
+
+        let account_id = self.get_or_create_account(discord_id, currency_id);
+        self.credit(account_id, new_balance - current_balance);
+        let now = self.now();
+        if let Some(wire) = self.wire_mut(id) {
+            wire.state = to.to_string();
+            wire.date_updated = now;
+        }
+        Ok(new_balance)
+    }
+
+    /// Refund what is left of the maker's escrow and set the final status
+    fn close_swap(&mut self, swap_id: i64, new_status: &str) -> Result<(), StorageError> {
+        let swap = self.swaps.iter_mut()
+            .find(|s| s.id == swap_id)
+            .ok_or(StorageError::Rejected("Swap not found".to_string()))?;
+        if swap.status != "pending" {
+            return Err(StorageError::Rejected("Swap is not pending".to_string()));
+        }
+
+        swap.status = new_status.to_string();
+        let (maker_id, refund) = (swap.maker_id, swap.maker_amount - swap.filled_amount);
+        self.credit(maker_id, refund);
+        Ok(())
+    }
+}
+
+fn rejected(message: &str) -> StorageError {
+    StorageError::Rejected(message.to_string())
+}
+
+#[derive(Default)]
+pub struct MemoryStorage {
+    state: Mutex<State>,
+}
+
+/// Parse an amount in a test, e.g. `amount("12.5")`
+pub fn amount(s: &str) -> Amount {
+    s.parse().unwrap()
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    /// Create a currency with a tax account and funded holders, returning its ID
+    pub fn seed_currency(&self, ticker: &str, tax_percentage: i32, balances: &[(i64, &str)]) -> i64 {
+        let mut state = self.lock();
+        let id = state.currencies.len() as i64 + 1;
+        let date_created = state.now();
+        state.currencies.push(Currency {
+            id,
+            guild_id: 1000 + id,
+            name: format!("{} Coin", ticker),
+            ticker: ticker.to_string(),
+            swap_expiry_minutes: None,
+            date_created,
+        });
+        let tax_id = state.tax_accounts.len() as i64 + 1;
+        state.tax_accounts.push(TaxAccount { id: tax_id, currency_id: id, balance: Amount::ZERO, tax_percentage });
+
+        for (discord_id, balance) in balances {
+            let account_id = state.get_or_create_account(*discord_id, id);
+            state.credit(account_id, amount(balance));
+        }
+        id
+    }
+
+    /// Move the clock forward, e.g. past a swap's expiry
+    pub fn advance_minutes(&self, minutes: i64) {
+        self.lock().clock_offset += minutes * 60;
+    }
+
+    /// Every trade logged so far, oldest first
+    pub fn trades(&self) -> Vec<Trade> {
+        self.lock().trades.clone()
+    }
+
+    /// Everything that exists of a currency: balances, the tax account and swap escrow
+    pub fn total_supply(&self, currency_id: i64) -> Amount {
+        let state = self.lock();
+        let accounts: Amount = state.accounts.iter()
+            .filter(|a| a.currency_id == currency_id)
+            .map(|a| a.balance)
+            .sum();
+        let tax: Amount = state.tax_accounts.iter()
+            .filter(|t| t.currency_id == currency_id)
+            .map(|t| t.balance)
+            .sum();
+        let escrow: Amount = state.swaps.iter()
+            .filter(|s| s.maker_currency_id == currency_id && s.status == "pending")
+            .map(|s| s.maker_amount - s.filled_amount)
+            .sum();
+        accounts + tax + escrow
+    }
+}
+
+#[async_trait]
+impl CurrencyStore for MemoryStorage {
+    async fn create_currency(&self, guild_id: i64, name: &str, ticker: &str) -> Result<i64, StorageError> {
+        let mut state = self.lock();
+        if state.currencies.iter().any(|c| c.guild_id == guild_id || c.name == name || c.ticker == ticker) {
+            return Err(StorageError::Rejected(format!("Duplicate currency '{}'", ticker)));
+        }
+
+        let id = state.currencies.len() as i64 + 1;
+        let date_created = state.now();
+        state.currencies.push(Currency {
+            id,
+            guild_id,
+            name: name.to_string(),
+            ticker: ticker.to_string(),
+            swap_expiry_minutes: None,
+            date_created,
+        });
+        Ok(id)
+    }
+
+    async fn get_currency_by_guild(&self, guild_id: i64) -> Result<Option<(i64, String, String)>, StorageError> {
+        let state = self.lock();
+        Ok(state.currencies.iter()
+            .find(|c| c.guild_id == guild_id)
+            .map(|c| (c.id, c.name.clone(), c.ticker.clone())))
+    }
+
+    async fn get_currency_by_id(&self, currency_id: i64) -> Result<Option<(i64, i64, String, String)>, StorageError> {
+        let state = self.lock();
+        Ok(state.currency(currency_id).map(|c| (c.id, c.guild_id, c.name.clone(), c.ticker.clone())))
+    }
+
+    async fn get_currency_by_ticker(&self, ticker: &str) -> Result<Option<(i64, String, String)>, StorageError> {
+        let state = self.lock();
+        Ok(state.currency_by_ticker(ticker).map(|c| (c.id, c.name.clone(), c.ticker.clone())))
+    }
+
+    async fn get_currency_by_ticker_with_guild(&self, ticker: &str) -> Result<Option<(i64, i64, String, String)>, StorageError> {
+        let state = self.lock();
+        Ok(state.currency_by_ticker(ticker).map(|c| (c.id, c.guild_id, c.name.clone(), c.ticker.clone())))
+    }
+
+    async fn get_currency_date(&self, currency_id: i64) -> Result<Option<String>, StorageError> {
+        Ok(self.lock().currency(currency_id).map(|c| {
+            chrono::DateTime::from_timestamp(c.date_created, 0)
+                .unwrap_or_default()
+                .format("%Y-%m-%d")
+                .to_string()
+        }))
+    }
+
+    async fn get_currencies_paginated(
+        &self,
+        sort_by: &str,
+        page: usize,
+        page_size: usize,
+    ) -> Result<(Vec<(i64, String, String)>, i64), StorageError> {
+        let state = self.lock();
+        let mut currencies: Vec<&Currency> = state.currencies.iter().collect();
+        currencies.sort_by_key(|c| (c.date_created, c.id));
+        if sort_by.to_lowercase() == "recent" {
+            currencies.reverse();
+        }
+
+        Ok((
+            currencies.iter()
+                .skip(page.saturating_sub(1) * page_size)
+                .take(page_size)
+                .map(|c| (c.id, c.name.clone(), c.ticker.clone()))
+                .collect(),
+            state.currencies.len() as i64,
+        ))
+    }
+
+    async fn search_tickers(&self, prefix: &str, limit: i64) -> Result<Vec<(String, String)>, StorageError> {
+        let state = self.lock();
+        let prefix = prefix.to_uppercase();
+        let mut matches: Vec<(String, String)> = state.currencies.iter()
+            .filter(|c| c.ticker.to_uppercase().starts_with(&prefix))
+            .map(|c| (c.name.clone(), c.ticker.clone()))
+            .collect();
+        matches.sort_by(|a, b| a.1.cmp(&b.1));
+        matches.truncate(limit.max(0) as usize);
+        Ok(matches)
+    }
+
+    async fn get_swap_expiry_minutes(&self, currency_id: i64) -> Result<Option<i64>, StorageError> {
+        Ok(self.lock().currency(currency_id).and_then(|c| c.swap_expiry_minutes))
+    }
+
+    async fn set_swap_expiry_minutes(&self, currency_id: i64, minutes: Option<i64>) -> Result<(), StorageError> {
+        if let Some(currency) = self.lock().currencies.iter_mut().find(|c| c.id == currency_id) {
+            currency.swap_expiry_minutes = minutes;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AccountStore for MemoryStorage {
+    async fn create_account(&self, discord_id: i64, currency_id: i64) -> Result<i64, StorageError> {
+        let mut state = self.lock();
+        if state.find_account(discord_id, currency_id).is_some() {
+            return Err(rejected("Account already exists"));
+        }
+        Ok(state.get_or_create_account(discord_id, currency_id))
+    }
+
+    async fn get_account_id(&self, discord_id: i64, currency_id: i64) -> Result<Option<i64>, StorageError> {
+        Ok(self.lock().find_account(discord_id, currency_id).map(|a| a.id))
+    }
+
+    async fn get_account_balance(&self, discord_id: i64, currency_id: i64) -> Result<Option<Amount>, StorageError> {
+        Ok(self.lock().find_account(discord_id, currency_id).map(|a| a.balance))
+    }
+
+    async fn get_discord_id_by_account_id(&self, account_id: i64) -> Result<Option<i64>, StorageError> {
+        Ok(self.lock().discord_id(account_id))
+    }
+
+    async fn get_total_balance(&self, currency_id: i64) -> Result<Option<Amount>, StorageError> {
+        let state = self.lock();
+        let mut balances = state.accounts.iter().filter(|a| a.currency_id == currency_id).map(|a| a.balance).peekable();
+        Ok(balances.peek().is_some().then(|| balances.sum()))
+    }
+
+    async fn update_balance(&self, account_id: i64, amount: Amount) -> Result<(), StorageError> {
+        self.lock().credit(account_id, amount);
+        Ok(())
+    }
+
+    async fn transfer(
+        &self,
+        sender_discord_id: i64,
+        receiver_discord_id: i64,
+        currency_id: i64,
+        amount: Amount,
+        memo: Option<&str>,
+    ) -> Result<TransferReceipt, LedgerError> {
+        if sender_discord_id == receiver_discord_id {
+            return Err(LedgerError::DuplicateAccount);
+        }
+
+        let mut state = self.lock();
+
+        let sender = state.find_account(sender_discord_id, currency_id).ok_or(LedgerError::AccountNotFound)?;
+        let (sender_account_id, sender_balance) = (sender.id, sender.balance);
+        let receiver_balance = state.find_account(receiver_discord_id, currency_id)
+            .map(|a| a.balance)
+            .unwrap_or(Amount::ZERO);
+
+        let tax_percentage = state.tax_accounts.iter()
+            .find(|t| t.currency_id == currency_id)
+            .map(|t| t.tax_percentage)
+            .unwrap_or(0);
+        let tax_amount = if tax_percentage > 0 { amount.percent(tax_percentage) } else { Amount::ZERO };
+        let total_deduction = amount + tax_amount;
+
+        if sender_balance < total_deduction {
+            return Err(LedgerError::InsufficientBalance {
+                required: total_deduction,
+                available: sender_balance,
+            });
+        }
+
+        let receiver_new_balance = receiver_balance + amount;
+        if !receiver_new_balance.is_within_limit() {
+            return Err(LedgerError::BalanceLimit {
+                current: receiver_balance,
+                new_balance: receiver_new_balance,
+            });
+        }
+
+        let receiver_account_id = state.get_or_create_account(receiver_discord_id, currency_id);
+        state.credit(sender_account_id, -total_deduction);
+        state.credit(receiver_account_id, amount);
+        if tax_amount.is_positive() {
+            if let Some(tax_account) = state.tax_account_mut(currency_id) {
+                tax_account.balance = tax_account.balance + tax_amount;
+            }
+        }
+
+        let transaction_uuid = state.log_transaction(sender_account_id, receiver_account_id, amount, memo);
+        Ok(TransferReceipt { transaction_uuid, tax_amount })
+    }
+
+    async fn transfer_many(
+        &self,
+        sender_discord_id: i64,
+        currency_id: i64,
+        payments: &[(i64, Amount)],
+        memo: Option<&str>,
+    ) -> Result<BatchTransferReceipt, LedgerError> {
+        let mut state = self.lock();
+
+        let mut discord_ids: Vec<i64> = payments.iter().map(|(id, _)| *id).collect();
+        discord_ids.push(sender_discord_id);
+        discord_ids.sort_unstable();
+        if discord_ids.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err(LedgerError::DuplicateAccount);
+        }
+
+        let sender = state.find_account(sender_discord_id, currency_id).ok_or(LedgerError::AccountNotFound)?;
+        let (sender_account_id, sender_balance) = (sender.id, sender.balance);
+
+        let tax_percentage = state.tax_accounts.iter()
+            .find(|t| t.currency_id == currency_id)
+            .map(|t| t.tax_percentage)
+            .unwrap_or(0);
+        let total: Amount = payments.iter().map(|(_, amount)| *amount).sum();
+        let tax_amount = if tax_percentage > 0 { total.percent(tax_percentage) } else { Amount::ZERO };
+        let total_deduction = total + tax_amount;
+
+        if sender_balance < total_deduction {
+            return Err(LedgerError::InsufficientBalance {
+                required: total_deduction,
+                available: sender_balance,
+            });
+        }
+
+        for (receiver_discord_id, amount) in payments {
+            let receiver_balance = state.find_account(*receiver_discord_id, currency_id)
+                .map(|a| a.balance)
+                .unwrap_or(Amount::ZERO);
+            let receiver_new_balance = receiver_balance + *amount;
+            if !receiver_new_balance.is_within_limit() {
+                return Err(LedgerError::BalanceLimit {
+                    current: receiver_balance,
+                    new_balance: receiver_new_balance,
+                });
+            }
+        }
+
+        state.credit(sender_account_id, -total_deduction);
+        let mut transaction_uuids = Vec::with_capacity(payments.len());
+        for (receiver_discord_id, amount) in payments {
+            let receiver_account_id = state.get_or_create_account(*receiver_discord_id, currency_id);
+            state.credit(receiver_account_id, *amount);
+            transaction_uuids.push(state.log_transaction(sender_account_id, receiver_account_id, *amount, memo));
+        }
+        if tax_amount.is_positive() {
+            if let Some(tax_account) = state.tax_account_mut(currency_id) {
+                tax_account.balance = tax_account.balance + tax_amount;
+            }
+        }
+
+        Ok(BatchTransferReceipt { transaction_uuids, tax_amount })
+    }
+
+    async fn mint(&self, discord_id: i64, currency_id: i64, amount: Amount) -> Result<(Amount, Amount), LedgerError> {
+        let mut state = self.lock();
+
+        let current_balance = state.find_account(discord_id, currency_id)
+            .map(|a| a.balance)
+            .unwrap_or(Amount::ZERO);
+        let new_balance = current_balance + amount;
+        if new_balance.is_negative() {
+            return Err(LedgerError::NegativeBalance { current: current_balance, new_balance });
+        }
+        if !new_balance.is_within_limit() {
+            return Err(LedgerError::BalanceLimit { current: current_balance, new_balance });
+        }
+
+        let account_id = state.get_or_create_account(discord_id, currency_id);
+        state.credit(account_id, amount);
+        Ok((current_balance, new_balance))
+    }
+
+    async fn payout(
+        &self,
+        issuer_discord_id: i64,
+        recipient_discord_id: i64,
+        currency_id: i64,
+        amount: Amount,
+        source: PayrollSource,
+    ) -> Result<String, LedgerError> {
+        let mut state = self.lock();
+
+        let recipient_balance = state.find_account(recipient_discord_id, currency_id)
+            .map(|a| a.balance)
+            .unwrap_or(Amount::ZERO);
+        let new_balance = recipient_balance + amount;
+        if !new_balance.is_within_limit() {
+            return Err(LedgerError::BalanceLimit { current: recipient_balance, new_balance });
+        }
+
+        if source == PayrollSource::Treasury {
+            let tax_account = state.tax_account_mut(currency_id).ok_or(LedgerError::TaxAccountNotFound)?;
+            if tax_account.balance < amount {
+                return Err(LedgerError::InsufficientBalance {
+                    required: amount,
+                    available: tax_account.balance,
+                });
+            }
+            tax_account.balance = tax_account.balance - amount;
+        }
+
+        let issuer_account_id = state.get_or_create_account(issuer_discord_id, currency_id);
+        let recipient_account_id = state.get_or_create_account(recipient_discord_id, currency_id);
+        state.credit(recipient_account_id, amount);
+        Ok(state.log_transaction(issuer_account_id, recipient_account_id, amount, None))
+    }
+}
+
+#[async_trait]
+impl SwapStore for MemoryStorage {
+    async fn create_swap(&self, swap: NewSwap) -> Result<i64, StorageError> {
+        let mut state = self.lock();
+
+        let maker_balance = state.account(swap.maker_account_id).map(|a| a.balance);
+        if maker_balance.is_none_or(|balance| balance < swap.maker_amount) {
+            return Err(rejected("Maker has insufficient balance"));
+        }
+        state.credit(swap.maker_account_id, -swap.maker_amount);
+
+        let id = state.swaps.len() as i64 + 1;
+        let expires_at = swap.expiry_minutes.map(|minutes| state.now() + minutes * 60);
+        state.swaps.push(Swap {
+            id,
+            maker_id: swap.maker_account_id,
+            taker_id: swap.taker_account_id,
+            maker_currency_id: swap.maker_currency_id,
+            taker_currency_id: swap.taker_currency_id,
+            maker_amount: swap.maker_amount,
+            taker_amount: swap.taker_amount,
+            filled_amount: Amount::ZERO,
+            status: "pending".to_string(),
+            expires_at,
+        });
+        Ok(id)
+    }
+
+    async fn accept_swap(
+        &self,
+        swap_id: i64,
+        taker_discord_id: i64,
+        fill_amount: Option<Amount>,
+    ) -> Result<(Amount, Amount, bool), StorageError> {
+        let mut state = self.lock();
+        let now = state.now();
+
+        let swap = state.swaps.iter().find(|s| s.id == swap_id).ok_or(rejected("Swap not found"))?;
+        if swap.status != "pending" {
+            return Err(rejected("Swap is not pending"));
+        }
+        if swap.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Err(rejected("Swap has expired"));
+        }
+        let (maker_account_id, taker_account_id) = (swap.maker_id, swap.taker_id);
+        let (maker_currency_id, taker_currency_id) = (swap.maker_currency_id, swap.taker_currency_id);
+        let (maker_amount, taker_amount, filled_amount) = (swap.maker_amount, swap.taker_amount, swap.filled_amount);
+
+        let maker_discord_id = state.discord_id(maker_account_id).ok_or(rejected("Maker Discord ID not found"))?;
+        let designated_taker = match taker_account_id {
+            Some(account_id) => Some(state.discord_id(account_id).ok_or(rejected("Taker Discord ID not found"))?),
+            None => None,
+        };
+
+        match designated_taker {
+            Some(discord_id) if discord_id != taker_discord_id => {
+                return Err(rejected("Not authorized to accept this swap"));
+            }
+            None if taker_discord_id == maker_discord_id => {
+                return Err(rejected("Cannot accept your own open swap"));
+            }
+            _ => {}
+        }
+
+        // Work out the size of this fill
+        let remaining = maker_amount - filled_amount;
+        let fill_maker_amount = fill_amount.unwrap_or(remaining);
+        if !fill_maker_amount.is_positive() {
+            return Err(rejected("Fill amount must be greater than 0"));
+        }
+        if fill_maker_amount > remaining {
+            return Err(rejected("Fill amount exceeds the remaining amount of the swap"));
+        }
+        let fully_filled = fill_maker_amount == remaining;
+        if !fully_filled && designated_taker.is_some() {
+            return Err(rejected("Only open swaps can be partially filled"));
+        }
+
+        let taker_paid: Amount = state.swap_fills.iter()
+            .filter(|f| f.swap_id == swap_id)
+            .map(|f| f.taker_amount)
+            .sum();
+        let fill_taker_amount = swaps::taker_payment(fill_maker_amount, remaining, (maker_amount, taker_amount), taker_paid);
+
+        let user_taker_balance = state.find_account(taker_discord_id, taker_currency_id)
+            .map(|a| a.balance)
+            .unwrap_or(Amount::ZERO);
+        if user_taker_balance < fill_taker_amount {
+            return Err(rejected("Insufficient balance to accept swap"));
+        }
+
+        // Accepting user gives their currency and gets the maker's
+        let user_taker_account_id = state.get_or_create_account(taker_discord_id, taker_currency_id);
+        let user_maker_account_id = state.get_or_create_account(taker_discord_id, maker_currency_id);
+        state.credit(user_taker_account_id, -fill_taker_amount);
+        state.credit(user_maker_account_id, fill_maker_amount);
+
+        let maker_taker_account_id = state.get_or_create_account(maker_discord_id, taker_currency_id);
+        state.credit(maker_taker_account_id, fill_taker_amount);
+
+        state.log_transaction(user_taker_account_id, maker_taker_account_id, fill_taker_amount, None);
+        state.log_transaction(maker_account_id, user_maker_account_id, fill_maker_amount, None);
+        state.swap_fills.push(SwapFill { swap_id, taker_amount: fill_taker_amount });
+
+        let swap = state.swaps.iter_mut().find(|s| s.id == swap_id).ok_or(rejected("Swap not found"))?;
+        swap.filled_amount = swap.filled_amount + fill_maker_amount;
+        if fully_filled {
+            swap.status = "accepted".to_string();
+            swap.taker_id = swap.taker_id.or(Some(user_taker_account_id));
+        }
+
+        Ok((fill_maker_amount, fill_taker_amount, fully_filled))
+    }
+
+    async fn cancel_swap(&self, swap_id: i64) -> Result<(), StorageError> {
+        self.lock().close_swap(swap_id, "cancelled")
+    }
+
+    async fn expire_swap(&self, swap_id: i64) -> Result<(), StorageError> {
+        self.lock().close_swap(swap_id, "expired")
+    }
+
+    async fn get_swap_by_id(&self, swap_id: i64) -> Result<Option<SwapRow>, StorageError> {
+        let state = self.lock();
+        Ok(state.swaps.iter().find(|s| s.id == swap_id).map(|s| (
+            s.id,
+            s.maker_id,
+            s.taker_id,
+            s.maker_currency_id,
+            s.taker_currency_id,
+            s.maker_amount,
+            s.taker_amount,
+            s.status.clone(),
+        )))
+    }
+
+    async fn get_swap_expiry(&self, swap_id: i64) -> Result<Option<i64>, StorageError> {
+        Ok(self.lock().swaps.iter().find(|s| s.id == swap_id).and_then(|s| s.expires_at))
+    }
+
+    async fn get_swap_fill_progress(&self, swap_id: i64) -> Result<Option<(Amount, i64)>, StorageError> {
+        let state = self.lock();
+        let fills = state.swap_fills.iter().filter(|f| f.swap_id == swap_id).count() as i64;
+        Ok(state.swaps.iter().find(|s| s.id == swap_id).map(|s| (s.filled_amount, fills)))
+    }
+
+    async fn get_total_swap_maker_amount(&self, currency_id: i64) -> Result<Option<Amount>, StorageError> {
+        let state = self.lock();
+        let mut escrow = state.swaps.iter()
+            .filter(|s| s.maker_currency_id == currency_id && s.status == "pending")
+            .map(|s| s.maker_amount - s.filled_amount)
+            .peekable();
+        Ok(escrow.peek().is_some().then(|| escrow.sum()))
+    }
+
+    async fn get_total_order_escrow(&self, _currency_id: i64) -> Result<Option<Amount>, StorageError> {
+        Ok(None)
+    }
+
+    async fn get_overdue_swaps(&self, limit: i64) -> Result<Vec<OverdueSwapRow>, StorageError> {
+        let state = self.lock();
+        let now = state.now();
+
+        let mut overdue: Vec<&Swap> = state.swaps.iter()
+            .filter(|s| s.status == "pending" && s.expires_at.is_some_and(|expires_at| expires_at <= now))
+            .collect();
+        overdue.sort_by_key(|s| s.expires_at);
+
+        Ok(overdue.into_iter()
+            .take(limit.max(0) as usize)
+            .map(|s| (
+                s.id,
+                state.discord_id(s.maker_id).unwrap_or_default(),
+                s.taker_id.and_then(|id| state.discord_id(id)),
+                state.ticker(s.maker_currency_id),
+                state.ticker(s.taker_currency_id),
+                s.maker_amount - s.filled_amount,
+                s.taker_amount,
+            ))
+            .collect())
+    }
+
+    async fn store_swap_message(&self, swap_id: i64, channel_id: i64, message_id: i64) -> Result<(), StorageError> {
+        self.lock().swap_messages.push((swap_id, channel_id, message_id));
+        Ok(())
+    }
+
+    async fn get_swap_messages(&self, swap_id: i64) -> Result<Vec<(i64, i64)>, StorageError> {
+        Ok(self.lock().swap_messages.iter()
+            .filter(|(id, _, _)| *id == swap_id)
+            .map(|(_, channel_id, message_id)| (*channel_id, *message_id))
+            .collect())
+    }
+
+    async fn get_swaps_paginated(
+        &self,
+        page: usize,
+        page_size: usize,
+        sort: SwapSort,
+        filter: &SwapFilter,
+    ) -> Result<(Vec<SwapListRow>, i64), StorageError> {
+        let state = self.lock();
+        let matches_ticker = |wanted: &Option<String>, currency_id: i64| {
+            wanted.as_ref().is_none_or(|ticker| state.ticker(currency_id).eq_ignore_ascii_case(ticker))
+        };
+
+        let mut swaps: Vec<&Swap> = state.swaps.iter()
+            .filter(|s| filter.status == SwapStatus::All || s.status == filter.status.as_str())
+            .filter(|s| matches_ticker(&filter.maker_ticker, s.maker_currency_id))
+            .filter(|s| matches_ticker(&filter.taker_ticker, s.taker_currency_id))
+            .collect();
+
+        // Swaps are created in ID order, and the ID breaks ties like the SQL does
+        swaps.sort_by(|a, b| match sort {
+            SwapSort::Oldest => a.id.cmp(&b.id),
+            SwapSort::Latest => b.id.cmp(&a.id),
+            SwapSort::HighMaker => b.maker_amount.cmp(&a.maker_amount).then(b.id.cmp(&a.id)),
+            SwapSort::LowMaker => a.maker_amount.cmp(&b.maker_amount).then(b.id.cmp(&a.id)),
+            SwapSort::HighTaker => b.taker_amount.cmp(&a.taker_amount).then(b.id.cmp(&a.id)),
+            SwapSort::LowTaker => a.taker_amount.cmp(&b.taker_amount).then(b.id.cmp(&a.id)),
+        });
+
+        let total = swaps.len() as i64;
+        let rows = swaps.into_iter()
+            .skip((page.max(1) - 1) * page_size)
+            .take(page_size)
+            .map(|s| (
+                s.id,
+                state.discord_id(s.maker_id).unwrap_or_default(),
+                s.taker_id.and_then(|id| state.discord_id(id)),
+                s.maker_currency_id,
+                s.taker_currency_id,
+                s.maker_amount,
+                s.taker_amount,
+                s.status.clone(),
+                state.ticker(s.maker_currency_id),
+                state.ticker(s.taker_currency_id),
+                s.filled_amount,
+            ))
+            .collect();
+
+        Ok((rows, total))
+    }
+}
+
+#[async_trait]
+impl TransactionStore for MemoryStorage {
+    async fn get_transaction_by_uuid(&self, uuid: &str) -> Result<Option<(i64, i64, String, Amount, String, Option<String>)>, StorageError> {
+        let state = self.lock();
+        Ok(state.transactions.iter()
+            .find(|t| t.uuid == uuid)
+            .map(|t| (t.sender_id, t.receiver_id, t.date_created.clone(), t.amount, t.uuid.clone(), t.memo.clone())))
+    }
+
+    async fn get_user_transactions_paginated(
+        &self,
+        discord_id: i64,
+        page: usize,
+        page_size: usize,
+        memo_search: Option<&str>,
+    ) -> Result<(Vec<TransactionRow>, i64), StorageError> {
+        let state = self.lock();
+        let memo_search = memo_search.map(str::to_lowercase);
+        let account_ids: Vec<i64> = state.accounts.iter()
+            .filter(|a| a.discord_id == discord_id)
+            .map(|a| a.id)
+            .collect();
+
+        let transactions: Vec<&Transaction> = state.transactions.iter()
+            .rev()
+            .filter(|t| account_ids.contains(&t.sender_id) || account_ids.contains(&t.receiver_id))
+            .filter(|t| match (&memo_search, &t.memo) {
+                (None, _) => true,
+                (Some(search), Some(memo)) => memo.to_lowercase().contains(search.as_str()),
+                (Some(_), None) => false,
+            })
+            .collect();
+
+        let total = transactions.len() as i64;
+        let rows = transactions.into_iter()
+            .skip((page.max(1) - 1) * page_size)
+            .take(page_size)
+            .map(|t| {
+                let ticker = state.account(t.sender_id).map(|a| state.ticker(a.currency_id)).unwrap_or_default();
+                (t.sender_id, t.receiver_id, t.amount, t.date_created.clone(), t.uuid.clone(), ticker, t.memo.clone())
+            })
+            .collect();
+
+        Ok((rows, total))
+    }
+
+    async fn find_transaction_by_memo(
+        &self,
+        sender_discord_id: i64,
+        receiver_discord_id: i64,
+        currency_id: i64,
+        memo: &str,
+    ) -> Result<Option<String>, StorageError> {
+        let state = self.lock();
+        let sender = state.find_account(sender_discord_id, currency_id).map(|a| a.id);
+        let receiver = state.find_account(receiver_discord_id, currency_id).map(|a| a.id);
+        Ok(state.transactions.iter()
+            .rev()
+            .find(|t| Some(t.sender_id) == sender && Some(t.receiver_id) == receiver && t.memo.as_deref() == Some(memo))
+            .map(|t| t.uuid.clone()))
+    }
+}
+
+#[async_trait]
+impl TradeLogStore for MemoryStorage {
+    async fn add_price_log(
+        &self,
+        base_currency_id: i64,
+        quote_currency_id: i64,
+        price: Amount,
+        volume: Amount,
+        quote_volume: Amount,
+    ) -> Result<i64, StorageError> {
+        let mut state = self.lock();
+        state.trades.push((base_currency_id, quote_currency_id, price, volume, quote_volume));
+        Ok(state.trades.len() as i64)
+    }
+}
+
+#[async_trait]
+impl TaxStore for MemoryStorage {
+    async fn get_tax_account(&self, currency_id: i64) -> Result<Option<(i64, i64, Amount, i32)>, StorageError> {
+        let state = self.lock();
+        Ok(state.tax_accounts.iter()
+            .find(|t| t.currency_id == currency_id)
+            .map(|t| (t.id, t.currency_id, t.balance, t.tax_percentage)))
+    }
+
+    async fn create_tax_account(&self, currency_id: i64, tax_percentage: i32) -> Result<i64, StorageError> {
+        let mut state = self.lock();
+        if state.tax_account_mut(currency_id).is_some() {
+            return Err(rejected("Tax account already exists"));
+        }
+
+        let id = state.tax_accounts.len() as i64 + 1;
+        state.tax_accounts.push(TaxAccount { id, currency_id, balance: Amount::ZERO, tax_percentage });
+        Ok(id)
+    }
+
+    async fn set_tax_percentage(&self, currency_id: i64, tax_percentage: i32) -> Result<(), StorageError> {
+        if let Some(tax_account) = self.lock().tax_account_mut(currency_id) {
+            tax_account.tax_percentage = tax_percentage;
+        }
+        Ok(())
+    }
+
+    async fn get_tax_percentage(&self, currency_id: i64) -> Result<Option<i32>, StorageError> {
+        let state = self.lock();
+        Ok(state.tax_accounts.iter().find(|t| t.currency_id == currency_id).map(|t| t.tax_percentage))
+    }
+
+    async fn get_total_tax_balance(&self, currency_id: i64) -> Result<Option<Amount>, StorageError> {
+        let state = self.lock();
+        Ok(Some(state.tax_accounts.iter().find(|t| t.currency_id == currency_id).map_or(Amount::ZERO, |t| t.balance)))
+    }
+
+    async fn add_tax(&self, currency_id: i64, amount: Amount) -> Result<(), StorageError> {
+        if let Some(tax_account) = self.lock().tax_account_mut(currency_id) {
+            tax_account.balance = tax_account.balance + amount;
+        }
+        Ok(())
+    }
+
+    async fn collect_tax(&self, discord_id: i64, currency_id: i64, amount: Option<Amount>) -> Result<Amount, LedgerError> {
+        let mut state = self.lock();
+
+        let tax_balance = state.tax_account_mut(currency_id)
+            .map(|t| t.balance)
+            .ok_or(LedgerError::TaxAccountNotFound)?;
+        let collect_amount = amount.unwrap_or(tax_balance);
+        if collect_amount > tax_balance {
+            return Err(LedgerError::InsufficientBalance {
+                required: collect_amount,
+                available: tax_balance,
+            });
+        }
+
+        let account_balance = state.find_account(discord_id, currency_id)
+            .map(|a| a.balance)
+            .unwrap_or(Amount::ZERO);
+        let new_balance = account_balance + collect_amount;
+        if !new_balance.is_within_limit() {
+            return Err(LedgerError::BalanceLimit { current: account_balance, new_balance });
+        }
+
+        let account_id = state.get_or_create_account(discord_id, currency_id);
+        state.credit(account_id, collect_amount);
+        if let Some(tax_account) = state.tax_account_mut(currency_id) {
+            tax_account.balance = tax_balance - collect_amount;
+        }
+        Ok(collect_amount)
+    }
+}
+
+#[async_trait]
+impl RecurringStore for MemoryStorage {
+    async fn create_recurring_payment(&self, payment: NewRecurringPayment) -> Result<i64, StorageError> {
+        let mut state = self.lock();
+        let id = state.recurring_payments.len() as i64 + 1;
+        state.recurring_payments.push(RecurringPayment {
+            id,
+            payer_id: payment.payer_id,
+            recipient_id: payment.recipient_id,
+            currency_id: payment.currency_id,
+            amount: payment.amount,
+            period: payment.period.as_str(),
+            next_run_at: payment.next_run_at,
+            failure_count: 0,
+            status: "active".to_string(),
+        });
+        Ok(id)
+    }
+
+    async fn get_recurring_payment(&self, id: i64) -> Result<Option<RecurringRow>, StorageError> {
+        let state = self.lock();
+        Ok(state.recurring_payments.iter().find(|p| p.id == id).map(|p| state.recurring_row(p)))
+    }
+
+    async fn get_recurring_payments_by_payer(&self, payer_id: i64) -> Result<Vec<RecurringRow>, StorageError> {
+        let state = self.lock();
+        Ok(state.recurring_payments.iter()
+            .filter(|p| p.payer_id == payer_id && p.status != "cancelled")
+            .map(|p| state.recurring_row(p))
+            .collect())
+    }
+
+    async fn get_due_recurring_payments(&self, limit: i64) -> Result<Vec<RecurringRow>, StorageError> {
+        let state = self.lock();
+        let now = state.now();
+        let mut due: Vec<&RecurringPayment> = state.recurring_payments.iter()
+            .filter(|p| p.status == "active" && p.next_run_at <= now)
+            .collect();
+        due.sort_by_key(|p| (p.next_run_at, p.id));
+        Ok(due.into_iter().take(limit as usize).map(|p| state.recurring_row(p)).collect())
+    }
+
+    async fn reschedule_recurring_payment(&self, id: i64, due_at: i64, next_run_at: i64) -> Result<bool, StorageError> {
+        let mut state = self.lock();
+        match state.recurring_payment_mut(id) {
+            Some(payment) if payment.status == "active" && payment.next_run_at == due_at => {
+                payment.next_run_at = next_run_at;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn record_recurring_failure(&self, id: i64) -> Result<i32, StorageError> {
+        let mut state = self.lock();
+        let payment = state.recurring_payment_mut(id).ok_or(rejected("Recurring payment not found"))?;
+        payment.failure_count += 1;
+        Ok(payment.failure_count)
+    }
+
+    async fn reset_recurring_failures(&self, id: i64) -> Result<(), StorageError> {
+        if let Some(payment) = self.lock().recurring_payment_mut(id) {
+            payment.failure_count = 0;
+        }
+        Ok(())
+    }
+
+    async fn set_recurring_status(&self, id: i64, status: &str) -> Result<(), StorageError> {
+        if let Some(payment) = self.lock().recurring_payment_mut(id) {
+            payment.status = status.to_string();
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PayrollStore for MemoryStorage {
+    async fn create_payroll(&self, payroll: NewPayroll) -> Result<i64, StorageError> {
+        let mut state = self.lock();
+        let id = state.payrolls.len() as i64 + 1;
+        state.payrolls.push(Payroll {
+            id,
+            guild_id: payroll.guild_id,
+            role_id: payroll.role_id,
+            currency_id: payroll.currency_id,
+            amount: payroll.amount,
+            source: payroll.source.as_str(),
+            period: payroll.period.as_str(),
+            next_run_at: payroll.next_run_at,
+            created_by: payroll.created_by,
+            status: "active".to_string(),
+        });
+        Ok(id)
+    }
+
+    async fn get_payroll(&self, id: i64) -> Result<Option<PayrollRow>, StorageError> {
+        let state = self.lock();
+        Ok(state.payrolls.iter().find(|p| p.id == id).map(|p| state.payroll_row(p)))
+    }
+
+    async fn get_payrolls_by_guild(&self, guild_id: i64) -> Result<Vec<PayrollRow>, StorageError> {
+        let state = self.lock();
+        Ok(state.payrolls.iter()
+            .filter(|p| p.guild_id == guild_id && p.status == "active")
+            .map(|p| state.payroll_row(p))
+            .collect())
+    }
+
+    async fn get_due_payrolls(&self, limit: i64) -> Result<Vec<PayrollRow>, StorageError> {
+        let state = self.lock();
+        let now = state.now();
+        let mut due: Vec<&Payroll> = state.payrolls.iter()
+            .filter(|p| p.status == "active" && p.next_run_at <= now)
+            .collect();
+        due.sort_by_key(|p| (p.next_run_at, p.id));
+        Ok(due.into_iter().take(limit as usize).map(|p| state.payroll_row(p)).collect())
+    }
+
+    async fn reschedule_payroll(&self, id: i64, due_at: i64, next_run_at: i64) -> Result<bool, StorageError> {
+        let mut state = self.lock();
+        match state.payroll_mut(id) {
+            Some(payroll) if payroll.status == "active" && payroll.next_run_at == due_at => {
+                payroll.next_run_at = next_run_at;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn set_payroll_status(&self, id: i64, status: &str) -> Result<(), StorageError> {
+        if let Some(payroll) = self.lock().payroll_mut(id) {
+            payroll.status = status.to_string();
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl InvoiceStore for MemoryStorage {
+    async fn create_invoice(&self, invoice: NewInvoice) -> Result<i64, StorageError> {
+        let mut state = self.lock();
+        let id = state.invoices.len() as i64 + 1;
+        let date_updated = state.now();
+        state.invoices.push(Invoice {
+            id,
+            issuer_id: invoice.issuer_id,
+            payer_id: invoice.payer_id,
+            currency_id: invoice.currency_id,
+            amount: invoice.amount,
+            memo: invoice.memo,
+            status: "open".to_string(),
+            transaction_uuid: None,
+            expires_at: invoice.expires_at,
+            date_updated,
+        });
+        Ok(id)
+    }
+
+    async fn get_invoice(&self, id: i64) -> Result<Option<InvoiceRow>, StorageError> {
+        let state = self.lock();
+        Ok(state.invoices.iter().find(|i| i.id == id).map(|i| state.invoice_row(i)))
+    }
+
+    async fn get_invoices_by_user(&self, discord_id: i64, status: Option<&str>, limit: i64) -> Result<Vec<InvoiceRow>, StorageError> {
+        let state = self.lock();
+        Ok(state.invoices.iter()
+            .rev()
+            .filter(|i| i.issuer_id == discord_id || i.payer_id == discord_id)
+            .filter(|i| status.is_none_or(|status| i.status == status))
+            .take(limit as usize)
+            .map(|i| state.invoice_row(i))
+            .collect())
+    }
+
+    async fn get_overdue_invoices(&self, limit: i64) -> Result<Vec<InvoiceRow>, StorageError> {
+        let state = self.lock();
+        let now = state.now();
+        let mut overdue: Vec<&Invoice> = state.invoices.iter()
+            .filter(|i| i.status == "open" && i.expires_at <= now)
+            .collect();
+        overdue.sort_by_key(|i| (i.expires_at, i.id));
+        Ok(overdue.into_iter().take(limit as usize).map(|i| state.invoice_row(i)).collect())
+    }
+
+    async fn get_stalled_invoices(&self, idle_seconds: i64, limit: i64) -> Result<Vec<InvoiceRow>, StorageError> {
+        let state = self.lock();
+        let cutoff = state.now() - idle_seconds;
+        Ok(state.invoices.iter()
+            .filter(|i| i.status == "paying" && i.date_updated <= cutoff)
+            .take(limit as usize)
+            .map(|i| state.invoice_row(i))
+            .collect())
+    }
+
+    async fn claim_invoice(&self, id: i64) -> Result<bool, StorageError> {
+        let mut state = self.lock();
+        let now = state.now();
+        match state.invoice_mut(id) {
+            Some(invoice) if invoice.status == "open" && invoice.expires_at > now => {
+                invoice.status = "paying".to_string();
+                invoice.date_updated = now;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn set_invoice_status(&self, id: i64, from: &str, to: &str) -> Result<bool, StorageError> {
+        let mut state = self.lock();
+        let now = state.now();
+        match state.invoice_mut(id) {
+            Some(invoice) if invoice.status == from => {
+                invoice.status = to.to_string();
+                invoice.date_updated = now;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn complete_invoice(&self, id: i64, transaction_uuid: &str) -> Result<bool, StorageError> {
+        let mut state = self.lock();
+        let now = state.now();
+        match state.invoice_mut(id) {
+            Some(invoice) if invoice.status == "paying" => {
+                invoice.status = "paid".to_string();
+                invoice.transaction_uuid = Some(transaction_uuid.to_string());
+                invoice.date_updated = now;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+#[async_trait]
+impl WireStore for MemoryStorage {
+    async fn create_wire(&self, wire: NewWire) -> Result<i64, StorageError> {
+        let mut state = self.lock();
+        let id = state.wires.len() as i64 + 1;
+        let now = state.now();
+        state.wires.push(Wire {
+            id,
+            discord_id: wire.discord_id,
+            currency_id: wire.currency_id,
+            direction: wire.direction,
+            amount: wire.amount,
+            fee: wire.fee,
+            remote_amount: wire.remote_amount,
+            state: "initiated".to_string(),
+            remote_before: None,
+            last_error: None,
+            date_created: now,
+            date_updated: now,
+        });
+        Ok(id)
+    }
+
+    async fn get_wire(&self, id: i64) -> Result<Option<WireRow>, StorageError> {
+        let state = self.lock();
+        Ok(state.wires.iter().find(|w| w.id == id).map(|w| state.wire_row(w)))
+    }
+
+    async fn get_wires_by_user(&self, discord_id: i64, limit: i64) -> Result<Vec<WireRow>, StorageError> {
+        let state = self.lock();
+        Ok(state.wires.iter()
+            .rev()
+            .filter(|w| w.discord_id == discord_id)
+            .take(limit as usize)
+            .map(|w| state.wire_row(w))
+            .collect())
+    }
+
+    async fn get_unsettled_wires(&self, currency_id: Option<i64>, idle_seconds: i64, limit: i64) -> Result<Vec<WireRow>, StorageError> {
+        let state = self.lock();
+        let cutoff = state.now() - idle_seconds;
+        Ok(state.wires.iter()
+            .filter(|w| w.state == "initiated" || w.state == "smite_applied")
+            .filter(|w| w.date_updated <= cutoff)
+            .filter(|w| currency_id.is_none_or(|currency_id| w.currency_id == currency_id))
+            .take(limit as usize)
+            .map(|w| state.wire_row(w))
+            .collect())
+    }
+
+    async fn apply_wire(&self, id: i64) -> Result<Amount, LedgerError> {
+        self.lock().move_wire_balance(id, "initiated", "smite_applied", false)
+    }
+
+    async fn compensate_wire(&self, id: i64) -> Result<Amount, LedgerError> {
+        self.lock().move_wire_balance(id, "smite_applied", "compensated", true)
+    }
+
+    async fn record_wire_remote_balance(&self, id: i64, balance: i64) -> Result<bool, StorageError> {
+        let mut state = self.lock();
+        let now = state.now();
+        match state.wire_mut(id) {
+            Some(wire) if wire.state == "smite_applied" => {
+                wire.remote_before = Some(balance);
+                wire.date_updated = now;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn set_wire_state(&self, id: i64, from: &str, to: &str, error: Option<&str>) -> Result<bool, StorageError> {
+        let mut state = self.lock();
+        let now = state.now();
+        match state.wire_mut(id) {
+            Some(wire) if wire.state == from => {
+                wire.state = to.to_string();
+                if let Some(error) = error {
+                    wire.last_error = Some(error.to_string());
+                }
+                wire.date_updated = now;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn get_wire_config(&self, currency_id: i64) -> Result<Option<WireConfig>, StorageError> {
+        Ok(self.lock().wire_configs.get(&currency_id).cloned())
+    }
+
+    async fn set_wire_config(&self, currency_id: i64, config: &WireConfig) -> Result<(), StorageError> {
+        self.lock().wire_configs.insert(currency_id, config.clone());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ApiTokenStore for MemoryStorage {
+    async fn get_api_token(&self, currency_id: i64, api_type_id: i32) -> Result<Option<String>, StorageError> {
+        Ok(self.lock().api_tokens.get(&(currency_id, api_type_id)).cloned())
+    }
+
+    async fn store_api_token(&self, currency_id: i64, api_type_id: i32, encrypted_token: &str) -> Result<(), StorageError> {
+        self.lock().api_tokens.insert((currency_id, api_type_id), encrypted_token.to_string());
+        Ok(())
+    }
+
+    async fn get_bridge_provider(&self, currency_id: i64) -> Result<Option<(i32, Option<String>)>, StorageError> {
+        Ok(self.lock().bridge_providers.get(&currency_id).cloned())
+    }
+
+    async fn set_bridge_provider(&self, currency_id: i64, api_type_id: i32, settings: Option<&str>) -> Result<(), StorageError> {
+        self.lock().bridge_providers.insert(currency_id, (api_type_id, settings.map(str::to_string)));
+        Ok(())
+    }
+}
@@ -0,0 +1,476 @@
+//! Storage traits the services run against.
+//!
+//! Each trait covers one area of the schema and mirrors the `db` functions for
+//! it, down to the tuple shapes they return. `MySqlStorage` is the production
+//! backend and wraps those functions; `SqliteStorage` runs the same operations
+//! on a single SQLite file for small self-hosted bots. `MemoryStorage` keeps
+//! everything in memory, so service flows can be tested without a database.
+
+use std::sync::Arc;
+
+use serenity::async_trait;
+use serenity::prelude::Context;
+use crate::db::filter::{SwapFilter, SwapSort};
+use crate::db::wire::WireConfig;
+use crate::db::ledger::{BatchTransferReceipt, TransferReceipt};
+use crate::db::payroll::PayrollSource;
+use crate::db::recurring::RecurringPeriod;
+use crate::utils::Amount;
+use crate::utils::errors::{LedgerError, StorageError};
+
+pub mod mysql;
+pub mod sqlite;
+pub mod swaps;
+#[cfg(test)]
+pub mod memory;
+
+pub use mysql::MySqlStorage;
+pub use sqlite::SqliteStorage;
+#[cfg(test)]
+pub use memory::{amount, MemoryStorage};
+
+/// (id, maker_id, taker_id, maker_currency_id, taker_currency_id, maker_amount, taker_amount, status)
+/// Maker and taker are account IDs
+pub type SwapRow = (i64, i64, Option<i64>, i64, i64, Amount, Amount, String);
+
+/// (id, maker_id, taker_id, maker_currency_id, taker_currency_id, maker_amount, taker_amount, status, maker_ticker, taker_ticker, filled_amount)
+/// Maker and taker are Discord IDs
+pub type SwapListRow = (i64, i64, Option<i64>, i64, i64, Amount, Amount, String, String, String, Amount);
+
+/// (swap_id, maker_discord_id, taker_discord_id, maker_ticker, taker_ticker, unfilled maker_amount, taker_amount)
+pub type OverdueSwapRow = (i64, i64, Option<i64>, String, String, Amount, Amount);
+
+/// (sender_id, receiver_id, amount, date_created, uuid, currency_ticker, memo)
+pub type TransactionRow = (i64, i64, Amount, String, String, String, Option<String>);
+
+/// (id, payer_id, recipient_id, currency_id, ticker, amount, period, next_run_at, failure_count, status)
+/// Payer and recipient are Discord IDs; next_run_at is a unix timestamp
+pub type RecurringRow = (i64, i64, i64, i64, String, Amount, String, i64, i32, String);
+
+/// (id, guild_id, role_id, currency_id, ticker, amount, source, period, next_run_at, created_by, status)
+/// created_by is a Discord ID; next_run_at is a unix timestamp
+pub type PayrollRow = (i64, i64, i64, i64, String, Amount, String, String, i64, i64, String);
+
+/// (id, issuer_id, payer_id, currency_id, ticker, amount, memo, status, transaction_uuid, expires_at)
+/// Issuer and payer are Discord IDs; expires_at is a unix timestamp
+pub type InvoiceRow = (i64, i64, i64, i64, String, Amount, Option<String>, String, Option<String>, i64);
+
+/// (id, discord_id, currency_id, ticker, direction, amount, fee, remote_amount, state, remote_before, last_error,
+/// date_created, date_updated)
+/// Dates are unix timestamps; remote_before is the UnbelievaBoat bank balance read before the remote change
+pub type WireRow = (i64, i64, i64, String, String, Amount, Amount, i64, String, Option<i64>, Option<String>, i64, i64);
+
+#[async_trait]
+pub trait CurrencyStore: Send + Sync {
+    async fn create_currency(&self, guild_id: i64, name: &str, ticker: &str) -> Result<i64, StorageError>;
+
+    /// (id, name, ticker)
+    async fn get_currency_by_guild(&self, guild_id: i64) -> Result<Option<(i64, String, String)>, StorageError>;
+
+    /// (id, guild_id, name, ticker)
+    async fn get_currency_by_id(&self, currency_id: i64) -> Result<Option<(i64, i64, String, String)>, StorageError>;
+
+    /// (id, name, ticker), matching the ticker ignoring case
+    async fn get_currency_by_ticker(&self, ticker: &str) -> Result<Option<(i64, String, String)>, StorageError>;
+
+    /// (id, guild_id, name, ticker), matching the ticker ignoring case
+    async fn get_currency_by_ticker_with_guild(&self, ticker: &str) -> Result<Option<(i64, i64, String, String)>, StorageError>;
+
+    /// Creation date as YYYY-MM-DD
+    async fn get_currency_date(&self, currency_id: i64) -> Result<Option<String>, StorageError>;
+
+    /// One page of every currency as (id, name, ticker), oldest first or newest first
+    /// for "recent", and the total number of currencies
+    async fn get_currencies_paginated(
+        &self,
+        sort_by: &str,
+        page: usize,
+        page_size: usize,
+    ) -> Result<(Vec<(i64, String, String)>, i64), StorageError>;
+
+    /// (name, ticker) of currencies whose ticker starts with `prefix`, by ticker
+    async fn search_tickers(&self, prefix: &str, limit: i64) -> Result<Vec<(String, String)>, StorageError>;
+
+    async fn get_swap_expiry_minutes(&self, currency_id: i64) -> Result<Option<i64>, StorageError>;
+
+    async fn set_swap_expiry_minutes(&self, currency_id: i64, minutes: Option<i64>) -> Result<(), StorageError>;
+}
+
+#[async_trait]
+pub trait AccountStore: Send + Sync {
+    async fn create_account(&self, discord_id: i64, currency_id: i64) -> Result<i64, StorageError>;
+
+    async fn get_account_id(&self, discord_id: i64, currency_id: i64) -> Result<Option<i64>, StorageError>;
+
+    async fn get_account_balance(&self, discord_id: i64, currency_id: i64) -> Result<Option<Amount>, StorageError>;
+
+    async fn get_discord_id_by_account_id(&self, account_id: i64) -> Result<Option<i64>, StorageError>;
+
+    /// Sum of every account balance in the currency, None if it has no accounts
+    async fn get_total_balance(&self, currency_id: i64) -> Result<Option<Amount>, StorageError>;
+
+    /// Add `amount` (negative to deduct) to an account without any checks
+    async fn update_balance(&self, account_id: i64, amount: Amount) -> Result<(), StorageError>;
+
+    /// Move `amount` from sender to receiver, charging the currency's tax to the sender.
+    /// The receiver account is created if it doesn't exist yet and must not be the
+    /// sender's. The memo, if any, is stored on the transaction record.
+    async fn transfer(
+        &self,
+        sender_discord_id: i64,
+        receiver_discord_id: i64,
+        currency_id: i64,
+        amount: Amount,
+        memo: Option<&str>,
+    ) -> Result<TransferReceipt, LedgerError>;
+
+    /// Pay several receivers in one transaction: all of them are paid or none are.
+    /// Tax is charged to the sender on the total, and each receiver gets a transaction
+    /// record of their own, all with the same memo. Receivers must be distinct and not
+    /// the sender.
+    async fn transfer_many(
+        &self,
+        sender_discord_id: i64,
+        currency_id: i64,
+        payments: &[(i64, Amount)],
+        memo: Option<&str>,
+    ) -> Result<BatchTransferReceipt, LedgerError>;
+
+    /// Add `amount` (negative to burn) to a user's balance.
+    /// Returns (previous balance, new balance).
+    async fn mint(&self, discord_id: i64, currency_id: i64, amount: Amount) -> Result<(Amount, Amount), LedgerError>;
+
+    /// Pay `amount` to a user from the currency's tax account or by minting it.
+    /// The transaction is logged from the issuer's account, whose balance is not touched.
+    /// Returns the transaction UUID.
+    async fn payout(
+        &self,
+        issuer_discord_id: i64,
+        recipient_discord_id: i64,
+        currency_id: i64,
+        amount: Amount,
+        source: PayrollSource,
+    ) -> Result<String, LedgerError>;
+}
+
+/// A swap to open. Maker and taker are account IDs; a taker of None makes it an open swap.
+#[derive(Debug, Clone)]
+pub struct NewSwap {
+    pub maker_account_id: i64,
+    pub maker_currency_id: i64,
+    pub taker_currency_id: i64,
+    pub maker_amount: Amount,
+    pub taker_amount: Amount,
+    pub taker_account_id: Option<i64>,
+    pub expiry_minutes: Option<i64>,
+}
+
+#[async_trait]
+pub trait SwapStore: Send + Sync {
+    /// Escrow the maker's amount and open the swap, returning its ID
+    async fn create_swap(&self, swap: NewSwap) -> Result<i64, StorageError>;
+
+    /// Accept a swap as the taker, or fill part of an open swap.
+    /// `fill_amount` is in the maker's currency; None takes whatever is left.
+    /// Returns (maker amount filled, taker amount paid, fully filled).
+    async fn accept_swap(
+        &self,
+        swap_id: i64,
+        taker_discord_id: i64,
+        fill_amount: Option<Amount>,
+    ) -> Result<(Amount, Amount, bool), StorageError>;
+
+    /// Cancel a pending swap and refund the unfilled part to the maker
+    async fn cancel_swap(&self, swap_id: i64) -> Result<(), StorageError>;
+
+    /// Expire a pending swap and refund the unfilled part to the maker
+    async fn expire_swap(&self, swap_id: i64) -> Result<(), StorageError>;
+
+    async fn get_swap_by_id(&self, swap_id: i64) -> Result<Option<SwapRow>, StorageError>;
+
+    /// Expiry as a unix timestamp, None if the swap never expires
+    async fn get_swap_expiry(&self, swap_id: i64) -> Result<Option<i64>, StorageError>;
+
+    /// (filled_amount, fill_count)
+    async fn get_swap_fill_progress(&self, swap_id: i64) -> Result<Option<(Amount, i64)>, StorageError>;
+
+    /// Unfilled maker amounts held by pending swaps offering the currency
+    async fn get_total_swap_maker_amount(&self, currency_id: i64) -> Result<Option<Amount>, StorageError>;
+
+    /// Held by open limit orders. Backends without the order book hold none.
+    async fn get_total_order_escrow(&self, currency_id: i64) -> Result<Option<Amount>, StorageError>;
+
+    /// Pending swaps whose expiry has passed, oldest first
+    async fn get_overdue_swaps(&self, limit: i64) -> Result<Vec<OverdueSwapRow>, StorageError>;
+
+    async fn store_swap_message(&self, swap_id: i64, channel_id: i64, message_id: i64) -> Result<(), StorageError>;
+
+    /// (channel_id, message_id) of every message showing the swap
+    async fn get_swap_messages(&self, swap_id: i64) -> Result<Vec<(i64, i64)>, StorageError>;
+
+    /// One page of the filtered swaps and the total number that match
+    async fn get_swaps_paginated(
+        &self,
+        page: usize,
+        page_size: usize,
+        sort: SwapSort,
+        filter: &SwapFilter,
+    ) -> Result<(Vec<SwapListRow>, i64), StorageError>;
+}
+
+#[async_trait]
+pub trait TransactionStore: Send + Sync {
+    /// (sender_id, receiver_id, date_created, amount, uuid, memo)
+    async fn get_transaction_by_uuid(&self, uuid: &str) -> Result<Option<(i64, i64, String, Amount, String, Option<String>)>, StorageError>;
+
+    /// Newest first, across all of a user's accounts, with the total count.
+    /// With `memo_search`, only transactions whose memo contains that text (case-insensitive).
+    async fn get_user_transactions_paginated(
+        &self,
+        discord_id: i64,
+        page: usize,
+        page_size: usize,
+        memo_search: Option<&str>,
+    ) -> Result<(Vec<TransactionRow>, i64), StorageError>;
+
+    /// UUID of the newest transaction from one user to another in a currency whose
+    /// memo is exactly `memo`
+    async fn find_transaction_by_memo(
+        &self,
+        sender_discord_id: i64,
+        receiver_discord_id: i64,
+        currency_id: i64,
+        memo: &str,
+    ) -> Result<Option<String>, StorageError>;
+}
+
+#[async_trait]
+pub trait TradeLogStore: Send + Sync {
+    /// Log a trade and fold it into the pair's candles.
+    /// Currencies must be in canonical order (alphabetically by ticker).
+    async fn add_price_log(
+        &self,
+        base_currency_id: i64,
+        quote_currency_id: i64,
+        price: Amount,
+        volume: Amount,
+        quote_volume: Amount,
+    ) -> Result<i64, StorageError>;
+}
+
+#[async_trait]
+pub trait TaxStore: Send + Sync {
+    /// (id, currency_id, balance, tax_percentage)
+    async fn get_tax_account(&self, currency_id: i64) -> Result<Option<(i64, i64, Amount, i32)>, StorageError>;
+
+    async fn create_tax_account(&self, currency_id: i64, tax_percentage: i32) -> Result<i64, StorageError>;
+
+    async fn set_tax_percentage(&self, currency_id: i64, tax_percentage: i32) -> Result<(), StorageError>;
+
+    async fn get_tax_percentage(&self, currency_id: i64) -> Result<Option<i32>, StorageError>;
+
+    /// The tax account's balance, zero if the currency has none
+    async fn get_total_tax_balance(&self, currency_id: i64) -> Result<Option<Amount>, StorageError>;
+
+    async fn add_tax(&self, currency_id: i64, amount: Amount) -> Result<(), StorageError>;
+
+    /// Move `amount` (or everything when None) from the tax account to a user.
+    /// Returns the amount collected.
+    async fn collect_tax(&self, discord_id: i64, currency_id: i64, amount: Option<Amount>) -> Result<Amount, LedgerError>;
+}
+
+/// A recurring payment to schedule. Payer and recipient are Discord IDs.
+#[derive(Debug, Clone)]
+pub struct NewRecurringPayment {
+    pub payer_id: i64,
+    pub recipient_id: i64,
+    pub currency_id: i64,
+    pub amount: Amount,
+    pub period: RecurringPeriod,
+    /// Unix timestamp of the first payment
+    pub next_run_at: i64,
+}
+
+#[async_trait]
+pub trait RecurringStore: Send + Sync {
+    async fn create_recurring_payment(&self, payment: NewRecurringPayment) -> Result<i64, StorageError>;
+
+    async fn get_recurring_payment(&self, id: i64) -> Result<Option<RecurringRow>, StorageError>;
+
+    /// The payer's active and suspended payments, oldest first
+    async fn get_recurring_payments_by_payer(&self, payer_id: i64) -> Result<Vec<RecurringRow>, StorageError>;
+
+    /// Active payments whose next run has come, most overdue first
+    async fn get_due_recurring_payments(&self, limit: i64) -> Result<Vec<RecurringRow>, StorageError>;
+
+    /// Move an active payment's next run from `due_at` to `next_run_at`.
+    /// Returns false if the payment was rescheduled, suspended or cancelled in the meantime.
+    async fn reschedule_recurring_payment(&self, id: i64, due_at: i64, next_run_at: i64) -> Result<bool, StorageError>;
+
+    /// Count a failed run, returning the failures in a row so far
+    async fn record_recurring_failure(&self, id: i64) -> Result<i32, StorageError>;
+
+    async fn reset_recurring_failures(&self, id: i64) -> Result<(), StorageError>;
+
+    /// Set a payment's status ("active", "suspended" or "cancelled")
+    async fn set_recurring_status(&self, id: i64, status: &str) -> Result<(), StorageError>;
+}
+
+/// A payroll schedule to create. Role and creator are Discord IDs.
+#[derive(Debug, Clone)]
+pub struct NewPayroll {
+    pub guild_id: i64,
+    pub role_id: i64,
+    pub currency_id: i64,
+    pub amount: Amount,
+    pub source: PayrollSource,
+    pub period: RecurringPeriod,
+    /// Unix timestamp of the first run
+    pub next_run_at: i64,
+    pub created_by: i64,
+}
+
+#[async_trait]
+pub trait PayrollStore: Send + Sync {
+    async fn create_payroll(&self, payroll: NewPayroll) -> Result<i64, StorageError>;
+
+    async fn get_payroll(&self, id: i64) -> Result<Option<PayrollRow>, StorageError>;
+
+    /// The guild's active schedules, oldest first
+    async fn get_payrolls_by_guild(&self, guild_id: i64) -> Result<Vec<PayrollRow>, StorageError>;
+
+    /// Active schedules whose next run has come, most overdue first
+    async fn get_due_payrolls(&self, limit: i64) -> Result<Vec<PayrollRow>, StorageError>;
+
+    /// Move an active schedule's next run from `due_at` to `next_run_at`.
+    /// Returns false if it was rescheduled or cancelled in the meantime.
+    async fn reschedule_payroll(&self, id: i64, due_at: i64, next_run_at: i64) -> Result<bool, StorageError>;
+
+    /// Set a schedule's status ("active" or "cancelled")
+    async fn set_payroll_status(&self, id: i64, status: &str) -> Result<(), StorageError>;
+}
+
+/// An invoice to create. Issuer and payer are Discord IDs.
+#[derive(Debug, Clone)]
+pub struct NewInvoice {
+    pub issuer_id: i64,
+    pub payer_id: i64,
+    pub currency_id: i64,
+    pub amount: Amount,
+    pub memo: Option<String>,
+    /// Unix timestamp after which it can no longer be paid
+    pub expires_at: i64,
+}
+
+#[async_trait]
+pub trait InvoiceStore: Send + Sync {
+    async fn create_invoice(&self, invoice: NewInvoice) -> Result<i64, StorageError>;
+
+    async fn get_invoice(&self, id: i64) -> Result<Option<InvoiceRow>, StorageError>;
+
+    /// Invoices the user issued or has to pay, newest first, optionally with one status
+    async fn get_invoices_by_user(&self, discord_id: i64, status: Option<&str>, limit: i64) -> Result<Vec<InvoiceRow>, StorageError>;
+
+    /// Open invoices past their expiry, oldest first
+    async fn get_overdue_invoices(&self, limit: i64) -> Result<Vec<InvoiceRow>, StorageError>;
+
+    /// Invoices still "paying" that haven't changed for `idle_seconds`, oldest first
+    async fn get_stalled_invoices(&self, idle_seconds: i64, limit: i64) -> Result<Vec<InvoiceRow>, StorageError>;
+
+    /// Move an open invoice that hasn't expired to "paying".
+    /// Returns false if it was paid, declined or expired in the meantime.
+    async fn claim_invoice(&self, id: i64) -> Result<bool, StorageError>;
+
+    /// Move an invoice from one status to another.
+    /// Returns false if it wasn't in `from`.
+    async fn set_invoice_status(&self, id: i64, from: &str, to: &str) -> Result<bool, StorageError>;
+
+    /// Mark a claimed invoice paid by the transaction `transaction_uuid`
+    async fn complete_invoice(&self, id: i64, transaction_uuid: &str) -> Result<bool, StorageError>;
+}
+
+/// A wire to journal. The user is a Discord ID.
+#[derive(Debug, Clone)]
+pub struct NewWire {
+    pub discord_id: i64,
+    pub currency_id: i64,
+    /// "in" or "out"
+    pub direction: String,
+    /// What the user's balance moves by
+    pub amount: Amount,
+    /// What the tax account gets
+    pub fee: Amount,
+    /// Whole UnbelievaBoat units moved
+    pub remote_amount: i64,
+}
+
+#[async_trait]
+pub trait WireStore: Send + Sync {
+    /// Journal a wire before either balance changes
+    async fn create_wire(&self, wire: NewWire) -> Result<i64, StorageError>;
+
+    async fn get_wire(&self, id: i64) -> Result<Option<WireRow>, StorageError>;
+
+    /// The user's wires, newest first
+    async fn get_wires_by_user(&self, discord_id: i64, limit: i64) -> Result<Vec<WireRow>, StorageError>;
+
+    /// Wires still "initiated" or "smite_applied" that haven't changed for `idle_seconds`,
+    /// oldest first, optionally for one currency
+    async fn get_unsettled_wires(&self, currency_id: Option<i64>, idle_seconds: i64, limit: i64) -> Result<Vec<WireRow>, StorageError>;
+
+    /// Credit ("in") or debit ("out") the user by an initiated wire's amount, pay its fee
+    /// into the tax account and move it to "smite_applied", in one transaction.
+    /// Returns the new balance.
+    async fn apply_wire(&self, id: i64) -> Result<Amount, LedgerError>;
+
+    /// Reverse the balance change and fee of a "smite_applied" wire and move it to
+    /// "compensated", in one transaction. Returns the new balance.
+    async fn compensate_wire(&self, id: i64) -> Result<Amount, LedgerError>;
+
+    /// Note the UnbelievaBoat bank balance seen just before the remote side is changed.
+    /// Returns false if the wire isn't "smite_applied".
+    async fn record_wire_remote_balance(&self, id: i64, balance: i64) -> Result<bool, StorageError>;
+
+    /// Move a wire from one state to another, recording `error` if given.
+    /// Returns false if it wasn't in `from`.
+    async fn set_wire_state(&self, id: i64, from: &str, to: &str, error: Option<&str>) -> Result<bool, StorageError>;
+
+    /// The currency's wire settings, None if it has never been configured
+    async fn get_wire_config(&self, currency_id: i64) -> Result<Option<WireConfig>, StorageError>;
+
+    async fn set_wire_config(&self, currency_id: i64, config: &WireConfig) -> Result<(), StorageError>;
+}
+
+#[async_trait]
+pub trait ApiTokenStore: Send + Sync {
+    /// type_id: 1 = UnbelievaBoat
+    async fn get_api_token(&self, currency_id: i64, api_type_id: i32) -> Result<Option<String>, StorageError>;
+
+    async fn store_api_token(&self, currency_id: i64, api_type_id: i32, encrypted_token: &str) -> Result<(), StorageError>;
+
+    /// The currency's wire provider as (api_type_id, settings JSON), None if it was never chosen
+    async fn get_bridge_provider(&self, currency_id: i64) -> Result<Option<(i32, Option<String>)>, StorageError>;
+
+    async fn set_bridge_provider(&self, currency_id: i64, api_type_id: i32, settings: Option<&str>) -> Result<(), StorageError>;
+}
+
+/// Everything the services need from a backend
+pub trait Storage:
+    CurrencyStore + AccountStore + SwapStore + TransactionStore + TradeLogStore + TaxStore + RecurringStore
+    + PayrollStore + InvoiceStore + WireStore + ApiTokenStore
+{
+}
+
+impl<T> Storage for T where
+    T: CurrencyStore + AccountStore + SwapStore + TransactionStore + TradeLogStore + TaxStore + RecurringStore
+        + PayrollStore + InvoiceStore + WireStore + ApiTokenStore
+{
+}
+
+/// Get the storage backend from context
+pub async fn from_ctx(ctx: &Context) -> Result<Arc<dyn Storage>, String> {
+    let data = ctx.data.read().await;
+    data.get::<crate::DataStore>()
+        .cloned()
+        .ok_or("Database not initialized".to_string())
+}
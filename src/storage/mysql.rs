@@ -0,0 +1,615 @@
+//! MySQL backend: the `db` modules, with swaps settled by `storage::swaps`.
+
+use serenity::async_trait;
+use sqlx::mysql::{MySql, MySqlPool};
+use sqlx::Transaction;
+use crate::db;
+use crate::db::filter::{SwapFilter, SwapSort};
+use crate::db::ledger::{BatchTransferReceipt, TransferReceipt};
+use crate::db::payroll::PayrollSource;
+use crate::db::wire::WireConfig;
+use crate::utils::Amount;
+use crate::utils::errors::{LedgerError, StorageError};
+use super::swaps::{self, LockedSwap, SwapTx};
+use super::{
+    AccountStore, ApiTokenStore, CurrencyStore, InvoiceRow, InvoiceStore, NewInvoice, NewPayroll, NewRecurringPayment,
+    NewSwap, NewWire, OverdueSwapRow, PayrollRow, PayrollStore, RecurringRow, RecurringStore, SwapListRow, SwapRow, SwapStore,
+    TaxStore, TradeLogStore, TransactionRow, TransactionStore, WireRow, WireStore,
+};
+
+#[derive(Clone)]
+pub struct MySqlStorage {
+    pool: MySqlPool,
+}
+
+impl MySqlStorage {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+/// Swap settlement rows, locked with `SELECT ... FOR UPDATE`
+#[async_trait]
+impl SwapTx for Transaction<'_, MySql> {
+    async fn insert_swap(&mut self, swap: &NewSwap) -> Result<i64, sqlx::Error> {
+        let result = sqlx::query(
+            "INSERT INTO currency_swap (maker_id, taker_id, maker_currency_id, taker_currency_id, maker_amount, taker_amount, status, expires_at)
+             VALUES (?, ?, ?, ?, ?, ?, 'pending', IF(? IS NULL, NULL, DATE_ADD(NOW(), INTERVAL ? MINUTE)))"
+        )
+        .bind(swap.maker_account_id)
+        .bind(swap.taker_account_id)
+        .bind(swap.maker_currency_id)
+        .bind(swap.taker_currency_id)
+        .bind(swap.maker_amount)
+        .bind(swap.taker_amount)
+        .bind(swap.expiry_minutes)
+        .bind(swap.expiry_minutes)
+        .execute(&mut **self)
+        .await?;
+        Ok(result.last_insert_id() as i64)
+    }
+
+    async fn lock_swap(&mut self, swap_id: i64) -> Result<Option<LockedSwap>, sqlx::Error> {
+        let row = sqlx::query_as::<_, (i64, Option<i64>, i64, i64, Amount, Amount, Amount, String, i64)>(
+            "SELECT CAST(maker_id AS SIGNED), CAST(taker_id AS SIGNED), CAST(maker_currency_id AS SIGNED),
+                    CAST(taker_currency_id AS SIGNED), maker_amount, taker_amount, filled_amount, CAST(status AS CHAR),
+                    CAST(expires_at IS NOT NULL AND expires_at <= NOW() AS SIGNED)
+             FROM currency_swap WHERE id = ? FOR UPDATE"
+        )
+        .bind(swap_id)
+        .fetch_optional(&mut **self)
+        .await?;
+
+        Ok(row.map(|row| LockedSwap {
+            maker_account_id: row.0,
+            taker_account_id: row.1,
+            maker_currency_id: row.2,
+            taker_currency_id: row.3,
+            maker_amount: row.4,
+            taker_amount: row.5,
+            filled_amount: row.6,
+            status: row.7,
+            expired: row.8 != 0,
+        }))
+    }
+
+    async fn lock_balance(&mut self, account_id: i64) -> Result<Option<Amount>, sqlx::Error> {
+        sqlx::query_scalar("SELECT balance FROM account WHERE id = ? FOR UPDATE")
+            .bind(account_id)
+            .fetch_optional(&mut **self)
+            .await
+    }
+
+    async fn discord_id(&mut self, account_id: i64) -> Result<Option<i64>, sqlx::Error> {
+        sqlx::query_scalar("SELECT CAST(discord_id AS SIGNED) FROM account WHERE id = ?")
+            .bind(account_id)
+            .fetch_optional(&mut **self)
+            .await
+    }
+
+    async fn account_for(&mut self, discord_id: i64, currency_id: i64) -> Result<i64, sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO account (discord_id, currency_id, balance) VALUES (?, ?, 0)
+             ON DUPLICATE KEY UPDATE id = id"
+        )
+        .bind(discord_id)
+        .bind(currency_id)
+        .execute(&mut **self)
+        .await?;
+
+        sqlx::query_scalar("SELECT CAST(id AS SIGNED) FROM account WHERE discord_id = ? AND currency_id = ?")
+            .bind(discord_id)
+            .bind(currency_id)
+            .fetch_one(&mut **self)
+            .await
+    }
+
+    async fn add_balance(&mut self, account_id: i64, amount: Amount) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE account SET balance = balance + ? WHERE id = ?")
+            .bind(amount)
+            .bind(account_id)
+            .execute(&mut **self)
+            .await?;
+        Ok(())
+    }
+
+    async fn taker_paid(&mut self, swap_id: i64) -> Result<Amount, sqlx::Error> {
+        sqlx::query_scalar("SELECT COALESCE(SUM(taker_amount), 0) FROM swap_fill WHERE swap_id = ?")
+            .bind(swap_id)
+            .fetch_one(&mut **self)
+            .await
+    }
+
+    async fn log_transaction(&mut self, uuid: &str, sender_id: i64, receiver_id: i64, amount: Amount) -> Result<(), sqlx::Error> {
+        sqlx::query("INSERT INTO transaction (uuid, sender_id, receiver_id, amount) VALUES (?, ?, ?, ?)")
+            .bind(uuid)
+            .bind(sender_id)
+            .bind(receiver_id)
+            .bind(amount)
+            .execute(&mut **self)
+            .await?;
+        Ok(())
+    }
+
+    async fn record_fill(
+        &mut self,
+        swap_id: i64,
+        taker_account_id: i64,
+        maker_amount: Amount,
+        taker_amount: Amount,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("INSERT INTO swap_fill (swap_id, taker_id, maker_amount, taker_amount) VALUES (?, ?, ?, ?)")
+            .bind(swap_id)
+            .bind(taker_account_id)
+            .bind(maker_amount)
+            .bind(taker_amount)
+            .execute(&mut **self)
+            .await?;
+
+        sqlx::query("UPDATE currency_swap SET filled_amount = filled_amount + ? WHERE id = ?")
+            .bind(maker_amount)
+            .bind(swap_id)
+            .execute(&mut **self)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_status(&mut self, swap_id: i64, status: &str, taker_account_id: Option<i64>) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE currency_swap SET status = ?, taker_id = COALESCE(?, taker_id) WHERE id = ?")
+            .bind(status)
+            .bind(taker_account_id)
+            .bind(swap_id)
+            .execute(&mut **self)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CurrencyStore for MySqlStorage {
+    async fn create_currency(&self, guild_id: i64, name: &str, ticker: &str) -> Result<i64, StorageError> {
+        Ok(db::currency::create_currency(&self.pool, guild_id, name, ticker).await?)
+    }
+
+    async fn get_currency_by_guild(&self, guild_id: i64) -> Result<Option<(i64, String, String)>, StorageError> {
+        Ok(db::currency::get_currency_by_guild(&self.pool, guild_id).await?)
+    }
+
+    async fn get_currency_by_id(&self, currency_id: i64) -> Result<Option<(i64, i64, String, String)>, StorageError> {
+        Ok(db::currency::get_currency_by_id(&self.pool, currency_id).await?)
+    }
+
+    async fn get_currency_by_ticker(&self, ticker: &str) -> Result<Option<(i64, String, String)>, StorageError> {
+        Ok(db::currency::get_currency_by_ticker(&self.pool, ticker).await?)
+    }
+
+    async fn get_currency_by_ticker_with_guild(&self, ticker: &str) -> Result<Option<(i64, i64, String, String)>, StorageError> {
+        Ok(db::currency::get_currency_by_ticker_with_guild(&self.pool, ticker).await?)
+    }
+
+    async fn get_currency_date(&self, currency_id: i64) -> Result<Option<String>, StorageError> {
+        Ok(db::currency::get_currency_date(&self.pool, currency_id).await?)
+    }
+
+    async fn get_currencies_paginated(
+        &self,
+        sort_by: &str,
+        page: usize,
+        page_size: usize,
+    ) -> Result<(Vec<(i64, String, String)>, i64), StorageError> {
+        Ok(db::currency::get_currencies_paginated(&self.pool, sort_by, page, page_size).await?)
+    }
+
+    async fn search_tickers(&self, prefix: &str, limit: i64) -> Result<Vec<(String, String)>, StorageError> {
+        Ok(db::currency::search_tickers(&self.pool, prefix, limit).await?)
+    }
+
+    async fn get_swap_expiry_minutes(&self, currency_id: i64) -> Result<Option<i64>, StorageError> {
+        Ok(db::currency::get_swap_expiry_minutes(&self.pool, currency_id).await?)
+    }
+
+    async fn set_swap_expiry_minutes(&self, currency_id: i64, minutes: Option<i64>) -> Result<(), StorageError> {
+        Ok(db::currency::set_swap_expiry_minutes(&self.pool, currency_id, minutes).await?)
+    }
+}
+
+#[async_trait]
+impl AccountStore for MySqlStorage {
+    async fn create_account(&self, discord_id: i64, currency_id: i64) -> Result<i64, StorageError> {
+        Ok(db::account::create_account(&self.pool, discord_id, currency_id).await?)
+    }
+
+    async fn get_account_id(&self, discord_id: i64, currency_id: i64) -> Result<Option<i64>, StorageError> {
+        Ok(db::account::get_account_id(&self.pool, discord_id, currency_id).await?)
+    }
+
+    async fn get_account_balance(&self, discord_id: i64, currency_id: i64) -> Result<Option<Amount>, StorageError> {
+        Ok(db::account::get_account_balance(&self.pool, discord_id, currency_id).await?)
+    }
+
+    async fn get_discord_id_by_account_id(&self, account_id: i64) -> Result<Option<i64>, StorageError> {
+        Ok(db::account::get_discord_id_by_account_id(&self.pool, account_id).await?)
+    }
+
+    async fn get_total_balance(&self, currency_id: i64) -> Result<Option<Amount>, StorageError> {
+        Ok(db::account::get_total_balance(&self.pool, currency_id).await?)
+    }
+
+    async fn update_balance(&self, account_id: i64, amount: Amount) -> Result<(), StorageError> {
+        Ok(db::account::update_balance(&self.pool, account_id, amount).await?)
+    }
+
+    async fn transfer(
+        &self,
+        sender_discord_id: i64,
+        receiver_discord_id: i64,
+        currency_id: i64,
+        amount: Amount,
+        memo: Option<&str>,
+    ) -> Result<TransferReceipt, LedgerError> {
+        db::ledger::transfer(&self.pool, sender_discord_id, receiver_discord_id, currency_id, amount, memo).await
+    }
+
+    async fn transfer_many(
+        &self,
+        sender_discord_id: i64,
+        currency_id: i64,
+        payments: &[(i64, Amount)],
+        memo: Option<&str>,
+    ) -> Result<BatchTransferReceipt, LedgerError> {
+        db::ledger::transfer_many(&self.pool, sender_discord_id, currency_id, payments, memo).await
+    }
+
+    async fn mint(&self, discord_id: i64, currency_id: i64, amount: Amount) -> Result<(Amount, Amount), LedgerError> {
+        db::ledger::mint(&self.pool, discord_id, currency_id, amount).await
+    }
+
+    async fn payout(
+        &self,
+        issuer_discord_id: i64,
+        recipient_discord_id: i64,
+        currency_id: i64,
+        amount: Amount,
+        source: PayrollSource,
+    ) -> Result<String, LedgerError> {
+        db::ledger::payout(&self.pool, issuer_discord_id, recipient_discord_id, currency_id, amount, source).await
+    }
+}
+
+#[async_trait]
+impl SwapStore for MySqlStorage {
+    async fn create_swap(&self, swap: NewSwap) -> Result<i64, StorageError> {
+        let mut tx = self.pool.begin().await?;
+        let swap_id = swaps::create(&mut tx, &swap).await?;
+        tx.commit().await?;
+        Ok(swap_id)
+    }
+
+    async fn accept_swap(
+        &self,
+        swap_id: i64,
+        taker_discord_id: i64,
+        fill_amount: Option<Amount>,
+    ) -> Result<(Amount, Amount, bool), StorageError> {
+        let mut tx = self.pool.begin().await?;
+        let result = swaps::fill(&mut tx, swap_id, taker_discord_id, fill_amount).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn cancel_swap(&self, swap_id: i64) -> Result<(), StorageError> {
+        let mut tx = self.pool.begin().await?;
+        swaps::close(&mut tx, swap_id, "cancelled").await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn expire_swap(&self, swap_id: i64) -> Result<(), StorageError> {
+        let mut tx = self.pool.begin().await?;
+        swaps::close(&mut tx, swap_id, "expired").await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_swap_by_id(&self, swap_id: i64) -> Result<Option<SwapRow>, StorageError> {
+        Ok(db::swap::get_swap_by_id(&self.pool, swap_id).await?)
+    }
+
+    async fn get_swap_expiry(&self, swap_id: i64) -> Result<Option<i64>, StorageError> {
+        Ok(db::swap::get_swap_expiry(&self.pool, swap_id).await?)
+    }
+
+    async fn get_swap_fill_progress(&self, swap_id: i64) -> Result<Option<(Amount, i64)>, StorageError> {
+        Ok(db::swap::get_swap_fill_progress(&self.pool, swap_id).await?)
+    }
+
+    async fn get_total_swap_maker_amount(&self, currency_id: i64) -> Result<Option<Amount>, StorageError> {
+        Ok(db::swap::get_total_swap_maker_amount(&self.pool, currency_id).await?)
+    }
+
+    async fn get_total_order_escrow(&self, currency_id: i64) -> Result<Option<Amount>, StorageError> {
+        Ok(db::orderbook::get_total_order_escrow(&self.pool, currency_id).await?)
+    }
+
+    async fn get_overdue_swaps(&self, limit: i64) -> Result<Vec<OverdueSwapRow>, StorageError> {
+        Ok(db::swap::get_overdue_swaps(&self.pool, limit).await?)
+    }
+
+    async fn store_swap_message(&self, swap_id: i64, channel_id: i64, message_id: i64) -> Result<(), StorageError> {
+        Ok(db::swap::store_swap_message(&self.pool, swap_id, channel_id, message_id).await?)
+    }
+
+    async fn get_swap_messages(&self, swap_id: i64) -> Result<Vec<(i64, i64)>, StorageError> {
+        Ok(db::swap::get_swap_messages(&self.pool, swap_id).await?)
+    }
+
+    async fn get_swaps_paginated(
+        &self,
+        page: usize,
+        page_size: usize,
+        sort: SwapSort,
+        filter: &SwapFilter,
+    ) -> Result<(Vec<SwapListRow>, i64), StorageError> {
+        Ok(db::swap::get_swaps_paginated(&self.pool, page, page_size, sort, filter).await?)
+    }
+}
+
+#[async_trait]
+impl TransactionStore for MySqlStorage {
+    async fn get_transaction_by_uuid(&self, uuid: &str) -> Result<Option<(i64, i64, String, Amount, String, Option<String>)>, StorageError> {
+        Ok(db::transaction::get_transaction_by_uuid(&self.pool, uuid).await?)
+    }
+
+    async fn get_user_transactions_paginated(
+        &self,
+        discord_id: i64,
+        page: usize,
+        page_size: usize,
+        memo_search: Option<&str>,
+    ) -> Result<(Vec<TransactionRow>, i64), StorageError> {
+        Ok(db::transaction::get_user_transactions_paginated(&self.pool, discord_id, page, page_size, memo_search).await?)
+    }
+
+    async fn find_transaction_by_memo(
+        &self,
+        sender_discord_id: i64,
+        receiver_discord_id: i64,
+        currency_id: i64,
+        memo: &str,
+    ) -> Result<Option<String>, StorageError> {
+        Ok(db::transaction::find_transaction_by_memo(&self.pool, sender_discord_id, receiver_discord_id, currency_id, memo).await?)
+    }
+}
+
+#[async_trait]
+impl TradeLogStore for MySqlStorage {
+    async fn add_price_log(
+        &self,
+        base_currency_id: i64,
+        quote_currency_id: i64,
+        price: Amount,
+        volume: Amount,
+        quote_volume: Amount,
+    ) -> Result<i64, StorageError> {
+        Ok(db::tradelog::add_price_log(&self.pool, base_currency_id, quote_currency_id, price, volume, quote_volume).await?)
+    }
+}
+
+#[async_trait]
+impl TaxStore for MySqlStorage {
+    async fn get_tax_account(&self, currency_id: i64) -> Result<Option<(i64, i64, Amount, i32)>, StorageError> {
+        Ok(db::tax::get_tax_account(&self.pool, currency_id).await?)
+    }
+
+    async fn create_tax_account(&self, currency_id: i64, tax_percentage: i32) -> Result<i64, StorageError> {
+        Ok(db::tax::create_tax_account(&self.pool, currency_id, tax_percentage).await?)
+    }
+
+    async fn set_tax_percentage(&self, currency_id: i64, tax_percentage: i32) -> Result<(), StorageError> {
+        Ok(db::tax::set_tax_percentage(&self.pool, currency_id, tax_percentage).await?)
+    }
+
+    async fn get_tax_percentage(&self, currency_id: i64) -> Result<Option<i32>, StorageError> {
+        Ok(db::tax::get_tax_percentage(&self.pool, currency_id).await?)
+    }
+
+    async fn get_total_tax_balance(&self, currency_id: i64) -> Result<Option<Amount>, StorageError> {
+        Ok(db::tax::get_total_tax_balance(&self.pool, currency_id).await?)
+    }
+
+    async fn add_tax(&self, currency_id: i64, amount: Amount) -> Result<(), StorageError> {
+        Ok(db::tax::add_tax(&self.pool, currency_id, amount).await?)
+    }
+
+    async fn collect_tax(&self, discord_id: i64, currency_id: i64, amount: Option<Amount>) -> Result<Amount, LedgerError> {
+        db::ledger::collect_tax(&self.pool, discord_id, currency_id, amount).await
+    }
+}
+
+#[async_trait]
+impl RecurringStore for MySqlStorage {
+    async fn create_recurring_payment(&self, payment: NewRecurringPayment) -> Result<i64, StorageError> {
+        Ok(db::recurring::create_recurring_payment(
+            &self.pool,
+            payment.payer_id,
+            payment.recipient_id,
+            payment.currency_id,
+            payment.amount,
+            payment.period,
+            payment.next_run_at,
+        )
+        .await?)
+    }
+
+    async fn get_recurring_payment(&self, id: i64) -> Result<Option<RecurringRow>, StorageError> {
+        Ok(db::recurring::get_recurring_payment(&self.pool, id).await?)
+    }
+
+    async fn get_recurring_payments_by_payer(&self, payer_id: i64) -> Result<Vec<RecurringRow>, StorageError> {
+        Ok(db::recurring::get_recurring_payments_by_payer(&self.pool, payer_id).await?)
+    }
+
+    async fn get_due_recurring_payments(&self, limit: i64) -> Result<Vec<RecurringRow>, StorageError> {
+        Ok(db::recurring::get_due_recurring_payments(&self.pool, limit).await?)
+    }
+
+    async fn reschedule_recurring_payment(&self, id: i64, due_at: i64, next_run_at: i64) -> Result<bool, StorageError> {
+        Ok(db::recurring::reschedule_recurring_payment(&self.pool, id, due_at, next_run_at).await?)
+    }
+
+    async fn record_recurring_failure(&self, id: i64) -> Result<i32, StorageError> {
+        Ok(db::recurring::record_recurring_failure(&self.pool, id).await?)
+    }
+
+    async fn reset_recurring_failures(&self, id: i64) -> Result<(), StorageError> {
+        Ok(db::recurring::reset_recurring_failures(&self.pool, id).await?)
+    }
+
+    async fn set_recurring_status(&self, id: i64, status: &str) -> Result<(), StorageError> {
+        Ok(db::recurring::set_recurring_status(&self.pool, id, status).await?)
+    }
+}
+
+#[async_trait]
+impl PayrollStore for MySqlStorage {
+    async fn create_payroll(&self, payroll: NewPayroll) -> Result<i64, StorageError> {
+        Ok(db::payroll::create_payroll(
+            &self.pool,
+            payroll.guild_id,
+            payroll.role_id,
+            payroll.currency_id,
+            payroll.amount,
+            payroll.source,
+            payroll.period,
+            payroll.next_run_at,
+            payroll.created_by,
+        )
+        .await?)
+    }
+
+    async fn get_payroll(&self, id: i64) -> Result<Option<PayrollRow>, StorageError> {
+        Ok(db::payroll::get_payroll(&self.pool, id).await?)
+    }
+
+    async fn get_payrolls_by_guild(&self, guild_id: i64) -> Result<Vec<PayrollRow>, StorageError> {
+        Ok(db::payroll::get_payrolls_by_guild(&self.pool, guild_id).await?)
+    }
+
+    async fn get_due_payrolls(&self, limit: i64) -> Result<Vec<PayrollRow>, StorageError> {
+        Ok(db::payroll::get_due_payrolls(&self.pool, limit).await?)
+    }
+
+    async fn reschedule_payroll(&self, id: i64, due_at: i64, next_run_at: i64) -> Result<bool, StorageError> {
+        Ok(db::payroll::reschedule_payroll(&self.pool, id, due_at, next_run_at).await?)
+    }
+
+    async fn set_payroll_status(&self, id: i64, status: &str) -> Result<(), StorageError> {
+        Ok(db::payroll::set_payroll_status(&self.pool, id, status).await?)
+    }
+}
+
+#[async_trait]
+impl InvoiceStore for MySqlStorage {
+    async fn create_invoice(&self, invoice: NewInvoice) -> Result<i64, StorageError> {
+        Ok(db::invoice::create_invoice(
+            &self.pool,
+            invoice.issuer_id,
+            invoice.payer_id,
+            invoice.currency_id,
+            invoice.amount,
+            invoice.memo.as_deref(),
+            invoice.expires_at,
+        )
+        .await?)
+    }
+
+    async fn get_invoice(&self, id: i64) -> Result<Option<InvoiceRow>, StorageError> {
+        Ok(db::invoice::get_invoice(&self.pool, id).await?)
+    }
+
+    async fn get_invoices_by_user(&self, discord_id: i64, status: Option<&str>, limit: i64) -> Result<Vec<InvoiceRow>, StorageError> {
+        Ok(db::invoice::get_invoices_by_user(&self.pool, discord_id, status, limit).await?)
+    }
+
+    async fn get_overdue_invoices(&self, limit: i64) -> Result<Vec<InvoiceRow>, StorageError> {
+        Ok(db::invoice::get_overdue_invoices(&self.pool, limit).await?)
+    }
+
+    async fn get_stalled_invoices(&self, idle_seconds: i64, limit: i64) -> Result<Vec<InvoiceRow>, StorageError> {
+        Ok(db::invoice::get_stalled_invoices(&self.pool, idle_seconds, limit).await?)
+    }
+
+    async fn claim_invoice(&self, id: i64) -> Result<bool, StorageError> {
+        Ok(db::invoice::claim_invoice(&self.pool, id).await?)
+    }
+
+    async fn set_invoice_status(&self, id: i64, from: &str, to: &str) -> Result<bool, StorageError> {
+        Ok(db::invoice::set_invoice_status(&self.pool, id, from, to).await?)
+    }
+
+    async fn complete_invoice(&self, id: i64, transaction_uuid: &str) -> Result<bool, StorageError> {
+        Ok(db::invoice::complete_invoice(&self.pool, id, transaction_uuid).await?)
+    }
+}
+
+#[async_trait]
+impl WireStore for MySqlStorage {
+    async fn create_wire(&self, wire: NewWire) -> Result<i64, StorageError> {
+        Ok(db::wire::create_wire(&self.pool, wire).await?)
+    }
+
+    async fn get_wire(&self, id: i64) -> Result<Option<WireRow>, StorageError> {
+        Ok(db::wire::get_wire(&self.pool, id).await?)
+    }
+
+    async fn get_wires_by_user(&self, discord_id: i64, limit: i64) -> Result<Vec<WireRow>, StorageError> {
+        Ok(db::wire::get_wires_by_user(&self.pool, discord_id, limit).await?)
+    }
+
+    async fn get_unsettled_wires(&self, currency_id: Option<i64>, idle_seconds: i64, limit: i64) -> Result<Vec<WireRow>, StorageError> {
+        Ok(db::wire::get_unsettled_wires(&self.pool, currency_id, idle_seconds, limit).await?)
+    }
+
+    async fn apply_wire(&self, id: i64) -> Result<Amount, LedgerError> {
+        db::wire::apply_wire(&self.pool, id).await
+    }
+
+    async fn compensate_wire(&self, id: i64) -> Result<Amount, LedgerError> {
+        db::wire::compensate_wire(&self.pool, id).await
+    }
+
+    async fn record_wire_remote_balance(&self, id: i64, balance: i64) -> Result<bool, StorageError> {
+        Ok(db::wire::record_wire_remote_balance(&self.pool, id, balance).await?)
+    }
+
+    async fn set_wire_state(&self, id: i64, from: &str, to: &str, error: Option<&str>) -> Result<bool, StorageError> {
+        Ok(db::wire::set_wire_state(&self.pool, id, from, to, error).await?)
+    }
+
+    async fn get_wire_config(&self, currency_id: i64) -> Result<Option<WireConfig>, StorageError> {
+        Ok(db::wire::get_wire_config(&self.pool, currency_id).await?)
+    }
+
+    async fn set_wire_config(&self, currency_id: i64, config: &WireConfig) -> Result<(), StorageError> {
+        Ok(db::wire::set_wire_config(&self.pool, currency_id, config).await?)
+    }
+}
+
+#[async_trait]
+impl ApiTokenStore for MySqlStorage {
+    async fn get_api_token(&self, currency_id: i64, api_type_id: i32) -> Result<Option<String>, StorageError> {
+        Ok(db::api::get_api_token(&self.pool, currency_id, api_type_id).await?)
+    }
+
+    async fn store_api_token(&self, currency_id: i64, api_type_id: i32, encrypted_token: &str) -> Result<(), StorageError> {
+        Ok(db::api::store_api_token(&self.pool, currency_id, api_type_id, encrypted_token).await?)
+    }
+
+    async fn get_bridge_provider(&self, currency_id: i64) -> Result<Option<(i32, Option<String>)>, StorageError> {
+        Ok(db::api::get_bridge_provider(&self.pool, currency_id).await?)
+    }
+
+    async fn set_bridge_provider(&self, currency_id: i64, api_type_id: i32, settings: Option<&str>) -> Result<(), StorageError> {
+        Ok(db::api::set_bridge_provider(&self.pool, currency_id, api_type_id, settings).await?)
+    }
+}
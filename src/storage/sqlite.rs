@@ -0,0 +1,1721 @@
+//! SQLite backend for self-hosted single-guild deployments.
+//!
+//! Writes that check a balance run in `BEGIN IMMEDIATE` transactions, which take
+//! the database's write lock up front, so they need no row locks and can't
+//! deadlock. The order book and price history need the MySQL backend.
+
+use serenity::async_trait;
+use sqlx::sqlite::{Sqlite, SqlitePool};
+use sqlx::Transaction;
+use uuid::Uuid;
+use crate::db::filter::{SwapFilter, SwapSort};
+use crate::db::ledger::{BatchTransferReceipt, TransferReceipt};
+use crate::db::payroll::PayrollSource;
+use crate::db::wire::WireConfig;
+use crate::utils::Amount;
+use crate::utils::errors::{LedgerError, StorageError};
+use super::swaps::{self, LockedSwap, SwapTx};
+use super::{
+    AccountStore, ApiTokenStore, CurrencyStore, InvoiceRow, InvoiceStore, NewInvoice, NewPayroll, NewRecurringPayment,
+    NewSwap, NewWire, OverdueSwapRow, PayrollRow, PayrollStore, RecurringRow, RecurringStore, SwapListRow, SwapRow, SwapStore,
+    TaxStore, TradeLogStore, TransactionRow, TransactionStore, WireRow, WireStore,
+};
+
+#[derive(Clone)]
+pub struct SqliteStorage {
+    pool: SqlitePool,
+}
+
+impl SqliteStorage {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// A migrated in-memory database. The single connection is never recycled,
+    /// since the database goes with it.
+    #[cfg(test)]
+    pub async fn in_memory() -> Self {
+        use std::str::FromStr;
+        use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+
+        let options = SqliteConnectOptions::from_str("sqlite::memory:").unwrap().foreign_keys(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await
+            .unwrap();
+        crate::db::sqlite::run(&pool).await.unwrap();
+        Self { pool }
+    }
+
+    async fn begin_write(&self) -> Result<Transaction<'static, Sqlite>, sqlx::Error> {
+        self.pool.begin_with("BEGIN IMMEDIATE").await
+    }
+}
+
+/// A user's account as (id, balance). With `create` set, a zero-balance account is inserted if missing.
+async fn find_account(
+    tx: &mut Transaction<'_, Sqlite>,
+    discord_id: i64,
+    currency_id: i64,
+    create: bool,
+) -> Result<Option<(i64, Amount)>, sqlx::Error> {
+    if create {
+        sqlx::query("INSERT OR IGNORE INTO account (discord_id, currency_id, balance) VALUES (?, ?, 0)")
+            .bind(discord_id)
+            .bind(currency_id)
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    sqlx::query_as::<_, (i64, Amount)>("SELECT id, balance FROM account WHERE discord_id = ? AND currency_id = ?")
+        .bind(discord_id)
+        .bind(currency_id)
+        .fetch_optional(&mut **tx)
+        .await
+}
+
+async fn set_balance(tx: &mut Transaction<'_, Sqlite>, account_id: i64, balance: Amount) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE account SET balance = ?, date_updated = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(balance)
+        .bind(account_id)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+async fn insert_transaction(
+    tx: &mut Transaction<'_, Sqlite>,
+    uuid: &str,
+    sender_id: i64,
+    receiver_id: i64,
+    amount: Amount,
+    memo: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO \"transaction\" (uuid, sender_id, receiver_id, amount, memo) VALUES (?, ?, ?, ?, ?)")
+        .bind(uuid)
+        .bind(sender_id)
+        .bind(receiver_id)
+        .bind(amount)
+        .bind(memo)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+/// Swap settlement rows; the write lock taken by `BEGIN IMMEDIATE` covers them all
+#[async_trait]
+impl SwapTx for Transaction<'_, Sqlite> {
+    async fn insert_swap(&mut self, swap: &NewSwap) -> Result<i64, sqlx::Error> {
+        let result = sqlx::query(
+            "INSERT INTO currency_swap (maker_id, taker_id, maker_currency_id, taker_currency_id, maker_amount, taker_amount, status, expires_at)
+             VALUES (?, ?, ?, ?, ?, ?, 'pending', CASE WHEN ? IS NULL THEN NULL ELSE datetime('now', '+' || ? || ' minutes') END)"
+        )
+        .bind(swap.maker_account_id)
+        .bind(swap.taker_account_id)
+        .bind(swap.maker_currency_id)
+        .bind(swap.taker_currency_id)
+        .bind(swap.maker_amount)
+        .bind(swap.taker_amount)
+        .bind(swap.expiry_minutes)
+        .bind(swap.expiry_minutes)
+        .execute(&mut **self)
+        .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn lock_swap(&mut self, swap_id: i64) -> Result<Option<LockedSwap>, sqlx::Error> {
+        let row = sqlx::query_as::<_, (i64, Option<i64>, i64, i64, Amount, Amount, Amount, String, bool)>(
+            "SELECT maker_id, taker_id, maker_currency_id, taker_currency_id, maker_amount, taker_amount,
+                    filled_amount, status, expires_at IS NOT NULL AND expires_at <= datetime('now')
+             FROM currency_swap WHERE id = ?"
+        )
+        .bind(swap_id)
+        .fetch_optional(&mut **self)
+        .await?;
+
+        Ok(row.map(|row| LockedSwap {
+            maker_account_id: row.0,
+            taker_account_id: row.1,
+            maker_currency_id: row.2,
+            taker_currency_id: row.3,
+            maker_amount: row.4,
+            taker_amount: row.5,
+            filled_amount: row.6,
+            status: row.7,
+            expired: row.8,
+        }))
+    }
+
+    async fn lock_balance(&mut self, account_id: i64) -> Result<Option<Amount>, sqlx::Error> {
+        sqlx::query_scalar("SELECT balance FROM account WHERE id = ?")
+            .bind(account_id)
+            .fetch_optional(&mut **self)
+            .await
+    }
+
+    async fn discord_id(&mut self, account_id: i64) -> Result<Option<i64>, sqlx::Error> {
+        sqlx::query_scalar("SELECT discord_id FROM account WHERE id = ?")
+            .bind(account_id)
+            .fetch_optional(&mut **self)
+            .await
+    }
+
+    async fn account_for(&mut self, discord_id: i64, currency_id: i64) -> Result<i64, sqlx::Error> {
+        let (account_id, _) = find_account(self, discord_id, currency_id, true)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)?;
+        Ok(account_id)
+    }
+
+    async fn add_balance(&mut self, account_id: i64, amount: Amount) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE account SET balance = balance + ?, date_updated = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(amount)
+            .bind(account_id)
+            .execute(&mut **self)
+            .await?;
+        Ok(())
+    }
+
+    async fn taker_paid(&mut self, swap_id: i64) -> Result<Amount, sqlx::Error> {
+        sqlx::query_scalar("SELECT COALESCE(SUM(taker_amount), 0) FROM swap_fill WHERE swap_id = ?")
+            .bind(swap_id)
+            .fetch_one(&mut **self)
+            .await
+    }
+
+    async fn log_transaction(&mut self, uuid: &str, sender_id: i64, receiver_id: i64, amount: Amount) -> Result<(), sqlx::Error> {
+        insert_transaction(self, uuid, sender_id, receiver_id, amount, None).await
+    }
+
+    async fn record_fill(
+        &mut self,
+        swap_id: i64,
+        taker_account_id: i64,
+        maker_amount: Amount,
+        taker_amount: Amount,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("INSERT INTO swap_fill (swap_id, taker_id, maker_amount, taker_amount) VALUES (?, ?, ?, ?)")
+            .bind(swap_id)
+            .bind(taker_account_id)
+            .bind(maker_amount)
+            .bind(taker_amount)
+            .execute(&mut **self)
+            .await?;
+
+        sqlx::query("UPDATE currency_swap SET filled_amount = filled_amount + ?, date_updated = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(maker_amount)
+            .bind(swap_id)
+            .execute(&mut **self)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_status(&mut self, swap_id: i64, status: &str, taker_account_id: Option<i64>) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE currency_swap SET status = ?, taker_id = COALESCE(?, taker_id), date_updated = CURRENT_TIMESTAMP
+             WHERE id = ?"
+        )
+        .bind(status)
+        .bind(taker_account_id)
+        .bind(swap_id)
+        .execute(&mut **self)
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CurrencyStore for SqliteStorage {
+    async fn create_currency(&self, guild_id: i64, name: &str, ticker: &str) -> Result<i64, StorageError> {
+        let result = sqlx::query("INSERT INTO currency (guild_id, name, ticker) VALUES (?, ?, ?)")
+            .bind(guild_id)
+            .bind(name)
+            .bind(ticker)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn get_currency_by_guild(&self, guild_id: i64) -> Result<Option<(i64, String, String)>, StorageError> {
+        Ok(sqlx::query_as("SELECT id, name, ticker FROM currency WHERE guild_id = ?")
+            .bind(guild_id)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn get_currency_by_id(&self, currency_id: i64) -> Result<Option<(i64, i64, String, String)>, StorageError> {
+        Ok(sqlx::query_as("SELECT id, guild_id, name, ticker FROM currency WHERE id = ?")
+            .bind(currency_id)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn get_currency_by_ticker(&self, ticker: &str) -> Result<Option<(i64, String, String)>, StorageError> {
+        Ok(sqlx::query_as("SELECT id, name, ticker FROM currency WHERE UPPER(ticker) = UPPER(?)")
+            .bind(ticker)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn get_currency_by_ticker_with_guild(&self, ticker: &str) -> Result<Option<(i64, i64, String, String)>, StorageError> {
+        Ok(sqlx::query_as("SELECT id, guild_id, name, ticker FROM currency WHERE UPPER(ticker) = UPPER(?)")
+            .bind(ticker)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn get_currency_date(&self, currency_id: i64) -> Result<Option<String>, StorageError> {
+        Ok(sqlx::query_scalar("SELECT date(date_created) FROM currency WHERE id = ?")
+            .bind(currency_id)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn get_currencies_paginated(
+        &self,
+        sort_by: &str,
+        page: usize,
+        page_size: usize,
+    ) -> Result<(Vec<(i64, String, String)>, i64), StorageError> {
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM currency")
+            .fetch_one(&self.pool)
+            .await?;
+
+        let query = if sort_by.to_lowercase() == "recent" {
+            "SELECT id, name, ticker FROM currency ORDER BY date_created DESC, id DESC LIMIT ? OFFSET ?"
+        } else {
+            "SELECT id, name, ticker FROM currency ORDER BY date_created ASC, id ASC LIMIT ? OFFSET ?"
+        };
+        let currencies = sqlx::query_as(query)
+            .bind(page_size as i64)
+            .bind((page.saturating_sub(1) * page_size) as i64)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok((currencies, total))
+    }
+
+    async fn search_tickers(&self, prefix: &str, limit: i64) -> Result<Vec<(String, String)>, StorageError> {
+        // Escape LIKE wildcards so the input is matched literally
+        let escaped = prefix
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+
+        Ok(sqlx::query_as("SELECT name, ticker FROM currency WHERE ticker LIKE ? || '%' ESCAPE '\\' ORDER BY ticker LIMIT ?")
+            .bind(escaped)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn get_swap_expiry_minutes(&self, currency_id: i64) -> Result<Option<i64>, StorageError> {
+        let minutes: Option<Option<i64>> = sqlx::query_scalar("SELECT swap_expiry_minutes FROM currency WHERE id = ?")
+            .bind(currency_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(minutes.flatten())
+    }
+
+    async fn set_swap_expiry_minutes(&self, currency_id: i64, minutes: Option<i64>) -> Result<(), StorageError> {
+        sqlx::query("UPDATE currency SET swap_expiry_minutes = ? WHERE id = ?")
+            .bind(minutes)
+            .bind(currency_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AccountStore for SqliteStorage {
+    async fn create_account(&self, discord_id: i64, currency_id: i64) -> Result<i64, StorageError> {
+        let result = sqlx::query("INSERT INTO account (discord_id, currency_id, balance) VALUES (?, ?, 0)")
+            .bind(discord_id)
+            .bind(currency_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn get_account_id(&self, discord_id: i64, currency_id: i64) -> Result<Option<i64>, StorageError> {
+        Ok(sqlx::query_scalar("SELECT id FROM account WHERE discord_id = ? AND currency_id = ?")
+            .bind(discord_id)
+            .bind(currency_id)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn get_account_balance(&self, discord_id: i64, currency_id: i64) -> Result<Option<Amount>, StorageError> {
+        Ok(sqlx::query_scalar("SELECT balance FROM account WHERE discord_id = ? AND currency_id = ?")
+            .bind(discord_id)
+            .bind(currency_id)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn get_discord_id_by_account_id(&self, account_id: i64) -> Result<Option<i64>, StorageError> {
+        Ok(sqlx::query_scalar("SELECT discord_id FROM account WHERE id = ?")
+            .bind(account_id)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn get_total_balance(&self, currency_id: i64) -> Result<Option<Amount>, StorageError> {
+        Ok(sqlx::query_scalar("SELECT SUM(balance) FROM account WHERE currency_id = ?")
+            .bind(currency_id)
+            .fetch_one(&self.pool)
+            .await?)
+    }
+
+    async fn update_balance(&self, account_id: i64, amount: Amount) -> Result<(), StorageError> {
+        sqlx::query("UPDATE account SET balance = balance + ?, date_updated = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(amount)
+            .bind(account_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn transfer(
+        &self,
+        sender_discord_id: i64,
+        receiver_discord_id: i64,
+        currency_id: i64,
+        amount: Amount,
+        memo: Option<&str>,
+    ) -> Result<TransferReceipt, LedgerError> {
+        // One row read twice would have the credit overwrite the debit
+        if sender_discord_id == receiver_discord_id {
+            return Err(LedgerError::DuplicateAccount);
+        }
+
+        let mut tx = self.begin_write().await?;
+
+        let (sender_account_id, sender_balance) = find_account(&mut tx, sender_discord_id, currency_id, false)
+            .await?
+            .ok_or(LedgerError::AccountNotFound)?;
+        let (receiver_account_id, receiver_balance) = find_account(&mut tx, receiver_discord_id, currency_id, true)
+            .await?
+            .ok_or(LedgerError::AccountNotFound)?;
+
+        let tax_percentage: i32 = sqlx::query_scalar("SELECT tax_percentage FROM tax_account WHERE currency_id = ?")
+            .bind(currency_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .unwrap_or(0);
+
+        let tax_amount = if tax_percentage > 0 {
+            amount.percent(tax_percentage)
+        } else {
+            Amount::ZERO
+        };
+        let total_deduction = amount + tax_amount;
+
+        // Returning early drops `tx`, which rolls it back
+        if sender_balance < total_deduction {
+            return Err(LedgerError::InsufficientBalance {
+                required: total_deduction,
+                available: sender_balance,
+            });
+        }
+
+        let receiver_new_balance = receiver_balance + amount;
+        if !receiver_new_balance.is_within_limit() {
+            return Err(LedgerError::BalanceLimit {
+                current: receiver_balance,
+                new_balance: receiver_new_balance,
+            });
+        }
+
+        set_balance(&mut tx, sender_account_id, sender_balance - total_deduction).await?;
+        set_balance(&mut tx, receiver_account_id, receiver_new_balance).await?;
+
+        if tax_amount.is_positive() {
+            sqlx::query("UPDATE tax_account SET balance = balance + ? WHERE currency_id = ?")
+                .bind(tax_amount)
+                .bind(currency_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let transaction_uuid = Uuid::new_v4().to_string();
+        insert_transaction(&mut tx, &transaction_uuid, sender_account_id, receiver_account_id, amount, memo).await?;
+
+        tx.commit().await?;
+
+        Ok(TransferReceipt {
+            transaction_uuid,
+            tax_amount,
+        })
+    }
+
+    async fn transfer_many(
+        &self,
+        sender_discord_id: i64,
+        currency_id: i64,
+        payments: &[(i64, Amount)],
+        memo: Option<&str>,
+    ) -> Result<BatchTransferReceipt, LedgerError> {
+        let mut discord_ids: Vec<i64> = payments.iter().map(|(id, _)| *id).collect();
+        discord_ids.push(sender_discord_id);
+        discord_ids.sort_unstable();
+        if discord_ids.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err(LedgerError::DuplicateAccount);
+        }
+
+        let mut tx = self.begin_write().await?;
+
+        let (sender_account_id, sender_balance) = find_account(&mut tx, sender_discord_id, currency_id, false)
+            .await?
+            .ok_or(LedgerError::AccountNotFound)?;
+
+        let tax_percentage: i32 = sqlx::query_scalar("SELECT tax_percentage FROM tax_account WHERE currency_id = ?")
+            .bind(currency_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .unwrap_or(0);
+
+        let total: Amount = payments.iter().map(|(_, amount)| *amount).sum();
+        let tax_amount = if tax_percentage > 0 {
+            total.percent(tax_percentage)
+        } else {
+            Amount::ZERO
+        };
+        let total_deduction = total + tax_amount;
+
+        // Returning early drops `tx`, which rolls it back
+        if sender_balance < total_deduction {
+            return Err(LedgerError::InsufficientBalance {
+                required: total_deduction,
+                available: sender_balance,
+            });
+        }
+
+        set_balance(&mut tx, sender_account_id, sender_balance - total_deduction).await?;
+
+        let mut transaction_uuids = Vec::with_capacity(payments.len());
+        for (receiver_discord_id, amount) in payments {
+            let (receiver_account_id, receiver_balance) = find_account(&mut tx, *receiver_discord_id, currency_id, true)
+                .await?
+                .ok_or(LedgerError::AccountNotFound)?;
+
+            let receiver_new_balance = receiver_balance + *amount;
+            if !receiver_new_balance.is_within_limit() {
+                return Err(LedgerError::BalanceLimit {
+                    current: receiver_balance,
+                    new_balance: receiver_new_balance,
+                });
+            }
+            set_balance(&mut tx, receiver_account_id, receiver_new_balance).await?;
+
+            let transaction_uuid = Uuid::new_v4().to_string();
+            insert_transaction(&mut tx, &transaction_uuid, sender_account_id, receiver_account_id, *amount, memo).await?;
+            transaction_uuids.push(transaction_uuid);
+        }
+
+        if tax_amount.is_positive() {
+            sqlx::query("UPDATE tax_account SET balance = balance + ? WHERE currency_id = ?")
+                .bind(tax_amount)
+                .bind(currency_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(BatchTransferReceipt {
+            transaction_uuids,
+            tax_amount,
+        })
+    }
+
+    async fn mint(&self, discord_id: i64, currency_id: i64, amount: Amount) -> Result<(Amount, Amount), LedgerError> {
+        let mut tx = self.begin_write().await?;
+
+        let (account_id, current_balance) = find_account(&mut tx, discord_id, currency_id, true)
+            .await?
+            .ok_or(LedgerError::AccountNotFound)?;
+
+        let new_balance = current_balance + amount;
+        if new_balance.is_negative() {
+            return Err(LedgerError::NegativeBalance { current: current_balance, new_balance });
+        }
+        if !new_balance.is_within_limit() {
+            return Err(LedgerError::BalanceLimit { current: current_balance, new_balance });
+        }
+
+        set_balance(&mut tx, account_id, new_balance).await?;
+        tx.commit().await?;
+
+        Ok((current_balance, new_balance))
+    }
+
+    async fn payout(
+        &self,
+        issuer_discord_id: i64,
+        recipient_discord_id: i64,
+        currency_id: i64,
+        amount: Amount,
+        source: PayrollSource,
+    ) -> Result<String, LedgerError> {
+        let mut tx = self.begin_write().await?;
+
+        let (issuer_account_id, _) = find_account(&mut tx, issuer_discord_id, currency_id, true)
+            .await?
+            .ok_or(LedgerError::AccountNotFound)?;
+        let (recipient_account_id, recipient_balance) = find_account(&mut tx, recipient_discord_id, currency_id, true)
+            .await?
+            .ok_or(LedgerError::AccountNotFound)?;
+
+        let new_balance = recipient_balance + amount;
+        if !new_balance.is_within_limit() {
+            return Err(LedgerError::BalanceLimit { current: recipient_balance, new_balance });
+        }
+
+        if source == PayrollSource::Treasury {
+            let tax_balance: Amount = sqlx::query_scalar("SELECT balance FROM tax_account WHERE currency_id = ?")
+                .bind(currency_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or(LedgerError::TaxAccountNotFound)?;
+
+            if tax_balance < amount {
+                return Err(LedgerError::InsufficientBalance {
+                    required: amount,
+                    available: tax_balance,
+                });
+            }
+
+            sqlx::query("UPDATE tax_account SET balance = ?, date_updated = CURRENT_TIMESTAMP WHERE currency_id = ?")
+                .bind(tax_balance - amount)
+                .bind(currency_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        set_balance(&mut tx, recipient_account_id, new_balance).await?;
+
+        let transaction_uuid = Uuid::new_v4().to_string();
+        tx.log_transaction(&transaction_uuid, issuer_account_id, recipient_account_id, amount).await?;
+
+        tx.commit().await?;
+
+        Ok(transaction_uuid)
+    }
+}
+
+#[async_trait]
+impl SwapStore for SqliteStorage {
+    async fn create_swap(&self, swap: NewSwap) -> Result<i64, StorageError> {
+        let mut tx = self.begin_write().await?;
+        let swap_id = swaps::create(&mut tx, &swap).await?;
+        tx.commit().await?;
+        Ok(swap_id)
+    }
+
+    async fn accept_swap(
+        &self,
+        swap_id: i64,
+        taker_discord_id: i64,
+        fill_amount: Option<Amount>,
+    ) -> Result<(Amount, Amount, bool), StorageError> {
+        let mut tx = self.begin_write().await?;
+        let result = swaps::fill(&mut tx, swap_id, taker_discord_id, fill_amount).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    async fn cancel_swap(&self, swap_id: i64) -> Result<(), StorageError> {
+        let mut tx = self.begin_write().await?;
+        swaps::close(&mut tx, swap_id, "cancelled").await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn expire_swap(&self, swap_id: i64) -> Result<(), StorageError> {
+        let mut tx = self.begin_write().await?;
+        swaps::close(&mut tx, swap_id, "expired").await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_swap_by_id(&self, swap_id: i64) -> Result<Option<SwapRow>, StorageError> {
+        Ok(sqlx::query_as(
+            "SELECT id, maker_id, taker_id, maker_currency_id, taker_currency_id, maker_amount, taker_amount, status
+             FROM currency_swap WHERE id = ?"
+        )
+        .bind(swap_id)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn get_swap_expiry(&self, swap_id: i64) -> Result<Option<i64>, StorageError> {
+        let expires_at: Option<Option<i64>> = sqlx::query_scalar(
+            "SELECT CAST(strftime('%s', expires_at) AS INTEGER) FROM currency_swap WHERE id = ?"
+        )
+        .bind(swap_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(expires_at.flatten())
+    }
+
+    async fn get_swap_fill_progress(&self, swap_id: i64) -> Result<Option<(Amount, i64)>, StorageError> {
+        Ok(sqlx::query_as(
+            "SELECT cs.filled_amount, (SELECT COUNT(*) FROM swap_fill sf WHERE sf.swap_id = cs.id)
+             FROM currency_swap cs WHERE cs.id = ?"
+        )
+        .bind(swap_id)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn get_total_swap_maker_amount(&self, currency_id: i64) -> Result<Option<Amount>, StorageError> {
+        Ok(sqlx::query_scalar(
+            "SELECT SUM(maker_amount - filled_amount) FROM currency_swap WHERE maker_currency_id = ? AND status = 'pending'"
+        )
+        .bind(currency_id)
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn get_total_order_escrow(&self, _currency_id: i64) -> Result<Option<Amount>, StorageError> {
+        // There is no order book on SQLite
+        Ok(None)
+    }
+
+    async fn get_overdue_swaps(&self, limit: i64) -> Result<Vec<OverdueSwapRow>, StorageError> {
+        Ok(sqlx::query_as(
+            "SELECT cs.id, a_maker.discord_id, a_taker.discord_id,
+                    c_maker.ticker, c_taker.ticker, cs.maker_amount - cs.filled_amount, cs.taker_amount
+             FROM currency_swap cs
+             JOIN account a_maker ON cs.maker_id = a_maker.id
+             LEFT JOIN account a_taker ON cs.taker_id = a_taker.id
+             JOIN currency c_maker ON cs.maker_currency_id = c_maker.id
+             JOIN currency c_taker ON cs.taker_currency_id = c_taker.id
+             WHERE cs.status = 'pending' AND cs.expires_at IS NOT NULL AND cs.expires_at <= datetime('now')
+             ORDER BY cs.expires_at ASC
+             LIMIT ?"
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn store_swap_message(&self, swap_id: i64, channel_id: i64, message_id: i64) -> Result<(), StorageError> {
+        sqlx::query("INSERT INTO swap_message (swap_id, channel_id, message_id) VALUES (?, ?, ?)")
+            .bind(swap_id)
+            .bind(channel_id)
+            .bind(message_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_swap_messages(&self, swap_id: i64) -> Result<Vec<(i64, i64)>, StorageError> {
+        Ok(sqlx::query_as("SELECT channel_id, message_id FROM swap_message WHERE swap_id = ? ORDER BY id")
+            .bind(swap_id)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn get_swaps_paginated(
+        &self,
+        page: usize,
+        page_size: usize,
+        sort: SwapSort,
+        filter: &SwapFilter,
+    ) -> Result<(Vec<SwapListRow>, i64), StorageError> {
+        let offset = (page.max(1) - 1) * page_size;
+
+        let (total_count,): (i64,) = filter
+            .select::<Sqlite>("COUNT(*)")
+            .build_query_as()
+            .fetch_one(&self.pool)
+            .await?;
+
+        let mut query = filter.select_page::<Sqlite>(
+            "cs.id, a_maker.discord_id, a_taker.discord_id, cs.maker_currency_id, cs.taker_currency_id,
+             cs.maker_amount, cs.taker_amount, cs.status, c_maker.ticker, c_taker.ticker, cs.filled_amount",
+            sort,
+            page_size as i64,
+            offset as i64,
+        );
+        let swaps = query.build_query_as().fetch_all(&self.pool).await?;
+
+        Ok((swaps, total_count))
+    }
+}
+
+#[async_trait]
+impl TransactionStore for SqliteStorage {
+    async fn get_transaction_by_uuid(&self, uuid: &str) -> Result<Option<(i64, i64, String, Amount, String, Option<String>)>, StorageError> {
+        Ok(sqlx::query_as("SELECT sender_id, receiver_id, date_created, amount, uuid, memo FROM \"transaction\" WHERE uuid = ?")
+            .bind(uuid)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn get_user_transactions_paginated(
+        &self,
+        discord_id: i64,
+        page: usize,
+        page_size: usize,
+        memo_search: Option<&str>,
+    ) -> Result<(Vec<TransactionRow>, i64), StorageError> {
+        // A NULL pattern matches every transaction, memo or not
+        const MINE: &str = "(t.sender_id IN (SELECT id FROM account WHERE discord_id = ?1)
+                             OR t.receiver_id IN (SELECT id FROM account WHERE discord_id = ?1))
+                            AND (?2 IS NULL OR t.memo LIKE ?2 ESCAPE '\\')";
+        let pattern = memo_search.map(crate::db::transaction::memo_pattern);
+
+        let total_count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM \"transaction\" t WHERE {MINE}"))
+            .bind(discord_id)
+            .bind(&pattern)
+            .fetch_one(&self.pool)
+            .await?;
+
+        let offset = (page.max(1) - 1) * page_size;
+        let transactions = sqlx::query_as(&format!(
+            "SELECT t.sender_id, t.receiver_id, t.amount, t.date_created, t.uuid,
+                    COALESCE((SELECT c.ticker FROM currency c JOIN account a ON a.currency_id = c.id WHERE a.id = t.sender_id), ''),
+                    t.memo
+             FROM \"transaction\" t
+             WHERE {MINE}
+             ORDER BY t.date_created DESC LIMIT ?3 OFFSET ?4"
+        ))
+        .bind(discord_id)
+        .bind(&pattern)
+        .bind(page_size as i64)
+        .bind(offset as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok((transactions, total_count))
+    }
+
+    async fn find_transaction_by_memo(
+        &self,
+        sender_discord_id: i64,
+        receiver_discord_id: i64,
+        currency_id: i64,
+        memo: &str,
+    ) -> Result<Option<String>, StorageError> {
+        Ok(sqlx::query_scalar(
+            "SELECT t.uuid FROM \"transaction\" t
+             JOIN account s ON t.sender_id = s.id
+             JOIN account r ON t.receiver_id = r.id
+             WHERE s.discord_id = ?1 AND r.discord_id = ?2 AND s.currency_id = ?3 AND r.currency_id = ?3 AND t.memo = ?4
+             ORDER BY t.date_created DESC, t.rowid DESC LIMIT 1"
+        )
+        .bind(sender_discord_id)
+        .bind(receiver_discord_id)
+        .bind(currency_id)
+        .bind(memo)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+}
+
+#[async_trait]
+impl TradeLogStore for SqliteStorage {
+    /// Only the price is kept; candles are part of the MySQL price history
+    async fn add_price_log(
+        &self,
+        base_currency_id: i64,
+        quote_currency_id: i64,
+        price: Amount,
+        _volume: Amount,
+        _quote_volume: Amount,
+    ) -> Result<i64, StorageError> {
+        let result = sqlx::query("INSERT INTO tradelog (base_currency_id, quote_currency_id, price) VALUES (?, ?, ?)")
+            .bind(base_currency_id)
+            .bind(quote_currency_id)
+            .bind(price)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.last_insert_rowid())
+    }
+}
+
+#[async_trait]
+impl TaxStore for SqliteStorage {
+    async fn get_tax_account(&self, currency_id: i64) -> Result<Option<(i64, i64, Amount, i32)>, StorageError> {
+        Ok(sqlx::query_as("SELECT id, currency_id, balance, tax_percentage FROM tax_account WHERE currency_id = ?")
+            .bind(currency_id)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn create_tax_account(&self, currency_id: i64, tax_percentage: i32) -> Result<i64, StorageError> {
+        let result = sqlx::query("INSERT INTO tax_account (currency_id, balance, tax_percentage) VALUES (?, 0, ?)")
+            .bind(currency_id)
+            .bind(tax_percentage)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn set_tax_percentage(&self, currency_id: i64, tax_percentage: i32) -> Result<(), StorageError> {
+        sqlx::query("UPDATE tax_account SET tax_percentage = ?, date_updated = CURRENT_TIMESTAMP WHERE currency_id = ?")
+            .bind(tax_percentage)
+            .bind(currency_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_tax_percentage(&self, currency_id: i64) -> Result<Option<i32>, StorageError> {
+        Ok(sqlx::query_scalar("SELECT tax_percentage FROM tax_account WHERE currency_id = ?")
+            .bind(currency_id)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn get_total_tax_balance(&self, currency_id: i64) -> Result<Option<Amount>, StorageError> {
+        let balance: Option<Amount> = sqlx::query_scalar("SELECT balance FROM tax_account WHERE currency_id = ?")
+            .bind(currency_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(Some(balance.unwrap_or(Amount::ZERO)))
+    }
+
+    async fn add_tax(&self, currency_id: i64, amount: Amount) -> Result<(), StorageError> {
+        sqlx::query("UPDATE tax_account SET balance = balance + ?, date_updated = CURRENT_TIMESTAMP WHERE currency_id = ?")
+            .bind(amount)
+            .bind(currency_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn collect_tax(&self, discord_id: i64, currency_id: i64, amount: Option<Amount>) -> Result<Amount, LedgerError> {
+        let mut tx = self.begin_write().await?;
+
+        let (account_id, account_balance) = find_account(&mut tx, discord_id, currency_id, true)
+            .await?
+            .ok_or(LedgerError::AccountNotFound)?;
+
+        let tax_balance: Amount = sqlx::query_scalar("SELECT balance FROM tax_account WHERE currency_id = ?")
+            .bind(currency_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(LedgerError::TaxAccountNotFound)?;
+
+        let collect_amount = amount.unwrap_or(tax_balance);
+        if collect_amount > tax_balance {
+            return Err(LedgerError::InsufficientBalance {
+                required: collect_amount,
+                available: tax_balance,
+            });
+        }
+
+        let new_balance = account_balance + collect_amount;
+        if !new_balance.is_within_limit() {
+            return Err(LedgerError::BalanceLimit { current: account_balance, new_balance });
+        }
+
+        sqlx::query("UPDATE tax_account SET balance = ?, date_updated = CURRENT_TIMESTAMP WHERE currency_id = ?")
+            .bind(tax_balance - collect_amount)
+            .bind(currency_id)
+            .execute(&mut *tx)
+            .await?;
+        set_balance(&mut tx, account_id, new_balance).await?;
+
+        tx.commit().await?;
+
+        Ok(collect_amount)
+    }
+}
+
+const SELECT_RECURRING: &str = "SELECT rp.id, rp.payer_id, rp.recipient_id, rp.currency_id, c.ticker, rp.amount,
+        rp.period, CAST(strftime('%s', rp.next_run_at) AS INTEGER), rp.failure_count, rp.status
+     FROM recurring_payment rp
+     JOIN currency c ON rp.currency_id = c.id";
+
+#[async_trait]
+impl RecurringStore for SqliteStorage {
+    async fn create_recurring_payment(&self, payment: NewRecurringPayment) -> Result<i64, StorageError> {
+        let result = sqlx::query(
+            "INSERT INTO recurring_payment (payer_id, recipient_id, currency_id, amount, period, next_run_at)
+             VALUES (?, ?, ?, ?, ?, datetime(?, 'unixepoch'))"
+        )
+        .bind(payment.payer_id)
+        .bind(payment.recipient_id)
+        .bind(payment.currency_id)
+        .bind(payment.amount)
+        .bind(payment.period.as_str())
+        .bind(payment.next_run_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn get_recurring_payment(&self, id: i64) -> Result<Option<RecurringRow>, StorageError> {
+        Ok(sqlx::query_as(&format!("{SELECT_RECURRING} WHERE rp.id = ?"))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn get_recurring_payments_by_payer(&self, payer_id: i64) -> Result<Vec<RecurringRow>, StorageError> {
+        Ok(sqlx::query_as(&format!("{SELECT_RECURRING} WHERE rp.payer_id = ? AND rp.status <> 'cancelled' ORDER BY rp.id"))
+            .bind(payer_id)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn get_due_recurring_payments(&self, limit: i64) -> Result<Vec<RecurringRow>, StorageError> {
+        Ok(sqlx::query_as(&format!(
+            "{SELECT_RECURRING} WHERE rp.status = 'active' AND rp.next_run_at <= datetime('now')
+             ORDER BY rp.next_run_at, rp.id LIMIT ?"
+        ))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn reschedule_recurring_payment(&self, id: i64, due_at: i64, next_run_at: i64) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            "UPDATE recurring_payment SET next_run_at = datetime(?, 'unixepoch'), date_updated = CURRENT_TIMESTAMP
+             WHERE id = ? AND status = 'active' AND next_run_at = datetime(?, 'unixepoch')"
+        )
+        .bind(next_run_at)
+        .bind(id)
+        .bind(due_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn record_recurring_failure(&self, id: i64) -> Result<i32, StorageError> {
+        Ok(sqlx::query_scalar(
+            "UPDATE recurring_payment SET failure_count = failure_count + 1, date_updated = CURRENT_TIMESTAMP
+             WHERE id = ? RETURNING failure_count"
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn reset_recurring_failures(&self, id: i64) -> Result<(), StorageError> {
+        sqlx::query("UPDATE recurring_payment SET failure_count = 0 WHERE id = ? AND failure_count <> 0")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_recurring_status(&self, id: i64, status: &str) -> Result<(), StorageError> {
+        sqlx::query("UPDATE recurring_payment SET status = ?, date_updated = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(status)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+const SELECT_PAYROLL: &str = "SELECT p.id, p.guild_id, p.role_id, p.currency_id, c.ticker, p.amount, p.source,
+        p.period, CAST(strftime('%s', p.next_run_at) AS INTEGER), p.created_by, p.status
+     FROM payroll_schedule p
+     JOIN currency c ON p.currency_id = c.id";
+
+#[async_trait]
+impl PayrollStore for SqliteStorage {
+    async fn create_payroll(&self, payroll: NewPayroll) -> Result<i64, StorageError> {
+        let result = sqlx::query(
+            "INSERT INTO payroll_schedule (guild_id, role_id, currency_id, amount, source, period, next_run_at, created_by)
+             VALUES (?, ?, ?, ?, ?, ?, datetime(?, 'unixepoch'), ?)"
+        )
+        .bind(payroll.guild_id)
+        .bind(payroll.role_id)
+        .bind(payroll.currency_id)
+        .bind(payroll.amount)
+        .bind(payroll.source.as_str())
+        .bind(payroll.period.as_str())
+        .bind(payroll.next_run_at)
+        .bind(payroll.created_by)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn get_payroll(&self, id: i64) -> Result<Option<PayrollRow>, StorageError> {
+        Ok(sqlx::query_as(&format!("{SELECT_PAYROLL} WHERE p.id = ?"))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn get_payrolls_by_guild(&self, guild_id: i64) -> Result<Vec<PayrollRow>, StorageError> {
+        Ok(sqlx::query_as(&format!("{SELECT_PAYROLL} WHERE p.guild_id = ? AND p.status = 'active' ORDER BY p.id"))
+            .bind(guild_id)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn get_due_payrolls(&self, limit: i64) -> Result<Vec<PayrollRow>, StorageError> {
+        Ok(sqlx::query_as(&format!(
+            "{SELECT_PAYROLL} WHERE p.status = 'active' AND p.next_run_at <= datetime('now')
+             ORDER BY p.next_run_at, p.id LIMIT ?"
+        ))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn reschedule_payroll(&self, id: i64, due_at: i64, next_run_at: i64) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            "UPDATE payroll_schedule SET next_run_at = datetime(?, 'unixepoch'), date_updated = CURRENT_TIMESTAMP
+             WHERE id = ? AND status = 'active' AND next_run_at = datetime(?, 'unixepoch')"
+        )
+        .bind(next_run_at)
+        .bind(id)
+        .bind(due_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn set_payroll_status(&self, id: i64, status: &str) -> Result<(), StorageError> {
+        sqlx::query("UPDATE payroll_schedule SET status = ?, date_updated = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(status)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+const SELECT_INVOICE: &str = "SELECT i.id, i.issuer_id, i.payer_id, i.currency_id, c.ticker, i.amount, i.memo, i.status,
+        i.transaction_uuid, CAST(strftime('%s', i.expires_at) AS INTEGER)
+     FROM invoice i
+     JOIN currency c ON i.currency_id = c.id";
+
+#[async_trait]
+impl InvoiceStore for SqliteStorage {
+    async fn create_invoice(&self, invoice: NewInvoice) -> Result<i64, StorageError> {
+        let result = sqlx::query(
+            "INSERT INTO invoice (issuer_id, payer_id, currency_id, amount, memo, expires_at)
+             VALUES (?, ?, ?, ?, ?, datetime(?, 'unixepoch'))"
+        )
+        .bind(invoice.issuer_id)
+        .bind(invoice.payer_id)
+        .bind(invoice.currency_id)
+        .bind(invoice.amount)
+        .bind(invoice.memo)
+        .bind(invoice.expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn get_invoice(&self, id: i64) -> Result<Option<InvoiceRow>, StorageError> {
+        Ok(sqlx::query_as(&format!("{SELECT_INVOICE} WHERE i.id = ?"))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn get_invoices_by_user(&self, discord_id: i64, status: Option<&str>, limit: i64) -> Result<Vec<InvoiceRow>, StorageError> {
+        Ok(sqlx::query_as(&format!(
+            "{SELECT_INVOICE} WHERE (i.issuer_id = ?1 OR i.payer_id = ?1) AND (?2 IS NULL OR i.status = ?2)
+             ORDER BY i.id DESC LIMIT ?3"
+        ))
+        .bind(discord_id)
+        .bind(status)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn get_overdue_invoices(&self, limit: i64) -> Result<Vec<InvoiceRow>, StorageError> {
+        Ok(sqlx::query_as(&format!(
+            "{SELECT_INVOICE} WHERE i.status = 'open' AND i.expires_at <= datetime('now')
+             ORDER BY i.expires_at, i.id LIMIT ?"
+        ))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn get_stalled_invoices(&self, idle_seconds: i64, limit: i64) -> Result<Vec<InvoiceRow>, StorageError> {
+        Ok(sqlx::query_as(&format!(
+            "{SELECT_INVOICE} WHERE i.status = 'paying' AND i.date_updated <= datetime('now', '-' || ? || ' seconds')
+             ORDER BY i.id LIMIT ?"
+        ))
+        .bind(idle_seconds)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn claim_invoice(&self, id: i64) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            "UPDATE invoice SET status = 'paying', date_updated = CURRENT_TIMESTAMP
+             WHERE id = ? AND status = 'open' AND expires_at > datetime('now')"
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn set_invoice_status(&self, id: i64, from: &str, to: &str) -> Result<bool, StorageError> {
+        let result = sqlx::query("UPDATE invoice SET status = ?, date_updated = CURRENT_TIMESTAMP WHERE id = ? AND status = ?")
+            .bind(to)
+            .bind(id)
+            .bind(from)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn complete_invoice(&self, id: i64, transaction_uuid: &str) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            "UPDATE invoice SET status = 'paid', transaction_uuid = ?, date_updated = CURRENT_TIMESTAMP
+             WHERE id = ? AND status = 'paying'"
+        )
+        .bind(transaction_uuid)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+}
+
+const SELECT_WIRE: &str = "SELECT w.id, w.discord_id, w.currency_id, c.ticker, w.direction, w.amount, w.fee, w.remote_amount, w.state,
+        w.remote_before, w.last_error, CAST(strftime('%s', w.date_created) AS INTEGER),
+        CAST(strftime('%s', w.date_updated) AS INTEGER)
+     FROM wire_transfer w
+     JOIN currency c ON w.currency_id = c.id";
+
+impl SqliteStorage {
+    /// Change the user's balance by the wire's amount and the tax account by its fee,
+    /// and move it from `from` to `to`. Credits the user for "in", debits them for "out";
+    /// `reverse` swaps the two and takes the fee back.
+    async fn move_wire_balance(&self, id: i64, from: &'static str, to: &str, reverse: bool) -> Result<Amount, LedgerError> {
+        let mut tx = self.begin_write().await?;
+
+        let (discord_id, currency_id, direction, amount, fee, state): (i64, i64, String, Amount, Amount, String) = sqlx::query_as(
+            "SELECT discord_id, currency_id, direction, amount, fee, state FROM wire_transfer WHERE id = ?"
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(LedgerError::WireState(from))?;
+
+        if state != from {
+            return Err(LedgerError::WireState(from));
+        }
+
+        let credit = (direction == "in") != reverse;
+        let (account_id, current_balance) = find_account(&mut tx, discord_id, currency_id, credit)
+            .await?
+            .ok_or(LedgerError::InsufficientBalance { required: amount, available: Amount::ZERO })?;
+
+        let new_balance = if credit { current_balance + amount } else { current_balance - amount };
+        if new_balance.is_negative() {
+            return Err(LedgerError::InsufficientBalance { required: amount, available: current_balance });
+        }
+        if !new_balance.is_within_limit() {
+            return Err(LedgerError::BalanceLimit { current: current_balance, new_balance });
+        }
+
+        set_balance(&mut tx, account_id, new_balance).await?;
+        if fee.is_positive() {
+            let tax_balance: Amount = sqlx::query_scalar("SELECT balance FROM tax_account WHERE currency_id = ?")
+                .bind(currency_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or(LedgerError::TaxAccountNotFound)?;
+            let new_tax_balance = if reverse { tax_balance - fee } else { tax_balance + fee };
+            if new_tax_balance.is_negative() {
+                return Err(LedgerError::InsufficientBalance { required: fee, available: tax_balance });
+            }
+            sqlx::query("UPDATE tax_account SET balance = ?, date_updated = CURRENT_TIMESTAMP WHERE currency_id = ?")
+                .bind(new_tax_balance)
+                .bind(currency_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query("UPDATE wire_transfer SET state = ?, date_updated = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(to)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(new_balance)
+    }
+}
+
+#[async_trait]
+impl WireStore for SqliteStorage {
+    async fn create_wire(&self, wire: NewWire) -> Result<i64, StorageError> {
+        let result = sqlx::query(
+            "INSERT INTO wire_transfer (discord_id, currency_id, direction, amount, fee, remote_amount)
+             VALUES (?, ?, ?, ?, ?, ?)"
+        )
+        .bind(wire.discord_id)
+        .bind(wire.currency_id)
+        .bind(wire.direction)
+        .bind(wire.amount)
+        .bind(wire.fee)
+        .bind(wire.remote_amount)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn get_wire(&self, id: i64) -> Result<Option<WireRow>, StorageError> {
+        Ok(sqlx::query_as(&format!("{SELECT_WIRE} WHERE w.id = ?"))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn get_wires_by_user(&self, discord_id: i64, limit: i64) -> Result<Vec<WireRow>, StorageError> {
+        Ok(sqlx::query_as(&format!("{SELECT_WIRE} WHERE w.discord_id = ? ORDER BY w.id DESC LIMIT ?"))
+            .bind(discord_id)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn get_unsettled_wires(&self, currency_id: Option<i64>, idle_seconds: i64, limit: i64) -> Result<Vec<WireRow>, StorageError> {
+        Ok(sqlx::query_as(&format!(
+            "{SELECT_WIRE} WHERE w.state IN ('initiated', 'smite_applied')
+               AND w.date_updated <= datetime('now', '-' || ?1 || ' seconds')
+               AND (?2 IS NULL OR w.currency_id = ?2)
+             ORDER BY w.id LIMIT ?3"
+        ))
+        .bind(idle_seconds)
+        .bind(currency_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn apply_wire(&self, id: i64) -> Result<Amount, LedgerError> {
+        self.move_wire_balance(id, "initiated", "smite_applied", false).await
+    }
+
+    async fn compensate_wire(&self, id: i64) -> Result<Amount, LedgerError> {
+        self.move_wire_balance(id, "smite_applied", "compensated", true).await
+    }
+
+    async fn record_wire_remote_balance(&self, id: i64, balance: i64) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            "UPDATE wire_transfer SET remote_before = ?, date_updated = CURRENT_TIMESTAMP WHERE id = ? AND state = 'smite_applied'"
+        )
+        .bind(balance)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn set_wire_state(&self, id: i64, from: &str, to: &str, error: Option<&str>) -> Result<bool, StorageError> {
+        let result = sqlx::query(
+            "UPDATE wire_transfer SET state = ?, last_error = COALESCE(?, last_error), date_updated = CURRENT_TIMESTAMP
+             WHERE id = ? AND state = ?"
+        )
+        .bind(to)
+        .bind(error.map(crate::db::wire::truncate_error))
+        .bind(id)
+        .bind(from)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn get_wire_config(&self, currency_id: i64) -> Result<Option<WireConfig>, StorageError> {
+        let row: Option<crate::db::wire::WireConfigRow> = sqlx::query_as(
+            "SELECT rate, min_amount, max_amount, fee_percent, fee_flat, rounding FROM wire_config WHERE currency_id = ?"
+        )
+        .bind(currency_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(WireConfig::from))
+    }
+
+    async fn set_wire_config(&self, currency_id: i64, config: &WireConfig) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO wire_config (currency_id, rate, min_amount, max_amount, fee_percent, fee_flat, rounding)
+             VALUES (?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT (currency_id) DO UPDATE SET rate = excluded.rate, min_amount = excluded.min_amount,
+                 max_amount = excluded.max_amount, fee_percent = excluded.fee_percent, fee_flat = excluded.fee_flat,
+                 rounding = excluded.rounding, date_updated = CURRENT_TIMESTAMP"
+        )
+        .bind(currency_id)
+        .bind(config.rate)
+        .bind(config.min_amount)
+        .bind(config.max_amount)
+        .bind(config.fee_percent)
+        .bind(config.fee_flat)
+        .bind(config.rounding.as_str())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ApiTokenStore for SqliteStorage {
+    async fn get_api_token(&self, currency_id: i64, api_type_id: i32) -> Result<Option<String>, StorageError> {
+        Ok(sqlx::query_scalar("SELECT encrypted_token FROM api_token WHERE currency_id = ? AND api_type_id = ?")
+            .bind(currency_id)
+            .bind(api_type_id)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn store_api_token(&self, currency_id: i64, api_type_id: i32, encrypted_token: &str) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO api_token (currency_id, api_type_id, encrypted_token) VALUES (?, ?, ?)
+             ON CONFLICT (currency_id, api_type_id)
+             DO UPDATE SET encrypted_token = excluded.encrypted_token, date_updated = CURRENT_TIMESTAMP"
+        )
+        .bind(currency_id)
+        .bind(api_type_id)
+        .bind(encrypted_token)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_bridge_provider(&self, currency_id: i64) -> Result<Option<(i32, Option<String>)>, StorageError> {
+        Ok(sqlx::query_as("SELECT api_type_id, settings FROM bridge_provider WHERE currency_id = ?")
+            .bind(currency_id)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn set_bridge_provider(&self, currency_id: i64, api_type_id: i32, settings: Option<&str>) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO bridge_provider (currency_id, api_type_id, settings) VALUES (?, ?, ?)
+             ON CONFLICT (currency_id)
+             DO UPDATE SET api_type_id = excluded.api_type_id, settings = excluded.settings, date_updated = CURRENT_TIMESTAMP"
+        )
+        .bind(currency_id)
+        .bind(api_type_id)
+        .bind(settings)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::amount;
+    use crate::db::recurring::RecurringPeriod;
+    use crate::utils::Rounding;
+
+    /// Two funded users of currencies A and B
+    async fn setup() -> (SqliteStorage, i64, i64) {
+        let storage = SqliteStorage::in_memory().await;
+        let a = storage.create_currency(1, "Alpha", "AAA").await.unwrap();
+        let b = storage.create_currency(2, "Beta", "BBB").await.unwrap();
+        storage.mint(10, a, amount("100")).await.unwrap();
+        storage.mint(20, b, amount("100")).await.unwrap();
+        (storage, a, b)
+    }
+
+    fn open_swap(maker_account_id: i64, a: i64, b: i64, expiry_minutes: Option<i64>) -> NewSwap {
+        NewSwap {
+            maker_account_id,
+            maker_currency_id: a,
+            taker_currency_id: b,
+            maker_amount: amount("3"),
+            taker_amount: amount("1"),
+            taker_account_id: None,
+            expiry_minutes,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_migrations_are_idempotent() {
+        let storage = SqliteStorage::in_memory().await;
+        crate::db::sqlite::run(&storage.pool).await.unwrap();
+
+        let states = crate::db::sqlite::status(&storage.pool).await.unwrap();
+        assert_eq!(states.len(), crate::db::sqlite::MIGRATIONS.len());
+        assert!(states.iter().all(|(_, state)| matches!(state, crate::db::migrate::MigrationState::Applied(_))));
+    }
+
+    #[tokio::test]
+    async fn test_transfer_with_tax() {
+        let (storage, a, _) = setup().await;
+        storage.create_tax_account(a, 10).await.unwrap();
+
+        let receipt = storage.transfer(10, 30, a, amount("50"), None).await.unwrap();
+        assert_eq!(receipt.tax_amount, amount("5"));
+        assert_eq!(storage.get_account_balance(10, a).await.unwrap(), Some(amount("45")));
+        assert_eq!(storage.get_account_balance(30, a).await.unwrap(), Some(amount("50")));
+        assert!(storage.get_transaction_by_uuid(&receipt.transaction_uuid).await.unwrap().is_some());
+
+        let overdraft = storage.transfer(10, 30, a, amount("45"), None).await;
+        assert!(matches!(overdraft, Err(LedgerError::InsufficientBalance { .. })));
+        assert_eq!(storage.get_account_balance(10, a).await.unwrap(), Some(amount("45")));
+
+        let to_self = storage.transfer(10, 10, a, amount("10"), None).await;
+        assert!(matches!(to_self, Err(LedgerError::DuplicateAccount)));
+        assert_eq!(storage.get_account_balance(10, a).await.unwrap(), Some(amount("45")));
+
+        assert_eq!(storage.collect_tax(10, a, None).await.unwrap(), amount("5"));
+        assert_eq!(storage.get_account_balance(10, a).await.unwrap(), Some(amount("50")));
+
+        let (rows, total) = storage.get_user_transactions_paginated(30, 1, 10, None).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(rows[0].5, "AAA");
+    }
+
+    #[tokio::test]
+    async fn test_history_filters_by_memo() {
+        let (storage, a, _) = setup().await;
+        storage.transfer(10, 30, a, amount("1"), Some("Rent for March")).await.unwrap();
+        storage.transfer(10, 30, a, amount("2"), Some("50% off")).await.unwrap();
+        storage.transfer(10, 30, a, amount("3"), None).await.unwrap();
+
+        let (rows, total) = storage.get_user_transactions_paginated(30, 1, 10, Some("rent")).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(rows[0].6.as_deref(), Some("Rent for March"));
+
+        // Wildcards in the search are matched literally
+        assert_eq!(storage.get_user_transactions_paginated(30, 1, 10, Some("%")).await.unwrap().1, 1);
+        assert_eq!(storage.get_user_transactions_paginated(30, 1, 10, Some("_")).await.unwrap().1, 0);
+        assert_eq!(storage.get_user_transactions_paginated(30, 1, 10, None).await.unwrap().1, 3);
+    }
+
+    #[tokio::test]
+    async fn test_currency_info_and_board() {
+        let (storage, a, b) = setup().await;
+        storage.create_tax_account(a, 10).await.unwrap();
+        storage.transfer(10, 30, a, amount("50"), None).await.unwrap();
+        let maker = storage.get_account_id(10, a).await.unwrap().unwrap();
+        storage.create_swap(open_swap(maker, a, b, None)).await.unwrap();
+
+        let info = crate::services::info_service::get_currency_info(&storage, "aaa").await.unwrap();
+        assert_eq!((info.account_balance_total, info.tax_balance_total), (amount("92"), amount("5")));
+        assert_eq!((info.swap_maker_total, info.order_escrow_total), (amount("3"), Amount::ZERO));
+        assert_eq!(info.total_in_circulation, amount("100"));
+        assert_eq!(info.date_created.len(), "YYYY-MM-DD".len());
+
+        let (oldest, total) = storage.get_currencies_paginated("oldest", 1, 1).await.unwrap();
+        assert_eq!((oldest[0].2.as_str(), total), ("AAA", 2));
+        let (recent, _) = storage.get_currencies_paginated("recent", 1, 10).await.unwrap();
+        assert_eq!(recent.iter().map(|c| c.2.as_str()).collect::<Vec<_>>(), ["BBB", "AAA"]);
+
+        assert_eq!(storage.search_tickers("b", 10).await.unwrap(), vec![("Beta".to_string(), "BBB".to_string())]);
+        assert!(storage.search_tickers("_", 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_partial_fills_settle_the_swap() {
+        let (storage, a, b) = setup().await;
+        let maker = storage.get_account_id(10, a).await.unwrap().unwrap();
+        let swap_id = storage.create_swap(open_swap(maker, a, b, None)).await.unwrap();
+        assert_eq!(storage.get_account_balance(10, a).await.unwrap(), Some(amount("97")));
+
+        let own = storage.accept_swap(swap_id, 10, None).await;
+        assert_eq!(own.err().unwrap().to_string(), "Cannot accept your own open swap");
+
+        assert_eq!(
+            storage.accept_swap(swap_id, 20, Some(amount("1"))).await.unwrap(),
+            (amount("1"), amount("0.33333334"), false)
+        );
+        assert_eq!(
+            storage.accept_swap(swap_id, 20, None).await.unwrap(),
+            (amount("2"), amount("0.66666666"), true)
+        );
+
+        assert_eq!(storage.get_account_balance(20, a).await.unwrap(), Some(amount("3")));
+        assert_eq!(storage.get_account_balance(20, b).await.unwrap(), Some(amount("99")));
+        assert_eq!(storage.get_account_balance(10, b).await.unwrap(), Some(amount("1")));
+        assert_eq!(storage.get_swap_fill_progress(swap_id).await.unwrap(), Some((amount("3"), 2)));
+
+        let swap = storage.get_swap_by_id(swap_id).await.unwrap().unwrap();
+        assert_eq!(swap.7, "accepted");
+        assert_eq!(swap.2, storage.get_account_id(20, b).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_and_expire_refund_the_maker() {
+        let (storage, a, b) = setup().await;
+        let maker = storage.get_account_id(10, a).await.unwrap().unwrap();
+
+        let cancelled = storage.create_swap(open_swap(maker, a, b, None)).await.unwrap();
+        storage.accept_swap(cancelled, 20, Some(amount("1"))).await.unwrap();
+        storage.cancel_swap(cancelled).await.unwrap();
+        assert_eq!(storage.get_account_balance(10, a).await.unwrap(), Some(amount("99")));
+        assert!(storage.cancel_swap(cancelled).await.is_err());
+
+        // An expiry of zero minutes is overdue straight away
+        let expired = storage.create_swap(open_swap(maker, a, b, Some(0))).await.unwrap();
+        assert!(storage.get_swap_expiry(expired).await.unwrap().is_some());
+        let late = storage.accept_swap(expired, 20, None).await;
+        assert_eq!(late.err().unwrap().to_string(), "Swap has expired");
+
+        let overdue = storage.get_overdue_swaps(10).await.unwrap();
+        assert_eq!(overdue.len(), 1);
+        assert_eq!(overdue[0].0, expired);
+
+        storage.expire_swap(expired).await.unwrap();
+        assert_eq!(storage.get_account_balance(10, a).await.unwrap(), Some(amount("99")));
+
+        let filter = SwapFilter { maker_ticker: Some("aaa".to_string()), ..Default::default() };
+        let (rows, total) = storage.get_swaps_paginated(1, 10, SwapSort::Oldest, &filter).await.unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(rows.iter().map(|row| row.7.as_str()).collect::<Vec<_>>(), ["cancelled", "expired"]);
+    }
+
+    #[tokio::test]
+    async fn test_recurring_payment_is_claimed_once() {
+        let (storage, a, _) = setup().await;
+        let due_at = chrono::Utc::now().timestamp() - 60;
+        let id = storage.create_recurring_payment(NewRecurringPayment {
+            payer_id: 10,
+            recipient_id: 20,
+            currency_id: a,
+            amount: amount("5"),
+            period: RecurringPeriod::Daily,
+            next_run_at: due_at,
+        })
+        .await
+        .unwrap();
+
+        let due = storage.get_due_recurring_payments(10).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!((due[0].0, due[0].4.as_str(), due[0].7), (id, "AAA", due_at));
+
+        let next_run_at = RecurringPeriod::Daily.next_run(due_at);
+        assert!(storage.reschedule_recurring_payment(id, due_at, next_run_at).await.unwrap());
+        assert!(!storage.reschedule_recurring_payment(id, due_at, next_run_at).await.unwrap());
+        assert!(storage.get_due_recurring_payments(10).await.unwrap().is_empty());
+
+        assert_eq!(storage.record_recurring_failure(id).await.unwrap(), 1);
+        assert_eq!(storage.record_recurring_failure(id).await.unwrap(), 2);
+        storage.reset_recurring_failures(id).await.unwrap();
+        storage.set_recurring_status(id, "suspended").await.unwrap();
+
+        let payment = storage.get_recurring_payment(id).await.unwrap().unwrap();
+        assert_eq!((payment.7, payment.8, payment.9.as_str()), (next_run_at, 0, "suspended"));
+        assert_eq!(storage.get_recurring_payments_by_payer(10).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stalled_invoice_payment_is_found_by_memo() {
+        let (storage, a, _) = setup().await;
+        let expires_at = chrono::Utc::now().timestamp() + 3600;
+        let id = storage.create_invoice(NewInvoice {
+            issuer_id: 30,
+            payer_id: 10,
+            currency_id: a,
+            amount: amount("5"),
+            memo: None,
+            expires_at,
+        })
+        .await
+        .unwrap();
+        assert!(storage.claim_invoice(id).await.unwrap());
+        assert!(storage.get_stalled_invoices(60, 10).await.unwrap().is_empty());
+        assert_eq!(storage.get_stalled_invoices(0, 10).await.unwrap()[0].0, id);
+
+        let receipt = storage.transfer(10, 30, a, amount("5"), Some("Invoice #1")).await.unwrap();
+        assert_eq!(storage.find_transaction_by_memo(10, 30, a, "Invoice #1").await.unwrap(), Some(receipt.transaction_uuid));
+        assert!(storage.find_transaction_by_memo(10, 30, a, "Invoice #12").await.unwrap().is_none());
+        assert!(storage.find_transaction_by_memo(30, 10, a, "Invoice #1").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_payout_logs_a_transaction() {
+        let (storage, a, _) = setup().await;
+        storage.create_tax_account(a, 0).await.unwrap();
+        storage.add_tax(a, amount("8")).await.unwrap();
+
+        let uuid = storage.payout(10, 30, a, amount("5"), PayrollSource::Treasury).await.unwrap();
+        assert!(storage.get_transaction_by_uuid(&uuid).await.unwrap().is_some());
+        let short = storage.payout(10, 30, a, amount("5"), PayrollSource::Treasury).await;
+        assert!(matches!(short, Err(LedgerError::InsufficientBalance { .. })));
+
+        storage.payout(10, 30, a, amount("5"), PayrollSource::Mint).await.unwrap();
+        assert_eq!(storage.get_account_balance(30, a).await.unwrap(), Some(amount("10")));
+        assert_eq!(storage.get_account_balance(10, a).await.unwrap(), Some(amount("100")));
+        assert_eq!(storage.get_tax_account(a).await.unwrap().unwrap().2, amount("3"));
+    }
+
+    #[tokio::test]
+    async fn test_wire_journal_moves_the_balance_once() {
+        let (storage, a, _) = setup().await;
+        let out = |amount: Amount| NewWire {
+            discord_id: 10,
+            currency_id: a,
+            direction: "out".to_string(),
+            amount,
+            fee: Amount::ZERO,
+            remote_amount: amount.whole_part().unwrap(),
+        };
+
+        let wire = storage.create_wire(out(amount("40"))).await.unwrap();
+        assert_eq!(storage.apply_wire(wire).await.unwrap(), amount("60"));
+        assert!(matches!(storage.apply_wire(wire).await, Err(LedgerError::WireState("initiated"))));
+        assert!(storage.record_wire_remote_balance(wire, 7).await.unwrap());
+
+        let short = storage.create_wire(out(amount("61"))).await.unwrap();
+        assert!(matches!(storage.apply_wire(short).await, Err(LedgerError::InsufficientBalance { .. })));
+        assert_eq!(storage.get_wire(short).await.unwrap().unwrap().8, "initiated");
+
+        assert!(storage.get_unsettled_wires(Some(a), 60, 10).await.unwrap().is_empty());
+        assert_eq!(storage.get_unsettled_wires(Some(a), 0, 10).await.unwrap().len(), 2);
+
+        assert_eq!(storage.compensate_wire(wire).await.unwrap(), amount("100"));
+        assert!(storage.compensate_wire(wire).await.is_err());
+        let row = storage.get_wire(wire).await.unwrap().unwrap();
+        assert_eq!((row.8.as_str(), row.9), ("compensated", Some(7)));
+        assert_eq!(storage.get_wires_by_user(10, 10).await.unwrap()[0].0, short);
+    }
+
+    #[tokio::test]
+    async fn test_wire_fee_goes_to_the_tax_account_and_back() {
+        let (storage, a, _) = setup().await;
+        storage.create_tax_account(a, 0).await.unwrap();
+        assert!(storage.get_wire_config(a).await.unwrap().is_none());
+
+        let config = WireConfig {
+            rate: amount("2.5"),
+            min_amount: Some(amount("1")),
+            max_amount: None,
+            fee_percent: amount("1.5"),
+            fee_flat: amount("0.25"),
+            rounding: Rounding::Nearest,
+        };
+        storage.set_wire_config(a, &config).await.unwrap();
+        storage.set_wire_config(a, &config).await.unwrap();
+        assert_eq!(storage.get_wire_config(a).await.unwrap(), Some(config));
+
+        let wire = storage.create_wire(NewWire {
+            discord_id: 10,
+            currency_id: a,
+            direction: "in".to_string(),
+            amount: amount("9"),
+            fee: amount("1"),
+            remote_amount: 4,
+        })
+        .await
+        .unwrap();
+        assert_eq!(storage.apply_wire(wire).await.unwrap(), amount("109"));
+        assert_eq!(storage.get_tax_account(a).await.unwrap().unwrap().2, amount("1"));
+        let row = storage.get_wire(wire).await.unwrap().unwrap();
+        assert_eq!((row.5, row.6, row.7), (amount("9"), amount("1"), 4));
+
+        assert_eq!(storage.compensate_wire(wire).await.unwrap(), amount("100"));
+        assert_eq!(storage.get_tax_account(a).await.unwrap().unwrap().2, Amount::ZERO);
+    }
+}
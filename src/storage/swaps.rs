@@ -0,0 +1,212 @@
+//! Swap settlement shared by the SQL backends.
+//!
+//! This was the `sp_create_swap`, `sp_fill_swap` and `sp_close_swap` stored
+//! procedures. It runs against a `SwapTx`, the few row operations each engine
+//! implements for its own transactions, so MySQL and SQLite settle swaps with the
+//! same checks, rounding and rejection messages. The caller owns the transaction
+//! and rolls it back when an operation here returns an error.
+
+use serenity::async_trait;
+use uuid::Uuid;
+use crate::utils::Amount;
+use crate::utils::errors::StorageError;
+use super::NewSwap;
+
+/// A swap as settlement sees it, read while holding its row
+pub struct LockedSwap {
+    pub maker_account_id: i64,
+    pub taker_account_id: Option<i64>,
+    pub maker_currency_id: i64,
+    pub taker_currency_id: i64,
+    pub maker_amount: Amount,
+    pub taker_amount: Amount,
+    pub filled_amount: Amount,
+    pub status: String,
+    /// The swap's expiry has passed
+    pub expired: bool,
+}
+
+/// Row operations inside one open transaction
+#[async_trait]
+pub trait SwapTx: Send {
+    /// Insert a pending swap, returning its ID
+    async fn insert_swap(&mut self, swap: &NewSwap) -> Result<i64, sqlx::Error>;
+
+    /// Read a swap and hold its row until the transaction ends
+    async fn lock_swap(&mut self, swap_id: i64) -> Result<Option<LockedSwap>, sqlx::Error>;
+
+    /// Read an account's balance and hold its row until the transaction ends
+    async fn lock_balance(&mut self, account_id: i64) -> Result<Option<Amount>, sqlx::Error>;
+
+    async fn discord_id(&mut self, account_id: i64) -> Result<Option<i64>, sqlx::Error>;
+
+    /// The user's account for a currency, inserting a zero-balance one if missing
+    async fn account_for(&mut self, discord_id: i64, currency_id: i64) -> Result<i64, sqlx::Error>;
+
+    /// Add `amount` (negative to deduct) to an account
+    async fn add_balance(&mut self, account_id: i64, amount: Amount) -> Result<(), sqlx::Error>;
+
+    /// Total the takers have paid across a swap's fills
+    async fn taker_paid(&mut self, swap_id: i64) -> Result<Amount, sqlx::Error>;
+
+    async fn log_transaction(&mut self, uuid: &str, sender_id: i64, receiver_id: i64, amount: Amount) -> Result<(), sqlx::Error>;
+
+    /// Record a fill and add it to the swap's filled amount
+    async fn record_fill(
+        &mut self,
+        swap_id: i64,
+        taker_account_id: i64,
+        maker_amount: Amount,
+        taker_amount: Amount,
+    ) -> Result<(), sqlx::Error>;
+
+    /// Set a swap's status, and its taker when one is given
+    async fn set_status(&mut self, swap_id: i64, status: &str, taker_account_id: Option<i64>) -> Result<(), sqlx::Error>;
+}
+
+fn rejected(message: &str) -> StorageError {
+    StorageError::Rejected(message.to_string())
+}
+
+/// Escrow the maker's amount and open the swap, returning its ID
+pub async fn create(tx: &mut impl SwapTx, swap: &NewSwap) -> Result<i64, StorageError> {
+    let maker_balance = tx.lock_balance(swap.maker_account_id).await?;
+    if maker_balance.is_none_or(|balance| balance < swap.maker_amount) {
+        return Err(rejected("Maker has insufficient balance"));
+    }
+
+    tx.add_balance(swap.maker_account_id, -swap.maker_amount).await?;
+    Ok(tx.insert_swap(swap).await?)
+}
+
+/// What the taker pays for `fill_maker_amount` of a swap.
+/// Partial fills round up so the maker never receives less than their price;
+/// the fill that completes the swap pays whatever is left of the taker amount.
+pub fn taker_payment(
+    fill_maker_amount: Amount,
+    remaining: Amount,
+    (maker_amount, taker_amount): (Amount, Amount),
+    taker_paid: Amount,
+) -> Amount {
+    let unpaid = taker_amount - taker_paid;
+    if fill_maker_amount == remaining {
+        return unpaid.max(Amount::ZERO);
+    }
+    fill_maker_amount
+        .checked_mul_ratio_ceil(taker_amount, maker_amount)
+        .unwrap_or(unpaid)
+        .min(unpaid)
+}
+
+/// Accept a swap as `taker_discord_id`, or fill part of an open swap.
+/// `fill_amount` is in the maker's currency; None takes whatever is left.
+/// Returns (maker amount filled, taker amount paid, fully filled).
+pub async fn fill(
+    tx: &mut impl SwapTx,
+    swap_id: i64,
+    taker_discord_id: i64,
+    fill_amount: Option<Amount>,
+) -> Result<(Amount, Amount, bool), StorageError> {
+    let swap = tx.lock_swap(swap_id).await?.ok_or(rejected("Swap not found"))?;
+    if swap.status != "pending" {
+        return Err(rejected("Swap is not pending"));
+    }
+    if swap.expired {
+        return Err(rejected("Swap has expired"));
+    }
+
+    let maker_discord_id = tx.discord_id(swap.maker_account_id).await?
+        .ok_or(rejected("Maker Discord ID not found"))?;
+    let designated_taker = match swap.taker_account_id {
+        Some(account_id) => Some(tx.discord_id(account_id).await?.ok_or(rejected("Taker Discord ID not found"))?),
+        None => None,
+    };
+
+    match designated_taker {
+        Some(discord_id) if discord_id != taker_discord_id => {
+            return Err(rejected("Not authorized to accept this swap"));
+        }
+        None if taker_discord_id == maker_discord_id => {
+            return Err(rejected("Cannot accept your own open swap"));
+        }
+        _ => {}
+    }
+
+    // Work out the size of this fill
+    let remaining = swap.maker_amount - swap.filled_amount;
+    let fill_maker_amount = fill_amount.unwrap_or(remaining);
+    if !fill_maker_amount.is_positive() {
+        return Err(rejected("Fill amount must be greater than 0"));
+    }
+    if fill_maker_amount > remaining {
+        return Err(rejected("Fill amount exceeds the remaining amount of the swap"));
+    }
+    let fully_filled = fill_maker_amount == remaining;
+    if !fully_filled && designated_taker.is_some() {
+        return Err(rejected("Only open swaps can be partially filled"));
+    }
+
+    let taker_paid = tx.taker_paid(swap_id).await?;
+    let fill_taker_amount = taker_payment(fill_maker_amount, remaining, (swap.maker_amount, swap.taker_amount), taker_paid);
+
+    // Get or create the accepting user's accounts
+    let user_taker_account_id = tx.account_for(taker_discord_id, swap.taker_currency_id).await?;
+    let user_maker_account_id = tx.account_for(taker_discord_id, swap.maker_currency_id).await?;
+
+    let user_balance = tx.lock_balance(user_taker_account_id).await?.unwrap_or(Amount::ZERO);
+    if user_balance < fill_taker_amount {
+        return Err(rejected("Insufficient balance to accept swap"));
+    }
+
+    // The accepting user gives their currency and gets the maker's
+    tx.add_balance(user_taker_account_id, -fill_taker_amount).await?;
+    tx.add_balance(user_maker_account_id, fill_maker_amount).await?;
+
+    let maker_taker_account_id = tx.account_for(maker_discord_id, swap.taker_currency_id).await?;
+    tx.add_balance(maker_taker_account_id, fill_taker_amount).await?;
+
+    // Two transactions per fill, one for each direction
+    tx.log_transaction(&Uuid::new_v4().to_string(), user_taker_account_id, maker_taker_account_id, fill_taker_amount).await?;
+    tx.log_transaction(&Uuid::new_v4().to_string(), swap.maker_account_id, user_maker_account_id, fill_maker_amount).await?;
+    tx.record_fill(swap_id, user_taker_account_id, fill_maker_amount, fill_taker_amount).await?;
+
+    if fully_filled {
+        // An open swap's taker is whoever completed it
+        let taker_account_id = swap.taker_account_id.is_none().then_some(user_taker_account_id);
+        tx.set_status(swap_id, "accepted", taker_account_id).await?;
+    }
+
+    Ok((fill_maker_amount, fill_taker_amount, fully_filled))
+}
+
+/// Close a pending swap with `status` ("cancelled" or "expired") and refund the
+/// unfilled part of the maker's escrow. Filled parts have already been paid out.
+pub async fn close(tx: &mut impl SwapTx, swap_id: i64, status: &str) -> Result<(), StorageError> {
+    let swap = tx.lock_swap(swap_id).await?.ok_or(rejected("Swap not found"))?;
+    if swap.status != "pending" {
+        return Err(rejected("Swap is not pending"));
+    }
+
+    tx.add_balance(swap.maker_account_id, swap.maker_amount - swap.filled_amount).await?;
+    tx.set_status(swap_id, status, None).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::amount;
+
+    #[test]
+    fn test_taker_payment() {
+        let terms = (amount("3"), amount("1"));
+
+        // Partial fills round up, the last one pays the rest
+        assert_eq!(taker_payment(amount("1"), amount("3"), terms, Amount::ZERO), amount("0.33333334"));
+        assert_eq!(taker_payment(amount("1"), amount("2"), terms, amount("0.33333334")), amount("0.33333334"));
+        assert_eq!(taker_payment(amount("1"), amount("1"), terms, amount("0.66666668")), amount("0.33333332"));
+
+        // Rounding never takes more than is left to pay
+        assert_eq!(taker_payment(amount("0.00000001"), amount("1"), terms, amount("1")), Amount::ZERO);
+    }
+}
@@ -0,0 +1,438 @@
+//! Fixed-point money type matching the `DECIMAL(24,8)` columns in the schema.
+//!
+//! Values are stored as a signed count of 10^-8 units so that balances, swap
+//! amounts and tax never pass through floating point on their way to and from MySQL.
+//! SQLite has no exact decimal type, so there the unit count itself is stored as an
+//! INTEGER, which caps SQLite amounts at what fits in an i64.
+
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, Neg, Sub};
+use std::str::FromStr;
+
+use sqlx::decode::Decode;
+use sqlx::encode::{Encode, IsNull};
+use sqlx::error::BoxDynError;
+use sqlx::mysql::{MySql, MySqlTypeInfo, MySqlValueRef};
+use sqlx::sqlite::{Sqlite, SqliteArgumentValue, SqliteTypeInfo, SqliteValueRef};
+use sqlx::{Type, TypeInfo};
+use thiserror::Error;
+
+/// Number of decimal places stored by every money column
+pub const SCALE: u32 = 8;
+
+/// 10^SCALE - the number of units in one whole currency unit
+const UNIT: i128 = 100_000_000;
+
+/// Errors produced when parsing an amount
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AmountError {
+    #[error("Invalid amount: '{0}'")]
+    Invalid(String),
+    #[error("Amount '{0}' has more than 8 decimal places")]
+    TooPrecise(String),
+    #[error("Amount '{0}' exceeds the maximum of 9999999999999999.99999999")]
+    Overflow(String),
+}
+
+/// How a division that must end in a whole number is rounded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rounding {
+    /// Toward zero
+    #[default]
+    Down,
+    /// Away from zero
+    Up,
+    /// Half away from zero
+    Nearest,
+}
+
+impl Rounding {
+    pub fn parse(rounding: &str) -> Option<Rounding> {
+        match rounding.to_lowercase().as_str() {
+            "down" | "floor" => Some(Rounding::Down),
+            "up" | "ceil" => Some(Rounding::Up),
+            "nearest" | "round" => Some(Rounding::Nearest),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rounding::Down => "down",
+            Rounding::Up => "up",
+            Rounding::Nearest => "nearest",
+        }
+    }
+}
+
+/// Exact decimal amount with 8 fractional digits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Amount(i128);
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+
+    /// Largest value a DECIMAL(24,8) column can hold: 9,999,999,999,999,999.99999999
+    pub const MAX: Amount = Amount(10i128.pow(24) - 1);
+
+    pub fn is_positive(&self) -> bool {
+        self.0 > 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0 < 0
+    }
+
+    /// True if the value fits in a DECIMAL(24,8) column
+    pub fn is_within_limit(&self) -> bool {
+        self.0.abs() <= Self::MAX.0
+    }
+
+    /// `percentage`% of this amount, rounded half away from zero to 8 decimals
+    pub fn percent(&self, percentage: i32) -> Amount {
+        Amount(div_round(self.0 * percentage as i128, 100))
+    }
+
+    /// Ratio `self / other` rounded to 8 decimals (used for prices)
+    pub fn checked_div(self, other: Amount) -> Option<Amount> {
+        if other.0 == 0 {
+            return None;
+        }
+        self.0.checked_mul(UNIT).map(|n| Amount(div_round(n, other.0)))
+    }
+
+    /// Product `self × other` rounded down to 8 decimals (e.g. quantity × price)
+    pub fn checked_mul_floor(self, other: Amount) -> Option<Amount> {
+        self.0.checked_mul(other.0).map(|n| Amount(n.div_euclid(UNIT)))
+    }
+
+    /// Product `self × other` rounded up to 8 decimals
+    pub fn checked_mul_ceil(self, other: Amount) -> Option<Amount> {
+        self.0.checked_mul(other.0).map(|n| Amount(-(-n).div_euclid(UNIT)))
+    }
+
+    /// `self × numerator / denominator` rounded down to 8 decimals (e.g. converting at a swap's rate)
+    pub fn checked_mul_ratio_floor(self, numerator: Amount, denominator: Amount) -> Option<Amount> {
+        if denominator.0 == 0 {
+            return None;
+        }
+        self.0.checked_mul(numerator.0).map(|n| Amount(n.div_euclid(denominator.0)))
+    }
+
+    /// `self × numerator / denominator` rounded up to 8 decimals
+    pub fn checked_mul_ratio_ceil(self, numerator: Amount, denominator: Amount) -> Option<Amount> {
+        if denominator.0 == 0 {
+            return None;
+        }
+        self.0.checked_mul(numerator.0).map(|n| Amount(-(-n).div_euclid(denominator.0)))
+    }
+
+    /// Ratio `self / other` as a whole number, rounded with `rounding`
+    /// (e.g. how many whole units of another currency an amount buys)
+    pub fn checked_div_whole(self, other: Amount, rounding: Rounding) -> Option<i64> {
+        if other.0 == 0 {
+            return None;
+        }
+        let quotient = match rounding {
+            Rounding::Down => self.0 / other.0,
+            Rounding::Up => {
+                let quotient = self.0 / other.0;
+                if self.0 % other.0 != 0 && (self.0 < 0) == (other.0 < 0) { quotient + 1 } else { quotient }
+            }
+            Rounding::Nearest => div_round(self.0, other.0),
+        };
+        i64::try_from(quotient).ok()
+    }
+
+    /// The amount for a whole number of units
+    pub fn from_whole(whole: i64) -> Amount {
+        Amount(whole as i128 * UNIT)
+    }
+
+    /// True if the amount has no fractional part
+    pub fn is_whole(&self) -> bool {
+        self.0 % UNIT == 0
+    }
+
+    /// Whole part of the amount, if it fits in an i64
+    pub fn whole_part(&self) -> Option<i64> {
+        i64::try_from(self.0 / UNIT).ok()
+    }
+
+    /// Lossy conversion for display and chart rendering only
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / UNIT as f64
+    }
+
+    /// Format with a fixed number of decimals, rounding half away from zero
+    fn format_fixed(&self, decimals: u32) -> String {
+        if decimals > SCALE {
+            let padding = "0".repeat((decimals - SCALE) as usize);
+            return format!("{}{}", self.format_fixed(SCALE), padding);
+        }
+        let divisor = 10i128.pow(SCALE - decimals);
+        let rounded = div_round(self.0, divisor);
+        let sign = if rounded < 0 { "-" } else { "" };
+        let scale = 10i128.pow(decimals);
+        let whole = rounded.abs() / scale;
+        if decimals == 0 {
+            format!("{}{}", sign, whole)
+        } else {
+            let frac = rounded.abs() % scale;
+            format!("{}{}.{:0width$}", sign, whole, frac, width = decimals as usize)
+        }
+    }
+}
+
+/// Integer division rounding half away from zero
+fn div_round(numerator: i128, denominator: i128) -> i128 {
+    let quotient = numerator / denominator;
+    let remainder = numerator % denominator;
+    if remainder.abs() * 2 >= denominator.abs() {
+        if (numerator < 0) != (denominator < 0) {
+            quotient - 1
+        } else {
+            quotient + 1
+        }
+    } else {
+        quotient
+    }
+}
+
+impl fmt::Display for Amount {
+    /// Without a precision the full value is shown with trailing zeros trimmed;
+    /// `{:.2}` style precision rounds to that many decimals.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match f.precision() {
+            Some(p) => self.format_fixed(p as u32),
+            None => {
+                let full = self.format_fixed(SCALE);
+                full.trim_end_matches('0').trim_end_matches('.').to_string()
+            }
+        };
+        match s.strip_prefix('-') {
+            Some(digits) => f.pad_integral(false, "", digits),
+            None => f.pad_integral(true, "", &s),
+        }
+    }
+}
+
+impl FromStr for Amount {
+    type Err = AmountError;
+
+    /// Parse user input such as `100`, `0.5` or `-12.25`
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let amount = Amount(parse_units(input, false)?);
+        if !amount.is_within_limit() {
+            return Err(AmountError::Overflow(input.trim().to_string()));
+        }
+        Ok(amount)
+    }
+}
+
+/// Parse a decimal string into 10^-8 units.
+/// With `truncate_scale` set, digits beyond the 8th decimal are dropped instead of rejected.
+fn parse_units(input: &str, truncate_scale: bool) -> Result<i128, AmountError> {
+    let s = input.trim();
+    let (negative, digits) = match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+
+    let (whole, mut frac) = digits.split_once('.').unwrap_or((digits, ""));
+
+    if (whole.is_empty() && frac.is_empty())
+        || !whole.chars().all(|c| c.is_ascii_digit())
+        || !frac.chars().all(|c| c.is_ascii_digit())
+    {
+        return Err(AmountError::Invalid(s.to_string()));
+    }
+
+    if frac.len() > SCALE as usize {
+        if !truncate_scale {
+            return Err(AmountError::TooPrecise(s.to_string()));
+        }
+        frac = &frac[..SCALE as usize];
+    }
+
+    let whole_units: i128 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().map_err(|_| AmountError::Overflow(s.to_string()))?
+    };
+    let frac_units: i128 = if frac.is_empty() {
+        0
+    } else {
+        frac.parse::<i128>().map_err(|_| AmountError::Invalid(s.to_string()))?
+            * 10i128.pow(SCALE - frac.len() as u32)
+    };
+
+    let units = whole_units
+        .checked_mul(UNIT)
+        .and_then(|w| w.checked_add(frac_units))
+        .ok_or_else(|| AmountError::Overflow(s.to_string()))?;
+
+    Ok(if negative { -units } else { units })
+}
+
+impl Add for Amount {
+    type Output = Amount;
+
+    fn add(self, other: Amount) -> Amount {
+        Amount(self.0 + other.0)
+    }
+}
+
+impl Sub for Amount {
+    type Output = Amount;
+
+    fn sub(self, other: Amount) -> Amount {
+        Amount(self.0 - other.0)
+    }
+}
+
+impl Neg for Amount {
+    type Output = Amount;
+
+    fn neg(self) -> Amount {
+        Amount(-self.0)
+    }
+}
+
+impl Sum for Amount {
+    fn sum<I: Iterator<Item = Amount>>(iter: I) -> Amount {
+        iter.fold(Amount::ZERO, |acc, a| acc + a)
+    }
+}
+
+impl Type<MySql> for Amount {
+    fn type_info() -> MySqlTypeInfo {
+        // Bound as a string so MySQL converts it to DECIMAL without rounding
+        <str as Type<MySql>>::type_info()
+    }
+
+    fn compatible(ty: &MySqlTypeInfo) -> bool {
+        ty.name() == "DECIMAL" || <str as Type<MySql>>::compatible(ty)
+    }
+}
+
+impl Encode<'_, MySql> for Amount {
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> Result<IsNull, BoxDynError> {
+        let s = self.format_fixed(SCALE);
+        <&str as Encode<MySql>>::encode(s.as_str(), buf)
+    }
+}
+
+impl Decode<'_, MySql> for Amount {
+    /// Aggregates like SUM() may exceed the column limit, so no limit check here
+    fn decode(value: MySqlValueRef<'_>) -> Result<Self, BoxDynError> {
+        let s = <&str as Decode<MySql>>::decode(value)?;
+        Ok(Amount(parse_units(s, true)?))
+    }
+}
+
+impl Type<Sqlite> for Amount {
+    fn type_info() -> SqliteTypeInfo {
+        <i64 as Type<Sqlite>>::type_info()
+    }
+
+    fn compatible(ty: &SqliteTypeInfo) -> bool {
+        <i64 as Type<Sqlite>>::compatible(ty)
+    }
+}
+
+impl<'q> Encode<'q, Sqlite> for Amount {
+    fn encode_by_ref(&self, buf: &mut Vec<SqliteArgumentValue<'q>>) -> Result<IsNull, BoxDynError> {
+        let units = i64::try_from(self.0)
+            .map_err(|_| format!("Amount {} is too large for the SQLite backend", self))?;
+        <i64 as Encode<Sqlite>>::encode(units, buf)
+    }
+}
+
+impl Decode<'_, Sqlite> for Amount {
+    fn decode(value: SqliteValueRef<'_>) -> Result<Self, BoxDynError> {
+        let units = <i64 as Decode<Sqlite>>::decode(value)?;
+        Ok(Amount(units as i128))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amt(s: &str) -> Amount {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(amt("100").0, 100 * UNIT);
+        assert_eq!(amt("0.1").0, 10_000_000);
+        assert_eq!(amt(".5").0, 50_000_000);
+        assert_eq!(amt("-2.25").0, -225_000_000);
+        assert_eq!(amt("12.30000000").to_string(), "12.3");
+        assert_eq!(amt("7").to_string(), "7");
+        assert_eq!(format!("{:.2}", amt("1.005")), "1.01");
+        assert_eq!(format!("{:.2}", amt("-1.005")), "-1.01");
+        assert_eq!(format!("{:.8}", amt("3")), "3.00000000");
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_input() {
+        assert!(matches!("abc".parse::<Amount>(), Err(AmountError::Invalid(_))));
+        assert!(matches!("1e5".parse::<Amount>(), Err(AmountError::Invalid(_))));
+        assert!(matches!(".".parse::<Amount>(), Err(AmountError::Invalid(_))));
+        assert!(matches!("1.123456789".parse::<Amount>(), Err(AmountError::TooPrecise(_))));
+        assert!(matches!("10000000000000000".parse::<Amount>(), Err(AmountError::Overflow(_))));
+        assert_eq!(amt("9999999999999999.99999999"), Amount::MAX);
+    }
+
+    #[test]
+    fn test_exact_arithmetic() {
+        // 0.1 + 0.2 drifts in f64 but must be exact here
+        assert_eq!(amt("0.1") + amt("0.2"), amt("0.3"));
+        let total: Amount = (0..1000).map(|_| amt("0.01")).sum();
+        assert_eq!(total, amt("10"));
+        assert!(!(Amount::MAX + amt("0.00000001")).is_within_limit());
+    }
+
+    #[test]
+    fn test_percent_and_ratio() {
+        assert_eq!(amt("100").percent(15), amt("15"));
+        assert_eq!(amt("0.00000001").percent(50), amt("0.00000001"));
+        assert_eq!(amt("33.33333333").percent(10), amt("3.33333333"));
+        assert_eq!(amt("50").checked_div(amt("100")), Some(amt("0.5")));
+        assert_eq!(amt("1").checked_div(amt("3")), Some(amt("0.33333333")));
+        assert_eq!(amt("3").checked_mul_floor(amt("0.33333333")), Some(amt("0.99999999")));
+        assert_eq!(amt("0.5").checked_mul_floor(amt("0.00000001")), Some(Amount::ZERO));
+        assert_eq!(amt("0.5").checked_mul_ceil(amt("0.00000001")), Some(amt("0.00000001")));
+        assert_eq!(amt("2.5").checked_mul_ceil(amt("4")), Some(amt("10")));
+        assert_eq!(Amount::MAX.checked_mul_floor(Amount::MAX), None);
+        assert_eq!(amt("10").checked_mul_ratio_floor(amt("1"), amt("3")), Some(amt("3.33333333")));
+        assert_eq!(amt("10").checked_mul_ratio_ceil(amt("1"), amt("3")), Some(amt("3.33333334")));
+        assert_eq!(amt("10").checked_mul_ratio_floor(amt("1"), Amount::ZERO), None);
+        assert_eq!(amt("1").checked_div(Amount::ZERO), None);
+    }
+
+    #[test]
+    fn test_divide_to_whole() {
+        assert_eq!(amt("10").checked_div_whole(amt("4"), Rounding::Down), Some(2));
+        assert_eq!(amt("10").checked_div_whole(amt("4"), Rounding::Up), Some(3));
+        assert_eq!(amt("10").checked_div_whole(amt("4"), Rounding::Nearest), Some(3));
+        assert_eq!(amt("9.9").checked_div_whole(amt("4"), Rounding::Nearest), Some(2));
+        assert_eq!(amt("3.00000001").checked_div_whole(amt("1"), Rounding::Up), Some(4));
+        assert_eq!(amt("12").checked_div_whole(amt("0.5"), Rounding::Up), Some(24));
+        assert_eq!(amt("1").checked_div_whole(Amount::ZERO, Rounding::Down), None);
+        assert_eq!(Amount::from_whole(-3), amt("-3"));
+    }
+
+    #[test]
+    fn test_decimal_from_database() {
+        assert_eq!(parse_units("123.45000000", true).unwrap(), amt("123.45").0);
+        assert_eq!(parse_units("1.123456789", true).unwrap(), amt("1.12345678").0);
+        // SUM() over DECIMAL(24,8) may exceed the column limit
+        assert!(parse_units("20000000000000000.00000000", true).is_ok());
+    }
+}
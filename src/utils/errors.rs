@@ -1,5 +1,6 @@
 use thiserror::Error;
 use crate::utils::encryption::CryptoError;
+use crate::utils::Amount;
 
 /// Extract clean error message from database error strings
 /// 
@@ -21,6 +22,84 @@ pub fn extract_clean_error(error_msg: &str) -> String {
     }
 }
 
+/// Errors from ledger operations (send, mint, tax collection, order book)
+#[derive(Debug, Error)]
+pub enum LedgerError {
+    #[error("Account not found")]
+    AccountNotFound,
+
+    #[error("No tax account found for this currency")]
+    TaxAccountNotFound,
+
+    #[error("Insufficient balance. Required: {required}, Available: {available}")]
+    InsufficientBalance { required: Amount, available: Amount },
+
+    #[error("Balance cannot go below 0 (would be {new_balance})")]
+    NegativeBalance { current: Amount, new_balance: Amount },
+
+    #[error("Balance would exceed the maximum limit (would be {new_balance})")]
+    BalanceLimit { current: Amount, new_balance: Amount },
+
+    #[error("Each account can only appear once in a transfer")]
+    DuplicateAccount,
+
+    #[error("Order not found")]
+    OrderNotFound,
+
+    #[error("Only the owner of an order can cancel it")]
+    NotOrderOwner,
+
+    #[error("Order is no longer open")]
+    OrderNotOpen,
+
+    /// A wire journal entry was not in the state the step needs
+    #[error("Wire transfer is not in the '{0}' state")]
+    WireState(&'static str),
+
+    #[error("Amount is too large")]
+    AmountTooLarge,
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Errors from a storage backend
+#[derive(Debug, Error)]
+pub enum StorageError {
+    /// The backend refused the operation, e.g. "Swap is not pending"
+    #[error("{0}")]
+    Rejected(String),
+
+    #[error("{0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Errors from applying schema migrations
+#[derive(Debug, Error)]
+pub enum MigrationError {
+    #[error("Migration {migration} failed at statement {statement}: {source}")]
+    Statement {
+        migration: String,
+        statement: usize,
+        source: sqlx::Error,
+    },
+
+    #[error("Migration {0} was changed after it was applied; add a new migration instead")]
+    ChecksumMismatch(String),
+
+    #[error("Database has migration {0} applied, which this build doesn't know about")]
+    UnknownVersion(String),
+
+    #[error("Timed out waiting for another instance to finish migrating")]
+    Locked,
+
+    #[error("{0} needs the MySQL backend")]
+    MySqlOnly(&'static str),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
 /// Wire command errors with Discord embed formatting
 #[derive(Debug, Error)]
 pub enum WireError {
@@ -38,12 +117,19 @@ pub enum WireError {
     
     #[error("Invalid configuration: {0}")]
     InvalidConfig(String),
+
+    #[error("Invalid amount: {0}")]
+    InvalidAmount(String),
     
     #[error("Transaction error: {0}")]
     Transaction(String),
     
     #[error("Compensation failed: {0}")]
     CompensationFailed(String),
+
+    /// The remote change may or may not have happened; recovery settles the wire
+    #[error("Transfer unconfirmed: {0}")]
+    Unconfirmed(String),
 }
 
 impl WireError {
@@ -86,9 +172,9 @@ impl WireError {
                 serenity::builder::CreateEmbed::default()
                     .title(title)
                     .description(format!(
-                        "UnbelievaBoat API communication failed:\n```\n{}\n```\n\n**Troubleshooting:**\n\
+                        "The wire provider's API communication failed:\n```\n{}\n```\n\n**Troubleshooting:**\n\
                         • Verify your API token is correct: `$wire set token <your_token>`\n\
-                        • Check UnbelievaBoat server status\n\
+                        • Check the wire provider's status\n\
                         • Try again in a few moments",
                         truncated
                     ))
@@ -108,6 +194,13 @@ impl WireError {
                     .description(truncated)
                     .color(0xff8800) // Orange
             }
+            WireError::InvalidAmount(msg) => {
+                let truncated = Self::truncate_for_embed(msg, 3500);
+                serenity::builder::CreateEmbed::default()
+                    .title("❌ Invalid Amount")
+                    .description(truncated)
+                    .color(0xff8800) // Orange
+            }
             WireError::Transaction(msg) => {
                 let truncated = Self::truncate_for_embed(msg, 3500);
                 serenity::builder::CreateEmbed::default()
@@ -120,12 +213,24 @@ impl WireError {
                 serenity::builder::CreateEmbed::default()
                     .title("⚠️ Compensation Failed")
                     .description(format!(
-                        "API failed AND automatic balance restoration failed. **Critical error**:\n```\n{}\n```\n\n\
-                        ⚠️ **Please contact server administrators immediately!**",
+                        "API failed AND automatic balance restoration failed:\n```\n{}\n```\n\n\
+                        The transfer is recorded and will be retried automatically. \
+                        If it isn't settled soon, ask a server admin to check `$wire reconcile`.",
                         truncated
                     ))
                     .color(0xff0000) // Red
             }
+            WireError::Unconfirmed(msg) => {
+                let truncated = Self::truncate_for_embed(msg, 3000);
+                serenity::builder::CreateEmbed::default()
+                    .title("⏳ Wire Unconfirmed")
+                    .description(format!(
+                        "{}\n\nThe transfer will be checked against the other balance in a few minutes and \
+                        then completed or refunded, never both. Follow it with `$wire history`.",
+                        truncated
+                    ))
+                    .color(0xffaa00) // Yellow-orange
+            }
         }
     }
 }
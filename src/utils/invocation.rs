@@ -0,0 +1,53 @@
+//! Who invoked a command and where, independent of how it was invoked.
+//!
+//! Services take an `Invocation` instead of a `Message` so the same code path
+//! serves `$prefix` messages and slash command interactions.
+
+use serenity::model::application::{CommandInteraction, ComponentInteraction};
+use serenity::model::channel::Message;
+use serenity::model::id::{GuildId, MessageId, UserId};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Invocation {
+    pub user_id: UserId,
+    pub guild_id: Option<GuildId>,
+    /// The message that carried the command, or the message whose button was pressed
+    pub message_id: Option<MessageId>,
+}
+
+impl Invocation {
+    /// Invoking user's Discord ID as stored in the database
+    pub fn user_id_i64(&self) -> i64 {
+        self.user_id.get() as i64
+    }
+}
+
+impl From<&Message> for Invocation {
+    fn from(msg: &Message) -> Self {
+        Invocation {
+            user_id: msg.author.id,
+            guild_id: msg.guild_id,
+            message_id: Some(msg.id),
+        }
+    }
+}
+
+impl From<&CommandInteraction> for Invocation {
+    fn from(command: &CommandInteraction) -> Self {
+        Invocation {
+            user_id: command.user.id,
+            guild_id: command.guild_id,
+            message_id: None,
+        }
+    }
+}
+
+impl From<&ComponentInteraction> for Invocation {
+    fn from(component: &ComponentInteraction) -> Self {
+        Invocation {
+            user_id: component.user.id,
+            guild_id: component.guild_id,
+            message_id: Some(component.message.id),
+        }
+    }
+}
@@ -4,11 +4,15 @@ pub mod errors;
 pub mod ratelimit;
 pub mod encryption;
 pub mod ub_ratelimit;
+pub mod amount;
+pub mod invocation;
 
 pub use errors::extract_clean_error;
 pub use ratelimit::{check_cooldown, check_global_rate_limit};
 pub use encryption::{encrypt_token, decrypt_token};
-pub use ub_ratelimit::rate_limit_ub_api;
+pub use ub_ratelimit::{rate_limit_api, rate_limit_ub_api};
+pub use amount::{Amount, Rounding};
+pub use invocation::Invocation;
 
 /// Check if a user has required roles in a guild (case-insensitive)
 /// Special behavior for "admin" role: checks Discord ADMINISTRATOR permission instead of role name
@@ -160,6 +164,84 @@ pub async fn check_user_roles(
     Ok(())
 }
 
+/// Resolve a role mention (`<@&id>`), role ID or role name (case-insensitive) to (role ID, role name)
+pub async fn resolve_role(
+    ctx: &serenity::prelude::Context,
+    guild_id: serenity::model::prelude::GuildId,
+    input: &str,
+) -> Result<(serenity::model::prelude::RoleId, String), String> {
+    use serenity::model::prelude::RoleId;
+
+    let role_id = input
+        .trim_start_matches("<@&")
+        .trim_end_matches('>')
+        .parse::<u64>()
+        .ok()
+        .filter(|id| *id != 0)
+        .map(RoleId::new);
+
+    let find = |roles: &std::collections::HashMap<RoleId, serenity::model::guild::Role>| {
+        roles
+            .values()
+            .find(|role| match role_id {
+                Some(id) => role.id == id,
+                None => role.name.eq_ignore_ascii_case(input.trim_start_matches('@')),
+            })
+            .map(|role| (role.id, role.name.clone()))
+    };
+
+    // Try the cache first, then the API
+    let cached = guild_id.to_guild_cached(&ctx.cache).and_then(|guild| find(&guild.roles));
+    if let Some(role) = cached {
+        return Ok(role);
+    }
+
+    let roles = guild_id.roles(&ctx.http)
+        .await
+        .map_err(|e| format!("Failed to get guild roles: {}", e))?;
+    find(&roles).ok_or_else(|| format!("❌ Role '{}' not found", input))
+}
+
+/// Every member (bots excluded) holding a role, fetched from the API.
+/// The @everyone role matches every member.
+pub async fn get_role_members(
+    ctx: &serenity::prelude::Context,
+    guild_id: serenity::model::prelude::GuildId,
+    role_id: serenity::model::prelude::RoleId,
+) -> Result<Vec<serenity::model::prelude::UserId>, String> {
+    use tracing::debug;
+
+    /// The most members the API returns per request
+    const MEMBERS_PER_REQUEST: u64 = 1000;
+
+    let everyone = role_id.get() == guild_id.get();
+    let mut user_ids = Vec::new();
+    let mut after = None;
+
+    loop {
+        let members = guild_id.members(&ctx.http, Some(MEMBERS_PER_REQUEST), after)
+            .await
+            .map_err(|e| format!("Failed to list guild members (is the Server Members intent enabled?): {}", e))?;
+
+        let fetched = members.len() as u64;
+        after = members.last().map(|member| member.user.id);
+
+        user_ids.extend(
+            members
+                .into_iter()
+                .filter(|member| !member.user.bot && (everyone || member.roles.contains(&role_id)))
+                .map(|member| member.user.id),
+        );
+
+        if fetched < MEMBERS_PER_REQUEST {
+            break;
+        }
+    }
+
+    debug!("Role {} in guild {} has {} members", role_id, guild_id, user_ids.len());
+    Ok(user_ids)
+}
+
 /// Ensure encryption key exists in .env, generate if missing
 pub fn ensure_encryption_key() -> Result<String, String> {
     use tracing::info;
@@ -1,24 +1,73 @@
-use serenity::builder::{CreateEmbed, CreateMessage};
+use std::time::Duration;
+use serenity::async_trait;
+use serenity::builder::{
+    CreateActionRow, CreateButton, CreateEmbed, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, EditMessage,
+};
 use serenity::model::prelude::*;
 use serenity::prelude::Context;
+use tracing::debug;
+
+/// How long the buttons keep working after the last press
+const NAVIGATION_TIMEOUT: Duration = Duration::from_secs(120);
+
+const PREVIOUS_BUTTON_ID: &str = "page_previous";
+const NEXT_BUTTON_ID: &str = "page_next";
+const INDICATOR_BUTTON_ID: &str = "page_indicator";
+
+/// Fetches pages on demand so only the pages a user visits are queried
+#[async_trait]
+pub trait PageSource: Send + Sync {
+    /// Build the embed for a 1-based page number
+    /// Returns the embed and the total number of pages
+    async fn fetch(&self, page: usize) -> Result<(CreateEmbed, usize), String>;
+}
 
 pub struct Page {
-    pub pages: Vec<CreateEmbed>,
+    /// Pages loaded so far, indexed from 0
+    pub pages: Vec<Option<CreateEmbed>>,
     pub current_page: usize,
+    source: Option<Box<dyn PageSource>>,
 }
 
 impl Page {
     /// Create a new pagination with pages
     pub fn new(pages: Vec<CreateEmbed>) -> Self {
         Page {
-            pages,
+            pages: pages.into_iter().map(Some).collect(),
             current_page: 0,
+            source: None,
         }
     }
 
-    /// Get the current page embed
-    pub fn current_embed(&self) -> &CreateEmbed {
-        &self.pages[self.current_page]
+    /// Create a pagination that fetches pages from `source`, starting at the 1-based `start_page`
+    pub async fn load(source: impl PageSource + 'static, start_page: usize) -> Result<Self, String> {
+        let start_page = start_page.max(1);
+        let (embed, total_pages) = source.fetch(start_page).await?;
+
+        let mut pages = vec![None; total_pages.max(start_page)];
+        pages[start_page - 1] = Some(embed);
+
+        Ok(Page {
+            pages,
+            current_page: start_page - 1,
+            source: Some(Box::new(source)),
+        })
+    }
+
+    /// Get the current page embed, fetching it if it hasn't been loaded yet
+    pub async fn current_embed(&mut self) -> Result<CreateEmbed, String> {
+        if let Some(embed) = &self.pages[self.current_page] {
+            return Ok(embed.clone());
+        }
+
+        let source = self.source.as_ref().ok_or("Page is not loaded".to_string())?;
+        let (embed, total_pages) = source.fetch(self.current_page + 1).await?;
+
+        // Rows may have been added or removed since the first page was fetched
+        self.pages.resize(total_pages.max(self.current_page + 1), None);
+        self.pages[self.current_page] = Some(embed.clone());
+        Ok(embed)
     }
 
     /// Move to next page
@@ -56,9 +105,177 @@ impl Page {
         self.current_page == self.pages.len() - 1
     }
 
-    /// Create a message with embed
-    pub fn create_message(&self) -> CreateMessage {
-        CreateMessage::default()
-            .embed(self.current_embed().clone())
+    /// ◀ ▶ buttons for the current position (none for a single page)
+    pub fn components(&self) -> Vec<CreateActionRow> {
+        if self.total_pages() <= 1 {
+            return Vec::new();
+        }
+
+        vec![CreateActionRow::Buttons(vec![
+            CreateButton::new(PREVIOUS_BUTTON_ID)
+                .emoji('◀')
+                .style(ButtonStyle::Secondary)
+                .disabled(self.is_first()),
+            CreateButton::new(INDICATOR_BUTTON_ID)
+                .label(format!("{}/{}", self.current_page + 1, self.total_pages()))
+                .style(ButtonStyle::Secondary)
+                .disabled(true),
+            CreateButton::new(NEXT_BUTTON_ID)
+                .emoji('▶')
+                .style(ButtonStyle::Secondary)
+                .disabled(self.is_last()),
+        ])]
+    }
+
+    /// Create a message with embed and navigation buttons
+    pub async fn create_message(&mut self) -> Result<CreateMessage, String> {
+        let embed = self.current_embed().await?;
+        Ok(CreateMessage::default()
+            .embed(embed)
+            .components(self.components()))
+    }
+
+    /// Send the current page to a channel and let `author_id` page through it
+    pub async fn send(mut self, ctx: &Context, channel_id: ChannelId, author_id: UserId) -> Result<(), String> {
+        let message = channel_id
+            .send_message(ctx, self.create_message().await?)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        self.spawn_navigation(ctx, message, author_id);
+        Ok(())
+    }
+
+    /// Handle button presses on an already-sent page message in the background
+    /// The message must show the current page with `components()` attached
+    pub fn spawn_navigation(self, ctx: &Context, message: Message, author_id: UserId) {
+        if self.total_pages() <= 1 {
+            return;
+        }
+
+        let ctx = ctx.clone();
+        tokio::spawn(async move {
+            self.navigate(&ctx, message, author_id).await;
+        });
+    }
+
+    async fn navigate(mut self, ctx: &Context, mut message: Message, author_id: UserId) {
+        while let Some(interaction) = message
+            .await_component_interaction(&ctx.shard)
+            .timeout(NAVIGATION_TIMEOUT)
+            .await
+        {
+            if interaction.user.id != author_id {
+                let reply = CreateInteractionResponseMessage::new()
+                    .content("Only the person who ran this command can change pages.")
+                    .ephemeral(true);
+                let _ = interaction
+                    .create_response(ctx, CreateInteractionResponse::Message(reply))
+                    .await;
+                continue;
+            }
+
+            let moved = match interaction.data.custom_id.as_str() {
+                PREVIOUS_BUTTON_ID => self.previous(),
+                NEXT_BUTTON_ID => self.next(),
+                _ => false,
+            };
+
+            let update = if moved {
+                match self.current_embed().await {
+                    Ok(embed) => CreateInteractionResponseMessage::new()
+                        .embed(embed)
+                        .components(self.components()),
+                    Err(e) => {
+                        debug!("Failed to fetch page {}: {}", self.current_page + 1, e);
+                        // Stay on the page that is still showing
+                        if interaction.data.custom_id == NEXT_BUTTON_ID {
+                            self.previous();
+                        } else {
+                            self.next();
+                        }
+                        CreateInteractionResponseMessage::new().components(self.components())
+                    }
+                }
+            } else {
+                CreateInteractionResponseMessage::new().components(self.components())
+            };
+
+            if let Err(e) = interaction
+                .create_response(ctx, CreateInteractionResponse::UpdateMessage(update))
+                .await
+            {
+                debug!("Failed to update page message: {}", e);
+            }
+        }
+
+        // Timed out - remove the buttons so they don't look clickable
+        if let Err(e) = message.edit(ctx, EditMessage::new().components(Vec::new())).await {
+            debug!("Failed to remove page buttons: {}", e);
+        }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Source with a fixed page count that records how many fetches it served
+    struct CountingSource {
+        total_pages: usize,
+        fetches: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl PageSource for CountingSource {
+        async fn fetch(&self, page: usize) -> Result<(CreateEmbed, usize), String> {
+            if page > self.total_pages {
+                return Err("Invalid page number".to_string());
+            }
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            Ok((CreateEmbed::default().title(format!("Page {}", page)), self.total_pages))
+        }
+    }
+
+    #[test]
+    fn test_navigation_bounds() {
+        let mut page = Page::new(vec![CreateEmbed::default(), CreateEmbed::default()]);
+        assert!(page.is_first());
+        assert!(!page.previous());
+        assert!(page.next());
+        assert!(page.is_last());
+        assert!(!page.next());
+        assert_eq!(page.components().len(), 1);
+
+        // A single page gets no buttons
+        assert!(Page::new(vec![CreateEmbed::default()]).components().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_lazy_pages_fetched_once() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let source = CountingSource { total_pages: 3, fetches: fetches.clone() };
+
+        let mut page = Page::load(source, 2).await.unwrap();
+        assert_eq!(page.total_pages(), 3);
+        assert_eq!(page.current_page, 1);
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        let _ = page.current_embed().await.unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        page.next();
+        let _ = page.current_embed().await.unwrap();
+        page.previous();
+        let _ = page.current_embed().await.unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_load_rejects_bad_start_page() {
+        let source = CountingSource { total_pages: 1, fetches: Arc::new(AtomicUsize::new(0)) };
+        assert!(Page::load(source, 5).await.is_err());
+    }
+}
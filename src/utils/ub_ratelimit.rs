@@ -1,11 +1,15 @@
-/// UnbelievaBoat API rate limiter - 20 requests per second globally
+/// UnbelievaBoat API rate limiter - 20 requests per second globally.
+/// Other economy bridge providers get a limiter of their own per API host.
 use lazy_static::lazy_static;
 use std::sync::Mutex;
 use std::time::{Duration, Instant};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 
 lazy_static! {
     static ref UB_RATE_LIMITER: Mutex<UbRateLimiter> = Mutex::new(UbRateLimiter::new());
+
+    // Key: provider API host, Value: its limiter
+    static ref API_RATE_LIMITERS: Mutex<HashMap<String, UbRateLimiter>> = Mutex::new(HashMap::new());
 }
 
 pub struct UbRateLimiter {
@@ -19,9 +23,13 @@ pub struct UbRateLimiter {
 
 impl UbRateLimiter {
     fn new() -> Self {
+        Self::with_limit(20)
+    }
+
+    fn with_limit(max_requests: usize) -> Self {
         Self {
             request_times: VecDeque::new(),
-            max_requests: 20,
+            max_requests,
             window: Duration::from_secs(1),
         }
     }
@@ -68,6 +76,24 @@ pub async fn rate_limit_ub_api() {
     }
 }
 
+/// Wait if necessary to keep the API at `host` within `max_requests` requests/second
+pub async fn rate_limit_api(host: &str, max_requests: usize) {
+    let wait_duration = {
+        let mut limiters = API_RATE_LIMITERS.lock().unwrap();
+        let limiter = limiters
+            .entry(host.to_string())
+            .or_insert_with(|| UbRateLimiter::with_limit(max_requests));
+        // The provider's settings may have changed since the limiter was made
+        limiter.max_requests = max_requests.max(1);
+        limiter.check_and_record()
+    };
+
+    if wait_duration.as_millis() > 0 {
+        tracing::debug!("{} API rate limit: waiting {}ms", host, wait_duration.as_millis());
+        tokio::time::sleep(wait_duration).await;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;